@@ -0,0 +1,269 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which create and read smart contract archives (.scar files)
+//!
+//! A smart contract archive is a bzip2-compressed tar file containing the contract's
+//! `manifest.yaml` and its compiled `.wasm` file.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use bzip2::read::BzDecoder;
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
+use tar::{Archive, Builder, Header};
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::error::CliError;
+
+/// The name of the manifest file within an archive
+const MANIFEST_FILE_NAME: &str = "manifest.yaml";
+
+/// The first bytes of every WebAssembly module
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// The fields of a contract manifest
+const MANIFEST_FIELDS: &[&str] = &["name", "version", "inputs", "outputs"];
+
+/// The contract described by a manifest
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
+/// The contents of a smart contract archive
+pub struct ContractArchive {
+    pub manifest: Manifest,
+    pub contract: Vec<u8>,
+}
+
+/// A smart contract archive written by `pack`
+pub struct PackedArchive {
+    pub path: String,
+    pub manifest: Manifest,
+    /// The hex-encoded SHA-512 hash of the contract, as recorded in the contract registry
+    pub contract_sha512: String,
+}
+
+/// Package the compiled contract and its manifest into a smart contract archive
+///
+/// If no output path is given, the archive is written to `<name>_<version>.scar` in the current
+/// directory, with any underscores in the contract name replaced by dashes.
+pub fn pack(
+    manifest_path: &str,
+    wasm_path: &str,
+    output: Option<&str>,
+) -> Result<PackedArchive, CliError> {
+    let manifest_bytes = fs::read(manifest_path).map_err(|err| {
+        CliError::UserError(format!(
+            "Could not load manifest \"{}\": {}",
+            manifest_path, err
+        ))
+    })?;
+    let manifest = Manifest::parse(manifest_path, &manifest_bytes)?;
+
+    let contract = fs::read(wasm_path).map_err(|err| {
+        CliError::UserError(format!(
+            "Could not load contract \"{}\": {}",
+            wasm_path, err
+        ))
+    })?;
+    if !contract.starts_with(WASM_MAGIC) {
+        return Err(CliError::UserError(format!(
+            "Contract \"{}\" is not a WebAssembly module",
+            wasm_path
+        )));
+    }
+
+    let wasm_name = Path::new(wasm_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| name.ends_with(".wasm"))
+        .ok_or_else(|| {
+            CliError::UserError(format!(
+                "Contract \"{}\" must have a .wasm extension",
+                wasm_path
+            ))
+        })?;
+
+    let path = output.map(String::from).unwrap_or_else(|| {
+        format!(
+            "{}_{}.scar",
+            manifest.name.replace('_', "-"),
+            manifest.version
+        )
+    });
+
+    write_archive(
+        &path,
+        &[
+            (MANIFEST_FILE_NAME, &manifest_bytes),
+            (wasm_name, &contract),
+        ],
+    )
+    .map_err(|err| CliError::UserError(format!("Could not write archive \"{}\": {}", path, err)))?;
+
+    Ok(PackedArchive {
+        path,
+        manifest,
+        contract_sha512: contract_sha512(&contract),
+    })
+}
+
+/// Read the manifest and the compiled contract from a smart contract archive
+pub fn load(path: &str) -> Result<ContractArchive, CliError> {
+    let invalid =
+        |reason: String| CliError::UserError(format!("Invalid archive \"{}\": {}", path, reason));
+
+    let file = File::open(path).map_err(|err| {
+        CliError::UserError(format!("Could not load archive \"{}\": {}", path, err))
+    })?;
+    let mut archive = Archive::new(BzDecoder::new(file));
+
+    let mut manifest = None;
+    let mut contract = None;
+
+    for entry in archive.entries().map_err(|err| invalid(err.to_string()))? {
+        let mut entry = entry.map_err(|err| invalid(err.to_string()))?;
+        let entry_path = entry
+            .path()
+            .map_err(|err| invalid(err.to_string()))?
+            .into_owned();
+
+        let mut bytes = Vec::new();
+        if entry_path.file_name() == Some(MANIFEST_FILE_NAME.as_ref()) {
+            entry
+                .read_to_end(&mut bytes)
+                .map_err(|err| invalid(err.to_string()))?;
+            manifest = Some(Manifest::parse(
+                &format!("{}:{}", path, MANIFEST_FILE_NAME),
+                &bytes,
+            )?);
+        } else if entry_path.extension() == Some("wasm".as_ref()) {
+            if contract.is_some() {
+                return Err(invalid("contains more than one .wasm file".into()));
+            }
+            entry
+                .read_to_end(&mut bytes)
+                .map_err(|err| invalid(err.to_string()))?;
+            contract = Some(bytes);
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| invalid(format!("{} not found", MANIFEST_FILE_NAME)))?;
+    let contract = contract.ok_or_else(|| invalid("no .wasm file found".into()))?;
+    if !contract.starts_with(WASM_MAGIC) {
+        return Err(invalid("contract is not a WebAssembly module".into()));
+    }
+
+    Ok(ContractArchive { manifest, contract })
+}
+
+/// Return the hex-encoded SHA-512 hash of the contract
+pub fn contract_sha512(contract: &[u8]) -> String {
+    let mut sha = Sha512::new();
+    sha.input(contract);
+    sha.result_str()
+}
+
+fn write_archive(path: &str, entries: &[(&str, &[u8])]) -> std::io::Result<()> {
+    let mut builder = Builder::new(BzEncoder::new(File::create(path)?, Compression::best()));
+
+    for (name, contents) in entries {
+        let mut header = Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, *contents)?;
+    }
+
+    builder.into_inner()?.finish()?;
+
+    Ok(())
+}
+
+impl Manifest {
+    /// Parse and validate the manifest, which must contain exactly the name, version, inputs and
+    /// outputs of the contract
+    pub fn parse(path: &str, bytes: &[u8]) -> Result<Manifest, CliError> {
+        let malformed = |reason: &str| {
+            CliError::UserError(format!("Malformed manifest \"{}\": {}", path, reason))
+        };
+
+        let contents = std::str::from_utf8(bytes).map_err(|_| malformed("not valid UTF-8"))?;
+        let docs =
+            YamlLoader::load_from_str(contents).map_err(|err| malformed(&err.to_string()))?;
+        let fields = docs
+            .first()
+            .and_then(Yaml::as_hash)
+            .ok_or_else(|| malformed("expected a mapping"))?;
+
+        for key in fields.keys() {
+            match key.as_str() {
+                Some(key) if MANIFEST_FIELDS.contains(&key) => (),
+                Some(key) => {
+                    return Err(malformed(&format!(
+                        "unexpected field \"{}\"; expected only {}",
+                        key,
+                        MANIFEST_FIELDS.join(", ")
+                    )))
+                }
+                None => return Err(malformed("field names must be strings")),
+            }
+        }
+
+        let doc = &docs[0];
+        let string_field = |name: &str| {
+            doc[name]
+                .as_str()
+                .filter(|value| !value.is_empty())
+                .map(String::from)
+                .ok_or_else(|| malformed(&format!("missing string field \"{}\"", name)))
+        };
+        let addresses_field = |name: &str| {
+            doc[name]
+                .as_vec()
+                .ok_or_else(|| malformed(&format!("missing array \"{}\"", name)))?
+                .iter()
+                .map(|address| match address.as_str() {
+                    Some(address) if is_namespace(address) => Ok(address.to_string()),
+                    _ => Err(malformed(&format!(
+                        "{} must be hex-encoded addresses of at least 6 characters",
+                        name
+                    ))),
+                })
+                .collect::<Result<Vec<_>, CliError>>()
+        };
+
+        Ok(Manifest {
+            name: string_field("name")?,
+            version: string_field("version")?,
+            inputs: addresses_field("inputs")?,
+            outputs: addresses_field("outputs")?,
+        })
+    }
+}
+
+/// Whether the address is a hex-encoded namespace of at least 6 characters
+pub fn is_namespace(address: &str) -> bool {
+    address.len() >= 6
+        && address.len().is_multiple_of(2)
+        && address.chars().all(|c| c.is_ascii_hexdigit())
+}
@@ -0,0 +1,356 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which assist with combining several transactions into a single batch
+
+use std::fs;
+use std::path::Path;
+
+use cylinder::{secp256k1::Secp256k1Context, Context, PublicKey, Signature, Signer};
+use protobuf::Message;
+use transact::protocol::batch::{Batch, BatchBuilder, BatchHeader};
+use transact::protocol::transaction::{Transaction, TransactionBuilder, TransactionHeader};
+use transact::protos::transaction::TransactionList;
+use transact::protos::{FromBytes, FromProto, IntoProto};
+
+use crate::error::CliError;
+
+/// Sign the transaction and append it to the batch file, returning its ID and the number of
+/// transactions in the file
+///
+/// The file is created if it does not exist. The transaction depends on every transaction already
+/// in the file, so the transactions are applied in the order they were added, as well as on the
+/// given `dependencies`. It uses the same batcher key as the first transaction in the file, which
+/// is the public key of `batcher`, if given, or of `signer`.
+pub fn add_transaction(
+    path: &str,
+    transaction: TransactionBuilder,
+    mut dependencies: Vec<Vec<u8>>,
+    signer: &dyn Signer,
+    batcher: Option<&dyn Signer>,
+) -> Result<(String, usize), CliError> {
+    let mut transactions = if Path::new(path).exists() {
+        load_transactions(path)?
+    } else {
+        Vec::new()
+    };
+
+    for transaction in transactions.iter() {
+        dependencies.push(from_hex(transaction.header_signature())?);
+    }
+
+    let batcher_public_key = match batcher {
+        Some(batcher) => Some(
+            batcher
+                .public_key()
+                .map_err(|err| CliError::SigningError(err.to_string()))?,
+        ),
+        None => None,
+    };
+    let transaction = match transactions.first() {
+        Some(first) => {
+            let header = read_header(first)?;
+            if let Some(batcher_public_key) = batcher_public_key {
+                if batcher_public_key.as_slice() != header.batcher_public_key() {
+                    return Err(CliError::UserError(format!(
+                        "The transactions in batch file {} are batched by {}, not by the given \
+                         batcher key",
+                        path,
+                        to_hex(header.batcher_public_key())
+                    )));
+                }
+            }
+            transaction.with_batcher_public_key(header.batcher_public_key().to_vec())
+        }
+        None => match batcher_public_key {
+            Some(batcher_public_key) => {
+                transaction.with_batcher_public_key(batcher_public_key.as_slice().to_vec())
+            }
+            None => transaction,
+        },
+    }
+    .with_dependencies(dependencies)
+    .build(signer)?;
+
+    let transaction_id = transaction.header_signature().to_string();
+    transactions.push(transaction);
+
+    let mut transaction_list = TransactionList::new();
+    for transaction in transactions.iter() {
+        transaction_list
+            .transactions
+            .push(transaction.clone().into_proto()?);
+    }
+
+    let bytes = transaction_list.write_to_bytes().map_err(|err| {
+        CliError::UserError(format!("Unable to serialize batch file {}: {}", path, err))
+    })?;
+    fs::write(path, bytes).map_err(|err| {
+        CliError::UserError(format!("Could not write batch file {}: {}", path, err))
+    })?;
+
+    Ok((transaction_id, transactions.len()))
+}
+
+/// Sign the transactions and a single batch containing them
+///
+/// The batch is signed by `batcher` if it is given, and otherwise by `signer`, which signs the
+/// transactions.
+pub fn sign_batch(
+    transactions: Vec<TransactionBuilder>,
+    signer: &dyn Signer,
+    batcher: Option<&dyn Signer>,
+) -> Result<Batch, CliError> {
+    let batcher = batcher.unwrap_or(signer);
+    let batcher_public_key = batcher
+        .public_key()
+        .map_err(|err| CliError::SigningError(err.to_string()))?;
+
+    let transactions = transactions
+        .into_iter()
+        .map(|transaction| {
+            transaction
+                .with_batcher_public_key(batcher_public_key.as_slice().to_vec())
+                .build(signer)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(BatchBuilder::new()
+        .with_transactions(transactions)
+        .build(batcher)?)
+}
+
+/// Sign the transactions and a single batch containing them, each transaction depending on the
+/// one before it
+///
+/// The first transaction depends on the given `dependencies`. The batch is signed by `batcher` if
+/// it is given, and otherwise by `signer`.
+pub fn sign_ordered_batch(
+    transactions: Vec<TransactionBuilder>,
+    mut dependencies: Vec<Vec<u8>>,
+    signer: &dyn Signer,
+    batcher: Option<&dyn Signer>,
+) -> Result<Batch, CliError> {
+    let batcher = batcher.unwrap_or(signer);
+    let batcher_public_key = batcher
+        .public_key()
+        .map_err(|err| CliError::SigningError(err.to_string()))?;
+
+    let mut signed = Vec::with_capacity(transactions.len());
+    for transaction in transactions {
+        let transaction = transaction
+            .with_batcher_public_key(batcher_public_key.as_slice().to_vec())
+            .with_dependencies(dependencies)
+            .build(signer)?;
+        dependencies = vec![from_hex(transaction.header_signature())?];
+        signed.push(transaction);
+    }
+
+    Ok(BatchBuilder::new()
+        .with_transactions(signed)
+        .build(batcher)?)
+}
+
+/// Combine the transactions in the batch file into a single batch
+///
+/// The batch must be signed by the batcher key of the first transaction added to the file, since
+/// every transaction names it as the batcher. This is the key that signed the transaction, unless
+/// it was added with a separate batcher key.
+pub fn build_batch(path: &str, signer: &dyn Signer) -> Result<Batch, CliError> {
+    let transactions = load_transactions(path)?;
+    if transactions.is_empty() {
+        return Err(CliError::UserError(format!(
+            "Batch file {} contains no transactions",
+            path
+        )));
+    }
+
+    let public_key = signer
+        .public_key()
+        .map_err(|err| CliError::SigningError(err.to_string()))?;
+    for transaction in transactions.iter() {
+        if read_header(transaction)?.batcher_public_key() != public_key.as_slice() {
+            return Err(CliError::UserError(format!(
+                "Transaction {} in {} must be batched by {}, the batcher key of the first \
+                 transaction in the file",
+                transaction.header_signature(),
+                path,
+                to_hex(read_header(&transactions[0])?.batcher_public_key())
+            )));
+        }
+    }
+
+    Ok(BatchBuilder::new()
+        .with_transactions(transactions)
+        .build(signer)?)
+}
+
+/// The signed transactions in a file written by the CLI
+pub enum SignedFile {
+    /// The batches of a serialized BatchList, as written with --output
+    Batches(Vec<Batch>),
+    /// The transactions of a batch file created with --batch-file, which are not yet batched
+    Transactions(Vec<Transaction>),
+}
+
+/// Read a serialized BatchList or a batch file
+///
+/// The transactions of a batch file may also parse as a BatchList, so the bytes are only read as
+/// batches if the header of each batch lists the IDs of its transactions.
+pub fn read_signed_file(bytes: &[u8], name: &str) -> Result<SignedFile, CliError> {
+    if let Ok(batches) = <Vec<Batch> as FromBytes<_>>::from_bytes(bytes) {
+        let lists_transactions = |batch: &Batch| match read_batch_header(batch) {
+            Ok(header) => header
+                .transaction_ids()
+                .iter()
+                .map(|id| to_hex(id))
+                .eq(batch
+                    .transactions()
+                    .iter()
+                    .map(|transaction| transaction.header_signature().to_string())),
+            Err(_) => false,
+        };
+        if !batches.is_empty() && batches.iter().all(lists_transactions) {
+            return Ok(SignedFile::Batches(batches));
+        }
+    }
+
+    match parse_transactions(bytes, name) {
+        Ok(transactions)
+            if !transactions.is_empty()
+                && transactions
+                    .iter()
+                    .all(|transaction| read_header(transaction).is_ok()) =>
+        {
+            Ok(SignedFile::Transactions(transactions))
+        }
+        _ => Err(CliError::UserError(format!(
+            "{} is neither a serialized BatchList nor a batch file",
+            name
+        ))),
+    }
+}
+
+/// Whether the signature of a batch or transaction header was made by the given public key
+pub fn verify_signature(header: &[u8], signature: &str, public_key: &[u8]) -> bool {
+    let signature = match Signature::from_hex(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+
+    Secp256k1Context::new()
+        .new_verifier()
+        .verify(header, &signature, &PublicKey::new(public_key.to_vec()))
+        .unwrap_or(false)
+}
+
+fn load_transactions(path: &str) -> Result<Vec<Transaction>, CliError> {
+    let bytes = fs::read(path).map_err(|err| {
+        CliError::UserError(format!("Could not read batch file {}: {}", path, err))
+    })?;
+
+    parse_transactions(&bytes, path)
+}
+
+fn parse_transactions(bytes: &[u8], path: &str) -> Result<Vec<Transaction>, CliError> {
+    let transaction_list = TransactionList::parse_from_bytes(bytes)
+        .map_err(|err| CliError::UserError(format!("Malformed batch file {}: {}", path, err)))?;
+
+    transaction_list
+        .transactions
+        .into_iter()
+        .map(|transaction| Transaction::from_proto(transaction).map_err(CliError::from))
+        .collect()
+}
+
+/// Decode the header of a transaction
+pub fn read_header(transaction: &Transaction) -> Result<TransactionHeader, CliError> {
+    Ok(TransactionHeader::from_bytes(transaction.header())?)
+}
+
+/// Decode the header of a batch
+pub fn read_batch_header(batch: &Batch) -> Result<BatchHeader, CliError> {
+    Ok(BatchHeader::from_bytes(batch.header())?)
+}
+
+/// Log the transactions of the batches being submitted or written
+///
+/// The batch and transaction IDs and the addresses each transaction reads and writes are logged
+/// at debug level, and the rest of each transaction header at trace level.
+pub fn log_batches(batches: &[Batch]) {
+    for batch in batches {
+        debug!(
+            "batch {} ({} transaction(s))",
+            batch.header_signature(),
+            batch.transactions().len()
+        );
+
+        for transaction in batch.transactions() {
+            let header = match read_header(transaction) {
+                Ok(header) => header,
+                Err(err) => {
+                    warn!(
+                        "Unable to read header of transaction {}: {}",
+                        transaction.header_signature(),
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            let join = |values: &[Vec<u8>]| {
+                values
+                    .iter()
+                    .map(|value| to_hex(value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+
+            debug!("transaction {}", transaction.header_signature());
+            debug!("  inputs: {}", join(header.inputs()));
+            debug!("  outputs: {}", join(header.outputs()));
+            trace!(
+                "  family: {} {}",
+                header.family_name(),
+                header.family_version()
+            );
+            trace!("  signer: {}", to_hex(header.signer_public_key()));
+            trace!("  batcher: {}", to_hex(header.batcher_public_key()));
+            trace!("  dependencies: {}", join(header.dependencies()));
+            trace!("  nonce: {}", to_hex(header.nonce()));
+            trace!("  payload sha512: {}", to_hex(header.payload_hash()));
+        }
+    }
+}
+
+/// Hex-encode bytes, such as the addresses and public keys of a transaction header
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// Decode a hex-encoded transaction ID
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, CliError> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| CliError::UserError(format!("Invalid transaction ID: {}", hex)))
+        })
+        .collect()
+}
@@ -0,0 +1,416 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains the functions used by `sabre build` to compile a contract crate to WebAssembly
+//!
+//! The crate is built with cargo for the `wasm32-unknown-unknown` target. Common causes of a
+//! failed build, and of a module that builds but cannot be executed by Sabre, are explained.
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use serde::de::IgnoredAny;
+
+use crate::error::CliError;
+use crate::wasm::{self, Kind};
+
+/// The target contracts are compiled for
+const TARGET: &str = "wasm32-unknown-unknown";
+
+/// The functions the Sabre transaction processor provides to contracts, in the "env" module
+const SABRE_FUNCTIONS: &[&str] = &[
+    "get_state",
+    "set_state",
+    "delete_state",
+    "add_event",
+    "add_receipt_data",
+    "invoke_smart_permission",
+    "invoke_contract",
+    "set_error",
+    "get_ptr_len",
+    "get_ptr_capacity",
+    "alloc",
+    "read_byte",
+    "write_byte",
+    "get_ptr_collection_len",
+    "get_ptr_from_collection",
+    "create_collection",
+    "add_to_collection",
+    "log_buffer",
+    "log_level",
+];
+
+/// The WebAssembly features beyond the original (MVP) specification that Sabre's interpreter
+/// supports
+const SUPPORTED_FEATURES: &[&str] = &["mutable-globals"];
+
+/// Explanations of cargo and rustc errors which are common when building for wasm32, with the
+/// text which identifies each error
+const BUILD_HINTS: &[(&[&str], &str)] = &[
+    (
+        &["target may not be installed", "can't find crate for `std`"],
+        "The wasm32-unknown-unknown target is not installed; install it with \
+         `rustup target add wasm32-unknown-unknown`",
+    ),
+    (
+        &["docs.rs/getrandom", "wasm_js"],
+        "A dependency uses getrandom, which has no source of randomness on \
+         wasm32-unknown-unknown; contracts must be deterministic, so remove the dependency or \
+         disable the feature that needs random numbers",
+    ),
+    (
+        &["failed to run custom build command"],
+        "A dependency's build script failed; dependencies which compile native (C) code, such as \
+         the *-sys crates, do not build for wasm32-unknown-unknown, so disable the feature that \
+         pulls them in or use a pure Rust alternative",
+    ),
+    (
+        &["undefined symbol"],
+        "The contract refers to a function which is not defined for wasm32-unknown-unknown, \
+         usually from a dependency's native (C) code; use a pure Rust alternative",
+    ),
+];
+
+/// A contract built by `build`
+pub struct BuiltContract {
+    pub path: PathBuf,
+    pub size: usize,
+    /// The size of the module before its custom sections were stripped
+    pub unstripped_size: usize,
+}
+
+/// Build the contract crate of the Cargo.toml at `manifest_path`, or in the current directory
+///
+/// The crate is built with the release profile unless `release` is false, in which case the
+/// module keeps its debug information. The module is checked for imports and features which
+/// Sabre cannot provide before it is reported.
+pub fn build(
+    manifest_path: Option<&str>,
+    release: bool,
+    features: Option<&str>,
+) -> Result<BuiltContract, CliError> {
+    let manifest_path = fs::canonicalize(manifest_path.unwrap_or("Cargo.toml")).map_err(|err| {
+        CliError::UserError(format!(
+            "Could not find the contract's Cargo.toml ({}); run sabre build in the contract's \
+             crate, or use --manifest-path",
+            err
+        ))
+    })?;
+    check_manifest(&manifest_path)?;
+    check_target_installed()?;
+
+    let mut command = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".into()));
+    command
+        .arg("build")
+        .arg("--target")
+        .arg(TARGET)
+        .arg("--manifest-path")
+        .arg(&manifest_path)
+        .arg("--message-format=json-render-diagnostics");
+    if release {
+        command.arg("--release");
+    }
+    if let Some(features) = features {
+        command.arg("--features").arg(features);
+    }
+    // Sabre's interpreter only supports the original WebAssembly instruction set, which rustc
+    // no longer targets by default. Flags set in the environment replace these.
+    if env::var_os("RUSTFLAGS").is_none() && env::var_os("CARGO_ENCODED_RUSTFLAGS").is_none() {
+        command.arg("--config").arg(format!(
+            "target.{}.rustflags=[\"-C\", \"target-cpu=mvp\"]",
+            TARGET
+        ));
+    }
+
+    debug!("Running {:?}", command);
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| CliError::UserError(format!("Unable to run cargo: {}", err)))?;
+
+    // cargo's progress and errors are passed through as they arrive, and kept to be explained
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let errors = thread::spawn(move || {
+        BufReader::new(stderr)
+            .lines()
+            .map_while(Result::ok)
+            .map(|line| {
+                eprintln!("{}", line);
+                line
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut modules = vec![];
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        let message = match serde_json::from_str::<serde_json::Value>(&line) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if message["reason"] != "compiler-artifact"
+            || message["manifest_path"].as_str().map(Path::new) != Some(manifest_path.as_path())
+        {
+            continue;
+        }
+
+        let kinds = message["target"]["kind"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        for filename in message["filenames"].as_array().into_iter().flatten() {
+            if let Some(filename) = filename.as_str().filter(|name| name.ends_with(".wasm")) {
+                modules.push((kinds.contains(&"cdylib".into()), PathBuf::from(filename)));
+            }
+        }
+    }
+
+    let status = child
+        .wait()
+        .map_err(|err| CliError::UserError(format!("Unable to run cargo: {}", err)))?;
+    let errors = errors.join().unwrap_or_default();
+    if !status.success() {
+        let hints = BUILD_HINTS
+            .iter()
+            .filter(|(patterns, _)| {
+                errors
+                    .iter()
+                    .any(|line| patterns.iter().any(|pattern| line.contains(pattern)))
+            })
+            .map(|(_, hint)| format!("\n  {}", hint))
+            .collect::<String>();
+        return Err(CliError::UserError(format!(
+            "cargo build failed{}",
+            if hints.is_empty() {
+                String::new()
+            } else {
+                format!("; possible causes:{}", hints)
+            }
+        )));
+    }
+
+    // A crate with both a cdylib and a binary produces two modules; the cdylib is the contract
+    modules.sort_by_key(|(is_cdylib, _)| !is_cdylib);
+    let path = match modules.into_iter().next() {
+        Some((_, path)) => path,
+        None => {
+            return Err(CliError::UserError(format!(
+                "cargo did not produce a .wasm module for {}; add `crate-type = [\"cdylib\"]` to \
+                 its [lib] section",
+                manifest_path.display()
+            )))
+        }
+    };
+
+    let contract = fs::read(&path).map_err(|err| {
+        CliError::UserError(format!(
+            "Could not read contract \"{}\": {}",
+            path.display(),
+            err
+        ))
+    })?;
+    check_module(&contract)?;
+
+    let unstripped_size = contract.len();
+    let size = if release {
+        let stripped = wasm::strip_custom_sections(&contract)?;
+        fs::write(&path, &stripped).map_err(|err| {
+            CliError::UserError(format!(
+                "Could not write contract \"{}\": {}",
+                path.display(),
+                err
+            ))
+        })?;
+        stripped.len()
+    } else {
+        unstripped_size
+    };
+
+    Ok(BuiltContract {
+        path,
+        size,
+        unstripped_size,
+    })
+}
+
+/// Check that the crate produces a module, which requires a cdylib library or a binary
+fn check_manifest(manifest_path: &Path) -> Result<(), CliError> {
+    let contents = fs::read_to_string(manifest_path).map_err(|err| {
+        CliError::UserError(format!(
+            "Could not read \"{}\": {}",
+            manifest_path.display(),
+            err
+        ))
+    })?;
+    let manifest = toml::from_str::<CargoManifest>(&contents).map_err(|err| {
+        CliError::UserError(format!(
+            "Could not parse \"{}\": {}",
+            manifest_path.display(),
+            err
+        ))
+    })?;
+
+    if manifest.package.is_none() {
+        return Err(CliError::UserError(format!(
+            "{} is a workspace manifest; use --manifest-path with the Cargo.toml of the contract's \
+             crate",
+            manifest_path.display()
+        )));
+    }
+
+    let dir = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+    let has_bin = manifest.bin.is_some() || dir.join("src/main.rs").exists();
+    let is_cdylib = manifest
+        .lib
+        .map(|lib| lib.crate_type.iter().any(|kind| kind == "cdylib"))
+        .unwrap_or(false);
+
+    if !is_cdylib && !has_bin {
+        return Err(CliError::UserError(format!(
+            "{} does not build a .wasm module; a contract library needs the cdylib crate \
+             type:\n\n[lib]\ncrate-type = [\"cdylib\", \"rlib\"]",
+            manifest_path.display()
+        )));
+    }
+
+    Ok(())
+}
+
+/// The parts of a Cargo.toml which determine whether a crate produces a module
+#[derive(Deserialize)]
+struct CargoManifest {
+    package: Option<IgnoredAny>,
+    lib: Option<CargoLib>,
+    bin: Option<IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+struct CargoLib {
+    #[serde(rename = "crate-type", default)]
+    crate_type: Vec<String>,
+}
+
+/// Check that the wasm32 target is installed, if the toolchain is managed by rustup
+fn check_target_installed() -> Result<(), CliError> {
+    let output = match Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return Ok(()),
+    };
+
+    if String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|target| target.trim() == TARGET)
+    {
+        Ok(())
+    } else {
+        Err(CliError::UserError(format!(
+            "The {} target is not installed; install it with `rustup target add {}`",
+            TARGET, TARGET
+        )))
+    }
+}
+
+/// Check that Sabre can execute the module, explaining any imports it cannot provide
+fn check_module(contract: &[u8]) -> Result<(), CliError> {
+    let info = wasm::inspect(contract)?;
+
+    if !info
+        .exports
+        .iter()
+        .any(|(name, kind)| name == "entrypoint" && *kind == Kind::Function)
+    {
+        return Err(CliError::UserError(
+            "The contract does not export an entrypoint function; define it with #[no_mangle] \
+             as `pub unsafe fn entrypoint(payload: WasmPtr, signer: WasmPtr, signature: WasmPtr) \
+             -> i32`, calling sabre_sdk::execute_entrypoint"
+                .into(),
+        ));
+    }
+
+    if info.has_start {
+        return Err(CliError::UserError(
+            "The contract has a start function, which Sabre does not run".into(),
+        ));
+    }
+
+    let unresolved = info
+        .imports
+        .iter()
+        .filter(|import| {
+            import.module != "env"
+                || match import.kind {
+                    Kind::Function => !SABRE_FUNCTIONS.contains(&import.field.as_str()),
+                    Kind::Memory => import.field != "memory",
+                    Kind::Table | Kind::Global => true,
+                }
+        })
+        .collect::<Vec<_>>();
+    if !unresolved.is_empty() {
+        let mut message =
+            "The contract imports items which Sabre does not provide, so it cannot be executed:"
+                .to_string();
+        for import in &unresolved {
+            message.push_str(&format!("\n  {}.{}", import.module, import.field));
+        }
+
+        let modules = unresolved
+            .iter()
+            .map(|import| import.module.as_str())
+            .collect::<Vec<_>>();
+        if modules.iter().any(|module| module.contains("wbindgen")) {
+            message.push_str(
+                "\nwasm-bindgen imports come from a dependency built for JavaScript, e.g. \
+                 getrandom's `js` feature or chrono's `wasmbind` feature; disable that feature",
+            );
+        }
+        if modules.iter().any(|module| module.starts_with("wasi")) {
+            message.push_str(
+                "\nWASI imports come from code built for wasm32-wasi; contracts must be built for \
+                 wasm32-unknown-unknown",
+            );
+        }
+        if modules.contains(&"env") {
+            message.push_str(
+                "\nOther env imports are functions declared in an extern block, usually by a \
+                 dependency's native (C) code, which are not defined on wasm32-unknown-unknown",
+            );
+        }
+
+        return Err(CliError::UserError(message));
+    }
+
+    let unsupported = info
+        .features
+        .iter()
+        .filter(|feature| !SUPPORTED_FEATURES.contains(&feature.as_str()))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !unsupported.is_empty() {
+        warn!(
+            "The contract uses WebAssembly features which Sabre may not support: {}; they are \
+             usually enabled by RUSTFLAGS or by the precompiled standard library",
+            unsupported.join(", ")
+        );
+    }
+
+    Ok(())
+}
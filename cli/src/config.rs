@@ -0,0 +1,229 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which assist with loading the CLI configuration file
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::CliError;
+
+/// Default values for command line arguments, loaded from a TOML file
+///
+/// Values given on the command line always take precedence over values from the configuration
+/// file.
+#[derive(Clone, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CliConfig {
+    url: Option<String>,
+    key: Option<String>,
+    key_dir: Option<String>,
+    batcher_key: Option<String>,
+    auth: Option<String>,
+    wait: Option<u64>,
+    format: Option<String>,
+    subscribe: Option<bool>,
+    target: Option<String>,
+    circuit: Option<String>,
+    service_id: Option<String>,
+    retries: Option<u32>,
+    retry_backoff: Option<u64>,
+    retry_jitter: Option<u64>,
+    chunk_size: Option<usize>,
+    max_contract_size: Option<usize>,
+    /// The name of the profile used when `--profile` is not given
+    profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, ConfigProfile>,
+}
+
+/// The connection details of a network, selected with `--profile`
+///
+/// A profile's values take precedence over the values at the top level of the configuration
+/// file.
+#[derive(Clone, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigProfile {
+    pub url: Option<String>,
+    pub key: Option<String>,
+    pub key_dir: Option<String>,
+    pub batcher_key: Option<String>,
+    pub auth: Option<String>,
+    pub target: Option<String>,
+    pub circuit: Option<String>,
+    pub service_id: Option<String>,
+}
+
+impl CliConfig {
+    /// Load the configuration file
+    ///
+    /// If `path` is None, the configuration is loaded from `$HOME/.config/sabre/config.toml`; if
+    /// that file does not exist, an empty configuration is returned. If `path` is given, the file
+    /// must exist.
+    pub fn load(path: Option<&str>) -> Result<CliConfig, CliError> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => match default_config_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(CliConfig::default()),
+            },
+        };
+
+        let contents = fs::read_to_string(&path).map_err(|err| {
+            CliError::UserError(format!(
+                "Could not load configuration file \"{}\": {}",
+                path.display(),
+                err
+            ))
+        })?;
+
+        toml::from_str(&contents).map_err(|err| {
+            CliError::UserError(format!(
+                "Malformed configuration file \"{}\": {}",
+                path.display(),
+                err
+            ))
+        })
+    }
+
+    /// Apply the values of the named profile, or of the default profile if `name` is None
+    ///
+    /// If no profile is named and the configuration file has no default, the configuration is
+    /// returned unchanged.
+    pub fn with_profile(mut self, name: Option<&str>) -> Result<CliConfig, CliError> {
+        let name = match name.map(String::from).or_else(|| self.profile.clone()) {
+            Some(name) => name,
+            None => return Ok(self),
+        };
+        let profile = self.profiles.get(&name).cloned().ok_or_else(|| {
+            CliError::UserError(format!(
+                "Profile \"{}\" is not defined in the configuration file",
+                name
+            ))
+        })?;
+
+        self.url = profile.url.or(self.url);
+        self.key = profile.key.or(self.key);
+        self.key_dir = profile.key_dir.or(self.key_dir);
+        self.batcher_key = profile.batcher_key.or(self.batcher_key);
+        self.auth = profile.auth.or(self.auth);
+        self.target = profile.target.or(self.target);
+        self.circuit = profile.circuit.or(self.circuit);
+        self.service_id = profile.service_id.or(self.service_id);
+        self.profile = Some(name);
+
+        Ok(self)
+    }
+
+    /// The name of the profile in use, if any
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// The profiles defined in the configuration file, by name
+    pub fn profiles(&self) -> &BTreeMap<String, ConfigProfile> {
+        &self.profiles
+    }
+
+    /// The URL of the Sawtooth REST API
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// The name of or path to the signing key
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// The directory containing signing keys
+    pub fn key_dir(&self) -> Option<&str> {
+        self.key_dir.as_deref()
+    }
+
+    /// The name of or path to the key which signs batches, if not the signing key
+    pub fn batcher_key(&self) -> Option<&str> {
+        self.batcher_key.as_deref()
+    }
+
+    /// The credentials to authenticate with the REST API, as `bearer:<token>` or
+    /// `basic:<user:password>`
+    pub fn auth(&self) -> Option<&str> {
+        self.auth.as_deref()
+    }
+
+    /// The time in seconds to wait for batches to be committed
+    pub fn wait(&self) -> Option<u64> {
+        self.wait
+    }
+
+    /// The format to display command output in
+    pub fn format(&self) -> Option<&str> {
+        self.format.as_deref()
+    }
+
+    /// Whether to wait for batches by subscribing to block events instead of polling
+    pub fn subscribe(&self) -> bool {
+        self.subscribe.unwrap_or(false)
+    }
+
+    /// Where batches are submitted: `sawtooth` or `scabbard`
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// The ID of the circuit of the scabbard service to submit batches to
+    pub fn circuit(&self) -> Option<&str> {
+        self.circuit.as_deref()
+    }
+
+    /// The ID of the scabbard service to submit batches to
+    pub fn service_id(&self) -> Option<&str> {
+        self.service_id.as_deref()
+    }
+
+    /// The number of times to retry REST API requests which fail with a transient error
+    pub fn retries(&self) -> Option<u32> {
+        self.retries
+    }
+
+    /// The delay in milliseconds before the first retry
+    pub fn retry_backoff(&self) -> Option<u64> {
+        self.retry_backoff
+    }
+
+    /// The maximum random delay in milliseconds added to each retry
+    pub fn retry_jitter(&self) -> Option<u64> {
+        self.retry_jitter
+    }
+
+    /// The size in bytes above which contracts are uploaded in chunks of this size
+    pub fn chunk_size(&self) -> Option<usize> {
+        self.chunk_size
+    }
+
+    /// The size in bytes above which `sabre upload` warns that a contract is large
+    pub fn max_contract_size(&self) -> Option<usize> {
+        self.max_contract_size
+    }
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|mut p| {
+        p.push(".config");
+        p.push("sabre");
+        p.push("config.toml");
+        p
+    })
+}
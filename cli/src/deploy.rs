@@ -0,0 +1,237 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which plan the deployment of a contract, comparing it with state
+//!
+//! Deploying a contract takes, in order:
+//!
+//! 1. creating the contract's registry
+//! 2. uploading the contract
+//! 3. creating each namespace the contract is granted permissions on
+//! 4. granting the contract its permissions on each namespace
+//!
+//! Each step is only planned if state does not already reflect it, so a deployment which was
+//! partially applied is completed by running it again.
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
+use cylinder::Signer;
+use sabre_sdk::protocol::payload::{
+    CreateContractRegistryActionBuilder, CreateNamespaceRegistryActionBuilder,
+    CreateNamespaceRegistryPermissionActionBuilder,
+};
+use sabre_sdk::protocol::state::{
+    ContractRegistry, ContractRegistryList, NamespaceRegistry, NamespaceRegistryList,
+};
+use sabre_sdk::protocol::{compute_contract_registry_address, compute_namespace_registry_address};
+use sabre_sdk::protos::FromBytes;
+use transact::protocol::transaction::TransactionBuilder;
+
+use crate::batch::to_hex;
+use crate::error::CliError;
+use crate::http::HttpClient;
+use crate::state;
+use crate::upload::ContractUpload;
+
+/// A contract to deploy, with the namespaces it is granted permissions on
+pub struct Deployment {
+    pub contract: ContractUpload,
+    pub namespaces: Vec<String>,
+    /// The owners of the contract registry and namespaces, if they are created, in addition to
+    /// the signing key
+    pub owners: Vec<String>,
+    pub read: bool,
+    pub write: bool,
+}
+
+/// What a step of a deployment does
+pub enum Action {
+    CreateContractRegistry,
+    UploadContract,
+    /// Create the given namespace
+    CreateNamespace(String),
+    /// Grant the contract its permissions on the given namespace
+    GrantPermission(String),
+}
+
+/// A transaction of a deployment
+pub struct Step {
+    pub action: Action,
+    pub transaction: TransactionBuilder,
+}
+
+/// Return the steps of the deployment which differ from state, in the order they must be applied
+///
+/// The signing key must own the contract registry, if it exists, since only its owners can upload
+/// new versions of the contract. If the version being deployed was already uploaded, it must be
+/// the same contract.
+pub fn plan(
+    client: &HttpClient,
+    url: &str,
+    deployment: Deployment,
+    signer: &dyn Signer,
+) -> Result<Vec<Step>, CliError> {
+    let public_key = to_hex(
+        signer
+            .public_key()
+            .map_err(|err| CliError::SigningError(err.to_string()))?
+            .as_slice(),
+    );
+
+    let mut owners = vec![public_key.clone()];
+    for owner in deployment.owners {
+        if !owners.contains(&owner) {
+            owners.push(owner);
+        }
+    }
+
+    let (read, write) = (deployment.read, deployment.write);
+    let contract = deployment.contract;
+    let name = contract.name.clone();
+    let mut steps = Vec::new();
+
+    let uploaded = match get_contract_registry(client, url, &name)? {
+        Some(registry) => {
+            if !registry.owners().contains(&public_key) {
+                return Err(CliError::UserError(format!(
+                    "contract registry '{}' is not owned by the signing key, so it cannot upload \
+                     the contract; its owners are {}",
+                    name,
+                    registry.owners().join(", ")
+                )));
+            }
+
+            let mut sha = Sha512::new();
+            sha.input(&contract.contract);
+            let sha512 = sha.result_str();
+
+            match registry
+                .versions()
+                .iter()
+                .find(|version| version.version() == &contract.version)
+            {
+                Some(version) if version.contract_sha512().eq_ignore_ascii_case(&sha512) => true,
+                Some(_) => {
+                    return Err(CliError::UserError(format!(
+                        "contract '{}:{}' was already uploaded with a different WebAssembly \
+                         module; deploy the contract under a new version",
+                        name, contract.version
+                    )))
+                }
+                None => false,
+            }
+        }
+        None => {
+            steps.push(Step {
+                action: Action::CreateContractRegistry,
+                transaction: CreateContractRegistryActionBuilder::new()
+                    .with_name(name.clone())
+                    .with_owners(owners.clone())
+                    .into_payload_builder()?
+                    .into_transaction_builder(signer)?,
+            });
+            false
+        }
+    };
+
+    if !uploaded {
+        steps.push(Step {
+            action: Action::UploadContract,
+            transaction: contract.into_transaction_builder(signer)?,
+        });
+    }
+
+    let mut namespaces: Vec<String> = Vec::new();
+    for namespace in deployment.namespaces {
+        if !namespaces.contains(&namespace) {
+            namespaces.push(namespace);
+        }
+    }
+
+    for namespace in namespaces {
+        let granted = match get_namespace_registry(client, url, &namespace)? {
+            Some(registry) => registry.permissions().iter().any(|permission| {
+                permission.contract_name() == &name
+                    && permission.read() == read
+                    && permission.write() == write
+            }),
+            None => {
+                steps.push(Step {
+                    action: Action::CreateNamespace(namespace.clone()),
+                    transaction: CreateNamespaceRegistryActionBuilder::new()
+                        .with_namespace(namespace.clone())
+                        .with_owners(owners.clone())
+                        .into_payload_builder()?
+                        .into_transaction_builder(signer)?,
+                });
+                false
+            }
+        };
+
+        if !granted {
+            steps.push(Step {
+                action: Action::GrantPermission(namespace.clone()),
+                transaction: CreateNamespaceRegistryPermissionActionBuilder::new()
+                    .with_namespace(namespace)
+                    .with_contract_name(name.clone())
+                    .with_read(read)
+                    .with_write(write)
+                    .into_payload_builder()?
+                    .into_transaction_builder(signer)?,
+            });
+        }
+    }
+
+    Ok(steps)
+}
+
+fn get_contract_registry(
+    client: &HttpClient,
+    url: &str,
+    name: &str,
+) -> Result<Option<ContractRegistry>, CliError> {
+    let address = to_hex(&compute_contract_registry_address(name).map_err(|err| {
+        CliError::UserError(format!("Unable to get contract registry address: {}", err))
+    })?);
+
+    match state::get_state_entry(client, url, &address)? {
+        Some(bytes) => Ok(ContractRegistryList::from_bytes(&bytes)?
+            .registries()
+            .iter()
+            .find(|registry| registry.name() == name)
+            .cloned()),
+        None => Ok(None),
+    }
+}
+
+fn get_namespace_registry(
+    client: &HttpClient,
+    url: &str,
+    namespace: &str,
+) -> Result<Option<NamespaceRegistry>, CliError> {
+    let address = to_hex(
+        &compute_namespace_registry_address(namespace).map_err(|err| {
+            CliError::UserError(format!("Unable to get namespace registry address: {}", err))
+        })?,
+    );
+
+    match state::get_state_entry(client, url, &address)? {
+        Some(bytes) => Ok(NamespaceRegistryList::from_bytes(&bytes)?
+            .registries()
+            .iter()
+            .find(|registry| registry.namespace() == namespace)
+            .cloned()),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which execute a transaction locally, against state fetched from the REST API
+//!
+//! The transaction is applied by the Sabre transaction processor's own handler, so the contract
+//! runs in the same WebAssembly environment it would on the validator. Nothing is submitted; the
+//! state changes, events and receipt data are collected and returned instead.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use protobuf::Message as _;
+use sawtooth_sabre::admin::SettingsAdminPermission;
+use sawtooth_sabre::handler::SabreTransactionHandler;
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::messages::transaction::TransactionHeader;
+use sawtooth_sdk::processor::handler::{
+    ApplyError, ContextError, TransactionContext, TransactionHandler,
+};
+use transact::protocol::transaction::Transaction;
+
+use crate::error::CliError;
+use crate::http::HttpClient;
+use crate::state;
+
+/// A change the transaction would make to state
+pub enum StateChange {
+    Set { address: String, value: Vec<u8> },
+    Delete { address: String },
+}
+
+/// An event the transaction would emit
+pub struct Event {
+    pub event_type: String,
+    pub attributes: Vec<(String, String)>,
+    pub data: Vec<u8>,
+}
+
+/// The result of executing a transaction locally
+pub struct DryRun {
+    /// The state changes, in the order they were made
+    pub changes: Vec<StateChange>,
+    pub events: Vec<Event>,
+    pub receipt_data: Vec<Vec<u8>>,
+}
+
+/// Apply the transaction against the state of the REST API at `url`, without submitting it
+///
+/// State is fetched as the transaction reads it, so only the contract, its registries and the
+/// addresses the contract actually reads are downloaded. If the transaction is rejected, the
+/// error is returned as an `InvalidBatchError`.
+pub fn execute(
+    client: &HttpClient,
+    url: &str,
+    transaction: &Transaction,
+) -> Result<DryRun, CliError> {
+    let header = TransactionHeader::parse_from_bytes(transaction.header()).map_err(|err| {
+        CliError::UserError(format!("Unable to read transaction header: {}", err))
+    })?;
+
+    let mut request = TpProcessRequest::new();
+    request.set_header(header.clone());
+    request.set_payload(transaction.payload().to_vec());
+    request.set_signature(transaction.header_signature().into());
+
+    let mut context = DryRunContext {
+        client,
+        url,
+        inputs: header.get_inputs().to_vec(),
+        outputs: header.get_outputs().to_vec(),
+        state: RefCell::new(HashMap::new()),
+        changes: RefCell::new(Vec::new()),
+        events: RefCell::new(Vec::new()),
+        receipt_data: RefCell::new(Vec::new()),
+    };
+
+    SabreTransactionHandler::new(Box::new(SettingsAdminPermission))
+        .apply(&request, &mut context)
+        .map_err(|err| match err {
+            ApplyError::InvalidTransaction(msg) => CliError::InvalidBatchError(format!(
+                "Transaction {} is invalid: {}",
+                transaction.header_signature(),
+                msg
+            )),
+            ApplyError::InternalError(msg) => {
+                CliError::UserError(format!("Unable to execute transaction: {}", msg))
+            }
+        })?;
+
+    Ok(DryRun {
+        changes: context.changes.into_inner(),
+        events: context.events.into_inner(),
+        receipt_data: context.receipt_data.into_inner(),
+    })
+}
+
+/// A transaction context which reads state from the REST API and records writes locally
+///
+/// As with the validator, reads and writes are limited to the transaction's inputs and outputs,
+/// and reads see the transaction's own earlier writes.
+struct DryRunContext<'a> {
+    client: &'a HttpClient,
+    url: &'a str,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    /// State fetched or written so far; `None` if there is no state at the address
+    state: RefCell<HashMap<String, Option<Vec<u8>>>>,
+    changes: RefCell<Vec<StateChange>>,
+    events: RefCell<Vec<Event>>,
+    receipt_data: RefCell<Vec<Vec<u8>>>,
+}
+
+impl<'a> DryRunContext<'a> {
+    fn get(&self, address: &str) -> Result<Option<Vec<u8>>, ContextError> {
+        if let Some(value) = self.state.borrow().get(address) {
+            return Ok(value.clone());
+        }
+
+        let value = state::get_state_entry(self.client, self.url, address)
+            .map_err(|err| ContextError::ReceiveError(Box::new(err)))?;
+        self.state
+            .borrow_mut()
+            .insert(address.to_string(), value.clone());
+
+        Ok(value)
+    }
+}
+
+impl<'a> TransactionContext for DryRunContext<'a> {
+    fn get_state_entries(
+        &self,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, ContextError> {
+        if !is_authorized(&self.inputs, addresses) {
+            return Err(ContextError::AuthorizationError(format!(
+                "Tried to get unauthorized addresses: {:?}",
+                addresses
+            )));
+        }
+
+        let mut entries = Vec::new();
+        for address in addresses {
+            match self.get(address)? {
+                Some(value) if !value.is_empty() => entries.push((address.clone(), value)),
+                _ => (),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn set_state_entries(&self, entries: Vec<(String, Vec<u8>)>) -> Result<(), ContextError> {
+        let addresses = entries
+            .iter()
+            .map(|(address, _)| address.clone())
+            .collect::<Vec<_>>();
+        if !is_authorized(&self.outputs, &addresses) {
+            return Err(ContextError::AuthorizationError(format!(
+                "Tried to set unauthorized addresses: {:?}",
+                addresses
+            )));
+        }
+
+        for (address, value) in entries {
+            self.state
+                .borrow_mut()
+                .insert(address.clone(), Some(value.clone()));
+            self.changes
+                .borrow_mut()
+                .push(StateChange::Set { address, value });
+        }
+
+        Ok(())
+    }
+
+    fn delete_state_entries(&self, addresses: &[String]) -> Result<Vec<String>, ContextError> {
+        if !is_authorized(&self.outputs, addresses) {
+            return Err(ContextError::AuthorizationError(format!(
+                "Tried to delete unauthorized addresses: {:?}",
+                addresses
+            )));
+        }
+
+        for address in addresses {
+            self.state.borrow_mut().insert(address.clone(), None);
+            self.changes.borrow_mut().push(StateChange::Delete {
+                address: address.clone(),
+            });
+        }
+
+        Ok(addresses.to_vec())
+    }
+
+    fn add_receipt_data(&self, data: &[u8]) -> Result<(), ContextError> {
+        self.receipt_data.borrow_mut().push(data.to_vec());
+        Ok(())
+    }
+
+    fn add_event(
+        &self,
+        event_type: String,
+        attributes: Vec<(String, String)>,
+        data: &[u8],
+    ) -> Result<(), ContextError> {
+        self.events.borrow_mut().push(Event {
+            event_type,
+            attributes,
+            data: data.to_vec(),
+        });
+        Ok(())
+    }
+}
+
+/// Whether every address is under one of the transaction's inputs or outputs
+fn is_authorized(allowed: &[String], addresses: &[String]) -> bool {
+    addresses
+        .iter()
+        .all(|address| allowed.iter().any(|prefix| address.starts_with(prefix)))
+}
@@ -33,6 +33,32 @@ pub enum CliError {
     ProtocolBuildError(Box<dyn StdError>),
     ProtoConversionError(ProtoConversionError),
     TransactProtoConversionError(TransactProtoConversionError),
+    /// Command output could not be formatted
+    OutputError(String),
+    /// The REST API could not be reached or is unavailable
+    ConnectionError(String),
+    /// A submitted batch was rejected; the string contains the invalid transaction messages
+    InvalidBatchError(String),
+    /// The batches were not committed before the wait time elapsed
+    WaitTimeoutError(String),
+}
+
+impl CliError {
+    /// The process exit code that identifies this kind of error to scripts
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::InvalidBatchError(_) => 2,
+            CliError::WaitTimeoutError(_) => 3,
+            CliError::ConnectionError(_) | CliError::HyperError(_) => 4,
+            _ => 1,
+        }
+    }
+
+    /// Whether the error means the REST API or validator could not be reached or is unavailable,
+    /// so that the request may succeed at another endpoint
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, CliError::ConnectionError(_) | CliError::HyperError(_))
+    }
 }
 
 impl StdError for CliError {
@@ -45,6 +71,10 @@ impl StdError for CliError {
             CliError::ProtocolBuildError(ref err) => Some(err.borrow()),
             CliError::ProtoConversionError(err) => Some(err),
             CliError::TransactProtoConversionError(err) => Some(err),
+            CliError::OutputError(_) => None,
+            CliError::ConnectionError(_) => None,
+            CliError::InvalidBatchError(_) => None,
+            CliError::WaitTimeoutError(_) => None,
         }
     }
 }
@@ -61,6 +91,10 @@ impl std::fmt::Display for CliError {
             CliError::TransactProtoConversionError(ref err) => {
                 write!(f, "Transact Proto Conversion Error: {}", err)
             }
+            CliError::OutputError(ref msg) => write!(f, "Output Error: {}", msg),
+            CliError::ConnectionError(ref msg) => write!(f, "Connection Error: {}", msg),
+            CliError::InvalidBatchError(ref msg) => write!(f, "Invalid Batch: {}", msg),
+            CliError::WaitTimeoutError(ref msg) => write!(f, "Wait Timeout: {}", msg),
         }
     }
 }
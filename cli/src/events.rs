@@ -0,0 +1,166 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which subscribe to the REST API for the state changes of each committed
+//! block
+
+use std::time::Duration;
+
+use crate::error::CliError;
+use crate::http::{self, HttpClient};
+use crate::websocket::WebSocket;
+
+/// How long to wait for the REST API to accept the subscription
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for a block before checking the connection again
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A change to state made by a committed block
+pub struct StateChange {
+    pub address: String,
+    /// The new value, or `None` if the entry was deleted
+    pub value: Option<Vec<u8>>,
+}
+
+/// An event emitted by a transaction in a committed block
+pub struct Event {
+    pub event_type: String,
+    pub attributes: Vec<(String, String)>,
+    pub data: Vec<u8>,
+}
+
+/// The state changes and events of a committed block
+pub struct Block {
+    pub block_num: u64,
+    pub block_id: String,
+    pub changes: Vec<StateChange>,
+    pub events: Vec<Event>,
+}
+
+/// Open a subscription to the REST API's state deltas under the given address prefixes
+///
+/// The REST API sends a message each time a block is committed. Subscriptions are made over a
+/// plain WebSocket, so they are not supported over https:// or through a proxy.
+pub fn subscribe(
+    client: &HttpClient,
+    url: &str,
+    prefixes: &[&str],
+    timeout: Duration,
+) -> Result<WebSocket, CliError> {
+    let ws_url = format!(
+        "{}/subscriptions",
+        url.trim_end_matches('/').replacen("http://", "ws://", 1)
+    );
+
+    if client.is_proxied(url)? {
+        return Err(CliError::UserError(
+            "subscriptions are not supported through a proxy".into(),
+        ));
+    }
+
+    let mut socket = WebSocket::connect(&ws_url, client.auth_header().as_deref(), timeout)?;
+    socket.send_text(
+        &serde_json::json!({
+            "action": "subscribe",
+            "address_prefixes": prefixes,
+        })
+        .to_string(),
+    )?;
+
+    Ok(socket)
+}
+
+/// Call `on_block` with the state changes under the given address prefixes in each block
+/// committed from now on
+///
+/// The subscription is made to the first of the endpoints listed in `url` which can be reached.
+/// Runs until the REST API closes the subscription, or `on_block` returns an error. The REST API
+/// does not forward the events emitted by transactions, so the events of each block are empty.
+pub fn listen(
+    client: &HttpClient,
+    url: &str,
+    prefixes: &[&str],
+    on_block: &mut dyn FnMut(Block) -> Result<(), CliError>,
+) -> Result<(), CliError> {
+    let (mut socket, url) = http::with_failover(url, |url| {
+        Ok((subscribe(client, url, prefixes, CONNECT_TIMEOUT)?, url))
+    })?;
+
+    loop {
+        let message = match socket.read_message(IDLE_TIMEOUT)? {
+            Some(message) => message,
+            None => continue,
+        };
+
+        let delta = serde_json::from_slice::<StateDeltaMessage>(&message).map_err(|err| {
+            CliError::ConnectionError(format!("Unable to parse state delta from {}: {}", url, err))
+        })?;
+
+        if let Some(error) = delta.error {
+            return Err(CliError::ConnectionError(format!(
+                "REST API at {} rejected the subscription: {}",
+                url, error
+            )));
+        }
+
+        let changes = delta
+            .state_changes
+            .into_iter()
+            .map(|change| {
+                let value = match change.change_type.as_str() {
+                    "DELETE" => None,
+                    _ => Some(base64::decode(&change.value).map_err(|_| {
+                        CliError::ConnectionError(format!(
+                            "Unable to decode state change at {}",
+                            change.address
+                        ))
+                    })?),
+                };
+                Ok(StateChange {
+                    address: change.address,
+                    value,
+                })
+            })
+            .collect::<Result<Vec<_>, CliError>>()?;
+
+        on_block(Block {
+            block_num: delta.block_num,
+            block_id: delta.block_id,
+            changes,
+            events: vec![],
+        })?;
+    }
+}
+
+/// A message sent by the REST API to a state delta subscriber
+#[derive(Deserialize)]
+struct StateDeltaMessage {
+    #[serde(default)]
+    block_num: u64,
+    #[serde(default)]
+    block_id: String,
+    #[serde(default)]
+    state_changes: Vec<JsonStateChange>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct JsonStateChange {
+    address: String,
+    #[serde(rename = "type")]
+    change_type: String,
+    #[serde(default)]
+    value: String,
+}
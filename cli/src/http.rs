@@ -0,0 +1,673 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains the HTTP client used for all requests to the REST API
+
+use std::env;
+use std::io;
+use std::str::{self, FromStr};
+use std::thread;
+use std::time::Duration;
+
+use futures::future::{self, Loop};
+use futures::{Future, Stream};
+use hyper::client::{Client, HttpConnector, Request, Service};
+use hyper::header::{ContentLength, ContentType};
+use hyper::{Method, StatusCode, Uri};
+use hyper_openssl::HttpsConnector;
+use openssl::ssl::{SslConnector, SslFiletype, SslMethod};
+use rand::Rng;
+use tokio_core::net::TcpStream;
+
+use crate::error::CliError;
+
+/// Credentials sent in the Authorization header of every request to the REST API
+pub enum Auth {
+    /// A bearer token, such as an OAuth access token
+    Bearer(String),
+    /// A user name and password, in the form `user:password`
+    Basic(String),
+}
+
+impl Auth {
+    /// The value of the Authorization header
+    pub fn header_value(&self) -> String {
+        match self {
+            Auth::Bearer(token) => format!("Bearer {}", token),
+            Auth::Basic(credentials) => format!("Basic {}", base64::encode(credentials)),
+        }
+    }
+}
+
+impl FromStr for Auth {
+    type Err = CliError;
+
+    /// Parse credentials given as `bearer:<token>` or `basic:<user:password>`
+    fn from_str(auth: &str) -> Result<Self, Self::Err> {
+        match auth.split_once(':') {
+            Some(("bearer", token)) if !token.is_empty() => Ok(Auth::Bearer(token.into())),
+            Some(("basic", credentials)) if credentials.contains(':') => {
+                Ok(Auth::Basic(credentials.into()))
+            }
+            _ => Err(CliError::UserError(
+                "Invalid authentication; expected bearer:<token> or basic:<user:password>".into(),
+            )),
+        }
+    }
+}
+
+/// Return the endpoints listed in a `--url` value, in the order they are tried
+///
+/// Several endpoints may be given, separated by commas.
+pub fn endpoints(url: &str) -> Vec<&str> {
+    url.split(',')
+        .map(str::trim)
+        .filter(|endpoint| !endpoint.is_empty())
+        .collect()
+}
+
+/// Make a request to each of the endpoints listed in `url` in turn, returning the result from the
+/// first one which can be reached
+///
+/// An endpoint is skipped if it cannot be reached or responds that it is unavailable; any other
+/// error is returned immediately, since the request would fail in the same way elsewhere. If no
+/// endpoint can be reached, the error from the last one is returned.
+pub fn with_failover<'a, T>(
+    url: &'a str,
+    mut request: impl FnMut(&'a str) -> Result<T, CliError>,
+) -> Result<T, CliError> {
+    let endpoints = endpoints(url);
+    let (last, others) = endpoints
+        .split_last()
+        .ok_or_else(|| CliError::UserError(format!("No URL given: \"{}\"", url)))?;
+
+    for (endpoint, next) in others.iter().zip(&endpoints[1..]) {
+        match request(endpoint) {
+            Err(err) if err.is_unreachable() => {
+                // Hints are left for the error from the last endpoint
+                let err = err.to_string();
+                let reason = err.lines().next().unwrap_or_default();
+                warn!("{} is unavailable ({}); trying {}", endpoint, reason, next);
+            }
+            result => return result,
+        }
+    }
+
+    request(last)
+}
+
+/// The status and body of a response from the REST API
+pub struct Response {
+    pub status: StatusCode,
+    pub body: Vec<u8>,
+    retry_after: Option<Duration>,
+}
+
+/// How requests are retried when the REST API responds with a transient error
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// The number of times a request is retried before its response is returned
+    pub retries: u32,
+    /// The delay before the first retry, which doubles with each further retry
+    pub backoff: Duration,
+    /// The maximum random delay added to each retry, so that clients do not retry in lockstep
+    pub jitter: Duration,
+}
+
+impl RetryPolicy {
+    /// The delay before the retry following the given number of previous retries
+    fn delay(&self, retry: u32) -> Duration {
+        let backoff = self
+            .backoff
+            .checked_mul(2u32.saturating_pow(retry))
+            .unwrap_or(MAX_RETRY_DELAY)
+            .min(MAX_RETRY_DELAY);
+        let jitter_ms = self.jitter.as_millis() as u64;
+        let jitter = if jitter_ms > 0 {
+            Duration::from_millis(rand::thread_rng().gen_range(0..=jitter_ms))
+        } else {
+            Duration::from_millis(0)
+        };
+
+        backoff + jitter
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 0,
+            backoff: Duration::from_millis(500),
+            jitter: Duration::from_millis(250),
+        }
+    }
+}
+
+impl Response {
+    /// Return the response if its status indicates success, or an error describing the failure
+    ///
+    /// Errors from the Sawtooth REST API are decoded to include the error code, title and
+    /// message, along with a hint for the errors a user can fix. A rejected batch submission is
+    /// an `InvalidBatchError`, and an unavailable REST API or validator is a `ConnectionError`.
+    pub fn error_for_status(self, url: &str) -> Result<Response, CliError> {
+        if self.status.is_success() {
+            return Ok(self);
+        }
+
+        let body = serde_json::from_slice::<ErrorResponse>(&self.body).ok();
+        let error = body.as_ref().and_then(|body| body.error.as_ref());
+        let code = error.and_then(|error| error.code);
+
+        let mut msg = format!("REST API at {} responded with {}", url, self.status);
+        match (error, body.as_ref().and_then(|body| body.message.as_ref())) {
+            (Some(error), _) => {
+                if let (Some(code), Some(title)) = (&error.code, &error.title) {
+                    msg += &format!(": error {} ({})", code, title);
+                }
+                if let Some(message) = &error.message {
+                    msg += &format!(": {}", message);
+                }
+            }
+            // Splinter responds with only a message
+            (None, Some(message)) => msg += &format!(": {}", message),
+            (None, None) => {
+                if let Ok(text) = str::from_utf8(&self.body) {
+                    let text = text.trim();
+                    if !text.is_empty() && text.len() <= MAX_ERROR_BODY_LEN {
+                        msg += &format!(": {}", text);
+                    }
+                }
+            }
+        }
+        if let Some(hint) = error_hint(self.status, code, body.is_some()) {
+            msg += &format!("\nHint: {}", hint);
+        }
+
+        match (code, self.status) {
+            (Some(SUBMITTED_BATCHES_INVALID), _) => Err(CliError::InvalidBatchError(msg)),
+            (Some(VALIDATOR_NOT_READY), _)
+            | (Some(VALIDATOR_TIMED_OUT), _)
+            | (Some(VALIDATOR_DISCONNECTED), _)
+            | (_, StatusCode::BadGateway)
+            | (_, StatusCode::ServiceUnavailable)
+            | (_, StatusCode::GatewayTimeout) => Err(CliError::ConnectionError(msg)),
+            _ => Err(CliError::UserError(msg)),
+        }
+    }
+}
+
+/// The body of an error response
+///
+/// The Sawtooth REST API responds with an error object, while Splinter responds with a message.
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: Option<ApiError>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    code: Option<u32>,
+    title: Option<String>,
+    message: Option<String>,
+}
+
+// Error codes returned by the Sawtooth REST API
+const VALIDATOR_NOT_READY: u32 = 15;
+const VALIDATOR_TIMED_OUT: u32 = 17;
+const VALIDATOR_DISCONNECTED: u32 = 18;
+const SUBMITTED_BATCHES_INVALID: u32 = 30;
+const BATCH_QUEUE_FULL: u32 = 31;
+const BAD_PROTOBUF_SUBMITTED: u32 = 35;
+const INVALID_STATE_ADDRESS: u32 = 62;
+
+/// The longest response body included in an error when it is not a recognized error response
+const MAX_ERROR_BODY_LEN: usize = 200;
+
+/// Suggest how to fix the error the REST API responded with, if the user can do so
+///
+/// `recognized` is whether the body was a Sawtooth or Splinter error response.
+fn error_hint(status: StatusCode, code: Option<u32>, recognized: bool) -> Option<&'static str> {
+    match (code, status) {
+        (Some(VALIDATOR_NOT_READY), _) => Some(
+            "the validator is still starting or has no genesis block; try again once it is ready",
+        ),
+        (Some(VALIDATOR_TIMED_OUT), _) => {
+            Some("the validator is busy; try again later, or use --retries to retry automatically")
+        }
+        (Some(VALIDATOR_DISCONNECTED), _) => {
+            Some("the REST API has lost its connection to the validator; check that it is running")
+        }
+        (Some(SUBMITTED_BATCHES_INVALID), _) => Some(
+            "the batches are malformed or their signatures are invalid; check that batches \
+             written with --output were not modified after they were signed",
+        ),
+        (Some(BATCH_QUEUE_FULL), _) => Some(
+            "the validator is not accepting batches; try again later, or use --retries to retry \
+             automatically",
+        ),
+        (Some(BAD_PROTOBUF_SUBMITTED), _) => {
+            Some("the file submitted is not a BatchList; check that it was written by --output")
+        }
+        (Some(INVALID_STATE_ADDRESS), _) => {
+            Some("state addresses are hex-encoded; check that the namespace or address is hex")
+        }
+        (_, StatusCode::Unauthorized) | (_, StatusCode::Forbidden) => {
+            Some("check the credentials given with --auth or SABRE_AUTH")
+        }
+        (None, StatusCode::NotFound) if !recognized => {
+            Some("check that --url is the Sawtooth REST API, or use --target scabbard for Splinter")
+        }
+        _ => None,
+    }
+}
+
+/// The longest delay between retries, before jitter is added
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Whether the response status indicates that the request may succeed if retried
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TooManyRequests
+            | StatusCode::BadGateway
+            | StatusCode::ServiceUnavailable
+            | StatusCode::GatewayTimeout
+    )
+}
+
+/// Sends requests to the REST API, applying the options given on the command line to each one
+#[derive(Default)]
+pub struct HttpClient {
+    auth: Option<Auth>,
+    ca_file: Option<String>,
+    client_identity: Option<(String, String)>,
+    proxy: Option<String>,
+    retry: RetryPolicy,
+}
+
+impl HttpClient {
+    pub fn new() -> Self {
+        HttpClient::default()
+    }
+
+    /// Send the credentials in the Authorization header of each request
+    pub fn with_auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Verify the REST API's certificate using the CA certificates in the given PEM file instead
+    /// of the system's trusted certificates
+    pub fn with_ca_file(mut self, ca_file: &str) -> Self {
+        self.ca_file = Some(ca_file.into());
+        self
+    }
+
+    /// Authenticate to the REST API using the certificate and private key in the given PEM files
+    pub fn with_client_identity(mut self, cert_file: &str, key_file: &str) -> Self {
+        self.client_identity = Some((cert_file.into(), key_file.into()));
+        self
+    }
+
+    /// Send requests through the given HTTP proxy instead of the proxy set in the environment
+    pub fn with_proxy(mut self, proxy: &str) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Retry requests which fail with a transient error according to the given policy
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Whether requests to the given URL are sent through a proxy
+    pub fn is_proxied(&self, url: &str) -> Result<bool, CliError> {
+        Ok(self.proxy_for(&parse_url(url)?)?.is_some())
+    }
+
+    /// The value of the Authorization header sent with each request, if any
+    pub fn auth_header(&self) -> Option<String> {
+        self.auth.as_ref().map(Auth::header_value)
+    }
+
+    pub fn get(&self, url: &str) -> Result<Response, CliError> {
+        self.send(Method::Get, url, None)
+    }
+
+    /// Post the bytes to the given URL with an `application/octet-stream` content type
+    pub fn post(&self, url: &str, bytes: Vec<u8>) -> Result<Response, CliError> {
+        self.send(Method::Post, url, Some(bytes))
+    }
+
+    /// Send the request, retrying it according to the retry policy while the REST API responds
+    /// with a transient error
+    ///
+    /// The same body is sent with each attempt, so a retried submission contains the same signed
+    /// batches and cannot be applied twice.
+    fn send(&self, method: Method, url: &str, body: Option<Vec<u8>>) -> Result<Response, CliError> {
+        let uri = parse_url(url)?;
+
+        let mut attempt = 0;
+        loop {
+            let response = self.send_once(method.clone(), uri.clone(), body.clone())?;
+            if attempt >= self.retry.retries || !is_transient(response.status) {
+                return Ok(response);
+            }
+
+            let delay = response
+                .retry_after
+                .unwrap_or_else(|| self.retry.delay(attempt));
+            attempt += 1;
+            warn!(
+                "REST API responded with {}; retrying in {:.1}s (retry {} of {})",
+                response.status,
+                delay.as_secs_f64(),
+                attempt,
+                self.retry.retries
+            );
+            thread::sleep(delay);
+        }
+    }
+
+    fn send_once(
+        &self,
+        method: Method,
+        uri: Uri,
+        body: Option<Vec<u8>>,
+    ) -> Result<Response, CliError> {
+        debug!("{} {}", method, uri);
+
+        let mut req = Request::new(method, uri);
+        if let Some(bytes) = body {
+            req.headers_mut().set(ContentType::octet_stream());
+            req.headers_mut().set(ContentLength(bytes.len() as u64));
+            req.set_body(bytes);
+        }
+
+        if let Some(auth) = self.auth_header() {
+            req.headers_mut().set_raw("Authorization", auth);
+        }
+
+        let proxy = self.proxy_for(req.uri())?;
+        if let Some(proxy) = &proxy {
+            // Requests to https URLs are tunneled through the proxy by the connector instead
+            if req.uri().scheme() != Some("https") {
+                req.set_proxy(true);
+                if let Some(authorization) = &proxy.authorization {
+                    req.headers_mut()
+                        .set_raw("Proxy-Authorization", authorization.clone());
+                }
+            }
+        }
+
+        let mut core = tokio_core::reactor::Core::new()?;
+        let handle = core.handle();
+
+        let mut http = HttpConnector::new(1, &handle);
+        http.enforce_http(false);
+        let connector =
+            HttpsConnector::with_connector(ProxyConnector { http, proxy }, self.ssl_connector()?);
+        let client = Client::configure().connector(connector).build(&handle);
+
+        let work = client.request(req).and_then(|res| {
+            let status = res.status();
+            let retry_after = res
+                .headers()
+                .get_raw("Retry-After")
+                .and_then(|value| value.one())
+                .and_then(|value| str::from_utf8(value).ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            res.body()
+                .concat2()
+                .and_then(move |chunks| future::ok((status, retry_after, chunks)))
+        });
+
+        let (status, retry_after, body) = core.run(work)?;
+
+        debug!("REST API responded with {} ({} bytes)", status, body.len());
+        if let Ok(text) = str::from_utf8(&body) {
+            trace!("response body: {}", text);
+        }
+
+        Ok(Response {
+            status,
+            body: body.to_vec(),
+            retry_after,
+        })
+    }
+
+    /// The proxy to send a request to the given URL through
+    ///
+    /// The proxy given with `with_proxy` is used if there is one; otherwise the proxy is read from
+    /// the HTTPS_PROXY or HTTP_PROXY environment variable, depending on the URL's scheme. No
+    /// proxy is used if the URL's host is listed in the NO_PROXY environment variable.
+    fn proxy_for(&self, uri: &Uri) -> Result<Option<Proxy>, CliError> {
+        let proxy = match &self.proxy {
+            Some(proxy) => Some(proxy.clone()),
+            None if uri.scheme() == Some("https") => env_var(&["HTTPS_PROXY", "https_proxy"]),
+            None => env_var(&["HTTP_PROXY", "http_proxy"]),
+        };
+
+        let proxy = match proxy {
+            Some(proxy) if !proxy.is_empty() => proxy,
+            _ => return Ok(None),
+        };
+
+        if let (Some(no_proxy), Some(host)) = (env_var(&["NO_PROXY", "no_proxy"]), uri.host()) {
+            if no_proxy_matches(&no_proxy, host) {
+                return Ok(None);
+            }
+        }
+
+        proxy.parse().map(Some)
+    }
+
+    fn ssl_connector(&self) -> Result<SslConnector, CliError> {
+        let mut builder = SslConnector::builder(SslMethod::tls())
+            .map_err(|err| CliError::UserError(format!("Unable to initialize TLS: {}", err)))?;
+
+        if let Some(ca_file) = &self.ca_file {
+            builder.set_ca_file(ca_file).map_err(|err| {
+                CliError::UserError(format!("Unable to load CA file {}: {}", ca_file, err))
+            })?;
+        }
+
+        if let Some((cert_file, key_file)) = &self.client_identity {
+            builder
+                .set_certificate_chain_file(cert_file)
+                .map_err(|err| {
+                    CliError::UserError(format!(
+                        "Unable to load client certificate {}: {}",
+                        cert_file, err
+                    ))
+                })?;
+            builder
+                .set_private_key_file(key_file, SslFiletype::PEM)
+                .map_err(|err| {
+                    CliError::UserError(format!("Unable to load client key {}: {}", key_file, err))
+                })?;
+            builder.check_private_key().map_err(|err| {
+                CliError::UserError(format!(
+                    "Client key {} does not match certificate {}: {}",
+                    key_file, cert_file, err
+                ))
+            })?;
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// An HTTP proxy, given as `http://[user:password@]host[:port]`
+struct Proxy {
+    uri: Uri,
+    authorization: Option<String>,
+}
+
+impl FromStr for Proxy {
+    type Err = CliError;
+
+    fn from_str(proxy: &str) -> Result<Self, Self::Err> {
+        let address = match proxy.split_once("://") {
+            Some(("http", address)) => address,
+            Some((scheme, _)) => {
+                return Err(CliError::UserError(format!(
+                    "Unsupported scheme ({}) in proxy URL: {}",
+                    scheme, proxy
+                )))
+            }
+            None => proxy,
+        }
+        .trim_end_matches('/');
+
+        let (authorization, address) = match address.rsplit_once('@') {
+            Some((credentials, address)) => (
+                Some(format!("Basic {}", base64::encode(credentials))),
+                address,
+            ),
+            None => (None, address),
+        };
+
+        let uri = format!("http://{}", address)
+            .parse::<Uri>()
+            .map_err(|err| CliError::UserError(format!("Invalid proxy URL: {}: {}", err, proxy)))?;
+
+        Ok(Proxy { uri, authorization })
+    }
+}
+
+/// Connects to the REST API directly or through a proxy
+///
+/// Connections to https URLs through a proxy are tunneled with a CONNECT request; the TLS
+/// session is then established with the REST API over the tunnel.
+struct ProxyConnector {
+    http: HttpConnector,
+    proxy: Option<Proxy>,
+}
+
+impl Service for ProxyConnector {
+    type Request = Uri;
+    type Response = TcpStream;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = TcpStream, Error = io::Error>>;
+
+    fn call(&self, uri: Uri) -> Self::Future {
+        let proxy = match &self.proxy {
+            Some(proxy) => proxy,
+            None => return Box::new(self.http.call(uri)),
+        };
+
+        let connecting = self.http.call(proxy.uri.clone());
+        if uri.scheme() != Some("https") {
+            return Box::new(connecting);
+        }
+
+        let mut request = format!(
+            "CONNECT {0}:{1} HTTP/1.1\r\nHost: {0}:{1}\r\n",
+            uri.host().unwrap_or_default(),
+            uri.port().unwrap_or(443)
+        );
+        if let Some(authorization) = &proxy.authorization {
+            request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+        }
+        request.push_str("\r\n");
+
+        Box::new(
+            connecting
+                .and_then(move |stream| tokio_io::io::write_all(stream, request.into_bytes()))
+                .and_then(|(stream, _)| read_connect_response(stream)),
+        )
+    }
+}
+
+/// Read the proxy's response to a CONNECT request, returning the stream if the tunnel was opened
+fn read_connect_response(stream: TcpStream) -> impl Future<Item = TcpStream, Error = io::Error> {
+    future::loop_fn((stream, Vec::new()), |(stream, mut response)| {
+        tokio_io::io::read_exact(stream, [0u8; 1]).and_then(move |(stream, byte)| {
+            response.push(byte[0]);
+            if response.ends_with(b"\r\n\r\n") {
+                Ok(Loop::Break((stream, response)))
+            } else if response.len() > MAX_CONNECT_RESPONSE_LENGTH {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Proxy response to CONNECT request is too long",
+                ))
+            } else {
+                Ok(Loop::Continue((stream, response)))
+            }
+        })
+    })
+    .and_then(|(stream, response)| {
+        let response = String::from_utf8_lossy(&response);
+        let status_line = response.lines().next().unwrap_or("");
+        if status_line.split_whitespace().nth(1) == Some("200") {
+            Ok(stream)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("Proxy refused to open tunnel: {}", status_line),
+            ))
+        }
+    })
+}
+
+/// The maximum length of a proxy's response to a CONNECT request
+const MAX_CONNECT_RESPONSE_LENGTH: usize = 8192;
+
+/// Whether the host is listed in the given NO_PROXY value
+///
+/// Each comma-separated entry matches the host itself and its subdomains; `*` matches any host.
+fn no_proxy_matches(no_proxy: &str, host: &str) -> bool {
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().trim_start_matches('.'))
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            entry == "*"
+                || host.eq_ignore_ascii_case(entry)
+                || host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", entry.to_ascii_lowercase()))
+        })
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| env::var(name).ok())
+}
+
+fn parse_url(url: &str) -> Result<hyper::Uri, CliError> {
+    let hyper_uri = match url.parse::<hyper::Uri>() {
+        Ok(uri) => uri,
+        Err(e) => return Err(CliError::UserError(format!("Invalid URL: {}: {}", e, url))),
+    };
+
+    match hyper_uri.scheme() {
+        Some(scheme) => {
+            if scheme != "http" && scheme != "https" {
+                return Err(CliError::UserError(format!(
+                    "Unsupported scheme ({}) in URL: {}",
+                    scheme, url
+                )));
+            }
+        }
+        None => {
+            return Err(CliError::UserError(format!("No scheme in URL: {}", url)));
+        }
+    }
+
+    Ok(hyper_uri)
+}
@@ -15,59 +15,530 @@
 //! Contains functions which assist with signing key management
 
 use std::env;
-use std::fs::File;
+use std::fs::{File, OpenOptions, Permissions};
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 
 // use sawtooth_sdk::signing::{
 //     create_context, secp256k1::Secp256k1PrivateKey,
 // };
+use crypto::aead::{AeadDecryptor, AeadEncryptor};
+use crypto::aes::KeySize;
+use crypto::aes_gcm::AesGcm;
+use crypto::scrypt::{scrypt, ScryptParams};
 use cylinder::{secp256k1::Secp256k1Context, Context, PrivateKey, Signer};
+use rand::RngCore;
 use users::get_current_username;
 
 use crate::error::CliError;
 
+/// The environment variable used to override the default key directory
+const KEY_DIR_ENV_VAR: &str = "SABRE_KEY_DIR";
+
+/// The environment variable used to supply the passphrase for encrypted key files
+const PASSPHRASE_ENV_VAR: &str = "SABRE_KEY_PASSPHRASE";
+
+/// Identifies the first line of an encrypted key file
+const ENCRYPTED_KEY_PREFIX: &str = "scrypt-aes256gcm";
+
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
 /// Return a `TransactSigner`, loading the signing key from the user's environment.
-pub fn new_signer(key_name: Option<&str>) -> Result<Box<dyn Signer>, CliError> {
+///
+/// If the `pkcs11` feature is enabled and the key name has the form `pkcs11:<key label>`, the
+/// returned signer uses the matching key pair on a PKCS#11 token. Likewise, if the `vault` feature
+/// is enabled and the key name has the form `vault:<key name>`, the returned signer signs with
+/// the named key of a Vault transit secrets engine.
+pub fn new_signer(
+    key_name: Option<&str>,
+    key_dir: Option<&str>,
+) -> Result<Box<dyn Signer>, CliError> {
+    #[cfg(feature = "pkcs11")]
+    {
+        if let Some(key) = key_name.filter(|key| key.starts_with(crate::pkcs11::PKCS11_KEY_PREFIX))
+        {
+            return crate::pkcs11::new_pkcs11_signer(key);
+        }
+    }
+
+    #[cfg(feature = "vault")]
+    {
+        if let Some(key) = key_name.filter(|key| key.starts_with(crate::vault::VAULT_KEY_PREFIX)) {
+            return crate::vault::new_vault_signer(key);
+        }
+    }
+
     let context = Secp256k1Context::new();
-    let private_key = load_signing_key(key_name)?;
+    let private_key = load_signing_key(key_name, key_dir)?;
     Ok(context.new_signer(private_key))
 }
 
+/// Generate a new secp256k1 key pair and write it to disk
+///
+/// The key files are named `<key_name>.priv` and `<key_name>.pub`. If `key_name` is None, the
+/// current user's name is used. The keys are written to the directory determined by
+/// `get_key_dir`, which is created if it does not exist.
+///
+/// Existing key files are only overwritten if `force` is true.
+pub fn generate_keys(
+    key_name: Option<&str>,
+    key_dir: Option<&str>,
+    force: bool,
+) -> Result<(), CliError> {
+    let key_name: String = key_name
+        .map(String::from)
+        .ok_or_else(|| env::var("USER"))
+        .or_else(|_| get_current_username().ok_or(0))
+        .map_err(|_| {
+            CliError::UserError(String::from(
+                "Could not generate keys: unable to determine username",
+            ))
+        })?;
+
+    let key_dir = get_key_dir(key_dir)?;
+
+    if !key_dir.exists() {
+        std::fs::create_dir_all(&key_dir).map_err(|err| {
+            CliError::UserError(format!(
+                "Could not create key directory {}: {}",
+                key_dir.display(),
+                err
+            ))
+        })?;
+    }
+
+    let private_key_path = key_dir.join(format!("{}.priv", key_name));
+    let public_key_path = key_dir.join(format!("{}.pub", key_name));
+
+    if !force {
+        for path in [&private_key_path, &public_key_path].iter() {
+            if path.exists() {
+                return Err(CliError::UserError(format!(
+                    "File already exists: {}; use --force to overwrite",
+                    path.display()
+                )));
+            }
+        }
+    }
+
+    let context = Secp256k1Context::new();
+    let private_key = context.new_random_private_key();
+    let public_key = context
+        .get_public_key(&private_key)
+        .map_err(|err| CliError::SigningError(err.to_string()))?;
+
+    write_key_file(&private_key_path, &private_key.as_hex(), 0o600)?;
+    write_key_file(&public_key_path, &public_key.as_hex(), 0o644)?;
+
+    Ok(())
+}
+
+fn write_key_file(path: &Path, key_hex: &str, mode: u32) -> Result<(), CliError> {
+    println!("writing file: {}", path.display());
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode)
+        .open(path)
+        .map_err(|err| {
+            CliError::UserError(format!(
+                "Could not write key file {}: {}",
+                path.display(),
+                err
+            ))
+        })?;
+
+    writeln!(file, "{}", key_hex)?;
+
+    // The mode passed to open only applies to newly created files, so set it explicitly in case
+    // an existing key file was overwritten
+    std::fs::set_permissions(path, Permissions::from_mode(mode))?;
+
+    Ok(())
+}
+
 /// Return a signing key loaded from the user's environment
 ///
-/// This method attempts to load the user's key from a file.
 /// The input parameter ```key_param (K)``` works as follows.
 /// The parameter `key_param` is optional
 ///
 /// If the parameter is Some:
-///   1) The short-name translates to the ```keyfile``` at
-///      ${HOME}/.sawtooth/(K).priv
-///   2) If a ```keyfile``` as in point (1) is not found then a file
-///      ${HOME}/.sawtooth/{K} is searched for.
-///   3) If a ```keyfile``` as in point (2) also fails then a path
+///   1) If {K} is a 64 character hex string and no file named {K}
+///      exists, it is used directly as the private key.
+///   2) If {K} contains a path separator, it is treated as a path
+///      to a ```keyfile```, either absolute or relative to the
+///      current directory.
+///   3) Otherwise the short-name translates to the ```keyfile``` at
+///      {KEY_DIR}/(K).priv
+///   4) If a ```keyfile``` as in point (3) is not found then a file
+///      {KEY_DIR}/{K} is searched for.
+///   5) If a ```keyfile``` as in point (4) also fails then a path
 ///      {K} is searched for.
 ///
 /// If the parameter is None:
 /// The USER environment variable is used as a key file identifier.
 /// The filename is constructed by appending ".priv" to the
 /// constructed key's name from the USER environment variable.
-/// The directory containing the keys is determined using the HOME
-/// environment variable:
 ///
-///   $HOME/.sawtooth/keys/
+/// The key directory {KEY_DIR} is determined by `get_key_dir`.
 ///
 /// # Arguments
 ///
 /// * `key_param` - The signing key parameter to be loaded
+/// * `key_dir` - The directory containing the signing keys, if given on the command line
 ///
 /// # Errors
 ///
 /// If a signing error occurs, a CliError::SigningError is returned.
 ///
 /// If a HOME or USER environment variable is required but cannot be
-/// retrieved from the environment, a CliError::VarError is returned.
-fn load_signing_key(key_param: Option<&str>) -> Result<PrivateKey, CliError> {
+/// retrieved from the environment, a CliError::UserError is returned.
+fn load_signing_key(
+    key_param: Option<&str>,
+    key_dir: Option<&str>,
+) -> Result<PrivateKey, CliError> {
+    if let Some(key) = key_param {
+        if is_hex_private_key(key) && !Path::new(key).exists() {
+            return PrivateKey::new_from_hex(key).map_err(|err| {
+                CliError::SigningError(format!("Unable to parse private key: {}", err))
+            });
+        }
+    }
+
+    let private_key_filename = match key_param {
+        Some(key) if key.contains(std::path::MAIN_SEPARATOR) => PathBuf::from(key),
+        _ => find_key_file(key_param, key_dir)?,
+    };
+
+    if !private_key_filename.as_path().exists() {
+        return Err(CliError::UserError(format!(
+            "No such key file: {}",
+            private_key_filename.display()
+        )));
+    }
+
+    let mut f = File::open(&private_key_filename)?;
+
+    let mut contents = String::new();
+    f.read_to_string(&mut contents)?;
+
+    let key_str = match contents.lines().next() {
+        Some(k) => k.trim(),
+        None => {
+            return Err(CliError::UserError(format!(
+                "Empty key file: {}",
+                private_key_filename.display()
+            )));
+        }
+    };
+
+    let decrypted;
+    let key_str = if is_encrypted_key(key_str) {
+        let passphrase = get_passphrase(&format!(
+            "Passphrase for {}: ",
+            private_key_filename.display()
+        ))?;
+        decrypted = decrypt_private_key(key_str, &passphrase, &private_key_filename)?;
+        decrypted.as_str()
+    } else {
+        key_str
+    };
+
+    PrivateKey::new_from_hex(key_str).map_err(|err| {
+        CliError::SigningError(format!(
+            "Unable to parse private key file {}: {} ",
+            private_key_filename.display(),
+            err
+        ))
+    })
+}
+
+/// Encrypt an existing plaintext key file with a passphrase
+///
+/// The key is resolved the same way as the `--key` argument. The encrypted key is written to
+/// `output` if provided, otherwise the key file is replaced in place. The passphrase is read from
+/// the SABRE_KEY_PASSPHRASE environment variable, or prompted for if it is not set.
+pub fn encrypt_key_file(
+    key_param: Option<&str>,
+    key_dir: Option<&str>,
+    output: Option<&str>,
+) -> Result<(), CliError> {
+    let key_filename = match key_param {
+        Some(key) if key.contains(std::path::MAIN_SEPARATOR) => PathBuf::from(key),
+        _ => find_key_file(key_param, key_dir)?,
+    };
+
+    if !key_filename.as_path().exists() {
+        return Err(CliError::UserError(format!(
+            "No such key file: {}",
+            key_filename.display()
+        )));
+    }
+
+    let mut contents = String::new();
+    File::open(&key_filename)?.read_to_string(&mut contents)?;
+
+    let key_str = contents.lines().next().map(str::trim).ok_or_else(|| {
+        CliError::UserError(format!("Empty key file: {}", key_filename.display()))
+    })?;
+
+    if is_encrypted_key(key_str) {
+        return Err(CliError::UserError(format!(
+            "Key file is already encrypted: {}",
+            key_filename.display()
+        )));
+    }
+
+    // Make sure the file actually contains a key before encrypting it
+    PrivateKey::new_from_hex(key_str).map_err(|err| {
+        CliError::SigningError(format!(
+            "Unable to parse private key file {}: {} ",
+            key_filename.display(),
+            err
+        ))
+    })?;
+
+    let passphrase = match env::var(PASSPHRASE_ENV_VAR) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let passphrase = prompt_passphrase("Enter passphrase: ")?;
+            if passphrase != prompt_passphrase("Confirm passphrase: ")? {
+                return Err(CliError::UserError("Passphrases do not match".into()));
+            }
+            passphrase
+        }
+    };
+
+    if passphrase.is_empty() {
+        return Err(CliError::UserError("Passphrase must not be empty".into()));
+    }
+
+    let encrypted = encrypt_private_key(key_str, &passphrase)?;
+
+    let output_path = output.map(PathBuf::from).unwrap_or(key_filename);
+    write_key_file(&output_path, &encrypted, 0o600)
+}
+
+/// Whether a key in the key directory has a private key file, and whether it is encrypted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrivateKeyStatus {
+    Plaintext,
+    Encrypted,
+    Missing,
+}
+
+impl PrivateKeyStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PrivateKeyStatus::Plaintext => "plaintext",
+            PrivateKeyStatus::Encrypted => "encrypted",
+            PrivateKeyStatus::Missing => "none",
+        }
+    }
+}
+
+/// A key found in the key directory
+pub struct KeyInfo {
+    pub name: String,
+    /// The hex-encoded public key, if it could be determined without a passphrase
+    pub public_key: Option<String>,
+    pub private_key: PrivateKeyStatus,
+}
+
+/// List the keys in the key directory, sorted by name
+///
+/// A key is any `<name>.priv` or `<name>.pub` file. The public key is read from the `.pub` file,
+/// or derived from the private key if there is no `.pub` file and the private key is not
+/// encrypted.
+pub fn list_keys(key_dir: Option<&str>) -> Result<Vec<KeyInfo>, CliError> {
+    let key_dir = get_key_dir(key_dir)?;
+
+    let entries = std::fs::read_dir(&key_dir).map_err(|err| {
+        CliError::UserError(format!(
+            "Could not read key directory {}: {}",
+            key_dir.display(),
+            err
+        ))
+    })?;
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_key_file = matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("priv") | Some("pub")
+        );
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            if is_key_file && path.is_file() && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+
+    let context = Secp256k1Context::new();
+    Ok(names
+        .into_iter()
+        .map(|name| {
+            let private_key = read_key_line(&key_dir.join(format!("{}.priv", name)));
+            let private_key_status = match &private_key {
+                Some(key) if is_encrypted_key(key) => PrivateKeyStatus::Encrypted,
+                Some(_) => PrivateKeyStatus::Plaintext,
+                None => PrivateKeyStatus::Missing,
+            };
+
+            let public_key = read_key_line(&key_dir.join(format!("{}.pub", name))).or_else(|| {
+                private_key
+                    .filter(|key| !is_encrypted_key(key))
+                    .and_then(|key| PrivateKey::new_from_hex(&key).ok())
+                    .and_then(|key| context.get_public_key(&key).ok())
+                    .map(|key| key.as_hex())
+            });
+
+            KeyInfo {
+                name,
+                public_key,
+                private_key: private_key_status,
+            }
+        })
+        .collect())
+}
+
+/// Import a hex-encoded private key into the key directory as `<key_name>.priv`, along with its
+/// public key in `<key_name>.pub`
+///
+/// The key is read from the first line of `source`, or from standard input if `source` is None or
+/// `-`. An encrypted key is imported as is, after its passphrase is checked. Existing key files
+/// are only overwritten if `force` is true.
+pub fn import_key(
+    key_name: &str,
+    key_dir: Option<&str>,
+    source: Option<&str>,
+    force: bool,
+) -> Result<(), CliError> {
+    let mut contents = String::new();
+    match source {
+        Some(path) if path != "-" => {
+            File::open(path)
+                .and_then(|mut file| file.read_to_string(&mut contents))
+                .map_err(|err| {
+                    CliError::UserError(format!("Could not read key file {}: {}", path, err))
+                })?;
+        }
+        _ => {
+            std::io::stdin().read_to_string(&mut contents)?;
+        }
+    }
+
+    let key_str = contents
+        .lines()
+        .next()
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| CliError::UserError("No key to import".into()))?;
+
+    let decrypted;
+    let key_hex = if is_encrypted_key(key_str) {
+        let passphrase = get_passphrase("Passphrase for the imported key: ")?;
+        decrypted = decrypt_private_key(key_str, &passphrase, Path::new(source.unwrap_or("-")))?;
+        decrypted.as_str()
+    } else {
+        key_str
+    };
+
+    let private_key = PrivateKey::new_from_hex(key_hex)
+        .map_err(|err| CliError::SigningError(format!("Unable to parse private key: {}", err)))?;
+    let public_key = Secp256k1Context::new()
+        .get_public_key(&private_key)
+        .map_err(|err| CliError::SigningError(err.to_string()))?;
+
+    let key_dir = get_key_dir(key_dir)?;
+    if !key_dir.exists() {
+        std::fs::create_dir_all(&key_dir).map_err(|err| {
+            CliError::UserError(format!(
+                "Could not create key directory {}: {}",
+                key_dir.display(),
+                err
+            ))
+        })?;
+    }
+
+    let private_key_path = key_dir.join(format!("{}.priv", key_name));
+    let public_key_path = key_dir.join(format!("{}.pub", key_name));
+
+    if !force {
+        for path in [&private_key_path, &public_key_path].iter() {
+            if path.exists() {
+                return Err(CliError::UserError(format!(
+                    "File already exists: {}; use --force to overwrite",
+                    path.display()
+                )));
+            }
+        }
+    }
+
+    write_key_file(&private_key_path, key_str, 0o600)?;
+    write_key_file(&public_key_path, &public_key.as_hex(), 0o644)?;
+
+    Ok(())
+}
+
+/// Return the hex-encoded public key of the signing key, resolved the same way as `--key`
+///
+/// If the key is a `.priv` file with a matching `.pub` file, the public key is read from that
+/// file, so encrypted keys can be exported without their passphrase.
+pub fn export_public_key(
+    key_param: Option<&str>,
+    key_dir: Option<&str>,
+) -> Result<String, CliError> {
+    let is_file_key = match key_param {
+        #[cfg(feature = "pkcs11")]
+        Some(key) if key.starts_with(crate::pkcs11::PKCS11_KEY_PREFIX) => false,
+        #[cfg(feature = "vault")]
+        Some(key) if key.starts_with(crate::vault::VAULT_KEY_PREFIX) => false,
+        Some(key) => !is_hex_private_key(key) || Path::new(key).exists(),
+        None => true,
+    };
+
+    if is_file_key {
+        let key_filename = match key_param {
+            Some(key) if key.contains(std::path::MAIN_SEPARATOR) => PathBuf::from(key),
+            _ => find_key_file(key_param, key_dir)?,
+        };
+        if key_filename.extension().and_then(|ext| ext.to_str()) == Some("priv") {
+            if let Some(public_key) = read_key_line(&key_filename.with_extension("pub")) {
+                return Ok(public_key);
+            }
+        }
+    }
+
+    let public_key = new_signer(key_param, key_dir)?
+        .public_key()
+        .map_err(|err| CliError::SigningError(err.to_string()))?;
+
+    Ok(public_key.as_hex())
+}
+
+/// Return the trimmed first line of a key file, or `None` if it cannot be read or is empty
+fn read_key_line(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()?
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+}
+
+/// Determine the key file for the given key name, searching the key directory
+fn find_key_file(key_param: Option<&str>, key_dir: Option<&str>) -> Result<PathBuf, CliError> {
     let derived_keyfile: String = key_param
         .map(String::from)
         .ok_or_else(|| env::var("USER"))
@@ -78,70 +549,185 @@ fn load_signing_key(key_param: Option<&str>) -> Result<PrivateKey, CliError> {
             ))
         })?;
 
-    // For the case Some(scenario 3)
+    let key_dir = get_key_dir(key_dir)?;
+
+    // For the case Some(scenario 5)
     let mut private_key_filename: PathBuf = PathBuf::from(&derived_keyfile);
 
-    // For the case Some(scenario 2)
-    let keyfile_identifier = dirs::home_dir()
-        .ok_or_else(|| {
-            CliError::UserError(String::from(
-                "Could not load signing key: unable to determine home directory",
-            ))
-        })
-        .map(|mut p| {
-            p.push(".sawtooth");
-            p.push("keys");
-            p.push(&derived_keyfile);
-            p
-        })?;
+    // For the case Some(scenario 4)
+    let keyfile_identifier = key_dir.join(&derived_keyfile);
     if keyfile_identifier.as_path().exists() {
         private_key_filename = keyfile_identifier;
     }
 
-    // For the case Some(scenario 1) and None
-    let key_identifier = dirs::home_dir()
+    // For the case Some(scenario 3) and None
+    let key_identifier = key_dir.join(format!("{}.priv", &derived_keyfile));
+    if key_identifier.as_path().exists() {
+        private_key_filename = key_identifier;
+    }
+
+    Ok(private_key_filename)
+}
+
+/// Return the directory containing the user's signing keys
+///
+/// The directory is determined by the first of the following that is set:
+///   1) The `key_dir` argument, given by the --key-dir command line flag
+///   2) The SABRE_KEY_DIR environment variable
+///   3) $HOME/.sawtooth/keys
+fn get_key_dir(key_dir: Option<&str>) -> Result<PathBuf, CliError> {
+    if let Some(dir) = key_dir {
+        return Ok(PathBuf::from(dir));
+    }
+
+    if let Ok(dir) = env::var(KEY_DIR_ENV_VAR) {
+        return Ok(PathBuf::from(dir));
+    }
+
+    dirs::home_dir()
         .ok_or_else(|| {
-            CliError::UserError(String::from(
-                "Could not load signing key: unable to determine home directory",
+            CliError::UserError(format!(
+                "Could not determine key directory: unable to determine home directory and {} \
+                 is not set",
+                KEY_DIR_ENV_VAR
             ))
         })
         .map(|mut p| {
             p.push(".sawtooth");
             p.push("keys");
-            p.push(format!("{}.priv", &derived_keyfile));
             p
-        })?;
-    if key_identifier.as_path().exists() {
-        private_key_filename = key_identifier;
+        })
+}
+
+/// Returns true if the given key file line holds an encrypted private key
+fn is_encrypted_key(key_str: &str) -> bool {
+    key_str.starts_with(&format!("{}:", ENCRYPTED_KEY_PREFIX))
+}
+
+/// Encrypt a hex-encoded private key with a passphrase
+///
+/// The encryption key is derived from the passphrase with scrypt and the private key is encrypted
+/// using AES-256-GCM. The result is a single line of the form
+/// `scrypt-aes256gcm:<log_n>:<r>:<p>:<salt>:<nonce>:<ciphertext>:<tag>`, where the binary values
+/// are base64 encoded.
+fn encrypt_private_key(key_hex: &str, passphrase: &str) -> Result<String, CliError> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let encryption_key = derive_encryption_key(
+        passphrase,
+        &salt,
+        &ScryptParams::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P),
+    );
+
+    let mut ciphertext = vec![0u8; key_hex.len()];
+    let mut tag = [0u8; TAG_LEN];
+    AesGcm::new(KeySize::KeySize256, &encryption_key, &nonce, &[]).encrypt(
+        key_hex.as_bytes(),
+        &mut ciphertext,
+        &mut tag,
+    );
+
+    Ok(format!(
+        "{}:{}:{}:{}:{}:{}:{}:{}",
+        ENCRYPTED_KEY_PREFIX,
+        SCRYPT_LOG_N,
+        SCRYPT_R,
+        SCRYPT_P,
+        base64::encode(salt),
+        base64::encode(nonce),
+        base64::encode(&ciphertext),
+        base64::encode(tag)
+    ))
+}
+
+/// Decrypt a private key that was encrypted by `encrypt_private_key`, returning the hex-encoded
+/// private key
+fn decrypt_private_key(encrypted: &str, passphrase: &str, path: &Path) -> Result<String, CliError> {
+    let malformed =
+        || CliError::UserError(format!("Malformed encrypted key file: {}", path.display()));
+
+    let parts = encrypted.split(':').collect::<Vec<_>>();
+    if parts.len() != 8 {
+        return Err(malformed());
     }
 
-    if !private_key_filename.as_path().exists() {
-        return Err(CliError::UserError(format!(
-            "No such key file: {}",
-            private_key_filename.display()
+    let log_n = parts[1].parse::<u8>().map_err(|_| malformed())?;
+    let r = parts[2].parse::<u32>().map_err(|_| malformed())?;
+    let p = parts[3].parse::<u32>().map_err(|_| malformed())?;
+    let salt = base64::decode(parts[4]).map_err(|_| malformed())?;
+    let nonce = base64::decode(parts[5]).map_err(|_| malformed())?;
+    let ciphertext = base64::decode(parts[6]).map_err(|_| malformed())?;
+    let tag = base64::decode(parts[7]).map_err(|_| malformed())?;
+
+    if nonce.len() != NONCE_LEN || tag.len() != TAG_LEN {
+        return Err(malformed());
+    }
+
+    let encryption_key = derive_encryption_key(passphrase, &salt, &ScryptParams::new(log_n, r, p));
+
+    let mut plaintext = vec![0u8; ciphertext.len()];
+    if !AesGcm::new(KeySize::KeySize256, &encryption_key, &nonce, &[]).decrypt(
+        &ciphertext,
+        &mut plaintext,
+        &tag,
+    ) {
+        return Err(CliError::SigningError(format!(
+            "Unable to decrypt key file {}: incorrect passphrase",
+            path.display()
         )));
     }
 
-    let mut f = File::open(&private_key_filename)?;
+    String::from_utf8(plaintext).map_err(|_| malformed())
+}
 
-    let mut contents = String::new();
-    f.read_to_string(&mut contents)?;
+fn derive_encryption_key(passphrase: &str, salt: &[u8], params: &ScryptParams) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    scrypt(passphrase.as_bytes(), salt, params, &mut key);
+    key
+}
 
-    let key_str = match contents.lines().next() {
-        Some(k) => k,
-        None => {
-            return Err(CliError::UserError(format!(
-                "Empty key file: {}",
-                private_key_filename.display()
-            )));
-        }
-    };
+/// Return the key passphrase from the SABRE_KEY_PASSPHRASE environment variable, prompting for
+/// it if the variable is not set
+fn get_passphrase(prompt: &str) -> Result<String, CliError> {
+    match env::var(PASSPHRASE_ENV_VAR) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => prompt_passphrase(prompt),
+    }
+}
 
-    PrivateKey::new_from_hex(&key_str).map_err(|err| {
-        CliError::SigningError(format!(
-            "Unable to parse private key file {}: {} ",
-            private_key_filename.display(),
-            err
-        ))
-    })
+/// Prompt for a passphrase on stdin, disabling terminal echo while it is typed
+pub fn prompt_passphrase(prompt: &str) -> Result<String, CliError> {
+    eprint!("{}", prompt);
+    std::io::stderr().flush()?;
+
+    let fd = libc::STDIN_FILENO;
+    // Safe because termios is a plain C struct and is only used if tcgetattr succeeds
+    let mut original: libc::termios = unsafe { std::mem::zeroed() };
+    let is_tty = unsafe { libc::tcgetattr(fd, &mut original) } == 0;
+
+    if is_tty {
+        let mut no_echo = original;
+        no_echo.c_lflag &= !libc::ECHO;
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &no_echo) };
+    }
+
+    let mut passphrase = String::new();
+    let result = std::io::stdin().read_line(&mut passphrase);
+
+    if is_tty {
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+        eprintln!();
+    }
+
+    result?;
+
+    Ok(passphrase.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+/// Returns true if the given string looks like a hex-encoded secp256k1 private key
+fn is_hex_private_key(key: &str) -> bool {
+    key.len() == 64 && key.chars().all(|c| c.is_ascii_hexdigit())
 }
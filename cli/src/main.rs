@@ -15,658 +15,5838 @@
 #[macro_use]
 extern crate clap;
 #[macro_use]
+extern crate log;
+#[macro_use]
 extern crate serde_derive;
 
+mod archive;
+mod batch;
+mod compile;
+mod config;
+mod deploy;
+#[cfg(feature = "dry-run")]
+mod dry_run;
 mod error;
+mod events;
+mod http;
 mod key;
+mod output;
+mod payload;
+mod permissions;
+#[cfg(feature = "pkcs11")]
+mod pkcs11;
+mod profile;
+mod receipt;
+mod scaffold;
+mod schema;
+mod settings;
 mod state;
 mod submit;
 mod upload;
+#[cfg(feature = "zmq")]
+mod validator;
+#[cfg(feature = "vault")]
+mod vault;
+mod wasm;
+mod websocket;
 
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::{prelude::*, BufReader};
 use std::path::Path;
-use std::time::Instant;
+use std::time::Duration;
 
-use clap::{AppSettings, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, ArgGroup, Shell, SubCommand};
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
+use log::LevelFilter;
 use sabre_sdk::protocol::payload::{
-    CreateContractRegistryActionBuilder, CreateNamespaceRegistryActionBuilder,
+    Action, CreateContractRegistryActionBuilder, CreateNamespaceRegistryActionBuilder,
     CreateNamespaceRegistryPermissionActionBuilder, CreateSmartPermissionActionBuilder,
-    DeleteContractRegistryActionBuilder, DeleteNamespaceRegistryActionBuilder,
-    DeleteNamespaceRegistryPermissionActionBuilder, DeleteSmartPermissionActionBuilder,
-    ExecuteContractActionBuilder, UpdateContractRegistryOwnersActionBuilder,
+    DeleteContractActionBuilder, DeleteContractRegistryActionBuilder,
+    DeleteNamespaceRegistryActionBuilder, DeleteNamespaceRegistryPermissionActionBuilder,
+    DeleteSmartPermissionActionBuilder, ExecuteContractActionBuilder, PauseContractActionBuilder,
+    ResumeContractActionBuilder, SabrePayload, UpdateContractRegistryOwnersActionBuilder,
     UpdateNamespaceRegistryOwnersActionBuilder, UpdateSmartPermissionActionBuilder,
 };
 use sabre_sdk::protocol::{
-    compute_contract_address,
-    state::{ContractList, ContractRegistryList},
-    CONTRACT_REGISTRY_ADDRESS_PREFIX,
+    compute_contract_address, compute_contract_registry_address,
+    compute_namespace_registry_address, compute_smart_permission_address,
+    state::{
+        Contract, ContractChunk, ContractChunkList, ContractList, ContractRegistry,
+        ContractRegistryList, NamespaceRegistry, NamespaceRegistryList, SmartPermission,
+        SmartPermissionList,
+    },
+    ADMINISTRATORS_SETTING_KEY, CONTRACT_ADDRESS_PREFIX, CONTRACT_CHUNK_ADDRESS_PREFIX,
+    CONTRACT_REGISTRY_ADDRESS_PREFIX, NAMESPACE_REGISTRY_ADDRESS_PREFIX,
+    SMART_PERMISSION_ADDRESS_PREFIX,
 };
 use sabre_sdk::protos::FromBytes;
 
+use batch::to_hex;
+use config::CliConfig;
+use cylinder::Signer;
 use error::CliError;
+use http::{HttpClient, RetryPolicy};
 use key::new_signer;
-use submit::submit_batches;
+use output::{print_document, print_stream_document, OutputFormat};
+use payload::PayloadFormat;
+use profile::Profile;
+use schema::StateSchema;
+use submit::{submit_batches, BatchSubmission, StatusResponse, SubmitTarget, Throttle};
+use transact::protocol::{
+    batch::Batch,
+    transaction::{Transaction, TransactionBuilder},
+};
 
 const APP_NAME: &str = env!("CARGO_PKG_NAME");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+const BIN_NAME: &str = "sabre";
 
 const DEFAULT_REST_API_ENDPOINT: &str = "http://localhost:8008/";
 
-fn run() -> Result<(), CliError> {
-    // Below, unwrap() is used on required arguments, since they will always
-    // contain a value (and lack of value is should cause a panic). unwrap()
-    // is also used on get_matches() because SubcommandRequiredElseHelp will
-    // ensure a value.
+/// How long to wait for each chunk of a chunked upload to be committed, if --wait is not given
+const CHUNK_WAIT: u64 = 300;
+
+/// The size in bytes above which `sabre upload` warns that a contract is large
+const DEFAULT_MAX_CONTRACT_SIZE: usize = 1_048_576;
 
+/// Build the CLI specification
+///
+/// The same specification is used to parse arguments and to generate shell completions.
+fn create_app<'a, 'b>() -> App<'a, 'b> {
     let app = clap_app!(myapp =>
         (name: APP_NAME)
         (version: VERSION)
         (about: "Sawtooth Sabre CLI")
         (@setting SubcommandRequiredElseHelp)
+        (@arg config: --config +takes_value +global "Path to the CLI configuration file")
+        (@arg format: --format +takes_value +global possible_value[human csv json yaml] "Format to display command output in")
+        (@arg auth: --auth +takes_value +global "Credentials to authenticate with the REST API, as bearer:<token> or basic:<user:password>")
+        (@arg ca_file: --("ca-file") +takes_value +global "Path to a PEM file with the CA certificates used to verify the REST API")
+        (@arg client_cert: --("client-cert") +takes_value +global "Path to a PEM client certificate to authenticate to the REST API with")
+        (@arg client_key: --("client-key") +takes_value +global "Path to the PEM private key of the client certificate")
+        (@arg proxy: --proxy +takes_value +global "URL of an HTTP proxy to send REST API requests through; overrides HTTPS_PROXY and HTTP_PROXY")
+        (@arg retries: --retries +takes_value +global "Number of times to retry REST API requests which fail with a 429, 502, 503 or 504 status")
+        (@arg retry_backoff: --("retry-backoff") +takes_value +global "Delay in milliseconds before the first retry; it doubles with each retry")
+        (@arg retry_jitter: --("retry-jitter") +takes_value +global "Maximum random delay in milliseconds added to each retry")
+        (@arg batcher_key: --("batcher-key") +takes_value +global "Key to sign batches with, if not the signing key; a name, path to a key file, or hex-encoded private key")
+        (@arg subscribe: --subscribe +global "Wait for batches by subscribing to block events instead of polling")
+        (@arg profile: --profile +takes_value +global "Name of the profile in the configuration file to take connection details from; defaults to $SABRE_PROFILE")
+        (@arg timings: --timings +global "Report how long each phase of a submission takes, with the batch and transaction IDs, on stderr")
+        (@arg verbose: -v --verbose +global +multiple "Log requests and transactions to stderr; use -vv to include transaction headers and response bodies")
+        (@arg quiet: -q --quiet +global conflicts_with[verbose] "Only print the IDs of submitted batches, and log only errors")
+        (@subcommand submit =>
+            (about: "submit batches from a file created with --output")
+            (@arg filename: "Path to a serialized BatchList; standard input is read if omitted or '-'")
+            (@arg url: --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
+            (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+            (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+            (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+            (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+            (@arg max_in_flight: --("max-in-flight") +takes_value "Submit batches one at a time, with at most this many submitted but not yet committed")
+            (@arg rate: --rate +takes_value "Submit batches one at a time, at most this many per second")
+        )
+        (@subcommand batch =>
+            (about: "submit transactions collected with --batch-file as a single batch")
+            (@setting SubcommandRequiredElseHelp)
+            (@subcommand submit =>
+                (about: "sign the transactions in a batch file as one batch and submit it")
+                (@arg batch_file: +required "Path to the batch file")
+                (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+                (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
+                (@arg url: --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
+                (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+                (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+                (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+                (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+                (@arg output: -o --output +takes_value "Write the signed batch to a file instead of submitting it")
+            )
+            (@subcommand inspect =>
+                (about: "decode the batches or transactions in a file and check their signatures")
+                (@arg filename: "Path to a serialized BatchList or a batch file; standard input is read if omitted or '-'")
+                (@arg raw: --raw "Show hex dumps of payloads instead of decoding Sabre actions")
+            )
+        )
         (@subcommand upload =>
             (about: "upload a Sabre contract")
-            (@arg filename: -f --filename +required +takes_value "Path to Sabre contract definition (*.yaml)")
-            (@arg key: -k --key +takes_value "Signing key name")
-            (@arg url: --url +takes_value "URL to the Sawtooth REST API")
+            (@arg filename: -f --filename +required +takes_value "Path to Sabre contract definition (*.yaml) or smart contract archive (*.scar)")
+            (@arg sha512: --sha512 +takes_value "Expected SHA-512 hash of the contract, as displayed by sabre pack")
+            (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+            (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
+            (@arg url: --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
             (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+            (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+            (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+            (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+            (@arg output: -o --output +takes_value "Write the signed batches to a file instead of submitting them")
+            (@arg batch_file: --("batch-file") +takes_value conflicts_with[output] "Add the transaction to a batch file instead of submitting it")
+            (@arg depends_on: --("depends-on") +takes_value +multiple number_of_values(1) "ID of a transaction that must be committed before this one; may be repeated")
+            (@arg wasm: -w --wasm +takes_value "Path to compiled smart contract (*.wasm)")
+            (@arg chunk_size: --("chunk-size") +takes_value conflicts_with[output batch_file] "Upload contracts larger than this many bytes in chunks of this size")
+            (@arg optimize: --optimize conflicts_with[sha512] "Optimize the contract with wasm-opt or wasm-strip if installed, or strip its custom sections, before uploading it")
+            (@arg max_size: --("max-size") +takes_value "Warn if the contract is larger than this many bytes; defaults to 1048576")
+            (@arg state_schema: --("state-schema") +takes_value requires[state_message] "Path to a protobuf FileDescriptorSet defining the contract's state, to register with the contract for sabre query")
+            (@arg state_message: --("state-message") +takes_value requires[state_schema] "Fully-qualified name of the message the contract's state entries are encoded as")
+        )
+        (@subcommand deploy =>
+            (about: "create a contract's registry, upload it, create its namespaces, and grant its permissions, in one batch")
+            (@arg filename: -f --filename +required +takes_value "Path to Sabre contract definition (*.yaml) or smart contract archive (*.scar)")
             (@arg wasm: -w --wasm +takes_value "Path to compiled smart contract (*.wasm)")
+            (@arg sha512: --sha512 +takes_value "Expected SHA-512 hash of the contract, as displayed by sabre pack")
+            (@arg namespace: -n --namespace +required +takes_value +multiple number_of_values(1) "A global state address prefix (namespace) to grant the contract permissions on; may be repeated")
+            (@arg owner: -O --owner +takes_value +multiple number_of_values(1) "Owner of the contract registry and namespaces, if they are created, in addition to the signing key; may be repeated")
+            (@arg read: --read "Grant read permission; both read and write are granted if neither --read nor --write is given")
+            (@arg write: --write "Grant write permission; both read and write are granted if neither --read nor --write is given")
+            (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+            (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
+            (@arg url: --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
+            (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+            (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+            (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+            (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+            (@arg output: -o --output +takes_value "Write the signed batch to a file instead of submitting it")
+            (@arg depends_on: --("depends-on") +takes_value +multiple number_of_values(1) "ID of a transaction that must be committed before the deployment; may be repeated")
+            (@arg max_size: --("max-size") +takes_value "Warn if the contract is larger than this many bytes; defaults to 1048576")
         )
         (@subcommand exec =>
             (about: "execute a Sabre contract")
             (@arg contract: -C --contract +required +takes_value "Name:Version of a Sabre contract")
-            (@arg payload: -p --payload +required +takes_value "Path to Sabre contract payload")
-            (@arg key: -k --key +takes_value "Signing key name")
-            (@arg url: --url +takes_value "URL to the Sawtooth REST API")
-            (@arg inputs: --inputs +takes_value +multiple "Input addresses used by the contract")
-            (@arg outputs: --outputs +takes_value +multiple "Output addresses used by the contract")
+            (@arg payload: -p --payload +required +takes_value "Path to Sabre contract payload; standard input is read if '-'")
+            (@arg payload_format: --("payload-format") +takes_value "Encoding of the payload file: raw, hex, json-cbor, or json-proto:<message>")
+            (@arg descriptor_set: --("descriptor-set") +takes_value "Path to a protobuf FileDescriptorSet defining the message of a json-proto payload")
+            (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+            (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
+            (@arg url: --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
+            (@arg inputs: --inputs +takes_value +multiple "Input addresses used by the contract; defaults to the contract's registered inputs")
+            (@arg outputs: --outputs +takes_value +multiple "Output addresses used by the contract; defaults to the contract's registered outputs")
+            (@arg address_hint: --("address-hint") +takes_value +multiple "Address or namespace the payload uses, narrowing the registered inputs and outputs to it")
             (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+            (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+            (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+            (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+            (@arg output: -o --output +takes_value "Write the signed batches to a file instead of submitting them")
+            (@arg batch_file: --("batch-file") +takes_value conflicts_with[output] "Add the transaction to a batch file instead of submitting it")
+            (@arg depends_on: --("depends-on") +takes_value +multiple number_of_values(1) "ID of a transaction that must be committed before this one; may be repeated")
+            (@arg dry_run: --("dry-run") conflicts_with[output batch_file target wait] "Execute the contract locally against the current state and print its changes instead of submitting it")
         )
         (@subcommand ns =>
-            (about: "create, update, or delete a Sabre namespace")
+            (about: "create, update, delete, or inspect a Sabre namespace")
+            (@setting SubcommandsNegateReqs)
             (@group action =>
                 (@arg create: -c --create "Create the namespace")
                 (@arg update: -u --update "Update the namespace")
                 (@arg delete: -d --delete "Delete the namespace")
             )
             (@arg namespace: +required "A global state address prefix (namespace)")
-            (@arg key: -k --key +takes_value "Signing key name")
-            (@arg url: -U --url +takes_value "URL to the Sawtooth REST API")
+            (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+            (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
+            (@arg url: -U --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
             (@arg owner: -O --owner +takes_value +multiple "Owner of this namespace")
             (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+            (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+            (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+            (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+            (@arg output: -o --output +takes_value "Write the signed batches to a file instead of submitting them")
+            (@arg batch_file: --("batch-file") +takes_value conflicts_with[output] "Add the transaction to a batch file instead of submitting it")
+            (@arg depends_on: --("depends-on") +takes_value +multiple number_of_values(1) "ID of a transaction that must be committed before this one; may be repeated")
+            (@arg yes: -y --yes "Delete the namespace without asking for confirmation")
+            (@subcommand list =>
+                (about: "List the registered Sabre namespaces")
+                (@arg prefix: "Only list namespaces starting with this prefix")
+                (@arg url: -U --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
+            )
+            (@subcommand show =>
+                (about: "Show the owners and permissions of a Sabre namespace")
+                (@arg namespace: +required "A global state address prefix (namespace)")
+                (@arg url: -U --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
+            )
+            (subcommand: SubCommand::with_name("update-owners")
+                .about("Add or remove owners of an existing Sabre namespace, keeping its permissions")
+                .args(&[
+                    Arg::with_name("namespace")
+                        .help("A global state address prefix (namespace)")
+                        .required(true),
+                    Arg::with_name("add")
+                        .help("Public key of an owner to add")
+                        .short("a")
+                        .long("add")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                    Arg::with_name("remove")
+                        .help("Public key of an owner to remove")
+                        .short("r")
+                        .long("remove")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                ])
+                .args(&owner_update_args())
+                .group(
+                    ArgGroup::with_name("change")
+                        .args(&["add", "remove"])
+                        .multiple(true)
+                        .required(true),
+                ))
         )
         (@subcommand perm =>
             (about: "set or delete a Sabre namespace permission")
+            (@setting SubcommandsNegateReqs)
             (@arg namespace: +required "A global state address prefix (namespace)")
             (@arg contract: +required "Name of the contract")
-            (@arg key: -k --key +takes_value "Signing key name")
-            (@arg url: -U --url +takes_value "URL to the Sawtooth REST API")
+            (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+            (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
+            (@arg url: -U --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
             (@arg delete: -d --delete "Remove all permissions")
             (@arg read: -r --read conflicts_with[delete] "Set read permission")
             (@arg write: -w --write conflicts_with[delete] "Set write permission")
             (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+            (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+            (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+            (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+            (@arg output: -o --output +takes_value "Write the signed batches to a file instead of submitting them")
+            (@arg batch_file: --("batch-file") +takes_value conflicts_with[output] "Add the transaction to a batch file instead of submitting it")
+            (@arg depends_on: --("depends-on") +takes_value +multiple number_of_values(1) "ID of a transaction that must be committed before this one; may be repeated")
+            (@subcommand list =>
+                (about: "List the namespace permissions in state, one row per namespace and contract")
+                (@arg prefix: "Only list namespaces starting with this prefix")
+                (@arg contract: --contract +takes_value "Only list the permissions of this contract")
+                (@arg url: -U --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
+            )
+            (@subcommand apply =>
+                (about: "Set the namespace permissions listed in a file, submitting only the changes")
+                (@arg filename: -f --filename +required +takes_value "Path to a YAML list of namespace permissions")
+                (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+                (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
+                (@arg url: -U --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
+                (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+                (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+                (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+                (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+                (@arg output: -o --output +takes_value "Write the signed batch to a file instead of submitting it")
+            )
         )
         (@subcommand cr =>
-            (about: "create, update, or delete a Sabre contract registry")
+            (about: "create, update, delete, or inspect a Sabre contract registry")
+            (@setting SubcommandsNegateReqs)
             (@group action =>
                 (@arg create: -c --create "Create the contract registry")
                 (@arg update: -u --update "Update the contract registry")
                 (@arg delete: -d --delete "Delete the contract registry")
             )
             (@arg name: +required "Name of the contracts in the registry")
-            (@arg key: -k --key +takes_value "Signing key name")
-            (@arg url: -U --url +takes_value "URL to the Sawtooth REST API")
+            (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+            (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
+            (@arg url: -U --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
             (@arg owner: -O --owner +takes_value +multiple "Owner of this contract registry")
+            (@arg max_memory_pages: --("max-memory-pages") +takes_value requires[create] "Number of 64KiB pages of memory the registry's contracts may use, instead of the transaction processor's limit")
             (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+            (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+            (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+            (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+            (@arg output: -o --output +takes_value "Write the signed batches to a file instead of submitting them")
+            (@arg batch_file: --("batch-file") +takes_value conflicts_with[output] "Add the transaction to a batch file instead of submitting it")
+            (@arg depends_on: --("depends-on") +takes_value +multiple number_of_values(1) "ID of a transaction that must be committed before this one; may be repeated")
+            (@arg yes: -y --yes "Delete the contract registry without asking for confirmation")
+            (@subcommand show =>
+                (about: "Show the owners and versions of a Sabre contract registry")
+                (@arg name: +required "Name of the contracts in the registry")
+                (@arg url: -U --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
+            )
+            (subcommand: SubCommand::with_name("add-owner")
+                .about("Add owners to an existing Sabre contract registry, keeping its current owners")
+                .args(&[
+                    Arg::with_name("name")
+                        .help("Name of the contracts in the registry")
+                        .required(true),
+                    Arg::with_name("owner")
+                        .help("Public key of an owner to add")
+                        .required(true)
+                        .multiple(true),
+                ])
+                .args(&owner_update_args()))
+            (subcommand: SubCommand::with_name("remove-owner")
+                .about("Remove owners from an existing Sabre contract registry, keeping its other owners")
+                .args(&[
+                    Arg::with_name("name")
+                        .help("Name of the contracts in the registry")
+                        .required(true),
+                    Arg::with_name("owner")
+                        .help("Public key of an owner to remove")
+                        .required(true)
+                        .multiple(true),
+                ])
+                .args(&owner_update_args()))
         )
         (@subcommand sp =>
-          (about: "Create, update or delete smart permissions")
-          (@arg url: -U --url +takes_value "URL to the Sawtooth REST API")
+          (about: "Create, update, delete, or inspect smart permissions")
+          (@arg url: -U --url +takes_value "URL to the Sawtooth REST API; a comma-separated list is tried in order")
           (@arg wait: --wait +takes_value "A time in seconds to wait for batches to be committed")
+          (@arg target: --target +takes_value possible_value[sawtooth scabbard validator] "Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+          (@arg circuit: --circuit +takes_value "ID of the circuit of the scabbard service to submit batches to")
+          (@arg service_id: --("service-id") +takes_value "ID of the scabbard service to submit batches to")
+          (@arg output: -o --output +takes_value "Write the signed batches to a file instead of submitting them")
+          (@arg batch_file: --("batch-file") +takes_value conflicts_with[output] "Add the transaction to a batch file instead of submitting it")
+          (@arg depends_on: --("depends-on") +takes_value +multiple number_of_values(1) "ID of a transaction that must be committed before this one; may be repeated")
           (@subcommand create =>
                 (@arg org_id: +required "Organization ID ")
                 (@arg name: +required "Name of the Smart Permission")
                 (@arg filename: -f --filename +required +takes_value "Path to smart_permission")
-                (@arg key: -k --key +takes_value "Signing key name")
+                (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+                (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
             )
             (@subcommand update =>
                 (@arg org_id: +required "Organization IDs")
                 (@arg name: +required "Name of the Smart Permission")
                 (@arg filename: -f --filename +required +takes_value "Path to smart_permission")
-                (@arg key: -k --key +takes_value "Signing key name")
+                (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+                (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
             )
             (@subcommand delete =>
                 (@arg org_id: +required "Organization IDs")
                 (@arg name: +required "Name of the Smart Permission")
-                (@arg key: -k --key +takes_value "Signing key name")
+                (@arg key: -k --key +takes_value "Signing key name, path to a key file, or hex-encoded private key")
+                (@arg key_dir: --("key-dir") +takes_value "Directory containing signing keys")
+                (@arg yes: -y --yes "Delete the smart permission without asking for confirmation")
+            )
+            (@subcommand list =>
+                (about: "List the smart permissions of an organization")
+                (@arg org_id: +required "Organization ID")
+            )
+            (@subcommand show =>
+                (about: "Show details about a smart permission")
+                (@arg org_id: +required "Organization ID")
+                (@arg name: +required "Name of the Smart Permission")
             )
         )
     );
 
     let app = app.subcommand(
         SubCommand::with_name("contract")
-            .about("List or show a Sabre smart contract")
+            .about("List, show, download, delete, pause, or resume a Sabre smart contract")
             .setting(AppSettings::SubcommandRequiredElseHelp)
             .subcommand(
                 SubCommand::with_name("list")
                     .about("List all registered Sabre smart contracts")
+                    .args(&[Arg::with_name("url")
+                        .help("URL to the Sawtooth REST API; a comma-separated list is tried in order")
+                        .short("U")
+                        .long("url")
+                        .takes_value(true)]),
+            )
+            .subcommand(
+                SubCommand::with_name("show")
+                    .about("Show details about a registered Sabre smart contract")
                     .args(&[
                         Arg::with_name("url")
-                            .help("URL to the Sawtooth REST API")
+                            .help("URL to the Sawtooth REST API; a comma-separated list is tried in order")
                             .short("U")
                             .long("url")
                             .takes_value(true),
-                        Arg::with_name("format")
-                            .help("Format to display list of smart contracts in")
-                            .short("f")
-                            .long("format")
+                        Arg::with_name("name")
+                            .help(
+                                "Name of the smart contract; may also be given in the form \
+                                 'name:version'",
+                            )
                             .takes_value(true)
-                            .possible_values(&["human", "csv"])
-                            .default_value("human"),
+                            .required(true),
+                        Arg::with_name("version")
+                            .help("Version of the smart contract")
+                            .takes_value(true),
                     ]),
             )
             .subcommand(
-                SubCommand::with_name("show")
-                    .about("Show details about a registered Sabre smart contract")
+                SubCommand::with_name("download")
+                    .about("Download the WebAssembly module of a registered Sabre smart contract")
                     .args(&[
                         Arg::with_name("url")
-                            .help("URL to the Sawtooth REST API")
+                            .help("URL to the Sawtooth REST API; a comma-separated list is tried in order")
                             .short("U")
                             .long("url")
                             .takes_value(true),
-                        Arg::with_name("contract")
+                        Arg::with_name("name")
                             .help(
-                                "Name and version of the smart contract in the form \
-                                     'name:version'",
+                                "Name of the smart contract; may also be given in the form \
+                                 'name:version'",
                             )
                             .takes_value(true)
                             .required(true),
+                        Arg::with_name("version")
+                            .help("Version of the smart contract")
+                            .takes_value(true),
+                        Arg::with_name("output")
+                            .help("File to write the WebAssembly module to")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .required(true),
                     ]),
+            )
+            .subcommand(
+                SubCommand::with_name("delete")
+                    .about(
+                        "Delete a version of a Sabre smart contract and remove it from its \
+                         contract registry",
+                    )
+                    .args(&[
+                        Arg::with_name("name")
+                            .help(
+                                "Name of the smart contract; may also be given in the form \
+                                 'name:version'",
+                            )
+                            .takes_value(true)
+                            .required(true),
+                        Arg::with_name("version")
+                            .help("Version of the smart contract")
+                            .takes_value(true),
+                        Arg::with_name("yes")
+                            .help("Delete the smart contract without asking for confirmation")
+                            .short("y")
+                            .long("yes"),
+                    ])
+                    .args(&owner_update_args()),
+            )
+            .subcommand(
+                SubCommand::with_name("pause")
+                    .about(
+                        "Pause a version of a Sabre smart contract, so that it cannot be executed \
+                         until it is resumed",
+                    )
+                    .args(&[
+                        Arg::with_name("name")
+                            .help(
+                                "Name of the smart contract; may also be given in the form \
+                                 'name:version'",
+                            )
+                            .takes_value(true)
+                            .required(true),
+                        Arg::with_name("version")
+                            .help("Version of the smart contract")
+                            .takes_value(true),
+                    ])
+                    .args(&owner_update_args()),
+            )
+            .subcommand(
+                SubCommand::with_name("resume")
+                    .about("Resume a paused version of a Sabre smart contract")
+                    .args(&[
+                        Arg::with_name("name")
+                            .help(
+                                "Name of the smart contract; may also be given in the form \
+                                 'name:version'",
+                            )
+                            .takes_value(true)
+                            .required(true),
+                        Arg::with_name("version")
+                            .help("Version of the smart contract")
+                            .takes_value(true),
+                    ])
+                    .args(&owner_update_args()),
             ),
     );
 
-    let matches = app.get_matches();
-
-    if let Some(contract_matches) = matches.subcommand_matches("contract") {
-        contract(contract_matches)?
-    } else {
-        let (batch_link, mut wait) =
-            if let Some(upload_matches) = matches.subcommand_matches("upload") {
-                upload(upload_matches)?
-            } else if let Some(exec_matches) = matches.subcommand_matches("exec") {
-                execute(exec_matches)?
-            } else if let Some(ns_matches) = matches.subcommand_matches("ns") {
-                namespace_registry(ns_matches)?
-            } else if let Some(perm_matches) = matches.subcommand_matches("perm") {
-                namespace_permission(perm_matches)?
-            } else if let Some(cr_matches) = matches.subcommand_matches("cr") {
-                contract_registry(cr_matches)?
-            } else if let Some(sp_matches) = matches.subcommand_matches("sp") {
-                smart_permission(sp_matches)?
-            } else {
-                return Err(CliError::UserError("Subcommand required".into()));
-            };
-
-        if wait > 0 {
-            let response_body = loop {
-                let time = Instant::now();
-                let status_response = submit::wait_for_batch(&batch_link, wait)?;
+    let app = app.subcommand(
+        SubCommand::with_name("diff")
+            .about("Compare two registered versions of a Sabre smart contract")
+            .args(&[
+                Arg::with_name("name")
+                    .help("Name of the smart contract")
+                    .takes_value(true)
+                    .required(true),
+                Arg::with_name("from")
+                    .help("Version of the smart contract to compare from")
+                    .takes_value(true)
+                    .required(true),
+                Arg::with_name("to")
+                    .help("Version of the smart contract to compare to")
+                    .takes_value(true)
+                    .required(true),
+                Arg::with_name("url")
+                    .help("URL to the Sawtooth REST API; a comma-separated list is tried in order")
+                    .short("U")
+                    .long("url")
+                    .takes_value(true),
+            ]),
+    );
 
-                wait = wait.saturating_sub(time.elapsed().as_secs());
+    let app = app.subcommand(
+        SubCommand::with_name("keygen")
+            .about("Generate a signing key pair for use with the Sabre CLI")
+            .args(&[
+                Arg::with_name("key_name")
+                    .help("Name of the key to create; defaults to the current user's name")
+                    .takes_value(true),
+                Arg::with_name("key_dir")
+                    .help(
+                        "Directory to write the key files to; defaults to $SABRE_KEY_DIR or \
+                         $HOME/.sawtooth/keys",
+                    )
+                    .long("key-dir")
+                    .takes_value(true),
+                Arg::with_name("force")
+                    .help("Overwrite existing key files")
+                    .long("force"),
+            ]),
+    );
 
-                if wait == 0 || status_response.is_finished() {
-                    break status_response;
-                }
-            };
+    let app = app.subcommand(
+        SubCommand::with_name("key")
+            .about("Manage signing keys")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("encrypt")
+                    .about("Encrypt a private key file with a passphrase")
+                    .args(&[
+                        Arg::with_name("key")
+                            .help(
+                                "Name of or path to the key to encrypt; defaults to the current \
+                                 user's name",
+                            )
+                            .takes_value(true),
+                        Arg::with_name("key_dir")
+                            .help("Directory containing signing keys")
+                            .long("key-dir")
+                            .takes_value(true),
+                        Arg::with_name("output")
+                            .help("File to write the encrypted key to; defaults to the key file")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true),
+                    ]),
+            )
+            .subcommand(
+                SubCommand::with_name("list")
+                    .about("List the keys in the key directory and their public keys")
+                    .arg(
+                        Arg::with_name("key_dir")
+                            .help("Directory containing signing keys")
+                            .long("key-dir")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("import")
+                    .about("Import a hex-encoded private key into the key directory")
+                    .args(&[
+                        Arg::with_name("key_name")
+                            .help("Name of the imported key")
+                            .required(true),
+                        Arg::with_name("filename").help(
+                            "File containing the private key; standard input is read if \
+                                 omitted or '-'",
+                        ),
+                        Arg::with_name("key_dir")
+                            .help("Directory to write the key files to")
+                            .long("key-dir")
+                            .takes_value(true),
+                        Arg::with_name("force")
+                            .help("Overwrite files if they exist")
+                            .long("force"),
+                    ]),
+            )
+            .subcommand(
+                SubCommand::with_name("export")
+                    .about("Print the public key of a signing key, such as for an owner list")
+                    .args(&[
+                        Arg::with_name("key")
+                            .help(
+                                "Signing key name, path to a key file, or hex-encoded private \
+                                 key; defaults to the current user's name",
+                            )
+                            .takes_value(true),
+                        Arg::with_name("key_dir")
+                            .help("Directory containing signing keys")
+                            .long("key-dir")
+                            .takes_value(true),
+                    ]),
+            ),
+    );
 
-            println!("Response Body:\n{}", response_body);
-        }
-    }
+    let app = app.subcommand(
+        SubCommand::with_name("admin")
+            .about("Manage the administrators of Sabre")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("set-admins")
+                    .about(
+                        "Add or remove public keys in the sawtooth.swa.administrators setting \
+                         with a Sawtooth Settings transaction",
+                    )
+                    .args(&[
+                        Arg::with_name("add")
+                            .help("Public key of an administrator to add")
+                            .short("a")
+                            .long("add")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1),
+                        Arg::with_name("remove")
+                            .help("Public key of an administrator to remove")
+                            .short("r")
+                            .long("remove")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1),
+                    ])
+                    .args(&owner_update_args())
+                    .group(
+                        ArgGroup::with_name("change")
+                            .args(&["add", "remove"])
+                            .multiple(true)
+                            .required(true),
+                    ),
+            ),
+    );
 
-    Ok(())
-}
+    let app = app.subcommand(
+        SubCommand::with_name("profile")
+            .about("List or show the profiles in the configuration file")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("list").about("List the profiles and the URL of each"),
+            )
+            .subcommand(
+                SubCommand::with_name("show")
+                    .about("Show the connection details a profile uses")
+                    .arg(
+                        Arg::with_name("name")
+                            .help("Name of the profile; defaults to the profile in use")
+                            .takes_value(true),
+                    ),
+            ),
+    );
 
-fn upload(upload_matches: &clap::ArgMatches) -> Result<(String, u64), CliError> {
-    let filename = upload_matches.value_of("filename").unwrap();
-    let key_name = upload_matches.value_of("key");
-    let url = upload_matches
-        .value_of("url")
-        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
-    let wasm_name = upload_matches.value_of("wasm");
+    let app = app.subcommand(
+        SubCommand::with_name("new")
+            .about("Create a Rust smart contract crate with an example handler")
+            .args(&[
+                Arg::with_name("name")
+                    .help("Name of the contract")
+                    .required(true),
+                Arg::with_name("path")
+                    .help("Directory to create the crate in; defaults to ./<name>")
+                    .long("path")
+                    .takes_value(true),
+                Arg::with_name("prefix")
+                    .help("Namespace of the contract's state; defaults to the first 6 characters of the SHA-512 hash of the name")
+                    .long("prefix")
+                    .takes_value(true),
+                Arg::with_name("sdk_path")
+                    .help("Path to a sabre-sdk checkout to depend on instead of the release matching this CLI")
+                    .long("sdk-path")
+                    .takes_value(true),
+            ]),
+    );
 
-    let wait = match value_t!(upload_matches, "wait", u64) {
-        Ok(wait) => wait,
-        Err(err) => match err.kind {
-            clap::ErrorKind::ArgumentNotFound => 0,
-            _ => return Err(CliError::UserError("Wait must be an integer".into())),
-        },
-    };
+    let app = app.subcommand(
+        SubCommand::with_name("build")
+            .about("Compile a Rust contract crate to a WebAssembly module that Sabre can execute")
+            .args(&[
+                Arg::with_name("manifest_path")
+                    .help("Path to the contract's Cargo.toml; defaults to ./Cargo.toml")
+                    .long("manifest-path")
+                    .takes_value(true),
+                Arg::with_name("features")
+                    .help("Space or comma separated list of the crate's features to enable")
+                    .long("features")
+                    .takes_value(true),
+                Arg::with_name("debug")
+                    .help("Build with the dev profile, keeping debug information")
+                    .long("debug"),
+            ]),
+    );
 
-    let batch_link = upload::do_upload(&filename, key_name, &url, wasm_name)?;
-    Ok((batch_link, wait))
-}
+    let app = app.subcommand(
+        SubCommand::with_name("pack")
+            .about("Package a compiled contract and its manifest into a smart contract archive")
+            .args(&[
+                Arg::with_name("manifest")
+                    .help("Path to the contract manifest (manifest.yaml)")
+                    .short("m")
+                    .long("manifest")
+                    .takes_value(true)
+                    .required(true),
+                Arg::with_name("wasm")
+                    .help("Path to the compiled contract (*.wasm)")
+                    .short("w")
+                    .long("wasm")
+                    .takes_value(true)
+                    .required(true),
+                Arg::with_name("output")
+                    .help("File to write the archive to; defaults to <name>_<version>.scar")
+                    .short("o")
+                    .long("output")
+                    .takes_value(true),
+            ]),
+    );
 
-fn execute(exec_matches: &clap::ArgMatches) -> Result<(String, u64), CliError> {
-    let contract = exec_matches.value_of("contract").unwrap();
-    let payload = exec_matches.value_of("payload").unwrap();
-    let key_name = exec_matches.value_of("key");
-    let url = exec_matches
-        .value_of("url")
-        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+    let app = app.subcommand(
+        SubCommand::with_name("verify")
+            .about("Compare a local contract with the hash recorded in its contract registry")
+            .args(&[
+                Arg::with_name("filename")
+                    .help("Path to the compiled contract (*.wasm) or smart contract archive (*.scar)")
+                    .required(true),
+                Arg::with_name("contract")
+                    .help("Name:Version of the registered contract; defaults to the manifest of a smart contract archive")
+                    .short("C")
+                    .long("contract")
+                    .takes_value(true),
+                Arg::with_name("url")
+                    .help("URL to the Sawtooth REST API; a comma-separated list is tried in order")
+                    .short("U")
+                    .long("url")
+                    .takes_value(true),
+            ]),
+    );
 
-    let wait = match value_t!(exec_matches, "wait", u64) {
-        Ok(wait) => wait,
-        Err(err) => match err.kind {
-            clap::ErrorKind::ArgumentNotFound => 0,
-            _ => return Err(CliError::UserError("Wait must be an integer".into())),
-        },
-    };
+    let app = app.subcommand(
+        SubCommand::with_name("addr")
+            .about("Compute the state address of a Sabre object")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("ns")
+                    .about("Compute the address of a namespace registry")
+                    .arg(
+                        Arg::with_name("namespace")
+                            .help("A global state address prefix (namespace)")
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("cr")
+                    .about("Compute the address of a contract registry")
+                    .arg(
+                        Arg::with_name("name")
+                            .help("Name of the contracts in the registry")
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("contract")
+                    .about("Compute the address of a contract")
+                    .args(&[
+                        Arg::with_name("name")
+                            .help(
+                                "Name of the smart contract; may also be given in the form \
+                                 'name:version'",
+                            )
+                            .required(true),
+                        Arg::with_name("version").help("Version of the smart contract"),
+                    ]),
+            )
+            .subcommand(
+                SubCommand::with_name("sp")
+                    .about("Compute the address of a smart permission")
+                    .args(&[
+                        Arg::with_name("org_id")
+                            .help("Organization ID")
+                            .required(true),
+                        Arg::with_name("name")
+                            .help("Name of the Smart Permission")
+                            .required(true),
+                    ]),
+            ),
+    );
 
-    let inputs = exec_matches
-        .values_of("inputs")
-        .map(|values| values.map(|v| v.into()).collect())
-        .ok_or_else(|| {
-            CliError::UserError("exec action requires one or more --inputs arguments".into())
-        })?;
+    let app = app.subcommand(
+        SubCommand::with_name("state")
+            .about("List or show the state entries stored in global state")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("list")
+                    .about("List the state entries under an address prefix")
+                    .args(&[
+                        Arg::with_name("prefix")
+                            .help("Hex-encoded address prefix; defaults to all Sabre state (00ec)"),
+                        Arg::with_name("url")
+                            .help("URL to the Sawtooth REST API; a comma-separated list is tried in order")
+                            .short("U")
+                            .long("url")
+                            .takes_value(true),
+                        Arg::with_name("raw")
+                            .help("Do not decode Sabre state entries")
+                            .long("raw"),
+                    ]),
+            )
+            .subcommand(
+                SubCommand::with_name("show")
+                    .about("Show the contents of a state entry")
+                    .args(&[
+                        Arg::with_name("address")
+                            .help("Hex-encoded state address")
+                            .required(true),
+                        Arg::with_name("url")
+                            .help("URL to the Sawtooth REST API; a comma-separated list is tried in order")
+                            .short("U")
+                            .long("url")
+                            .takes_value(true),
+                        Arg::with_name("raw")
+                            .help("Show a hex dump instead of decoding Sabre state entries")
+                            .long("raw"),
+                    ]),
+            ),
+    );
 
-    let outputs = exec_matches
-        .values_of("outputs")
-        .map(|values| values.map(|v| v.into()).collect())
-        .ok_or_else(|| {
-            CliError::UserError("exec action requires one or more --outputs arguments".into())
-        })?;
-    let (name, version) = match contract.split(':').collect::<Vec<_>>() {
-        ref v if (v.len() == 1 || v.len() == 2) && v[0].is_empty() => Err(CliError::UserError(
-            "contract name must be specified".into(),
-        )),
-        ref v if v.len() == 1 || v.len() == 2 && v[1].is_empty() => Ok((v[0], "latest")),
-        ref v if v.len() == 2 => Ok((v[0], v[1])),
-        _ => Err(CliError::UserError(
-            "malformed contract argument, may contain at most one ':'".into(),
-        )),
-    }?;
+    let app = app.subcommand(
+        SubCommand::with_name("query")
+            .about("Decode a state entry of a contract with the state schema registered with it")
+            .args(&[
+                Arg::with_name("name")
+                    .help(
+                        "Name of the smart contract; may also be given in the form \
+                         'name:version'",
+                    )
+                    .takes_value(true)
+                    .required(true),
+                Arg::with_name("version")
+                    .help("Version of the smart contract")
+                    .takes_value(true),
+                Arg::with_name("address")
+                    .help("Hex-encoded address of the state entry")
+                    .short("a")
+                    .long("address")
+                    .takes_value(true)
+                    .required(true),
+                Arg::with_name("message")
+                    .help(
+                        "Fully-qualified name of the message to decode the entry as; defaults to \
+                         the message registered with the contract",
+                    )
+                    .long("message")
+                    .takes_value(true),
+                Arg::with_name("url")
+                    .help("URL to the Sawtooth REST API; a comma-separated list is tried in order")
+                    .short("U")
+                    .long("url")
+                    .takes_value(true),
+            ]),
+    );
 
-    let contract_payload = load_bytes_from_file(payload)?;
-    let signer = new_signer(key_name)?;
-    let batch = ExecuteContractActionBuilder::new()
-        .with_name(name.into())
-        .with_version(version.into())
-        .with_inputs(inputs)
-        .with_outputs(outputs)
-        .with_payload(contract_payload)
-        .into_payload_builder()?
-        .into_transaction_builder(&*signer)?
-        .into_batch_builder(&*signer)?
-        .build(&*signer)?;
+    let app = app.subcommand(
+        SubCommand::with_name("event")
+            .about("Follow the state changes and events of committed blocks")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("listen")
+                    .about("Print the Sabre state changes and events of each block as it is committed")
+                    .args(&[
+                        Arg::with_name("prefix")
+                            .help("Hex-encoded address prefix to follow; defaults to all Sabre state (00ec)")
+                            .short("p")
+                            .long("prefix")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1),
+                        Arg::with_name("event_type")
+                            .help("Type of event emitted by a contract to follow; requires --target validator")
+                            .short("e")
+                            .long("event-type")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1),
+                        Arg::with_name("url")
+                            .help("URL to the Sawtooth REST API, or to the validator with --target validator; a comma-separated list is tried in order")
+                            .short("U")
+                            .long("url")
+                            .takes_value(true),
+                        Arg::with_name("target")
+                            .help("Listen to the Sawtooth REST API, or directly to a validator")
+                            .long("target")
+                            .takes_value(true)
+                            .possible_values(&["sawtooth", "validator"]),
+                    ]),
+            ),
+    );
 
-    let batch_link = submit_batches(&url, vec![batch])?;
+    let app = app.subcommand(
+        SubCommand::with_name("receipt")
+            .about("Show the state changes, events and data recorded for committed transactions")
+            .args(&[
+                Arg::with_name("transaction_id")
+                    .help("ID of a committed transaction")
+                    .required(true)
+                    .multiple(true),
+                Arg::with_name("url")
+                    .help("URL to the Sawtooth REST API, or to the validator with --target validator; a comma-separated list is tried in order")
+                    .short("U")
+                    .long("url")
+                    .takes_value(true),
+                Arg::with_name("target")
+                    .help("Fetch the receipts from the Sawtooth REST API, or directly from a validator")
+                    .long("target")
+                    .takes_value(true)
+                    .possible_values(&["sawtooth", "validator"]),
+                Arg::with_name("raw")
+                    .help("Show hex dumps instead of decoding Sabre state entries")
+                    .long("raw"),
+            ]),
+    );
 
-    Ok((batch_link, wait))
+    app.subcommand(
+        SubCommand::with_name("completions")
+            .about("Generate a shell completion script for the Sabre CLI")
+            .arg(
+                Arg::with_name("shell")
+                    .help("Shell to generate the completion script for")
+                    .possible_values(&Shell::variants())
+                    .required(true),
+            ),
+    )
 }
 
-fn namespace_registry(ns_matches: &clap::ArgMatches) -> Result<(String, u64), CliError> {
-    let namespace = ns_matches.value_of("namespace").unwrap();
+/// The arguments of the commands which update the owners of a registry, other than the registry
+/// and the owners to change
+fn owner_update_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("key")
+            .help("Signing key name, path to a key file, or hex-encoded private key")
+            .short("k")
+            .long("key")
+            .takes_value(true),
+        Arg::with_name("key_dir")
+            .help("Directory containing signing keys")
+            .long("key-dir")
+            .takes_value(true),
+        Arg::with_name("url")
+            .help("URL to the Sawtooth REST API; a comma-separated list is tried in order")
+            .short("U")
+            .long("url")
+            .takes_value(true),
+        Arg::with_name("wait")
+            .help("A time in seconds to wait for batches to be committed")
+            .long("wait")
+            .takes_value(true),
+        Arg::with_name("target")
+            .help("Submit batches to the Sawtooth REST API, a Splinter scabbard service, or directly to a validator")
+            .long("target")
+            .takes_value(true)
+            .possible_values(&["sawtooth", "scabbard", "validator"]),
+        Arg::with_name("circuit")
+            .help("ID of the circuit of the scabbard service to submit batches to")
+            .long("circuit")
+            .takes_value(true),
+        Arg::with_name("service_id")
+            .help("ID of the scabbard service to submit batches to")
+            .long("service-id")
+            .takes_value(true),
+        Arg::with_name("output")
+            .help("Write the signed batches to a file instead of submitting them")
+            .short("o")
+            .long("output")
+            .takes_value(true),
+        Arg::with_name("batch_file")
+            .help("Add the transaction to a batch file instead of submitting it")
+            .long("batch-file")
+            .takes_value(true)
+            .conflicts_with("output"),
+        Arg::with_name("depends_on")
+            .help("ID of a transaction that must be committed before this one; may be repeated")
+            .long("depends-on")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+    ]
+}
 
-    let key_name = ns_matches.value_of("key");
+fn run() -> Result<(), CliError> {
+    // Below, unwrap() is used on required arguments, since they will always
+    // contain a value (and lack of value is should cause a panic). unwrap()
+    // is also used on get_matches() because SubcommandRequiredElseHelp will
+    // ensure a value.
 
-    let url = ns_matches
-        .value_of("url")
-        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+    let matches = create_app().get_matches();
 
-    let wait = match value_t!(ns_matches, "wait", u64) {
-        Ok(wait) => wait,
-        Err(err) => match err.kind {
-            clap::ErrorKind::ArgumentNotFound => 0,
-            _ => return Err(CliError::UserError("Wait must be an integer".into())),
-        },
+    let quiet = global_present(&matches, "quiet");
+    let logger = simple_logger::SimpleLogger::new().with_level(LevelFilter::Warn);
+    let logger = match (quiet, global_occurrences(&matches, "verbose")) {
+        (true, _) => logger.with_level(LevelFilter::Error),
+        (false, 0) => logger.with_module_level(BIN_NAME, LevelFilter::Info),
+        (false, 1) => logger.with_module_level(BIN_NAME, LevelFilter::Debug),
+        (false, 2) => logger.with_module_level(BIN_NAME, LevelFilter::Trace),
+        (false, _) => logger.with_level(LevelFilter::Trace),
     };
+    logger
+        .init()
+        .map_err(|err| CliError::UserError(format!("Unable to initialize logging: {}", err)))?;
 
-    let signer = new_signer(key_name)?;
+    let base_config = CliConfig::load(global_value(&matches, "config"))?;
 
-    let owners = ns_matches
-        .values_of("owner")
-        .map(|values| values.map(|v| v.into()).collect());
+    let format = global_value(&matches, "format")
+        .or_else(|| base_config.format())
+        .unwrap_or("human")
+        .parse::<OutputFormat>()?;
 
-    let batch_link = if ns_matches.is_present("update") {
-        let owners = owners.ok_or_else(|| {
-            CliError::UserError("update action requires one or more --owner arguments".into())
+    let profile_name = global_value(&matches, "profile")
+        .map(String::from)
+        .or_else(|| {
+            std::env::var("SABRE_PROFILE")
+                .ok()
+                .filter(|name| !name.is_empty())
+        });
+    if let Some(profile_matches) = matches.subcommand_matches("profile") {
+        return profile_command(
+            profile_matches,
+            &base_config,
+            profile_name.as_deref(),
+            format,
+        );
+    }
+    let config = base_config.with_profile(profile_name.as_deref())?;
+
+    let subscribe = global_present(&matches, "subscribe") || config.subscribe();
+    let profile = Profile::new(global_present(&matches, "timings"));
+
+    let auth = match global_value(&matches, "auth") {
+        Some(auth) => Some(auth.to_string()),
+        None => std::env::var("SABRE_AUTH")
+            .ok()
+            .or_else(|| config.auth().map(String::from)),
+    };
+    let mut client = HttpClient::new();
+    if let Some(auth) = auth {
+        client = client.with_auth(auth.parse()?);
+    }
+    let default_retry = RetryPolicy::default();
+    client = client.with_retry(RetryPolicy {
+        retries: match global_u64(&matches, "retries")? {
+            Some(retries) => u32::try_from(retries)
+                .map_err(|_| CliError::UserError("retries is too large".into()))?,
+            None => config.retries().unwrap_or(default_retry.retries),
+        },
+        backoff: global_u64(&matches, "retry_backoff")?
+            .or_else(|| config.retry_backoff())
+            .map(Duration::from_millis)
+            .unwrap_or(default_retry.backoff),
+        jitter: global_u64(&matches, "retry_jitter")?
+            .or_else(|| config.retry_jitter())
+            .map(Duration::from_millis)
+            .unwrap_or(default_retry.jitter),
+    });
+    if let Some(proxy) = global_value(&matches, "proxy") {
+        client = client.with_proxy(proxy);
+    }
+    if let Some(ca_file) = global_value(&matches, "ca_file") {
+        client = client.with_ca_file(ca_file);
+    }
+    match (
+        global_value(&matches, "client_cert"),
+        global_value(&matches, "client_key"),
+    ) {
+        (Some(cert_file), Some(key_file)) => {
+            client = client.with_client_identity(cert_file, key_file)
+        }
+        (None, None) => (),
+        _ => {
+            return Err(CliError::UserError(
+                "--client-cert and --client-key must be used together".into(),
+            ))
+        }
+    }
+
+    if let Some(completions_matches) = matches.subcommand_matches("completions") {
+        let shell = value_t!(completions_matches, "shell", Shell)
+            .map_err(|err| CliError::UserError(err.to_string()))?;
+        create_app().gen_completions_to(BIN_NAME, shell, &mut std::io::stdout());
+    } else if let Some(addr_matches) = matches.subcommand_matches("addr") {
+        address(addr_matches, format)?
+    } else if let Some(new_matches) = matches.subcommand_matches("new") {
+        new_command(new_matches, format)?
+    } else if let Some(build_matches) = matches.subcommand_matches("build") {
+        build_command(build_matches, format)?
+    } else if let Some(pack_matches) = matches.subcommand_matches("pack") {
+        pack(pack_matches, format)?
+    } else if let Some(verify_matches) = matches.subcommand_matches("verify") {
+        verify(verify_matches, &config, &client, format)?
+    } else if let Some(contract_matches) =
+        matches
+            .subcommand_matches("contract")
+            .filter(|contract_matches| {
+                !matches!(
+                    contract_matches.subcommand_name(),
+                    Some("delete") | Some("pause") | Some("resume")
+                )
+            })
+    {
+        contract(contract_matches, &config, &client, format)?
+    } else if let Some(diff_matches) = matches.subcommand_matches("diff") {
+        contract_diff(diff_matches, &config, &client, format)?
+    } else if let Some(state_matches) = matches.subcommand_matches("state") {
+        state_command(state_matches, &config, &client, format)?
+    } else if let Some(query_matches) = matches.subcommand_matches("query") {
+        query(query_matches, &config, &client, format)?
+    } else if let Some(event_matches) = matches.subcommand_matches("event") {
+        event_command(event_matches, &config, &client, format)?
+    } else if let Some(inspect_matches) = matches
+        .subcommand_matches("batch")
+        .and_then(|batch_matches| batch_matches.subcommand_matches("inspect"))
+    {
+        batch_inspect(inspect_matches, format)?
+    } else if let Some(receipt_matches) = matches.subcommand_matches("receipt") {
+        receipt_command(receipt_matches, &config, &client, format)?
+    } else if let Some(keygen_matches) = matches.subcommand_matches("keygen") {
+        key::generate_keys(
+            keygen_matches.value_of("key_name"),
+            keygen_matches
+                .value_of("key_dir")
+                .or_else(|| config.key_dir()),
+            keygen_matches.is_present("force"),
+        )?
+    } else if let Some(key_matches) = matches.subcommand_matches("key") {
+        key_command(key_matches, &config, format)?
+    } else if let Some(ns_matches) = matches
+        .subcommand_matches("ns")
+        .filter(|ns_matches| matches!(ns_matches.subcommand_name(), Some("list") | Some("show")))
+    {
+        namespace_query(ns_matches, &config, &client, format)?
+    } else if let Some(cr_matches) = matches
+        .subcommand_matches("cr")
+        .filter(|cr_matches| cr_matches.subcommand_name() == Some("show"))
+    {
+        contract_registry_query(cr_matches, &config, &client, format)?
+    } else if let Some(list_matches) = matches
+        .subcommand_matches("perm")
+        .and_then(|perm_matches| perm_matches.subcommand_matches("list"))
+    {
+        permission_list(list_matches, &config, &client, format)?
+    } else if let Some(sp_matches) = matches
+        .subcommand_matches("sp")
+        .filter(|sp_matches| matches!(sp_matches.subcommand_name(), Some("list") | Some("show")))
+    {
+        smart_permission_query(sp_matches, &config, &client, format)?
+    } else if let Some(exec_matches) = matches
+        .subcommand_matches("exec")
+        .filter(|exec_matches| exec_matches.is_present("dry_run"))
+    {
+        dry_run(exec_matches, &config, &client, format)?
+    } else {
+        if format == OutputFormat::Csv {
+            return Err(CliError::UserError(
+                "csv format is only supported by list commands".into(),
+            ));
+        }
+
+        let signed_batches = if let Some(submit_matches) = matches.subcommand_matches("submit") {
+            profile.time("load", || submit(submit_matches, &config))?
+        } else if let Some(batch_matches) = matches.subcommand_matches("batch") {
+            profile.time("load", || submit_batch_file(batch_matches, &config))?
+        } else if let Some(deploy_matches) = matches.subcommand_matches("deploy") {
+            let batcher = batcher_signer(&matches, &config)?;
+            match profile.time("payload", || {
+                deploy(
+                    deploy_matches,
+                    &config,
+                    &client,
+                    format,
+                    quiet,
+                    batcher.as_deref(),
+                )
+            })? {
+                Some(signed_batches) => signed_batches,
+                None => return Ok(()),
+            }
+        } else if let Some(apply_matches) = matches
+            .subcommand_matches("perm")
+            .and_then(|perm_matches| perm_matches.subcommand_matches("apply"))
+        {
+            let batcher = batcher_signer(&matches, &config)?;
+            match profile.time("payload", || {
+                permission_apply(
+                    apply_matches,
+                    &config,
+                    &client,
+                    format,
+                    quiet,
+                    batcher.as_deref(),
+                )
+            })? {
+                Some(signed_batches) => signed_batches,
+                None => return Ok(()),
+            }
+        } else {
+            let batcher = batcher_signer(&matches, &config)?;
+            let pending = profile.time("payload", || {
+                if let Some(upload_matches) = matches.subcommand_matches("upload") {
+                    upload(
+                        upload_matches,
+                        &config,
+                        &client,
+                        subscribe,
+                        batcher.as_deref(),
+                        &profile,
+                    )
+                } else if let Some(exec_matches) = matches.subcommand_matches("exec") {
+                    execute(exec_matches, &config, &client)
+                } else if let Some(update_matches) = matches
+                    .subcommand_matches("ns")
+                    .and_then(|ns_matches| ns_matches.subcommand_matches("update-owners"))
+                {
+                    namespace_update_owners(update_matches, &config, &client)
+                } else if let Some(admin_matches) = matches
+                    .subcommand_matches("admin")
+                    .and_then(|admin_matches| admin_matches.subcommand_matches("set-admins"))
+                {
+                    set_admins(admin_matches, &config, &client)
+                } else if let Some(ns_matches) = matches.subcommand_matches("ns") {
+                    namespace_registry(ns_matches, &config, &client)
+                } else if let Some(perm_matches) = matches.subcommand_matches("perm") {
+                    namespace_permission(perm_matches, &config)
+                } else if let Some(cr_matches) = matches
+                    .subcommand_matches("cr")
+                    .filter(|cr_matches| cr_matches.subcommand_name().is_some())
+                {
+                    contract_registry_update_owners(cr_matches, &config, &client)
+                } else if let Some(cr_matches) = matches.subcommand_matches("cr") {
+                    contract_registry(cr_matches, &config, &client)
+                } else if let Some(sp_matches) = matches.subcommand_matches("sp") {
+                    smart_permission(sp_matches, &config, &client)
+                } else if let Some(contract_matches) = matches.subcommand_matches("contract") {
+                    contract_update(contract_matches, &config, &client)
+                } else {
+                    Err(CliError::UserError("Subcommand required".into()))
+                }
+            })?;
+
+            if let Some(batch_file) = pending.batch_file {
+                let (transaction_id, count) = profile.time("signing", || {
+                    batch::add_transaction(
+                        batch_file,
+                        pending.transaction,
+                        pending.dependencies,
+                        &*pending.signer,
+                        batcher.as_deref(),
+                    )
+                })?;
+                profile.report_total();
+
+                if format == OutputFormat::Human && quiet {
+                    println!("{}", transaction_id);
+                } else if format == OutputFormat::Human {
+                    println!(
+                        "added transaction {} to {} ({} transaction(s))",
+                        transaction_id, batch_file, count
+                    );
+                } else {
+                    print_document(
+                        format,
+                        &BatchFileTransactionOutput {
+                            transaction_id,
+                            batch_file,
+                            transaction_count: count,
+                        },
+                    )?;
+                }
+
+                return Ok(());
+            }
+
+            let transaction = pending.transaction.with_dependencies(pending.dependencies);
+            let signer = &*pending.signer;
+            SignedBatches {
+                batches: vec![profile.time("signing", || {
+                    batch::sign_batch(vec![transaction], signer, batcher.as_deref())
+                })?],
+                url: pending.url,
+                target: pending.target,
+                wait: pending.wait,
+                output: pending.output,
+                throttle: Throttle::default(),
+            }
+        };
+
+        profile.report_batches(&signed_batches.batches);
+
+        if let Some(output) = signed_batches.output {
+            let batch_ids = submit::write_batches(output, signed_batches.batches)?;
+            profile.report_total();
+
+            if format == OutputFormat::Human && quiet {
+                println!("{}", batch_ids.join("\n"));
+            } else if format == OutputFormat::Human {
+                println!("wrote {} batch(es) to {}", batch_ids.len(), output);
+            } else {
+                print_document(format, &BatchFileOutput { batch_ids, output })?;
+            }
+
+            return Ok(());
+        }
+
+        let wait = signed_batches.wait;
+        let target = &signed_batches.target;
+        let (url, batches) = (signed_batches.url, signed_batches.batches);
+        let throttle = &signed_batches.throttle;
+        let submission = profile.time("submit", || {
+            if throttle.is_active() {
+                submit::submit_throttled(&client, url, target, batches, throttle)
+            } else {
+                submit_batches(&client, url, target, batches)
+            }
         })?;
 
-        let batch = UpdateNamespaceRegistryOwnersActionBuilder::new()
-            .with_namespace(namespace.into())
-            .with_owners(owners)
-            .into_payload_builder()?
-            .into_transaction_builder(&*signer)?
-            .into_batch_builder(&*signer)?
-            .build(&*signer)?;
+        if format == OutputFormat::Human && quiet {
+            println!("{}", submission.batch_ids.join("\n"));
+        } else if format == OutputFormat::Human {
+            println!("Response Body:\n{}", submission);
+        }
 
-        submit_batches(&url, vec![batch])?
-    } else if ns_matches.is_present("delete") {
-        if ns_matches.is_present("owner") {
+        let status = if wait > 0 {
+            let response_body = profile.time("wait", || {
+                if subscribe {
+                    submit::subscribe_batch_status(&client, target, &submission, wait)
+                } else {
+                    submit::poll_batch_status(&client, target, &submission, wait)
+                }
+            })?;
+
+            if format == OutputFormat::Human && !quiet {
+                println!("Response Body:\n{}", response_body);
+            }
+
+            Some(response_body)
+        } else {
+            None
+        };
+
+        if format != OutputFormat::Human {
+            print_document(
+                format,
+                &SubmissionOutput {
+                    submission: &submission,
+                    status: status.as_ref(),
+                },
+            )?;
+        }
+
+        profile.report_total();
+
+        if let Some(status) = status {
+            status.check()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn submit<'a>(
+    submit_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+) -> Result<SignedBatches<'a>, CliError> {
+    let url = submit_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(submit_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let bytes = match submit_matches.value_of("filename") {
+        Some(filename) if filename != "-" => load_bytes_from_file(filename)?,
+        _ => {
+            let mut bytes = Vec::new();
+            std::io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        }
+    };
+
+    let throttle = Throttle {
+        max_in_flight: match value_t!(submit_matches, "max_in_flight", usize) {
+            Ok(max_in_flight) if max_in_flight > 0 => Some(max_in_flight),
+            Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => None,
+            _ => {
+                return Err(CliError::UserError(
+                    "Max in flight must be a positive integer".into(),
+                ))
+            }
+        },
+        rate: match value_t!(submit_matches, "rate", f64) {
+            Ok(rate) if rate > 0.0 && rate.is_finite() => Some(rate),
+            Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => None,
+            _ => return Err(CliError::UserError("Rate must be a positive number".into())),
+        },
+    };
+
+    let batches = <Vec<Batch> as transact::protos::FromBytes<_>>::from_bytes(&bytes)?;
+    if batches.is_empty() {
+        return Err(CliError::UserError("No batches to submit".into()));
+    }
+
+    Ok(SignedBatches {
+        batches,
+        url,
+        target: submit_target(submit_matches, config)?,
+        wait,
+        output: None,
+        throttle,
+    })
+}
+
+fn submit_batch_file<'a>(
+    batch_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+) -> Result<SignedBatches<'a>, CliError> {
+    match batch_matches.subcommand() {
+        ("submit", Some(matches)) => {
+            let batch_file = matches.value_of("batch_file").unwrap();
+            let key_name = matches.value_of("key").or_else(|| config.key());
+            let key_dir = matches.value_of("key_dir").or_else(|| config.key_dir());
+            let url = matches
+                .value_of("url")
+                .or_else(|| config.url())
+                .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+            let wait = match value_t!(matches, "wait", u64) {
+                Ok(wait) => wait,
+                Err(err) => match err.kind {
+                    clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+                    _ => return Err(CliError::UserError("Wait must be an integer".into())),
+                },
+            };
+
+            let signer = new_signer(key_name, key_dir)?;
+            let batch = batch::build_batch(batch_file, &*signer)?;
+
+            Ok(SignedBatches {
+                batches: vec![batch],
+                url,
+                target: submit_target(matches, config)?,
+                wait,
+                output: matches.value_of("output"),
+                throttle: Throttle::default(),
+            })
+        }
+        _ => Err(CliError::UserError("Invalid Subcommand".into())),
+    }
+}
+
+/// Decode and print the batches or transactions in a file, checking their signatures and payload
+/// hashes, so that signed batches can be reviewed before they are submitted
+fn batch_inspect(inspect_matches: &clap::ArgMatches, format: OutputFormat) -> Result<(), CliError> {
+    if format == OutputFormat::Csv {
+        return Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        ));
+    }
+
+    let (bytes, name) = match inspect_matches.value_of("filename") {
+        Some(filename) if filename != "-" => (load_bytes_from_file(filename)?, filename),
+        _ => {
+            let mut bytes = Vec::new();
+            std::io::stdin().read_to_end(&mut bytes)?;
+            (bytes, "standard input")
+        }
+    };
+    let raw = inspect_matches.is_present("raw");
+
+    let output = match batch::read_signed_file(&bytes, name)? {
+        batch::SignedFile::Batches(batches) => InspectOutput::Batches(
+            batches
+                .iter()
+                .map(|batch| BatchInspectOutput::new(batch, raw))
+                .collect::<Result<_, _>>()?,
+        ),
+        batch::SignedFile::Transactions(transactions) => InspectOutput::Transactions(
+            transactions
+                .iter()
+                .map(|transaction| TransactionInspectOutput::new(transaction, None, raw))
+                .collect::<Result<_, _>>()?,
+        ),
+    };
+
+    if format != OutputFormat::Human {
+        return print_document(format, &output);
+    }
+
+    let validity = |valid: bool| if valid { "valid" } else { "INVALID" };
+    let describe_transaction = |transaction: &TransactionInspectOutput| {
+        let mut description = format!(
+            "transaction {}\n  family: {} {}\n  signer: {}\n",
+            transaction.transaction_id,
+            transaction.family_name,
+            transaction.family_version,
+            transaction.signer_public_key
+        );
+        description += &format!("  batcher: {}", transaction.batcher_public_key);
+        if transaction.batcher_matches == Some(false) {
+            description += " (does not match the batch signer)";
+        }
+        description += &format!(
+            "\n  signature: {}\n  payload sha512: {} ({})\n  nonce: {}\n",
+            validity(transaction.signature_valid),
+            transaction.payload_sha512,
+            validity(transaction.payload_hash_valid),
+            transaction.nonce
+        );
+        for (label, addresses) in &[
+            ("inputs", &transaction.inputs),
+            ("outputs", &transaction.outputs),
+            ("dependencies", &transaction.dependencies),
+        ] {
+            if addresses.is_empty() {
+                continue;
+            }
+            description += &format!("  {}:\n", label);
+            for address in addresses.iter() {
+                description += &format!("  - {}\n", address);
+            }
+        }
+        description += &indent(&transaction.description, 2);
+        description
+    };
+
+    match &output {
+        InspectOutput::Batches(batches) => {
+            for batch in batches {
+                println!(
+                    "batch {} ({} transaction(s))\n  signer: {}\n  signature: {}",
+                    batch.batch_id,
+                    batch.transactions.len(),
+                    batch.signer_public_key,
+                    validity(batch.signature_valid)
+                );
+                for transaction in &batch.transactions {
+                    print!("{}", indent(&describe_transaction(transaction), 2));
+                }
+            }
+        }
+        InspectOutput::Transactions(transactions) => {
+            println!(
+                "batch file with {} unbatched transaction(s)",
+                transactions.len()
+            );
+            for transaction in transactions {
+                print!("{}", describe_transaction(transaction));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Format a Sabre action as it is shown by `sabre batch inspect`
+fn describe_action(action: &Action) -> String {
+    let list = |label: &str, values: &[String]| {
+        let mut description = format!("  {}:\n", label);
+        for value in values {
+            description += &format!("  - {}\n", value);
+        }
+        description
+    };
+    let code = |label: &str, bytes: &[u8]| {
+        format!(
+            "  {} size: {} bytes\n  {} sha512: {}\n",
+            label,
+            bytes.len(),
+            label,
+            sha512_hex(bytes)
+        )
+    };
+
+    match action {
+        Action::CreateContract(action) => {
+            format!(
+                "action: create contract {} {}\n",
+                action.name(),
+                action.version()
+            ) + &list("inputs", action.inputs())
+                + &list("outputs", action.outputs())
+                + &code("wasm", action.contract())
+        }
+        Action::DeleteContract(action) => {
+            format!(
+                "action: delete contract {} {}\n",
+                action.name(),
+                action.version()
+            )
+        }
+        Action::ExecuteContract(action) => {
+            format!(
+                "action: execute contract {} {}\n",
+                action.name(),
+                action.version()
+            ) + &list("inputs", action.inputs())
+                + &list("outputs", action.outputs())
+                + &format!("  payload ({} bytes):\n", action.payload().len())
+                + &indent(&describe_payload(action.payload(), false), 4)
+        }
+        Action::CreateContractRegistry(action) => {
+            format!("action: create contract registry {}\n", action.name())
+                + &list("owners", action.owners())
+        }
+        Action::DeleteContractRegistry(action) => {
+            format!("action: delete contract registry {}\n", action.name())
+        }
+        Action::UpdateContractRegistryOwners(action) => {
+            format!(
+                "action: update owners of contract registry {}\n",
+                action.name()
+            ) + &list("owners", action.owners())
+        }
+        Action::CreateNamespaceRegistry(action) => {
+            format!("action: create namespace {}\n", action.namespace())
+                + &list("owners", action.owners())
+        }
+        Action::DeleteNamespaceRegistry(action) => {
+            format!("action: delete namespace {}\n", action.namespace())
+        }
+        Action::UpdateNamespaceRegistryOwners(action) => {
+            format!(
+                "action: update owners of namespace {}\n",
+                action.namespace()
+            ) + &list("owners", action.owners())
+        }
+        Action::CreateNamespaceRegistryPermission(action) => format!(
+            "action: set permission for {} on {} to {}\n",
+            action.contract_name(),
+            action.namespace(),
+            permission_flags(action.read(), action.write())
+        ),
+        Action::DeleteNamespaceRegistryPermission(action) => format!(
+            "action: delete permission for {} on {}\n",
+            action.contract_name(),
+            action.namespace()
+        ),
+        Action::CreateSmartPermission(action) => {
+            format!(
+                "action: create smart permission {} of organization {}\n",
+                action.name(),
+                action.org_id()
+            ) + &code("function", action.function())
+        }
+        Action::UpdateSmartPermission(action) => {
+            format!(
+                "action: update smart permission {} of organization {}\n",
+                action.name(),
+                action.org_id()
+            ) + &code("function", action.function())
+        }
+        Action::DeleteSmartPermission(action) => format!(
+            "action: delete smart permission {} of organization {}\n",
+            action.name(),
+            action.org_id()
+        ),
+        Action::CreateContractChunk(action) => {
+            format!(
+                "action: upload chunk {} of contract {} {}\n",
+                action.index(),
+                action.name(),
+                action.version()
+            ) + &code("chunk", action.chunk())
+        }
+        Action::CommitContract(action) => {
+            format!(
+                "action: commit contract {} {} from {} chunk(s)\n",
+                action.name(),
+                action.version(),
+                action.chunk_count()
+            ) + &list("inputs", action.inputs())
+                + &list("outputs", action.outputs())
+                + &format!("  wasm sha512: {}\n", action.contract_sha512())
+        }
+        Action::PauseContract(action) => format!(
+            "action: pause contract {} {}\n",
+            action.name(),
+            action.version()
+        ),
+        Action::ResumeContract(action) => format!(
+            "action: resume contract {} {}\n",
+            action.name(),
+            action.version()
+        ),
+    }
+}
+
+fn upload<'a>(
+    upload_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+    subscribe: bool,
+    batcher: Option<&dyn Signer>,
+    profile: &Profile,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let filename = upload_matches.value_of("filename").unwrap();
+    let key_name = upload_matches.value_of("key").or_else(|| config.key());
+    let key_dir = upload_matches
+        .value_of("key_dir")
+        .or_else(|| config.key_dir());
+    let url = upload_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+    let wasm_name = upload_matches.value_of("wasm");
+    let output = upload_matches.value_of("output");
+    let batch_file = upload_matches.value_of("batch_file");
+
+    let wait = match value_t!(upload_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    // Chunks must be submitted before the contract can be committed, so a chunk size from the
+    // configuration file is ignored when the transaction is not being submitted
+    let chunk_size = match value_t!(upload_matches, "chunk_size", usize) {
+        Ok(chunk_size) => Some(chunk_size),
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound if output.is_none() && batch_file.is_none() => {
+                config.chunk_size()
+            }
+            clap::ErrorKind::ArgumentNotFound => None,
+            _ => return Err(CliError::UserError("Chunk size must be an integer".into())),
+        },
+    };
+
+    let max_size = match value_t!(upload_matches, "max_size", usize) {
+        Ok(max_size) => max_size,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config
+                .max_contract_size()
+                .unwrap_or(DEFAULT_MAX_CONTRACT_SIZE),
+            _ => return Err(CliError::UserError("Max size must be an integer".into())),
+        },
+    };
+
+    let target = submit_target(upload_matches, config)?;
+    let signer = new_signer(key_name, key_dir)?;
+    let mut contract =
+        upload::load_contract(filename, wasm_name, upload_matches.value_of("sha512"))?;
+    if upload_matches.is_present("optimize") {
+        // The contract in an archive is the one `sabre verify` compares with the registry
+        if filename.ends_with(".scar") {
             return Err(CliError::UserError(
-                "arguments --delete and --owner conflict".into(),
+                "--optimize cannot be used with a smart contract archive; optimize the contract \
+                 before packing it"
+                    .into(),
             ));
         }
+        contract.optimize()?;
+    }
+    if let (Some(state_schema), Some(state_message)) = (
+        upload_matches.value_of("state_schema"),
+        upload_matches.value_of("state_message"),
+    ) {
+        contract.state_schema = Some(StateSchema::load(state_schema, state_message)?);
+    }
+    if contract.state_schema.is_some() && filename.ends_with(".scar") {
+        return Err(CliError::UserError(
+            "--state-schema cannot be used with a smart contract archive, since the contract \
+             would no longer match the archive; upload its contract definition file instead"
+                .into(),
+        ));
+    }
+    contract.register_state_schema()?;
+    contract.check_size(max_size);
 
-        let batch = DeleteNamespaceRegistryActionBuilder::new()
-            .with_namespace(namespace.into())
-            .into_payload_builder()?
-            .into_transaction_builder(&*signer)?
-            .into_batch_builder(&*signer)?
-            .build(&*signer)?;
+    let mut dependencies = transaction_dependencies(upload_matches)?;
+    let transaction = match chunk_size {
+        Some(chunk_size) if contract.contract.len() > chunk_size => upload_chunks(
+            client,
+            url,
+            &target,
+            wait,
+            subscribe,
+            &*signer,
+            batcher,
+            contract,
+            chunk_size,
+            &mut dependencies,
+            profile,
+        )?,
+        _ => {
+            info!(
+                "Uploading contract {} {} ({} bytes)",
+                contract.name,
+                contract.version,
+                contract.contract.len()
+            );
+            contract.into_transaction_builder(&*signer)?
+        }
+    };
 
-        submit_batches(&url, vec![batch])?
-    } else {
-        let owners = owners.ok_or_else(|| {
-            CliError::UserError("create action requires one or more --owner arguments".into())
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target,
+        wait,
+        output,
+        batch_file,
+        dependencies,
+    })
+}
+
+/// Build a single batch which deploys a contract, with each transaction depending on the one
+/// before it
+///
+/// Only the steps which differ from state are included; if there are none, nothing is returned.
+/// Contracts cannot be uploaded in chunks in the same batch, so the contract is always uploaded
+/// whole.
+fn deploy<'a>(
+    deploy_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+    quiet: bool,
+    batcher: Option<&dyn Signer>,
+) -> Result<Option<SignedBatches<'a>>, CliError> {
+    let filename = deploy_matches.value_of("filename").unwrap();
+    let key_name = deploy_matches.value_of("key").or_else(|| config.key());
+    let key_dir = deploy_matches
+        .value_of("key_dir")
+        .or_else(|| config.key_dir());
+    let url = deploy_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(deploy_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let max_size = match value_t!(deploy_matches, "max_size", usize) {
+        Ok(max_size) => max_size,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config
+                .max_contract_size()
+                .unwrap_or(DEFAULT_MAX_CONTRACT_SIZE),
+            _ => return Err(CliError::UserError("Max size must be an integer".into())),
+        },
+    };
+
+    let (read, write) = match (
+        deploy_matches.is_present("read"),
+        deploy_matches.is_present("write"),
+    ) {
+        (false, false) => (true, true),
+        permissions => permissions,
+    };
+
+    let target = submit_target(deploy_matches, config)?;
+    let dependencies = transaction_dependencies(deploy_matches)?;
+    let signer = new_signer(key_name, key_dir)?;
+
+    let mut contract = upload::load_contract(
+        filename,
+        deploy_matches.value_of("wasm"),
+        deploy_matches.value_of("sha512"),
+    )?;
+    contract.register_state_schema()?;
+    contract.check_size(max_size);
+
+    let (name, version, size) = (
+        contract.name.clone(),
+        contract.version.clone(),
+        contract.contract.len(),
+    );
+    let deployment = deploy::Deployment {
+        contract,
+        namespaces: deploy_matches
+            .values_of("namespace")
+            .unwrap()
+            .map(String::from)
+            .collect(),
+        owners: deploy_matches
+            .values_of("owner")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default(),
+        read,
+        write,
+    };
+
+    let steps = deploy::plan(client, url, deployment, &*signer)?;
+
+    if steps.is_empty() {
+        if format == OutputFormat::Human {
+            if !quiet {
+                println!("contract {} {} is already deployed", name, version);
+            }
+        } else {
+            print_document(format, &EmptySubmissionOutput { batch_ids: vec![] })?;
+        }
+        return Ok(None);
+    }
+
+    let mut transactions = Vec::with_capacity(steps.len());
+    for step in steps {
+        if format == OutputFormat::Human && !quiet {
+            match step.action {
+                deploy::Action::CreateContractRegistry => {
+                    println!("creating contract registry {}", name)
+                }
+                deploy::Action::UploadContract => {
+                    println!("uploading contract {} {} ({} bytes)", name, version, size)
+                }
+                deploy::Action::CreateNamespace(namespace) => {
+                    println!("creating namespace {}", namespace)
+                }
+                deploy::Action::GrantPermission(namespace) => println!(
+                    "setting permission for {} on {} to {}",
+                    name,
+                    namespace,
+                    permission_flags(read, write)
+                ),
+            }
+        }
+        transactions.push(step.transaction);
+    }
+
+    Ok(Some(SignedBatches {
+        batches: vec![batch::sign_ordered_batch(
+            transactions,
+            dependencies,
+            &*signer,
+            batcher,
+        )?],
+        url,
+        target,
+        wait,
+        output: deploy_matches.value_of("output"),
+        throttle: Throttle::default(),
+    }))
+}
+
+/// Submit the chunks of the contract one batch at a time, waiting for each to be committed, and
+/// return the transaction which assembles them into the contract
+///
+/// Each chunk transaction depends on the transactions in `dependencies`, and the ID of each is
+/// added to `dependencies`, so that the commit transaction depends on every chunk.
+#[allow(clippy::too_many_arguments)]
+fn upload_chunks(
+    client: &HttpClient,
+    url: &str,
+    target: &SubmitTarget,
+    wait: u64,
+    subscribe: bool,
+    signer: &dyn Signer,
+    batcher: Option<&dyn Signer>,
+    contract: upload::ContractUpload,
+    chunk_size: usize,
+    dependencies: &mut Vec<Vec<u8>>,
+    profile: &Profile,
+) -> Result<TransactionBuilder, CliError> {
+    let wait = if wait > 0 { wait } else { CHUNK_WAIT };
+    let description = format!(
+        "{} {} ({} bytes)",
+        contract.name,
+        contract.version,
+        contract.contract.len()
+    );
+
+    let chunked = contract.into_chunked_transaction_builders(chunk_size, signer)?;
+    let count = chunked.chunks.len();
+    info!("Uploading contract {} in {} chunks", description, count);
+
+    let chunk_dependencies = dependencies.clone();
+    for (index, (transaction, size)) in chunked.chunks.into_iter().enumerate() {
+        let phase = |name| format!("chunk {}/{} {}", index + 1, count, name);
+
+        let batch = profile.time(&phase("signing"), || {
+            let batch = batch::sign_batch(
+                vec![transaction.with_dependencies(chunk_dependencies.clone())],
+                signer,
+                batcher,
+            )?;
+            for transaction in batch.transactions() {
+                dependencies.push(batch::from_hex(transaction.header_signature())?);
+            }
+            Ok::<_, CliError>(batch)
         })?;
+        profile.report_batches(std::slice::from_ref(&batch));
 
-        let batch = CreateNamespaceRegistryActionBuilder::new()
-            .with_namespace(namespace.into())
-            .with_owners(owners)
-            .into_payload_builder()?
-            .into_transaction_builder(&*signer)?
-            .into_batch_builder(&*signer)?
-            .build(&*signer)?;
+        let submission = profile.time(&phase("submit"), || {
+            submit_batches(client, url, target, vec![batch])
+        })?;
+        let status = profile.time(&phase("wait"), || {
+            if subscribe {
+                submit::subscribe_batch_status(client, target, &submission, wait)
+            } else {
+                submit::poll_batch_status(client, target, &submission, wait)
+            }
+        })?;
+        let not_committed = |msg| {
+            format!(
+                "Chunk {} of {} was not committed: {}",
+                index + 1,
+                count,
+                msg
+            )
+        };
+        status.check().map_err(|err| match err {
+            CliError::InvalidBatchError(msg) => CliError::InvalidBatchError(not_committed(msg)),
+            CliError::WaitTimeoutError(msg) => CliError::WaitTimeoutError(not_committed(msg)),
+            err => err,
+        })?;
+
+        info!(
+            "Committed chunk {} of {} ({} bytes)",
+            index + 1,
+            count,
+            size
+        );
+    }
+
+    info!("Committing contract {}", description);
+    Ok(chunked.commit)
+}
+
+fn execute<'a>(
+    exec_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let contract = exec_matches.value_of("contract").unwrap();
+    let payload = exec_matches.value_of("payload").unwrap();
+    let key_name = exec_matches.value_of("key").or_else(|| config.key());
+    let key_dir = exec_matches
+        .value_of("key_dir")
+        .or_else(|| config.key_dir());
+    let url = exec_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
 
-        submit_batches(&url, vec![batch])?
+    let wait = match value_t!(exec_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
     };
 
-    Ok((batch_link, wait))
+    let (name, version) = match contract.split(':').collect::<Vec<_>>() {
+        ref v if (v.len() == 1 || v.len() == 2) && v[0].is_empty() => Err(CliError::UserError(
+            "contract name must be specified".into(),
+        )),
+        ref v if v.len() == 1 || v.len() == 2 && v[1].is_empty() => Ok((v[0], "latest")),
+        ref v if v.len() == 2 => Ok((v[0], v[1])),
+        _ => Err(CliError::UserError(
+            "malformed contract argument, may contain at most one ':'".into(),
+        )),
+    }?;
+
+    let inputs = exec_matches
+        .values_of("inputs")
+        .map(|values| values.map(String::from).collect::<Vec<_>>());
+    let outputs = exec_matches
+        .values_of("outputs")
+        .map(|values| values.map(String::from).collect::<Vec<_>>());
+    let (version, inputs, outputs) = match (inputs, outputs) {
+        (Some(inputs), Some(outputs)) => {
+            if exec_matches.is_present("address_hint") {
+                warn!("--address-hint is ignored when both --inputs and --outputs are given");
+            }
+            (version.to_string(), inputs, outputs)
+        }
+        (inputs, outputs) => {
+            let hints = exec_matches
+                .values_of("address_hint")
+                .map(|values| values.collect::<Vec<_>>())
+                .unwrap_or_default();
+            let contract = registered_contract(client, url, name, version)?;
+            let (derived_inputs, derived_outputs) =
+                narrow_to_hints(contract.inputs(), contract.outputs(), &hints)?;
+            let inputs = inputs.unwrap_or_else(|| {
+                info!("Using inputs {}", derived_inputs.join(", "));
+                derived_inputs
+            });
+            let outputs = outputs.unwrap_or_else(|| {
+                info!("Using outputs {}", derived_outputs.join(", "));
+                derived_outputs
+            });
+            (contract.version().to_string(), inputs, outputs)
+        }
+    };
+
+    let payload_format = exec_matches
+        .value_of("payload_format")
+        .unwrap_or("raw")
+        .parse::<PayloadFormat>()?;
+    let descriptor_set = exec_matches
+        .value_of("descriptor_set")
+        .map(load_bytes_from_file)
+        .transpose()?;
+
+    let contract_payload = match payload {
+        "-" => {
+            let mut bytes = Vec::new();
+            std::io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        }
+        _ => load_bytes_from_file(payload)?,
+    };
+    let contract_payload =
+        payload::encode_payload(contract_payload, &payload_format, descriptor_set.as_deref())?;
+    let signer = new_signer(key_name, key_dir)?;
+    let transaction = ExecuteContractActionBuilder::new()
+        .with_name(name.into())
+        .with_version(version)
+        .with_inputs(inputs)
+        .with_outputs(outputs)
+        .with_payload(contract_payload)
+        .into_payload_builder()?
+        .into_transaction_builder(&*signer)?;
+
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target: submit_target(exec_matches, config)?,
+        wait,
+        output: exec_matches.value_of("output"),
+        batch_file: exec_matches.value_of("batch_file"),
+        dependencies: transaction_dependencies(exec_matches)?,
+    })
+}
+
+/// Fetch the contract an exec transaction runs, for the inputs and outputs it registered
+///
+/// Sabre has no notion of a latest version, so when no version is given the version most
+/// recently uploaded to the contract's registry is used.
+fn registered_contract(
+    client: &HttpClient,
+    url: &str,
+    name: &str,
+    version: &str,
+) -> Result<Contract, CliError> {
+    if version != "latest" {
+        return get_contract(client, url, name, version);
+    }
+
+    let registry = get_contract_registry(client, url, name)?;
+    let latest = registry.versions().last().ok_or_else(|| {
+        CliError::UserError(format!("contract '{}' has no uploaded versions", name))
+    })?;
+    info!("Using version {} of contract '{}'", latest.version(), name);
+    get_contract(client, url, name, latest.version())
+}
+
+/// Narrow a contract's registered inputs and outputs to the addresses or namespaces given as
+/// hints, each of which must fall under a registered input or output
+///
+/// Without hints the registered inputs and outputs are returned unchanged.
+fn narrow_to_hints(
+    inputs: &[String],
+    outputs: &[String],
+    hints: &[&str],
+) -> Result<(Vec<String>, Vec<String>), CliError> {
+    if hints.is_empty() {
+        return Ok((inputs.to_vec(), outputs.to_vec()));
+    }
+
+    let narrow = |registered: &[String]| {
+        hints
+            .iter()
+            .filter(|hint| {
+                registered
+                    .iter()
+                    .any(|prefix| hint.starts_with(prefix.as_str()))
+            })
+            .map(|hint| hint.to_string())
+            .collect::<Vec<_>>()
+    };
+    let narrowed_inputs = narrow(inputs);
+    let narrowed_outputs = narrow(outputs);
+
+    if let Some(hint) = hints.iter().find(|hint| {
+        !narrowed_inputs.iter().any(|input| input == *hint)
+            && !narrowed_outputs.iter().any(|output| output == *hint)
+    }) {
+        return Err(CliError::UserError(format!(
+            "Address hint {} is not under any of the contract's inputs or outputs",
+            hint
+        )));
+    }
+
+    Ok((narrowed_inputs, narrowed_outputs))
+}
+
+/// Execute the contract locally against the state of the REST API instead of submitting it
+#[cfg(feature = "dry-run")]
+fn dry_run(
+    exec_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    if format == OutputFormat::Csv {
+        return Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        ));
+    }
+
+    let pending = execute(exec_matches, config, client)?;
+    let transaction = pending.transaction.build(&*pending.signer)?;
+    let result = dry_run::execute(client, pending.url, &transaction)?;
+
+    if format != OutputFormat::Human {
+        return print_document(
+            format,
+            &DryRunOutput {
+                transaction_id: transaction.header_signature(),
+                changes: result
+                    .changes
+                    .iter()
+                    .map(|change| match change {
+                        dry_run::StateChange::Set { address, value } => StateChangeOutput {
+                            action: "set",
+                            address,
+                            state_type: None,
+                            data: Some(base64::encode(value)),
+                            decoded: None,
+                        },
+                        dry_run::StateChange::Delete { address } => StateChangeOutput {
+                            action: "delete",
+                            address,
+                            state_type: None,
+                            data: None,
+                            decoded: None,
+                        },
+                    })
+                    .collect(),
+                events: result
+                    .events
+                    .iter()
+                    .map(|event| EventOutput {
+                        event_type: &event.event_type,
+                        attributes: event
+                            .attributes
+                            .iter()
+                            .map(|(key, value)| EventAttributeOutput { key, value })
+                            .collect(),
+                        data: base64::encode(&event.data),
+                    })
+                    .collect(),
+                receipt_data: result.receipt_data.iter().map(base64::encode).collect(),
+            },
+        );
+    }
+
+    println!(
+        "dry run of transaction {} succeeded; nothing was submitted",
+        transaction.header_signature()
+    );
+    if result.changes.is_empty() {
+        println!("no state changes");
+    }
+    for change in &result.changes {
+        match change {
+            dry_run::StateChange::Set { address, value } => {
+                println!("set {}: {}", address, base64::encode(value))
+            }
+            dry_run::StateChange::Delete { address } => println!("delete {}", address),
+        }
+    }
+    for event in &result.events {
+        let attributes = event
+            .attributes
+            .iter()
+            .map(|(key, value)| format!(" {}={}", key, value))
+            .collect::<String>();
+        println!(
+            "event {}{}: {}",
+            event.event_type,
+            attributes,
+            base64::encode(&event.data)
+        );
+    }
+    for data in &result.receipt_data {
+        println!("receipt data: {}", base64::encode(data));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "dry-run"))]
+fn dry_run(
+    _exec_matches: &clap::ArgMatches,
+    _config: &CliConfig,
+    _client: &HttpClient,
+    _format: OutputFormat,
+) -> Result<(), CliError> {
+    Err(CliError::UserError(
+        "--dry-run requires sabre to be built with the dry-run feature".into(),
+    ))
+}
+
+fn namespace_registry<'a>(
+    ns_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let namespace = ns_matches.value_of("namespace").unwrap();
+
+    let key_name = ns_matches.value_of("key").or_else(|| config.key());
+    let key_dir = ns_matches.value_of("key_dir").or_else(|| config.key_dir());
+
+    let url = ns_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(ns_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let signer = new_signer(key_name, key_dir)?;
+
+    let owners = ns_matches
+        .values_of("owner")
+        .map(|values| values.map(|v| v.into()).collect());
+
+    let transaction = if ns_matches.is_present("update") {
+        let owners = owners.ok_or_else(|| {
+            CliError::UserError("update action requires one or more --owner arguments".into())
+        })?;
+
+        UpdateNamespaceRegistryOwnersActionBuilder::new()
+            .with_namespace(namespace.into())
+            .with_owners(owners)
+            .into_payload_builder()?
+            .into_transaction_builder(&*signer)?
+    } else if ns_matches.is_present("delete") {
+        if ns_matches.is_present("owner") {
+            return Err(CliError::UserError(
+                "arguments --delete and --owner conflict".into(),
+            ));
+        }
+
+        if !ns_matches.is_present("yes") && is_submitted(ns_matches) {
+            let registry = get_namespace_registry(client, url, namespace)?;
+            confirm_delete(
+                &describe_namespace_registry(&registry),
+                &format!("namespace registry '{}'", namespace),
+            )?;
+        }
+
+        DeleteNamespaceRegistryActionBuilder::new()
+            .with_namespace(namespace.into())
+            .into_payload_builder()?
+            .into_transaction_builder(&*signer)?
+    } else {
+        let owners = owners.ok_or_else(|| {
+            CliError::UserError("create action requires one or more --owner arguments".into())
+        })?;
+
+        CreateNamespaceRegistryActionBuilder::new()
+            .with_namespace(namespace.into())
+            .with_owners(owners)
+            .into_payload_builder()?
+            .into_transaction_builder(&*signer)?
+    };
+
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target: submit_target(ns_matches, config)?,
+        wait,
+        output: ns_matches.value_of("output"),
+        batch_file: ns_matches.value_of("batch_file"),
+        dependencies: transaction_dependencies(ns_matches)?,
+    })
+}
+
+/// Add and remove public keys in the sawtooth.swa.administrators setting, based on its value in
+/// state
+///
+/// The setting is changed by a Sawtooth Settings proposal, so the signer must be one of the
+/// network's sawtooth.settings.vote.authorized_keys.
+fn set_admins<'a>(
+    admin_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let key_name = admin_matches.value_of("key").or_else(|| config.key());
+    let key_dir = admin_matches
+        .value_of("key_dir")
+        .or_else(|| config.key_dir());
+
+    let url = admin_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(admin_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let added = admin_matches
+        .values_of("add")
+        .map(|values| values.collect::<Vec<_>>())
+        .unwrap_or_default();
+    let removed = admin_matches
+        .values_of("remove")
+        .map(|values| values.collect::<Vec<_>>())
+        .unwrap_or_default();
+    if let Some(admin) = added.iter().find(|admin| removed.contains(admin)) {
+        return Err(CliError::UserError(format!(
+            "{} cannot be both added and removed",
+            admin
+        )));
+    }
+    if let Some(admin) = added.iter().find(|admin| !is_public_key(admin)) {
+        return Err(CliError::UserError(format!(
+            "{} is not a hex-encoded public key",
+            admin
+        )));
+    }
+
+    let current = settings::get_setting(client, url, ADMINISTRATORS_SETTING_KEY)?
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|admin| !admin.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    for admin in &added {
+        if current.iter().any(|current| current == admin) {
+            warn!("{} is already an administrator", admin);
+        }
+    }
+    for admin in &removed {
+        if !current.iter().any(|current| current == admin) {
+            warn!("{} is not an administrator", admin);
+        }
+    }
+
+    let mut admins = current
+        .iter()
+        .filter(|admin| !removed.contains(&admin.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    for admin in added {
+        if !admins.iter().any(|current| current == admin) {
+            admins.push(admin.into());
+        }
+    }
+
+    if admins.is_empty() {
+        return Err(CliError::UserError(
+            "At least one administrator must remain".into(),
+        ));
+    }
+    if admins == current {
+        return Err(CliError::UserError("Administrators are unchanged".into()));
+    }
+
+    if let Some(threshold) =
+        settings::get_setting(client, url, settings::APPROVAL_THRESHOLD_SETTING_KEY)?
+    {
+        if matches!(threshold.trim().parse::<u32>(), Ok(threshold) if threshold > 1) {
+            warn!(
+                "The approval threshold is {}; the administrators change only once enough \
+                 authorized keys vote for the proposal",
+                threshold.trim()
+            );
+        }
+    }
+
+    info!("Setting administrators to {}", admins.join(", "));
+
+    let signer = new_signer(key_name, key_dir)?;
+    let transaction = settings::propose_setting(ADMINISTRATORS_SETTING_KEY, &admins.join(","))?;
+
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target: submit_target(admin_matches, config)?,
+        wait,
+        output: admin_matches.value_of("output"),
+        batch_file: admin_matches.value_of("batch_file"),
+        dependencies: transaction_dependencies(admin_matches)?,
+    })
+}
+
+/// Add and remove owners of a namespace registry, based on its owners in state
+///
+/// The registry's permissions are unchanged. Only an owner or an administrator can update the
+/// owners, and a namespace registry must keep at least one owner.
+fn namespace_update_owners<'a>(
+    update_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let namespace = update_matches.value_of("namespace").unwrap();
+
+    let key_name = update_matches.value_of("key").or_else(|| config.key());
+    let key_dir = update_matches
+        .value_of("key_dir")
+        .or_else(|| config.key_dir());
+
+    let url = update_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(update_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let signer = new_signer(key_name, key_dir)?;
+    let public_key = to_hex(
+        signer
+            .public_key()
+            .map_err(|err| CliError::SigningError(err.to_string()))?
+            .as_slice(),
+    );
+
+    let added = update_matches
+        .values_of("add")
+        .map(|values| values.collect::<Vec<_>>())
+        .unwrap_or_default();
+    let removed = update_matches
+        .values_of("remove")
+        .map(|values| values.collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let registry = get_namespace_registry(client, url, namespace)?;
+    let owners = update_owners(
+        &format!("namespace '{}'", namespace),
+        registry.owners(),
+        &added,
+        &removed,
+        &public_key,
+    )?;
+
+    info!(
+        "Updating owners of namespace '{}' to {}",
+        namespace,
+        owners.join(", ")
+    );
+
+    let transaction = UpdateNamespaceRegistryOwnersActionBuilder::new()
+        .with_namespace(namespace.into())
+        .with_owners(owners)
+        .into_payload_builder()?
+        .into_transaction_builder(&*signer)?;
+
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target: submit_target(update_matches, config)?,
+        wait,
+        output: update_matches.value_of("output"),
+        batch_file: update_matches.value_of("batch_file"),
+        dependencies: transaction_dependencies(update_matches)?,
+    })
+}
+
+/// Add and remove owners of a contract registry, based on its owners in state
+///
+/// The registry's versions are unchanged. Only an owner or an administrator can update the
+/// owners, and a contract registry must keep at least one owner.
+fn contract_registry_update_owners<'a>(
+    cr_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let (update_matches, adding) = match cr_matches.subcommand() {
+        ("add-owner", Some(update_matches)) => (update_matches, true),
+        ("remove-owner", Some(update_matches)) => (update_matches, false),
+        _ => return Err(CliError::UserError("Subcommand required".into())),
+    };
+    let name = update_matches.value_of("name").unwrap();
+
+    let key_name = update_matches.value_of("key").or_else(|| config.key());
+    let key_dir = update_matches
+        .value_of("key_dir")
+        .or_else(|| config.key_dir());
+
+    let url = update_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(update_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let signer = new_signer(key_name, key_dir)?;
+    let public_key = to_hex(
+        signer
+            .public_key()
+            .map_err(|err| CliError::SigningError(err.to_string()))?
+            .as_slice(),
+    );
+
+    let changed = update_matches
+        .values_of("owner")
+        .map(|values| values.collect::<Vec<_>>())
+        .unwrap_or_default();
+    let (added, removed) = if adding {
+        (changed, vec![])
+    } else {
+        (vec![], changed)
+    };
+
+    let registry = get_contract_registry(client, url, name)?;
+    let owners = update_owners(
+        &format!("contract registry '{}'", name),
+        registry.owners(),
+        &added,
+        &removed,
+        &public_key,
+    )?;
+
+    info!(
+        "Updating owners of contract registry '{}' to {}",
+        name,
+        owners.join(", ")
+    );
+
+    let transaction = UpdateContractRegistryOwnersActionBuilder::new()
+        .with_name(name.into())
+        .with_owners(owners)
+        .into_payload_builder()?
+        .into_transaction_builder(&*signer)?;
+
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target: submit_target(update_matches, config)?,
+        wait,
+        output: update_matches.value_of("output"),
+        batch_file: update_matches.value_of("batch_file"),
+        dependencies: transaction_dependencies(update_matches)?,
+    })
+}
+
+/// Apply the owners added and removed to the current owners of a registry
+///
+/// `registry` describes the registry in messages, e.g. "namespace 'abcdef'". Changes which have no
+/// effect are warned about, as is a signer which is not an owner, since the update will be
+/// rejected unless the signer is an administrator.
+fn update_owners(
+    registry: &str,
+    current: &[String],
+    added: &[&str],
+    removed: &[&str],
+    public_key: &str,
+) -> Result<Vec<String>, CliError> {
+    if let Some(owner) = added.iter().find(|owner| removed.contains(owner)) {
+        return Err(CliError::UserError(format!(
+            "{} cannot be both added and removed",
+            owner
+        )));
+    }
+
+    let is_owner = |owners: &[String], key: &str| owners.iter().any(|owner| owner == key);
+
+    if !is_owner(current, public_key) {
+        warn!(
+            "Signer {} is not an owner of {}; the update will be rejected unless the signer is \
+             an administrator",
+            public_key, registry
+        );
+    }
+    for owner in added {
+        if is_owner(current, owner) {
+            warn!("{} is already an owner of {}", owner, registry);
+        }
+    }
+    for owner in removed {
+        if !is_owner(current, owner) {
+            warn!("{} is not an owner of {}", owner, registry);
+        }
+    }
+
+    let mut owners = current
+        .iter()
+        .filter(|owner| !removed.contains(&owner.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    for owner in added {
+        if !is_owner(&owners, owner) {
+            owners.push((*owner).into());
+        }
+    }
+
+    if owners.is_empty() {
+        return Err(CliError::UserError(format!(
+            "At least one owner of {} must remain",
+            registry
+        )));
+    }
+    if owners == current {
+        return Err(CliError::UserError(format!(
+            "Owners of {} are unchanged",
+            registry
+        )));
+    }
+    if is_owner(current, public_key) && !is_owner(&owners, public_key) {
+        warn!(
+            "Signer {} will no longer be an owner of {}",
+            public_key, registry
+        );
+    }
+
+    Ok(owners)
+}
+
+fn namespace_query(
+    ns_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    match ns_matches.subcommand() {
+        ("list", Some(matches)) => {
+            let url = matches
+                .value_of("url")
+                .or_else(|| config.url())
+                .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+            let prefix = matches.value_of("prefix").unwrap_or("");
+
+            let registries = list_namespace_registries(client, url, prefix)?;
+
+            if format == OutputFormat::Json || format == OutputFormat::Yaml {
+                let namespaces = registries
+                    .iter()
+                    .map(NamespaceRegistryOutput::from)
+                    .collect::<Vec<_>>();
+
+                return print_document(format, &namespaces);
+            }
+
+            let mut data = vec![
+                // Headers
+                vec![
+                    "NAMESPACE".to_string(),
+                    "OWNERS".to_string(),
+                    "PERMISSIONS".to_string(),
+                ],
+            ];
+            for registry in registries {
+                let permissions = registry
+                    .permissions()
+                    .iter()
+                    .map(|permission| {
+                        format!(
+                            "{}({})",
+                            permission.contract_name(),
+                            permission_flags(permission.read(), permission.write())
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                data.push(vec![
+                    registry.namespace().to_string(),
+                    registry.owners().join(", "),
+                    permissions,
+                ]);
+            }
+
+            if format == OutputFormat::Csv {
+                print_csv(data);
+            } else {
+                print_table(data);
+            }
+
+            Ok(())
+        }
+        ("show", Some(matches)) => {
+            let url = matches
+                .value_of("url")
+                .or_else(|| config.url())
+                .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+            let namespace = matches.value_of("namespace").unwrap();
+
+            let registry = get_namespace_registry(client, url, namespace)?;
+
+            match format {
+                OutputFormat::Json | OutputFormat::Yaml => {
+                    return print_document(format, &NamespaceRegistryOutput::from(&registry))
+                }
+                OutputFormat::Csv => {
+                    return Err(CliError::UserError(
+                        "csv format is only supported by list commands".into(),
+                    ))
+                }
+                OutputFormat::Human => (),
+            }
+
+            print!("{}", describe_namespace_registry(&registry));
+
+            Ok(())
+        }
+        _ => Err(CliError::UserError("Invalid Subcommand".into())),
+    }
+}
+
+/// Fetch the namespace registries in state whose namespace starts with `prefix`, sorted by
+/// namespace
+fn list_namespace_registries(
+    client: &HttpClient,
+    url: &str,
+    prefix: &str,
+) -> Result<Vec<NamespaceRegistry>, CliError> {
+    let registry_lists =
+        state::get_state_with_prefix(client, url, NAMESPACE_REGISTRY_ADDRESS_PREFIX)?
+            .into_iter()
+            .map(|entry| {
+                base64::decode(entry.data)
+                    .map_err(|_| CliError::UserError("Unable to decode state".into()))
+                    .and_then(|bytes| {
+                        NamespaceRegistryList::from_bytes(&bytes)
+                            .map_err(CliError::ProtoConversionError)
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+    let mut registries = registry_lists
+        .iter()
+        .flat_map(|registry_list| registry_list.registries())
+        .filter(|registry| registry.namespace().starts_with(prefix))
+        .cloned()
+        .collect::<Vec<_>>();
+    registries.sort_by(|a, b| a.namespace().cmp(b.namespace()));
+
+    Ok(registries)
+}
+
+/// List the permissions of every namespace registry, one row per namespace and contract
+///
+/// Namespaces without permissions are not listed.
+fn permission_list(
+    list_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    let url = list_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+    let prefix = list_matches.value_of("prefix").unwrap_or("");
+    let contract = list_matches.value_of("contract");
+
+    let registries = list_namespace_registries(client, url, prefix)?;
+    let permissions = registries
+        .iter()
+        .flat_map(|registry| {
+            registry
+                .permissions()
+                .iter()
+                .map(move |permission| NamespacePermissionOutput {
+                    namespace: registry.namespace(),
+                    contract_name: permission.contract_name(),
+                    read: permission.read(),
+                    write: permission.write(),
+                })
+        })
+        .filter(|permission| match contract {
+            Some(name) => permission.contract_name == name,
+            None => true,
+        })
+        .collect::<Vec<_>>();
+
+    if format == OutputFormat::Json || format == OutputFormat::Yaml {
+        return print_document(format, &permissions);
+    }
+
+    let mut data = vec![vec![
+        "NAMESPACE".to_string(),
+        "CONTRACT".to_string(),
+        "READ".to_string(),
+        "WRITE".to_string(),
+    ]];
+    for permission in permissions {
+        data.push(vec![
+            permission.namespace.to_string(),
+            permission.contract_name.to_string(),
+            permission.read.to_string(),
+            permission.write.to_string(),
+        ]);
+    }
+
+    if format == OutputFormat::Csv {
+        print_csv(data);
+    } else {
+        print_table(data);
+    }
+
+    Ok(())
+}
+
+/// Format read and write permissions as "r", "w", "rw", or "-"
+fn permission_flags(read: bool, write: bool) -> &'static str {
+    match (read, write) {
+        (true, true) => "rw",
+        (true, false) => "r",
+        (false, true) => "w",
+        (false, false) => "-",
+    }
+}
+
+fn namespace_permission<'a>(
+    perm_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let namespace = perm_matches.value_of("namespace").unwrap();
+    let contract = perm_matches.value_of("contract").unwrap();
+    let key_name = perm_matches.value_of("key").or_else(|| config.key());
+    let key_dir = perm_matches
+        .value_of("key_dir")
+        .or_else(|| config.key_dir());
+    let url = perm_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(perm_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let signer = new_signer(key_name, key_dir)?;
+
+    let transaction = if perm_matches.is_present("delete") {
+        DeleteNamespaceRegistryPermissionActionBuilder::new()
+            .with_namespace(namespace.into())
+            .into_payload_builder()?
+            .into_transaction_builder(&*signer)?
+    } else {
+        let read = perm_matches.is_present("read");
+        let write = perm_matches.is_present("write");
+
+        if !(read || write) {
+            return Err(CliError::UserError("no permissions provided".into()));
+        }
+
+        CreateNamespaceRegistryPermissionActionBuilder::new()
+            .with_namespace(namespace.into())
+            .with_contract_name(contract.into())
+            .with_read(read)
+            .with_write(write)
+            .into_payload_builder()?
+            .into_transaction_builder(&*signer)?
+    };
+
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target: submit_target(perm_matches, config)?,
+        wait,
+        output: perm_matches.value_of("output"),
+        batch_file: perm_matches.value_of("batch_file"),
+        dependencies: transaction_dependencies(perm_matches)?,
+    })
+}
+
+/// Build a batch which sets the permissions listed in a permissions file
+///
+/// Only the grants which differ from state are included; if there are none, nothing is returned.
+fn permission_apply<'a>(
+    apply_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+    quiet: bool,
+    batcher: Option<&dyn Signer>,
+) -> Result<Option<SignedBatches<'a>>, CliError> {
+    let filename = apply_matches.value_of("filename").unwrap();
+    let key_name = apply_matches.value_of("key").or_else(|| config.key());
+    let key_dir = apply_matches
+        .value_of("key_dir")
+        .or_else(|| config.key_dir());
+    let url = apply_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(apply_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let target = submit_target(apply_matches, config)?;
+    let signer = new_signer(key_name, key_dir)?;
+
+    let changes = permissions::changes(client, url, permissions::load(filename)?)?;
+
+    if changes.is_empty() {
+        if format == OutputFormat::Human {
+            if !quiet {
+                println!("permissions in {} are already set", filename);
+            }
+        } else {
+            print_document(format, &EmptySubmissionOutput { batch_ids: vec![] })?;
+        }
+        return Ok(None);
+    }
+
+    let mut transactions = Vec::with_capacity(changes.len());
+    for grant in changes {
+        if format == OutputFormat::Human && !quiet {
+            if grant.is_revoke() {
+                println!(
+                    "removing permission for {} on {}",
+                    grant.contract, grant.namespace
+                );
+            } else {
+                println!(
+                    "setting permission for {} on {} to {}",
+                    grant.contract,
+                    grant.namespace,
+                    permission_flags(grant.read, grant.write)
+                );
+            }
+        }
+
+        let transaction = if grant.is_revoke() {
+            DeleteNamespaceRegistryPermissionActionBuilder::new()
+                .with_namespace(grant.namespace)
+                .with_contract_name(grant.contract)
+                .into_payload_builder()?
+                .into_transaction_builder(&*signer)?
+        } else {
+            CreateNamespaceRegistryPermissionActionBuilder::new()
+                .with_namespace(grant.namespace)
+                .with_contract_name(grant.contract)
+                .with_read(grant.read)
+                .with_write(grant.write)
+                .into_payload_builder()?
+                .into_transaction_builder(&*signer)?
+        };
+        transactions.push(transaction);
+    }
+
+    Ok(Some(SignedBatches {
+        batches: vec![batch::sign_batch(transactions, &*signer, batcher)?],
+        url,
+        target,
+        wait,
+        output: apply_matches.value_of("output"),
+        throttle: Throttle::default(),
+    }))
+}
+
+fn contract_registry<'a>(
+    cr_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let name = cr_matches.value_of("name").unwrap();
+
+    let key_name = cr_matches.value_of("key").or_else(|| config.key());
+    let key_dir = cr_matches.value_of("key_dir").or_else(|| config.key_dir());
+
+    let url = cr_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = value_t!(cr_matches, "wait", u64).unwrap_or_else(|_| config.wait().unwrap_or(0));
+
+    let signer = new_signer(key_name, key_dir)?;
+
+    let owners = cr_matches
+        .values_of("owner")
+        .map(|values| values.map(|v| v.into()).collect());
+
+    let transaction = if cr_matches.is_present("update") {
+        let owners = owners.ok_or_else(|| {
+            CliError::UserError("update action requires one or more --owner arguments".into())
+        })?;
+
+        UpdateContractRegistryOwnersActionBuilder::new()
+            .with_name(name.into())
+            .with_owners(owners)
+            .into_payload_builder()?
+            .into_transaction_builder(&*signer)?
+    } else if cr_matches.is_present("delete") {
+        if cr_matches.is_present("owner") {
+            return Err(CliError::UserError(
+                "arguments --delete and --owner conflict".into(),
+            ));
+        }
+
+        if !cr_matches.is_present("yes") && is_submitted(cr_matches) {
+            let registry = get_contract_registry(client, url, name)?;
+            confirm_delete(
+                &describe_contract_registry(&registry),
+                &format!("contract registry '{}'", name),
+            )?;
+        }
+
+        DeleteContractRegistryActionBuilder::new()
+            .with_name(name.into())
+            .into_payload_builder()?
+            .into_transaction_builder(&*signer)?
+    } else {
+        let owners = owners.ok_or_else(|| {
+            CliError::UserError("create action requires one or more --owner arguments".into())
+        })?;
+
+        let max_memory_pages = match value_t!(cr_matches, "max_memory_pages", u32) {
+            Ok(max_memory_pages) => max_memory_pages,
+            Err(err) => match err.kind {
+                clap::ErrorKind::ArgumentNotFound => 0,
+                _ => {
+                    return Err(CliError::UserError(
+                        "Max memory pages must be an integer".into(),
+                    ))
+                }
+            },
+        };
+
+        CreateContractRegistryActionBuilder::new()
+            .with_name(name.into())
+            .with_owners(owners)
+            .with_max_memory_pages(max_memory_pages)
+            .into_payload_builder()?
+            .into_transaction_builder(&*signer)?
+    };
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target: submit_target(cr_matches, config)?,
+        wait,
+        output: cr_matches.value_of("output"),
+        batch_file: cr_matches.value_of("batch_file"),
+        dependencies: transaction_dependencies(cr_matches)?,
+    })
+}
+
+fn contract_registry_query(
+    cr_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    match cr_matches.subcommand() {
+        ("show", Some(matches)) => {
+            let url = matches
+                .value_of("url")
+                .or_else(|| config.url())
+                .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+            let name = matches.value_of("name").unwrap();
+
+            let registry = get_contract_registry(client, url, name)?;
+
+            match format {
+                OutputFormat::Json | OutputFormat::Yaml => {
+                    return print_document(format, &ContractRegistryDetailOutput::from(&registry))
+                }
+                OutputFormat::Csv => {
+                    return Err(CliError::UserError(
+                        "csv format is only supported by list commands".into(),
+                    ))
+                }
+                OutputFormat::Human => (),
+            }
+
+            print!("{}", describe_contract_registry(&registry));
+
+            Ok(())
+        }
+        _ => Err(CliError::UserError("Invalid Subcommand".into())),
+    }
+}
+
+fn smart_permission<'a>(
+    sp_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let url = sp_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(sp_matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let (transaction, signer) = match sp_matches.subcommand() {
+        ("create", Some(m)) => {
+            let org_id = m.value_of("org_id").unwrap();
+            let name = m.value_of("name").unwrap();
+            let filename = m.value_of("filename").unwrap();
+            let key = m.value_of("key").or_else(|| config.key());
+            let key_dir = m.value_of("key_dir").or_else(|| config.key_dir());
+
+            let function = load_bytes_from_file(filename)?;
+
+            let signer = new_signer(key, key_dir)?;
+            let transaction = CreateSmartPermissionActionBuilder::new()
+                .with_name(name.into())
+                .with_org_id(org_id.into())
+                .with_function(function)
+                .into_payload_builder()?
+                .into_transaction_builder(&*signer)?;
+            (transaction, signer)
+        }
+        ("update", Some(m)) => {
+            let org_id = m.value_of("org_id").unwrap();
+            let name = m.value_of("name").unwrap();
+            let filename = m.value_of("filename").unwrap();
+            let key = m.value_of("key").or_else(|| config.key());
+            let key_dir = m.value_of("key_dir").or_else(|| config.key_dir());
+
+            let function = load_bytes_from_file(filename)?;
+
+            let signer = new_signer(key, key_dir)?;
+            let transaction = UpdateSmartPermissionActionBuilder::new()
+                .with_name(name.to_string())
+                .with_org_id(org_id.to_string())
+                .with_function(function)
+                .into_payload_builder()?
+                .into_transaction_builder(&*signer)?;
+            (transaction, signer)
+        }
+        ("delete", Some(m)) => {
+            let org_id = m.value_of("org_id").unwrap();
+            let name = m.value_of("name").unwrap();
+            let key = m.value_of("key").or_else(|| config.key());
+            let key_dir = m.value_of("key_dir").or_else(|| config.key_dir());
+
+            if !m.is_present("yes") && is_submitted(sp_matches) {
+                let smart_permission = get_smart_permission(client, url, org_id, name)?;
+                confirm_delete(
+                    &describe_smart_permission(&smart_permission),
+                    &format!("smart permission '{}'", name),
+                )?;
+            }
+
+            let signer = new_signer(key, key_dir)?;
+            let transaction = DeleteSmartPermissionActionBuilder::new()
+                .with_name(name.to_string())
+                .with_org_id(org_id.to_string())
+                .into_payload_builder()?
+                .into_transaction_builder(&*signer)?;
+            (transaction, signer)
+        }
+        _ => {
+            return Err(CliError::UserError(
+                "Unrecognized smart permission subcommand".into(),
+            ));
+        }
+    };
+
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target: submit_target(sp_matches, config)?,
+        wait,
+        output: sp_matches.value_of("output"),
+        batch_file: sp_matches.value_of("batch_file"),
+        dependencies: transaction_dependencies(sp_matches)?,
+    })
+}
+
+/// Delete, pause or resume a version of a contract
+///
+/// Deleting must be signed by an owner of the contract registry; pausing and resuming may also
+/// be signed by an administrator.
+fn contract_update<'a>(
+    contract_matches: &'a clap::ArgMatches,
+    config: &'a CliConfig,
+    client: &HttpClient,
+) -> Result<PendingTransaction<'a>, CliError> {
+    let (subcommand, matches) = match contract_matches.subcommand() {
+        (subcommand, Some(matches)) => (subcommand, matches),
+        _ => return Err(CliError::UserError("Subcommand required".into())),
+    };
+    let (name, version) = contract_name_version(matches)?;
+
+    let key_name = matches.value_of("key").or_else(|| config.key());
+    let key_dir = matches.value_of("key_dir").or_else(|| config.key_dir());
+
+    let url = matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let wait = match value_t!(matches, "wait", u64) {
+        Ok(wait) => wait,
+        Err(err) => match err.kind {
+            clap::ErrorKind::ArgumentNotFound => config.wait().unwrap_or(0),
+            _ => return Err(CliError::UserError("Wait must be an integer".into())),
+        },
+    };
+
+    let signer = new_signer(key_name, key_dir)?;
+    let payload_builder = match subcommand {
+        "delete" => {
+            if !matches.is_present("yes") && is_submitted(matches) {
+                let contract = get_contract(client, url, name, version)?;
+                confirm_delete(
+                    &describe_contract(&contract),
+                    &format!("contract '{}:{}'", name, version),
+                )?;
+            }
+
+            DeleteContractActionBuilder::new()
+                .with_name(name.into())
+                .with_version(version.into())
+                .into_payload_builder()?
+        }
+        "pause" => PauseContractActionBuilder::new()
+            .with_name(name.into())
+            .with_version(version.into())
+            .into_payload_builder()?,
+        "resume" => ResumeContractActionBuilder::new()
+            .with_name(name.into())
+            .with_version(version.into())
+            .into_payload_builder()?,
+        _ => {
+            return Err(CliError::UserError(
+                "Unrecognized contract subcommand".into(),
+            ));
+        }
+    };
+    let transaction = payload_builder.into_transaction_builder(&*signer)?;
+
+    Ok(PendingTransaction {
+        transaction,
+        signer,
+        url,
+        target: submit_target(matches, config)?,
+        wait,
+        output: matches.value_of("output"),
+        batch_file: matches.value_of("batch_file"),
+        dependencies: transaction_dependencies(matches)?,
+    })
+}
+
+/// The prefix shared by the addresses of all Sabre state
+const SABRE_ADDRESS_PREFIX: &str = "00ec";
+
+/// The length of a hex-encoded state address
+const ADDRESS_LENGTH: usize = 70;
+
+fn state_command(
+    state_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    match state_matches.subcommand() {
+        ("list", Some(matches)) => {
+            let url = matches
+                .value_of("url")
+                .or_else(|| config.url())
+                .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+            let prefix = matches.value_of("prefix").unwrap_or(SABRE_ADDRESS_PREFIX);
+            if prefix.len() > ADDRESS_LENGTH || !is_hex(prefix) {
+                return Err(CliError::UserError(format!(
+                    "Address prefix must be at most {} hex characters: {}",
+                    ADDRESS_LENGTH, prefix
+                )));
+            }
+            let raw = matches.is_present("raw");
+
+            let mut entries = state::get_state_with_prefix(client, url, prefix)?
+                .into_iter()
+                .map(|entry| {
+                    let bytes = base64::decode(&entry.data).map_err(|_| {
+                        CliError::UserError(format!("Unable to decode state at {}", entry.address))
+                    })?;
+                    let names = if raw {
+                        vec![]
+                    } else {
+                        DecodedState::decode(&entry.address, &bytes)
+                            .map(|decoded| decoded.names())
+                            .unwrap_or_default()
+                    };
+                    Ok((entry.address, bytes.len(), names))
+                })
+                .collect::<Result<Vec<_>, CliError>>()?;
+            entries.sort();
+
+            if format == OutputFormat::Json || format == OutputFormat::Yaml {
+                let entries = entries
+                    .iter()
+                    .map(|(address, size, names)| StateListOutput {
+                        address,
+                        state_type: DecodedState::type_of(address),
+                        size: *size,
+                        names: names.clone(),
+                    })
+                    .collect::<Vec<_>>();
+
+                return print_document(format, &entries);
+            }
+
+            let mut data = vec![
+                // Headers
+                vec![
+                    "ADDRESS".to_string(),
+                    "TYPE".to_string(),
+                    "SIZE".to_string(),
+                    "NAMES".to_string(),
+                ],
+            ];
+            for (address, size, names) in entries {
+                let state_type = DecodedState::type_of(&address).unwrap_or("-").to_string();
+                let names = if names.is_empty() {
+                    "-".to_string()
+                } else {
+                    names.join(", ")
+                };
+                data.push(vec![address, state_type, size.to_string(), names]);
+            }
+
+            if format == OutputFormat::Csv {
+                print_csv(data);
+            } else {
+                print_table(data);
+            }
+
+            Ok(())
+        }
+        ("show", Some(matches)) => {
+            let url = matches
+                .value_of("url")
+                .or_else(|| config.url())
+                .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+            let address = matches.value_of("address").unwrap();
+            if address.len() != ADDRESS_LENGTH || !is_hex(address) {
+                return Err(CliError::UserError(format!(
+                    "Address must be {} hex characters: {}",
+                    ADDRESS_LENGTH, address
+                )));
+            }
+
+            let bytes = state::get_state_entry(client, url, address)?
+                .ok_or_else(|| CliError::UserError(format!("no state at address {}", address)))?;
+            let decoded = if matches.is_present("raw") {
+                None
+            } else {
+                DecodedState::decode(address, &bytes)
+            };
+            let state_type = DecodedState::type_of(address);
+
+            match format {
+                OutputFormat::Json | OutputFormat::Yaml => {
+                    return print_document(
+                        format,
+                        &StateEntryOutput {
+                            address,
+                            state_type,
+                            size: bytes.len(),
+                            data: base64::encode(&bytes),
+                            decoded: decoded.as_ref().map(DecodedStateOutput::from),
+                        },
+                    )
+                }
+                OutputFormat::Csv => {
+                    return Err(CliError::UserError(
+                        "csv format is only supported by list commands".into(),
+                    ))
+                }
+                OutputFormat::Human => (),
+            }
+
+            println!("{}", address);
+            println!("  type: {}", state_type.unwrap_or("unknown"));
+            println!("  size: {} bytes", bytes.len());
+            println!();
+            match decoded {
+                Some(decoded) => print!("{}", decoded.describe()),
+                None => print!("{}", hex_dump(&bytes)),
+            }
+
+            Ok(())
+        }
+        _ => Err(CliError::UserError("Invalid Subcommand".into())),
+    }
+}
+
+/// Decode a contract's state entry with the state schema registered with the contract
+fn query(
+    query_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    if format == OutputFormat::Csv {
+        return Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        ));
+    }
+
+    let url = query_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+    let address = query_matches.value_of("address").unwrap();
+    if address.len() != ADDRESS_LENGTH || !is_hex(address) {
+        return Err(CliError::UserError(format!(
+            "Address must be {} hex characters: {}",
+            ADDRESS_LENGTH, address
+        )));
+    }
+
+    let (name, version) = contract_name_version(query_matches)?;
+    let contract = get_contract(client, url, name, version)?;
+    let schema = StateSchema::from_contract(contract.contract())?.ok_or_else(|| {
+        CliError::UserError(format!(
+            "contract '{}:{}' has no registered state schema; register one with sabre upload \
+             --state-schema",
+            name, version
+        ))
+    })?;
+    if !contract
+        .inputs()
+        .iter()
+        .any(|prefix| address.starts_with(prefix.as_str()))
+    {
+        warn!(
+            "Address {} is not in the inputs of contract '{}:{}', so it may not hold the \
+             contract's state",
+            address, name, version
+        );
+    }
+
+    let message = query_matches.value_of("message").unwrap_or(&schema.message);
+    let bytes = state::get_state_entry(client, url, address)?
+        .ok_or_else(|| CliError::UserError(format!("no state at address {}", address)))?;
+    let value = schema.decode(&bytes, Some(message))?;
+
+    match format {
+        OutputFormat::Json | OutputFormat::Yaml => print_document(
+            format,
+            &QueryOutput {
+                address,
+                contract: name,
+                version,
+                message,
+                value,
+            },
+        ),
+        _ => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&value).map_err(|err| CliError::OutputError(
+                    format!("Unable to serialize output: {}", err)
+                ))?
+            );
+            Ok(())
+        }
+    }
+}
+
+/// The length of a hex-encoded transaction ID
+const TRANSACTION_ID_LENGTH: usize = 128;
+
+fn receipt_command(
+    matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    if format == OutputFormat::Csv {
+        return Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        ));
+    }
+
+    let transaction_ids = matches
+        .values_of("transaction_id")
+        .unwrap()
+        .collect::<Vec<_>>();
+    for id in &transaction_ids {
+        if id.len() != TRANSACTION_ID_LENGTH || !is_hex(id) {
+            return Err(CliError::UserError(format!(
+                "Transaction ID must be {} hex characters: {}",
+                TRANSACTION_ID_LENGTH, id
+            )));
+        }
+    }
+    let raw = matches.is_present("raw");
+    let url = matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let receipts = match matches
+        .value_of("target")
+        .or_else(|| config.target())
+        .unwrap_or("sawtooth")
+    {
+        "sawtooth" => receipt::get_receipts(client, url, &transaction_ids)?,
+        #[cfg(feature = "zmq")]
+        "validator" => validator::get_receipts(url, &transaction_ids)?,
+        #[cfg(not(feature = "zmq"))]
+        "validator" => {
+            return Err(CliError::UserError(
+                "--target validator requires sabre to be built with the zmq feature".into(),
+            ))
+        }
+        target => {
+            return Err(CliError::UserError(format!(
+                "receipt does not support target {}",
+                target
+            )))
+        }
+    };
+
+    // Decode the state set by each transaction up front, since the structured output borrows it
+    let decoded = receipts
+        .iter()
+        .map(|receipt| {
+            receipt
+                .changes
+                .iter()
+                .map(|change| match &change.value {
+                    Some(value) if !raw => DecodedState::decode(&change.address, value),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    if format != OutputFormat::Human {
+        let receipts = receipts
+            .iter()
+            .zip(&decoded)
+            .map(|(receipt, decoded)| ReceiptOutput {
+                transaction_id: &receipt.transaction_id,
+                changes: receipt
+                    .changes
+                    .iter()
+                    .zip(decoded)
+                    .map(|(change, decoded)| StateChangeOutput {
+                        action: if change.value.is_some() {
+                            "set"
+                        } else {
+                            "delete"
+                        },
+                        address: &change.address,
+                        state_type: DecodedState::type_of(&change.address),
+                        data: change.value.as_ref().map(base64::encode),
+                        decoded: decoded.as_ref().map(DecodedStateOutput::from),
+                    })
+                    .collect(),
+                events: receipt
+                    .events
+                    .iter()
+                    .map(|event| EventOutput {
+                        event_type: &event.event_type,
+                        attributes: event
+                            .attributes
+                            .iter()
+                            .map(|(key, value)| EventAttributeOutput { key, value })
+                            .collect(),
+                        data: base64::encode(&event.data),
+                    })
+                    .collect(),
+                data: receipt.data.iter().map(base64::encode).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        return print_document(format, &receipts);
+    }
+
+    for (receipt, decoded) in receipts.iter().zip(&decoded) {
+        println!("transaction {}", receipt.transaction_id);
+        if receipt.changes.is_empty() {
+            println!("  no state changes");
+        }
+        for (change, decoded) in receipt.changes.iter().zip(decoded) {
+            let state_type = DecodedState::type_of(&change.address).unwrap_or("unknown");
+            let value = match &change.value {
+                Some(value) => value,
+                None => {
+                    println!("  delete {} {}", change.address, state_type);
+                    continue;
+                }
+            };
+            println!(
+                "  set {} {} ({} bytes)",
+                change.address,
+                state_type,
+                value.len()
+            );
+            match decoded {
+                Some(decoded) => print!("{}", indent(&decoded.describe(), 4)),
+                None => print!("{}", indent(&describe_payload(value, raw), 4)),
+            }
+        }
+        for event in &receipt.events {
+            let attributes = event
+                .attributes
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>();
+            println!(
+                "  event {}: {} ({} bytes)",
+                event.event_type,
+                attributes.join(", "),
+                event.data.len()
+            );
+            print!("{}", indent(&describe_payload(&event.data, raw), 4));
+        }
+        for data in &receipt.data {
+            println!("  data ({} bytes)", data.len());
+            print!("{}", indent(&describe_payload(data, raw), 4));
+        }
+    }
+
+    Ok(())
+}
+
+fn event_command(
+    event_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    let matches = match event_matches.subcommand() {
+        ("listen", Some(matches)) => matches,
+        _ => return Err(CliError::UserError("Unrecognized event subcommand".into())),
+    };
+
+    if format == OutputFormat::Csv {
+        return Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        ));
+    }
+
+    let prefixes = matches
+        .values_of("prefix")
+        .map(|values| values.collect::<Vec<_>>())
+        .unwrap_or_else(|| vec![SABRE_ADDRESS_PREFIX]);
+    for prefix in &prefixes {
+        if prefix.len() > ADDRESS_LENGTH || !is_hex(prefix) {
+            return Err(CliError::UserError(format!(
+                "Address prefix must be at most {} hex characters: {}",
+                ADDRESS_LENGTH, prefix
+            )));
+        }
+    }
+    let event_types = matches
+        .values_of("event_type")
+        .map(|values| values.map(String::from).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let mut on_block = |block: events::Block| {
+        if block.changes.is_empty() && block.events.is_empty() {
+            debug!(
+                "Block {} {} has no matching changes",
+                block.block_num, block.block_id
+            );
+            return Ok(());
+        }
+
+        if format != OutputFormat::Human {
+            return print_stream_document(
+                format,
+                &BlockOutput {
+                    block_num: block.block_num,
+                    block_id: &block.block_id,
+                    changes: block
+                        .changes
+                        .iter()
+                        .map(|change| StateChangeOutput {
+                            action: if change.value.is_some() {
+                                "set"
+                            } else {
+                                "delete"
+                            },
+                            address: &change.address,
+                            state_type: None,
+                            data: change.value.as_ref().map(base64::encode),
+                            decoded: None,
+                        })
+                        .collect(),
+                    events: block
+                        .events
+                        .iter()
+                        .map(|event| EventOutput {
+                            event_type: &event.event_type,
+                            attributes: event
+                                .attributes
+                                .iter()
+                                .map(|(key, value)| EventAttributeOutput { key, value })
+                                .collect(),
+                            data: base64::encode(&event.data),
+                        })
+                        .collect(),
+                },
+            );
+        }
+
+        println!("block {} {}", block.block_num, block.block_id);
+        for change in &block.changes {
+            let state_type = DecodedState::type_of(&change.address).unwrap_or("unknown");
+            match &change.value {
+                Some(value) => {
+                    let names = DecodedState::decode(&change.address, value)
+                        .map(|decoded| decoded.names())
+                        .unwrap_or_default();
+                    if names.is_empty() {
+                        println!("  set {} {}", change.address, state_type);
+                    } else {
+                        println!(
+                            "  set {} {} ({})",
+                            change.address,
+                            state_type,
+                            names.join(", ")
+                        );
+                    }
+                }
+                None => println!("  delete {} {}", change.address, state_type),
+            }
+        }
+        for event in &block.events {
+            let attributes = event
+                .attributes
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>();
+            println!(
+                "  event {}: {} ({} bytes)",
+                event.event_type,
+                attributes.join(", "),
+                event.data.len()
+            );
+        }
+
+        Ok(())
+    };
+
+    let url = matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    match matches
+        .value_of("target")
+        .or_else(|| config.target())
+        .unwrap_or("sawtooth")
+    {
+        "sawtooth" => {
+            if !event_types.is_empty() {
+                return Err(CliError::UserError(
+                    "--event-type requires --target validator, since the REST API does not \
+                     forward events"
+                        .into(),
+                ));
+            }
+            info!("Listening for blocks committed by {}", url);
+            events::listen(client, url, &prefixes, &mut on_block)
+        }
+        #[cfg(feature = "zmq")]
+        "validator" => {
+            info!("Listening for blocks committed by {}", url);
+            validator::listen(url, &prefixes, &event_types, &mut on_block)
+        }
+        #[cfg(not(feature = "zmq"))]
+        "validator" => Err(CliError::UserError(
+            "--target validator requires sabre to be built with the zmq feature".into(),
+        )),
+        target => Err(CliError::UserError(format!(
+            "event listen does not support target {}",
+            target
+        ))),
+    }
+}
+
+/// Whether the string is lowercase hex, as state addresses are
+fn is_hex(value: &str) -> bool {
+    value
+        .chars()
+        .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+/// Whether the string is a hex-encoded compressed secp256k1 public key, as Sawtooth signers are
+/// identified by
+fn is_public_key(value: &str) -> bool {
+    value.len() == 66 && is_hex(value)
+}
+
+fn contract(
+    contract_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    match contract_matches.subcommand() {
+        ("list", Some(matches)) => {
+            let url = matches
+                .value_of("url")
+                .or_else(|| config.url())
+                .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+            let registries =
+                state::get_state_with_prefix(client, url, &CONTRACT_REGISTRY_ADDRESS_PREFIX)?
+                    .into_iter()
+                    .map(|entry| {
+                        base64::decode(entry.data)
+                            .map_err(|_| CliError::UserError("Unable to decode state".into()))
+                            .and_then(|bytes| {
+                                ContractRegistryList::from_bytes(&bytes)
+                                    .map_err(CliError::ProtoConversionError)
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+            let mut registries = registries
+                .iter()
+                .flat_map(|registry_list| registry_list.registries())
+                .collect::<Vec<_>>();
+            registries.sort_by_key(|registry| registry.name());
+
+            if format == OutputFormat::Json || format == OutputFormat::Yaml {
+                let contracts = registries
+                    .iter()
+                    .map(|registry| ContractRegistryOutput {
+                        name: registry.name(),
+                        versions: registry
+                            .versions()
+                            .iter()
+                            .map(|version| version.version())
+                            .collect(),
+                        owners: registry.owners(),
+                    })
+                    .collect::<Vec<_>>();
+
+                return print_document(format, &contracts);
+            }
+
+            let mut data = vec![
+                // Headers
+                vec![
+                    "NAME".to_string(),
+                    "VERSIONS".to_string(),
+                    "OWNERS".to_string(),
+                ],
+            ];
+            for registry in registries {
+                let name = registry.name().to_string();
+                let versions = registry
+                    .versions()
+                    .iter()
+                    .map(|version| {
+                        if version.paused() {
+                            format!("{} (paused)", version.version())
+                        } else {
+                            version.version().to_string()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let owners = registry.owners().join(", ");
+
+                data.push(vec![name, versions, owners]);
+            }
+
+            if format == OutputFormat::Csv {
+                print_csv(data);
+            } else {
+                print_table(data);
+            }
+
+            Ok(())
+        }
+        ("show", Some(matches)) => {
+            let url = matches
+                .value_of("url")
+                .or_else(|| config.url())
+                .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+            let (name, version) = contract_name_version(matches)?;
+            let contract = get_contract(client, url, name, version)?;
+
+            match format {
+                OutputFormat::Json | OutputFormat::Yaml => {
+                    return print_document(format, &ContractOutput::from(&contract))
+                }
+                OutputFormat::Csv => {
+                    return Err(CliError::UserError(
+                        "csv format is only supported by list commands".into(),
+                    ))
+                }
+                OutputFormat::Human => (),
+            }
+
+            print!("{}", describe_contract(&contract));
+
+            Ok(())
+        }
+        ("download", Some(matches)) => {
+            let url = matches
+                .value_of("url")
+                .or_else(|| config.url())
+                .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+            let output = matches
+                .value_of("output")
+                .ok_or_else(|| CliError::UserError("Missing output file".into()))?;
+
+            let (name, version) = contract_name_version(matches)?;
+            let contract = get_contract(client, url, name, version)?;
+            let wasm_sha512 = sha512_hex(contract.contract());
+
+            // The registry records the hash of the module when it was uploaded
+            let registry = get_contract_registry(client, url, name)?;
+            let registry_version = registry
+                .versions()
+                .iter()
+                .find(|registry_version| registry_version.version() == version)
+                .ok_or_else(|| {
+                    CliError::UserError(format!(
+                        "contract registry '{}' has no version '{}'",
+                        name, version
+                    ))
+                })?;
+
+            if registry_version.contract_sha512() != &wasm_sha512 {
+                return Err(CliError::UserError(format!(
+                    "contract '{}:{}' does not match the hash in its registry: expected {}, got {}",
+                    name,
+                    version,
+                    registry_version.contract_sha512(),
+                    wasm_sha512
+                )));
+            }
+
+            let mut file = File::create(output).map_err(|err| {
+                CliError::UserError(format!("Could not create file {}: {}", output, err))
+            })?;
+            file.write_all(contract.contract())?;
+
+            println!(
+                "wrote {} ({} bytes, sha512 {})",
+                output,
+                contract.contract().len(),
+                wasm_sha512
+            );
+
+            Ok(())
+        }
+        _ => Err(CliError::UserError("Invalid Subcommand".into())),
+    }
+}
+
+/// Return the contract name and version given as either NAME VERSION or 'name:version'
+fn contract_name_version<'a>(
+    matches: &'a clap::ArgMatches,
+) -> Result<(&'a str, &'a str), CliError> {
+    let name = matches
+        .value_of("name")
+        .ok_or_else(|| CliError::UserError("Missing contract name".into()))?;
+
+    match matches.value_of("version") {
+        Some(version) => Ok((name, version)),
+        None => parse_name_version(name).ok_or_else(|| {
+            CliError::UserError(
+                "A contract version must be given, either as VERSION or as 'name:version'".into(),
+            )
+        }),
+    }
+}
+
+/// Fetch the given version of a contract from state
+fn get_contract(
+    client: &HttpClient,
+    url: &str,
+    name: &str,
+    version: &str,
+) -> Result<Contract, CliError> {
+    let address =
+        to_hex(&compute_contract_address(name, version).map_err(|err| {
+            CliError::UserError(format!("Unable to get contract address: {}", err))
+        })?);
+
+    let entry = state::get_state_with_prefix(client, url, &address)?
+        .into_iter()
+        .find(|entry| entry.address == address)
+        .ok_or_else(|| CliError::UserError(format!("contract '{}:{}' not found", name, version)))?;
+
+    let contract_list = ContractList::from_bytes(
+        &base64::decode(entry.data)
+            .map_err(|_| CliError::UserError("Unable to decode state".into()))?,
+    )?;
+
+    contract_list
+        .contracts()
+        .iter()
+        .find(|contract| contract.name() == name && contract.version() == version)
+        .cloned()
+        .ok_or_else(|| CliError::UserError(format!("contract '{}:{}' not found", name, version)))
+}
+
+/// Report the differences between two versions of a contract, for review before the new version
+/// is granted permissions
+fn contract_diff(
+    diff_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    if format == OutputFormat::Csv {
+        return Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        ));
+    }
+
+    let url = diff_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+    let name = diff_matches.value_of("name").unwrap();
+
+    let registry = get_contract_registry(client, url, name)?;
+    let from = ContractVersionOutput::new(&get_contract(
+        client,
+        url,
+        name,
+        diff_matches.value_of("from").unwrap(),
+    )?)?;
+    let to = ContractVersionOutput::new(&get_contract(
+        client,
+        url,
+        name,
+        diff_matches.value_of("to").unwrap(),
+    )?)?;
+
+    let output = ContractDiffOutput {
+        name,
+        inputs: ListDiffOutput::new(&from.inputs, &to.inputs),
+        outputs: ListDiffOutput::new(&from.outputs, &to.outputs),
+        exports: ListDiffOutput::new(&from.exports, &to.exports),
+        imports: ListDiffOutput::new(&from.imports, &to.imports),
+        owners: registry.owners(),
+        from,
+        to,
+    };
+
+    if format != OutputFormat::Human {
+        return print_document(format, &output);
+    }
+
+    let (from, to) = (&output.from, &output.to);
+    println!("{} {} -> {}", name, from.version, to.version);
+
+    let describe = |label: &str, from: &str, to: &str| {
+        if from == to {
+            println!("  {}: {} (unchanged)", label, from);
+        } else {
+            println!("  {}: {} -> {}", label, from, to);
+        }
+    };
+    describe("wasm sha512", &from.wasm_sha512, &to.wasm_sha512);
+    describe(
+        "wasm size",
+        &format!("{} bytes", from.wasm_size),
+        &format!("{} bytes", to.wasm_size),
+    );
+    describe("creator", &from.creator, &to.creator);
+    describe(
+        "state schema",
+        from.state_message.as_deref().unwrap_or("-"),
+        to.state_message.as_deref().unwrap_or("-"),
+    );
+
+    for (label, diff) in &[
+        ("inputs", &output.inputs),
+        ("outputs", &output.outputs),
+        ("exported functions", &output.exports),
+        ("imports", &output.imports),
+    ] {
+        if diff.added.is_empty() && diff.removed.is_empty() {
+            println!("  {}: unchanged", label);
+            continue;
+        }
+        println!("  {}:", label);
+        for added in &diff.added {
+            println!("  + {}", added);
+        }
+        for removed in &diff.removed {
+            println!("  - {}", removed);
+        }
+    }
+
+    println!("  current owners: {}", output.owners.join(", "));
+
+    Ok(())
+}
+
+/// Fetch the registry of the given contract from state
+fn get_contract_registry(
+    client: &HttpClient,
+    url: &str,
+    name: &str,
+) -> Result<ContractRegistry, CliError> {
+    let address = to_hex(&compute_contract_registry_address(name).map_err(|err| {
+        CliError::UserError(format!("Unable to get contract registry address: {}", err))
+    })?);
+
+    let entry = state::get_state_with_prefix(client, url, &address)?
+        .into_iter()
+        .find(|entry| entry.address == address)
+        .ok_or_else(|| CliError::UserError(format!("contract registry '{}' not found", name)))?;
+
+    let registry_list = ContractRegistryList::from_bytes(
+        &base64::decode(entry.data)
+            .map_err(|_| CliError::UserError("Unable to decode state".into()))?,
+    )?;
+
+    registry_list
+        .registries()
+        .iter()
+        .find(|registry| registry.name() == name)
+        .cloned()
+        .ok_or_else(|| CliError::UserError(format!("contract registry '{}' not found", name)))
+}
+
+/// Fetch the registry of the given namespace from state
+fn get_namespace_registry(
+    client: &HttpClient,
+    url: &str,
+    namespace: &str,
+) -> Result<NamespaceRegistry, CliError> {
+    let address = to_hex(
+        &compute_namespace_registry_address(namespace).map_err(|err| {
+            CliError::UserError(format!("Unable to get namespace registry address: {}", err))
+        })?,
+    );
+
+    let entry = state::get_state_with_prefix(client, url, &address)?
+        .into_iter()
+        .find(|entry| entry.address == address)
+        .ok_or_else(|| CliError::UserError(format!("namespace '{}' not found", namespace)))?;
+
+    let registry_list = NamespaceRegistryList::from_bytes(
+        &base64::decode(entry.data)
+            .map_err(|_| CliError::UserError("Unable to decode state".into()))?,
+    )?;
+
+    registry_list
+        .registries()
+        .iter()
+        .find(|registry| registry.namespace() == namespace)
+        .cloned()
+        .ok_or_else(|| CliError::UserError(format!("namespace '{}' not found", namespace)))
+}
+
+/// Fetch the given smart permission of an organization from state
+fn get_smart_permission(
+    client: &HttpClient,
+    url: &str,
+    org_id: &str,
+    name: &str,
+) -> Result<SmartPermission, CliError> {
+    let not_found = || {
+        CliError::UserError(format!(
+            "smart permission '{}' not found for organization '{}'",
+            name, org_id
+        ))
+    };
+
+    let address = to_hex(
+        &compute_smart_permission_address(org_id, name).map_err(|err| {
+            CliError::UserError(format!("Unable to get smart permission address: {}", err))
+        })?,
+    );
+
+    let entry = state::get_state_with_prefix(client, url, &address)?
+        .into_iter()
+        .find(|entry| entry.address == address)
+        .ok_or_else(not_found)?;
+
+    let permission_list = SmartPermissionList::from_bytes(
+        &base64::decode(entry.data)
+            .map_err(|_| CliError::UserError("Unable to decode state".into()))?,
+    )?;
+
+    permission_list
+        .smart_permissions()
+        .iter()
+        .find(|smart_permission| {
+            smart_permission.org_id() == org_id && smart_permission.name() == name
+        })
+        .cloned()
+        .ok_or_else(not_found)
+}
+
+/// Format a namespace registry as it is shown by `sabre ns show`
+fn describe_namespace_registry(registry: &NamespaceRegistry) -> String {
+    let mut description = format!("{}\n  owners:\n", registry.namespace());
+    for owner in registry.owners() {
+        description += &format!("  - {}\n", owner);
+    }
+    description += "  permissions:\n";
+    for permission in registry.permissions() {
+        description += &format!(
+            "  - {} ({})\n",
+            permission.contract_name(),
+            permission_flags(permission.read(), permission.write())
+        );
+    }
+    description
+}
+
+/// Format a contract registry as it is shown by `sabre cr show`
+fn describe_contract_registry(registry: &ContractRegistry) -> String {
+    let mut description = format!("{}\n  owners:\n", registry.name());
+    for owner in registry.owners() {
+        description += &format!("  - {}\n", owner);
+    }
+    if registry.max_memory_pages() > 0 {
+        description += &format!("  max memory pages: {}\n", registry.max_memory_pages());
+    }
+    description += "  versions:\n";
+    for version in registry.versions() {
+        description += &format!(
+            "  - version: {}\n    contract sha512: {}\n    creator: {}\n",
+            version.version(),
+            version.contract_sha512(),
+            version.creator()
+        );
+        if version.paused() {
+            description += "    paused: true\n";
+        }
+    }
+    description
+}
+
+/// Format a smart permission as it is shown by `sabre sp show`
+fn describe_smart_permission(smart_permission: &SmartPermission) -> String {
+    format!(
+        "{}\n  org id: {}\n  function size: {} bytes\n  function sha512: {}\n",
+        smart_permission.name(),
+        smart_permission.org_id(),
+        smart_permission.function().len(),
+        sha512_hex(smart_permission.function())
+    )
+}
+
+/// Format a contract as it is shown by `sabre contract show`
+fn describe_contract(contract: &Contract) -> String {
+    let mut description = format!("{} {}\n  inputs:\n", contract.name(), contract.version());
+    for input in contract.inputs() {
+        description += &format!("  - {}\n", input);
+    }
+    description += "  outputs:\n";
+    for output in contract.outputs() {
+        description += &format!("  - {}\n", output);
+    }
+    description += &format!(
+        "  creator: {}\n  wasm size: {} bytes\n  wasm sha512: {}\n",
+        contract.creator(),
+        contract.contract().len(),
+        sha512_hex(contract.contract())
+    );
+    description
+}
+
+/// Format an uploaded chunk of a contract
+fn describe_contract_chunk(chunk: &ContractChunk) -> String {
+    format!(
+        "{} {} chunk {}\n  creator: {}\n  size: {} bytes\n  sha512: {}\n",
+        chunk.name(),
+        chunk.version(),
+        chunk.index(),
+        chunk.creator(),
+        chunk.chunk().len(),
+        sha512_hex(chunk.chunk())
+    )
+}
+
+/// The contents of a state entry managed by Sabre
+enum DecodedState {
+    NamespaceRegistries(NamespaceRegistryList),
+    ContractRegistries(ContractRegistryList),
+    Contracts(ContractList),
+    SmartPermissions(SmartPermissionList),
+    ContractChunks(ContractChunkList),
+}
+
+impl DecodedState {
+    /// The kind of Sabre state stored at the address, based on its prefix
+    fn type_of(address: &str) -> Option<&'static str> {
+        match address.get(..6)? {
+            NAMESPACE_REGISTRY_ADDRESS_PREFIX => Some("namespace registry"),
+            CONTRACT_REGISTRY_ADDRESS_PREFIX => Some("contract registry"),
+            CONTRACT_ADDRESS_PREFIX => Some("contract"),
+            SMART_PERMISSION_ADDRESS_PREFIX => Some("smart permission"),
+            CONTRACT_CHUNK_ADDRESS_PREFIX => Some("contract chunk"),
+            _ => None,
+        }
+    }
+
+    /// Decode the state entry at the address, or return `None` if it is not Sabre state
+    ///
+    /// An entry which has a Sabre prefix but cannot be decoded is logged and treated as unknown.
+    fn decode(address: &str, bytes: &[u8]) -> Option<DecodedState> {
+        let decoded = match address.get(..6)? {
+            NAMESPACE_REGISTRY_ADDRESS_PREFIX => {
+                NamespaceRegistryList::from_bytes(bytes).map(DecodedState::NamespaceRegistries)
+            }
+            CONTRACT_REGISTRY_ADDRESS_PREFIX => {
+                ContractRegistryList::from_bytes(bytes).map(DecodedState::ContractRegistries)
+            }
+            CONTRACT_ADDRESS_PREFIX => ContractList::from_bytes(bytes).map(DecodedState::Contracts),
+            SMART_PERMISSION_ADDRESS_PREFIX => {
+                SmartPermissionList::from_bytes(bytes).map(DecodedState::SmartPermissions)
+            }
+            CONTRACT_CHUNK_ADDRESS_PREFIX => {
+                ContractChunkList::from_bytes(bytes).map(DecodedState::ContractChunks)
+            }
+            _ => return None,
+        };
+
+        decoded
+            .map_err(|err| warn!("Unable to decode state at {}: {}", address, err))
+            .ok()
+    }
+
+    /// The names of the objects in the entry, such as namespaces or contract names and versions
+    fn names(&self) -> Vec<String> {
+        match self {
+            DecodedState::NamespaceRegistries(list) => list
+                .registries()
+                .iter()
+                .map(|registry| registry.namespace().to_string())
+                .collect(),
+            DecodedState::ContractRegistries(list) => list
+                .registries()
+                .iter()
+                .map(|registry| registry.name().to_string())
+                .collect(),
+            DecodedState::Contracts(list) => list
+                .contracts()
+                .iter()
+                .map(|contract| format!("{}:{}", contract.name(), contract.version()))
+                .collect(),
+            DecodedState::SmartPermissions(list) => list
+                .smart_permissions()
+                .iter()
+                .map(|smart_permission| {
+                    format!("{}/{}", smart_permission.org_id(), smart_permission.name())
+                })
+                .collect(),
+            DecodedState::ContractChunks(list) => list
+                .chunks()
+                .iter()
+                .map(|chunk| format!("{}:{}#{}", chunk.name(), chunk.version(), chunk.index()))
+                .collect(),
+        }
+    }
+
+    /// Format the objects in the entry as they are shown by their own show commands
+    fn describe(&self) -> String {
+        match self {
+            DecodedState::NamespaceRegistries(list) => list
+                .registries()
+                .iter()
+                .map(describe_namespace_registry)
+                .collect(),
+            DecodedState::ContractRegistries(list) => list
+                .registries()
+                .iter()
+                .map(describe_contract_registry)
+                .collect(),
+            DecodedState::Contracts(list) => {
+                list.contracts().iter().map(describe_contract).collect()
+            }
+            DecodedState::SmartPermissions(list) => list
+                .smart_permissions()
+                .iter()
+                .map(describe_smart_permission)
+                .collect(),
+            DecodedState::ContractChunks(list) => {
+                list.chunks().iter().map(describe_contract_chunk).collect()
+            }
+        }
+    }
+}
+
+/// Format bytes as a hex dump, with 16 bytes and their printable ASCII characters on each line
+fn hex_dump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(line, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            format!("{:08x}  {:<47}  |{}|\n", line * 16, hex, ascii)
+        })
+        .collect()
+}
+
+/// Format bytes set or emitted by a contract: as text if they are printable UTF-8 and `raw` is
+/// not set, otherwise as a hex dump
+fn describe_payload(bytes: &[u8], raw: bool) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text)
+            if !raw
+                && !text.is_empty()
+                && text
+                    .chars()
+                    .all(|c| !c.is_control() || c == '\n' || c == '\t') =>
+        {
+            text.lines().map(|line| format!("{}\n", line)).collect()
+        }
+        _ => hex_dump(bytes),
+    }
+}
+
+/// Indent each line of the text by the given number of spaces
+fn indent(text: &str, spaces: usize) -> String {
+    text.lines()
+        .map(|line| format!("{:width$}{}\n", "", line, width = spaces))
+        .collect()
+}
+
+/// Ask the user to confirm that the object described should be deleted
+///
+/// The description and prompt are written to stderr. An error is returned if the user does not
+/// answer yes, or if stdin is not a terminal, in which case `--yes` must be used instead.
+fn confirm_delete(description: &str, object: &str) -> Result<(), CliError> {
+    // Safe because isatty only inspects the file descriptor
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } == 0 {
+        return Err(CliError::UserError(format!(
+            "Refusing to delete {} without confirmation; use --yes to skip the prompt",
+            object
+        )));
+    }
+
+    eprint!("{}Delete {}? [y/N] ", description, object);
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(CliError::UserError(format!("Did not delete {}", object))),
+    }
+}
+
+fn address(addr_matches: &clap::ArgMatches, format: OutputFormat) -> Result<(), CliError> {
+    let address = match addr_matches.subcommand() {
+        ("ns", Some(matches)) => {
+            compute_namespace_registry_address(matches.value_of("namespace").unwrap())
+        }
+        ("cr", Some(matches)) => {
+            compute_contract_registry_address(matches.value_of("name").unwrap())
+        }
+        ("contract", Some(matches)) => {
+            let (name, version) = contract_name_version(matches)?;
+            compute_contract_address(name, version)
+        }
+        ("sp", Some(matches)) => compute_smart_permission_address(
+            matches.value_of("org_id").unwrap(),
+            matches.value_of("name").unwrap(),
+        ),
+        _ => return Err(CliError::UserError("Invalid Subcommand".into())),
+    }
+    .map_err(|err| CliError::UserError(format!("Unable to compute address: {}", err)))?;
+
+    match format {
+        OutputFormat::Json | OutputFormat::Yaml => print_document(
+            format,
+            &AddressOutput {
+                address: to_hex(&address),
+            },
+        ),
+        OutputFormat::Csv => Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        )),
+        OutputFormat::Human => {
+            println!("{}", to_hex(&address));
+            Ok(())
+        }
+    }
+}
+
+fn new_command(new_matches: &clap::ArgMatches, format: OutputFormat) -> Result<(), CliError> {
+    if format == OutputFormat::Csv {
+        return Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        ));
+    }
+
+    let contract = scaffold::new_contract(
+        new_matches.value_of("name").unwrap(),
+        new_matches.value_of("path"),
+        new_matches.value_of("prefix"),
+        new_matches.value_of("sdk_path"),
+    )?;
+    let path = contract.path.display().to_string();
+
+    match format {
+        OutputFormat::Human => {
+            println!("created contract {} in {}", contract.name, path);
+            println!("prefix: {}", contract.prefix);
+            for file in &contract.files {
+                println!("  {}", file);
+            }
+            Ok(())
+        }
+        _ => print_document(
+            format,
+            &NewContractOutput {
+                name: &contract.name,
+                path: &path,
+                prefix: &contract.prefix,
+                files: &contract.files,
+            },
+        ),
+    }
+}
+
+fn build_command(build_matches: &clap::ArgMatches, format: OutputFormat) -> Result<(), CliError> {
+    if format == OutputFormat::Csv {
+        return Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        ));
+    }
+
+    let contract = compile::build(
+        build_matches.value_of("manifest_path"),
+        !build_matches.is_present("debug"),
+        build_matches.value_of("features"),
+    )?;
+    let path = contract.path.display().to_string();
+
+    match format {
+        OutputFormat::Human => {
+            if contract.size == contract.unstripped_size {
+                println!("built {} ({} bytes)", path, contract.size);
+            } else {
+                println!(
+                    "built {} ({} bytes, {} before stripping)",
+                    path, contract.size, contract.unstripped_size
+                );
+            }
+            Ok(())
+        }
+        _ => print_document(
+            format,
+            &BuildOutput {
+                path: &path,
+                size: contract.size,
+                unstripped_size: contract.unstripped_size,
+            },
+        ),
+    }
+}
+
+fn pack(pack_matches: &clap::ArgMatches, format: OutputFormat) -> Result<(), CliError> {
+    let archive = archive::pack(
+        pack_matches.value_of("manifest").unwrap(),
+        pack_matches.value_of("wasm").unwrap(),
+        pack_matches.value_of("output"),
+    )?;
+
+    match format {
+        OutputFormat::Json | OutputFormat::Yaml => print_document(
+            format,
+            &PackOutput {
+                archive: &archive.path,
+                name: &archive.manifest.name,
+                version: &archive.manifest.version,
+                inputs: &archive.manifest.inputs,
+                outputs: &archive.manifest.outputs,
+                contract_sha512: &archive.contract_sha512,
+            },
+        ),
+        OutputFormat::Csv => Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        )),
+        OutputFormat::Human => {
+            println!(
+                "packed {} {} into {}",
+                archive.manifest.name, archive.manifest.version, archive.path
+            );
+            println!("contract sha512: {}", archive.contract_sha512);
+            Ok(())
+        }
+    }
+}
+
+fn verify(
+    verify_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    if format == OutputFormat::Csv {
+        return Err(CliError::UserError(
+            "csv format is only supported by list commands".into(),
+        ));
+    }
+
+    let filename = verify_matches.value_of("filename").unwrap();
+    let url = verify_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    let (manifest, contract) = if filename.ends_with(".scar") {
+        let archive = archive::load(filename)?;
+        (Some(archive.manifest), archive.contract)
+    } else {
+        (None, load_bytes_from_file(filename)?)
+    };
+
+    let (name, version) = match (verify_matches.value_of("contract"), &manifest) {
+        (Some(contract), _) => match contract.split(':').collect::<Vec<_>>()[..] {
+            [name, version] if !name.is_empty() && !version.is_empty() => (name, version),
+            _ => {
+                return Err(CliError::UserError(
+                    "--contract must be given as <name>:<version>".into(),
+                ))
+            }
+        },
+        (None, Some(manifest)) => (manifest.name.as_str(), manifest.version.as_str()),
+        (None, None) => {
+            return Err(CliError::UserError(
+                "--contract is required to verify a .wasm file".into(),
+            ))
+        }
+    };
+
+    let registry = get_contract_registry(client, url, name)?;
+    let registered_sha512 = registry
+        .versions()
+        .iter()
+        .find(|registered| registered.version() == version)
+        .map(|registered| registered.contract_sha512().to_string())
+        .ok_or_else(|| {
+            CliError::UserError(format!(
+                "version '{}' of contract '{}' is not registered",
+                version, name
+            ))
+        })?;
+    let local_sha512 = archive::contract_sha512(&contract);
+    let matches = registered_sha512.eq_ignore_ascii_case(&local_sha512);
+
+    if format == OutputFormat::Human {
+        println!("{} {}", name, version);
+        println!("  local sha512:      {}", local_sha512);
+        println!("  registered sha512: {}", registered_sha512);
+    } else {
+        print_document(
+            format,
+            &VerifyOutput {
+                name,
+                version,
+                local_sha512: &local_sha512,
+                registered_sha512: &registered_sha512,
+                matches,
+            },
+        )?;
+    }
+
+    if !matches {
+        return Err(CliError::UserError(format!(
+            "{} does not match the registered contract {} {}",
+            filename, name, version
+        )));
+    }
+
+    if format == OutputFormat::Human {
+        println!("{} matches the registered contract", filename);
+    }
+
+    Ok(())
+}
+
+fn key_command(
+    key_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    match key_matches.subcommand() {
+        ("encrypt", Some(matches)) => key::encrypt_key_file(
+            matches.value_of("key").or_else(|| config.key()),
+            matches.value_of("key_dir").or_else(|| config.key_dir()),
+            matches.value_of("output"),
+        ),
+        ("list", Some(matches)) => {
+            let keys = key::list_keys(matches.value_of("key_dir").or_else(|| config.key_dir()))?;
+
+            if format == OutputFormat::Json || format == OutputFormat::Yaml {
+                let keys = keys
+                    .iter()
+                    .map(|key| KeyOutput {
+                        name: &key.name,
+                        public_key: key.public_key.as_deref(),
+                        private_key: key.private_key.as_str(),
+                    })
+                    .collect::<Vec<_>>();
+
+                return print_document(format, &keys);
+            }
+
+            let mut data = vec![vec![
+                "NAME".to_string(),
+                "PUBLIC KEY".to_string(),
+                "PRIVATE KEY".to_string(),
+            ]];
+            for key in keys {
+                data.push(vec![
+                    key.name,
+                    key.public_key.unwrap_or_else(|| "-".into()),
+                    key.private_key.as_str().to_string(),
+                ]);
+            }
+
+            if format == OutputFormat::Csv {
+                print_csv(data);
+            } else {
+                print_table(data);
+            }
+
+            Ok(())
+        }
+        ("import", Some(matches)) => key::import_key(
+            matches.value_of("key_name").unwrap(),
+            matches.value_of("key_dir").or_else(|| config.key_dir()),
+            matches.value_of("filename"),
+            matches.is_present("force"),
+        ),
+        ("export", Some(matches)) => {
+            let public_key = key::export_public_key(
+                matches.value_of("key").or_else(|| config.key()),
+                matches.value_of("key_dir").or_else(|| config.key_dir()),
+            )?;
+
+            match format {
+                OutputFormat::Human => println!("{}", public_key),
+                OutputFormat::Csv => {
+                    return Err(CliError::UserError(
+                        "csv format is only supported by list commands".into(),
+                    ))
+                }
+                _ => print_document(
+                    format,
+                    &PublicKeyOutput {
+                        public_key: &public_key,
+                    },
+                )?,
+            }
+
+            Ok(())
+        }
+        _ => Err(CliError::UserError("Invalid Subcommand".into())),
+    }
+}
+
+/// List or show the profiles of the configuration file
+///
+/// `active` is the profile selected with `--profile` or `$SABRE_PROFILE`, if any. Credentials
+/// are never printed; only the scheme of a profile's `auth` is shown.
+fn profile_command(
+    profile_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    active: Option<&str>,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    let active = active.or_else(|| config.profile());
+
+    match profile_matches.subcommand() {
+        ("list", Some(_)) => {
+            let profiles = config
+                .profiles()
+                .iter()
+                .map(|(name, profile)| ProfileOutput {
+                    name,
+                    active: Some(name.as_str()) == active,
+                    url: profile.url.as_deref(),
+                    key: profile.key.as_deref(),
+                    key_dir: profile.key_dir.as_deref(),
+                    batcher_key: profile.batcher_key.as_deref(),
+                    auth: profile.auth.as_deref().map(auth_scheme),
+                    target: profile.target.as_deref(),
+                    circuit: profile.circuit.as_deref(),
+                    service_id: profile.service_id.as_deref(),
+                })
+                .collect::<Vec<_>>();
+
+            if format == OutputFormat::Json || format == OutputFormat::Yaml {
+                return print_document(format, &profiles);
+            }
+
+            let mut data = vec![vec!["NAME".to_string(), "URL".to_string()]];
+            for profile in profiles {
+                let name = if profile.active && format == OutputFormat::Human {
+                    format!("{} *", profile.name)
+                } else {
+                    profile.name.to_string()
+                };
+                data.push(vec![name, profile.url.unwrap_or("-").to_string()]);
+            }
+
+            if format == OutputFormat::Csv {
+                print_csv(data);
+            } else {
+                print_table(data);
+            }
+
+            Ok(())
+        }
+        ("show", Some(matches)) => {
+            if format == OutputFormat::Csv {
+                return Err(CliError::UserError(
+                    "csv format is only supported by list commands".into(),
+                ));
+            }
+
+            let name = matches.value_of("name").or(active).ok_or_else(|| {
+                CliError::UserError(
+                    "No profile is in use; give the name of the profile to show".into(),
+                )
+            })?;
+            let selected = config.clone().with_profile(Some(name))?;
+            let output = ProfileOutput {
+                name,
+                active: Some(name) == active,
+                url: selected.url(),
+                key: selected.key(),
+                key_dir: selected.key_dir(),
+                batcher_key: selected.batcher_key(),
+                auth: selected.auth().map(auth_scheme),
+                target: selected.target(),
+                circuit: selected.circuit(),
+                service_id: selected.service_id(),
+            };
+
+            if format != OutputFormat::Human {
+                return print_document(format, &output);
+            }
+
+            println!("Profile: {}", output.name);
+            for (field, value) in &[
+                ("url", output.url),
+                ("key", output.key),
+                ("key_dir", output.key_dir),
+                ("batcher_key", output.batcher_key),
+                ("auth", output.auth),
+                ("target", output.target),
+                ("circuit", output.circuit),
+                ("service_id", output.service_id),
+            ] {
+                if let Some(value) = value {
+                    println!("  {}: {}", field, value);
+                }
+            }
+
+            Ok(())
+        }
+        _ => Err(CliError::UserError("Invalid Subcommand".into())),
+    }
+}
+
+/// Returns the scheme of REST API credentials, e.g. "bearer", without the secret
+fn auth_scheme(auth: &str) -> &str {
+    auth.split(':').next().unwrap_or(auth)
+}
+
+/// Returns the transaction IDs given with --depends-on, for the transaction's header
+fn transaction_dependencies(matches: &clap::ArgMatches) -> Result<Vec<Vec<u8>>, CliError> {
+    matches
+        .values_of("depends_on")
+        .map(|values| values.collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|id| {
+            if id.len() != 128 || !is_hex(id) {
+                return Err(CliError::UserError(format!(
+                    "Invalid transaction ID \"{}\"; it must be 128 lowercase hex characters",
+                    id
+                )));
+            }
+            batch::from_hex(id)
+        })
+        .collect()
+}
+
+/// Returns whether the subcommand's transaction will be submitted, rather than written to a file
+fn is_submitted(matches: &clap::ArgMatches) -> bool {
+    !matches.is_present("output") && !matches.is_present("batch_file")
+}
+
+/// Returns the value of the given global argument, which may have been given after any of the
+/// subcommands.
+fn global_value<'a>(matches: &'a clap::ArgMatches, name: &str) -> Option<&'a str> {
+    let mut matches = matches;
+    loop {
+        if let Some(value) = matches.value_of(name) {
+            return Some(value);
+        }
+        matches = matches.subcommand().1?;
+    }
+}
+
+/// Returns the signer of the key given with --batcher-key, or in the configuration file, if any
+///
+/// The key is only loaded by subcommands which sign batches, so that it is not needed, and its
+/// passphrase is not asked for, by the others.
+fn batcher_signer(
+    matches: &clap::ArgMatches,
+    config: &CliConfig,
+) -> Result<Option<Box<dyn Signer>>, CliError> {
+    match global_value(matches, "batcher_key").or_else(|| config.batcher_key()) {
+        Some(key_name) => Ok(Some(new_signer(
+            Some(key_name),
+            global_value(matches, "key_dir").or_else(|| config.key_dir()),
+        )?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns where the batches created by a subcommand are submitted
+fn submit_target(matches: &clap::ArgMatches, config: &CliConfig) -> Result<SubmitTarget, CliError> {
+    match matches
+        .value_of("target")
+        .or_else(|| config.target())
+        .unwrap_or("sawtooth")
+    {
+        "sawtooth" => Ok(SubmitTarget::Sawtooth),
+        "scabbard" => {
+            let circuit = matches
+                .value_of("circuit")
+                .or_else(|| config.circuit())
+                .ok_or_else(|| {
+                    CliError::UserError("--circuit is required with --target scabbard".into())
+                })?;
+            let service_id = matches
+                .value_of("service_id")
+                .or_else(|| config.service_id())
+                .ok_or_else(|| {
+                    CliError::UserError("--service-id is required with --target scabbard".into())
+                })?;
+
+            Ok(SubmitTarget::Scabbard {
+                circuit: circuit.into(),
+                service_id: service_id.into(),
+            })
+        }
+        #[cfg(feature = "zmq")]
+        "validator" => Ok(SubmitTarget::Validator),
+        #[cfg(not(feature = "zmq"))]
+        "validator" => Err(CliError::UserError(
+            "--target validator requires sabre to be built with the zmq feature".into(),
+        )),
+        target => Err(CliError::UserError(format!(
+            "Unsupported target: {}",
+            target
+        ))),
+    }
+}
+
+/// Returns the integer value of the given global argument, if it was given
+fn global_u64(matches: &clap::ArgMatches, name: &str) -> Result<Option<u64>, CliError> {
+    global_value(matches, name)
+        .map(|value| {
+            value
+                .parse::<u64>()
+                .map_err(|_| CliError::UserError(format!("{} must be an integer", name)))
+        })
+        .transpose()
+}
+
+/// Returns the number of times the given global flag was given, before or after any of the
+/// subcommands.
+///
+/// Global flags are also counted by the subcommands they precede, so the largest count is used.
+fn global_occurrences(matches: &clap::ArgMatches, name: &str) -> u64 {
+    let mut matches = matches;
+    let mut occurrences = 0;
+    loop {
+        occurrences = occurrences.max(matches.occurrences_of(name));
+        matches = match matches.subcommand().1 {
+            Some(matches) => matches,
+            None => return occurrences,
+        };
+    }
+}
+
+/// Returns whether the given global flag was given after any of the subcommands.
+fn global_present(matches: &clap::ArgMatches, name: &str) -> bool {
+    let mut matches = matches;
+    loop {
+        if matches.is_present(name) {
+            return true;
+        }
+        matches = match matches.subcommand().1 {
+            Some(matches) => matches,
+            None => return false,
+        };
+    }
+}
+
+/// A transaction created by a subcommand, along with how it should be batched and submitted
+struct PendingTransaction<'a> {
+    transaction: TransactionBuilder,
+    signer: Box<dyn Signer>,
+    url: &'a str,
+    target: SubmitTarget,
+    wait: u64,
+    output: Option<&'a str>,
+    /// If set, the transaction is added to this batch file instead of being submitted
+    batch_file: Option<&'a str>,
+    /// The IDs of the transactions which must be committed before this one
+    dependencies: Vec<Vec<u8>>,
+}
+
+#[derive(Serialize)]
+struct BatchFileTransactionOutput<'a> {
+    transaction_id: String,
+    batch_file: &'a str,
+    transaction_count: usize,
+}
+
+/// Signed batches created by a subcommand, along with how they should be submitted
+struct SignedBatches<'a> {
+    batches: Vec<Batch>,
+    url: &'a str,
+    target: SubmitTarget,
+    wait: u64,
+    /// If set, the batches are written to this file instead of being submitted
+    output: Option<&'a str>,
+    /// Limits on how quickly the batches are submitted
+    throttle: Throttle,
+}
+
+#[derive(Serialize)]
+struct BatchFileOutput<'a> {
+    batch_ids: Vec<String>,
+    output: &'a str,
+}
+
+/// The contents of a file decoded by `sabre batch inspect`, as displayed by the structured output
+/// formats
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum InspectOutput {
+    Batches(Vec<BatchInspectOutput>),
+    Transactions(Vec<TransactionInspectOutput>),
+}
+
+#[derive(Serialize)]
+struct BatchInspectOutput {
+    batch_id: String,
+    signer_public_key: String,
+    signature_valid: bool,
+    transactions: Vec<TransactionInspectOutput>,
+}
+
+impl BatchInspectOutput {
+    fn new(batch: &Batch, raw: bool) -> Result<Self, CliError> {
+        let header = batch::read_batch_header(batch)?;
+
+        Ok(BatchInspectOutput {
+            batch_id: batch.header_signature().into(),
+            signer_public_key: to_hex(header.signer_public_key()),
+            signature_valid: batch::verify_signature(
+                batch.header(),
+                batch.header_signature(),
+                header.signer_public_key(),
+            ),
+            transactions: batch
+                .transactions()
+                .iter()
+                .map(|transaction| {
+                    TransactionInspectOutput::new(
+                        transaction,
+                        Some(header.signer_public_key()),
+                        raw,
+                    )
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct TransactionInspectOutput {
+    transaction_id: String,
+    family_name: String,
+    family_version: String,
+    signer_public_key: String,
+    batcher_public_key: String,
+    /// Whether the batcher key is the key that signed the batch; absent for unbatched transactions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batcher_matches: Option<bool>,
+    signature_valid: bool,
+    payload_sha512: String,
+    payload_hash_valid: bool,
+    nonce: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    dependencies: Vec<String>,
+    /// The decoded Sabre action, or a description of a payload which is not one
+    #[serde(skip)]
+    description: String,
+    /// The payload, base64-encoded
+    payload: String,
 }
 
-fn namespace_permission(perm_matches: &clap::ArgMatches) -> Result<(String, u64), CliError> {
-    let namespace = perm_matches.value_of("namespace").unwrap();
-    let contract = perm_matches.value_of("contract").unwrap();
-    let key_name = perm_matches.value_of("key");
-    let url = perm_matches
-        .value_of("url")
-        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+impl TransactionInspectOutput {
+    /// Decode a transaction, checking its batcher key against the key of the batch containing it,
+    /// if any
+    fn new(
+        transaction: &Transaction,
+        batch_signer: Option<&[u8]>,
+        raw: bool,
+    ) -> Result<Self, CliError> {
+        let header = batch::read_header(transaction)?;
+        let payload_sha512 = sha512_hex(transaction.payload());
 
-    let wait = match value_t!(perm_matches, "wait", u64) {
-        Ok(wait) => wait,
-        Err(err) => match err.kind {
-            clap::ErrorKind::ArgumentNotFound => 0,
-            _ => return Err(CliError::UserError("Wait must be an integer".into())),
-        },
-    };
+        let description = match SabrePayload::from_bytes(transaction.payload()) {
+            Ok(payload) if header.family_name() == "sabre" && !raw => {
+                describe_action(payload.action())
+            }
+            _ => format!(
+                "payload ({} bytes):\n{}",
+                transaction.payload().len(),
+                indent(&describe_payload(transaction.payload(), raw), 2)
+            ),
+        };
 
-    let signer = new_signer(key_name)?;
+        let to_hex_list = |values: &[Vec<u8>]| values.iter().map(|value| to_hex(value)).collect();
 
-    let batch_link = if perm_matches.is_present("delete") {
-        let batch = DeleteNamespaceRegistryPermissionActionBuilder::new()
-            .with_namespace(namespace.into())
-            .into_payload_builder()?
-            .into_transaction_builder(&*signer)?
-            .into_batch_builder(&*signer)?
-            .build(&*signer)?;
+        Ok(TransactionInspectOutput {
+            transaction_id: transaction.header_signature().into(),
+            family_name: header.family_name().into(),
+            family_version: header.family_version().into(),
+            signer_public_key: to_hex(header.signer_public_key()),
+            batcher_public_key: to_hex(header.batcher_public_key()),
+            batcher_matches: batch_signer
+                .map(|batch_signer| batch_signer == header.batcher_public_key()),
+            signature_valid: batch::verify_signature(
+                transaction.header(),
+                transaction.header_signature(),
+                header.signer_public_key(),
+            ),
+            payload_hash_valid: payload_sha512 == to_hex(header.payload_hash()),
+            payload_sha512,
+            nonce: to_hex(header.nonce()),
+            inputs: to_hex_list(header.inputs()),
+            outputs: to_hex_list(header.outputs()),
+            dependencies: to_hex_list(header.dependencies()),
+            description,
+            payload: base64::encode(transaction.payload()),
+        })
+    }
+}
 
-        submit_batches(&url, vec![batch])?
-    } else {
-        let read = perm_matches.is_present("read");
-        let write = perm_matches.is_present("write");
+/// The result of a command which submits batches, as displayed by the structured output formats
+#[derive(Serialize)]
+struct SubmissionOutput<'a> {
+    #[serde(flatten)]
+    submission: &'a BatchSubmission,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<&'a StatusResponse>,
+}
 
-        if !(read || write) {
-            return Err(CliError::UserError("no permissions provided".into()));
-        }
+/// The result of a command which had no batches to submit, as displayed by the structured output
+/// formats
+#[derive(Serialize)]
+struct EmptySubmissionOutput {
+    batch_ids: Vec<String>,
+}
 
-        let batch = CreateNamespaceRegistryPermissionActionBuilder::new()
-            .with_namespace(namespace.into())
-            .with_contract_name(contract.into())
-            .with_read(read)
-            .with_write(write)
-            .into_payload_builder()?
-            .into_transaction_builder(&*signer)?
-            .into_batch_builder(&*signer)?
-            .build(&*signer)?;
+#[derive(Serialize)]
+struct KeyOutput<'a> {
+    name: &'a str,
+    public_key: Option<&'a str>,
+    private_key: &'a str,
+}
 
-        submit_batches(&url, vec![batch])?
-    };
+#[derive(Serialize)]
+struct ProfileOutput<'a> {
+    name: &'a str,
+    active: bool,
+    url: Option<&'a str>,
+    key: Option<&'a str>,
+    key_dir: Option<&'a str>,
+    batcher_key: Option<&'a str>,
+    auth: Option<&'a str>,
+    target: Option<&'a str>,
+    circuit: Option<&'a str>,
+    service_id: Option<&'a str>,
+}
 
-    Ok((batch_link, wait))
+#[derive(Serialize)]
+struct PublicKeyOutput<'a> {
+    public_key: &'a str,
 }
 
-fn contract_registry(cr_matches: &clap::ArgMatches) -> Result<(String, u64), CliError> {
-    let name = cr_matches.value_of("name").unwrap();
+#[derive(Serialize)]
+struct ContractRegistryOutput<'a> {
+    name: &'a str,
+    versions: Vec<&'a String>,
+    owners: &'a [String],
+}
 
-    let key_name = cr_matches.value_of("key");
+#[derive(Serialize)]
+struct ContractRegistryDetailOutput<'a> {
+    name: &'a str,
+    owners: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_memory_pages: Option<u32>,
+    versions: Vec<VersionOutput<'a>>,
+}
 
-    let url = cr_matches
-        .value_of("url")
-        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+impl<'a> From<&'a ContractRegistry> for ContractRegistryDetailOutput<'a> {
+    fn from(registry: &'a ContractRegistry) -> Self {
+        ContractRegistryDetailOutput {
+            name: registry.name(),
+            owners: registry.owners(),
+            max_memory_pages: Some(registry.max_memory_pages()).filter(|pages| *pages > 0),
+            versions: registry
+                .versions()
+                .iter()
+                .map(|version| VersionOutput {
+                    version: version.version(),
+                    contract_sha512: version.contract_sha512(),
+                    creator: version.creator(),
+                    paused: version.paused(),
+                })
+                .collect(),
+        }
+    }
+}
 
-    let wait = value_t!(cr_matches, "wait", u64).unwrap_or(0);
+#[derive(Serialize)]
+struct VersionOutput<'a> {
+    version: &'a str,
+    contract_sha512: &'a str,
+    creator: &'a str,
+    paused: bool,
+}
 
-    let signer = new_signer(key_name)?;
+#[derive(Serialize)]
+struct AddressOutput {
+    address: String,
+}
 
-    let owners = cr_matches
-        .values_of("owner")
-        .map(|values| values.map(|v| v.into()).collect());
+#[cfg(feature = "dry-run")]
+#[derive(Serialize)]
+struct DryRunOutput<'a> {
+    transaction_id: &'a str,
+    changes: Vec<StateChangeOutput<'a>>,
+    events: Vec<EventOutput<'a>>,
+    receipt_data: Vec<String>,
+}
 
-    let batch_link = if cr_matches.is_present("update") {
-        let owners = owners.ok_or_else(|| {
-            CliError::UserError("update action requires one or more --owner arguments".into())
-        })?;
+#[derive(Serialize)]
+struct BlockOutput<'a> {
+    block_num: u64,
+    block_id: &'a str,
+    changes: Vec<StateChangeOutput<'a>>,
+    events: Vec<EventOutput<'a>>,
+}
 
-        let batch = UpdateContractRegistryOwnersActionBuilder::new()
-            .with_name(name.into())
-            .with_owners(owners)
-            .into_payload_builder()?
-            .into_transaction_builder(&*signer)?
-            .into_batch_builder(&*signer)?
-            .build(&*signer)?;
+#[derive(Serialize)]
+struct ReceiptOutput<'a> {
+    transaction_id: &'a str,
+    changes: Vec<StateChangeOutput<'a>>,
+    events: Vec<EventOutput<'a>>,
+    data: Vec<String>,
+}
 
-        submit_batches(&url, vec![batch])?
-    } else if cr_matches.is_present("delete") {
-        if cr_matches.is_present("owner") {
-            return Err(CliError::UserError(
-                "arguments --delete and --owner conflict".into(),
-            ));
-        }
+#[derive(Serialize)]
+struct StateChangeOutput<'a> {
+    action: &'a str,
+    address: &'a str,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    state_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decoded: Option<DecodedStateOutput<'a>>,
+}
 
-        let batch = DeleteContractRegistryActionBuilder::new()
-            .with_name(name.into())
-            .into_payload_builder()?
-            .into_transaction_builder(&*signer)?
-            .into_batch_builder(&*signer)?
-            .build(&*signer)?;
+#[derive(Serialize)]
+struct EventOutput<'a> {
+    event_type: &'a str,
+    attributes: Vec<EventAttributeOutput<'a>>,
+    data: String,
+}
 
-        submit_batches(&url, vec![batch])?
-    } else {
-        let owners = owners.ok_or_else(|| {
-            CliError::UserError("create action requires one or more --owner arguments".into())
-        })?;
+#[derive(Serialize)]
+struct EventAttributeOutput<'a> {
+    key: &'a str,
+    value: &'a str,
+}
 
-        let batch = CreateContractRegistryActionBuilder::new()
-            .with_name(name.into())
-            .with_owners(owners)
-            .into_payload_builder()?
-            .into_transaction_builder(&*signer)?
-            .into_batch_builder(&*signer)?
-            .build(&*signer)?;
+#[derive(Serialize)]
+struct VerifyOutput<'a> {
+    name: &'a str,
+    version: &'a str,
+    local_sha512: &'a str,
+    registered_sha512: &'a str,
+    matches: bool,
+}
 
-        submit_batches(&url, vec![batch])?
-    };
-    Ok((batch_link, wait))
+#[derive(Serialize)]
+struct NewContractOutput<'a> {
+    name: &'a str,
+    path: &'a str,
+    prefix: &'a str,
+    files: &'a [String],
 }
 
-fn smart_permission(sp_matches: &clap::ArgMatches) -> Result<(String, u64), CliError> {
-    let url = sp_matches
-        .value_of("url")
-        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+#[derive(Serialize)]
+struct BuildOutput<'a> {
+    path: &'a str,
+    size: usize,
+    unstripped_size: usize,
+}
 
-    let wait = match value_t!(sp_matches, "wait", u64) {
-        Ok(wait) => wait,
-        Err(err) => match err.kind {
-            clap::ErrorKind::ArgumentNotFound => 0,
-            _ => return Err(CliError::UserError("Wait must be an integer".into())),
-        },
-    };
+#[derive(Serialize)]
+struct PackOutput<'a> {
+    archive: &'a str,
+    name: &'a str,
+    version: &'a str,
+    inputs: &'a [String],
+    outputs: &'a [String],
+    contract_sha512: &'a str,
+}
 
-    let batch_link = match sp_matches.subcommand() {
-        ("create", Some(m)) => {
-            let org_id = m.value_of("org_id").unwrap();
-            let name = m.value_of("name").unwrap();
-            let filename = m.value_of("filename").unwrap();
-            let key = m.value_of("key");
+#[derive(Serialize)]
+struct ContractDiffOutput<'a> {
+    name: &'a str,
+    from: ContractVersionOutput,
+    to: ContractVersionOutput,
+    inputs: ListDiffOutput,
+    outputs: ListDiffOutput,
+    exports: ListDiffOutput,
+    imports: ListDiffOutput,
+    owners: &'a [String],
+}
 
-            let function = load_bytes_from_file(filename)?;
+/// The details of a contract version compared by `sabre diff`
+#[derive(Serialize)]
+struct ContractVersionOutput {
+    version: String,
+    wasm_sha512: String,
+    wasm_size: usize,
+    /// The key that uploaded the version
+    creator: String,
+    state_message: Option<String>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    /// The names of the exported functions
+    exports: Vec<String>,
+    /// The imports, as "module.field"
+    imports: Vec<String>,
+}
 
-            let signer = new_signer(key)?;
-            let batch = CreateSmartPermissionActionBuilder::new()
-                .with_name(name.into())
-                .with_org_id(org_id.into())
-                .with_function(function)
-                .into_payload_builder()?
-                .into_transaction_builder(&*signer)?
-                .into_batch_builder(&*signer)?
-                .build(&*signer)?;
+impl ContractVersionOutput {
+    fn new(contract: &Contract) -> Result<ContractVersionOutput, CliError> {
+        let info = wasm::inspect(contract.contract())?;
 
-            submit_batches(&url, vec![batch])?
-        }
-        ("update", Some(m)) => {
-            let org_id = m.value_of("org_id").unwrap();
-            let name = m.value_of("name").unwrap();
-            let filename = m.value_of("filename").unwrap();
-            let key = m.value_of("key");
+        Ok(ContractVersionOutput {
+            version: contract.version().to_string(),
+            wasm_sha512: sha512_hex(contract.contract()),
+            wasm_size: contract.contract().len(),
+            creator: contract.creator().to_string(),
+            state_message: StateSchema::from_contract(contract.contract())?
+                .map(|schema| schema.message),
+            inputs: contract.inputs().to_vec(),
+            outputs: contract.outputs().to_vec(),
+            exports: info
+                .exports
+                .into_iter()
+                .filter(|(_, kind)| *kind == wasm::Kind::Function)
+                .map(|(name, _)| name)
+                .collect(),
+            imports: info
+                .imports
+                .into_iter()
+                .map(|import| format!("{}.{}", import.module, import.field))
+                .collect(),
+        })
+    }
+}
 
-            let function = load_bytes_from_file(filename)?;
+/// The entries added to and removed from a list between two contract versions
+#[derive(Serialize)]
+struct ListDiffOutput {
+    added: Vec<String>,
+    removed: Vec<String>,
+}
 
-            let signer = new_signer(key)?;
-            let batch = UpdateSmartPermissionActionBuilder::new()
-                .with_name(name.to_string())
-                .with_org_id(org_id.to_string())
-                .with_function(function)
-                .into_payload_builder()?
-                .into_transaction_builder(&*signer)?
-                .into_batch_builder(&*signer)?
-                .build(&*signer)?;
+impl ListDiffOutput {
+    fn new(from: &[String], to: &[String]) -> Self {
+        let mut added = to
+            .iter()
+            .filter(|entry| !from.contains(entry))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut removed = from
+            .iter()
+            .filter(|entry| !to.contains(entry))
+            .cloned()
+            .collect::<Vec<_>>();
+        added.sort();
+        removed.sort();
 
-            submit_batches(&url, vec![batch])?
-        }
-        ("delete", Some(m)) => {
-            let org_id = m.value_of("org_id").unwrap();
-            let name = m.value_of("name").unwrap();
-            let key = m.value_of("key");
+        ListDiffOutput { added, removed }
+    }
+}
 
-            let signer = new_signer(key)?;
-            let batch = DeleteSmartPermissionActionBuilder::new()
-                .with_name(name.to_string())
-                .with_org_id(org_id.to_string())
-                .into_payload_builder()?
-                .into_transaction_builder(&*signer)?
-                .into_batch_builder(&*signer)?
-                .build(&*signer)?;
+#[derive(Serialize)]
+struct ContractOutput<'a> {
+    name: &'a str,
+    version: &'a str,
+    inputs: &'a [String],
+    outputs: &'a [String],
+    creator: &'a str,
+    wasm_size: usize,
+    wasm_sha512: String,
+}
 
-            submit_batches(&url, vec![batch])?
-        }
-        _ => {
-            return Err(CliError::UserError(
-                "Unrecognized smart permission subcommand".into(),
-            ));
+impl<'a> From<&'a Contract> for ContractOutput<'a> {
+    fn from(contract: &'a Contract) -> Self {
+        ContractOutput {
+            name: contract.name(),
+            version: contract.version(),
+            inputs: contract.inputs(),
+            outputs: contract.outputs(),
+            creator: contract.creator(),
+            wasm_size: contract.contract().len(),
+            wasm_sha512: sha512_hex(contract.contract()),
         }
-    };
-
-    Ok((batch_link, wait))
+    }
 }
 
-fn contract(contract_matches: &clap::ArgMatches) -> Result<(), CliError> {
-    match contract_matches.subcommand() {
-        ("list", Some(matches)) => {
-            let url = matches.value_of("url").unwrap_or(DEFAULT_REST_API_ENDPOINT);
+#[derive(Serialize)]
+struct ContractChunkOutput<'a> {
+    name: &'a str,
+    version: &'a str,
+    index: u32,
+    creator: &'a str,
+    size: usize,
+    sha512: String,
+}
 
-            let format = matches
-                .value_of("format")
-                .expect("default not set for --format");
+impl<'a> From<&'a ContractChunk> for ContractChunkOutput<'a> {
+    fn from(chunk: &'a ContractChunk) -> Self {
+        ContractChunkOutput {
+            name: chunk.name(),
+            version: chunk.version(),
+            index: chunk.index(),
+            creator: chunk.creator(),
+            size: chunk.chunk().len(),
+            sha512: sha512_hex(chunk.chunk()),
+        }
+    }
+}
 
-            let registries = state::get_state_with_prefix(url, &CONTRACT_REGISTRY_ADDRESS_PREFIX)?
-                .into_iter()
-                .map(|entry| {
-                    base64::decode(entry.data)
-                        .map_err(|_| CliError::UserError("Unable to decode state".into()))
-                        .and_then(|bytes| {
-                            ContractRegistryList::from_bytes(&bytes)
-                                .map_err(CliError::ProtoConversionError)
-                        })
-                })
-                .collect::<Result<Vec<_>, _>>()?;
+#[derive(Serialize)]
+struct StateListOutput<'a> {
+    address: &'a str,
+    #[serde(rename = "type")]
+    state_type: Option<&'static str>,
+    size: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    names: Vec<String>,
+}
 
-            let mut data = vec![
-                // Headers
-                vec![
-                    "NAME".to_string(),
-                    "VERSIONS".to_string(),
-                    "OWNERS".to_string(),
-                ],
-            ];
-            for registry_list in registries {
-                for registry in registry_list.registries() {
-                    let name = registry.name().to_string();
-                    let versions = registry
-                        .versions()
-                        .iter()
-                        .map(|version| version.version().to_string())
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    let owners = registry.owners().join(", ");
+#[derive(Serialize)]
+struct QueryOutput<'a> {
+    address: &'a str,
+    contract: &'a str,
+    version: &'a str,
+    message: &'a str,
+    value: serde_json::Value,
+}
 
-                    data.push(vec![name, versions, owners]);
-                }
-            }
+#[derive(Serialize)]
+struct StateEntryOutput<'a> {
+    address: &'a str,
+    #[serde(rename = "type")]
+    state_type: Option<&'static str>,
+    size: usize,
+    data: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decoded: Option<DecodedStateOutput<'a>>,
+}
 
-            if format == "csv" {
-                for row in data {
-                    println!("{}", row.join(","))
-                }
-            } else {
-                print_table(data);
-            }
+/// The objects in a decoded state entry, as displayed by the structured output formats
+#[derive(Serialize)]
+#[serde(untagged)]
+enum DecodedStateOutput<'a> {
+    NamespaceRegistries(Vec<NamespaceRegistryOutput<'a>>),
+    ContractRegistries(Vec<ContractRegistryDetailOutput<'a>>),
+    Contracts(Vec<ContractOutput<'a>>),
+    SmartPermissions(Vec<SmartPermissionOutput<'a>>),
+    ContractChunks(Vec<ContractChunkOutput<'a>>),
+}
 
-            Ok(())
+impl<'a> From<&'a DecodedState> for DecodedStateOutput<'a> {
+    fn from(decoded: &'a DecodedState) -> Self {
+        match decoded {
+            DecodedState::NamespaceRegistries(list) => DecodedStateOutput::NamespaceRegistries(
+                list.registries()
+                    .iter()
+                    .map(NamespaceRegistryOutput::from)
+                    .collect(),
+            ),
+            DecodedState::ContractRegistries(list) => DecodedStateOutput::ContractRegistries(
+                list.registries()
+                    .iter()
+                    .map(ContractRegistryDetailOutput::from)
+                    .collect(),
+            ),
+            DecodedState::Contracts(list) => DecodedStateOutput::Contracts(
+                list.contracts().iter().map(ContractOutput::from).collect(),
+            ),
+            DecodedState::SmartPermissions(list) => DecodedStateOutput::SmartPermissions(
+                list.smart_permissions()
+                    .iter()
+                    .map(SmartPermissionOutput::from)
+                    .collect(),
+            ),
+            DecodedState::ContractChunks(list) => DecodedStateOutput::ContractChunks(
+                list.chunks()
+                    .iter()
+                    .map(ContractChunkOutput::from)
+                    .collect(),
+            ),
         }
-        ("show", Some(matches)) => {
-            let url = matches.value_of("url").unwrap_or(DEFAULT_REST_API_ENDPOINT);
-
-            let contract = matches
-                .value_of("contract")
-                .ok_or_else(|| CliError::UserError("Missing contract".into()))?;
+    }
+}
 
-            let (name, version) = parse_name_version(contract).ok_or_else(|| {
-                CliError::UserError("--contract must be of the form 'name:version'".into())
-            })?;
+#[derive(Serialize)]
+struct NamespaceRegistryOutput<'a> {
+    namespace: &'a str,
+    owners: &'a [String],
+    permissions: Vec<PermissionOutput<'a>>,
+}
 
-            let address = to_hex(&compute_contract_address(name, version).map_err(|err| {
-                CliError::UserError(format!("Unable to get contract address: {}", err))
-            })?);
+impl<'a> From<&'a NamespaceRegistry> for NamespaceRegistryOutput<'a> {
+    fn from(registry: &'a NamespaceRegistry) -> Self {
+        NamespaceRegistryOutput {
+            namespace: registry.namespace(),
+            owners: registry.owners(),
+            permissions: registry
+                .permissions()
+                .iter()
+                .map(|permission| PermissionOutput {
+                    contract_name: permission.contract_name(),
+                    read: permission.read(),
+                    write: permission.write(),
+                })
+                .collect(),
+        }
+    }
+}
 
-            let contract_bytes = state::get_state_with_prefix(url, &address)?
-                .get(0)
-                .cloned()
-                .ok_or_else(|| CliError::UserError(format!("contract '{}' not found", contract)))?;
+#[derive(Serialize)]
+struct NamespacePermissionOutput<'a> {
+    namespace: &'a str,
+    contract_name: &'a str,
+    read: bool,
+    write: bool,
+}
 
-            let contract_list = ContractList::from_bytes(
-                &base64::decode(contract_bytes.data)
-                    .map_err(|_| CliError::UserError("Unable to decode state".into()))?,
-            )?;
-            let contract = contract_list
-                .contracts()
-                .get(0)
-                .ok_or_else(|| CliError::UserError("contract list is empty".into()))?;
+#[derive(Serialize)]
+struct PermissionOutput<'a> {
+    contract_name: &'a str,
+    read: bool,
+    write: bool,
+}
 
-            println!("{} {}", contract.name(), contract.version());
-            println!("  inputs:");
-            for input in contract.inputs() {
-                println!("  - {}", input);
-            }
-            println!("  outputs:");
-            for output in contract.outputs() {
-                println!("  - {}", output);
-            }
-            println!("  creator: {}", contract.creator());
+#[derive(Serialize)]
+struct SmartPermissionOutput<'a> {
+    name: &'a str,
+    org_id: &'a str,
+    function_size: usize,
+    function_sha512: String,
+}
 
-            Ok(())
+impl<'a> From<&'a SmartPermission> for SmartPermissionOutput<'a> {
+    fn from(smart_permission: &'a SmartPermission) -> Self {
+        SmartPermissionOutput {
+            name: smart_permission.name(),
+            org_id: smart_permission.org_id(),
+            function_size: smart_permission.function().len(),
+            function_sha512: sha512_hex(smart_permission.function()),
         }
-        _ => Err(CliError::UserError("Invalid Subcommand".into())),
+    }
+}
+
+/// Print each row as comma separated values, quoting values which contain commas, quotes or
+/// line breaks
+fn print_csv(table: Vec<Vec<String>>) {
+    for row in table {
+        let values = row
+            .iter()
+            .map(|value| {
+                if value.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", value.replace('"', "\"\""))
+                } else {
+                    value.to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+        println!("{}", values.join(","));
     }
 }
 
@@ -711,12 +5891,112 @@ fn parse_name_version(name_version_string: &str) -> Option<(&str, &str)> {
     }
 }
 
-fn to_hex(bytes: &[u8]) -> String {
-    bytes
-        .iter()
-        .map(|b| format!("{:02x}", b))
-        .collect::<Vec<_>>()
-        .join("")
+fn sha512_hex(bytes: &[u8]) -> String {
+    let mut sha = Sha512::new();
+    sha.input(bytes);
+    sha.result_str()
+}
+
+fn smart_permission_query(
+    sp_matches: &clap::ArgMatches,
+    config: &CliConfig,
+    client: &HttpClient,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    let url = sp_matches
+        .value_of("url")
+        .or_else(|| config.url())
+        .unwrap_or(DEFAULT_REST_API_ENDPOINT);
+
+    match sp_matches.subcommand() {
+        ("list", Some(m)) => {
+            let org_id = m.value_of("org_id").unwrap();
+
+            // Smart permission addresses start with the first 3 bytes of the org ID's hash
+            let prefix = format!(
+                "{}{}",
+                SMART_PERMISSION_ADDRESS_PREFIX,
+                &sha512_hex(org_id.as_bytes())[..6]
+            );
+
+            let permission_lists = state::get_state_with_prefix(client, url, &prefix)?
+                .into_iter()
+                .map(|entry| {
+                    base64::decode(entry.data)
+                        .map_err(|_| CliError::UserError("Unable to decode state".into()))
+                        .and_then(|bytes| {
+                            SmartPermissionList::from_bytes(&bytes)
+                                .map_err(CliError::ProtoConversionError)
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut smart_permissions = permission_lists
+                .iter()
+                .flat_map(|permission_list| permission_list.smart_permissions())
+                .filter(|smart_permission| smart_permission.org_id() == org_id)
+                .collect::<Vec<_>>();
+            smart_permissions.sort_by_key(|smart_permission| smart_permission.name());
+
+            if format == OutputFormat::Json || format == OutputFormat::Yaml {
+                let smart_permissions = smart_permissions
+                    .into_iter()
+                    .map(SmartPermissionOutput::from)
+                    .collect::<Vec<_>>();
+
+                return print_document(format, &smart_permissions);
+            }
+
+            let mut data = vec![
+                // Headers
+                vec![
+                    "NAME".to_string(),
+                    "ORG_ID".to_string(),
+                    "SIZE".to_string(),
+                    "SHA512".to_string(),
+                ],
+            ];
+            for smart_permission in smart_permissions {
+                data.push(vec![
+                    smart_permission.name().to_string(),
+                    smart_permission.org_id().to_string(),
+                    smart_permission.function().len().to_string(),
+                    sha512_hex(smart_permission.function()),
+                ]);
+            }
+
+            if format == OutputFormat::Csv {
+                print_csv(data);
+            } else {
+                print_table(data);
+            }
+
+            Ok(())
+        }
+        ("show", Some(m)) => {
+            let org_id = m.value_of("org_id").unwrap();
+            let name = m.value_of("name").unwrap();
+
+            let smart_permission = get_smart_permission(client, url, org_id, name)?;
+
+            match format {
+                OutputFormat::Json | OutputFormat::Yaml => {
+                    return print_document(format, &SmartPermissionOutput::from(&smart_permission))
+                }
+                OutputFormat::Csv => {
+                    return Err(CliError::UserError(
+                        "csv format is only supported by list commands".into(),
+                    ))
+                }
+                OutputFormat::Human => (),
+            }
+
+            print!("{}", describe_smart_permission(&smart_permission));
+
+            Ok(())
+        }
+        _ => Err(CliError::UserError("Invalid Subcommand".into())),
+    }
 }
 
 fn load_bytes_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, CliError> {
@@ -743,6 +6023,6 @@ fn load_bytes_from_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, CliError> {
 fn main() {
     if let Err(e) = run() {
         println!("{}", e);
-        std::process::exit(1);
+        std::process::exit(e.exit_code());
     }
 }
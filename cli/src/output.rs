@@ -0,0 +1,125 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which assist with formatting command output
+
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::Value;
+use yaml_rust::{Yaml, YamlEmitter};
+
+use crate::error::CliError;
+
+/// The names of the supported output formats, as accepted by --format
+pub const OUTPUT_FORMATS: &[&str] = &["human", "csv", "json", "yaml"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Free-form text intended to be read by a person
+    Human,
+    /// Comma separated values; only supported by list commands
+    Csv,
+    Json,
+    Yaml,
+}
+
+impl FromStr for OutputFormat {
+    type Err = CliError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(OutputFormat::Human),
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            _ => Err(CliError::UserError(format!(
+                "Unsupported output format: {}; must be one of {}",
+                s,
+                OUTPUT_FORMATS.join(", ")
+            ))),
+        }
+    }
+}
+
+/// Print the given value to stdout as a JSON or YAML document
+///
+/// Returns an error if the format is not one of the structured formats.
+pub fn print_document<T: Serialize>(format: OutputFormat, value: &T) -> Result<(), CliError> {
+    let value = serde_json::to_value(value)
+        .map_err(|err| CliError::OutputError(format!("Unable to serialize output: {}", err)))?;
+
+    match format {
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(&value).map_err(|err| {
+                CliError::OutputError(format!("Unable to serialize output: {}", err))
+            })?;
+            println!("{}", json);
+        }
+        OutputFormat::Yaml => {
+            let mut yaml = String::new();
+            YamlEmitter::new(&mut yaml)
+                .dump(&to_yaml(&value))
+                .map_err(|err| {
+                    CliError::OutputError(format!("Unable to serialize output: {:?}", err))
+                })?;
+            println!("{}", yaml);
+        }
+        _ => {
+            return Err(CliError::UserError(format!(
+                "{:?} is not a structured output format",
+                format
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the given value to stdout as one document of a stream of JSON or YAML documents
+///
+/// JSON documents are printed on a single line, so that the stream can be read line by line;
+/// YAML documents are separated by their `---` markers.
+pub fn print_stream_document<T: Serialize>(
+    format: OutputFormat,
+    value: &T,
+) -> Result<(), CliError> {
+    if format != OutputFormat::Json {
+        return print_document(format, value);
+    }
+
+    let json = serde_json::to_string(value)
+        .map_err(|err| CliError::OutputError(format!("Unable to serialize output: {}", err)))?;
+    println!("{}", json);
+
+    Ok(())
+}
+
+fn to_yaml(value: &Value) -> Yaml {
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(b) => Yaml::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        Value::String(s) => Yaml::String(s.clone()),
+        Value::Array(values) => Yaml::Array(values.iter().map(to_yaml).collect()),
+        Value::Object(map) => Yaml::Hash(
+            map.iter()
+                .map(|(key, value)| (Yaml::String(key.clone()), to_yaml(value)))
+                .collect(),
+        ),
+    }
+}
@@ -0,0 +1,609 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which encode contract payloads from human-authored documents, and decode
+//! protobuf messages back into them
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use protobuf::descriptor::{
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FieldDescriptorProto_Label,
+    FieldDescriptorProto_Type, FileDescriptorSet,
+};
+use protobuf::wire_format::WireType;
+use protobuf::{CodedInputStream, CodedOutputStream, Message, ProtobufError};
+use serde_json::{Number, Value};
+
+use crate::error::CliError;
+
+/// The names of the supported payload formats, as accepted by --payload-format
+pub const PAYLOAD_FORMATS: &[&str] = &["raw", "hex", "json-cbor", "json-proto:<message>"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PayloadFormat {
+    /// The payload is used as is
+    Raw,
+    /// The payload is a hex-encoded string
+    Hex,
+    /// The payload is a JSON document, encoded as CBOR
+    JsonCbor,
+    /// The payload is a JSON document, encoded as the named protobuf message
+    JsonProto(String),
+}
+
+impl FromStr for PayloadFormat {
+    type Err = CliError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(PayloadFormat::Raw),
+            "hex" => Ok(PayloadFormat::Hex),
+            "json-cbor" => Ok(PayloadFormat::JsonCbor),
+            _ => match s.strip_prefix("json-proto:") {
+                Some(message) if !message.is_empty() => Ok(PayloadFormat::JsonProto(
+                    message.trim_start_matches('.').into(),
+                )),
+                _ => Err(CliError::UserError(format!(
+                    "Unsupported payload format: {}; must be one of {}",
+                    s,
+                    PAYLOAD_FORMATS.join(", ")
+                ))),
+            },
+        }
+    }
+}
+
+/// Convert the payload to the bytes the contract expects
+///
+/// `descriptor_set` is the serialized FileDescriptorSet that defines the message of a
+/// `json-proto` payload.
+pub fn encode_payload(
+    payload: Vec<u8>,
+    format: &PayloadFormat,
+    descriptor_set: Option<&[u8]>,
+) -> Result<Vec<u8>, CliError> {
+    match format {
+        PayloadFormat::Raw => Ok(payload),
+        PayloadFormat::Hex => decode_hex(&payload),
+        PayloadFormat::JsonCbor => serde_cbor::to_vec(&parse_json(&payload)?)
+            .map_err(|err| CliError::UserError(format!("Unable to encode payload: {}", err))),
+        PayloadFormat::JsonProto(message) => {
+            let descriptor_set = descriptor_set.ok_or_else(|| {
+                CliError::UserError(
+                    "--descriptor-set is required with --payload-format json-proto".into(),
+                )
+            })?;
+            let descriptor_set = FileDescriptorSet::parse_from_bytes(descriptor_set)
+                .map_err(|err| CliError::UserError(format!("Invalid descriptor set: {}", err)))?;
+
+            Descriptors::new(&descriptor_set).encode(message, &parse_json(&payload)?)
+        }
+    }
+}
+
+/// Decode the bytes as the named protobuf message, into a JSON document following the proto3
+/// JSON mapping
+///
+/// `descriptor_set` is the serialized FileDescriptorSet that defines the message.
+pub fn decode_message(
+    bytes: &[u8],
+    message: &str,
+    descriptor_set: &[u8],
+) -> Result<Value, CliError> {
+    let descriptor_set = FileDescriptorSet::parse_from_bytes(descriptor_set)
+        .map_err(|err| CliError::UserError(format!("Invalid descriptor set: {}", err)))?;
+
+    Descriptors::new(&descriptor_set).decode(message.trim_start_matches('.'), bytes)
+}
+
+fn parse_json(payload: &[u8]) -> Result<Value, CliError> {
+    serde_json::from_slice(payload)
+        .map_err(|err| CliError::UserError(format!("Invalid JSON payload: {}", err)))
+}
+
+fn decode_hex(payload: &[u8]) -> Result<Vec<u8>, CliError> {
+    let invalid = || CliError::UserError("Invalid hex payload".into());
+
+    let hex = std::str::from_utf8(payload).map_err(|_| invalid())?.trim();
+    if hex.len() % 2 != 0 {
+        return Err(invalid());
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(invalid)
+        })
+        .collect()
+}
+
+/// The messages and enums of a descriptor set, by fully-qualified name without the leading '.'
+struct Descriptors<'a> {
+    messages: HashMap<String, &'a DescriptorProto>,
+    enums: HashMap<String, &'a EnumDescriptorProto>,
+}
+
+impl<'a> Descriptors<'a> {
+    fn new(descriptor_set: &'a FileDescriptorSet) -> Self {
+        let mut descriptors = Descriptors {
+            messages: HashMap::new(),
+            enums: HashMap::new(),
+        };
+
+        for file in descriptor_set.get_file() {
+            let package = file.get_package();
+            for message in file.get_message_type() {
+                descriptors.add_message(package, message);
+            }
+            for enum_type in file.get_enum_type() {
+                descriptors
+                    .enums
+                    .insert(qualify(package, enum_type.get_name()), enum_type);
+            }
+        }
+
+        descriptors
+    }
+
+    fn add_message(&mut self, scope: &str, message: &'a DescriptorProto) {
+        let name = qualify(scope, message.get_name());
+        for nested in message.get_nested_type() {
+            self.add_message(&name, nested);
+        }
+        for enum_type in message.get_enum_type() {
+            self.enums
+                .insert(qualify(&name, enum_type.get_name()), enum_type);
+        }
+        self.messages.insert(name, message);
+    }
+
+    fn message(&self, name: &str) -> Result<&'a DescriptorProto, CliError> {
+        self.messages
+            .get(name.trim_start_matches('.'))
+            .copied()
+            .ok_or_else(|| {
+                CliError::UserError(format!("Message {} is not in the descriptor set", name))
+            })
+    }
+
+    /// Encode the JSON object as the named message, following the proto3 JSON mapping
+    fn encode(&self, name: &str, value: &Value) -> Result<Vec<u8>, CliError> {
+        let message = self.message(name)?;
+        let object = value.as_object().ok_or_else(|| {
+            CliError::UserError(format!("Expected a JSON object for message {}", name))
+        })?;
+
+        let mut bytes = Vec::new();
+        let mut os = CodedOutputStream::vec(&mut bytes);
+
+        for (key, value) in object {
+            let field = message
+                .get_field()
+                .iter()
+                .find(|field| field.get_name() == key || field.get_json_name() == key)
+                .ok_or_else(|| {
+                    CliError::UserError(format!("Unknown field {} in message {}", key, name))
+                })?;
+
+            match value {
+                Value::Null => (),
+                Value::Array(values)
+                    if field.get_label() == FieldDescriptorProto_Label::LABEL_REPEATED =>
+                {
+                    for value in values {
+                        self.encode_field(&mut os, field, value)?;
+                    }
+                }
+                Value::Object(entries) if self.is_map(field) => {
+                    let entry = self.message(field.get_type_name())?;
+                    for (key, value) in entries {
+                        let key = map_key(entry, key)?;
+                        let mut entry_object = serde_json::Map::new();
+                        entry_object.insert("key".into(), key);
+                        entry_object.insert("value".into(), value.clone());
+                        self.encode_field(&mut os, field, &Value::Object(entry_object))?;
+                    }
+                }
+                _ if field.get_label() == FieldDescriptorProto_Label::LABEL_REPEATED => {
+                    return Err(CliError::UserError(format!(
+                        "Expected a JSON array for repeated field {}",
+                        key
+                    )))
+                }
+                _ => self.encode_field(&mut os, field, value)?,
+            }
+        }
+
+        os.flush().map_err(|err| {
+            CliError::UserError(format!("Unable to encode message {}: {}", name, err))
+        })?;
+        drop(os);
+
+        Ok(bytes)
+    }
+
+    /// Decode the bytes as the named message, following the proto3 JSON mapping
+    ///
+    /// Fields that are not in the message's descriptor are skipped.
+    fn decode(&self, name: &str, bytes: &[u8]) -> Result<Value, CliError> {
+        let message = self.message(name)?;
+        let invalid = |err: ProtobufError| {
+            CliError::UserError(format!("Unable to decode message {}: {}", name, err))
+        };
+
+        let mut object = serde_json::Map::new();
+        let mut is = CodedInputStream::from_bytes(bytes);
+        while !is.eof().map_err(invalid)? {
+            let (number, wire_type) = is.read_tag_unpack().map_err(invalid)?;
+            let field = match message
+                .get_field()
+                .iter()
+                .find(|field| field.get_number() as u32 == number)
+            {
+                Some(field) => field,
+                None => {
+                    is.skip_field(wire_type).map_err(invalid)?;
+                    continue;
+                }
+            };
+            let key = json_name(field).to_string();
+
+            if self.is_map(field) {
+                let entry = self.message(field.get_type_name())?;
+                let bytes = is.read_bytes().map_err(invalid)?;
+                let mut decoded = match self.decode(field.get_type_name(), &bytes)? {
+                    Value::Object(decoded) => decoded,
+                    _ => serde_json::Map::new(),
+                };
+                let mut entry_value = |number| {
+                    let field = entry
+                        .get_field()
+                        .iter()
+                        .find(|field| field.get_number() == number);
+                    let name = field.map(json_name).unwrap_or_default();
+                    decoded
+                        .remove(name)
+                        .or_else(|| field.map(|field| self.default_value(field)))
+                        .unwrap_or(Value::Null)
+                };
+
+                let entry_key = match entry_value(1) {
+                    Value::String(entry_key) => entry_key,
+                    entry_key => entry_key.to_string(),
+                };
+                let entry_value = entry_value(2);
+                if let Value::Object(entries) = object
+                    .entry(key)
+                    .or_insert_with(|| Value::Object(serde_json::Map::new()))
+                {
+                    entries.insert(entry_key, entry_value);
+                }
+            } else if field.get_label() == FieldDescriptorProto_Label::LABEL_REPEATED {
+                let mut values = vec![];
+                if wire_type == WireType::WireTypeLengthDelimited && is_packable(field) {
+                    let bytes = is.read_bytes().map_err(invalid)?;
+                    let mut packed = CodedInputStream::from_bytes(&bytes);
+                    while !packed.eof().map_err(invalid)? {
+                        values.push(self.decode_field(&mut packed, field)?);
+                    }
+                } else {
+                    values.push(self.decode_field(&mut is, field)?);
+                }
+                if let Value::Array(array) =
+                    object.entry(key).or_insert_with(|| Value::Array(vec![]))
+                {
+                    array.extend(values);
+                }
+            } else {
+                let value = self.decode_field(&mut is, field)?;
+                object.insert(key, value);
+            }
+        }
+
+        Ok(Value::Object(object))
+    }
+
+    fn decode_field(
+        &self,
+        is: &mut CodedInputStream,
+        field: &FieldDescriptorProto,
+    ) -> Result<Value, CliError> {
+        let invalid = |err: ProtobufError| {
+            CliError::UserError(format!(
+                "Unable to decode field {}: {}",
+                field.get_name(),
+                err
+            ))
+        };
+
+        // 64-bit integers are strings in the proto3 JSON mapping, since JSON numbers may not
+        // hold them exactly
+        Ok(match field.get_field_type() {
+            FieldDescriptorProto_Type::TYPE_INT32 => is.read_int32().map_err(invalid)?.into(),
+            FieldDescriptorProto_Type::TYPE_SINT32 => is.read_sint32().map_err(invalid)?.into(),
+            FieldDescriptorProto_Type::TYPE_SFIXED32 => is.read_sfixed32().map_err(invalid)?.into(),
+            FieldDescriptorProto_Type::TYPE_UINT32 => is.read_uint32().map_err(invalid)?.into(),
+            FieldDescriptorProto_Type::TYPE_FIXED32 => is.read_fixed32().map_err(invalid)?.into(),
+            FieldDescriptorProto_Type::TYPE_INT64 => {
+                Value::String(is.read_int64().map_err(invalid)?.to_string())
+            }
+            FieldDescriptorProto_Type::TYPE_SINT64 => {
+                Value::String(is.read_sint64().map_err(invalid)?.to_string())
+            }
+            FieldDescriptorProto_Type::TYPE_SFIXED64 => {
+                Value::String(is.read_sfixed64().map_err(invalid)?.to_string())
+            }
+            FieldDescriptorProto_Type::TYPE_UINT64 => {
+                Value::String(is.read_uint64().map_err(invalid)?.to_string())
+            }
+            FieldDescriptorProto_Type::TYPE_FIXED64 => {
+                Value::String(is.read_fixed64().map_err(invalid)?.to_string())
+            }
+            FieldDescriptorProto_Type::TYPE_FLOAT => {
+                from_float(f64::from(is.read_float().map_err(invalid)?))
+            }
+            FieldDescriptorProto_Type::TYPE_DOUBLE => {
+                from_float(is.read_double().map_err(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_BOOL => Value::Bool(is.read_bool().map_err(invalid)?),
+            FieldDescriptorProto_Type::TYPE_STRING => {
+                Value::String(is.read_string().map_err(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_BYTES => {
+                Value::String(base64::encode(is.read_bytes().map_err(invalid)?))
+            }
+            FieldDescriptorProto_Type::TYPE_ENUM => {
+                let number = is.read_int32().map_err(invalid)?;
+                self.enums
+                    .get(field.get_type_name().trim_start_matches('.'))
+                    .and_then(|enum_type| {
+                        enum_type
+                            .get_value()
+                            .iter()
+                            .find(|enum_value| enum_value.get_number() == number)
+                    })
+                    .map(|enum_value| Value::String(enum_value.get_name().into()))
+                    .unwrap_or_else(|| number.into())
+            }
+            FieldDescriptorProto_Type::TYPE_MESSAGE => {
+                let bytes = is.read_bytes().map_err(invalid)?;
+                self.decode(field.get_type_name(), &bytes)?
+            }
+            FieldDescriptorProto_Type::TYPE_GROUP => {
+                return Err(CliError::UserError(format!(
+                    "Group field {} is not supported",
+                    field.get_name()
+                )))
+            }
+        })
+    }
+
+    /// The JSON value of a field that is not set, used for the missing key or value of a map
+    /// entry
+    fn default_value(&self, field: &FieldDescriptorProto) -> Value {
+        match field.get_field_type() {
+            FieldDescriptorProto_Type::TYPE_INT64
+            | FieldDescriptorProto_Type::TYPE_SINT64
+            | FieldDescriptorProto_Type::TYPE_SFIXED64
+            | FieldDescriptorProto_Type::TYPE_UINT64
+            | FieldDescriptorProto_Type::TYPE_FIXED64 => Value::String("0".into()),
+            FieldDescriptorProto_Type::TYPE_STRING | FieldDescriptorProto_Type::TYPE_BYTES => {
+                Value::String(String::new())
+            }
+            FieldDescriptorProto_Type::TYPE_BOOL => Value::Bool(false),
+            FieldDescriptorProto_Type::TYPE_ENUM => self
+                .enums
+                .get(field.get_type_name().trim_start_matches('.'))
+                .and_then(|enum_type| enum_type.get_value().first())
+                .map(|enum_value| Value::String(enum_value.get_name().into()))
+                .unwrap_or_else(|| 0.into()),
+            FieldDescriptorProto_Type::TYPE_MESSAGE | FieldDescriptorProto_Type::TYPE_GROUP => {
+                Value::Object(serde_json::Map::new())
+            }
+            _ => 0.into(),
+        }
+    }
+
+    fn is_map(&self, field: &FieldDescriptorProto) -> bool {
+        field.get_field_type() == FieldDescriptorProto_Type::TYPE_MESSAGE
+            && self
+                .message(field.get_type_name())
+                .map(|message| message.get_options().get_map_entry())
+                .unwrap_or(false)
+    }
+
+    fn encode_field(
+        &self,
+        os: &mut CodedOutputStream,
+        field: &FieldDescriptorProto,
+        value: &Value,
+    ) -> Result<(), CliError> {
+        let number = field.get_number() as u32;
+        let invalid = || {
+            CliError::UserError(format!(
+                "Invalid value for field {}: {}",
+                field.get_name(),
+                value
+            ))
+        };
+
+        let result = match field.get_field_type() {
+            FieldDescriptorProto_Type::TYPE_INT32 => {
+                os.write_int32(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_SINT32 => {
+                os.write_sint32(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_SFIXED32 => {
+                os.write_sfixed32(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_INT64 => {
+                os.write_int64(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_SINT64 => {
+                os.write_sint64(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_SFIXED64 => {
+                os.write_sfixed64(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_UINT32 => {
+                os.write_uint32(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_FIXED32 => {
+                os.write_fixed32(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_UINT64 => {
+                os.write_uint64(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_FIXED64 => {
+                os.write_fixed64(number, to_integer(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_FLOAT => {
+                os.write_float(number, to_float(value).ok_or_else(invalid)? as f32)
+            }
+            FieldDescriptorProto_Type::TYPE_DOUBLE => {
+                os.write_double(number, to_float(value).ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_BOOL => {
+                os.write_bool(number, value.as_bool().ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_STRING => {
+                os.write_string(number, value.as_str().ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_BYTES => {
+                let bytes = value
+                    .as_str()
+                    .and_then(|value| base64::decode(value).ok())
+                    .ok_or_else(invalid)?;
+                os.write_bytes(number, &bytes)
+            }
+            FieldDescriptorProto_Type::TYPE_ENUM => {
+                let number_value = match value {
+                    Value::String(name) => self
+                        .enums
+                        .get(field.get_type_name().trim_start_matches('.'))
+                        .and_then(|enum_type| {
+                            enum_type
+                                .get_value()
+                                .iter()
+                                .find(|enum_value| enum_value.get_name() == name)
+                        })
+                        .map(|enum_value| enum_value.get_number()),
+                    _ => to_integer(value),
+                };
+                os.write_enum(number, number_value.ok_or_else(invalid)?)
+            }
+            FieldDescriptorProto_Type::TYPE_MESSAGE => {
+                let bytes = self.encode(field.get_type_name(), value)?;
+                os.write_bytes(number, &bytes)
+            }
+            FieldDescriptorProto_Type::TYPE_GROUP => {
+                return Err(CliError::UserError(format!(
+                    "Group field {} is not supported",
+                    field.get_name()
+                )))
+            }
+        };
+
+        result.map_err(|err| {
+            CliError::UserError(format!(
+                "Unable to encode field {}: {}",
+                field.get_name(),
+                err
+            ))
+        })
+    }
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.into()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+/// The name of a field in the proto3 JSON mapping, which protoc records in the descriptor
+fn json_name(field: &FieldDescriptorProto) -> &str {
+    if field.has_json_name() {
+        field.get_json_name()
+    } else {
+        field.get_name()
+    }
+}
+
+/// Whether a repeated field of this type may be encoded as a packed list
+fn is_packable(field: &FieldDescriptorProto) -> bool {
+    !matches!(
+        field.get_field_type(),
+        FieldDescriptorProto_Type::TYPE_STRING
+            | FieldDescriptorProto_Type::TYPE_BYTES
+            | FieldDescriptorProto_Type::TYPE_MESSAGE
+            | FieldDescriptorProto_Type::TYPE_GROUP
+    )
+}
+
+/// Convert a JSON object key to the JSON value of the map entry's key type
+fn map_key(entry: &DescriptorProto, key: &str) -> Result<Value, CliError> {
+    let key_type = entry
+        .get_field()
+        .iter()
+        .find(|field| field.get_number() == 1)
+        .map(|field| field.get_field_type());
+
+    match key_type {
+        Some(FieldDescriptorProto_Type::TYPE_BOOL) => match key {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err(CliError::UserError(format!("Invalid map key: {}", key))),
+        },
+        // Integer keys are accepted as strings, like any other integer
+        _ => Ok(Value::String(key.into())),
+    }
+}
+
+/// Integers may be given as JSON numbers or as strings
+fn to_integer<T: FromStr>(value: &Value) -> Option<T> {
+    match value {
+        Value::Number(number) => number.to_string().parse().ok(),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Non-finite floats are strings in the proto3 JSON mapping
+fn from_float(value: f64) -> Value {
+    match Number::from_f64(value) {
+        Some(number) => Value::Number(number),
+        None if value.is_nan() => Value::String("NaN".into()),
+        None if value > 0.0 => Value::String("Infinity".into()),
+        None => Value::String("-Infinity".into()),
+    }
+}
+
+fn to_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => match string.as_str() {
+            "NaN" => Some(f64::NAN),
+            "Infinity" => Some(f64::INFINITY),
+            "-Infinity" => Some(f64::NEG_INFINITY),
+            _ => string.parse().ok(),
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which load a file of namespace permissions and compare it with state
+//!
+//! A permissions file is a YAML list of grants, each giving the read and write permissions a
+//! contract should have on a namespace:
+//!
+//! ```yaml
+//! - namespace: 1cf126
+//!   contract: intkey_multiply
+//!   read: true
+//!   write: true
+//! ```
+//!
+//! `read` and `write` default to false; a grant with neither removes the contract's permission.
+
+use std::collections::HashMap;
+use std::fs;
+
+use sabre_sdk::protocol::compute_namespace_registry_address;
+use sabre_sdk::protocol::state::{NamespaceRegistry, NamespaceRegistryList};
+use sabre_sdk::protos::FromBytes;
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::error::CliError;
+use crate::http::HttpClient;
+use crate::state;
+
+/// The fields of a grant
+const GRANT_FIELDS: &[&str] = &["namespace", "contract", "read", "write"];
+
+/// The permissions a contract should have on a namespace
+#[derive(Debug, PartialEq)]
+pub struct Grant {
+    pub namespace: String,
+    pub contract: String,
+    pub read: bool,
+    pub write: bool,
+}
+
+impl Grant {
+    /// Whether the grant removes the contract's permission
+    pub fn is_revoke(&self) -> bool {
+        !(self.read || self.write)
+    }
+}
+
+/// Load and validate the grants in a permissions file
+///
+/// Each namespace and contract pair may only be listed once.
+pub fn load(path: &str) -> Result<Vec<Grant>, CliError> {
+    let malformed = |reason: String| {
+        CliError::UserError(format!(
+            "Malformed permissions file \"{}\": {}",
+            path, reason
+        ))
+    };
+
+    let contents = fs::read_to_string(path).map_err(|err| {
+        CliError::UserError(format!(
+            "Could not load permissions file \"{}\": {}",
+            path, err
+        ))
+    })?;
+    let docs = YamlLoader::load_from_str(&contents).map_err(|err| malformed(err.to_string()))?;
+    let entries = match docs.first() {
+        Some(Yaml::Array(entries)) => entries,
+        // An empty file grants nothing
+        None | Some(Yaml::Null) => return Ok(vec![]),
+        Some(_) => return Err(malformed("expected a list of grants".into())),
+    };
+
+    let mut grants: Vec<Grant> = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let invalid = |reason: &str| malformed(format!("grant {}: {}", index + 1, reason));
+
+        let fields = entry
+            .as_hash()
+            .ok_or_else(|| invalid("expected a mapping"))?;
+        for key in fields.keys() {
+            match key.as_str() {
+                Some(key) if GRANT_FIELDS.contains(&key) => (),
+                Some(key) => {
+                    return Err(invalid(&format!(
+                        "unexpected field \"{}\"; expected only {}",
+                        key,
+                        GRANT_FIELDS.join(", ")
+                    )))
+                }
+                None => return Err(invalid("field names must be strings")),
+            }
+        }
+
+        let string_field = |name: &str| match &entry[name] {
+            Yaml::String(value) if !value.is_empty() => Ok(value.clone()),
+            // Namespaces made only of digits are read as numbers unless they are quoted
+            Yaml::Integer(_) | Yaml::Real(_) => Err(invalid(&format!(
+                "\"{}\" must be a string; quote values made only of digits",
+                name
+            ))),
+            _ => Err(invalid(&format!("missing string field \"{}\"", name))),
+        };
+        let bool_field = |name: &str| match &entry[name] {
+            Yaml::BadValue => Ok(false),
+            value => value
+                .as_bool()
+                .ok_or_else(|| invalid(&format!("\"{}\" must be true or false", name))),
+        };
+
+        let grant = Grant {
+            namespace: string_field("namespace")?,
+            contract: string_field("contract")?,
+            read: bool_field("read")?,
+            write: bool_field("write")?,
+        };
+
+        if grant.namespace.len() < 6 {
+            return Err(invalid(&format!(
+                "namespace must be at least 6 characters long: {}",
+                grant.namespace
+            )));
+        }
+        if grants
+            .iter()
+            .any(|other| other.namespace == grant.namespace && other.contract == grant.contract)
+        {
+            return Err(invalid(&format!(
+                "{} is already listed for namespace {}",
+                grant.contract, grant.namespace
+            )));
+        }
+
+        grants.push(grant);
+    }
+
+    Ok(grants)
+}
+
+/// Return the grants which differ from the permissions in state, in the order they were given
+///
+/// Every namespace must be registered. A revoking grant is only returned if the contract has a
+/// permission to remove.
+pub fn changes(client: &HttpClient, url: &str, grants: Vec<Grant>) -> Result<Vec<Grant>, CliError> {
+    let mut registries: HashMap<String, NamespaceRegistry> = HashMap::new();
+    let mut changes = Vec::new();
+
+    for grant in grants {
+        if !registries.contains_key(&grant.namespace) {
+            let registry = get_namespace_registry(client, url, &grant.namespace)?;
+            registries.insert(grant.namespace.clone(), registry);
+        }
+
+        let current = registries[&grant.namespace]
+            .permissions()
+            .iter()
+            .find(|permission| permission.contract_name() == &grant.contract);
+
+        let unchanged = match current {
+            Some(permission) => {
+                permission.read() == grant.read && permission.write() == grant.write
+            }
+            None => grant.is_revoke(),
+        };
+        if !unchanged {
+            changes.push(grant);
+        }
+    }
+
+    Ok(changes)
+}
+
+fn get_namespace_registry(
+    client: &HttpClient,
+    url: &str,
+    namespace: &str,
+) -> Result<NamespaceRegistry, CliError> {
+    let not_found = || CliError::UserError(format!("namespace '{}' not found", namespace));
+
+    let address = compute_namespace_registry_address(namespace)
+        .map_err(|err| {
+            CliError::UserError(format!("Unable to get namespace registry address: {}", err))
+        })?
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    let bytes = state::get_state_entry(client, url, &address)?.ok_or_else(not_found)?;
+
+    NamespaceRegistryList::from_bytes(&bytes)?
+        .registries()
+        .iter()
+        .find(|registry| registry.namespace() == namespace)
+        .cloned()
+        .ok_or_else(not_found)
+}
@@ -0,0 +1,192 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains a signer backed by a PKCS#11 token, such as a hardware security module
+
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cylinder::{PublicKey, Signature, Signer, SigningError};
+
+use crate::error::CliError;
+use crate::key::prompt_passphrase;
+
+/// Keys with this prefix are loaded from a PKCS#11 token, e.g. `pkcs11:<key label>`
+pub const PKCS11_KEY_PREFIX: &str = "pkcs11:";
+
+/// Path to the PKCS#11 module (shared library) provided by the token vendor
+const MODULE_ENV_VAR: &str = "SABRE_PKCS11_MODULE";
+/// Index of the slot, among the slots with a token present, to use; defaults to 0
+const SLOT_ENV_VAR: &str = "SABRE_PKCS11_SLOT";
+/// User PIN for the token; prompted for if not set
+const PIN_ENV_VAR: &str = "SABRE_PKCS11_PIN";
+
+/// Return a signer using the secp256k1 key pair with the label given in `key`, which must be of
+/// the form `pkcs11:<key label>`.
+pub fn new_pkcs11_signer(key: &str) -> Result<Box<dyn Signer>, CliError> {
+    let label = key.trim_start_matches(PKCS11_KEY_PREFIX);
+    if label.is_empty() {
+        return Err(CliError::UserError(
+            "PKCS#11 keys must be of the form 'pkcs11:<key label>'".into(),
+        ));
+    }
+
+    let module = env::var(MODULE_ENV_VAR).map_err(|_| {
+        CliError::UserError(format!(
+            "{} must be set to the path of a PKCS#11 module to use a PKCS#11 key",
+            MODULE_ENV_VAR
+        ))
+    })?;
+
+    let slot_index = match env::var(SLOT_ENV_VAR) {
+        Ok(slot) => slot
+            .parse::<usize>()
+            .map_err(|_| CliError::UserError(format!("{} must be an integer", SLOT_ENV_VAR)))?,
+        Err(_) => 0,
+    };
+
+    let pkcs11 = Pkcs11::new(&module).map_err(to_signing_error)?;
+    pkcs11
+        .initialize(CInitializeArgs::OsThreads)
+        .map_err(to_signing_error)?;
+
+    let slot = pkcs11
+        .get_slots_with_token()
+        .map_err(to_signing_error)?
+        .get(slot_index)
+        .cloned()
+        .ok_or_else(|| {
+            CliError::UserError(format!("No PKCS#11 token found in slot {}", slot_index))
+        })?;
+
+    let session = pkcs11
+        .open_session_no_callback(slot, false)
+        .map_err(to_signing_error)?;
+
+    let pin = match env::var(PIN_ENV_VAR) {
+        Ok(pin) => pin,
+        Err(_) => prompt_passphrase("PKCS#11 user PIN: ")?,
+    };
+    session
+        .login(UserType::User, Some(&pin))
+        .map_err(to_signing_error)?;
+
+    let private_key = find_key(&session, ObjectClass::PRIVATE_KEY, label)?;
+    let public_key = read_public_key(
+        &session,
+        find_key(&session, ObjectClass::PUBLIC_KEY, label)?,
+    )?;
+
+    Ok(Box::new(Pkcs11Signer {
+        _context: pkcs11,
+        session: Arc::new(Mutex::new(session)),
+        private_key,
+        public_key,
+    }))
+}
+
+/// A cylinder `Signer` which signs with a secp256k1 private key stored on a PKCS#11 token
+#[derive(Clone)]
+struct Pkcs11Signer {
+    // The context must outlive the session
+    _context: Pkcs11,
+    session: Arc<Mutex<Session>>,
+    private_key: ObjectHandle,
+    public_key: PublicKey,
+}
+
+impl Signer for Pkcs11Signer {
+    fn algorithm_name(&self) -> &str {
+        "secp256k1"
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature, SigningError> {
+        let mut sha = Sha256::new();
+        sha.input(message);
+        let hash: &mut [u8] = &mut [0; 32];
+        sha.result(hash);
+
+        let raw_signature = self
+            .session
+            .lock()
+            .map_err(|_| SigningError::Internal("PKCS#11 session lock poisoned".into()))?
+            .sign(&Mechanism::Ecdsa, self.private_key, hash)
+            .map_err(|err| SigningError::Internal(format!("PKCS#11 signing failed: {}", err)))?;
+
+        // Tokens are not required to produce low-S signatures, which secp256k1 verifiers reject
+        let mut signature = secp256k1::Signature::from_compact(&raw_signature)
+            .map_err(|err| SigningError::Internal(format!("Invalid PKCS#11 signature: {}", err)))?;
+        signature.normalize_s();
+
+        Ok(Signature::new(signature.serialize_compact().to_vec()))
+    }
+
+    fn public_key(&self) -> Result<PublicKey, SigningError> {
+        Ok(self.public_key.clone())
+    }
+
+    fn clone_box(&self) -> Box<dyn Signer> {
+        Box::new(self.clone())
+    }
+}
+
+fn find_key(session: &Session, class: ObjectClass, label: &str) -> Result<ObjectHandle, CliError> {
+    session
+        .find_objects(&[
+            Attribute::Class(class),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ])
+        .map_err(to_signing_error)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| CliError::UserError(format!("No PKCS#11 key found with label '{}'", label)))
+}
+
+/// Read the EC point of the given public key and return it in compressed form
+fn read_public_key(session: &Session, handle: ObjectHandle) -> Result<PublicKey, CliError> {
+    let ec_point = session
+        .get_attributes(handle, &[AttributeType::EcPoint])
+        .map_err(to_signing_error)?
+        .into_iter()
+        .find_map(|attribute| match attribute {
+            Attribute::EcPoint(point) => Some(point),
+            _ => None,
+        })
+        .ok_or_else(|| CliError::SigningError("PKCS#11 public key has no EC point".into()))?;
+
+    // The point is usually wrapped in a DER octet string; strip the tag and length if present
+    let point = match ec_point.as_slice() {
+        [0x04, len, rest @ ..]
+            if *len as usize == rest.len() && (rest.len() == 65 || rest.len() == 33) =>
+        {
+            rest
+        }
+        point => point,
+    };
+
+    let public_key = secp256k1::PublicKey::from_slice(point)
+        .map_err(|err| CliError::SigningError(format!("Invalid PKCS#11 public key: {}", err)))?;
+
+    Ok(PublicKey::new(public_key.serialize().to_vec()))
+}
+
+fn to_signing_error(err: cryptoki::error::Error) -> CliError {
+    CliError::SigningError(format!("PKCS#11 error: {}", err))
+}
@@ -0,0 +1,72 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains the timing report printed by `--timings`
+//!
+//! Each phase of a submission is reported on stderr as soon as it finishes, so the phases that
+//! completed are still reported if a later one fails.
+
+use std::time::{Duration, Instant};
+
+use transact::protocol::batch::Batch;
+
+/// Times the phases of a submission, if profiling is enabled
+pub struct Profile {
+    enabled: bool,
+    start: Instant,
+}
+
+impl Profile {
+    pub fn new(enabled: bool) -> Self {
+        Profile {
+            enabled,
+            start: Instant::now(),
+        }
+    }
+
+    /// Run `f` as the named phase, reporting how long it took
+    ///
+    /// The phase is reported whether or not `f` succeeds.
+    pub fn time<T>(&self, phase: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.report(phase, start.elapsed());
+        result
+    }
+
+    /// Report the IDs of the batches and of the transactions they contain
+    pub fn report_batches(&self, batches: &[Batch]) {
+        if !self.enabled {
+            return;
+        }
+
+        for batch in batches {
+            eprintln!("timings: batch {}", batch.header_signature());
+            for transaction in batch.transactions() {
+                eprintln!("timings:   transaction {}", transaction.header_signature());
+            }
+        }
+    }
+
+    /// Report the time elapsed since profiling started
+    pub fn report_total(&self) {
+        self.report("total", self.start.elapsed());
+    }
+
+    fn report(&self, phase: &str, duration: Duration) {
+        if self.enabled {
+            eprintln!("timings: {:<24} {:>9.3}s", phase, duration.as_secs_f64());
+        }
+    }
+}
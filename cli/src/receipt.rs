@@ -0,0 +1,153 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which fetch the receipts of committed transactions from the REST API
+
+use crate::error::CliError;
+use crate::events::{Event, StateChange};
+use crate::http::{self, HttpClient};
+
+/// The result of a committed transaction, as recorded by the validator
+pub struct Receipt {
+    pub transaction_id: String,
+    /// The state changes, in the order they were made
+    pub changes: Vec<StateChange>,
+    pub events: Vec<Event>,
+    /// The data added to the receipt by the transaction
+    pub data: Vec<Vec<u8>>,
+}
+
+/// Fetch the receipts of the given transactions from the REST API at `url`
+///
+/// The REST API responds with an error if any of the transactions has no receipt.
+pub fn get_receipts(
+    client: &HttpClient,
+    url: &str,
+    transaction_ids: &[&str],
+) -> Result<Vec<Receipt>, CliError> {
+    let receipts = http::with_failover(url, |url| {
+        let response = client
+            .get(&format!(
+                "{}/receipts?id={}",
+                url.trim_end_matches('/'),
+                transaction_ids.join(",")
+            ))?
+            .error_for_status(url)?;
+
+        serde_json::from_slice::<JsonReceiptList>(&response.body).map_err(|err| {
+            CliError::UserError(format!(
+                "Unable to parse receipt response from {}: {}",
+                url, err
+            ))
+        })
+    })?;
+
+    receipts.data.into_iter().map(Receipt::try_from).collect()
+}
+
+impl Receipt {
+    fn try_from(receipt: JsonReceipt) -> Result<Receipt, CliError> {
+        let decode = |value: &str, what: &str| {
+            base64::decode(value).map_err(|_| {
+                CliError::UserError(format!(
+                    "Unable to decode {} in the receipt of transaction {}",
+                    what, receipt.transaction_id
+                ))
+            })
+        };
+
+        let changes = receipt
+            .state_changes
+            .iter()
+            .map(|change| {
+                Ok(StateChange {
+                    address: change.address.clone(),
+                    value: match change.change_type.as_str() {
+                        "DELETE" => None,
+                        _ => Some(decode(&change.value, "state change")?),
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, CliError>>()?;
+
+        let events = receipt
+            .events
+            .iter()
+            .map(|event| {
+                Ok(Event {
+                    event_type: event.event_type.clone(),
+                    attributes: event
+                        .attributes
+                        .iter()
+                        .map(|attribute| (attribute.key.clone(), attribute.value.clone()))
+                        .collect(),
+                    data: decode(&event.data, "event data")?,
+                })
+            })
+            .collect::<Result<Vec<_>, CliError>>()?;
+
+        let data = receipt
+            .data
+            .iter()
+            .map(|data| decode(data, "receipt data"))
+            .collect::<Result<Vec<_>, CliError>>()?;
+
+        Ok(Receipt {
+            transaction_id: receipt.transaction_id,
+            changes,
+            events,
+            data,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonReceiptList {
+    data: Vec<JsonReceipt>,
+}
+
+#[derive(Deserialize)]
+struct JsonReceipt {
+    transaction_id: String,
+    #[serde(default)]
+    state_changes: Vec<JsonStateChange>,
+    #[serde(default)]
+    events: Vec<JsonEvent>,
+    #[serde(default)]
+    data: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonStateChange {
+    address: String,
+    #[serde(rename = "type")]
+    change_type: String,
+    #[serde(default)]
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct JsonEvent {
+    event_type: String,
+    #[serde(default)]
+    attributes: Vec<JsonEventAttribute>,
+    #[serde(default)]
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct JsonEventAttribute {
+    key: String,
+    value: String,
+}
@@ -0,0 +1,222 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains the generator of new smart contract crates, used by `sabre new`
+//!
+//! The files of a new crate are rendered from the templates in cli/templates/contract, which
+//! are compiled into the CLI.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
+
+use crate::archive;
+use crate::error::CliError;
+
+/// The prefix of Sabre's own state, which a contract cannot write to
+const SABRE_PREFIX: &str = "00ec";
+
+/// The files of a new contract crate, with their paths relative to the crate and templates
+const TEMPLATES: &[(&str, &str)] = &[
+    (
+        "Cargo.toml",
+        include_str!("../templates/contract/Cargo.toml.tmpl"),
+    ),
+    (
+        "manifest.yaml",
+        include_str!("../templates/contract/manifest.yaml.tmpl"),
+    ),
+    (
+        "README.md",
+        include_str!("../templates/contract/README.md.tmpl"),
+    ),
+    (
+        ".gitignore",
+        include_str!("../templates/contract/gitignore.tmpl"),
+    ),
+    (
+        "src/lib.rs",
+        include_str!("../templates/contract/lib.rs.tmpl"),
+    ),
+    (
+        "src/addressing.rs",
+        include_str!("../templates/contract/addressing.rs.tmpl"),
+    ),
+    (
+        "src/handler.rs",
+        include_str!("../templates/contract/handler.rs.tmpl"),
+    ),
+];
+
+/// A contract crate created by `new_contract`
+pub struct NewContract {
+    pub name: String,
+    pub path: PathBuf,
+    /// The namespace of the contract's state
+    pub prefix: String,
+    /// The files written, relative to `path`
+    pub files: Vec<String>,
+}
+
+/// Create a smart contract crate named `name` in the directory `path`, or in `./<name>`
+///
+/// The contract's state is stored under `prefix`, which defaults to the first 6 characters of
+/// the SHA-512 hash of the name. The crate depends on the sabre-sdk release matching the CLI,
+/// or on the checkout at `sdk_path`. The directory must not exist, or must be empty.
+pub fn new_contract(
+    name: &str,
+    path: Option<&str>,
+    prefix: Option<&str>,
+    sdk_path: Option<&str>,
+) -> Result<NewContract, CliError> {
+    if !is_contract_name(name) {
+        return Err(CliError::UserError(format!(
+            "Invalid contract name \"{}\"; it must start with a letter and contain only letters, \
+             digits, '-' and '_'",
+            name
+        )));
+    }
+
+    let prefix = match prefix {
+        Some(prefix) => prefix.to_lowercase(),
+        None => {
+            let mut sha = Sha512::new();
+            sha.input_str(name);
+            sha.result_str()[..6].to_string()
+        }
+    };
+    if !archive::is_namespace(&prefix) || prefix.len() >= 70 {
+        return Err(CliError::UserError(format!(
+            "Invalid prefix \"{}\"; it must be an even number of hex characters, at least 6 and \
+             fewer than 70",
+            prefix
+        )));
+    }
+    if prefix.starts_with(SABRE_PREFIX) {
+        return Err(CliError::UserError(format!(
+            "Prefix \"{}\" is reserved for Sabre; choose another with --prefix",
+            prefix
+        )));
+    }
+
+    let path = PathBuf::from(path.unwrap_or(name));
+    check_empty(&path)?;
+
+    let sabre_sdk = match sdk_path {
+        // Cargo resolves a relative path from the new crate, not from where it was created
+        Some(sdk_path) => {
+            let sdk_path = fs::canonicalize(sdk_path).map_err(|err| {
+                CliError::UserError(format!("Invalid sabre-sdk path \"{}\": {}", sdk_path, err))
+            })?;
+            format!(
+                "{{ path = \"{}\" }}",
+                sdk_path.display().to_string().replace('\\', "/")
+            )
+        }
+        None => format!("\"{}\"", env!("CARGO_PKG_VERSION")),
+    };
+
+    let crate_name = name.replace('_', "-");
+    let replacements = [
+        ("{{name}}", name.to_string()),
+        ("{{crate_name}}", crate_name.clone()),
+        ("{{lib_name}}", name.replace('-', "_")),
+        ("{{archive_name}}", crate_name),
+        ("{{type_name}}", type_name(name)),
+        ("{{prefix}}", prefix.clone()),
+        ("{{sabre_sdk}}", sabre_sdk),
+    ];
+
+    let mut files = vec![];
+    for (file, template) in TEMPLATES {
+        let contents = replacements
+            .iter()
+            .fold(template.to_string(), |contents, (placeholder, value)| {
+                contents.replace(placeholder, value)
+            });
+
+        let file_path = path.join(file);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).map_err(|err| {
+                CliError::UserError(format!(
+                    "Could not create directory \"{}\": {}",
+                    parent.display(),
+                    err
+                ))
+            })?;
+        }
+        fs::write(&file_path, contents).map_err(|err| {
+            CliError::UserError(format!(
+                "Could not write \"{}\": {}",
+                file_path.display(),
+                err
+            ))
+        })?;
+
+        files.push(file.to_string());
+    }
+
+    Ok(NewContract {
+        name: name.into(),
+        path,
+        prefix,
+        files,
+    })
+}
+
+/// Whether the name can be used as both a contract name and a crate name
+fn is_contract_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The name of the contract in upper camel case, for the handler type, e.g. "IntkeyMultiply"
+fn type_name(name: &str) -> String {
+    name.split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn check_empty(path: &Path) -> Result<(), CliError> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path).map_err(|err| {
+        CliError::UserError(format!(
+            "Could not read directory \"{}\": {}",
+            path.display(),
+            err
+        ))
+    })?;
+    if entries.next().is_some() {
+        return Err(CliError::UserError(format!(
+            "Directory \"{}\" already exists and is not empty",
+            path.display()
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which register the protobuf schema of a contract's state, and decode the
+//! contract's state entries with it
+//!
+//! The schema is registered alongside the contract by storing it in a custom section of the
+//! contract's WebAssembly module, which Sabre ignores when it executes the contract.
+
+use std::fs;
+
+use protobuf::wire_format::WireType;
+use protobuf::{CodedInputStream, CodedOutputStream, ProtobufResult};
+use serde_json::Value;
+
+use crate::error::CliError;
+use crate::payload;
+use crate::wasm;
+
+/// The name of the custom section which holds the schema
+const STATE_SCHEMA_SECTION: &str = "sabre-state-schema";
+
+/// The protobuf message a contract's state entries are encoded as, and the descriptor set that
+/// defines it
+pub struct StateSchema {
+    /// The fully-qualified name of the message, e.g. "intkey.State"
+    pub message: String,
+    /// A serialized FileDescriptorSet
+    pub descriptor_set: Vec<u8>,
+}
+
+impl StateSchema {
+    /// Load the descriptor set from a file, checking that it defines the message
+    pub fn load(descriptor_set_path: &str, message: &str) -> Result<StateSchema, CliError> {
+        let descriptor_set = fs::read(descriptor_set_path).map_err(|err| {
+            CliError::UserError(format!(
+                "Could not read descriptor set \"{}\": {}",
+                descriptor_set_path, err
+            ))
+        })?;
+
+        // Decoding an empty message fails if the descriptor set is invalid or lacks the message
+        payload::decode_message(&[], message, &descriptor_set)?;
+
+        Ok(StateSchema {
+            message: message.trim_start_matches('.').into(),
+            descriptor_set,
+        })
+    }
+
+    /// Read the schema registered in a contract's WebAssembly module, if any
+    pub fn from_contract(contract: &[u8]) -> Result<Option<StateSchema>, CliError> {
+        match wasm::custom_section(contract, STATE_SCHEMA_SECTION)? {
+            Some(section) => decode_section(&section).map(Some).map_err(|err| {
+                CliError::UserError(format!("Invalid state schema in contract: {}", err))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the schema in a contract's WebAssembly module, replacing any schema it has
+    pub fn register(&self, contract: &[u8]) -> Result<Vec<u8>, CliError> {
+        let section = encode_section(self).map_err(|err| {
+            CliError::UserError(format!("Unable to encode state schema: {}", err))
+        })?;
+
+        wasm::set_custom_section(contract, STATE_SCHEMA_SECTION, &section)
+    }
+
+    /// Decode a state entry as the schema's message, or as another message of its descriptor set
+    pub fn decode(&self, bytes: &[u8], message: Option<&str>) -> Result<Value, CliError> {
+        payload::decode_message(
+            bytes,
+            message.unwrap_or(&self.message),
+            &self.descriptor_set,
+        )
+    }
+}
+
+/// Serialize the schema as a message with the message name in field 1 and the descriptor set in
+/// field 2
+fn encode_section(schema: &StateSchema) -> ProtobufResult<Vec<u8>> {
+    let mut section = Vec::new();
+    {
+        let mut os = CodedOutputStream::vec(&mut section);
+        os.write_string(1, &schema.message)?;
+        os.write_bytes(2, &schema.descriptor_set)?;
+        os.flush()?;
+    }
+
+    Ok(section)
+}
+
+fn decode_section(section: &[u8]) -> ProtobufResult<StateSchema> {
+    let mut schema = StateSchema {
+        message: String::new(),
+        descriptor_set: Vec::new(),
+    };
+
+    let mut is = CodedInputStream::from_bytes(section);
+    while !is.eof()? {
+        match is.read_tag_unpack()? {
+            (1, WireType::WireTypeLengthDelimited) => schema.message = is.read_string()?,
+            (2, WireType::WireTypeLengthDelimited) => schema.descriptor_set = is.read_bytes()?,
+            (_, wire_type) => is.skip_field(wire_type)?,
+        }
+    }
+
+    Ok(schema)
+}
@@ -0,0 +1,150 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which read Sawtooth settings and build Sawtooth Settings transactions
+//!
+//! Only the few fields of the settings messages the CLI needs are encoded and decoded, so the
+//! Sawtooth Settings protos are not required.
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use protobuf::wire_format::WireType;
+use protobuf::{CodedInputStream, CodedOutputStream, ProtobufResult};
+use rand::Rng;
+use transact::protocol::transaction::{HashMethod, TransactionBuilder};
+
+use crate::batch::from_hex;
+use crate::error::CliError;
+use crate::http::HttpClient;
+use crate::state;
+
+const SETTINGS_FAMILY_NAME: &str = "sawtooth_settings";
+const SETTINGS_FAMILY_VERSION: &str = "1.0";
+
+const SETTINGS_NAMESPACE: &str = "000000";
+const SETTINGS_ADDRESS_PARTS: usize = 4;
+
+const PROPOSALS_SETTING_KEY: &str = "sawtooth.settings.vote.proposals";
+const AUTHORIZED_KEYS_SETTING_KEY: &str = "sawtooth.settings.vote.authorized_keys";
+pub const APPROVAL_THRESHOLD_SETTING_KEY: &str = "sawtooth.settings.vote.approval_threshold";
+
+/// The `PROPOSE` action of a `SettingsPayload`
+const PROPOSE_ACTION: i32 = 1;
+
+/// Compute the address of a setting
+///
+/// The key is split on '.' into at most four parts, the last holding the remainder of the
+/// key, and each part is hashed with SHA-256 to 16 hex characters; missing parts hash the empty
+/// string.
+pub fn compute_setting_address(key: &str) -> String {
+    let mut parts = key.splitn(SETTINGS_ADDRESS_PARTS, '.').collect::<Vec<_>>();
+    parts.resize(SETTINGS_ADDRESS_PARTS, "");
+
+    parts
+        .iter()
+        .fold(SETTINGS_NAMESPACE.to_string(), |address, part| {
+            let mut sha = Sha256::new();
+            sha.input_str(part);
+            address + &sha.result_str()[..16]
+        })
+}
+
+/// Return the value of a setting, or `None` if it is not set
+pub fn get_setting(client: &HttpClient, url: &str, key: &str) -> Result<Option<String>, CliError> {
+    let address = compute_setting_address(key);
+    match state::get_state_entry(client, url, &address)? {
+        Some(bytes) => decode_setting(&bytes, key).map_err(|err| {
+            CliError::UserError(format!("Unable to decode setting {}: {}", key, err))
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Build a Sawtooth Settings transaction which proposes a new value for a setting
+///
+/// The setting is changed when the transaction is committed, unless the network's approval
+/// threshold requires votes from other authorized keys.
+pub fn propose_setting(key: &str, value: &str) -> Result<TransactionBuilder, CliError> {
+    let nonce = format!("{:016x}", rand::thread_rng().gen::<u64>());
+
+    let payload = encode_proposal(key, value, &nonce).map_err(|err| {
+        CliError::UserError(format!("Unable to encode settings payload: {}", err))
+    })?;
+
+    let setting_address = from_hex(&compute_setting_address(key))?;
+    let proposals_address = from_hex(&compute_setting_address(PROPOSALS_SETTING_KEY))?;
+
+    Ok(TransactionBuilder::new()
+        .with_family_name(SETTINGS_FAMILY_NAME.into())
+        .with_family_version(SETTINGS_FAMILY_VERSION.into())
+        .with_inputs(vec![
+            proposals_address.clone(),
+            from_hex(&compute_setting_address(AUTHORIZED_KEYS_SETTING_KEY))?,
+            from_hex(&compute_setting_address(APPROVAL_THRESHOLD_SETTING_KEY))?,
+            setting_address.clone(),
+        ])
+        .with_outputs(vec![proposals_address, setting_address])
+        .with_payload_hash_method(HashMethod::SHA512)
+        .with_payload(payload))
+}
+
+/// Serialize a `SettingsPayload` which proposes a value for a setting
+fn encode_proposal(key: &str, value: &str, nonce: &str) -> ProtobufResult<Vec<u8>> {
+    let mut proposal = Vec::new();
+    {
+        let mut os = CodedOutputStream::vec(&mut proposal);
+        os.write_string(1, key)?;
+        os.write_string(2, value)?;
+        os.write_string(3, nonce)?;
+        os.flush()?;
+    }
+
+    let mut payload = Vec::new();
+    {
+        let mut os = CodedOutputStream::vec(&mut payload);
+        os.write_enum(1, PROPOSE_ACTION)?;
+        os.write_bytes(2, &proposal)?;
+        os.flush()?;
+    }
+
+    Ok(payload)
+}
+
+/// Find the value of `key` in a serialized `Setting`, whose entries are key/value pairs
+fn decode_setting(bytes: &[u8], key: &str) -> ProtobufResult<Option<String>> {
+    let mut is = CodedInputStream::from_bytes(bytes);
+    while !is.eof()? {
+        let (field, wire_type) = is.read_tag_unpack()?;
+        if field != 1 || wire_type != WireType::WireTypeLengthDelimited {
+            is.skip_field(wire_type)?;
+            continue;
+        }
+
+        let entry = is.read_bytes()?;
+        let mut entry_is = CodedInputStream::from_bytes(&entry);
+        let (mut entry_key, mut entry_value) = (String::new(), String::new());
+        while !entry_is.eof()? {
+            match entry_is.read_tag_unpack()? {
+                (1, WireType::WireTypeLengthDelimited) => entry_key = entry_is.read_string()?,
+                (2, WireType::WireTypeLengthDelimited) => entry_value = entry_is.read_string()?,
+                (_, wire_type) => entry_is.skip_field(wire_type)?,
+            }
+        }
+        if entry_key == key {
+            return Ok(Some(entry_value));
+        }
+    }
+
+    Ok(None)
+}
@@ -14,55 +14,89 @@
 
 //! Contains functions which assist with fetching state
 
-use futures::Stream;
-use futures::{future, Future};
-use hyper::client::{Client, Request};
-use hyper::Method;
-use std::str;
-
 use crate::error::CliError;
+use crate::http::{self, HttpClient};
 
-pub fn get_state_with_prefix(url: &str, prefix: &str) -> Result<Vec<StateEntry>, CliError> {
-    let post_url = String::from(url) + "/state?address=" + prefix;
-    let hyper_uri = match post_url.parse::<hyper::Uri>() {
-        Ok(uri) => uri,
-        Err(e) => return Err(CliError::UserError(format!("Invalid URL: {}: {}", e, url))),
-    };
-
-    match hyper_uri.scheme() {
-        Some(scheme) => {
-            if scheme != "http" {
-                return Err(CliError::UserError(format!(
-                    "Unsupported scheme ({}) in URL: {}",
-                    scheme, url
-                )));
-            }
+/// Return the data stored at the given address, or `None` if there is no state at the address
+pub fn get_state_entry(
+    client: &HttpClient,
+    url: &str,
+    address: &str,
+) -> Result<Option<Vec<u8>>, CliError> {
+    http::with_failover(url, |url| {
+        let response = client.get(&format!("{}/state/{}", url, address))?;
+        if response.status == hyper::StatusCode::NotFound {
+            return Ok(None);
         }
-        None => {
-            return Err(CliError::UserError(format!("No scheme in URL: {}", url)));
-        }
-    }
 
-    let mut core = tokio_core::reactor::Core::new()?;
-    let handle = core.handle();
-    let client = Client::configure().build(&handle);
+        let response = response.error_for_status(url)?;
+
+        let entry = serde_json::from_slice::<JsonStateData>(&response.body).map_err(|err| {
+            CliError::UserError(format!(
+                "Unable to parse state response from {}: {}",
+                url, err
+            ))
+        })?;
 
-    let req = Request::new(Method::Get, hyper_uri);
+        base64::decode(entry.data)
+            .map(Some)
+            .map_err(|_| CliError::UserError(format!("Unable to decode state at {}", address)))
+    })
+}
 
-    let work = client.request(req).and_then(|res| {
-        res.body().concat2().and_then(move |chunks| {
-            future::ok(serde_json::from_slice::<JsonStateEntry>(&chunks).unwrap())
-        })
-    });
+/// Return all state entries under the given address prefix
+///
+/// The REST API returns state in pages; all pages are fetched from the same endpoint and
+/// combined.
+pub fn get_state_with_prefix(
+    client: &HttpClient,
+    url: &str,
+    prefix: &str,
+) -> Result<Vec<StateEntry>, CliError> {
+    http::with_failover(url, |url| {
+        let mut entries = Vec::new();
+        let mut next_page = Some(String::from(url) + "/state?address=" + prefix);
 
-    let response = core.run(work)?;
+        while let Some(page_url) = next_page {
+            let page = get_state_page(client, url, &page_url)?;
+            entries.extend(page.data);
+            next_page = page.paging.and_then(|paging| paging.next);
+        }
 
-    Ok(response.data)
+        Ok(entries)
+    })
+}
+
+fn get_state_page(
+    client: &HttpClient,
+    url: &str,
+    page_url: &str,
+) -> Result<JsonStateEntry, CliError> {
+    let response = client.get(page_url)?.error_for_status(url)?;
+
+    serde_json::from_slice::<JsonStateEntry>(&response.body).map_err(|err| {
+        CliError::UserError(format!(
+            "Unable to parse state response from {}: {}",
+            url, err
+        ))
+    })
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct JsonStateEntry {
     data: Vec<StateEntry>,
+    #[serde(default)]
+    paging: Option<Paging>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct JsonStateData {
+    data: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Paging {
+    next: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -14,103 +14,370 @@
 
 //! Contains functions which assist with batch submission to a REST API
 
-use futures::Stream;
-use futures::{future, Future};
-use hyper::client::{Client, Request};
-use hyper::header::{ContentLength, ContentType};
-use hyper::Method;
-use hyper::StatusCode;
-use std::{fmt, str};
+use serde::de::IgnoredAny;
+use std::time::{Duration, Instant};
+use std::{fmt, fs, str, thread};
 
 use transact::{protocol::batch::Batch, protos::IntoBytes};
 
+use crate::batch;
 use crate::error::CliError;
+use crate::events;
+use crate::http::{self, HttpClient};
+use crate::websocket::WebSocket;
 
-pub fn submit_batches(url: &str, batch_list: Vec<Batch>) -> Result<String, CliError> {
-    let post_url = String::from(url) + "/batches";
-    let hyper_uri = match post_url.parse::<hyper::Uri>() {
-        Ok(uri) => uri,
-        Err(e) => return Err(CliError::UserError(format!("Invalid URL: {}: {}", e, url))),
-    };
+/// The prefix shared by the addresses of all Sabre state
+const SABRE_ADDRESS_PREFIX: &str = "00ec";
+
+/// How often the status of batches in flight is checked while waiting to submit more
+const IN_FLIGHT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Where batches are submitted
+pub enum SubmitTarget {
+    /// The Sawtooth REST API
+    Sawtooth,
+    /// A scabbard service on a Splinter node
+    Scabbard { circuit: String, service_id: String },
+    /// The validator's client interface, over ZMQ
+    #[cfg(feature = "zmq")]
+    Validator,
+}
+
+/// Submit the batches to the endpoints listed in `url`, failing over to the next endpoint if one
+/// cannot be reached
+///
+/// The returned submission records the endpoint which accepted the batches, so that their status
+/// is checked with the same endpoint.
+pub fn submit_batches(
+    client: &HttpClient,
+    url: &str,
+    target: &SubmitTarget,
+    batch_list: Vec<Batch>,
+) -> Result<BatchSubmission, CliError> {
+    batch::log_batches(&batch_list);
+
+    let batch_ids = batch_list
+        .iter()
+        .map(|batch| batch.header_signature().to_string())
+        .collect::<Vec<_>>();
+
+    let bytes = batch_list.into_bytes()?;
+
+    http::with_failover(url, |url| {
+        let post_url = match target {
+            SubmitTarget::Sawtooth => String::from(url) + "/batches",
+            SubmitTarget::Scabbard {
+                circuit,
+                service_id,
+            } => format!("{}/scabbard/{}/{}/batches", url, circuit, service_id),
+            #[cfg(feature = "zmq")]
+            SubmitTarget::Validator => {
+                return crate::validator::submit_batches(url, &batch_ids, &bytes)
+            }
+        };
+
+        let response = client
+            .post(&post_url, bytes.clone())?
+            .error_for_status(url)?;
+
+        let batch_link = serde_json::from_slice::<Link>(&response.body).map_err(|err| {
+            CliError::UserError(format!(
+                "Unable to parse batch submission response from {}: {}",
+                url, err
+            ))
+        })?;
 
-    match hyper_uri.scheme() {
-        Some(scheme) => {
-            if scheme != "http" {
-                return Err(CliError::UserError(format!(
-                    "Unsupported scheme ({}) in URL: {}",
-                    scheme, url
-                )));
+        // Scabbard responds with a link relative to the Splinter REST API
+        let link = if batch_link.link.starts_with('/') {
+            url.trim_end_matches('/').to_string() + &batch_link.link
+        } else {
+            batch_link.link
+        };
+
+        Ok(BatchSubmission {
+            batch_ids: batch_ids.clone(),
+            link,
+            url: url.into(),
+        })
+    })
+}
+
+/// Limits on how quickly batches are submitted by `submit_throttled`
+#[derive(Default)]
+pub struct Throttle {
+    /// The most batches that may be submitted but not yet committed or found invalid
+    pub max_in_flight: Option<usize>,
+    /// The most batches submitted per second
+    pub rate: Option<f64>,
+}
+
+impl Throttle {
+    pub fn is_active(&self) -> bool {
+        self.max_in_flight.is_some() || self.rate.is_some()
+    }
+}
+
+/// Submit each batch in its own request, within the limits of the throttle, so that a large
+/// number of batches does not overwhelm the REST API's queue
+///
+/// Before each batch is submitted, the status of the batches in flight is checked until fewer
+/// than `max_in_flight` remain. Invalid batches are logged and do not stop the submission. The
+/// returned submission includes every batch, with a link to the status of all of them.
+pub fn submit_throttled(
+    client: &HttpClient,
+    url: &str,
+    target: &SubmitTarget,
+    batch_list: Vec<Batch>,
+    throttle: &Throttle,
+) -> Result<BatchSubmission, CliError> {
+    let interval = throttle
+        .rate
+        .map(|rate| Duration::from_secs_f64(1.0 / rate));
+    let count = batch_list.len();
+
+    let mut batch_ids = Vec::with_capacity(count);
+    let mut in_flight: Vec<String> = Vec::new();
+    let mut last = None;
+    for (index, batch) in batch_list.into_iter().enumerate() {
+        if let Some(max_in_flight) = throttle.max_in_flight {
+            while !in_flight.is_empty() {
+                let status_response = get_batch_statuses(client, target, &last, &in_flight)?;
+                for status in status_response.data {
+                    match status.status.as_str() {
+                        "COMMITTED" => in_flight.retain(|id| id != &status.id),
+                        "INVALID" => {
+                            warn!("Batch {} is invalid", status.id);
+                            in_flight.retain(|id| id != &status.id);
+                        }
+                        _ => (),
+                    }
+                }
+
+                if in_flight.len() < max_in_flight {
+                    break;
+                }
+                debug!(
+                    "{} batches in flight; waiting to submit more",
+                    in_flight.len()
+                );
+                thread::sleep(IN_FLIGHT_POLL_INTERVAL);
             }
         }
-        None => {
-            return Err(CliError::UserError(format!("No scheme in URL: {}", url)));
+
+        if let (Some(interval), Some((_, submitted_at))) = (interval, &last) {
+            if let Some(remaining) = interval.checked_sub(submitted_at.elapsed()) {
+                thread::sleep(remaining);
+            }
+        }
+
+        let submission = submit_batches(client, url, target, vec![batch])?;
+        info!(
+            "Submitted batch {} of {} ({} in flight)",
+            index + 1,
+            count,
+            in_flight.len() + 1
+        );
+
+        batch_ids.extend(submission.batch_ids.iter().cloned());
+        if throttle.max_in_flight.is_some() {
+            in_flight.extend(submission.batch_ids.iter().cloned());
         }
+        last = Some((submission, Instant::now()));
     }
 
-    let mut core = tokio_core::reactor::Core::new()?;
-    let handle = core.handle();
-    let client = Client::configure().build(&handle);
+    let url = match last {
+        Some((submission, _)) => submission.url,
+        None => return Err(CliError::UserError("No batches to submit".into())),
+    };
+    Ok(BatchSubmission {
+        link: status_link(target, &url, &batch_ids),
+        batch_ids,
+        url,
+    })
+}
+
+/// Fetch the status of the given batches from the endpoint which accepted the last submission
+fn get_batch_statuses(
+    client: &HttpClient,
+    target: &SubmitTarget,
+    last: &Option<(BatchSubmission, Instant)>,
+    batch_ids: &[String],
+) -> Result<StatusResponse, CliError> {
+    let url = match last {
+        Some((submission, _)) => &submission.url,
+        None => return Ok(StatusResponse::default()),
+    };
+
+    match target {
+        #[cfg(feature = "zmq")]
+        SubmitTarget::Validator => crate::validator::get_batch_status(url, batch_ids, 0),
+        _ => get_batch_status(client, target, &status_link(target, url, batch_ids)),
+    }
+}
+
+/// The link to the status of the given batches, as the endpoint would return when they are
+/// submitted together
+fn status_link(target: &SubmitTarget, url: &str, batch_ids: &[String]) -> String {
+    let url = url.trim_end_matches('/');
+    match target {
+        SubmitTarget::Scabbard {
+            circuit,
+            service_id,
+        } => format!(
+            "{}/scabbard/{}/{}/batch_statuses?ids={}",
+            url,
+            circuit,
+            service_id,
+            batch_ids.join(",")
+        ),
+        #[cfg(feature = "zmq")]
+        SubmitTarget::Validator => url.into(),
+        SubmitTarget::Sawtooth => format!("{}/batch_statuses?id={}", url, batch_ids.join(",")),
+    }
+}
+
+/// Write the batches to a file as a serialized BatchList, returning their IDs
+///
+/// The file can later be submitted with `sabre submit`.
+pub fn write_batches(path: &str, batch_list: Vec<Batch>) -> Result<Vec<String>, CliError> {
+    batch::log_batches(&batch_list);
+
+    let batch_ids = batch_list
+        .iter()
+        .map(|batch| batch.header_signature().to_string())
+        .collect();
 
     let bytes = batch_list.into_bytes()?;
 
-    let mut req = Request::new(Method::Post, hyper_uri);
-    req.headers_mut().set(ContentType::octet_stream());
-    req.headers_mut().set(ContentLength(bytes.len() as u64));
-    req.set_body(bytes);
+    fs::write(path, bytes).map_err(|err| {
+        CliError::UserError(format!("Could not write batches to {}: {}", path, err))
+    })?;
 
-    let work = client.request(req).and_then(|res| {
-        res.body()
-            .concat2()
-            .and_then(move |chunks| future::ok(serde_json::from_slice::<Link>(&chunks).unwrap()))
-    });
+    Ok(batch_ids)
+}
 
-    let batch_link = core.run(work)?;
-    println!("Response Body:\n{:?}", batch_link);
+/// Poll the batch status link until the batches are finished or `wait` seconds have elapsed
+pub fn poll_batch_status(
+    client: &HttpClient,
+    target: &SubmitTarget,
+    submission: &BatchSubmission,
+    wait: u64,
+) -> Result<StatusResponse, CliError> {
+    let mut wait = wait;
+    loop {
+        let time = Instant::now();
+        let status_response = match target {
+            #[cfg(feature = "zmq")]
+            SubmitTarget::Validator => {
+                crate::validator::get_batch_status(&submission.link, &submission.batch_ids, wait)?
+            }
+            _ => wait_for_batch(client, target, &submission.link, wait)?,
+        };
 
-    Ok(batch_link.link)
+        wait = wait.saturating_sub(time.elapsed().as_secs());
+
+        if wait == 0 || status_response.is_finished() {
+            return Ok(status_response);
+        }
+    }
 }
 
-pub fn wait_for_batch(url: &str, wait: u64) -> Result<StatusResponse, CliError> {
-    let url_with_wait_query = format!("{}&wait={}", url, wait);
+/// Wait for the batches by subscribing to the REST API's block events, checking the batch status
+/// each time a block is committed, until the batches are finished or `wait` seconds have elapsed
+///
+/// Falls back to polling if the REST API does not accept the subscription.
+pub fn subscribe_batch_status(
+    client: &HttpClient,
+    target: &SubmitTarget,
+    submission: &BatchSubmission,
+    wait: u64,
+) -> Result<StatusResponse, CliError> {
+    // The validator already holds the status response until the batches are finished
+    #[cfg(feature = "zmq")]
+    if let SubmitTarget::Validator = target {
+        return poll_batch_status(client, target, submission, wait);
+    }
 
-    // Validate url
+    let deadline = Instant::now() + Duration::from_secs(wait);
 
-    let hyper_uri = match url_with_wait_query.parse::<hyper::Uri>() {
-        Ok(uri) => uri,
-        Err(e) => return Err(CliError::UserError(format!("Invalid URL: {}: {}", e, url))),
+    let mut socket = match subscribe_to_blocks(client, target, &submission.url, wait) {
+        Ok(socket) => socket,
+        Err(err) => {
+            warn!(
+                "Unable to subscribe to block events ({}); polling for batch status",
+                err
+            );
+            return poll_batch_status(client, target, submission, wait);
+        }
     };
 
-    match hyper_uri.scheme() {
-        Some(scheme) => {
-            if scheme != "http" {
-                return Err(CliError::UserError(format!(
-                    "Unsupported scheme ({}) in URL: {}",
-                    scheme, url
-                )));
-            }
-        }
-        None => {
-            return Err(CliError::UserError(format!("No scheme in URL: {}", url)));
+    loop {
+        let status_response = get_batch_status(client, target, &submission.link)?;
+        let now = Instant::now();
+        if status_response.is_finished() || now >= deadline {
+            return Ok(status_response);
         }
+
+        // Any message means a new block was committed; the batches may be in it
+        socket.read_message(deadline - now)?;
     }
+}
 
-    let mut core = tokio_core::reactor::Core::new()?;
-    let handle = core.handle();
-    let client = Client::configure().build(&handle);
+fn subscribe_to_blocks(
+    client: &HttpClient,
+    target: &SubmitTarget,
+    url: &str,
+    wait: u64,
+) -> Result<WebSocket, CliError> {
+    if let SubmitTarget::Scabbard { .. } = target {
+        return Err(CliError::UserError(
+            "subscriptions are not supported by scabbard".into(),
+        ));
+    }
 
-    let work = client.get(hyper_uri).and_then(|res| {
-        if res.status() == StatusCode::ServiceUnavailable {
-            panic!("Service Unavailable");
-        } else {
-            res.body().concat2().and_then(move |chunks| {
-                future::ok(serde_json::from_slice::<StatusResponse>(&chunks).unwrap())
+    events::subscribe(
+        client,
+        url,
+        &[SABRE_ADDRESS_PREFIX],
+        Duration::from_secs(wait),
+    )
+}
+
+pub fn wait_for_batch(
+    client: &HttpClient,
+    target: &SubmitTarget,
+    url: &str,
+    wait: u64,
+) -> Result<StatusResponse, CliError> {
+    get_batch_status(client, target, &format!("{}&wait={}", url, wait))
+}
+
+/// Fetch the status of the batches from the given batch status link
+fn get_batch_status(
+    client: &HttpClient,
+    target: &SubmitTarget,
+    url: &str,
+) -> Result<StatusResponse, CliError> {
+    let response = client.get(url)?.error_for_status(url)?;
+
+    let status_response = match target {
+        SubmitTarget::Scabbard { .. } => {
+            serde_json::from_slice::<Vec<ScabbardBatchInfo>>(&response.body).map(|batches| {
+                StatusResponse {
+                    data: batches.into_iter().map(BatchStatus::from).collect(),
+                    link: url.into(),
+                }
             })
         }
-    });
-
-    let body = core.run(work)?;
+        _ => serde_json::from_slice::<StatusResponse>(&response.body),
+    };
 
-    Ok(body)
+    status_response.map_err(|err| {
+        CliError::UserError(format!(
+            "Unable to parse batch status response from {}: {}",
+            url, err
+        ))
+    })
 }
 
 #[derive(Deserialize, Debug)]
@@ -118,23 +385,80 @@ struct Link {
     link: String,
 }
 
-#[derive(Deserialize, Debug)]
+/// The IDs of batches submitted to the REST API, and the link to check their status
+#[derive(Serialize, Debug)]
+pub struct BatchSubmission {
+    pub batch_ids: Vec<String>,
+    pub link: String,
+    /// The endpoint which accepted the batches
+    #[serde(skip)]
+    pub url: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
 pub struct BatchStatus {
-    id: String,
-    status: String,
-    invalid_transactions: Vec<InvalidTransaction>,
+    pub id: String,
+    pub status: String,
+    pub invalid_transactions: Vec<InvalidTransaction>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct InvalidTransaction {
+    pub id: String,
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct StatusResponse {
+    pub data: Vec<BatchStatus>,
+    pub link: String,
+}
+
+/// The status of a batch, as returned by scabbard
+#[derive(Deserialize, Debug)]
+struct ScabbardBatchInfo {
     id: String,
-    message: String,
+    status: ScabbardBatchStatus,
 }
 
 #[derive(Deserialize, Debug)]
-pub struct StatusResponse {
-    data: Vec<BatchStatus>,
-    link: String,
+#[serde(tag = "statusType", content = "message")]
+enum ScabbardBatchStatus {
+    Unknown,
+    Pending,
+    Invalid(Vec<ScabbardInvalidTransaction>),
+    Valid(IgnoredAny),
+    Committed(IgnoredAny),
+}
+
+#[derive(Deserialize, Debug)]
+struct ScabbardInvalidTransaction {
+    transaction_id: String,
+    error_message: String,
+}
+
+impl From<ScabbardBatchInfo> for BatchStatus {
+    fn from(batch: ScabbardBatchInfo) -> Self {
+        let (status, invalid_transactions) = match batch.status {
+            ScabbardBatchStatus::Unknown => ("UNKNOWN", vec![]),
+            // A valid batch has been executed, but not yet committed
+            ScabbardBatchStatus::Pending | ScabbardBatchStatus::Valid(_) => ("PENDING", vec![]),
+            ScabbardBatchStatus::Invalid(transactions) => ("INVALID", transactions),
+            ScabbardBatchStatus::Committed(_) => ("COMMITTED", vec![]),
+        };
+
+        BatchStatus {
+            id: batch.id,
+            status: status.into(),
+            invalid_transactions: invalid_transactions
+                .into_iter()
+                .map(|transaction| InvalidTransaction {
+                    id: transaction.transaction_id,
+                    message: transaction.error_message,
+                })
+                .collect(),
+        }
+    }
 }
 
 impl StatusResponse {
@@ -142,6 +466,49 @@ impl StatusResponse {
         self.data.iter().all(|x| x.status == "COMMITTED")
             || self.data.iter().any(|x| x.status == "INVALID")
     }
+
+    /// Return an error if any batch is invalid or if not all batches have been committed
+    pub fn check(&self) -> Result<(), CliError> {
+        let invalid_batches = self
+            .data
+            .iter()
+            .filter(|batch| batch.status == "INVALID")
+            .collect::<Vec<_>>();
+
+        if !invalid_batches.is_empty() {
+            let messages = invalid_batches
+                .iter()
+                .flat_map(|batch| {
+                    batch.invalid_transactions.iter().map(move |txn| {
+                        format!("batch {} transaction {}: {}", batch.id, txn.id, txn.message)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            return Err(CliError::InvalidBatchError(if messages.is_empty() {
+                invalid_batches
+                    .iter()
+                    .map(|batch| format!("batch {} is invalid", batch.id))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            } else {
+                messages.join("; ")
+            }));
+        }
+
+        let pending_batches = self
+            .data
+            .iter()
+            .filter(|batch| batch.status != "COMMITTED")
+            .map(|batch| format!("batch {} is {}", batch.id, batch.status))
+            .collect::<Vec<_>>();
+
+        if !pending_batches.is_empty() {
+            return Err(CliError::WaitTimeoutError(pending_batches.join("; ")));
+        }
+
+        Ok(())
+    }
 }
 
 impl fmt::Display for Link {
@@ -150,6 +517,12 @@ impl fmt::Display for Link {
     }
 }
 
+impl fmt::Display for BatchSubmission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{\"link\": \"{}\"}}", self.link)
+    }
+}
+
 impl fmt::Display for BatchStatus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut invalid_txn_string_vec = Vec::new();
@@ -12,25 +12,74 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 use std::path::Path;
 use std::path::PathBuf;
 
-use sabre_sdk::protocol::payload::CreateContractActionBuilder;
+use cylinder::Signer;
+use sabre_sdk::protocol::payload::{
+    CommitContractActionBuilder, CreateContractActionBuilder, CreateContractChunkActionBuilder,
+};
+use transact::protocol::transaction::TransactionBuilder;
 use yaml_rust::YamlLoader;
 
+use crate::archive;
 use crate::error::CliError;
-use crate::key::new_signer;
-use crate::submit::submit_batches;
+use crate::schema::StateSchema;
+use crate::wasm;
 
-pub fn do_upload(
+/// A contract to be uploaded, as loaded from a contract definition file or a smart contract
+/// archive
+pub struct ContractUpload {
+    pub name: String,
+    pub version: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub contract: Vec<u8>,
+    /// The schema of the contract's state, registered with `register_state_schema`
+    pub state_schema: Option<StateSchema>,
+}
+
+/// The transactions that upload a contract in chunks
+pub struct ChunkedUpload {
+    /// One transaction per chunk, in order, with the size of the chunk it carries
+    pub chunks: Vec<(TransactionBuilder, usize)>,
+    /// The transaction which assembles the chunks into the contract
+    pub commit: TransactionBuilder,
+}
+
+/// Load the contract described by a contract definition file or contained in a smart contract
+/// archive (*.scar)
+///
+/// If `sha512` is given, the contract must have that hex-encoded SHA-512 hash.
+pub fn load_contract(
     filename: &str,
-    key_name: Option<&str>,
-    url: &str,
     wasm_name: Option<&str>,
-) -> Result<String, CliError> {
+    sha512: Option<&str>,
+) -> Result<ContractUpload, CliError> {
+    if filename.ends_with(".scar") {
+        if wasm_name.is_some() {
+            return Err(CliError::UserError(
+                "--wasm cannot be used with a smart contract archive".into(),
+            ));
+        }
+
+        let archive = archive::load(filename)?;
+        verify_sha512(&archive.contract, sha512)?;
+
+        return Ok(ContractUpload {
+            name: archive.manifest.name,
+            version: archive.manifest.version,
+            inputs: archive.manifest.inputs,
+            outputs: archive.manifest.outputs,
+            contract: archive.contract,
+            state_schema: None,
+        });
+    }
+
     let definition = ContractDefinition::load(filename)?;
 
     // Load the contract file relative to the directory containing the
@@ -50,20 +99,156 @@ pub fn do_upload(
     }
 
     let contract = load_contract_file(contract_path_buf.as_path())?;
+    verify_sha512(&contract, sha512)?;
 
-    let signer = new_signer(key_name)?;
-    let batch = CreateContractActionBuilder::new()
-        .with_name(definition.name)
-        .with_version(definition.version)
-        .with_inputs(definition.inputs)
-        .with_outputs(definition.outputs)
-        .with_contract(contract)
-        .into_payload_builder()?
-        .into_transaction_builder(&*signer)?
-        .into_batch_builder(&*signer)?
-        .build(&*signer)?;
-
-    submit_batches(url, vec![batch])
+    // The descriptor set is also loaded relative to the directory containing the definition
+    let state_schema = match (definition.state_schema, definition.state_message) {
+        (Some(state_schema), Some(state_message)) => {
+            let mut path = PathBuf::from(filename);
+            path.pop();
+            path.push(state_schema);
+            Some(StateSchema::load(&path.to_string_lossy(), &state_message)?)
+        }
+        (None, None) => None,
+        _ => {
+            return Err(CliError::UserError(format!(
+                "Malformed contract definition file \"{}\": \"state_schema\" and \
+                 \"state_message\" must be given together",
+                filename
+            )))
+        }
+    };
+
+    Ok(ContractUpload {
+        name: definition.name,
+        version: definition.version,
+        inputs: definition.inputs,
+        outputs: definition.outputs,
+        contract,
+        state_schema,
+    })
+}
+
+impl ContractUpload {
+    /// Replace the contract with a smaller, optimized module
+    pub fn optimize(&mut self) -> Result<(), CliError> {
+        let (contract, tool) = wasm::optimize(&self.contract)?;
+        info!(
+            "Optimized contract {} {} with {}: {} bytes, down from {}",
+            self.name,
+            self.version,
+            tool,
+            contract.len(),
+            self.contract.len()
+        );
+        self.contract = contract;
+        Ok(())
+    }
+
+    /// Store the schema of the contract's state, if any, in the contract's WebAssembly module, so
+    /// that `sabre query` can decode the contract's state
+    ///
+    /// This changes the contract, so it must be done after the contract is optimized.
+    pub fn register_state_schema(&mut self) -> Result<(), CliError> {
+        if let Some(state_schema) = &self.state_schema {
+            self.contract = state_schema.register(&self.contract)?;
+            info!(
+                "Registered state schema {} for contract {} {}",
+                state_schema.message, self.name, self.version
+            );
+        }
+        Ok(())
+    }
+
+    /// Warn if the contract is larger than `max_size` bytes
+    ///
+    /// Every version of a contract is kept in state, so large contracts make state grow quickly.
+    pub fn check_size(&self, max_size: usize) {
+        if self.contract.len() > max_size {
+            warn!(
+                "Contract {} {} is {} bytes, more than the limit of {} bytes; every uploaded \
+                 version is kept in state, so consider --optimize, or raise the limit with \
+                 --max-size",
+                self.name,
+                self.version,
+                self.contract.len(),
+                max_size
+            );
+        }
+    }
+
+    /// Build the transaction that uploads the whole contract at once
+    pub fn into_transaction_builder(
+        self,
+        signer: &dyn Signer,
+    ) -> Result<TransactionBuilder, CliError> {
+        CreateContractActionBuilder::new()
+            .with_name(self.name)
+            .with_version(self.version)
+            .with_inputs(self.inputs)
+            .with_outputs(self.outputs)
+            .with_contract(self.contract)
+            .into_payload_builder()?
+            .into_transaction_builder(signer)
+            .map_err(CliError::from)
+    }
+
+    /// Build the transactions that upload the contract in chunks of at most `chunk_size` bytes,
+    /// followed by the transaction which assembles the chunks into the contract
+    pub fn into_chunked_transaction_builders(
+        self,
+        chunk_size: usize,
+        signer: &dyn Signer,
+    ) -> Result<ChunkedUpload, CliError> {
+        if chunk_size == 0 {
+            return Err(CliError::UserError(
+                "Chunk size must be greater than zero".into(),
+            ));
+        }
+
+        let chunks = self
+            .contract
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let index = u32::try_from(index)
+                    .map_err(|_| CliError::UserError("Contract has too many chunks".into()))?;
+                let transaction = CreateContractChunkActionBuilder::new()
+                    .with_name(self.name.clone())
+                    .with_version(self.version.clone())
+                    .with_index(index)
+                    .with_chunk(chunk.to_vec())
+                    .into_payload_builder()?
+                    .into_transaction_builder(signer)?;
+                Ok((transaction, chunk.len()))
+            })
+            .collect::<Result<Vec<_>, CliError>>()?;
+
+        let commit = CommitContractActionBuilder::new()
+            .with_name(self.name)
+            .with_version(self.version)
+            .with_inputs(self.inputs)
+            .with_outputs(self.outputs)
+            .with_chunk_count(chunks.len() as u32)
+            .with_contract_sha512(archive::contract_sha512(&self.contract))
+            .into_payload_builder()?
+            .into_transaction_builder(signer)?;
+
+        Ok(ChunkedUpload { chunks, commit })
+    }
+}
+
+fn verify_sha512(contract: &[u8], sha512: Option<&str>) -> Result<(), CliError> {
+    let actual = archive::contract_sha512(contract);
+    match sha512 {
+        Some(expected) if !expected.eq_ignore_ascii_case(&actual) => {
+            Err(CliError::UserError(format!(
+                "Contract hash mismatch: expected {}, found {}",
+                expected, actual
+            )))
+        }
+        _ => Ok(()),
+    }
 }
 
 fn load_contract_file(path: &Path) -> Result<Vec<u8>, CliError> {
@@ -93,6 +278,8 @@ struct ContractDefinition {
     inputs: Vec<String>,
     outputs: Vec<String>,
     wasm: Option<String>,
+    state_schema: Option<String>,
+    state_message: Option<String>,
 }
 
 impl ContractDefinition {
@@ -136,6 +323,8 @@ impl ContractDefinition {
         })?;
 
         let wasm = doc["wasm"].as_str().map(ToString::to_string);
+        let state_schema = doc["state_schema"].as_str().map(ToString::to_string);
+        let state_message = doc["state_message"].as_str().map(ToString::to_string);
 
         let inputs = doc["inputs"]
             .as_vec()
@@ -175,6 +364,8 @@ impl ContractDefinition {
             inputs,
             outputs,
             wasm,
+            state_schema,
+            state_message,
         })
     }
 }
@@ -0,0 +1,491 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which submit batches directly to a validator's client interface over ZMQ
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use protobuf::Message as _;
+use rand::RngCore;
+use sawtooth_sdk::messages::batch::BatchList;
+use sawtooth_sdk::messages::client_batch_submit::ClientBatchStatusResponse_Status as StatusResponseStatus;
+use sawtooth_sdk::messages::client_batch_submit::ClientBatchStatus_Status as BatchStatusStatus;
+use sawtooth_sdk::messages::client_batch_submit::ClientBatchSubmitResponse_Status as SubmitResponseStatus;
+use sawtooth_sdk::messages::client_batch_submit::{
+    ClientBatchStatus, ClientBatchStatusRequest, ClientBatchStatusResponse,
+    ClientBatchSubmitRequest, ClientBatchSubmitResponse,
+};
+use sawtooth_sdk::messages::client_event::ClientEventsSubscribeResponse_Status as SubscribeResponseStatus;
+use sawtooth_sdk::messages::client_event::{
+    ClientEventsSubscribeRequest, ClientEventsSubscribeResponse,
+};
+use sawtooth_sdk::messages::client_receipt::ClientReceiptGetResponse_Status as ReceiptResponseStatus;
+use sawtooth_sdk::messages::client_receipt::{ClientReceiptGetRequest, ClientReceiptGetResponse};
+use sawtooth_sdk::messages::events::EventFilter_FilterType;
+use sawtooth_sdk::messages::events::{EventFilter, EventList, EventSubscription};
+use sawtooth_sdk::messages::transaction_receipt::StateChange_Type;
+use sawtooth_sdk::messages::transaction_receipt::{StateChangeList, TransactionReceipt};
+use sawtooth_sdk::messages::validator::Message_MessageType;
+use sawtooth_sdk::messages::{events as proto_events, transaction_receipt as proto_receipt};
+use sawtooth_sdk::messaging::stream::{
+    MessageConnection, MessageReceiver, MessageSender, ReceiveError,
+};
+use sawtooth_sdk::messaging::zmq_stream::{ZmqMessageConnection, ZmqMessageSender};
+
+use crate::error::CliError;
+use crate::events::{Block, Event, StateChange};
+use crate::http;
+use crate::receipt::Receipt;
+use crate::submit::{BatchStatus, BatchSubmission, InvalidTransaction, StatusResponse};
+
+/// How long to wait for the validator to reply, in addition to any requested wait time
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Submit the serialized BatchList to the validator at the given `tcp://` URL
+///
+/// The link in the returned submission is the validator URL, since the validator is also queried
+/// for the batch status.
+pub fn submit_batches(
+    url: &str,
+    batch_ids: &[String],
+    batch_list: &[u8],
+) -> Result<BatchSubmission, CliError> {
+    // The validator's BatchList message has the same encoding as transact's
+    let batch_list = BatchList::parse_from_bytes(batch_list)
+        .map_err(|err| CliError::UserError(format!("Unable to encode batches: {}", err)))?;
+
+    let mut request = ClientBatchSubmitRequest::new();
+    request.set_batches(batch_list.batches);
+
+    let response: ClientBatchSubmitResponse = send(
+        url,
+        Message_MessageType::CLIENT_BATCH_SUBMIT_REQUEST,
+        &request,
+        RESPONSE_TIMEOUT,
+    )?;
+
+    match response.status {
+        SubmitResponseStatus::OK => Ok(BatchSubmission {
+            batch_ids: batch_ids.to_vec(),
+            link: url.into(),
+            url: url.into(),
+        }),
+        SubmitResponseStatus::INVALID_BATCH => Err(CliError::InvalidBatchError(
+            "The submitted BatchList was rejected by the validator".into(),
+        )),
+        SubmitResponseStatus::QUEUE_FULL => Err(CliError::ConnectionError(format!(
+            "Validator at {} is not accepting batches because its queue is full",
+            url
+        ))),
+        status => Err(CliError::ConnectionError(format!(
+            "Validator at {} failed to accept the batches: {:?}",
+            url, status
+        ))),
+    }
+}
+
+/// Fetch the status of the batches from the validator at the given `tcp://` URL
+///
+/// If `wait` is not zero, the validator holds the response until the batches are committed or
+/// invalid, or until `wait` seconds have elapsed.
+pub fn get_batch_status(
+    url: &str,
+    batch_ids: &[String],
+    wait: u64,
+) -> Result<StatusResponse, CliError> {
+    let timeout = u32::try_from(wait)
+        .map_err(|_| CliError::UserError(format!("Wait is too large: {}", wait)))?;
+
+    let mut request = ClientBatchStatusRequest::new();
+    request.set_batch_ids(batch_ids.to_vec().into());
+    request.set_wait(timeout > 0);
+    request.set_timeout(timeout);
+
+    let response: ClientBatchStatusResponse = send(
+        url,
+        Message_MessageType::CLIENT_BATCH_STATUS_REQUEST,
+        &request,
+        RESPONSE_TIMEOUT + Duration::from_secs(wait),
+    )?;
+
+    match response.status {
+        StatusResponseStatus::OK => Ok(StatusResponse {
+            data: response
+                .batch_statuses
+                .into_iter()
+                .map(BatchStatus::from)
+                .collect(),
+            link: url.into(),
+        }),
+        StatusResponseStatus::NO_RESOURCE => Err(CliError::UserError(format!(
+            "Validator at {} has no record of the batches",
+            url
+        ))),
+        StatusResponseStatus::INVALID_ID => Err(CliError::UserError(format!(
+            "Validator at {} rejected the batch IDs as invalid",
+            url
+        ))),
+        status => Err(CliError::ConnectionError(format!(
+            "Validator at {} failed to return the batch status: {:?}",
+            url, status
+        ))),
+    }
+}
+
+/// Fetch the receipts of the given transactions from the first of the validators listed in `url`
+/// which can be reached
+pub fn get_receipts(url: &str, transaction_ids: &[&str]) -> Result<Vec<Receipt>, CliError> {
+    let mut request = ClientReceiptGetRequest::new();
+    request.set_transaction_ids(
+        transaction_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .into(),
+    );
+
+    let (url, response): (&str, ClientReceiptGetResponse) = http::with_failover(url, |url| {
+        let response = send(
+            url,
+            Message_MessageType::CLIENT_RECEIPT_GET_REQUEST,
+            &request,
+            RESPONSE_TIMEOUT,
+        )?;
+        Ok((url, response))
+    })?;
+
+    match response.status {
+        ReceiptResponseStatus::OK => Ok(response.receipts.into_iter().map(Receipt::from).collect()),
+        ReceiptResponseStatus::NO_RESOURCE => Err(CliError::UserError(format!(
+            "Validator at {} has no receipt for the transactions",
+            url
+        ))),
+        ReceiptResponseStatus::INVALID_ID => Err(CliError::UserError(format!(
+            "Validator at {} rejected the transaction IDs as invalid",
+            url
+        ))),
+        status => Err(CliError::ConnectionError(format!(
+            "Validator at {} failed to return the receipts: {:?}",
+            url, status
+        ))),
+    }
+}
+
+/// The event sent by the validator when a block is committed
+const BLOCK_COMMIT_EVENT: &str = "sawtooth/block-commit";
+
+/// The event sent by the validator with the state changes of a committed block
+const STATE_DELTA_EVENT: &str = "sawtooth/state-delta";
+
+/// Call `on_block` with the state changes under the given address prefixes, and the events of
+/// the given types, in each block committed from now on
+///
+/// The subscription is made to the first of the validators listed in `url` which can be reached.
+/// Runs until the validator disconnects, or `on_block` returns an error.
+pub fn listen(
+    url: &str,
+    prefixes: &[&str],
+    event_types: &[String],
+    on_block: &mut dyn FnMut(Block) -> Result<(), CliError>,
+) -> Result<(), CliError> {
+    let mut state_filter = EventFilter::new();
+    state_filter.set_key("address".into());
+    state_filter.set_match_string(
+        prefixes
+            .iter()
+            .map(|prefix| format!("^{}", prefix))
+            .collect::<Vec<_>>()
+            .join("|"),
+    );
+    state_filter.set_filter_type(EventFilter_FilterType::REGEX_ANY);
+
+    let mut subscriptions = vec![
+        subscription(BLOCK_COMMIT_EVENT),
+        subscription(STATE_DELTA_EVENT),
+    ];
+    subscriptions[1].set_filters(vec![state_filter].into());
+    subscriptions.extend(
+        event_types
+            .iter()
+            .map(|event_type| subscription(event_type)),
+    );
+
+    let mut request = ClientEventsSubscribeRequest::new();
+    request.set_subscriptions(subscriptions.into());
+    let content = request
+        .write_to_bytes()
+        .map_err(|err| CliError::UserError(format!("Unable to encode request: {}", err)))?;
+
+    let (url, mut sender, receiver) = http::with_failover(url, |url| {
+        check_url(url)?;
+        let (mut sender, receiver) = ZmqMessageConnection::new(url).create();
+        match subscribe(url, &sender, &content) {
+            Ok(()) => Ok((url, sender, receiver)),
+            Err(err) => {
+                sender.close();
+                Err(err)
+            }
+        }
+    })?;
+    let result = receive(url, &receiver, on_block);
+    sender.close();
+
+    result
+}
+
+fn subscribe(url: &str, sender: &ZmqMessageSender, content: &[u8]) -> Result<(), CliError> {
+    let reply = send_on(
+        sender,
+        Message_MessageType::CLIENT_EVENTS_SUBSCRIBE_REQUEST,
+        content,
+        RESPONSE_TIMEOUT,
+    )
+    .map_err(|err| {
+        CliError::ConnectionError(format!("No response from validator at {}: {}", url, err))
+    })?;
+    let response =
+        ClientEventsSubscribeResponse::parse_from_bytes(reply.get_content()).map_err(|err| {
+            CliError::ConnectionError(format!(
+                "Unable to parse response from validator at {}: {}",
+                url, err
+            ))
+        })?;
+
+    match response.status {
+        SubscribeResponseStatus::OK => Ok(()),
+        SubscribeResponseStatus::INVALID_FILTER => Err(CliError::UserError(format!(
+            "Validator at {} rejected the subscription filters",
+            url
+        ))),
+        status => Err(CliError::ConnectionError(format!(
+            "Validator at {} failed to accept the subscription: {:?}",
+            url, status
+        ))),
+    }
+}
+
+fn receive(
+    url: &str,
+    receiver: &MessageReceiver,
+    on_block: &mut dyn FnMut(Block) -> Result<(), CliError>,
+) -> Result<(), CliError> {
+    loop {
+        let message = match receiver.recv() {
+            Ok(Ok(message)) => message,
+            Ok(Err(ReceiveError::TimeoutError)) => continue,
+            Ok(Err(err)) => {
+                return Err(CliError::ConnectionError(format!(
+                    "Lost connection to validator at {}: {}",
+                    url, err
+                )))
+            }
+            Err(_) => {
+                return Err(CliError::ConnectionError(format!(
+                    "Lost connection to validator at {}",
+                    url
+                )))
+            }
+        };
+        if message.get_message_type() != Message_MessageType::CLIENT_EVENTS {
+            continue;
+        }
+
+        let event_list = EventList::parse_from_bytes(message.get_content()).map_err(|err| {
+            CliError::ConnectionError(format!(
+                "Unable to parse events from validator at {}: {}",
+                url, err
+            ))
+        })?;
+        on_block(to_block(event_list)?)?;
+    }
+}
+
+fn subscription(event_type: &str) -> EventSubscription {
+    let mut subscription = EventSubscription::new();
+    subscription.set_event_type(event_type.into());
+    subscription
+}
+
+/// Collect the events the validator sent for a committed block
+fn to_block(event_list: EventList) -> Result<Block, CliError> {
+    let mut block = Block {
+        block_num: 0,
+        block_id: String::new(),
+        changes: vec![],
+        events: vec![],
+    };
+
+    for event in event_list.events.into_iter() {
+        match event.get_event_type() {
+            BLOCK_COMMIT_EVENT => {
+                for attribute in event.get_attributes() {
+                    match attribute.get_key() {
+                        "block_id" => block.block_id = attribute.get_value().into(),
+                        "block_num" => {
+                            block.block_num = attribute.get_value().parse().map_err(|_| {
+                                CliError::ConnectionError(format!(
+                                    "Invalid block number from validator: {}",
+                                    attribute.get_value()
+                                ))
+                            })?
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            STATE_DELTA_EVENT => {
+                let mut changes =
+                    StateChangeList::parse_from_bytes(event.get_data()).map_err(|err| {
+                        CliError::ConnectionError(format!(
+                            "Unable to parse state changes from validator: {}",
+                            err
+                        ))
+                    })?;
+                block.changes.extend(
+                    changes
+                        .take_state_changes()
+                        .into_iter()
+                        .map(StateChange::from),
+                );
+            }
+            _ => block.events.push(Event::from(event)),
+        }
+    }
+
+    Ok(block)
+}
+
+/// Send a request to the validator and wait up to `timeout` for its reply
+fn send<T: protobuf::Message>(
+    url: &str,
+    message_type: Message_MessageType,
+    request: &dyn protobuf::Message,
+    timeout: Duration,
+) -> Result<T, CliError> {
+    check_url(url)?;
+
+    let content = request
+        .write_to_bytes()
+        .map_err(|err| CliError::UserError(format!("Unable to encode request: {}", err)))?;
+
+    let (mut sender, _) = ZmqMessageConnection::new(url).create();
+    let reply = send_on(&sender, message_type, &content, timeout);
+    sender.close();
+
+    let reply = reply.map_err(|err| {
+        CliError::ConnectionError(format!("No response from validator at {}: {}", url, err))
+    })?;
+
+    T::parse_from_bytes(reply.get_content()).map_err(|err| {
+        CliError::ConnectionError(format!(
+            "Unable to parse response from validator at {}: {}",
+            url, err
+        ))
+    })
+}
+
+fn check_url(url: &str) -> Result<(), CliError> {
+    if !url.starts_with("tcp://") {
+        return Err(CliError::UserError(format!(
+            "Validator URL must start with tcp://, such as tcp://localhost:4004: {}",
+            url
+        )));
+    }
+
+    Ok(())
+}
+
+fn send_on(
+    sender: &ZmqMessageSender,
+    message_type: Message_MessageType,
+    content: &[u8],
+    timeout: Duration,
+) -> Result<sawtooth_sdk::messages::validator::Message, Box<dyn std::error::Error>> {
+    let mut correlation_id = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut correlation_id);
+    let correlation_id = correlation_id
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    Ok(sender
+        .send(message_type, &correlation_id, content)?
+        .get_timeout(timeout)?)
+}
+
+impl From<proto_receipt::StateChange> for StateChange {
+    fn from(mut change: proto_receipt::StateChange) -> Self {
+        StateChange {
+            address: change.take_address(),
+            value: match change.get_field_type() {
+                StateChange_Type::DELETE => None,
+                _ => Some(change.take_value()),
+            },
+        }
+    }
+}
+
+impl From<proto_events::Event> for Event {
+    fn from(mut event: proto_events::Event) -> Self {
+        Event {
+            event_type: event.take_event_type(),
+            attributes: event
+                .take_attributes()
+                .into_iter()
+                .map(|mut attribute| (attribute.take_key(), attribute.take_value()))
+                .collect(),
+            data: event.take_data(),
+        }
+    }
+}
+
+impl From<TransactionReceipt> for Receipt {
+    fn from(mut receipt: TransactionReceipt) -> Self {
+        Receipt {
+            transaction_id: receipt.take_transaction_id(),
+            changes: receipt
+                .take_state_changes()
+                .into_iter()
+                .map(StateChange::from)
+                .collect(),
+            events: receipt.take_events().into_iter().map(Event::from).collect(),
+            data: receipt.take_data().into_vec(),
+        }
+    }
+}
+
+impl From<ClientBatchStatus> for BatchStatus {
+    fn from(mut batch: ClientBatchStatus) -> Self {
+        let status = match batch.status {
+            BatchStatusStatus::COMMITTED => "COMMITTED",
+            BatchStatusStatus::INVALID => "INVALID",
+            BatchStatusStatus::PENDING => "PENDING",
+            BatchStatusStatus::UNKNOWN | BatchStatusStatus::STATUS_UNSET => "UNKNOWN",
+        };
+
+        BatchStatus {
+            id: batch.take_batch_id(),
+            status: status.into(),
+            invalid_transactions: batch
+                .take_invalid_transactions()
+                .into_iter()
+                .map(|mut transaction| InvalidTransaction {
+                    id: transaction.take_transaction_id(),
+                    message: transaction.take_message(),
+                })
+                .collect(),
+        }
+    }
+}
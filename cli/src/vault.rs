@@ -0,0 +1,254 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains a signer backed by a HashiCorp Vault transit secrets engine
+//!
+//! The private key never leaves Vault: each header is hashed locally, and only the hash is sent
+//! to Vault to be signed.
+
+use std::env;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use cylinder::{PublicKey, Signature, Signer, SigningError};
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, PointConversionForm};
+use openssl::ecdsa::EcdsaSig;
+use openssl::error::ErrorStack;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use serde_json::Value;
+
+use crate::error::CliError;
+use crate::http::{Auth, HttpClient, Response};
+
+/// Keys with this prefix are held by a Vault transit secrets engine, e.g. `vault:<key name>`
+pub const VAULT_KEY_PREFIX: &str = "vault:";
+
+/// Address of the Vault server, e.g. `https://vault.example.com:8200`
+const ADDR_ENV_VAR: &str = "VAULT_ADDR";
+/// Token to authenticate to Vault with; it must be allowed to read and sign with the key
+const TOKEN_ENV_VAR: &str = "VAULT_TOKEN";
+/// Path to a PEM file with the CA certificates used to verify the Vault server
+const CACERT_ENV_VAR: &str = "VAULT_CACERT";
+/// Path the transit secrets engine is mounted at; defaults to "transit"
+const MOUNT_ENV_VAR: &str = "SABRE_VAULT_TRANSIT_MOUNT";
+
+const DEFAULT_MOUNT: &str = "transit";
+
+/// Return a signer using the transit key with the name given in `key`, which must be of the form
+/// `vault:<key name>`
+///
+/// The latest version of the key is used for every signature.
+pub fn new_vault_signer(key: &str) -> Result<Box<dyn Signer>, CliError> {
+    let name = key.trim_start_matches(VAULT_KEY_PREFIX);
+    if name.is_empty() {
+        return Err(CliError::UserError(
+            "Vault keys must be of the form 'vault:<key name>'".into(),
+        ));
+    }
+
+    let addr = env::var(ADDR_ENV_VAR).map_err(|_| {
+        CliError::UserError(format!(
+            "{} must be set to the address of a Vault server to use a Vault key",
+            ADDR_ENV_VAR
+        ))
+    })?;
+    let token = env::var(TOKEN_ENV_VAR).map_err(|_| {
+        CliError::UserError(format!(
+            "{} must be set to a Vault token to use a Vault key",
+            TOKEN_ENV_VAR
+        ))
+    })?;
+    let mount = env::var(MOUNT_ENV_VAR).unwrap_or_else(|_| DEFAULT_MOUNT.into());
+
+    let mut signer = VaultSigner {
+        url: format!(
+            "{}/v1/{}",
+            addr.trim_end_matches('/'),
+            mount.trim_matches('/')
+        ),
+        name: name.into(),
+        token,
+        ca_file: env::var(CACERT_ENV_VAR).ok(),
+        version: 0,
+        public_key: PublicKey::new(vec![]),
+    };
+
+    let key_url = format!("{}/keys/{}", signer.url, signer.name);
+    let data = signer.read_data(signer.client().get(&key_url)?, &key_url)?;
+
+    let key_type = data["type"].as_str().unwrap_or("unknown");
+    signer.version = data["latest_version"].as_u64().ok_or_else(|| {
+        CliError::SigningError(format!("Vault key '{}' has no latest version", name))
+    })?;
+    let pem = data["keys"][signer.version.to_string()]["public_key"]
+        .as_str()
+        .ok_or_else(|| {
+            CliError::UserError(format!(
+                "Vault key '{}' has type {}, which has no public key; Sabre transactions must be \
+                 signed with a secp256k1 key",
+                name, key_type
+            ))
+        })?;
+    signer.public_key = read_public_key(pem).map_err(|err| {
+        CliError::UserError(format!(
+            "Vault key '{}' has type {}, but Sabre transactions must be signed with a \
+             secp256k1 key: {}",
+            name, key_type, err
+        ))
+    })?;
+
+    Ok(Box::new(signer))
+}
+
+/// A cylinder `Signer` which signs with a secp256k1 key held by a Vault transit secrets engine
+#[derive(Clone)]
+struct VaultSigner {
+    /// The URL of the transit secrets engine, e.g. `https://vault.example.com:8200/v1/transit`
+    url: String,
+    name: String,
+    token: String,
+    ca_file: Option<String>,
+    /// The version of the key to sign with
+    version: u64,
+    public_key: PublicKey,
+}
+
+impl VaultSigner {
+    fn client(&self) -> HttpClient {
+        let client = HttpClient::new().with_auth(Auth::Bearer(self.token.clone()));
+        match &self.ca_file {
+            Some(ca_file) => client.with_ca_file(ca_file),
+            None => client,
+        }
+    }
+
+    /// Return the `data` object of a Vault response, or an error with the messages Vault gave
+    fn read_data(&self, response: Response, url: &str) -> Result<Value, CliError> {
+        let body = serde_json::from_slice::<Value>(&response.body).unwrap_or(Value::Null);
+        if !response.status.is_success() {
+            let mut msg = format!("Vault at {} responded with {}", url, response.status);
+            let errors = body["errors"]
+                .as_array()
+                .map(|errors| errors.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+                .unwrap_or_default();
+            if !errors.is_empty() {
+                msg.push_str(&format!(": {}", errors.join("; ")));
+            }
+            return Err(CliError::SigningError(msg));
+        }
+
+        match body.get("data") {
+            Some(data) => Ok(data.clone()),
+            None => Err(CliError::SigningError(format!(
+                "Vault at {} responded without data",
+                url
+            ))),
+        }
+    }
+
+    fn sign_hash(&self, hash: &[u8]) -> Result<Vec<u8>, CliError> {
+        let sign_url = format!("{}/sign/{}", self.url, self.name);
+        let request = serde_json::json!({
+            "input": base64::encode(hash),
+            "prehashed": true,
+            "hash_algorithm": "sha2-256",
+            "key_version": self.version,
+        });
+        let data = self.read_data(
+            self.client()
+                .post(&sign_url, request.to_string().into_bytes())?,
+            &sign_url,
+        )?;
+
+        // Signatures have the form `vault:v<key version>:<base64-encoded DER signature>`
+        let der = data["signature"]
+            .as_str()
+            .and_then(|signature| signature.rsplit(':').next())
+            .and_then(|signature| base64::decode(signature).ok())
+            .ok_or_else(|| CliError::SigningError("Vault returned an invalid signature".into()))?;
+
+        compact_signature(&der)
+            .map_err(|err| CliError::SigningError(format!("Invalid Vault signature: {}", err)))
+    }
+}
+
+impl Signer for VaultSigner {
+    fn algorithm_name(&self) -> &str {
+        "secp256k1"
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature, SigningError> {
+        let mut sha = Sha256::new();
+        sha.input(message);
+        let hash: &mut [u8] = &mut [0; 32];
+        sha.result(hash);
+
+        self.sign_hash(hash)
+            .map(Signature::new)
+            .map_err(|err| SigningError::Internal(err.to_string()))
+    }
+
+    fn public_key(&self) -> Result<PublicKey, SigningError> {
+        Ok(self.public_key.clone())
+    }
+
+    fn clone_box(&self) -> Box<dyn Signer> {
+        Box::new(self.clone())
+    }
+}
+
+/// Read a PEM-encoded secp256k1 public key and return it in compressed form
+fn read_public_key(pem: &str) -> Result<PublicKey, String> {
+    let key = PKey::public_key_from_pem(pem.as_bytes())
+        .and_then(|key| key.ec_key())
+        .map_err(|_| "the public key is not an EC key".to_string())?;
+    if key.group().curve_name() != Some(Nid::SECP256K1) {
+        return Err("the key is not on the secp256k1 curve".into());
+    }
+
+    let mut ctx = BigNumContext::new().map_err(|err| err.to_string())?;
+    let point = key
+        .public_key()
+        .to_bytes(key.group(), PointConversionForm::COMPRESSED, &mut ctx)
+        .map_err(|err| err.to_string())?;
+
+    Ok(PublicKey::new(point))
+}
+
+/// Convert a DER-encoded ECDSA signature to the 64-byte compact form Sawtooth verifies
+///
+/// Vault is not required to produce low-S signatures, which secp256k1 verifiers reject, so the
+/// signature is normalized.
+fn compact_signature(der: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    let signature = EcdsaSig::from_der(der)?;
+
+    let mut ctx = BigNumContext::new()?;
+    let mut order = BigNum::new()?;
+    EcGroup::from_curve_name(Nid::SECP256K1)?.order(&mut order, &mut ctx)?;
+    let mut half_order = BigNum::new()?;
+    half_order.rshift1(&order)?;
+
+    let mut compact = signature.r().to_vec_padded(32)?;
+    if signature.s() > &*half_order {
+        let mut s = BigNum::new()?;
+        s.checked_sub(&order, signature.s())?;
+        compact.extend(s.to_vec_padded(32)?);
+    } else {
+        compact.extend(signature.s().to_vec_padded(32)?);
+    }
+
+    Ok(compact)
+}
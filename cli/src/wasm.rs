@@ -0,0 +1,344 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains functions which inspect, strip and optimize compiled WebAssembly modules
+//!
+//! Only the section structure of a module is read, which is enough to find the imports and
+//! exports Sabre resolves when it executes a contract.
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::process::{self, Command};
+
+use crate::error::CliError;
+
+/// The first bytes of every WebAssembly module: the magic number and version 1
+const HEADER: &[u8] = b"\0asm\x01\0\0\0";
+
+const CUSTOM_SECTION: u8 = 0;
+const IMPORT_SECTION: u8 = 2;
+const EXPORT_SECTION: u8 = 7;
+const START_SECTION: u8 = 8;
+
+/// The name of the custom section in which the linker records the features a module uses
+const TARGET_FEATURES_SECTION: &str = "target_features";
+
+/// The kind of an imported or exported item
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Function,
+    Table,
+    Memory,
+    Global,
+}
+
+/// An item imported by a module
+pub struct Import {
+    pub module: String,
+    pub field: String,
+    pub kind: Kind,
+}
+
+/// The parts of a module which determine whether Sabre can execute it
+pub struct ModuleInfo {
+    pub imports: Vec<Import>,
+    pub exports: Vec<(String, Kind)>,
+    pub has_start: bool,
+    /// The WebAssembly features the module was compiled to use, e.g. "sign-ext"
+    pub features: Vec<String>,
+}
+
+/// Read the imports, exports and features of a module
+pub fn inspect(wasm: &[u8]) -> Result<ModuleInfo, CliError> {
+    let mut info = ModuleInfo {
+        imports: vec![],
+        exports: vec![],
+        has_start: false,
+        features: vec![],
+    };
+
+    for (id, mut section) in sections(wasm)? {
+        match id {
+            IMPORT_SECTION => {
+                for _ in 0..section.read_u32()? {
+                    let module = section.read_name()?;
+                    let field = section.read_name()?;
+                    let kind = section.read_kind()?;
+                    match kind {
+                        Kind::Function => {
+                            section.read_u32()?;
+                        }
+                        Kind::Table => {
+                            section.read_byte()?;
+                            section.read_limits()?;
+                        }
+                        Kind::Memory => section.read_limits()?,
+                        Kind::Global => {
+                            section.read_bytes(2)?;
+                        }
+                    }
+                    info.imports.push(Import {
+                        module,
+                        field,
+                        kind,
+                    });
+                }
+            }
+            EXPORT_SECTION => {
+                for _ in 0..section.read_u32()? {
+                    let name = section.read_name()?;
+                    let kind = section.read_kind()?;
+                    section.read_u32()?;
+                    info.exports.push((name, kind));
+                }
+            }
+            START_SECTION => info.has_start = true,
+            CUSTOM_SECTION => {
+                if section.read_name()? != TARGET_FEATURES_SECTION {
+                    continue;
+                }
+                for _ in 0..section.read_u32()? {
+                    let prefix = section.read_byte()?;
+                    let feature = section.read_name()?;
+                    if prefix != b'-' {
+                        info.features.push(feature);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(info)
+}
+
+/// Return the contents of the first custom section of a module with the given name, if any
+pub fn custom_section(wasm: &[u8], name: &str) -> Result<Option<Vec<u8>>, CliError> {
+    for (id, mut section) in sections(wasm)? {
+        if id == CUSTOM_SECTION && section.read_name()? == name {
+            return Ok(Some(section.bytes[section.position..].to_vec()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Append a custom section with the given name and contents to a module, replacing any custom
+/// sections of the same name
+pub fn set_custom_section(wasm: &[u8], name: &str, contents: &[u8]) -> Result<Vec<u8>, CliError> {
+    if !wasm.starts_with(HEADER) {
+        return Err(invalid("missing WebAssembly version 1 header"));
+    }
+
+    let mut module = HEADER.to_vec();
+    let mut reader = Reader::new(wasm);
+    reader.read_bytes(HEADER.len())?;
+
+    while !reader.is_empty() {
+        let start = reader.position;
+        let id = reader.read_byte()?;
+        let size = reader.read_u32()? as usize;
+        let mut section = Reader::new(reader.read_bytes(size)?);
+        if id != CUSTOM_SECTION || section.read_name()? != name {
+            module.extend_from_slice(&wasm[start..reader.position]);
+        }
+    }
+
+    let mut section = vec![];
+    write_u32(&mut section, name.len() as u32);
+    section.extend_from_slice(name.as_bytes());
+    section.extend_from_slice(contents);
+
+    module.push(CUSTOM_SECTION);
+    write_u32(&mut module, section.len() as u32);
+    module.extend_from_slice(&section);
+
+    Ok(module)
+}
+
+/// Remove the custom sections of a module, which hold debug information, symbol names and
+/// producer details that are not needed to execute it
+pub fn strip_custom_sections(wasm: &[u8]) -> Result<Vec<u8>, CliError> {
+    if !wasm.starts_with(HEADER) {
+        return Err(invalid("missing WebAssembly version 1 header"));
+    }
+
+    let mut stripped = HEADER.to_vec();
+    let mut reader = Reader::new(wasm);
+    reader.read_bytes(HEADER.len())?;
+
+    while !reader.is_empty() {
+        let start = reader.position;
+        let id = reader.read_byte()?;
+        let size = reader.read_u32()? as usize;
+        reader.read_bytes(size)?;
+        if id != CUSTOM_SECTION {
+            stripped.extend_from_slice(&wasm[start..reader.position]);
+        }
+    }
+
+    Ok(stripped)
+}
+
+/// Make a module smaller with wasm-opt or wasm-strip, whichever is installed first, falling back
+/// to stripping its custom sections
+///
+/// Returns the smaller module and the name of the tool that produced it.
+pub fn optimize(wasm: &[u8]) -> Result<(Vec<u8>, &'static str), CliError> {
+    let tools: &[(&str, &[&str])] = &[
+        ("wasm-opt", &["-Oz", "--strip-debug", "--strip-producers"]),
+        ("wasm-strip", &[]),
+    ];
+
+    let dir = env::temp_dir();
+    let input = dir.join(format!("sabre-{}-contract.wasm", process::id()));
+    let output = dir.join(format!("sabre-{}-optimized.wasm", process::id()));
+    fs::write(&input, wasm).map_err(|err| {
+        CliError::UserError(format!("Could not write \"{}\": {}", input.display(), err))
+    })?;
+
+    let mut optimized = None;
+    for (tool, args) in tools {
+        let result = Command::new(tool)
+            .args(*args)
+            .arg(&input)
+            .arg("-o")
+            .arg(&output)
+            .output();
+        match result {
+            Ok(result) if result.status.success() => {
+                if let Ok(bytes) = fs::read(&output) {
+                    optimized = Some((bytes, *tool));
+                    break;
+                }
+            }
+            Ok(result) => warn!(
+                "{} failed; trying the next optimizer: {}",
+                tool,
+                String::from_utf8_lossy(&result.stderr).trim()
+            ),
+            Err(err) if err.kind() == ErrorKind::NotFound => debug!("{} is not installed", tool),
+            Err(err) => warn!("Unable to run {}: {}", tool, err),
+        }
+    }
+
+    let _ = fs::remove_file(&input);
+    let _ = fs::remove_file(&output);
+
+    match optimized {
+        Some(optimized) => Ok(optimized),
+        None => Ok((strip_custom_sections(wasm)?, "the built-in stripper")),
+    }
+}
+
+/// Split a module into its sections, checking its header
+fn sections(wasm: &[u8]) -> Result<Vec<(u8, Reader<'_>)>, CliError> {
+    if !wasm.starts_with(HEADER) {
+        return Err(invalid("missing WebAssembly version 1 header"));
+    }
+
+    let mut reader = Reader::new(&wasm[HEADER.len()..]);
+    let mut sections = vec![];
+    while !reader.is_empty() {
+        let id = reader.read_byte()?;
+        let size = reader.read_u32()? as usize;
+        sections.push((id, Reader::new(reader.read_bytes(size)?)));
+    }
+
+    Ok(sections)
+}
+
+/// Write an unsigned LEB128 integer
+fn write_u32(bytes: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn invalid(reason: &str) -> CliError {
+    CliError::UserError(format!("Invalid WebAssembly module: {}", reason))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], CliError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + len)
+            .ok_or_else(|| invalid("unexpected end of module"))?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, CliError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    /// Read an unsigned LEB128 integer
+    fn read_u32(&mut self) -> Result<u32, CliError> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_byte()?;
+            value |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("integer is too long"))
+    }
+
+    fn read_name(&mut self) -> Result<String, CliError> {
+        let len = self.read_u32()? as usize;
+        String::from_utf8(self.read_bytes(len)?.to_vec())
+            .map_err(|_| invalid("name is not valid UTF-8"))
+    }
+
+    fn read_kind(&mut self) -> Result<Kind, CliError> {
+        match self.read_byte()? {
+            0 => Ok(Kind::Function),
+            1 => Ok(Kind::Table),
+            2 => Ok(Kind::Memory),
+            3 => Ok(Kind::Global),
+            kind => Err(invalid(&format!("unknown import or export kind {}", kind))),
+        }
+    }
+
+    fn read_limits(&mut self) -> Result<(), CliError> {
+        let flags = self.read_byte()?;
+        self.read_u32()?;
+        if flags & 1 != 0 {
+            self.read_u32()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains a minimal WebSocket client, used to subscribe to REST API events
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use rand::RngCore;
+
+use crate::error::CliError;
+
+/// Appended to the client's key to compute the accept key, as defined by RFC 6455
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// A WebSocket connection over plain TCP
+pub struct WebSocket {
+    stream: TcpStream,
+}
+
+impl WebSocket {
+    /// Open a WebSocket connection to the given `ws://` URL, sending the Authorization header
+    /// with the upgrade request if one is given
+    pub fn connect(
+        url: &str,
+        authorization: Option<&str>,
+        timeout: Duration,
+    ) -> Result<WebSocket, CliError> {
+        let uri = url
+            .parse::<hyper::Uri>()
+            .map_err(|err| CliError::UserError(format!("Invalid URL: {}: {}", err, url)))?;
+
+        if uri.scheme() != Some("ws") {
+            return Err(CliError::UserError(format!(
+                "Unsupported scheme in WebSocket URL: {}",
+                url
+            )));
+        }
+
+        let host = uri
+            .host()
+            .ok_or_else(|| CliError::UserError(format!("No host in URL: {}", url)))?;
+        let port = uri.port().unwrap_or(80);
+
+        let stream = TcpStream::connect((host, port))
+            .map_err(|err| CliError::ConnectionError(format!("{}: {}", url, err)))?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        let mut nonce = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let key = base64::encode(nonce);
+
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\n\
+             Host: {}:{}\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\n\
+             Sec-WebSocket-Version: 13\r\n",
+            uri.path(),
+            host,
+            port,
+            key
+        );
+        if let Some(authorization) = authorization {
+            request.push_str(&format!("Authorization: {}\r\n", authorization));
+        }
+        request.push_str("\r\n");
+
+        let mut socket = WebSocket { stream };
+        socket.stream.write_all(request.as_bytes())?;
+        socket.read_handshake_response(&key)?;
+
+        Ok(socket)
+    }
+
+    /// Send a text message
+    pub fn send_text(&mut self, message: &str) -> Result<(), CliError> {
+        self.send_frame(OPCODE_TEXT, message.as_bytes())
+    }
+
+    /// Wait up to `timeout` for the next text or binary message
+    ///
+    /// Returns `None` if no message arrived before the timeout. Pings are answered while waiting.
+    pub fn read_message(&mut self, timeout: Duration) -> Result<Option<Vec<u8>>, CliError> {
+        // A zero timeout would block forever
+        self.stream
+            .set_read_timeout(Some(timeout.max(Duration::from_millis(1))))?;
+
+        let mut message = Vec::new();
+        loop {
+            let (fin, opcode, payload) = match self.read_frame() {
+                Ok(frame) => frame,
+                Err(CliError::IoError(ref err))
+                    if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut =>
+                {
+                    return Ok(None)
+                }
+                Err(err) => return Err(err),
+            };
+
+            match opcode {
+                OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+                    message.extend(payload);
+                    if fin {
+                        return Ok(Some(message));
+                    }
+                }
+                OPCODE_PING => self.send_frame(OPCODE_PONG, &payload)?,
+                OPCODE_PONG => (),
+                OPCODE_CLOSE => {
+                    return Err(CliError::ConnectionError(
+                        "WebSocket closed by the server".into(),
+                    ))
+                }
+                _ => {
+                    return Err(CliError::ConnectionError(format!(
+                        "Unexpected WebSocket opcode {}",
+                        opcode
+                    )))
+                }
+            }
+        }
+    }
+
+    fn read_handshake_response(&mut self, key: &str) -> Result<(), CliError> {
+        let mut response = Vec::new();
+        let mut byte = [0u8; 1];
+        while !response.ends_with(b"\r\n\r\n") {
+            self.stream.read_exact(&mut byte)?;
+            response.push(byte[0]);
+        }
+        let response = String::from_utf8_lossy(&response);
+
+        let mut lines = response.lines();
+        let status_line = lines.next().unwrap_or("");
+        if status_line.split_whitespace().nth(1) != Some("101") {
+            return Err(CliError::ConnectionError(format!(
+                "WebSocket upgrade rejected: {}",
+                status_line
+            )));
+        }
+
+        let mut sha = Sha1::new();
+        sha.input_str(key);
+        sha.input_str(ACCEPT_GUID);
+        let mut digest = [0u8; 20];
+        sha.result(&mut digest);
+        let expected_accept = base64::encode(digest);
+
+        let accept = lines
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-accept"))
+            .map(|(_, value)| value.trim());
+
+        if accept != Some(expected_accept.as_str()) {
+            return Err(CliError::ConnectionError(
+                "WebSocket upgrade response has an invalid accept key".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), CliError> {
+        let mut frame = vec![0x80 | opcode];
+
+        // Frames sent by a client must be masked
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(0x80 | 126);
+                frame.extend(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend(&(len as u64).to_be_bytes());
+            }
+        }
+
+        let mut mask = [0u8; 4];
+        rand::thread_rng().fill_bytes(&mut mask);
+        frame.extend(&mask);
+        frame.extend(
+            payload
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ mask[i % 4]),
+        );
+
+        self.stream.write_all(&frame)?;
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Result<(bool, u8, Vec<u8>), CliError> {
+        let mut header = [0u8; 2];
+        self.stream.read_exact(&mut header)?;
+
+        let fin = header[0] & 0x80 != 0;
+        let opcode = header[0] & 0x0F;
+        let masked = header[1] & 0x80 != 0;
+
+        let len = match header[1] & 0x7F {
+            126 => {
+                let mut len = [0u8; 2];
+                self.stream.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0u8; 8];
+                self.stream.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+
+        let mut mask = [0u8; 4];
+        if masked {
+            self.stream.read_exact(&mut mask)?;
+        }
+
+        let mut payload = vec![0u8; len as usize];
+        self.stream.read_exact(&mut payload)?;
+        if masked {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+
+        Ok((fin, opcode, payload))
+    }
+}
@@ -20,6 +20,7 @@ extern "C" {
     pub fn set_state(addr_data: WasmPtrList) -> i32;
     pub fn delete_state(addresses: WasmPtrList) -> WasmPtrList;
     pub fn add_event(event_type: WasmPtr, attributes: WasmPtrList, data: WasmPtr) -> i32;
+    pub fn add_receipt_data(data: WasmPtr) -> i32;
     pub fn get_ptr_len(ptr: WasmPtr) -> isize;
     pub fn alloc(len: usize) -> WasmPtr;
     pub fn read_byte(offset: isize) -> u8;
@@ -36,6 +37,8 @@ extern "C" {
         public_key: WasmPtr,
         payload: WasmPtr,
     ) -> i32;
+    pub fn invoke_contract(name: WasmPtr, version: WasmPtr, payload: WasmPtr) -> i32;
+    pub fn set_error(code: i32, message: WasmPtr) -> i32;
     pub fn log_buffer(log_level: WasmPtr, log_string: WasmPtr);
     pub fn log_level() -> WasmPtr;
 }
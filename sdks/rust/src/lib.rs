@@ -21,6 +21,7 @@ pub mod protos;
 
 use std::collections::HashMap;
 use std::string::FromUtf8Error;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub use crate::externs::{WasmPtr, WasmPtrList};
 
@@ -197,6 +198,14 @@ pub trait TransactionContext {
         attributes: Vec<(String, String)>,
         data: &[u8],
     ) -> Result<(), WasmSdkError>;
+
+    /// add_receipt_data adds a blob to the execution result for this transaction, which clients
+    /// can retrieve from the transaction receipt once the transaction is committed.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - the data to add, which is opaque to the validator
+    fn add_receipt_data(&self, data: &[u8]) -> Result<(), WasmSdkError>;
 }
 
 #[derive(Default)]
@@ -230,7 +239,7 @@ impl TransactionContext for SabreTransactionContext {
                 WasmBuffer::from_list(externs::get_state(header_address_buffer.to_raw()))?;
             let mut result_vec = Vec::new();
 
-            if (result_vec.len() % 2) != 0 {
+            if (results.len() % 2) != 0 {
                 return Err(WasmSdkError::InvalidTransaction(
                     "Get state returned incorrect data fmt".into(),
                 ));
@@ -356,6 +365,20 @@ impl TransactionContext for SabreTransactionContext {
             Ok(())
         }
     }
+
+    fn add_receipt_data(&self, data: &[u8]) -> Result<(), WasmSdkError> {
+        unsafe {
+            let data_buffer = WasmBuffer::new(data)?;
+
+            if externs::add_receipt_data(data_buffer.to_raw()) != 0 {
+                return Err(WasmSdkError::InvalidTransaction(
+                    "Unable to add receipt data".into(),
+                ));
+            }
+
+            Ok(())
+        }
+    }
 }
 
 // Mimics the sawtooth sdk TransactionHandler
@@ -407,11 +430,57 @@ pub fn invoke_smart_permission(
     }
 }
 
+/// Execute another contract for the current transaction, with the same signer, returning the
+/// result of its entrypoint: 1 if the payload was applied, -3 if the contract returned
+/// InvalidTransaction and -4 if it returned InternalError
+///
+/// The contract must be permitted to read and write every input and output of the transaction.
+/// The state it changes is not restored if it fails, so a failed invocation should fail the
+/// transaction.
+pub fn invoke_contract(name: &str, version: &str, payload: &[u8]) -> Result<i32, WasmSdkError> {
+    unsafe {
+        let name_buffer = WasmBuffer::new(name.as_bytes())?;
+        let version_buffer = WasmBuffer::new(version.as_bytes())?;
+        let payload_buffer = WasmBuffer::new(payload)?;
+
+        Ok(externs::invoke_contract(
+            name_buffer.to_raw(),
+            version_buffer.to_raw(),
+            payload_buffer.to_raw(),
+        ))
+    }
+}
+
+/// Whether the contract has reported an error with `set_error` during the current execution
+static ERROR_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Report the code and message of the error the contract is about to return, which the
+/// transaction processor adds to the error of the transaction
+///
+/// The code is defined by the contract; 0 gives no code. An error reported again replaces the
+/// error reported before. `execute_entrypoint` reports the message of an error returned by
+/// `apply` with code 0, unless the contract reported an error itself.
+pub fn set_error(code: i32, message: &str) -> Result<(), WasmSdkError> {
+    unsafe {
+        let message_buffer = WasmBuffer::new(message.as_bytes())?;
+
+        if externs::set_error(code, message_buffer.to_raw()) != 0 {
+            return Err(WasmSdkError::InternalError("Unable to set error".into()));
+        }
+    }
+    ERROR_REPORTED.store(true, Ordering::SeqCst);
+
+    Ok(())
+}
+
 /// -1: Failed to deserialize payload
 /// -2: Failed to deserialize signer
 /// -3: apply returned InvalidTransaction
 /// -4: apply returned InternalError
 ///
+/// The message of an error returned by `apply` is reported with `set_error`, unless the contract
+/// reported an error itself.
+///
 /// # Safety
 ///
 /// This function is unsafe due to the call to WasmBuffer::from_raw which converts a WasmPtr
@@ -450,10 +519,22 @@ where
     };
 
     let mut header = Header::new(signer);
-    match apply(
+    ERROR_REPORTED.store(false, Ordering::SeqCst);
+    let result = apply(
         &TpProcessRequest::new(payload, &mut header, signature),
         &mut SabreTransactionContext::new(),
-    ) {
+    );
+
+    if let Err(ApplyError::InvalidTransaction(message) | ApplyError::InternalError(message)) =
+        &result
+    {
+        if !ERROR_REPORTED.load(Ordering::SeqCst) {
+            // The result is returned whether or not the error could be reported
+            let _ = set_error(0, message);
+        }
+    }
+
+    match result {
         Ok(r) => {
             if r {
                 1
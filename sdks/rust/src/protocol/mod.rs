@@ -26,10 +26,38 @@ pub const ADMINISTRATORS_SETTING_KEY: &str = "sawtooth.swa.administrators";
 
 pub const ADMINISTRATORS_SETTING_ADDRESS: &str =
     "000000a87cb5eafdcca6a814e4add97c4b517d3c530c2f44b31d18e3b0c44298fc1c14";
+
+pub const FLOAT_POLICY_SETTING_KEY: &str = "sawtooth.swa.float_policy";
+
+pub const FLOAT_POLICY_SETTING_ADDRESS: &str =
+    "000000a87cb5eafdcca6a814e4add97c4b517d1f420189f9a07b95e3b0c44298fc1c14";
+
+pub const MAX_CONTRACT_SIZE_SETTING_KEY: &str = "sawtooth.swa.max_contract_size";
+
+pub const MAX_CONTRACT_SIZE_SETTING_ADDRESS: &str =
+    "000000a87cb5eafdcca6a814e4add97c4b517dacf67ee4f6606477e3b0c44298fc1c14";
+
+pub const AUDIT_SETTING_KEY: &str = "sawtooth.swa.audit";
+
+pub const AUDIT_SETTING_ADDRESS: &str =
+    "000000a87cb5eafdcca6a814e4add97c4b517db81f37a043a6f767e3b0c44298fc1c14";
+
+/// The version of WebAssembly contracts may use: 1, the default, for WebAssembly 1.0, or 2 for
+/// WebAssembly 1.0 with the bulk memory, sign extension and reference types proposals
+pub const WASM_PROTOCOL_VERSION_SETTING_KEY: &str = "sawtooth.swa.wasm_protocol_version";
+
+pub const WASM_PROTOCOL_VERSION_SETTING_ADDRESS: &str =
+    "000000a87cb5eafdcca6a814e4add97c4b517d313276805f802549e3b0c44298fc1c14";
+
+pub const BLOCK_INFO_CONFIG_ADDRESS: &str =
+    "00b10c0100000000000000000000000000000000000000000000000000000000000000";
+pub const BLOCK_INFO_ADDRESS_PREFIX: &str = "00b10c00";
 pub const NAMESPACE_REGISTRY_ADDRESS_PREFIX: &str = "00ec00";
 pub const CONTRACT_REGISTRY_ADDRESS_PREFIX: &str = "00ec01";
 pub const CONTRACT_ADDRESS_PREFIX: &str = "00ec02";
 pub const SMART_PERMISSION_ADDRESS_PREFIX: &str = "00ec03";
+pub const CONTRACT_CHUNK_ADDRESS_PREFIX: &str = "00ec04";
+pub const AUDIT_LOG_ADDRESS_PREFIX: &str = "00ec05";
 pub const AGENT_ADDRESS_PREFIX: &str = "cad11d00";
 pub const ORG_ADDRESS_PREFIX: &str = "cad11d01";
 
@@ -37,10 +65,33 @@ pub const ADMINISTRATORS_SETTING_ADDRESS_BYTES: &[u8] = &[
     0, 0, 0, 168, 124, 181, 234, 253, 204, 166, 168, 20, 228, 173, 217, 124, 75, 81, 125, 60, 83,
     12, 47, 68, 179, 29, 24, 227, 176, 196, 66, 152, 252, 28, 20,
 ];
+pub const FLOAT_POLICY_SETTING_ADDRESS_BYTES: &[u8] = &[
+    0, 0, 0, 168, 124, 181, 234, 253, 204, 166, 168, 20, 228, 173, 217, 124, 75, 81, 125, 31, 66,
+    1, 137, 249, 160, 123, 149, 227, 176, 196, 66, 152, 252, 28, 20,
+];
+pub const MAX_CONTRACT_SIZE_SETTING_ADDRESS_BYTES: &[u8] = &[
+    0, 0, 0, 168, 124, 181, 234, 253, 204, 166, 168, 20, 228, 173, 217, 124, 75, 81, 125, 172, 246,
+    126, 228, 246, 96, 100, 119, 227, 176, 196, 66, 152, 252, 28, 20,
+];
+pub const AUDIT_SETTING_ADDRESS_BYTES: &[u8] = &[
+    0, 0, 0, 168, 124, 181, 234, 253, 204, 166, 168, 20, 228, 173, 217, 124, 75, 81, 125, 184, 31,
+    55, 160, 67, 166, 247, 103, 227, 176, 196, 66, 152, 252, 28, 20,
+];
+pub const WASM_PROTOCOL_VERSION_SETTING_ADDRESS_BYTES: &[u8] = &[
+    0, 0, 0, 168, 124, 181, 234, 253, 204, 166, 168, 20, 228, 173, 217, 124, 75, 81, 125, 49, 50,
+    118, 128, 95, 128, 37, 73, 227, 176, 196, 66, 152, 252, 28, 20,
+];
+pub const BLOCK_INFO_CONFIG_ADDRESS_BYTES: &[u8] = &[
+    0, 177, 12, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0,
+];
+pub const BLOCK_INFO_ADDRESS_PREFIX_BYTES: &[u8] = &[0, 177, 12, 0];
 pub const NAMESPACE_REGISTRY_ADDRESS_PREFIX_BYTES: &[u8] = &[0, 236, 0];
 pub const CONTRACT_REGISTRY_ADDRESS_PREFIX_BYTES: &[u8] = &[0, 236, 1];
 pub const CONTRACT_ADDRESS_PREFIX_BYTES: &[u8] = &[0, 236, 2];
 pub const SMART_PERMISSION_ADDRESS_PREFIX_BYTES: &[u8] = &[0, 236, 3];
+pub const CONTRACT_CHUNK_ADDRESS_PREFIX_BYTES: &[u8] = &[0, 236, 4];
+pub const AUDIT_LOG_ADDRESS_PREFIX_BYTES: &[u8] = &[0, 236, 5];
 pub const AGENT_ADDRESS_PREFIX_BYTES: &[u8] = &[202, 209, 29, 0];
 pub const ORG_ADDRESS_PREFIX_BYTES: &[u8] = &[202, 209, 29, 1];
 
@@ -85,6 +136,40 @@ pub fn compute_contract_address(name: &str, version: &str) -> Result<Vec<u8>, Ad
     Ok([CONTRACT_ADDRESS_PREFIX_BYTES, &hash[..32]].concat())
 }
 
+/// Compute a state address for a chunk of a contract.
+///
+/// # Arguments
+///
+/// * `name` - the name of the contract
+/// * `version` - the version of the contract
+/// * `index` - the position of the chunk in the contract
+pub fn compute_contract_chunk_address(
+    name: &str,
+    version: &str,
+    index: u32,
+) -> Result<Vec<u8>, AddressingError> {
+    let s = format!("{},{},{}", name, version, index);
+    let hash = sha512_hash(s.as_bytes());
+    Ok([CONTRACT_CHUNK_ADDRESS_PREFIX_BYTES, &hash[..32]].concat())
+}
+
+/// Compute the state address of the audit log of a namespace registry or contract registry.
+///
+/// # Arguments
+///
+/// * `registry_address` - the state address of the namespace registry or contract registry
+pub fn compute_audit_log_address(registry_address: &[u8]) -> Result<Vec<u8>, AddressingError> {
+    let is_registry = registry_address.len() == 35
+        && (registry_address.starts_with(NAMESPACE_REGISTRY_ADDRESS_PREFIX_BYTES)
+            || registry_address.starts_with(CONTRACT_REGISTRY_ADDRESS_PREFIX_BYTES));
+    if !is_registry {
+        return Err(AddressingError::InvalidInput(
+            "address is not that of a namespace registry or contract registry".to_string(),
+        ));
+    }
+    Ok([AUDIT_LOG_ADDRESS_PREFIX_BYTES, &registry_address[2..34]].concat())
+}
+
 /// Compute a state address for a given smart permission.
 ///
 /// # Arguments
@@ -28,9 +28,13 @@ use crate::protos::{
 use super::AddressingError;
 #[cfg(not(target_arch = "wasm32"))]
 use super::{
-    compute_agent_address, compute_contract_address, compute_contract_registry_address,
+    compute_agent_address, compute_audit_log_address, compute_contract_address,
+    compute_contract_chunk_address, compute_contract_registry_address,
     compute_namespace_registry_address, compute_org_address, compute_smart_permission_address,
-    ADMINISTRATORS_SETTING_ADDRESS_BYTES, SABRE_PROTOCOL_VERSION,
+    ADMINISTRATORS_SETTING_ADDRESS_BYTES, AUDIT_SETTING_ADDRESS_BYTES,
+    BLOCK_INFO_ADDRESS_PREFIX_BYTES, BLOCK_INFO_CONFIG_ADDRESS_BYTES, CONTRACT_ADDRESS_PREFIX,
+    FLOAT_POLICY_SETTING_ADDRESS_BYTES, MAX_CONTRACT_SIZE_SETTING_ADDRESS_BYTES,
+    SABRE_PROTOCOL_VERSION, WASM_PROTOCOL_VERSION_SETTING_ADDRESS_BYTES,
 };
 
 /// Native implementation for SabrePayload_Action
@@ -50,6 +54,10 @@ pub enum Action {
     CreateSmartPermission(CreateSmartPermissionAction),
     UpdateSmartPermission(UpdateSmartPermissionAction),
     DeleteSmartPermission(DeleteSmartPermissionAction),
+    CreateContractChunk(CreateContractChunkAction),
+    CommitContract(CommitContractAction),
+    PauseContract(PauseContractAction),
+    ResumeContract(ResumeContractAction),
 }
 
 impl std::fmt::Display for Action {
@@ -77,6 +85,10 @@ impl std::fmt::Display for Action {
             Action::CreateSmartPermission(_) => write!(f, "Create smart permission"),
             Action::UpdateSmartPermission(_) => write!(f, "Update smart permission"),
             Action::DeleteSmartPermission(_) => write!(f, "Delete smart permission"),
+            Action::CreateContractChunk(_) => write!(f, "Action: Create Contract Chunk"),
+            Action::CommitContract(_) => write!(f, "Action: Commit Contract"),
+            Action::PauseContract(_) => write!(f, "Action: Pause Contract"),
+            Action::ResumeContract(_) => write!(f, "Action: Resume Contract"),
         }
     }
 }
@@ -165,9 +177,34 @@ impl From<DeleteSmartPermissionAction> for Action {
     }
 }
 
+impl From<CreateContractChunkAction> for Action {
+    fn from(action: CreateContractChunkAction) -> Self {
+        Action::CreateContractChunk(action)
+    }
+}
+
+impl From<CommitContractAction> for Action {
+    fn from(action: CommitContractAction) -> Self {
+        Action::CommitContract(action)
+    }
+}
+
+impl From<PauseContractAction> for Action {
+    fn from(action: PauseContractAction) -> Self {
+        Action::PauseContract(action)
+    }
+}
+
+impl From<ResumeContractAction> for Action {
+    fn from(action: ResumeContractAction) -> Self {
+        Action::ResumeContract(action)
+    }
+}
+
 #[derive(Debug)]
 pub enum ActionBuildError {
     MissingField(String),
+    InvalidField(String),
 }
 
 impl StdError for ActionBuildError {}
@@ -176,6 +213,7 @@ impl std::fmt::Display for ActionBuildError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match *self {
             Self::MissingField(ref s) => write!(f, "missing field: {}", s),
+            Self::InvalidField(ref s) => write!(f, "invalid field: {}", s),
         }
     }
 }
@@ -311,7 +349,574 @@ impl CreateContractActionBuilder {
         self
     }
 
-    pub fn build(self) -> Result<CreateContractAction, ActionBuildError> {
+    pub fn build(self) -> Result<CreateContractAction, ActionBuildError> {
+        let name = self.name.ok_or_else(|| {
+            ActionBuildError::MissingField("'name' field is required".to_string())
+        })?;
+
+        let version = self.version.ok_or_else(|| {
+            ActionBuildError::MissingField("'version' field is required".to_string())
+        })?;
+
+        let inputs = self.inputs;
+        let outputs = self.outputs;
+
+        let contract = {
+            if self.contract.is_empty() {
+                return Err(ActionBuildError::MissingField(
+                    "'contract' field is required".to_string(),
+                ));
+            } else {
+                self.contract
+            }
+        };
+
+        Ok(CreateContractAction {
+            name,
+            version,
+            inputs,
+            outputs,
+            contract,
+        })
+    }
+
+    pub fn into_payload_builder(self) -> Result<SabrePayloadBuilder, ActionBuildError> {
+        self.build()
+            .map(|action| SabrePayloadBuilder::new().with_action(Action::from(action)))
+    }
+}
+
+/// Native implementation for CreateContractChunkAction
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct CreateContractChunkAction {
+    name: String,
+    version: String,
+    index: u32,
+    chunk: Vec<u8>,
+}
+
+impl CreateContractChunkAction {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn chunk(&self) -> &[u8] {
+        &self.chunk
+    }
+}
+
+impl FromProto<protos::payload::CreateContractChunkAction> for CreateContractChunkAction {
+    fn from_proto(
+        proto: protos::payload::CreateContractChunkAction,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(CreateContractChunkAction {
+            name: proto.get_name().to_string(),
+            version: proto.get_version().to_string(),
+            index: proto.get_index(),
+            chunk: proto.get_chunk().to_vec(),
+        })
+    }
+}
+
+impl FromNative<CreateContractChunkAction> for protos::payload::CreateContractChunkAction {
+    fn from_native(
+        create_contract_chunk_action: CreateContractChunkAction,
+    ) -> Result<Self, ProtoConversionError> {
+        let mut proto = protos::payload::CreateContractChunkAction::new();
+        proto.set_name(create_contract_chunk_action.name().to_string());
+        proto.set_version(create_contract_chunk_action.version().to_string());
+        proto.set_index(create_contract_chunk_action.index());
+        proto.set_chunk(create_contract_chunk_action.chunk().to_vec());
+        Ok(proto)
+    }
+}
+
+impl FromBytes<CreateContractChunkAction> for CreateContractChunkAction {
+    fn from_bytes(bytes: &[u8]) -> Result<CreateContractChunkAction, ProtoConversionError> {
+        let proto: protos::payload::CreateContractChunkAction = Message::parse_from_bytes(bytes)
+            .map_err(|_| {
+                ProtoConversionError::SerializationError(
+                    "Unable to get CreateContractChunkAction from bytes".to_string(),
+                )
+            })?;
+        proto.into_native()
+    }
+}
+
+impl IntoBytes for CreateContractChunkAction {
+    fn into_bytes(self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto = self.into_proto()?;
+        let bytes = proto.write_to_bytes().map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get bytes from CreateContractChunkAction".to_string(),
+            )
+        })?;
+        Ok(bytes)
+    }
+}
+
+impl IntoProto<protos::payload::CreateContractChunkAction> for CreateContractChunkAction {}
+impl IntoNative<CreateContractChunkAction> for protos::payload::CreateContractChunkAction {}
+
+/// Builder used to create a CreateContractChunkAction
+#[derive(Default, Clone)]
+pub struct CreateContractChunkActionBuilder {
+    name: Option<String>,
+    version: Option<String>,
+    index: Option<u32>,
+    chunk: Vec<u8>,
+}
+
+impl CreateContractChunkActionBuilder {
+    pub fn new() -> Self {
+        CreateContractChunkActionBuilder::default()
+    }
+
+    pub fn with_name(mut self, name: String) -> CreateContractChunkActionBuilder {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn with_version(mut self, version: String) -> CreateContractChunkActionBuilder {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn with_index(mut self, index: u32) -> CreateContractChunkActionBuilder {
+        self.index = Some(index);
+        self
+    }
+
+    pub fn with_chunk(mut self, chunk: Vec<u8>) -> CreateContractChunkActionBuilder {
+        self.chunk = chunk;
+        self
+    }
+
+    pub fn build(self) -> Result<CreateContractChunkAction, ActionBuildError> {
+        let name = self.name.ok_or_else(|| {
+            ActionBuildError::MissingField("'name' field is required".to_string())
+        })?;
+
+        let version = self.version.ok_or_else(|| {
+            ActionBuildError::MissingField("'version' field is required".to_string())
+        })?;
+
+        let index = self.index.ok_or_else(|| {
+            ActionBuildError::MissingField("'index' field is required".to_string())
+        })?;
+
+        let chunk = {
+            if self.chunk.is_empty() {
+                return Err(ActionBuildError::MissingField(
+                    "'chunk' field is required".to_string(),
+                ));
+            } else {
+                self.chunk
+            }
+        };
+
+        Ok(CreateContractChunkAction {
+            name,
+            version,
+            index,
+            chunk,
+        })
+    }
+
+    pub fn into_payload_builder(self) -> Result<SabrePayloadBuilder, ActionBuildError> {
+        self.build()
+            .map(|action| SabrePayloadBuilder::new().with_action(Action::from(action)))
+    }
+}
+
+/// Native implementation for CommitContractAction
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct CommitContractAction {
+    name: String,
+    version: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    chunk_count: u32,
+    contract_sha512: String,
+}
+
+impl CommitContractAction {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[String] {
+        &self.outputs
+    }
+
+    pub fn chunk_count(&self) -> u32 {
+        self.chunk_count
+    }
+
+    pub fn contract_sha512(&self) -> &str {
+        &self.contract_sha512
+    }
+}
+
+impl FromProto<protos::payload::CommitContractAction> for CommitContractAction {
+    fn from_proto(
+        proto: protos::payload::CommitContractAction,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(CommitContractAction {
+            name: proto.get_name().to_string(),
+            version: proto.get_version().to_string(),
+            inputs: proto.get_inputs().to_vec(),
+            outputs: proto.get_outputs().to_vec(),
+            chunk_count: proto.get_chunk_count(),
+            contract_sha512: proto.get_contract_sha512().to_string(),
+        })
+    }
+}
+
+impl FromNative<CommitContractAction> for protos::payload::CommitContractAction {
+    fn from_native(
+        commit_contract_action: CommitContractAction,
+    ) -> Result<Self, ProtoConversionError> {
+        let mut proto = protos::payload::CommitContractAction::new();
+        proto.set_name(commit_contract_action.name().to_string());
+        proto.set_version(commit_contract_action.version().to_string());
+        proto.set_inputs(RepeatedField::from_vec(
+            commit_contract_action.inputs().to_vec(),
+        ));
+        proto.set_outputs(RepeatedField::from_vec(
+            commit_contract_action.outputs().to_vec(),
+        ));
+        proto.set_chunk_count(commit_contract_action.chunk_count());
+        proto.set_contract_sha512(commit_contract_action.contract_sha512().to_string());
+        Ok(proto)
+    }
+}
+
+impl FromBytes<CommitContractAction> for CommitContractAction {
+    fn from_bytes(bytes: &[u8]) -> Result<CommitContractAction, ProtoConversionError> {
+        let proto: protos::payload::CommitContractAction = Message::parse_from_bytes(bytes)
+            .map_err(|_| {
+                ProtoConversionError::SerializationError(
+                    "Unable to get CommitContractAction from bytes".to_string(),
+                )
+            })?;
+        proto.into_native()
+    }
+}
+
+impl IntoBytes for CommitContractAction {
+    fn into_bytes(self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto = self.into_proto()?;
+        let bytes = proto.write_to_bytes().map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get bytes from CommitContractAction".to_string(),
+            )
+        })?;
+        Ok(bytes)
+    }
+}
+
+impl IntoProto<protos::payload::CommitContractAction> for CommitContractAction {}
+impl IntoNative<CommitContractAction> for protos::payload::CommitContractAction {}
+
+/// Builder used to create a CommitContractAction
+#[derive(Default, Clone)]
+pub struct CommitContractActionBuilder {
+    name: Option<String>,
+    version: Option<String>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    chunk_count: Option<u32>,
+    contract_sha512: Option<String>,
+}
+
+impl CommitContractActionBuilder {
+    pub fn new() -> Self {
+        CommitContractActionBuilder::default()
+    }
+
+    pub fn with_name(mut self, name: String) -> CommitContractActionBuilder {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn with_version(mut self, version: String) -> CommitContractActionBuilder {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn with_inputs(mut self, inputs: Vec<String>) -> CommitContractActionBuilder {
+        self.inputs = inputs;
+        self
+    }
+
+    pub fn with_outputs(mut self, outputs: Vec<String>) -> CommitContractActionBuilder {
+        self.outputs = outputs;
+        self
+    }
+
+    pub fn with_chunk_count(mut self, chunk_count: u32) -> CommitContractActionBuilder {
+        self.chunk_count = Some(chunk_count);
+        self
+    }
+
+    pub fn with_contract_sha512(mut self, contract_sha512: String) -> CommitContractActionBuilder {
+        self.contract_sha512 = Some(contract_sha512);
+        self
+    }
+
+    pub fn build(self) -> Result<CommitContractAction, ActionBuildError> {
+        let name = self.name.ok_or_else(|| {
+            ActionBuildError::MissingField("'name' field is required".to_string())
+        })?;
+
+        let version = self.version.ok_or_else(|| {
+            ActionBuildError::MissingField("'version' field is required".to_string())
+        })?;
+
+        let inputs = self.inputs;
+        let outputs = self.outputs;
+
+        let chunk_count = match self.chunk_count {
+            Some(chunk_count) if chunk_count > 0 => chunk_count,
+            _ => {
+                return Err(ActionBuildError::MissingField(
+                    "'chunk_count' field is required".to_string(),
+                ))
+            }
+        };
+
+        let contract_sha512 = self.contract_sha512.ok_or_else(|| {
+            ActionBuildError::MissingField("'contract_sha512' field is required".to_string())
+        })?;
+
+        Ok(CommitContractAction {
+            name,
+            version,
+            inputs,
+            outputs,
+            chunk_count,
+            contract_sha512,
+        })
+    }
+
+    pub fn into_payload_builder(self) -> Result<SabrePayloadBuilder, ActionBuildError> {
+        self.build()
+            .map(|action| SabrePayloadBuilder::new().with_action(Action::from(action)))
+    }
+}
+
+/// Native implementation for DeleteContractAction
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct DeleteContractAction {
+    name: String,
+    version: String,
+}
+
+impl DeleteContractAction {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+}
+
+impl FromProto<protos::payload::DeleteContractAction> for DeleteContractAction {
+    fn from_proto(
+        proto: protos::payload::DeleteContractAction,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(DeleteContractAction {
+            name: proto.get_name().to_string(),
+            version: proto.get_version().to_string(),
+        })
+    }
+}
+
+impl FromNative<DeleteContractAction> for protos::payload::DeleteContractAction {
+    fn from_native(
+        delete_contract_action: DeleteContractAction,
+    ) -> Result<Self, ProtoConversionError> {
+        let mut proto = protos::payload::DeleteContractAction::new();
+        proto.set_name(delete_contract_action.name().to_string());
+        proto.set_version(delete_contract_action.version().to_string());
+        Ok(proto)
+    }
+}
+
+impl FromBytes<DeleteContractAction> for DeleteContractAction {
+    fn from_bytes(bytes: &[u8]) -> Result<DeleteContractAction, ProtoConversionError> {
+        let proto: protos::payload::DeleteContractAction = Message::parse_from_bytes(bytes)
+            .map_err(|_| {
+                ProtoConversionError::SerializationError(
+                    "Unable to get DeleteContractAction from bytes".to_string(),
+                )
+            })?;
+        proto.into_native()
+    }
+}
+
+impl IntoBytes for DeleteContractAction {
+    fn into_bytes(self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto = self.into_proto()?;
+        let bytes = proto.write_to_bytes().map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get bytes from DeleteContractAction".to_string(),
+            )
+        })?;
+        Ok(bytes)
+    }
+}
+
+impl IntoProto<protos::payload::DeleteContractAction> for DeleteContractAction {}
+impl IntoNative<DeleteContractAction> for protos::payload::DeleteContractAction {}
+
+/// Builder used to create a DeleteContractAction
+#[derive(Default, Clone)]
+pub struct DeleteContractActionBuilder {
+    name: Option<String>,
+    version: Option<String>,
+}
+
+impl DeleteContractActionBuilder {
+    pub fn new() -> Self {
+        DeleteContractActionBuilder::default()
+    }
+
+    pub fn with_name(mut self, name: String) -> DeleteContractActionBuilder {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn with_version(mut self, version: String) -> DeleteContractActionBuilder {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn build(self) -> Result<DeleteContractAction, ActionBuildError> {
+        let name = self.name.ok_or_else(|| {
+            ActionBuildError::MissingField("'name' field is required".to_string())
+        })?;
+
+        let version = self.version.ok_or_else(|| {
+            ActionBuildError::MissingField("'version' field is required".to_string())
+        })?;
+
+        Ok(DeleteContractAction { name, version })
+    }
+
+    pub fn into_payload_builder(self) -> Result<SabrePayloadBuilder, ActionBuildError> {
+        self.build()
+            .map(|action| SabrePayloadBuilder::new().with_action(Action::from(action)))
+    }
+}
+
+/// Native implementation for PauseContractAction
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct PauseContractAction {
+    name: String,
+    version: String,
+}
+
+impl PauseContractAction {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+}
+
+impl FromProto<protos::payload::PauseContractAction> for PauseContractAction {
+    fn from_proto(
+        proto: protos::payload::PauseContractAction,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(PauseContractAction {
+            name: proto.get_name().to_string(),
+            version: proto.get_version().to_string(),
+        })
+    }
+}
+
+impl FromNative<PauseContractAction> for protos::payload::PauseContractAction {
+    fn from_native(
+        pause_contract_action: PauseContractAction,
+    ) -> Result<Self, ProtoConversionError> {
+        let mut proto = protos::payload::PauseContractAction::new();
+        proto.set_name(pause_contract_action.name().to_string());
+        proto.set_version(pause_contract_action.version().to_string());
+        Ok(proto)
+    }
+}
+
+impl FromBytes<PauseContractAction> for PauseContractAction {
+    fn from_bytes(bytes: &[u8]) -> Result<PauseContractAction, ProtoConversionError> {
+        let proto: protos::payload::PauseContractAction = Message::parse_from_bytes(bytes)
+            .map_err(|_| {
+                ProtoConversionError::SerializationError(
+                    "Unable to get PauseContractAction from bytes".to_string(),
+                )
+            })?;
+        proto.into_native()
+    }
+}
+
+impl IntoBytes for PauseContractAction {
+    fn into_bytes(self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto = self.into_proto()?;
+        let bytes = proto.write_to_bytes().map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get bytes from PauseContractAction".to_string(),
+            )
+        })?;
+        Ok(bytes)
+    }
+}
+
+impl IntoProto<protos::payload::PauseContractAction> for PauseContractAction {}
+impl IntoNative<PauseContractAction> for protos::payload::PauseContractAction {}
+
+/// Builder used to create a PauseContractAction
+#[derive(Default, Clone)]
+pub struct PauseContractActionBuilder {
+    name: Option<String>,
+    version: Option<String>,
+}
+
+impl PauseContractActionBuilder {
+    pub fn new() -> Self {
+        PauseContractActionBuilder::default()
+    }
+
+    pub fn with_name(mut self, name: String) -> PauseContractActionBuilder {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn with_version(mut self, version: String) -> PauseContractActionBuilder {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn build(self) -> Result<PauseContractAction, ActionBuildError> {
         let name = self.name.ok_or_else(|| {
             ActionBuildError::MissingField("'name' field is required".to_string())
         })?;
@@ -320,26 +925,7 @@ impl CreateContractActionBuilder {
             ActionBuildError::MissingField("'version' field is required".to_string())
         })?;
 
-        let inputs = self.inputs;
-        let outputs = self.outputs;
-
-        let contract = {
-            if self.contract.is_empty() {
-                return Err(ActionBuildError::MissingField(
-                    "'contract' field is required".to_string(),
-                ));
-            } else {
-                self.contract
-            }
-        };
-
-        Ok(CreateContractAction {
-            name,
-            version,
-            inputs,
-            outputs,
-            contract,
-        })
+        Ok(PauseContractAction { name, version })
     }
 
     pub fn into_payload_builder(self) -> Result<SabrePayloadBuilder, ActionBuildError> {
@@ -348,14 +934,14 @@ impl CreateContractActionBuilder {
     }
 }
 
-/// Native implementation for DeleteContractAction
+/// Native implementation for ResumeContractAction
 #[derive(Default, Debug, Clone, PartialEq)]
-pub struct DeleteContractAction {
+pub struct ResumeContractAction {
     name: String,
     version: String,
 }
 
-impl DeleteContractAction {
+impl ResumeContractAction {
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -365,78 +951,78 @@ impl DeleteContractAction {
     }
 }
 
-impl FromProto<protos::payload::DeleteContractAction> for DeleteContractAction {
+impl FromProto<protos::payload::ResumeContractAction> for ResumeContractAction {
     fn from_proto(
-        proto: protos::payload::DeleteContractAction,
+        proto: protos::payload::ResumeContractAction,
     ) -> Result<Self, ProtoConversionError> {
-        Ok(DeleteContractAction {
+        Ok(ResumeContractAction {
             name: proto.get_name().to_string(),
             version: proto.get_version().to_string(),
         })
     }
 }
 
-impl FromNative<DeleteContractAction> for protos::payload::DeleteContractAction {
+impl FromNative<ResumeContractAction> for protos::payload::ResumeContractAction {
     fn from_native(
-        delete_contract_action: DeleteContractAction,
+        resume_contract_action: ResumeContractAction,
     ) -> Result<Self, ProtoConversionError> {
-        let mut proto = protos::payload::DeleteContractAction::new();
-        proto.set_name(delete_contract_action.name().to_string());
-        proto.set_version(delete_contract_action.version().to_string());
+        let mut proto = protos::payload::ResumeContractAction::new();
+        proto.set_name(resume_contract_action.name().to_string());
+        proto.set_version(resume_contract_action.version().to_string());
         Ok(proto)
     }
 }
 
-impl FromBytes<DeleteContractAction> for DeleteContractAction {
-    fn from_bytes(bytes: &[u8]) -> Result<DeleteContractAction, ProtoConversionError> {
-        let proto: protos::payload::DeleteContractAction = Message::parse_from_bytes(bytes)
+impl FromBytes<ResumeContractAction> for ResumeContractAction {
+    fn from_bytes(bytes: &[u8]) -> Result<ResumeContractAction, ProtoConversionError> {
+        let proto: protos::payload::ResumeContractAction = Message::parse_from_bytes(bytes)
             .map_err(|_| {
                 ProtoConversionError::SerializationError(
-                    "Unable to get DeleteContractAction from bytes".to_string(),
+                    "Unable to get ResumeContractAction from bytes".to_string(),
                 )
             })?;
         proto.into_native()
     }
 }
 
-impl IntoBytes for DeleteContractAction {
+impl IntoBytes for ResumeContractAction {
     fn into_bytes(self) -> Result<Vec<u8>, ProtoConversionError> {
         let proto = self.into_proto()?;
         let bytes = proto.write_to_bytes().map_err(|_| {
             ProtoConversionError::SerializationError(
-                "Unable to get bytes from DeleteContractAction".to_string(),
+                "Unable to get bytes from ResumeContractAction".to_string(),
             )
         })?;
         Ok(bytes)
     }
 }
 
-impl IntoProto<protos::payload::DeleteContractAction> for DeleteContractAction {}
-impl IntoNative<DeleteContractAction> for protos::payload::DeleteContractAction {}
+impl IntoProto<protos::payload::ResumeContractAction> for ResumeContractAction {}
+impl IntoNative<ResumeContractAction> for protos::payload::ResumeContractAction {}
 
-/// Builder used to create a DeleteContractAction
+/// Builder used to create a ResumeContractAction
 #[derive(Default, Clone)]
-pub struct DeleteContractActionBuilder {
+pub struct ResumeContractActionBuilder {
     name: Option<String>,
     version: Option<String>,
 }
 
-impl DeleteContractActionBuilder {
+impl ResumeContractActionBuilder {
     pub fn new() -> Self {
-        DeleteContractActionBuilder::default()
+        ResumeContractActionBuilder::default()
     }
 
-    pub fn with_name(mut self, name: String) -> DeleteContractActionBuilder {
+    pub fn with_name(mut self, name: String) -> ResumeContractActionBuilder {
         self.name = Some(name);
         self
     }
 
-    pub fn with_version(mut self, version: String) -> DeleteContractActionBuilder {
+    pub fn with_version(mut self, version: String) -> ResumeContractActionBuilder {
         self.version = Some(version);
         self
     }
 
-    pub fn build(self) -> Result<DeleteContractAction, ActionBuildError> {
+    pub fn build(self) -> Result<ResumeContractAction, ActionBuildError> {
         let name = self.name.ok_or_else(|| {
             ActionBuildError::MissingField("'name' field is required".to_string())
         })?;
@@ -445,7 +1031,7 @@ impl DeleteContractActionBuilder {
             ActionBuildError::MissingField("'version' field is required".to_string())
         })?;
 
-        Ok(DeleteContractAction { name, version })
+        Ok(ResumeContractAction { name, version })
     }
 
     pub fn into_payload_builder(self) -> Result<SabrePayloadBuilder, ActionBuildError> {
@@ -462,6 +1048,7 @@ pub struct ExecuteContractAction {
     inputs: Vec<String>,
     outputs: Vec<String>,
     payload: Vec<u8>,
+    version_requirement: String,
 }
 
 impl ExecuteContractAction {
@@ -469,10 +1056,18 @@ impl ExecuteContractAction {
         &self.name
     }
 
+    /// The version of the contract to execute, which is empty if a version requirement is given
+    /// instead
     pub fn version(&self) -> &str {
         &self.version
     }
 
+    /// The semver requirement the executed version of the contract must match, which is empty
+    /// if an exact version is given instead
+    pub fn version_requirement(&self) -> &str {
+        &self.version_requirement
+    }
+
     pub fn inputs(&self) -> &[String] {
         &self.inputs
     }
@@ -496,6 +1091,7 @@ impl FromProto<protos::payload::ExecuteContractAction> for ExecuteContractAction
             inputs: proto.get_inputs().to_vec(),
             outputs: proto.get_outputs().to_vec(),
             payload: proto.get_payload().to_vec(),
+            version_requirement: proto.get_version_requirement().to_string(),
         })
     }
 }
@@ -514,6 +1110,7 @@ impl FromNative<ExecuteContractAction> for protos::payload::ExecuteContractActio
             execute_contract_action.outputs().to_vec(),
         ));
         proto.set_payload(execute_contract_action.payload().to_vec());
+        proto.set_version_requirement(execute_contract_action.version_requirement().to_string());
         Ok(proto)
     }
 }
@@ -553,6 +1150,7 @@ pub struct ExecuteContractActionBuilder {
     inputs: Vec<String>,
     outputs: Vec<String>,
     payload: Vec<u8>,
+    version_requirement: Option<String>,
 }
 
 impl ExecuteContractActionBuilder {
@@ -570,6 +1168,16 @@ impl ExecuteContractActionBuilder {
         self
     }
 
+    /// Execute the highest registered version of the contract matching a semver requirement,
+    /// such as `^1.2`, instead of an exact version
+    pub fn with_version_requirement(
+        mut self,
+        version_requirement: String,
+    ) -> ExecuteContractActionBuilder {
+        self.version_requirement = Some(version_requirement);
+        self
+    }
+
     pub fn with_inputs(mut self, inputs: Vec<String>) -> ExecuteContractActionBuilder {
         self.inputs = inputs;
         self
@@ -590,9 +1198,20 @@ impl ExecuteContractActionBuilder {
             ActionBuildError::MissingField("'name' field is required".to_string())
         })?;
 
-        let version = self.version.ok_or_else(|| {
-            ActionBuildError::MissingField("'version' field is required".to_string())
-        })?;
+        let (version, version_requirement) = match (self.version, self.version_requirement) {
+            (Some(version), None) => (version, String::new()),
+            (None, Some(version_requirement)) => (String::new(), version_requirement),
+            (None, None) => {
+                return Err(ActionBuildError::MissingField(
+                    "'version' or 'version_requirement' field is required".to_string(),
+                ))
+            }
+            (Some(_), Some(_)) => {
+                return Err(ActionBuildError::InvalidField(
+                    "only one of 'version' and 'version_requirement' may be set".to_string(),
+                ))
+            }
+        };
 
         let inputs = self.inputs;
         let outputs = self.outputs;
@@ -613,6 +1232,7 @@ impl ExecuteContractActionBuilder {
             inputs,
             outputs,
             payload,
+            version_requirement,
         })
     }
 
@@ -627,6 +1247,7 @@ impl ExecuteContractActionBuilder {
 pub struct CreateContractRegistryAction {
     name: String,
     owners: Vec<String>,
+    max_memory_pages: u32,
 }
 
 impl CreateContractRegistryAction {
@@ -637,6 +1258,12 @@ impl CreateContractRegistryAction {
     pub fn owners(&self) -> &[String] {
         &self.owners
     }
+
+    /// The number of pages of memory the contract may use, or 0 if the transaction processor's
+    /// limit applies
+    pub fn max_memory_pages(&self) -> u32 {
+        self.max_memory_pages
+    }
 }
 
 impl FromProto<protos::payload::CreateContractRegistryAction> for CreateContractRegistryAction {
@@ -646,6 +1273,7 @@ impl FromProto<protos::payload::CreateContractRegistryAction> for CreateContract
         Ok(CreateContractRegistryAction {
             name: proto.get_name().to_string(),
             owners: proto.get_owners().to_vec(),
+            max_memory_pages: proto.get_max_memory_pages(),
         })
     }
 }
@@ -659,6 +1287,7 @@ impl FromNative<CreateContractRegistryAction> for protos::payload::CreateContrac
         proto.set_owners(RepeatedField::from_vec(
             create_contract_registry_action.owners().to_vec(),
         ));
+        proto.set_max_memory_pages(create_contract_registry_action.max_memory_pages());
         Ok(proto)
     }
 }
@@ -695,6 +1324,7 @@ impl IntoNative<CreateContractRegistryAction> for protos::payload::CreateContrac
 pub struct CreateContractRegistryActionBuilder {
     name: Option<String>,
     owners: Vec<String>,
+    max_memory_pages: u32,
 }
 
 impl CreateContractRegistryActionBuilder {
@@ -712,6 +1342,14 @@ impl CreateContractRegistryActionBuilder {
         self
     }
 
+    pub fn with_max_memory_pages(
+        mut self,
+        max_memory_pages: u32,
+    ) -> CreateContractRegistryActionBuilder {
+        self.max_memory_pages = max_memory_pages;
+        self
+    }
+
     pub fn build(self) -> Result<CreateContractRegistryAction, ActionBuildError> {
         let name = self.name.ok_or_else(|| {
             ActionBuildError::MissingField("'name' field is required".to_string())
@@ -727,7 +1365,11 @@ impl CreateContractRegistryActionBuilder {
             }
         };
 
-        Ok(CreateContractRegistryAction { name, owners })
+        Ok(CreateContractRegistryAction {
+            name,
+            owners,
+            max_memory_pages: self.max_memory_pages,
+        })
     }
 
     pub fn into_payload_builder(self) -> Result<SabrePayloadBuilder, ActionBuildError> {
@@ -2044,6 +2686,19 @@ impl FromProto<protos::payload::SabrePayload> for SabrePayload {
                 )?
                 .into()
             }
+            protos::payload::SabrePayload_Action::CREATE_CONTRACT_CHUNK => {
+                CreateContractChunkAction::from_proto(proto.get_create_contract_chunk().clone())?
+                    .into()
+            }
+            protos::payload::SabrePayload_Action::COMMIT_CONTRACT => {
+                CommitContractAction::from_proto(proto.get_commit_contract().clone())?.into()
+            }
+            protos::payload::SabrePayload_Action::PAUSE_CONTRACT => {
+                PauseContractAction::from_proto(proto.get_pause_contract().clone())?.into()
+            }
+            protos::payload::SabrePayload_Action::RESUME_CONTRACT => {
+                ResumeContractAction::from_proto(proto.get_resume_contract().clone())?.into()
+            }
             protos::payload::SabrePayload_Action::ACTION_UNSET => {
                 return Err(ProtoConversionError::InvalidTypeError(
                     "Cannot convert SabrePayload_Action with type unset.".to_string(),
@@ -2125,6 +2780,22 @@ impl FromNative<SabrePayload> for protos::payload::SabrePayload {
                 proto.set_action(protos::payload::SabrePayload_Action::DELETE_SMART_PERMISSION);
                 proto.set_delete_smart_permission(payload.clone().into_proto()?);
             }
+            Action::CreateContractChunk(payload) => {
+                proto.set_action(protos::payload::SabrePayload_Action::CREATE_CONTRACT_CHUNK);
+                proto.set_create_contract_chunk(payload.clone().into_proto()?);
+            }
+            Action::CommitContract(payload) => {
+                proto.set_action(protos::payload::SabrePayload_Action::COMMIT_CONTRACT);
+                proto.set_commit_contract(payload.clone().into_proto()?);
+            }
+            Action::PauseContract(payload) => {
+                proto.set_action(protos::payload::SabrePayload_Action::PAUSE_CONTRACT);
+                proto.set_pause_contract(payload.clone().into_proto()?);
+            }
+            Action::ResumeContract(payload) => {
+                proto.set_action(protos::payload::SabrePayload_Action::RESUME_CONTRACT);
+                proto.set_resume_contract(payload.clone().into_proto()?);
+            }
         }
 
         Ok(proto)
@@ -2235,28 +2906,90 @@ impl SabrePayloadBuilder {
     ) -> Result<TransactionBuilder, SabrePayloadBuildError> {
         let payload = self.build()?;
 
-        let (input_addresses, output_addresses) = match payload.action() {
-            Action::CreateContract(CreateContractAction { name, version, .. })
-            | Action::DeleteContract(DeleteContractAction { name, version, .. }) => {
+        let (mut input_addresses, mut output_addresses) = match payload.action() {
+            Action::CreateContract(CreateContractAction { name, version, .. }) => {
+                let addresses = vec![
+                    compute_contract_registry_address(name)?,
+                    compute_contract_address(name, version)?,
+                ];
+                let mut input_addresses = addresses.clone();
+                input_addresses.push(MAX_CONTRACT_SIZE_SETTING_ADDRESS_BYTES.to_vec());
+                input_addresses.push(WASM_PROTOCOL_VERSION_SETTING_ADDRESS_BYTES.to_vec());
+                (input_addresses, addresses)
+            }
+            Action::DeleteContract(DeleteContractAction { name, version, .. }) => {
                 let addresses = vec![
                     compute_contract_registry_address(&name)?,
                     compute_contract_address(&name, &version)?,
                 ];
                 (addresses.clone(), addresses)
             }
+            Action::CreateContractChunk(CreateContractChunkAction {
+                name,
+                version,
+                index,
+                ..
+            }) => {
+                let chunk_address = compute_contract_chunk_address(name, version, *index)?;
+                let mut input_addresses = vec![
+                    compute_contract_registry_address(name)?,
+                    compute_contract_address(name, version)?,
+                ];
+                // the chunks before this one, and the one after it, are read to limit the size
+                // of the upload
+                for index in 0..=index.saturating_add(1) {
+                    input_addresses.push(compute_contract_chunk_address(name, version, index)?);
+                }
+                input_addresses.push(MAX_CONTRACT_SIZE_SETTING_ADDRESS_BYTES.to_vec());
+                (input_addresses, vec![chunk_address])
+            }
+            Action::CommitContract(CommitContractAction {
+                name,
+                version,
+                chunk_count,
+                ..
+            }) => {
+                let mut addresses = vec![
+                    compute_contract_registry_address(name)?,
+                    compute_contract_address(name, version)?,
+                ];
+                for index in 0..*chunk_count {
+                    addresses.push(compute_contract_chunk_address(name, version, index)?);
+                }
+                let mut input_addresses = addresses.clone();
+                // the chunk after the last one is read to check that none are left behind
+                input_addresses.push(compute_contract_chunk_address(name, version, *chunk_count)?);
+                input_addresses.push(MAX_CONTRACT_SIZE_SETTING_ADDRESS_BYTES.to_vec());
+                input_addresses.push(WASM_PROTOCOL_VERSION_SETTING_ADDRESS_BYTES.to_vec());
+                (input_addresses, addresses)
+            }
             Action::ExecuteContract(ExecuteContractAction {
                 name,
                 version,
                 inputs,
                 outputs,
+                version_requirement,
                 ..
             }) => {
-                let addresses = vec![
-                    compute_contract_registry_address(&name)?,
-                    compute_contract_address(&name, &version)?,
-                ];
-
-                let mut input_addresses = addresses.clone();
+                let (mut input_addresses, mut output_addresses) = if version_requirement.is_empty()
+                {
+                    let addresses = vec![
+                        compute_contract_registry_address(&name)?,
+                        compute_contract_address(&name, &version)?,
+                    ];
+                    (addresses.clone(), addresses)
+                } else {
+                    // The version executed is only known once the requirement is resolved
+                    // against the contract registry, so any contract may be read
+                    let registry = compute_contract_registry_address(name)?;
+                    (
+                        vec![registry.clone(), parse_hex(CONTRACT_ADDRESS_PREFIX)?],
+                        vec![registry],
+                    )
+                };
+
+                input_addresses.push(FLOAT_POLICY_SETTING_ADDRESS_BYTES.to_vec());
+                input_addresses.push(WASM_PROTOCOL_VERSION_SETTING_ADDRESS_BYTES.to_vec());
                 for input in inputs {
                     let namespace = match input.get(..6) {
                         Some(namespace) => namespace,
@@ -2271,7 +3004,6 @@ impl SabrePayloadBuilder {
                     input_addresses.push(parse_hex(&input)?);
                 }
 
-                let mut output_addresses = addresses;
                 for output in outputs {
                     let namespace = match output.get(..6) {
                         Some(namespace) => namespace,
@@ -2293,7 +3025,9 @@ impl SabrePayloadBuilder {
             | Action::UpdateContractRegistryOwners(UpdateContractRegistryOwnersAction {
                 name,
                 ..
-            }) => {
+            })
+            | Action::PauseContract(PauseContractAction { name, .. })
+            | Action::ResumeContract(ResumeContractAction { name, .. }) => {
                 let addresses = vec![
                     compute_contract_registry_address(&name)?,
                     ADMINISTRATORS_SETTING_ADDRESS_BYTES.to_vec(),
@@ -2343,6 +3077,17 @@ impl SabrePayloadBuilder {
             }
         };
 
+        // The transaction processor records administrative actions in the audit log of their
+        // registry when the audit setting is enabled, with the latest block's info if it is set
+        if let Some(registry_address) = audited_registry_address(payload.action())? {
+            let audit_log_address = compute_audit_log_address(&registry_address)?;
+            input_addresses.push(AUDIT_SETTING_ADDRESS_BYTES.to_vec());
+            input_addresses.push(audit_log_address.clone());
+            input_addresses.push(BLOCK_INFO_CONFIG_ADDRESS_BYTES.to_vec());
+            input_addresses.push(BLOCK_INFO_ADDRESS_PREFIX_BYTES.to_vec());
+            output_addresses.push(audit_log_address);
+        }
+
         let payload_bytes = payload.into_bytes().map_err(|err| {
             SabrePayloadBuildError::ProtoConversionError(format!(
                 "failed to serialize SabrePayload as bytes: {}",
@@ -2360,6 +3105,38 @@ impl SabrePayloadBuilder {
     }
 }
 
+/// Return the address of the registry whose audit log records an action, if the action is audited
+#[cfg(not(target_arch = "wasm32"))]
+fn audited_registry_address(action: &Action) -> Result<Option<Vec<u8>>, AddressingError> {
+    match action {
+        Action::DeleteContract(DeleteContractAction { name, .. })
+        | Action::CreateContractRegistry(CreateContractRegistryAction { name, .. })
+        | Action::DeleteContractRegistry(DeleteContractRegistryAction { name, .. })
+        | Action::UpdateContractRegistryOwners(UpdateContractRegistryOwnersAction {
+            name, ..
+        })
+        | Action::PauseContract(PauseContractAction { name, .. })
+        | Action::ResumeContract(ResumeContractAction { name, .. }) => {
+            Ok(Some(compute_contract_registry_address(name)?))
+        }
+        Action::CreateNamespaceRegistry(CreateNamespaceRegistryAction { namespace, .. })
+        | Action::DeleteNamespaceRegistry(DeleteNamespaceRegistryAction { namespace, .. })
+        | Action::UpdateNamespaceRegistryOwners(UpdateNamespaceRegistryOwnersAction {
+            namespace,
+            ..
+        })
+        | Action::CreateNamespaceRegistryPermission(CreateNamespaceRegistryPermissionAction {
+            namespace,
+            ..
+        })
+        | Action::DeleteNamespaceRegistryPermission(DeleteNamespaceRegistryPermissionAction {
+            namespace,
+            ..
+        }) => Ok(Some(compute_namespace_registry_address(namespace)?)),
+        _ => Ok(None),
+    }
+}
+
 fn parse_hex(hex: &str) -> Result<Vec<u8>, AddressingError> {
     if hex.len() % 2 != 0 {
         return Err(AddressingError::InvalidInput(format!(
@@ -2423,6 +3200,92 @@ mod tests {
         assert_eq!(create, original);
     }
 
+    #[test]
+    // check that a create contract chunk action is built correctly
+    fn check_create_contract_chunk_action() {
+        let builder = CreateContractChunkActionBuilder::new();
+        let action = builder
+            .with_name("TestContract".to_string())
+            .with_version("0.1".to_string())
+            .with_index(2)
+            .with_chunk(b"test".to_vec())
+            .build()
+            .unwrap();
+
+        assert_eq!(action.name(), "TestContract");
+        assert_eq!(action.version(), "0.1");
+        assert_eq!(action.index(), 2);
+        assert_eq!(action.chunk(), b"test");
+    }
+
+    #[test]
+    // check that a create contract chunk action can be converted to bytes and back
+    fn check_create_contract_chunk_action_bytes() {
+        let builder = CreateContractChunkActionBuilder::new();
+        let original = builder
+            .with_name("TestContract".to_string())
+            .with_version("0.1".to_string())
+            .with_index(2)
+            .with_chunk(b"test".to_vec())
+            .build()
+            .unwrap();
+
+        let bytes = original.clone().into_bytes().unwrap();
+
+        let create = CreateContractChunkAction::from_bytes(&bytes).unwrap();
+        assert_eq!(create, original);
+    }
+
+    #[test]
+    // check that a commit contract action is built correctly
+    fn check_commit_contract_action() {
+        let builder = CommitContractActionBuilder::new();
+        let action = builder
+            .with_name("TestContract".to_string())
+            .with_version("0.1".to_string())
+            .with_inputs(vec!["test".to_string(), "input".to_string()])
+            .with_outputs(vec!["test".to_string(), "output".to_string()])
+            .with_chunk_count(3)
+            .with_contract_sha512("abcd".to_string())
+            .build()
+            .unwrap();
+
+        assert_eq!(action.name(), "TestContract");
+        assert_eq!(action.version(), "0.1");
+        assert_eq!(action.inputs(), ["test".to_string(), "input".to_string()]);
+        assert_eq!(action.outputs(), ["test".to_string(), "output".to_string()]);
+        assert_eq!(action.chunk_count(), 3);
+        assert_eq!(action.contract_sha512(), "abcd");
+
+        assert!(CommitContractActionBuilder::new()
+            .with_name("TestContract".to_string())
+            .with_version("0.1".to_string())
+            .with_chunk_count(0)
+            .with_contract_sha512("abcd".to_string())
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    // check that a commit contract action can be converted to bytes and back
+    fn check_commit_contract_action_bytes() {
+        let builder = CommitContractActionBuilder::new();
+        let original = builder
+            .with_name("TestContract".to_string())
+            .with_version("0.1".to_string())
+            .with_inputs(vec!["test".to_string(), "input".to_string()])
+            .with_outputs(vec!["test".to_string(), "output".to_string()])
+            .with_chunk_count(3)
+            .with_contract_sha512("abcd".to_string())
+            .build()
+            .unwrap();
+
+        let bytes = original.clone().into_bytes().unwrap();
+
+        let commit = CommitContractAction::from_bytes(&bytes).unwrap();
+        assert_eq!(commit, original);
+    }
+
     #[test]
     // check that a delete create action is built correctly
     fn check_delete_contract_action() {
@@ -2453,6 +3316,38 @@ mod tests {
         assert_eq!(create, original);
     }
 
+    #[test]
+    // check that a pause contract can be converted to bytes and back
+    fn check_pause_contract_action_bytes() {
+        let builder = PauseContractActionBuilder::new();
+        let original = builder
+            .with_name("TestContract".to_string())
+            .with_version("0.1".to_string())
+            .build()
+            .unwrap();
+
+        let bytes = original.clone().into_bytes().unwrap();
+
+        let pause = PauseContractAction::from_bytes(&bytes).unwrap();
+        assert_eq!(pause, original);
+    }
+
+    #[test]
+    // check that a resume contract can be converted to bytes and back
+    fn check_resume_contract_action_bytes() {
+        let builder = ResumeContractActionBuilder::new();
+        let original = builder
+            .with_name("TestContract".to_string())
+            .with_version("0.1".to_string())
+            .build()
+            .unwrap();
+
+        let bytes = original.clone().into_bytes().unwrap();
+
+        let resume = ResumeContractAction::from_bytes(&bytes).unwrap();
+        assert_eq!(resume, original);
+    }
+
     #[test]
     // check that a execute contract action is built correctly
     fn check_execute_contract_action() {
@@ -2492,6 +3387,51 @@ mod tests {
         assert_eq!(execute, original);
     }
 
+    #[test]
+    // check that a execute contract action with a version requirement is built correctly and
+    // can be converted to bytes and back
+    fn check_execute_contract_action_version_requirement() {
+        let original = ExecuteContractActionBuilder::new()
+            .with_name("TestContract".to_string())
+            .with_version_requirement("^0.1".to_string())
+            .with_inputs(vec!["test".to_string(), "input".to_string()])
+            .with_outputs(vec!["test".to_string(), "output".to_string()])
+            .with_payload(b"test_payload".to_vec())
+            .build()
+            .unwrap();
+
+        assert_eq!(original.version(), "");
+        assert_eq!(original.version_requirement(), "^0.1");
+
+        let bytes = original.clone().into_bytes().unwrap();
+
+        let execute = ExecuteContractAction::from_bytes(&bytes).unwrap();
+        assert_eq!(execute, original);
+    }
+
+    #[test]
+    // check that a execute contract action must have exactly one of a version and a version
+    // requirement
+    fn check_execute_contract_action_version_or_requirement() {
+        let builder = ExecuteContractActionBuilder::new()
+            .with_name("TestContract".to_string())
+            .with_payload(b"test_payload".to_vec());
+
+        match builder.clone().build() {
+            Err(ActionBuildError::MissingField(_)) => (),
+            res => panic!("Expected MissingField, got {:?}", res),
+        }
+
+        match builder
+            .with_version("0.1".to_string())
+            .with_version_requirement("^0.1".to_string())
+            .build()
+        {
+            Err(ActionBuildError::InvalidField(_)) => (),
+            res => panic!("Expected InvalidField, got {:?}", res),
+        }
+    }
+
     #[test]
     // check that a create contract registry action is built correctly
     fn check_create_contract_registry_action() {
@@ -2499,11 +3439,13 @@ mod tests {
         let action = builder
             .with_name("TestContract".to_string())
             .with_owners(vec!["test".to_string(), "owner".to_string()])
+            .with_max_memory_pages(64)
             .build()
             .unwrap();
 
         assert_eq!(action.name(), "TestContract");
         assert_eq!(action.owners(), ["test".to_string(), "owner".to_string()]);
+        assert_eq!(action.max_memory_pages(), 64);
     }
 
     #[test]
@@ -2513,6 +3455,7 @@ mod tests {
         let original = builder
             .with_name("TestContract".to_string())
             .with_owners(vec!["test".to_string(), "owner".to_string()])
+            .with_max_memory_pages(64)
             .build()
             .unwrap();
 
@@ -2895,6 +3838,15 @@ mod tests {
             SABRE_PROTOCOL_VERSION.to_string()
         );
         assert_eq!(txn_header.payload_hash_method(), &HashMethod::SHA512);
+        assert!(txn_header
+            .inputs()
+            .contains(&MAX_CONTRACT_SIZE_SETTING_ADDRESS_BYTES.to_vec()));
+        assert!(!txn_header
+            .outputs()
+            .contains(&MAX_CONTRACT_SIZE_SETTING_ADDRESS_BYTES.to_vec()));
+        assert!(txn_header
+            .inputs()
+            .contains(&WASM_PROTOCOL_VERSION_SETTING_ADDRESS_BYTES.to_vec()));
     }
 
     #[test]
@@ -2923,6 +3875,43 @@ mod tests {
         assert_eq!(txn_header.payload_hash_method(), &HashMethod::SHA512);
     }
 
+    #[test]
+    // check that a pause contract only needs the contract registry, the administrators and the
+    // audit log, and not the contract itself
+    fn pause_contract_into_transaction() {
+        let signer = new_signer();
+
+        let txn_pair = PauseContractActionBuilder::new()
+            .with_name("TestContract".to_string())
+            .with_version("0.1".to_string())
+            .into_payload_builder()
+            .expect("failed to convert to payload builder")
+            .into_transaction_builder(&*signer)
+            .expect("failed to convert to transaction builder")
+            .build_pair(&*signer)
+            .expect("failed to build transaction pair");
+
+        let txn_header = txn_pair.header();
+
+        let registry_address = compute_contract_registry_address("TestContract").unwrap();
+        let audit_log_address = compute_audit_log_address(&registry_address).unwrap();
+        let inputs = vec![
+            registry_address.clone(),
+            ADMINISTRATORS_SETTING_ADDRESS_BYTES.to_vec(),
+            AUDIT_SETTING_ADDRESS_BYTES.to_vec(),
+            audit_log_address.clone(),
+            BLOCK_INFO_CONFIG_ADDRESS_BYTES.to_vec(),
+            BLOCK_INFO_ADDRESS_PREFIX_BYTES.to_vec(),
+        ];
+        let outputs = vec![
+            registry_address,
+            ADMINISTRATORS_SETTING_ADDRESS_BYTES.to_vec(),
+            audit_log_address,
+        ];
+        assert_eq!(txn_header.inputs(), inputs.as_slice());
+        assert_eq!(txn_header.outputs(), outputs.as_slice());
+    }
+
     #[test]
     // check that an execute contract can be converted -> sabre payload builder -> transaction
     // builder -> transaction
@@ -2950,6 +3939,41 @@ mod tests {
             SABRE_PROTOCOL_VERSION.to_string()
         );
         assert_eq!(txn_header.payload_hash_method(), &HashMethod::SHA512);
+        assert!(txn_header
+            .inputs()
+            .contains(&FLOAT_POLICY_SETTING_ADDRESS_BYTES.to_vec()));
+        assert!(txn_header
+            .inputs()
+            .contains(&WASM_PROTOCOL_VERSION_SETTING_ADDRESS_BYTES.to_vec()));
+    }
+
+    #[test]
+    // check that an execute contract with a version requirement may read any contract, since
+    // the version executed is not known until the requirement is resolved
+    fn execute_contract_version_requirement_into_transaction() {
+        let signer = new_signer();
+
+        let txn_pair = ExecuteContractActionBuilder::new()
+            .with_name("TestContract".to_string())
+            .with_version_requirement("^0.1".to_string())
+            .with_inputs(vec!["abcdef".to_string(), "012345".to_string()])
+            .with_outputs(vec!["abcdef".to_string(), "678910".to_string()])
+            .with_payload(b"test_payload".to_vec())
+            .into_payload_builder()
+            .expect("failed to convert to payload builder")
+            .into_transaction_builder(&*signer)
+            .expect("failed to convert to transaction builder")
+            .build_pair(&*signer)
+            .expect("failed to build transaction pair");
+
+        let txn_header = txn_pair.header();
+
+        assert!(txn_header
+            .inputs()
+            .contains(&parse_hex(CONTRACT_ADDRESS_PREFIX).unwrap()));
+        assert!(!txn_header
+            .outputs()
+            .contains(&parse_hex(CONTRACT_ADDRESS_PREFIX).unwrap()));
     }
 
     #[test]
@@ -28,6 +28,7 @@ pub struct Version {
     version: String,
     contract_sha512: String,
     creator: String,
+    paused: bool,
 }
 
 impl Version {
@@ -43,11 +44,17 @@ impl Version {
         &self.creator
     }
 
+    /// Whether executing the version is blocked until it is resumed
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
     pub fn into_builder(self) -> VersionBuilder {
         VersionBuilder::new()
             .with_version(self.version)
             .with_contract_sha512(self.contract_sha512)
             .with_creator(self.creator)
+            .with_paused(self.paused)
     }
 }
 
@@ -59,6 +66,7 @@ impl FromProto<protos::contract_registry::ContractRegistry_Version> for Version
             version: proto.get_version().to_string(),
             contract_sha512: proto.get_contract_sha512().to_string(),
             creator: proto.get_creator().to_string(),
+            paused: proto.get_paused(),
         })
     }
 }
@@ -70,6 +78,7 @@ impl FromNative<Version> for protos::contract_registry::ContractRegistry_Version
         proto.set_version(native.version().to_string());
         proto.set_contract_sha512(native.contract_sha512().to_string());
         proto.set_creator(native.creator().to_string());
+        proto.set_paused(native.paused());
 
         Ok(proto)
     }
@@ -105,6 +114,7 @@ pub struct VersionBuilder {
     version: Option<String>,
     contract_sha512: Option<String>,
     creator: Option<String>,
+    paused: bool,
 }
 
 impl VersionBuilder {
@@ -127,6 +137,11 @@ impl VersionBuilder {
         self
     }
 
+    pub fn with_paused(mut self, paused: bool) -> VersionBuilder {
+        self.paused = paused;
+        self
+    }
+
     pub fn build(self) -> Result<Version, VersionBuildError> {
         let version = self.version.ok_or_else(|| {
             VersionBuildError::MissingField("'versions' field is required".to_string())
@@ -144,6 +159,7 @@ impl VersionBuilder {
             version,
             contract_sha512,
             creator,
+            paused: self.paused,
         })
     }
 }
@@ -154,6 +170,7 @@ pub struct ContractRegistry {
     name: String,
     versions: Vec<Version>,
     owners: Vec<String>,
+    max_memory_pages: u32,
 }
 
 impl ContractRegistry {
@@ -169,11 +186,18 @@ impl ContractRegistry {
         &self.owners
     }
 
+    /// The number of pages of memory the contract may use, or 0 if the transaction processor's
+    /// limit applies
+    pub fn max_memory_pages(&self) -> u32 {
+        self.max_memory_pages
+    }
+
     pub fn into_builder(self) -> ContractRegistryBuilder {
         ContractRegistryBuilder::new()
             .with_name(self.name)
             .with_versions(self.versions)
             .with_owners(self.owners)
+            .with_max_memory_pages(self.max_memory_pages)
     }
 }
 
@@ -190,6 +214,7 @@ impl FromProto<protos::contract_registry::ContractRegistry> for ContractRegistry
                 .map(Version::from_proto)
                 .collect::<Result<Vec<Version>, ProtoConversionError>>()?,
             owners: proto.get_owners().to_vec(),
+            max_memory_pages: proto.get_max_memory_pages(),
         })
     }
 }
@@ -210,6 +235,7 @@ impl FromNative<ContractRegistry> for protos::contract_registry::ContractRegistr
                 >>()?,
         ));
         proto.set_owners(RepeatedField::from_vec(contract_registry.owners().to_vec()));
+        proto.set_max_memory_pages(contract_registry.max_memory_pages());
 
         Ok(proto)
     }
@@ -269,6 +295,7 @@ pub struct ContractRegistryBuilder {
     name: Option<String>,
     versions: Vec<Version>,
     owners: Vec<String>,
+    max_memory_pages: u32,
 }
 
 impl ContractRegistryBuilder {
@@ -291,6 +318,11 @@ impl ContractRegistryBuilder {
         self
     }
 
+    pub fn with_max_memory_pages(mut self, max_memory_pages: u32) -> ContractRegistryBuilder {
+        self.max_memory_pages = max_memory_pages;
+        self
+    }
+
     pub fn build(self) -> Result<ContractRegistry, ContractRegistryBuildError> {
         let name = self.name.ok_or_else(|| {
             ContractRegistryBuildError::MissingField("'name' field is required".to_string())
@@ -312,6 +344,7 @@ impl ContractRegistryBuilder {
             name,
             versions,
             owners,
+            max_memory_pages: self.max_memory_pages,
         })
     }
 }
@@ -1190,6 +1223,294 @@ impl ContractListBuilder {
     }
 }
 
+/// Native implementation for ContractChunk
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ContractChunk {
+    name: String,
+    version: String,
+    index: u32,
+    creator: String,
+    chunk: Vec<u8>,
+}
+
+impl ContractChunk {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn creator(&self) -> &str {
+        &self.creator
+    }
+
+    pub fn chunk(&self) -> &[u8] {
+        &self.chunk
+    }
+}
+
+impl FromProto<protos::contract_chunk::ContractChunk> for ContractChunk {
+    fn from_proto(
+        proto: protos::contract_chunk::ContractChunk,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(ContractChunk {
+            name: proto.get_name().to_string(),
+            version: proto.get_version().to_string(),
+            index: proto.get_index(),
+            creator: proto.get_creator().to_string(),
+            chunk: proto.get_chunk().to_vec(),
+        })
+    }
+}
+
+impl FromNative<ContractChunk> for protos::contract_chunk::ContractChunk {
+    fn from_native(contract_chunk: ContractChunk) -> Result<Self, ProtoConversionError> {
+        let mut proto = protos::contract_chunk::ContractChunk::new();
+
+        proto.set_name(contract_chunk.name().to_string());
+        proto.set_version(contract_chunk.version().to_string());
+        proto.set_index(contract_chunk.index());
+        proto.set_creator(contract_chunk.creator().to_string());
+        proto.set_chunk(contract_chunk.chunk().to_vec());
+
+        Ok(proto)
+    }
+}
+
+impl FromBytes<ContractChunk> for ContractChunk {
+    fn from_bytes(bytes: &[u8]) -> Result<ContractChunk, ProtoConversionError> {
+        let proto: protos::contract_chunk::ContractChunk = Message::parse_from_bytes(bytes)
+            .map_err(|_| {
+                ProtoConversionError::SerializationError(
+                    "Unable to get ContractChunk from bytes".to_string(),
+                )
+            })?;
+        proto.into_native()
+    }
+}
+
+impl IntoBytes for ContractChunk {
+    fn into_bytes(self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto = self.into_proto()?;
+        let bytes = proto.write_to_bytes().map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get bytes from ContractChunk".to_string(),
+            )
+        })?;
+        Ok(bytes)
+    }
+}
+
+impl IntoProto<protos::contract_chunk::ContractChunk> for ContractChunk {}
+impl IntoNative<ContractChunk> for protos::contract_chunk::ContractChunk {}
+
+#[derive(Debug)]
+pub enum ContractChunkBuildError {
+    MissingField(String),
+}
+
+impl StdError for ContractChunkBuildError {}
+
+impl std::fmt::Display for ContractChunkBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ContractChunkBuildError::MissingField(ref s) => write!(f, "MissingField: {}", s),
+        }
+    }
+}
+
+/// Builder used to create a ContractChunk
+#[derive(Default, Clone)]
+pub struct ContractChunkBuilder {
+    name: Option<String>,
+    version: Option<String>,
+    index: Option<u32>,
+    creator: Option<String>,
+    chunk: Vec<u8>,
+}
+
+impl ContractChunkBuilder {
+    pub fn new() -> Self {
+        ContractChunkBuilder::default()
+    }
+
+    pub fn with_name(mut self, name: String) -> ContractChunkBuilder {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn with_version(mut self, version: String) -> ContractChunkBuilder {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn with_index(mut self, index: u32) -> ContractChunkBuilder {
+        self.index = Some(index);
+        self
+    }
+
+    pub fn with_creator(mut self, creator: String) -> ContractChunkBuilder {
+        self.creator = Some(creator);
+        self
+    }
+
+    pub fn with_chunk(mut self, chunk: Vec<u8>) -> ContractChunkBuilder {
+        self.chunk = chunk;
+        self
+    }
+
+    pub fn build(self) -> Result<ContractChunk, ContractChunkBuildError> {
+        let name = self.name.ok_or_else(|| {
+            ContractChunkBuildError::MissingField("'name' field is required".to_string())
+        })?;
+
+        let version = self.version.ok_or_else(|| {
+            ContractChunkBuildError::MissingField("'version' field is required".to_string())
+        })?;
+
+        let index = self.index.ok_or_else(|| {
+            ContractChunkBuildError::MissingField("'index' field is required".to_string())
+        })?;
+
+        let creator = self.creator.ok_or_else(|| {
+            ContractChunkBuildError::MissingField("'creator' field is required".to_string())
+        })?;
+
+        let chunk = {
+            if !self.chunk.is_empty() {
+                self.chunk
+            } else {
+                return Err(ContractChunkBuildError::MissingField(
+                    "'chunk' field is required".to_string(),
+                ));
+            }
+        };
+
+        Ok(ContractChunk {
+            name,
+            version,
+            index,
+            creator,
+            chunk,
+        })
+    }
+}
+
+// Native implementation for ContractChunkList
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ContractChunkList {
+    chunks: Vec<ContractChunk>,
+}
+
+impl ContractChunkList {
+    pub fn chunks(&self) -> &[ContractChunk] {
+        &self.chunks
+    }
+}
+
+impl FromProto<protos::contract_chunk::ContractChunkList> for ContractChunkList {
+    fn from_proto(
+        proto: protos::contract_chunk::ContractChunkList,
+    ) -> Result<Self, ProtoConversionError> {
+        Ok(ContractChunkList {
+            chunks: proto
+                .get_chunks()
+                .to_vec()
+                .into_iter()
+                .map(ContractChunk::from_proto)
+                .collect::<Result<Vec<ContractChunk>, ProtoConversionError>>()?,
+        })
+    }
+}
+
+impl FromNative<ContractChunkList> for protos::contract_chunk::ContractChunkList {
+    fn from_native(chunk_list: ContractChunkList) -> Result<Self, ProtoConversionError> {
+        let mut proto = protos::contract_chunk::ContractChunkList::new();
+        proto.set_chunks(RepeatedField::from_vec(
+            chunk_list
+                .chunks()
+                .to_vec()
+                .into_iter()
+                .map(ContractChunk::into_proto)
+                .collect::<Result<Vec<protos::contract_chunk::ContractChunk>, ProtoConversionError>>(
+                )?,
+        ));
+
+        Ok(proto)
+    }
+}
+
+impl FromBytes<ContractChunkList> for ContractChunkList {
+    fn from_bytes(bytes: &[u8]) -> Result<ContractChunkList, ProtoConversionError> {
+        let proto: protos::contract_chunk::ContractChunkList = Message::parse_from_bytes(bytes)
+            .map_err(|_| {
+                ProtoConversionError::SerializationError(
+                    "Unable to get ContractChunkList from bytes".to_string(),
+                )
+            })?;
+        proto.into_native()
+    }
+}
+
+impl IntoBytes for ContractChunkList {
+    fn into_bytes(self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto = self.into_proto()?;
+        let bytes = proto.write_to_bytes().map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get bytes from ContractChunkList".to_string(),
+            )
+        })?;
+        Ok(bytes)
+    }
+}
+
+impl IntoProto<protos::contract_chunk::ContractChunkList> for ContractChunkList {}
+impl IntoNative<ContractChunkList> for protos::contract_chunk::ContractChunkList {}
+
+#[derive(Debug)]
+pub enum ContractChunkListBuildError {
+    MissingField(String),
+}
+
+impl StdError for ContractChunkListBuildError {}
+
+impl std::fmt::Display for ContractChunkListBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ContractChunkListBuildError::MissingField(ref s) => write!(f, "MissingField: {}", s),
+        }
+    }
+}
+
+/// Builder used to create a ContractChunkList
+#[derive(Default, Clone)]
+pub struct ContractChunkListBuilder {
+    chunks: Vec<ContractChunk>,
+}
+
+impl ContractChunkListBuilder {
+    pub fn new() -> Self {
+        ContractChunkListBuilder::default()
+    }
+
+    pub fn with_chunks(mut self, chunks: Vec<ContractChunk>) -> ContractChunkListBuilder {
+        self.chunks = chunks;
+        self
+    }
+
+    pub fn build(self) -> Result<ContractChunkList, ContractChunkListBuildError> {
+        let chunks = self.chunks;
+
+        Ok(ContractChunkList { chunks })
+    }
+}
+
 /// Native implementation for SmartPermission
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct SmartPermission {
@@ -1465,6 +1786,299 @@ impl SmartPermissionListBuilder {
     }
 }
 
+/// Native implementation for AuditEntry
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    signer: String,
+    action: String,
+    transaction_id: String,
+    payload: Vec<u8>,
+    block_num: u64,
+    timestamp: u64,
+}
+
+impl AuditEntry {
+    pub fn signer(&self) -> &str {
+        &self.signer
+    }
+
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    pub fn transaction_id(&self) -> &str {
+        &self.transaction_id
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    pub fn block_num(&self) -> u64 {
+        self.block_num
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+impl FromProto<protos::audit::AuditEntry> for AuditEntry {
+    fn from_proto(proto: protos::audit::AuditEntry) -> Result<Self, ProtoConversionError> {
+        Ok(AuditEntry {
+            signer: proto.get_signer().to_string(),
+            action: proto.get_action().to_string(),
+            transaction_id: proto.get_transaction_id().to_string(),
+            payload: proto.get_payload().to_vec(),
+            block_num: proto.get_block_num(),
+            timestamp: proto.get_timestamp(),
+        })
+    }
+}
+
+impl FromNative<AuditEntry> for protos::audit::AuditEntry {
+    fn from_native(audit_entry: AuditEntry) -> Result<Self, ProtoConversionError> {
+        let mut proto = protos::audit::AuditEntry::new();
+
+        proto.set_signer(audit_entry.signer().to_string());
+        proto.set_action(audit_entry.action().to_string());
+        proto.set_transaction_id(audit_entry.transaction_id().to_string());
+        proto.set_payload(audit_entry.payload().to_vec());
+        proto.set_block_num(audit_entry.block_num());
+        proto.set_timestamp(audit_entry.timestamp());
+
+        Ok(proto)
+    }
+}
+
+impl FromBytes<AuditEntry> for AuditEntry {
+    fn from_bytes(bytes: &[u8]) -> Result<AuditEntry, ProtoConversionError> {
+        let proto: protos::audit::AuditEntry = Message::parse_from_bytes(bytes).map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get AuditEntry from bytes".to_string(),
+            )
+        })?;
+        proto.into_native()
+    }
+}
+
+impl IntoBytes for AuditEntry {
+    fn into_bytes(self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto = self.into_proto()?;
+        let bytes = proto.write_to_bytes().map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get bytes from AuditEntry".to_string(),
+            )
+        })?;
+        Ok(bytes)
+    }
+}
+
+impl IntoProto<protos::audit::AuditEntry> for AuditEntry {}
+impl IntoNative<AuditEntry> for protos::audit::AuditEntry {}
+
+#[derive(Debug)]
+pub enum AuditEntryBuildError {
+    MissingField(String),
+}
+
+impl StdError for AuditEntryBuildError {}
+
+impl std::fmt::Display for AuditEntryBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            AuditEntryBuildError::MissingField(ref s) => write!(f, "MissingField: {}", s),
+        }
+    }
+}
+
+/// Builder used to create an AuditEntry
+///
+/// The block number and timestamp default to 0, which means they are not available.
+#[derive(Default, Clone)]
+pub struct AuditEntryBuilder {
+    signer: Option<String>,
+    action: Option<String>,
+    transaction_id: Option<String>,
+    payload: Vec<u8>,
+    block_num: u64,
+    timestamp: u64,
+}
+
+impl AuditEntryBuilder {
+    pub fn new() -> Self {
+        AuditEntryBuilder::default()
+    }
+
+    pub fn with_signer(mut self, signer: String) -> AuditEntryBuilder {
+        self.signer = Some(signer);
+        self
+    }
+
+    pub fn with_action(mut self, action: String) -> AuditEntryBuilder {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn with_transaction_id(mut self, transaction_id: String) -> AuditEntryBuilder {
+        self.transaction_id = Some(transaction_id);
+        self
+    }
+
+    pub fn with_payload(mut self, payload: Vec<u8>) -> AuditEntryBuilder {
+        self.payload = payload;
+        self
+    }
+
+    pub fn with_block_num(mut self, block_num: u64) -> AuditEntryBuilder {
+        self.block_num = block_num;
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: u64) -> AuditEntryBuilder {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn build(self) -> Result<AuditEntry, AuditEntryBuildError> {
+        let signer = self.signer.ok_or_else(|| {
+            AuditEntryBuildError::MissingField("'signer' field is required".to_string())
+        })?;
+
+        let action = self.action.ok_or_else(|| {
+            AuditEntryBuildError::MissingField("'action' field is required".to_string())
+        })?;
+
+        let transaction_id = self.transaction_id.ok_or_else(|| {
+            AuditEntryBuildError::MissingField("'transaction_id' field is required".to_string())
+        })?;
+
+        let payload = {
+            if !self.payload.is_empty() {
+                self.payload
+            } else {
+                return Err(AuditEntryBuildError::MissingField(
+                    "'payload' field is required".to_string(),
+                ));
+            }
+        };
+
+        Ok(AuditEntry {
+            signer,
+            action,
+            transaction_id,
+            payload,
+            block_num: self.block_num,
+            timestamp: self.timestamp,
+        })
+    }
+}
+
+// Native implementation for AuditLog
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+}
+
+impl FromProto<protos::audit::AuditLog> for AuditLog {
+    fn from_proto(proto: protos::audit::AuditLog) -> Result<Self, ProtoConversionError> {
+        Ok(AuditLog {
+            entries: proto
+                .get_entries()
+                .to_vec()
+                .into_iter()
+                .map(AuditEntry::from_proto)
+                .collect::<Result<Vec<AuditEntry>, ProtoConversionError>>()?,
+        })
+    }
+}
+
+impl FromNative<AuditLog> for protos::audit::AuditLog {
+    fn from_native(audit_log: AuditLog) -> Result<Self, ProtoConversionError> {
+        let mut proto = protos::audit::AuditLog::new();
+        proto.set_entries(RepeatedField::from_vec(
+            audit_log
+                .entries()
+                .to_vec()
+                .into_iter()
+                .map(AuditEntry::into_proto)
+                .collect::<Result<Vec<protos::audit::AuditEntry>, ProtoConversionError>>()?,
+        ));
+
+        Ok(proto)
+    }
+}
+
+impl FromBytes<AuditLog> for AuditLog {
+    fn from_bytes(bytes: &[u8]) -> Result<AuditLog, ProtoConversionError> {
+        let proto: protos::audit::AuditLog = Message::parse_from_bytes(bytes).map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get AuditLog from bytes".to_string(),
+            )
+        })?;
+        proto.into_native()
+    }
+}
+
+impl IntoBytes for AuditLog {
+    fn into_bytes(self) -> Result<Vec<u8>, ProtoConversionError> {
+        let proto = self.into_proto()?;
+        let bytes = proto.write_to_bytes().map_err(|_| {
+            ProtoConversionError::SerializationError(
+                "Unable to get bytes from AuditLog".to_string(),
+            )
+        })?;
+        Ok(bytes)
+    }
+}
+
+impl IntoProto<protos::audit::AuditLog> for AuditLog {}
+impl IntoNative<AuditLog> for protos::audit::AuditLog {}
+
+#[derive(Debug)]
+pub enum AuditLogBuildError {
+    MissingField(String),
+}
+
+impl StdError for AuditLogBuildError {}
+
+impl std::fmt::Display for AuditLogBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            AuditLogBuildError::MissingField(ref s) => write!(f, "MissingField: {}", s),
+        }
+    }
+}
+
+/// Builder used to create an AuditLog
+#[derive(Default, Clone)]
+pub struct AuditLogBuilder {
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLogBuilder {
+    pub fn new() -> Self {
+        AuditLogBuilder::default()
+    }
+
+    pub fn with_entries(mut self, entries: Vec<AuditEntry>) -> AuditLogBuilder {
+        self.entries = entries;
+        self
+    }
+
+    pub fn build(self) -> Result<AuditLog, AuditLogBuildError> {
+        let entries = self.entries;
+
+        Ok(AuditLog { entries })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1485,12 +2099,14 @@ mod tests {
             .with_name("Tests".to_string())
             .with_versions(vec![version.clone()])
             .with_owners(vec!["owner".to_string()])
+            .with_max_memory_pages(64)
             .build()
             .unwrap();
 
         assert_eq!(contract_registry.name(), "Tests");
         assert_eq!(contract_registry.versions(), [version]);
         assert_eq!(contract_registry.owners(), ["owner"]);
+        assert_eq!(contract_registry.max_memory_pages(), 64);
     }
 
     #[test]
@@ -1509,6 +2125,7 @@ mod tests {
             .with_name("Tests".to_string())
             .with_versions(vec![version.clone()])
             .with_owners(vec!["owner".to_string()])
+            .with_max_memory_pages(64)
             .build()
             .unwrap();
 
@@ -1518,6 +2135,33 @@ mod tests {
         assert_eq!(contract_registry, original);
     }
 
+    #[test]
+    // check that a paused version stays paused when converted to bytes and back
+    fn check_paused_version_bytes() {
+        let builder = VersionBuilder::new();
+        let version = builder
+            .with_version("0.0.0".to_string())
+            .with_contract_sha512("sha512".to_string())
+            .with_creator("The Creator".to_string())
+            .with_paused(true)
+            .build()
+            .unwrap();
+
+        let builder = ContractRegistryBuilder::new();
+        let original = builder
+            .with_name("Tests".to_string())
+            .with_versions(vec![version])
+            .with_owners(vec!["owner".to_string()])
+            .build()
+            .unwrap();
+
+        let bytes = original.clone().into_bytes().unwrap();
+
+        let contract_registry = ContractRegistry::from_bytes(&bytes).unwrap();
+        assert!(contract_registry.versions()[0].paused());
+        assert_eq!(contract_registry, original);
+    }
+
     #[test]
     // check that a contract registry can be converted into builder
     fn check_contract_registry_into_builder() {
@@ -1534,6 +2178,7 @@ mod tests {
             .with_name("Tests".to_string())
             .with_versions(vec![version.clone()])
             .with_owners(vec!["owner".to_string()])
+            .with_max_memory_pages(64)
             .build()
             .unwrap();
 
@@ -1542,6 +2187,7 @@ mod tests {
         assert_eq!(builder.name, Some("Tests".to_string()));
         assert_eq!(builder.versions, [version]);
         assert_eq!(builder.owners, ["owner"]);
+        assert_eq!(builder.max_memory_pages, 64);
     }
 
     #[test]
@@ -1861,6 +2507,48 @@ mod tests {
         assert_eq!(contract_list, original);
     }
 
+    #[test]
+    // check that a contract chunk is built correctly
+    fn check_contract_chunk() {
+        let builder = ContractChunkBuilder::new();
+        let contract_chunk = builder
+            .with_name("Tests".to_string())
+            .with_version("0.0.0".to_string())
+            .with_index(1)
+            .with_creator("The Creator".to_string())
+            .with_chunk(b"test_chunk".to_vec())
+            .build()
+            .unwrap();
+
+        assert_eq!(contract_chunk.name(), "Tests");
+        assert_eq!(contract_chunk.version(), "0.0.0");
+        assert_eq!(contract_chunk.index(), 1);
+        assert_eq!(contract_chunk.creator(), "The Creator");
+        assert_eq!(contract_chunk.chunk(), b"test_chunk");
+    }
+
+    #[test]
+    // check that a contract chunk list can be converted to bytes and back
+    fn check_contract_chunk_list_bytes() {
+        let builder = ContractChunkBuilder::new();
+        let contract_chunk = builder
+            .with_name("Tests".to_string())
+            .with_version("0.0.0".to_string())
+            .with_index(1)
+            .with_creator("The Creator".to_string())
+            .with_chunk(b"test_chunk".to_vec())
+            .build()
+            .unwrap();
+
+        let builder = ContractChunkListBuilder::new();
+        let original = builder.with_chunks(vec![contract_chunk]).build().unwrap();
+
+        let bytes = original.clone().into_bytes().unwrap();
+
+        let contract_chunk_list = ContractChunkList::from_bytes(&bytes).unwrap();
+        assert_eq!(contract_chunk_list, original);
+    }
+
     #[test]
     // check that a smart permission is built correctly
     fn check_smart_permission() {
@@ -1957,4 +2645,48 @@ mod tests {
         let smart_permission_list = SmartPermissionList::from_bytes(&bytes).unwrap();
         assert_eq!(smart_permission_list, original);
     }
+
+    #[test]
+    // check that an audit entry is built correctly
+    fn check_audit_entry() {
+        let builder = AuditEntryBuilder::new();
+        let audit_entry = builder
+            .with_signer("signer".to_string())
+            .with_action("create_namespace_registry".to_string())
+            .with_transaction_id("transaction".to_string())
+            .with_payload(b"payload".to_vec())
+            .with_block_num(3)
+            .with_timestamp(1600000000)
+            .build()
+            .unwrap();
+
+        assert_eq!(audit_entry.signer(), "signer");
+        assert_eq!(audit_entry.action(), "create_namespace_registry");
+        assert_eq!(audit_entry.transaction_id(), "transaction");
+        assert_eq!(audit_entry.payload(), b"payload");
+        assert_eq!(audit_entry.block_num(), 3);
+        assert_eq!(audit_entry.timestamp(), 1600000000);
+    }
+
+    #[test]
+    // check that an audit log can be converted to bytes and back
+    fn check_audit_log_bytes() {
+        let builder = AuditEntryBuilder::new();
+        let audit_entry = builder
+            .with_signer("signer".to_string())
+            .with_action("create_namespace_registry".to_string())
+            .with_transaction_id("transaction".to_string())
+            .with_payload(b"payload".to_vec())
+            .build()
+            .unwrap();
+
+        let builder = AuditLogBuilder::new();
+        let original = builder.with_entries(vec![audit_entry]).build().unwrap();
+
+        let bytes = original.clone().into_bytes().unwrap();
+
+        let audit_log = AuditLog::from_bytes(&bytes).unwrap();
+        assert_eq!(audit_log, original);
+        assert_eq!(audit_log.entries()[0].block_num(), 0);
+    }
 }
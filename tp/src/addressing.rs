@@ -20,14 +20,23 @@ use sawtooth_sdk::processor::handler::ApplyError;
 const NAMESPACE_REGISTRY_PREFIX: &str = "00ec00";
 
 /// The contract registry prefix for global state (00ec01)
-const CONTRACT_REGISTRY_PREFIX: &str = "00ec01";
+pub const CONTRACT_REGISTRY_PREFIX: &str = "00ec01";
 
 /// The contract prefix for global state (00ec02)
-const CONTRACT_PREFIX: &str = "00ec02";
+pub const CONTRACT_PREFIX: &str = "00ec02";
 
 /// The smart permission prefix for global state (00ec03)
 const SMART_PERMISSION_PREFIX: &str = "00ec03";
 
+/// The contract chunk prefix for global state (00ec04)
+const CONTRACT_CHUNK_PREFIX: &str = "00ec04";
+
+/// The audit log prefix for global state (00ec05)
+const AUDIT_LOG_PREFIX: &str = "00ec05";
+
+/// The block info prefix for global state (00b10c00)
+const BLOCK_INFO_PREFIX: &str = "00b10c00";
+
 const PIKE_AGENT_PREFIX: &str = "cad11d00";
 
 const PIKE_ORG_PREFIX: &str = "cad11d01";
@@ -64,6 +73,14 @@ pub fn make_contract_address(name: &str, version: &str) -> Result<String, ApplyE
     Ok(CONTRACT_PREFIX.to_string() + &hash(&(name.to_string() + "," + version), 64)?)
 }
 
+pub fn make_contract_chunk_address(
+    name: &str,
+    version: &str,
+    index: u32,
+) -> Result<String, ApplyError> {
+    Ok(CONTRACT_CHUNK_PREFIX.to_string() + &hash(&format!("{},{},{}", name, version, index), 64)?)
+}
+
 pub fn make_contract_registry_address(name: &str) -> Result<String, ApplyError> {
     Ok(CONTRACT_REGISTRY_PREFIX.to_string() + &hash(name, 64)?)
 }
@@ -81,6 +98,37 @@ pub fn make_namespace_registry_address(namespace: &str) -> Result<String, ApplyE
     Ok(NAMESPACE_REGISTRY_PREFIX.to_string() + &hash(prefix, 64)?)
 }
 
+/// Returns whether an address is that of a namespace registry or contract registry
+pub fn is_registry_address(address: &str) -> bool {
+    address.starts_with(NAMESPACE_REGISTRY_PREFIX) || address.starts_with(CONTRACT_REGISTRY_PREFIX)
+}
+
+/// Returns the state address of the audit log of a namespace registry or contract registry
+///
+/// # Arguments
+///
+/// * `registry_address` - the state address of the namespace registry or contract registry
+pub fn make_audit_log_address(registry_address: &str) -> Result<String, ApplyError> {
+    match registry_address.get(4..68) {
+        Some(suffix) if is_registry_address(registry_address) => {
+            Ok(AUDIT_LOG_PREFIX.to_string() + suffix)
+        }
+        _ => Err(ApplyError::InternalError(format!(
+            "Not the address of a registry: {}",
+            registry_address
+        ))),
+    }
+}
+
+/// Returns the state address of the info of a block recorded by the Block Info transaction family
+///
+/// # Arguments
+///
+/// * `block_num` - the number of the block
+pub fn make_block_info_address(block_num: u64) -> String {
+    format!("{}{:062x}", BLOCK_INFO_PREFIX, block_num)
+}
+
 /// Returns a state address for a smart permission
 ///
 /// # Arguments
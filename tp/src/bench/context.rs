@@ -0,0 +1,152 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use sawtooth_sdk::processor::handler::{ContextError, TransactionContext};
+
+/// The changes of the transactions of a batch, which are `None` for a deleted address
+pub type Changes = HashMap<String, Option<Vec<u8>>>;
+
+/// The context of a replayed transaction, which reads the state in memory and keeps its writes
+/// apart, so that they are only applied if its batch is valid
+///
+/// As with the validator, reads and writes are limited to the transaction's inputs and outputs,
+/// and events and receipt data are discarded.
+pub struct MemoryContext<'a> {
+    state: &'a HashMap<String, Vec<u8>>,
+    /// The changes of the earlier transactions of the batch
+    batch: &'a Changes,
+    inputs: &'a [String],
+    outputs: &'a [String],
+    changes: RefCell<Changes>,
+}
+
+impl<'a> MemoryContext<'a> {
+    pub fn new(
+        state: &'a HashMap<String, Vec<u8>>,
+        batch: &'a Changes,
+        inputs: &'a [String],
+        outputs: &'a [String],
+    ) -> MemoryContext<'a> {
+        MemoryContext {
+            state,
+            batch,
+            inputs,
+            outputs,
+            changes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Return the changes the transaction made
+    pub fn into_changes(self) -> Changes {
+        self.changes.into_inner()
+    }
+
+    fn get(&self, address: &str) -> Option<Vec<u8>> {
+        match self
+            .changes
+            .borrow()
+            .get(address)
+            .or_else(|| self.batch.get(address))
+        {
+            Some(value) => value.clone(),
+            None => self.state.get(address).cloned(),
+        }
+    }
+}
+
+impl<'a> TransactionContext for MemoryContext<'a> {
+    fn get_state_entries(
+        &self,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, ContextError> {
+        if !is_authorized(self.inputs, addresses) {
+            return Err(ContextError::AuthorizationError(format!(
+                "Tried to get unauthorized addresses: {:?}",
+                addresses
+            )));
+        }
+
+        Ok(addresses
+            .iter()
+            .filter_map(|address| match self.get(address) {
+                Some(value) if !value.is_empty() => Some((address.clone(), value)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn set_state_entries(&self, entries: Vec<(String, Vec<u8>)>) -> Result<(), ContextError> {
+        let addresses = entries
+            .iter()
+            .map(|(address, _)| address.clone())
+            .collect::<Vec<_>>();
+        if !is_authorized(self.outputs, &addresses) {
+            return Err(ContextError::AuthorizationError(format!(
+                "Tried to set unauthorized addresses: {:?}",
+                addresses
+            )));
+        }
+
+        let mut changes = self.changes.borrow_mut();
+        for (address, value) in entries {
+            changes.insert(address, Some(value));
+        }
+
+        Ok(())
+    }
+
+    fn delete_state_entries(&self, addresses: &[String]) -> Result<Vec<String>, ContextError> {
+        if !is_authorized(self.outputs, addresses) {
+            return Err(ContextError::AuthorizationError(format!(
+                "Tried to delete unauthorized addresses: {:?}",
+                addresses
+            )));
+        }
+
+        let deleted = addresses
+            .iter()
+            .filter(|address| self.get(address).is_some())
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut changes = self.changes.borrow_mut();
+        for address in &deleted {
+            changes.insert(address.clone(), None);
+        }
+
+        Ok(deleted)
+    }
+
+    fn add_receipt_data(&self, _data: &[u8]) -> Result<(), ContextError> {
+        Ok(())
+    }
+
+    fn add_event(
+        &self,
+        _event_type: String,
+        _attributes: Vec<(String, String)>,
+        _data: &[u8],
+    ) -> Result<(), ContextError> {
+        Ok(())
+    }
+}
+
+/// Whether every address is under one of the transaction's inputs or outputs
+fn is_authorized(allowed: &[String], addresses: &[String]) -> bool {
+    addresses
+        .iter()
+        .all(|address| allowed.iter().any(|prefix| address.starts_with(prefix)))
+}
@@ -0,0 +1,268 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replays captured batches against state kept in memory, to measure the Sabre transaction
+//! handler without a validator
+//!
+//! The batches are read from a serialized BatchList, such as the one the CLI writes with
+//! `--output`. State starts empty, so the batches must create the registries and contracts their
+//! transactions use. As on a validator, the transactions of a batch are applied in order, and the
+//! changes of a batch are only kept if all of its transactions are valid. Transactions of other
+//! families are skipped.
+
+pub(crate) mod context;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use protobuf::Message as _;
+use sabre_sdk::protocol::payload::Action;
+use sawtooth_sdk::messages::batch::BatchList;
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::messages::transaction::TransactionHeader;
+use sawtooth_sdk::processor::handler::TransactionHandler;
+
+use crate::handler::{contract_of, SabreTransactionHandler};
+use crate::metrics::{action_name, Metrics};
+use crate::payload::SabreRequestPayload;
+
+use self::context::{Changes, MemoryContext};
+
+/// The percentiles of the latencies of transactions reported
+const PERCENTILES: &[f64] = &[50.0, 90.0, 99.0];
+
+/// The transactions of a contract, or of an action which does not execute a contract, replayed
+#[derive(Default)]
+struct Transactions {
+    /// The time each transaction took to apply
+    latencies: Vec<Duration>,
+    /// The number of transactions which were rejected
+    rejected: usize,
+}
+
+impl Transactions {
+    /// The number of transactions applied per second of the time taken to apply them
+    fn throughput(&self) -> f64 {
+        let total = self.latencies.iter().sum::<Duration>().as_secs_f64();
+        if total > 0.0 {
+            self.latencies.len() as f64 / total
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Return the latency at the percentile, by the nearest-rank method, of latencies sorted from
+/// the shortest
+fn latency_at(sorted: &[Duration], percentile: f64) -> Duration {
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1) - 1]
+}
+
+/// The results of replaying batches
+pub struct Report {
+    batches: usize,
+    invalid_batches: usize,
+    /// The number of transactions of other families, which were not applied
+    skipped: usize,
+    elapsed: Duration,
+    /// The transactions replayed, by the name and version of the contract they execute, or by
+    /// the name of their action
+    transactions: BTreeMap<String, Transactions>,
+    contract_cache_hits: u64,
+    contract_cache_misses: u64,
+}
+
+/// Apply the transactions of the serialized BatchList with the handler, and report how long they
+/// took
+///
+/// The handler is given its own metrics, from which the contract cache statistics are reported.
+pub fn replay(handler: SabreTransactionHandler, batch_list: &[u8]) -> Result<Report, String> {
+    let batch_list = BatchList::parse_from_bytes(batch_list)
+        .map_err(|err| format!("Unable to read BatchList: {}", err))?;
+
+    let metrics = Arc::new(Metrics::new());
+    let handler = handler.with_metrics(metrics.clone());
+    let family_name = handler.family_name();
+
+    let mut state = HashMap::new();
+    let mut transactions = BTreeMap::<String, Transactions>::new();
+    let mut invalid_batches = 0;
+    let mut skipped = 0;
+
+    let started = Instant::now();
+    for batch in batch_list.get_batches() {
+        let mut batch_changes = Changes::new();
+        let mut valid = true;
+
+        for transaction in batch.get_transactions() {
+            let header =
+                TransactionHeader::parse_from_bytes(transaction.get_header()).map_err(|err| {
+                    format!(
+                        "Unable to read header of transaction {}: {}",
+                        transaction.get_header_signature(),
+                        err
+                    )
+                })?;
+            if header.get_family_name() != family_name {
+                skipped += 1;
+                continue;
+            }
+
+            let label = match SabreRequestPayload::new(transaction.get_payload()) {
+                Ok(Some(payload)) => transaction_label(&payload),
+                _ => "invalid_payload".into(),
+            };
+
+            let mut request = TpProcessRequest::new();
+            request.set_payload(transaction.get_payload().to_vec());
+            request.set_signature(transaction.get_header_signature().into());
+            request.set_header(header);
+
+            let mut context = MemoryContext::new(
+                &state,
+                &batch_changes,
+                request.get_header().get_inputs(),
+                request.get_header().get_outputs(),
+            );
+            let applied = Instant::now();
+            let result = handler.apply(&request, &mut context);
+            let latency = applied.elapsed();
+            let changes = context.into_changes();
+
+            let replayed = transactions.entry(label).or_default();
+            replayed.latencies.push(latency);
+            if result.is_err() {
+                replayed.rejected += 1;
+                valid = false;
+                break;
+            }
+            batch_changes.extend(changes);
+        }
+
+        if valid {
+            for (address, value) in batch_changes {
+                match value {
+                    Some(value) => state.insert(address, value),
+                    None => state.remove(&address),
+                };
+            }
+        } else {
+            invalid_batches += 1;
+        }
+    }
+    let elapsed = started.elapsed();
+
+    Ok(Report {
+        batches: batch_list.get_batches().len(),
+        invalid_batches,
+        skipped,
+        elapsed,
+        transactions,
+        contract_cache_hits: metrics.cache_hits(),
+        contract_cache_misses: metrics.cache_misses(),
+    })
+}
+
+/// Return the name and version of the contract a transaction executes, or the name of its action
+fn transaction_label(payload: &SabreRequestPayload) -> String {
+    let action = payload.get_action();
+    match (&action, contract_of(&action)) {
+        (Action::ExecuteContract(_), Some((name, version))) => format!("{} {}", name, version),
+        _ => action_name(&action).into(),
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let count = self
+            .transactions
+            .values()
+            .map(|replayed| replayed.latencies.len())
+            .sum::<usize>();
+        let seconds = self.elapsed.as_secs_f64();
+        writeln!(
+            f,
+            "Replayed {} transactions of {} batches in {:.3} s ({:.1} transactions/s)",
+            count,
+            self.batches,
+            seconds,
+            if seconds > 0.0 {
+                count as f64 / seconds
+            } else {
+                0.0
+            }
+        )?;
+        writeln!(
+            f,
+            "{} invalid batches; {} transactions of other families skipped",
+            self.invalid_batches, self.skipped
+        )?;
+        writeln!(f)?;
+
+        let width = self
+            .transactions
+            .keys()
+            .map(|label| label.len())
+            .chain(std::iter::once("transactions".len()))
+            .max()
+            .unwrap_or(0);
+        write!(
+            f,
+            "{:<width$} {:>8} {:>8} {:>10}",
+            "transactions",
+            "count",
+            "rejected",
+            "txns/s",
+            width = width
+        )?;
+        for percentile in PERCENTILES {
+            write!(f, " {:>9}", format!("p{} ms", percentile))?;
+        }
+        writeln!(f, " {:>9}", "max ms")?;
+
+        for (label, replayed) in &self.transactions {
+            let mut sorted = replayed.latencies.clone();
+            sorted.sort();
+            write!(
+                f,
+                "{:<width$} {:>8} {:>8} {:>10.1}",
+                label,
+                sorted.len(),
+                replayed.rejected,
+                replayed.throughput(),
+                width = width
+            )?;
+            for percentile in PERCENTILES {
+                write!(f, " {:>9.3}", millis(latency_at(&sorted, *percentile)))?;
+            }
+            writeln!(f, " {:>9.3}", millis(sorted[sorted.len() - 1]))?;
+        }
+        writeln!(f)?;
+
+        writeln!(
+            f,
+            "contract cache: {} hits, {} misses",
+            self.contract_cache_hits, self.contract_cache_misses
+        )?;
+
+        Ok(())
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,223 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loads the configuration file of the transaction processor
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use log::LevelFilter;
+
+/// The configuration file loaded when `--config` is not given, if it exists
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/sabre/sabre-tp.toml";
+
+/// Default values for command line arguments, loaded from a TOML file
+///
+/// Each value has the name of its command line argument, with underscores instead of hyphens,
+/// and values given on the command line always take precedence over values from the
+/// configuration file.
+#[derive(Clone, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TpConfig {
+    connect: Option<String>,
+    /// One of off, error, warn, info, debug or trace, used unless `--verbose` is given
+    log_level: Option<String>,
+    log_format: Option<String>,
+    contract_log_level: Option<String>,
+    contract_cache_size: Option<usize>,
+    module_cache_dir: Option<String>,
+    max_instructions: Option<u64>,
+    max_memory_pages: Option<u32>,
+    max_call_depth: Option<u32>,
+    max_stack_depth: Option<u32>,
+    max_stack_size: Option<usize>,
+    max_contract_size: Option<usize>,
+    execution_timeout: Option<u64>,
+    execution_timeout_error: Option<String>,
+    workers: Option<usize>,
+    drain_timeout: Option<u64>,
+    metrics_address: Option<String>,
+    health_address: Option<String>,
+}
+
+impl TpConfig {
+    /// Load the configuration file
+    ///
+    /// If `path` is None, the configuration is loaded from `DEFAULT_CONFIG_PATH`; if that file
+    /// does not exist, an empty configuration is returned. If `path` is given, the file must
+    /// exist.
+    pub fn load(path: Option<&str>) -> Result<TpConfig, String> {
+        let path = match path {
+            Some(path) => Path::new(path),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Path::new(DEFAULT_CONFIG_PATH),
+            None => return Ok(TpConfig::default()),
+        };
+
+        let contents = fs::read_to_string(path).map_err(|err| {
+            format!(
+                "Could not load configuration file \"{}\": {}",
+                path.display(),
+                err
+            )
+        })?;
+
+        let config = toml::from_str::<TpConfig>(&contents)
+            .map_err(|err| err.to_string())
+            .and_then(|config| config.check().map(|_| config))
+            .map_err(|err| {
+                format!(
+                    "Malformed configuration file \"{}\": {}",
+                    path.display(),
+                    err
+                )
+            })?;
+
+        Ok(config)
+    }
+
+    /// Check the values which the command line restricts to a set of values or a range
+    fn check(&self) -> Result<(), String> {
+        for (name, level) in &[
+            ("log_level", &self.log_level),
+            ("contract_log_level", &self.contract_log_level),
+        ] {
+            if let Some(level) = level {
+                LevelFilter::from_str(level).map_err(|_| {
+                    format!(
+                        "{} must be one of off, error, warn, info, debug or trace, not \"{}\"",
+                        name, level
+                    )
+                })?;
+            }
+        }
+        check_one_of("log_format", &self.log_format, &["text", "json"])?;
+        check_one_of(
+            "execution_timeout_error",
+            &self.execution_timeout_error,
+            &["invalid", "internal"],
+        )?;
+        if self.workers == Some(0) {
+            return Err("workers must be at least 1".into());
+        }
+
+        Ok(())
+    }
+
+    /// The endpoint of the validator
+    pub fn connect(&self) -> Option<&str> {
+        self.connect.as_deref()
+    }
+
+    /// The level at which the transaction processor's records are written
+    pub fn log_level(&self) -> Option<LevelFilter> {
+        self.log_level
+            .as_deref()
+            .and_then(|level| level.parse().ok())
+    }
+
+    /// The format of log records: text or json
+    pub fn log_format(&self) -> Option<&str> {
+        self.log_format.as_deref()
+    }
+
+    /// The level at which the messages contracts log are written
+    pub fn contract_log_level(&self) -> Option<LevelFilter> {
+        self.contract_log_level
+            .as_deref()
+            .and_then(|level| level.parse().ok())
+    }
+
+    /// The number of parsed contracts, and of parsed smart permissions, kept in memory
+    pub fn contract_cache_size(&self) -> Option<usize> {
+        self.contract_cache_size
+    }
+
+    /// The directory in which loaded contracts are stored
+    pub fn module_cache_dir(&self) -> Option<&str> {
+        self.module_cache_dir.as_deref()
+    }
+
+    /// The number of instructions each execution of a contract may run
+    pub fn max_instructions(&self) -> Option<u64> {
+        self.max_instructions
+    }
+
+    /// The number of 64KiB pages of memory a contract may use
+    pub fn max_memory_pages(&self) -> Option<u32> {
+        self.max_memory_pages
+    }
+
+    /// The depth of nested contract invocations a contract may make
+    pub fn max_call_depth(&self) -> Option<u32> {
+        self.max_call_depth
+    }
+
+    /// The number of nested function calls a contract interpreted by wasmi may make
+    pub fn max_stack_depth(&self) -> Option<u32> {
+        self.max_stack_depth
+    }
+
+    /// The number of bytes of stack a contract may use
+    pub fn max_stack_size(&self) -> Option<usize> {
+        self.max_stack_size
+    }
+
+    /// The number of bytes a contract may have
+    pub fn max_contract_size(&self) -> Option<usize> {
+        self.max_contract_size
+    }
+
+    /// The number of milliseconds each execution of a contract may take
+    pub fn execution_timeout(&self) -> Option<u64> {
+        self.execution_timeout
+    }
+
+    /// How a transaction whose contract times out is rejected: invalid or internal
+    pub fn execution_timeout_error(&self) -> Option<&str> {
+        self.execution_timeout_error.as_deref()
+    }
+
+    /// The number of transactions processed concurrently
+    pub fn workers(&self) -> Option<usize> {
+        self.workers
+    }
+
+    /// The number of milliseconds the transaction processor may take to shut down
+    pub fn drain_timeout(&self) -> Option<u64> {
+        self.drain_timeout
+    }
+
+    /// The address at which metrics are served
+    pub fn metrics_address(&self) -> Option<&str> {
+        self.metrics_address.as_deref()
+    }
+
+    /// The address at which the health of the transaction processor is served
+    pub fn health_address(&self) -> Option<&str> {
+        self.health_address.as_deref()
+    }
+}
+
+fn check_one_of(name: &str, value: &Option<String>, allowed: &[&str]) -> Result<(), String> {
+    match value {
+        Some(value) if !allowed.contains(&value.as_str()) => Err(format!(
+            "{} must be one of {}, not \"{}\"",
+            name,
+            allowed.join(", "),
+            value
+        )),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,294 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
+
+/// The first line of every entry, which changes whenever the layout of entries does
+const FORMAT: &str = "sabre-module-cache 1";
+
+/// The number of lines of the header of an entry, which precedes its artifact
+const HEADER_LINES: usize = 6;
+
+/// Distinguishes the temporary files written at the same time by the workers of a process
+static NEXT_TEMPORARY: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory of the artifacts of loaded contracts, from which a restarted transaction
+/// processor loads its contracts again without parsing, validating and compiling them
+///
+/// Entries are keyed, as the modules of a `ModuleCache` are, by the sha512 of the contract and
+/// the limits it was loaded with, and by the runtime which loaded it. Each entry records the
+/// sha512 of the contract it was stored for and of its artifact, and the version of the
+/// transaction processor which stored it; an entry whose records do not match is removed rather
+/// than used. The cache may be shared by the workers of a transaction processor, and the
+/// directory emptied whenever the transaction processor is stopped.
+///
+/// The records only detect damaged entries: an artifact is executed as it is stored, without
+/// being derived from the contract in state again, so anyone who may write the directory may make
+/// the transaction processor execute any code. The directory must therefore be owned by the user
+/// of the transaction processor and writable by no one else, which `new` checks.
+pub struct DiskModuleCache {
+    directory: PathBuf,
+}
+
+impl DiskModuleCache {
+    /// Use `directory` as the cache, creating it, accessible only by the user of the transaction
+    /// processor, if it does not exist
+    ///
+    /// The directory is rejected if it is owned by another user, or if its group or other users
+    /// may write it.
+    pub fn new(directory: &Path) -> Result<DiskModuleCache, String> {
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(directory)
+            .map_err(|err| {
+                format!(
+                    "Unable to create module cache directory {}: {}",
+                    directory.display(),
+                    err
+                )
+            })?;
+
+        let metadata = fs::metadata(directory).map_err(|err| {
+            format!(
+                "Unable to read module cache directory {}: {}",
+                directory.display(),
+                err
+            )
+        })?;
+        // Safety: geteuid cannot fail and has no side effects
+        if metadata.uid() != unsafe { libc::geteuid() } {
+            return Err(format!(
+                "Module cache directory {} is owned by another user",
+                directory.display()
+            ));
+        }
+        if metadata.mode() & 0o022 != 0 {
+            return Err(format!(
+                "Module cache directory {} may be written by other users",
+                directory.display()
+            ));
+        }
+
+        Ok(DiskModuleCache {
+            directory: directory.to_path_buf(),
+        })
+    }
+
+    /// Return the directory of the cache
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Return the artifact the runtime stored for the module with the given key, if it was
+    /// stored for the contract with the given sha512 and is intact
+    pub fn get(&self, runtime: &str, key: &str, contract_sha512: &str) -> Option<Vec<u8>> {
+        let path = self.path(runtime, key);
+        let entry = match fs::read(&path) {
+            Ok(entry) => entry,
+            Err(err) if err.kind() == ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!(
+                    "Unable to read module cache entry {}: {}",
+                    path.display(),
+                    err
+                );
+                return None;
+            }
+        };
+
+        match check_entry(&entry, runtime, key, contract_sha512) {
+            Ok(artifact) => {
+                debug!("Using module {} from the module cache directory", key);
+                Some(artifact.to_vec())
+            }
+            Err(err) => {
+                warn!("Removing module cache entry {}: {}", path.display(), err);
+                self.remove(&path);
+                None
+            }
+        }
+    }
+
+    /// Store the artifact of the module with the given key, loaded by the runtime from the
+    /// contract with the given sha512
+    ///
+    /// The entry is written to a temporary file which then replaces any previous entry, so that
+    /// an entry is never read while it is written. A failure to store an entry is only logged,
+    /// since the module is loaded again when it is next used.
+    pub fn put(&self, runtime: &str, key: &str, contract_sha512: &str, artifact: &[u8]) {
+        let mut sha = Sha512::new();
+        sha.input(artifact);
+        let header = [
+            FORMAT,
+            env!("CARGO_PKG_VERSION"),
+            runtime,
+            key,
+            &contract_sha512.to_lowercase(),
+            &sha.result_str(),
+        ]
+        .join("\n");
+        let mut entry = Vec::with_capacity(header.len() + 1 + artifact.len());
+        entry.extend_from_slice(header.as_bytes());
+        entry.push(b'\n');
+        entry.extend_from_slice(artifact);
+
+        let path = self.path(runtime, key);
+        let temporary = path.with_extension(format!(
+            "{}.{}.tmp",
+            process::id(),
+            NEXT_TEMPORARY.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&temporary)
+            .and_then(|mut file| file.write_all(&entry))
+            .and_then(|_| fs::rename(&temporary, &path));
+        match result {
+            Ok(()) => debug!("Stored module {} in the module cache directory", key),
+            Err(err) => {
+                warn!(
+                    "Unable to store module cache entry {}: {}",
+                    path.display(),
+                    err
+                );
+                self.remove(&temporary);
+            }
+        }
+    }
+
+    /// Remove the entries stored for the contract with the given sha512, whichever limits and
+    /// runtime they were loaded with
+    pub fn invalidate(&self, contract_sha512: &str) {
+        let prefix = format!("{}_", contract_sha512.to_lowercase());
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!(
+                    "Unable to read module cache directory {}: {}",
+                    self.directory.display(),
+                    err
+                );
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                self.remove(&entry.path());
+                debug!("Invalidated module cache entry {}", entry.path().display());
+            }
+        }
+    }
+
+    /// Return the path of the entry of a module, which starts with the contract's sha512 since
+    /// keys start with it
+    fn path(&self, runtime: &str, key: &str) -> PathBuf {
+        self.directory
+            .join(format!("{}.{}", key.replace(':', "_"), runtime))
+    }
+
+    fn remove(&self, path: &Path) {
+        if let Err(err) = fs::remove_file(path) {
+            if err.kind() != ErrorKind::NotFound {
+                warn!(
+                    "Unable to remove module cache entry {}: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Return the artifact of an entry, if its header matches the module it is read for and the
+/// artifact matches its sha512
+fn check_entry<'a>(
+    entry: &'a [u8],
+    runtime: &str,
+    key: &str,
+    contract_sha512: &str,
+) -> Result<&'a [u8], String> {
+    let mut header = Vec::with_capacity(HEADER_LINES);
+    let mut artifact = entry;
+    for _ in 0..HEADER_LINES {
+        let end = artifact
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .ok_or_else(|| "Entry is truncated".to_string())?;
+        header.push(str::from_utf8(&artifact[..end]).map_err(|_| "Header is not UTF-8")?);
+        artifact = &artifact[end + 1..];
+    }
+
+    if header[0] != FORMAT {
+        return Err(format!("Entry has format {:?}", header[0]));
+    }
+    if header[1] != env!("CARGO_PKG_VERSION") {
+        return Err(format!(
+            "Entry was stored by version {} of the transaction processor",
+            header[1]
+        ));
+    }
+    if header[2] != runtime || header[3] != key {
+        return Err(format!("Entry is of module {} of {}", header[3], header[2]));
+    }
+    if !header[4].eq_ignore_ascii_case(contract_sha512) {
+        return Err(format!(
+            "Entry was stored for contract {}, not {}",
+            header[4], contract_sha512
+        ));
+    }
+    let mut sha = Sha512::new();
+    sha.input(artifact);
+    if sha.result_str() != header[5] {
+        return Err("Artifact does not match its sha512".into());
+    }
+
+    Ok(artifact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn directory_must_not_be_writable_by_others() {
+        let directory =
+            std::env::temp_dir().join(format!("sabre-module-cache-test-{}", process::id()));
+        let cache = DiskModuleCache::new(&directory.join("cache"));
+        assert!(cache.is_ok(), "{:?}", cache.err());
+        let mode = fs::metadata(directory.join("cache")).unwrap().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        fs::set_permissions(&directory, fs::Permissions::from_mode(0o777)).unwrap();
+        let result = DiskModuleCache::new(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+        match result {
+            Err(err) => assert!(err.contains("may be written by other users"), "{}", err),
+            Ok(_) => panic!("Module cache directory writable by others was used"),
+        }
+    }
+}
@@ -0,0 +1,481 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Drives the parts of the handler which read untrusted bytes with arbitrary inputs, for the
+//! cargo-fuzz targets in `tp/fuzz` and for the `fuzz-smoke` subcommand
+//!
+//! Each target takes the bytes the fuzzer generates and must never panic, whatever they are:
+//!
+//! * `payload` applies them as the payload of a transaction
+//! * `state` stores them in the state entries a contract execution and a contract creation
+//!   read, as length-prefixed chunks
+//! * `module` validates and executes them as a contract
+//! * `externals` executes a contract which calls the host functions with the arguments they
+//!   encode, against a memory they initialize
+//!
+//! `smoke` runs every target over the inputs of a corpus, so that CI can check the corpus
+//! without a fuzzer.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use parity_wasm::elements::{
+    CodeSection, DataSection, DataSegment, ExportEntry, ExportSection, External, Func, FuncBody,
+    FunctionSection, FunctionType, ImportEntry, ImportSection, InitExpr, Instruction, Instructions,
+    Internal, MemoryType, Module, Section, Type, TypeSection, ValueType,
+};
+use protobuf::RepeatedField;
+use sabre_sdk::protocol::payload::{
+    ActionBuildError, CreateContractActionBuilder, ExecuteContractActionBuilder,
+    SabrePayloadBuilder,
+};
+use sabre_sdk::protocol::{
+    ADMINISTRATORS_SETTING_ADDRESS, AUDIT_SETTING_ADDRESS, BLOCK_INFO_CONFIG_ADDRESS,
+    FLOAT_POLICY_SETTING_ADDRESS, MAX_CONTRACT_SIZE_SETTING_ADDRESS,
+    WASM_PROTOCOL_VERSION_SETTING_ADDRESS,
+};
+use sabre_sdk::protos::IntoBytes;
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::messages::transaction::TransactionHeader;
+use sawtooth_sdk::processor::handler::TransactionHandler;
+
+use crate::addressing::{
+    make_contract_address, make_contract_registry_address, make_namespace_registry_address,
+};
+use crate::admin::AllowAllAdminPermission;
+use crate::bench::context::{Changes, MemoryContext};
+use crate::handler::SabreTransactionHandler;
+use crate::wasm_executor::float_policy::FloatPolicy;
+use crate::wasm_executor::module_cache::ModuleCache;
+use crate::wasm_executor::proposals::WasmProtocolVersion;
+use crate::wasm_executor::validation;
+use crate::wasm_executor::wasm_externals::HOST_FUNCTIONS;
+use crate::wasm_executor::wasm_module::{self, WasmModule};
+use crate::wasm_executor::{ExecutionLimits, Transaction};
+
+/// The instructions each contract a target executes may run, so that inputs which loop are
+/// stopped quickly
+const MAX_INSTRUCTIONS: u64 = 1_000_000;
+
+/// The 64KiB pages of memory each contract a target executes may use
+const MAX_MEMORY_PAGES: u32 = 16;
+
+/// The public key which signs the transactions the targets apply
+const SIGNER: &str = "0283a0a7b1637599d9c2d67ac7e2411b3e2ba0e4fd9fd6e412572ad2f9ab23e832";
+
+/// The name and version of the contract of the state target, and its namespace
+const CONTRACT_NAME: &str = "fuzz";
+const CONTRACT_VERSION: &str = "1.0";
+const NAMESPACE: &str = "abcdef";
+
+/// The smallest valid WebAssembly module, which the state target's contract creation creates
+const EMPTY_MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+/// A fuzz target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Payload,
+    State,
+    Module,
+    Externals,
+}
+
+/// Every fuzz target, in the order `smoke` runs them
+pub const TARGETS: &[Target] = &[
+    Target::Payload,
+    Target::State,
+    Target::Module,
+    Target::Externals,
+];
+
+impl Target {
+    /// The name of the target, which is also that of its cargo-fuzz target and of its directory
+    /// in a corpus
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::Payload => "payload",
+            Target::State => "state",
+            Target::Module => "module",
+            Target::Externals => "externals",
+        }
+    }
+
+    /// Run the target with one input
+    pub fn run(&self, data: &[u8]) {
+        match self {
+            Target::Payload => payload(data),
+            Target::State => state(data),
+            Target::Module => module(data),
+            Target::Externals => externals(data),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Target, String> {
+        TARGETS
+            .iter()
+            .find(|target| target.name() == name)
+            .copied()
+            .ok_or_else(|| format!("Unknown fuzz target: {}", name))
+    }
+}
+
+/// Apply the bytes as the payload of a transaction which may read and write all of state,
+/// starting from empty state
+pub fn payload(data: &[u8]) {
+    apply(&HashMap::new(), data);
+}
+
+/// Store the bytes in the state entries which executing and creating a contract read, and
+/// apply both transactions
+///
+/// The bytes are read as chunks of a little-endian `u16` length followed by that many bytes,
+/// stored in turn at the contract, its contract registry and namespace registry, and the
+/// settings and block info the handler reads.
+pub fn state(data: &[u8]) {
+    let addresses = [
+        make_contract_address(CONTRACT_NAME, CONTRACT_VERSION),
+        make_contract_registry_address(CONTRACT_NAME),
+        make_namespace_registry_address(NAMESPACE),
+    ];
+    let settings = [
+        ADMINISTRATORS_SETTING_ADDRESS,
+        FLOAT_POLICY_SETTING_ADDRESS,
+        MAX_CONTRACT_SIZE_SETTING_ADDRESS,
+        WASM_PROTOCOL_VERSION_SETTING_ADDRESS,
+        AUDIT_SETTING_ADDRESS,
+        BLOCK_INFO_CONFIG_ADDRESS,
+    ];
+    let addresses = addresses
+        .iter()
+        .filter_map(|address| address.as_ref().ok().cloned())
+        .chain(settings.iter().map(|address| address.to_string()));
+
+    let mut input = Input::new(data);
+    let mut state = HashMap::new();
+    for address in addresses {
+        let length = match input.u16() {
+            Some(length) => length as usize,
+            None => break,
+        };
+        state.insert(address, input.take(length).to_vec());
+    }
+
+    for payload in &[execute_contract_payload(), create_contract_payload()] {
+        apply(&state, payload);
+    }
+}
+
+/// Validate the bytes as a new contract, and load and execute them as a contract under each
+/// wasm protocol version
+pub fn module(data: &[u8]) {
+    let _ = validation::validate_contract(data, data.len(), WasmProtocolVersion::V2);
+    for version in &[WasmProtocolVersion::V1, WasmProtocolVersion::V2] {
+        execute(data, *version, Vec::new());
+    }
+}
+
+/// Execute a contract which calls the host functions with the arguments the bytes encode
+///
+/// The bytes start with a little-endian `u16` length and that many bytes, which initialize the
+/// contract's memory from address 0 and are its payload. Each call follows: a byte selecting
+/// the host function, then its arguments as little-endian `i32`s, which are often pointers and
+/// lengths into the memory. The calls end where the bytes do.
+pub fn externals(data: &[u8]) {
+    let mut input = Input::new(data);
+    let length = input.u16().unwrap_or(0) as usize;
+    let memory = input.take(length).to_vec();
+
+    let mut calls = Vec::new();
+    while let Some(selector) = input.u8() {
+        let index = selector as usize % HOST_FUNCTIONS.len();
+        let (_, _, params, result) = HOST_FUNCTIONS[index];
+        let args = params
+            .iter()
+            .map(|_| input.i32())
+            .collect::<Option<Vec<_>>>();
+        match args {
+            Some(args) => {
+                calls.extend(args.into_iter().map(Instruction::I32Const));
+                calls.push(Instruction::Call(index as u32));
+                if result.is_some() {
+                    calls.push(Instruction::Drop);
+                }
+            }
+            None => break,
+        }
+    }
+
+    if let Ok(wasm) = parity_wasm::serialize(host_calls_module(&memory, calls)) {
+        execute(&wasm, WasmProtocolVersion::V1, memory);
+    }
+}
+
+/// Run every target over the inputs in the directory of the corpus named after it, returning
+/// the number of inputs run or the first input a target panicked on
+pub fn smoke(corpus: &Path) -> Result<usize, String> {
+    let mut count = 0;
+    for target in TARGETS {
+        let dir = corpus.join(target.name());
+        let mut paths = fs::read_dir(&dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|err| format!("Unable to read corpus {}: {}", dir.display(), err))?;
+        paths.sort();
+
+        for path in paths {
+            let data = fs::read(&path)
+                .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+            panic::catch_unwind(AssertUnwindSafe(|| target.run(&data)))
+                .map_err(|_| format!("Fuzz target {} panicked on {}", target, path.display()))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Apply a transaction with the payload to the state, with a handler which lets anyone
+/// administer Sabre
+fn apply(state: &HashMap<String, Vec<u8>>, payload: &[u8]) {
+    let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission))
+        .with_max_instructions(MAX_INSTRUCTIONS)
+        .with_max_memory_pages(MAX_MEMORY_PAGES);
+    let handler = match handler {
+        Ok(handler) => handler,
+        Err(_) => return,
+    };
+
+    let mut header = TransactionHeader::new();
+    header.set_signer_public_key(SIGNER.into());
+    header.set_inputs(RepeatedField::from_vec(vec!["".into()]));
+    header.set_outputs(RepeatedField::from_vec(vec!["".into()]));
+    let mut request = TpProcessRequest::new();
+    request.set_payload(payload.to_vec());
+    request.set_signature("fuzz".into());
+    request.set_header(header);
+
+    let batch = Changes::new();
+    let mut context = MemoryContext::new(
+        state,
+        &batch,
+        request.get_header().get_inputs(),
+        request.get_header().get_outputs(),
+    );
+    let _ = handler.apply(&request, &mut context);
+}
+
+/// Load the contract and execute it with the payload against empty state, if it loads
+fn execute(wasm: &[u8], wasm_protocol_version: WasmProtocolVersion, payload: Vec<u8>) {
+    let limits = ExecutionLimits {
+        max_instructions: MAX_INSTRUCTIONS,
+        max_memory_pages: MAX_MEMORY_PAGES,
+        deadline: None,
+        max_call_depth: 2,
+        max_stack_depth: 1024,
+        max_stack_size: 64 * 1024,
+        float_policy: FloatPolicy::Canonicalize,
+        wasm_protocol_version,
+    };
+    let module = match wasm_module::load(
+        wasm,
+        limits.max_memory_pages,
+        limits.float_policy,
+        wasm_protocol_version,
+    ) {
+        Ok(module) => module,
+        Err(_) => return,
+    };
+
+    let transaction = Transaction {
+        signer: SIGNER.into(),
+        signature: "fuzz".into(),
+        inputs: vec![NAMESPACE.into()],
+        outputs: vec![NAMESPACE.into()],
+    };
+    let state = HashMap::new();
+    let batch = Changes::new();
+    let mut context = MemoryContext::new(&state, &batch, &transaction.inputs, &transaction.outputs);
+    let contract_cache = Mutex::new(ModuleCache::new(1));
+    let smart_permission_cache = Mutex::new(ModuleCache::new(1));
+    let _ = WasmModule::new(
+        &module,
+        &mut context,
+        &transaction,
+        limits,
+        &contract_cache,
+        &smart_permission_cache,
+    )
+    .entrypoint(payload);
+}
+
+/// Return a contract importing every host function and the `env` memory, whose entrypoint runs
+/// the calls and returns 0, and whose memory starts with the bytes
+fn host_calls_module(memory: &[u8], mut calls: Vec<Instruction>) -> Module {
+    let mut types = HOST_FUNCTIONS
+        .iter()
+        .map(|(_, _, params, result)| {
+            Type::Function(FunctionType::new(
+                params.iter().copied().map(element_type).collect(),
+                result.iter().copied().map(element_type).collect(),
+            ))
+        })
+        .collect::<Vec<_>>();
+    let entrypoint_type = types.len() as u32;
+    types.push(Type::Function(FunctionType::new(
+        vec![ValueType::I32, ValueType::I32, ValueType::I32],
+        vec![ValueType::I32],
+    )));
+
+    let mut imports = HOST_FUNCTIONS
+        .iter()
+        .enumerate()
+        .map(|(index, (name, ..))| {
+            ImportEntry::new(
+                "env".into(),
+                name.to_string(),
+                External::Function(index as u32),
+            )
+        })
+        .collect::<Vec<_>>();
+    imports.push(ImportEntry::new(
+        "env".into(),
+        "memory".into(),
+        External::Memory(MemoryType::new(1, None)),
+    ));
+
+    calls.extend(vec![Instruction::I32Const(0), Instruction::End]);
+    Module::new(vec![
+        Section::Type(TypeSection::with_types(types)),
+        Section::Import(ImportSection::with_entries(imports)),
+        Section::Function(FunctionSection::with_entries(vec![Func::new(
+            entrypoint_type,
+        )])),
+        Section::Export(ExportSection::with_entries(vec![ExportEntry::new(
+            "entrypoint".into(),
+            Internal::Function(HOST_FUNCTIONS.len() as u32),
+        )])),
+        Section::Code(CodeSection::with_bodies(vec![FuncBody::new(
+            vec![],
+            Instructions::new(calls),
+        )])),
+        Section::Data(DataSection::with_entries(vec![DataSegment::new(
+            0,
+            Some(InitExpr::new(vec![
+                Instruction::I32Const(0),
+                Instruction::End,
+            ])),
+            memory.to_vec(),
+        )])),
+    ])
+}
+
+/// Return the parity-wasm type of a wasmi value type
+fn element_type(value_type: wasmi::ValueType) -> ValueType {
+    match value_type {
+        wasmi::ValueType::I32 => ValueType::I32,
+        wasmi::ValueType::I64 => ValueType::I64,
+        wasmi::ValueType::F32 => ValueType::F32,
+        wasmi::ValueType::F64 => ValueType::F64,
+    }
+}
+
+/// Return the payload of the transaction executing the state target's contract
+fn execute_contract_payload() -> Vec<u8> {
+    let builder = ExecuteContractActionBuilder::new()
+        .with_name(CONTRACT_NAME.into())
+        .with_version(CONTRACT_VERSION.into())
+        .with_inputs(vec![NAMESPACE.into()])
+        .with_outputs(vec![NAMESPACE.into()])
+        .with_payload(b"fuzz".to_vec())
+        .into_payload_builder();
+    build_payload(builder)
+}
+
+/// Return the payload of the transaction creating a new version of the state target's contract
+fn create_contract_payload() -> Vec<u8> {
+    let builder = CreateContractActionBuilder::new()
+        .with_name(CONTRACT_NAME.into())
+        .with_version("2.0".into())
+        .with_inputs(vec![NAMESPACE.into()])
+        .with_outputs(vec![NAMESPACE.into()])
+        .with_contract(EMPTY_MODULE.to_vec())
+        .into_payload_builder();
+    build_payload(builder)
+}
+
+/// Return the serialized payload of an action the target builds, which is always valid
+fn build_payload(builder: Result<SabrePayloadBuilder, ActionBuildError>) -> Vec<u8> {
+    builder
+        .expect("Unable to build fuzz target action")
+        .build()
+        .expect("Unable to build fuzz target payload")
+        .into_bytes()
+        .expect("Unable to serialize fuzz target payload")
+}
+
+/// Reads the fields of a fuzz input, which end early if the input does
+struct Input<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn new(data: &'a [u8]) -> Input<'a> {
+        Input { data }
+    }
+
+    /// Return the next `length` bytes, or those which remain if there are fewer
+    fn take(&mut self, length: usize) -> &'a [u8] {
+        let (taken, rest) = self.data.split_at(length.min(self.data.len()));
+        self.data = rest;
+        taken
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        if self.data.len() < N {
+            return None;
+        }
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.take(N));
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes::<1>().map(|[byte]| byte)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes().map(u16::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.bytes().map(i32::from_le_bytes)
+    }
+}
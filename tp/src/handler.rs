@@ -14,30 +14,56 @@
 
 //! Provides a Sawtooth Transaction Handler for executing Sabre transactions.
 
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
 use crypto::digest::Digest;
 use crypto::sha2::Sha512;
 use sabre_sdk::protocol::state::{
-    ContractBuilder, ContractRegistry, ContractRegistryBuilder, NamespaceRegistry,
-    NamespaceRegistryBuilder, PermissionBuilder, SmartPermissionBuilder, VersionBuilder,
+    AuditEntryBuilder, Contract, ContractBuilder, ContractChunk, ContractChunkBuilder,
+    ContractRegistry, ContractRegistryBuilder, NamespaceRegistry, NamespaceRegistryBuilder,
+    PermissionBuilder, SmartPermissionBuilder, VersionBuilder,
 };
 use sawtooth_sdk::messages::processor::TpProcessRequest;
 use sawtooth_sdk::processor::handler::ApplyError;
 use sawtooth_sdk::processor::handler::TransactionContext;
 use sawtooth_sdk::processor::handler::TransactionHandler;
+use semver::{Version, VersionReq};
 
+use crate::addressing::{
+    make_audit_log_address, make_contract_registry_address, make_namespace_registry_address,
+};
 use crate::admin::AdminPermission;
+use crate::disk_cache::DiskModuleCache;
+use crate::health::Health;
+use crate::logging;
+use crate::metrics::{action_name, Metrics};
 use crate::payload::SabreRequestPayload;
+use crate::preload::Preload;
+use crate::registry_cache::RegistryContext;
 use crate::state::SabreState;
-use crate::wasm_executor::wasm_module::WasmModule;
+use crate::wasm_executor::float_policy::FloatPolicy;
+use crate::wasm_executor::memory_limit::MAX_MEMORY_PAGES;
+use crate::wasm_executor::module_cache::{ContractCache, ModuleCache, SmartPermissionCache};
+use crate::wasm_executor::proposals::WasmProtocolVersion;
+use crate::wasm_executor::validation::validate_contract;
+use crate::wasm_executor::wasm_externals::ExternalsError;
+#[cfg(feature = "wasmtime")]
+use crate::wasm_executor::wasmtime_module::WasmtimeEngine;
+use crate::wasm_executor::{ExecutionLimits, LoadedModule, Runtime, Transaction};
 use sabre_sdk::protocol::payload::{
-    Action, CreateContractAction, CreateContractRegistryAction, CreateNamespaceRegistryAction,
+    Action, CommitContractAction, CreateContractAction, CreateContractActionBuilder,
+    CreateContractChunkAction, CreateContractRegistryAction, CreateNamespaceRegistryAction,
     CreateNamespaceRegistryPermissionAction, CreateSmartPermissionAction, DeleteContractAction,
     DeleteContractRegistryAction, DeleteNamespaceRegistryAction,
     DeleteNamespaceRegistryPermissionAction, DeleteSmartPermissionAction, ExecuteContractAction,
-    UpdateContractRegistryOwnersAction, UpdateNamespaceRegistryOwnersAction,
-    UpdateSmartPermissionAction,
+    PauseContractAction, ResumeContractAction, UpdateContractRegistryOwnersAction,
+    UpdateNamespaceRegistryOwnersAction, UpdateSmartPermissionAction,
+};
+use sabre_sdk::protocol::{
+    AUDIT_SETTING_KEY, FLOAT_POLICY_SETTING_KEY, MAX_CONTRACT_SIZE_SETTING_KEY,
+    SABRE_PROTOCOL_VERSION, WASM_PROTOCOL_VERSION_SETTING_KEY,
 };
-use sabre_sdk::protocol::SABRE_PROTOCOL_VERSION;
 
 /// The namespace registry prefix for global state (00ec00)
 const NAMESPACE_REGISTRY_PREFIX: &str = "00ec00";
@@ -48,6 +74,72 @@ const CONTRACT_REGISTRY_PREFIX: &str = "00ec01";
 /// The contract prefix for global state (00ec02)
 const CONTRACT_PREFIX: &str = "00ec02";
 
+/// The contract chunk prefix for global state (00ec04)
+const CONTRACT_CHUNK_PREFIX: &str = "00ec04";
+
+/// The audit log prefix for global state (00ec05)
+const AUDIT_LOG_PREFIX: &str = "00ec05";
+
+/// The number of parsed contracts kept in memory by default
+pub const DEFAULT_CONTRACT_CACHE_SIZE: usize = 32;
+
+/// The number of instructions each execution of a contract may run by default
+pub const DEFAULT_MAX_INSTRUCTIONS: u64 = 100_000_000;
+
+/// The number of 64KiB pages of memory a contract may use by default, unless its contract
+/// registry sets a limit
+pub const DEFAULT_MAX_MEMORY_PAGES: u32 = 1024;
+
+/// The depth of nested contract invocations a contract may make by default
+pub const DEFAULT_MAX_CALL_DEPTH: u32 = 8;
+
+/// The number of nested function calls a contract interpreted by wasmi may make by default
+pub const DEFAULT_MAX_STACK_DEPTH: u32 = 64 * 1024;
+
+/// The number of bytes of stack a contract may use by default
+pub const DEFAULT_MAX_STACK_SIZE: usize = 1024 * 1024;
+
+/// The number of bytes a contract may have by default
+pub const DEFAULT_MAX_CONTRACT_SIZE: usize = 16 * 1024 * 1024;
+
+/// The number of chunks a contract may be uploaded in
+pub const MAX_CONTRACT_CHUNKS: u32 = 4096;
+
+/// The WebAssembly runtime used to execute contracts
+#[derive(Clone, Copy)]
+pub enum WasmBackend {
+    /// Interpret contracts with wasmi
+    Wasmi,
+    /// Compile contracts to native code with wasmtime
+    #[cfg(feature = "wasmtime")]
+    Wasmtime,
+}
+
+/// How a transaction is rejected when its contract does not finish executing in time
+#[derive(Clone, Copy)]
+pub enum TimeoutError {
+    /// Reject the transaction as invalid
+    InvalidTransaction,
+    /// Report an internal error, so that the validator retries the transaction
+    InternalError,
+}
+
+/// The wall-clock time an execution of a contract may take
+#[derive(Clone, Copy)]
+struct ExecutionTimeout {
+    duration: Duration,
+    error: TimeoutError,
+}
+
+impl ExecutionTimeout {
+    fn reject(&self, message: String) -> ApplyError {
+        match self.error {
+            TimeoutError::InvalidTransaction => ApplyError::InvalidTransaction(message),
+            TimeoutError::InternalError => ApplyError::InternalError(message),
+        }
+    }
+}
+
 /// Handles Sabre Transactions
 ///
 /// This handler implements the Sawtooth TransactionHandler trait, in order to execute Sabre
@@ -62,6 +154,15 @@ pub struct SabreTransactionHandler {
     family_versions: Vec<String>,
     namespaces: Vec<String>,
     admin_permissions: Box<dyn AdminPermission>,
+    runtime: Runtime,
+    module_cache: ContractCache,
+    disk_cache: Option<Arc<DiskModuleCache>>,
+    smart_permission_cache: SmartPermissionCache,
+    limits: ExecutionLimits,
+    timeout: Option<ExecutionTimeout>,
+    max_contract_size: usize,
+    metrics: Option<Arc<Metrics>>,
+    health: Option<Arc<Health>>,
 }
 
 impl SabreTransactionHandler {
@@ -74,9 +175,244 @@ impl SabreTransactionHandler {
                 NAMESPACE_REGISTRY_PREFIX.into(),
                 CONTRACT_REGISTRY_PREFIX.into(),
                 CONTRACT_PREFIX.into(),
+                CONTRACT_CHUNK_PREFIX.into(),
+                AUDIT_LOG_PREFIX.into(),
             ],
             admin_permissions,
+            runtime: Runtime::Wasmi,
+            module_cache: Mutex::new(ModuleCache::new(DEFAULT_CONTRACT_CACHE_SIZE)),
+            disk_cache: None,
+            smart_permission_cache: Mutex::new(ModuleCache::new(DEFAULT_CONTRACT_CACHE_SIZE)),
+            limits: ExecutionLimits {
+                max_instructions: DEFAULT_MAX_INSTRUCTIONS,
+                max_memory_pages: DEFAULT_MAX_MEMORY_PAGES,
+                deadline: None,
+                max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+                max_stack_depth: DEFAULT_MAX_STACK_DEPTH,
+                max_stack_size: DEFAULT_MAX_STACK_SIZE,
+                float_policy: FloatPolicy::Allow,
+                wasm_protocol_version: WasmProtocolVersion::V1,
+            },
+            timeout: None,
+            max_contract_size: DEFAULT_MAX_CONTRACT_SIZE,
+            metrics: None,
+            health: None,
+        }
+    }
+
+    /// Sets the number of parsed contracts, and of parsed smart permissions, kept in memory, so
+    /// that they are not parsed and validated again each time they are executed; 0 disables the
+    /// caches
+    pub fn with_contract_cache_size(mut self, size: usize) -> SabreTransactionHandler {
+        self.module_cache = Mutex::new(ModuleCache::new(size));
+        self.smart_permission_cache = Mutex::new(ModuleCache::new(size));
+        if let Some(health) = &self.health {
+            health.record_cache_capacity(size);
+        }
+        self
+    }
+
+    /// Sets the runtime used to execute contracts, which is wasmi by default
+    pub fn with_wasm_backend(
+        mut self,
+        backend: WasmBackend,
+    ) -> Result<SabreTransactionHandler, String> {
+        self.runtime = match backend {
+            WasmBackend::Wasmi => Runtime::Wasmi,
+            #[cfg(feature = "wasmtime")]
+            WasmBackend::Wasmtime => Runtime::Wasmtime(
+                WasmtimeEngine::new(self.limits.max_stack_size).map_err(|err| err.to_string())?,
+            ),
+        };
+        // Modules loaded by one runtime cannot be executed by another
+        self.module_cache
+            .lock()
+            .map_err(|_| "Contract cache lock poisoned".to_string())?
+            .clear();
+        Ok(self)
+    }
+
+    /// Sets the number of instructions each execution of a contract, together with the contracts
+    /// and smart permissions it invokes, may run before the transaction is rejected as invalid
+    pub fn with_max_instructions(mut self, max_instructions: u64) -> SabreTransactionHandler {
+        self.limits.max_instructions = max_instructions;
+        self
+    }
+
+    /// Sets the number of 64KiB pages of memory a contract may use, unless its contract registry
+    /// sets a limit
+    pub fn with_max_memory_pages(
+        mut self,
+        max_memory_pages: u32,
+    ) -> Result<SabreTransactionHandler, String> {
+        if max_memory_pages == 0 {
+            return Err("Memory limit must be at least 1 page".into());
+        }
+        check_max_memory_pages(max_memory_pages)?;
+        self.limits.max_memory_pages = max_memory_pages;
+        Ok(self)
+    }
+
+    /// Sets the number of bytes a contract may have; larger contracts are rejected when they are
+    /// created
+    pub fn with_max_contract_size(mut self, max_contract_size: usize) -> SabreTransactionHandler {
+        self.max_contract_size = max_contract_size;
+        self
+    }
+
+    /// Sets how deeply contracts invoking other contracts may be nested; 0 prevents contracts
+    /// from invoking contracts
+    pub fn with_max_call_depth(mut self, max_call_depth: u32) -> SabreTransactionHandler {
+        self.limits.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Sets the number of nested function calls a contract interpreted by wasmi may make before
+    /// the transaction is rejected as invalid
+    pub fn with_max_stack_depth(
+        mut self,
+        max_stack_depth: u32,
+    ) -> Result<SabreTransactionHandler, String> {
+        if max_stack_depth == 0 {
+            return Err("Stack depth must be at least 1 call".into());
+        }
+        self.limits.max_stack_depth = max_stack_depth;
+        Ok(self)
+    }
+
+    /// Sets the number of bytes of stack a contract may use before the transaction is rejected
+    /// as invalid: of value stack for wasmi, and of native stack for wasmtime
+    pub fn with_max_stack_size(
+        mut self,
+        max_stack_size: usize,
+    ) -> Result<SabreTransactionHandler, String> {
+        if max_stack_size == 0 {
+            return Err("Stack size must be at least 1 byte".into());
+        }
+        self.limits.max_stack_size = max_stack_size;
+        // The stack of wasmtime is a setting of its engine
+        #[cfg(feature = "wasmtime")]
+        {
+            if let Runtime::Wasmtime(_) = self.runtime {
+                return self.with_wasm_backend(WasmBackend::Wasmtime);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Sets the metrics the handler records the transactions it processes in
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> SabreTransactionHandler {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Sets the health the handler records its registration and contract cache in
+    pub fn with_health(mut self, health: Arc<Health>) -> SabreTransactionHandler {
+        if let Ok(module_cache) = self.module_cache.lock() {
+            health.record_cache_capacity(module_cache.capacity());
+        }
+        self.health = Some(health);
+        self
+    }
+
+    /// Sets the directory of loaded contracts, which may be shared with other handlers, from
+    /// which contracts missing from the contract cache are loaded without being parsed,
+    /// validated and compiled again, if the sha512 their contract registry records for them is
+    /// the one they were stored for; without it, contracts are loaded again after each restart
+    pub fn with_disk_module_cache(
+        mut self,
+        disk_cache: Arc<DiskModuleCache>,
+    ) -> SabreTransactionHandler {
+        self.disk_cache = Some(disk_cache);
+        self
+    }
+
+    /// Sets the wall-clock time each execution of a contract, including the smart permissions it
+    /// invokes, may take before it is aborted and its transaction rejected with `error`; by
+    /// default executions are only limited by their number of instructions
+    pub fn with_execution_timeout(
+        mut self,
+        duration: Duration,
+        error: TimeoutError,
+    ) -> SabreTransactionHandler {
+        self.timeout = Some(ExecutionTimeout { duration, error });
+        self
+    }
+
+    /// Loads the contracts of `preload` into the contract cache with the handler's runtime and
+    /// limits, so that the first transactions which execute them do not wait for them to be
+    /// loaded; it is called once the handler is otherwise configured, since changing the cache
+    /// size or runtime empties the cache
+    ///
+    /// A contract which cannot be loaded is logged and left to be loaded when it is executed.
+    pub fn with_preloaded_contracts(self, preload: &Preload) -> SabreTransactionHandler {
+        let mut context = preload.context();
+        let mut state = SabreState::new(&mut context);
+        let mut preloaded = 0;
+
+        for (name, version) in preload.contracts() {
+            match self.preload_contract(name, version, &mut state) {
+                Ok(()) => preloaded += 1,
+                Err(err) => warn!("Unable to preload contract {}, {}: {}", name, version, err),
+            }
         }
+
+        let capacity = self
+            .module_cache
+            .lock()
+            .map(|module_cache| module_cache.capacity())
+            .unwrap_or_default();
+        if preloaded > capacity {
+            warn!(
+                "Only {} of the {} preloaded contracts fit in the contract cache",
+                capacity, preloaded
+            );
+        }
+        info!("Preloaded {} contracts", preloaded);
+
+        self
+    }
+
+    fn preload_contract(
+        &self,
+        name: &str,
+        version: &str,
+        state: &mut SabreState,
+    ) -> Result<(), String> {
+        let contract = state
+            .get_contract(name, version)
+            .map_err(|err| err.to_string())?
+            .ok_or_else(|| "Contract does not exist".to_string())?;
+        let contract_registry = state
+            .get_contract_registry(name)
+            .map_err(|err| err.to_string())?;
+        let limits = contract_limits(contract_registry.as_ref(), state, self.limits)
+            .map_err(|err| err.to_string())?;
+
+        let started = Instant::now();
+        let sha512 = contract_sha512(&contract);
+        self.module_cache
+            .lock()
+            .map_err(|_| "Contract cache lock poisoned".to_string())?
+            .get_or_load(&cached_module_key(&self.runtime, &sha512, limits), || {
+                load_contract(
+                    &self.runtime,
+                    self.disk_cache.as_deref(),
+                    &contract,
+                    &sha512,
+                    registered_sha512(contract_registry.as_ref(), version),
+                    limits,
+                )
+            })
+            .map_err(|err| err.to_string())?;
+        debug!(
+            "Preloaded contract {}, {} in {} ms",
+            name,
+            version,
+            started.elapsed().as_millis()
+        );
+
+        Ok(())
     }
 }
 
@@ -86,6 +422,10 @@ impl TransactionHandler for SabreTransactionHandler {
     }
 
     fn family_versions(&self) -> Vec<String> {
+        // The processor asks for the versions of the family when it registers with the validator
+        if let Some(health) = &self.health {
+            health.record_registration();
+        }
         self.family_versions.clone()
     }
 
@@ -113,8 +453,14 @@ impl TransactionHandler for SabreTransactionHandler {
             }
         };
 
+        let started = Instant::now();
+        let _transaction_scope = logging::enter_transaction(request.get_signature());
+        let _contract_scope = contract_of(&payload.get_action())
+            .map(|(name, version)| logging::enter_contract(name, version));
+
         let signer = request.get_header().get_signer_public_key();
-        let mut state = SabreState::new(context);
+        let mut context = RegistryContext::new(context);
+        let mut state = SabreState::new(&mut context);
 
         info!(
             "{} {:?} {:?}",
@@ -123,18 +469,34 @@ impl TransactionHandler for SabreTransactionHandler {
             request.get_header().get_outputs()
         );
 
-        match payload.get_action() {
-            Action::CreateContract(create_contract_payload) => {
-                create_contract(create_contract_payload, signer, &mut state)
-            }
-            Action::DeleteContract(delete_contract_payload) => {
-                delete_contract(delete_contract_payload, signer, &mut state)
-            }
+        let action = payload.get_action();
+        let result = match action.clone() {
+            Action::CreateContract(create_contract_payload) => create_contract(
+                create_contract_payload,
+                signer,
+                &mut state,
+                self.max_contract_size,
+            ),
+            Action::DeleteContract(delete_contract_payload) => delete_contract(
+                delete_contract_payload,
+                signer,
+                &mut state,
+                &self.module_cache,
+                self.disk_cache.as_deref(),
+            ),
             Action::ExecuteContract(execute_contract_payload) => execute_contract(
                 execute_contract_payload,
                 signer,
                 request.get_signature(),
                 &mut state,
+                &self.runtime,
+                &self.module_cache,
+                self.disk_cache.as_deref(),
+                &self.smart_permission_cache,
+                self.limits,
+                self.timeout,
+                self.metrics.as_deref(),
+                self.health.as_deref(),
             ),
             Action::CreateContractRegistry(create_contract_registry_payload) => {
                 create_contract_registry(
@@ -209,7 +571,162 @@ impl TransactionHandler for SabreTransactionHandler {
             Action::DeleteSmartPermission(payload) => {
                 delete_smart_permission(payload, signer, &mut state)
             }
+            Action::CreateContractChunk(payload) => {
+                create_contract_chunk(payload, signer, &mut state, self.max_contract_size)
+            }
+            Action::CommitContract(payload) => {
+                commit_contract(payload, signer, &mut state, self.max_contract_size)
+            }
+            Action::PauseContract(payload) => {
+                pause_contract(payload, signer, &mut state, &*self.admin_permissions)
+            }
+            Action::ResumeContract(payload) => {
+                resume_contract(payload, signer, &mut state, &*self.admin_permissions)
+            }
+        };
+        let result = result.and_then(|()| record_audit_entry(&action, request, &mut state));
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_transaction(&action, &result);
+        }
+        let duration_ms = started.elapsed().as_millis() as u64;
+        match &result {
+            Ok(()) => info!(
+                action = action_name(&action), duration_ms = duration_ms;
+                "Transaction applied in {} ms", duration_ms
+            ),
+            Err(err) => info!(
+                action = action_name(&action), duration_ms = duration_ms;
+                "Transaction rejected in {} ms: {}", duration_ms, err
+            ),
+        }
+        result
+    }
+}
+
+/// Return the name and version of the contract an action creates, deletes, executes, pauses or
+/// resumes
+pub(crate) fn contract_of(action: &Action) -> Option<(&str, &str)> {
+    match action {
+        Action::CreateContract(payload) => Some((payload.name(), payload.version())),
+        Action::DeleteContract(payload) => Some((payload.name(), payload.version())),
+        Action::ExecuteContract(payload) if payload.version().is_empty() => {
+            Some((payload.name(), payload.version_requirement()))
+        }
+        Action::ExecuteContract(payload) => Some((payload.name(), payload.version())),
+        Action::CreateContractChunk(payload) => Some((payload.name(), payload.version())),
+        Action::CommitContract(payload) => Some((payload.name(), payload.version())),
+        Action::PauseContract(payload) => Some((payload.name(), payload.version())),
+        Action::ResumeContract(payload) => Some((payload.name(), payload.version())),
+        _ => None,
+    }
+}
+
+/// Return the address of the registry whose audit log records an action, if the action is audited
+fn audited_registry_address(action: &Action) -> Result<Option<String>, ApplyError> {
+    let registry_address = match action {
+        Action::DeleteContract(payload) => make_contract_registry_address(payload.name())?,
+        Action::CreateContractRegistry(payload) => make_contract_registry_address(payload.name())?,
+        Action::DeleteContractRegistry(payload) => make_contract_registry_address(payload.name())?,
+        Action::UpdateContractRegistryOwners(payload) => {
+            make_contract_registry_address(payload.name())?
+        }
+        Action::PauseContract(payload) => make_contract_registry_address(payload.name())?,
+        Action::ResumeContract(payload) => make_contract_registry_address(payload.name())?,
+        Action::CreateNamespaceRegistry(payload) => {
+            make_namespace_registry_address(payload.namespace())?
+        }
+        Action::DeleteNamespaceRegistry(payload) => {
+            make_namespace_registry_address(payload.namespace())?
+        }
+        Action::UpdateNamespaceRegistryOwners(payload) => {
+            make_namespace_registry_address(payload.namespace())?
+        }
+        Action::CreateNamespaceRegistryPermission(payload) => {
+            make_namespace_registry_address(payload.namespace())?
+        }
+        Action::DeleteNamespaceRegistryPermission(payload) => {
+            make_namespace_registry_address(payload.namespace())?
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(registry_address))
+}
+
+/// Record an administrative action which has been applied in the audit log of its registry, if
+/// the audit setting is enabled
+fn record_audit_entry(
+    action: &Action,
+    request: &TpProcessRequest,
+    state: &mut SabreState,
+) -> Result<(), ApplyError> {
+    let registry_address = match audited_registry_address(action)? {
+        Some(registry_address) => registry_address,
+        None => return Ok(()),
+    };
+    if !is_audit_enabled(state)? {
+        return Ok(());
+    }
+    // An action which cannot be recorded is not applied, so that it cannot avoid the audit log
+    let audit_log_address = make_audit_log_address(&registry_address)?;
+    if !request
+        .get_header()
+        .get_outputs()
+        .iter()
+        .any(|output| audit_log_address.starts_with(output.as_str()))
+    {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Audit log {} is not among the transaction's outputs; {} cannot be recorded",
+            audit_log_address,
+            action_name(action)
+        )));
+    }
+
+    let (block_num, timestamp) = match state.get_latest_block_info()? {
+        Some(block_info) => (block_info.get_block_num(), block_info.get_timestamp()),
+        None => (0, 0),
+    };
+    let entry = AuditEntryBuilder::new()
+        .with_signer(request.get_header().get_signer_public_key().to_string())
+        .with_action(action_name(action).to_string())
+        .with_transaction_id(request.get_signature().to_string())
+        .with_payload(request.get_payload().to_vec())
+        .with_block_num(block_num)
+        .with_timestamp(timestamp)
+        .build()
+        .map_err(|err| {
+            ApplyError::InvalidTransaction(format!("Cannot build audit entry: {}", err))
+        })?;
+
+    state.add_audit_entry(&registry_address, entry)
+}
+
+/// Return whether administrative actions are recorded in the audit logs, which they are only if
+/// the audit setting is `true`
+fn is_audit_enabled(state: &mut SabreState) -> Result<bool, ApplyError> {
+    let setting = match state.get_audit_setting() {
+        Ok(Some(setting)) => setting,
+        Ok(None) => return Ok(false),
+        Err(err) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Unable to check state: {}",
+                err,
+            )));
         }
+    };
+
+    match setting
+        .get_entries()
+        .iter()
+        .find(|entry| entry.key == AUDIT_SETTING_KEY)
+    {
+        Some(entry) => entry.value.trim().parse().map_err(|err| {
+            ApplyError::InvalidTransaction(format!(
+                "Invalid {} setting: {}",
+                AUDIT_SETTING_KEY, err
+            ))
+        }),
+        None => Ok(false),
     }
 }
 
@@ -217,6 +734,7 @@ fn create_contract(
     payload: CreateContractAction,
     signer: &str,
     state: &mut SabreState,
+    max_contract_size: usize,
 ) -> Result<(), ApplyError> {
     let name = payload.name();
     let version = payload.version();
@@ -260,6 +778,19 @@ fn create_contract(
         )));
     }
 
+    let max_contract_size = get_max_contract_size(state, max_contract_size)?;
+    let wasm_protocol_version = get_wasm_protocol_version(state)?;
+    validate_contract(payload.contract(), max_contract_size, wasm_protocol_version).map_err(
+        |err| {
+            ApplyError::InvalidTransaction(format!(
+                "Invalid contract {}, {}: {}",
+                name,
+                version,
+                err.message()
+            ))
+        },
+    )?;
+
     let contract = ContractBuilder::new()
         .with_name(name.into())
         .with_version(version.into())
@@ -298,16 +829,201 @@ fn create_contract(
     state.set_contract_registry(name, contract_registry)
 }
 
+fn create_contract_chunk(
+    payload: CreateContractChunkAction,
+    signer: &str,
+    state: &mut SabreState,
+    max_contract_size: usize,
+) -> Result<(), ApplyError> {
+    let name = payload.name();
+    let version = payload.version();
+    match state.get_contract(name, version) {
+        Ok(None) => (),
+        Ok(Some(_)) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Contract already exists: {}, {}",
+                name, version,
+            )));
+        }
+        Err(err) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Unable to check state: {}",
+                err,
+            )));
+        }
+    };
+
+    let contract_registry = match state.get_contract_registry(name) {
+        Ok(None) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "The Contract Registry does not exist: {}",
+                name,
+            )));
+        }
+        Ok(Some(contract_registry)) => contract_registry,
+        Err(err) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Unable to check state: {}",
+                err,
+            )));
+        }
+    };
+
+    if !contract_registry.owners().contains(&signer.into()) {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Only owners can submit new versions of contracts: {}",
+            signer,
+        )));
+    }
+
+    let index = payload.index();
+    if index >= MAX_CONTRACT_CHUNKS {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Contracts may not be uploaded in more than {} chunks: {}, {}",
+            MAX_CONTRACT_CHUNKS, name, version,
+        )));
+    }
+
+    // Chunks are uploaded in order, and only the last chunk may be replaced, so the chunks in
+    // state are always those before this one, and limiting their size with this one limits the
+    // size of the whole upload
+    if get_contract_chunk(state, name, version, index + 1)?.is_some() {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Only the last contract chunk may be replaced: {}, {}, {}",
+            name, version, index,
+        )));
+    }
+    let mut size = payload.chunk().len();
+    for previous in 0..index {
+        match get_contract_chunk(state, name, version, previous)? {
+            Some(chunk) => size += chunk.chunk().len(),
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Contract chunk {} must be uploaded before chunk {}: {}, {}",
+                    previous, index, name, version,
+                )));
+            }
+        }
+    }
+    let max_contract_size = get_max_contract_size(state, max_contract_size)?;
+    if size > max_contract_size {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Contract chunks exceed the maximum contract size of {} bytes: {}, {}",
+            max_contract_size, name, version,
+        )));
+    }
+
+    let chunk = ContractChunkBuilder::new()
+        .with_name(name.into())
+        .with_version(version.into())
+        .with_index(index)
+        .with_creator(signer.into())
+        .with_chunk(payload.chunk().to_vec())
+        .build()
+        .map_err(|_| ApplyError::InvalidTransaction(String::from("Cannot build contract chunk")))?;
+
+    state.set_contract_chunk(chunk)
+}
+
+/// Assembles the contract from its uploaded chunks and creates it as CreateContract would
+///
+/// The chunks must all have been uploaded by the signer, and `chunk_count` must cover every chunk
+/// uploaded for the version; they are removed from state once the contract has been created.
+fn commit_contract(
+    payload: CommitContractAction,
+    signer: &str,
+    state: &mut SabreState,
+    max_contract_size: usize,
+) -> Result<(), ApplyError> {
+    let name = payload.name();
+    let version = payload.version();
+
+    // Chunks are uploaded in order, so the contract must be assembled from all of them for none
+    // to be left in state once it is committed
+    if get_contract_chunk(state, name, version, payload.chunk_count())?.is_some() {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Contract has more than {} chunks: {}, {}",
+            payload.chunk_count(),
+            name,
+            version,
+        )));
+    }
+
+    let mut contract = Vec::new();
+    for index in 0..payload.chunk_count() {
+        let chunk = match get_contract_chunk(state, name, version, index)? {
+            Some(chunk) => chunk,
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Contract chunk does not exist: {}, {}, {}",
+                    name, version, index,
+                )));
+            }
+        };
+
+        if chunk.creator() != signer {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Contract chunk {} was not uploaded by the signer: {}",
+                index, signer,
+            )));
+        }
+
+        contract.extend_from_slice(chunk.chunk());
+    }
+
+    let mut sha = Sha512::new();
+    sha.input(&contract);
+    let contract_sha512 = sha.result_str();
+    if !contract_sha512.eq_ignore_ascii_case(payload.contract_sha512()) {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Assembled contract does not match its sha512: {}, {}",
+            name, version,
+        )));
+    }
+
+    let create_contract_payload = CreateContractActionBuilder::new()
+        .with_name(name.into())
+        .with_version(version.into())
+        .with_inputs(payload.inputs().to_vec())
+        .with_outputs(payload.outputs().to_vec())
+        .with_contract(contract)
+        .build()
+        .map_err(|_| {
+            ApplyError::InvalidTransaction(String::from("Cannot build create contract action"))
+        })?;
+
+    create_contract(create_contract_payload, signer, state, max_contract_size)?;
+
+    for index in 0..payload.chunk_count() {
+        state.delete_contract_chunk(name, version, index)?;
+    }
+
+    Ok(())
+}
+
+fn get_contract_chunk(
+    state: &mut SabreState,
+    name: &str,
+    version: &str,
+    index: u32,
+) -> Result<Option<ContractChunk>, ApplyError> {
+    state
+        .get_contract_chunk(name, version, index)
+        .map_err(|err| ApplyError::InvalidTransaction(format!("Unable to check state: {}", err)))
+}
+
 fn delete_contract(
     payload: DeleteContractAction,
     signer: &str,
     state: &mut SabreState,
+    module_cache: &ContractCache,
+    disk_cache: Option<&DiskModuleCache>,
 ) -> Result<(), ApplyError> {
     let name = payload.name();
     let version = payload.version();
 
-    match state.get_contract(name, version) {
-        Ok(Some(_)) => (),
+    let contract = match state.get_contract(name, version) {
+        Ok(Some(contract)) => contract,
         Ok(_) => {
             return Err(ApplyError::InvalidTransaction(format!(
                 "Contract does not exist: {}, {}",
@@ -346,10 +1062,18 @@ fn delete_contract(
         )));
     }
     let mut versions = contract_registry.versions().to_vec();
-    for (index, contract_registry_version) in versions.iter().enumerate() {
-        if contract_registry_version.version() == version {
+    match versions
+        .iter()
+        .position(|contract_registry_version| contract_registry_version.version() == version)
+    {
+        Some(index) => {
             versions.remove(index);
-            break;
+        }
+        None => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Contract Registry does not contain version: {}, {}",
+                name, version,
+            )));
         }
     }
 
@@ -362,17 +1086,141 @@ fn delete_contract(
         })?;
 
     state.set_contract_registry(name, contract_registry)?;
-    state.delete_contract(name, version)
+    state.delete_contract(name, version)?;
+
+    // The modules of the contract are keyed by its hash rather than its name and version, so
+    // they would otherwise only be evicted once they are least recently used; a contract with
+    // the same bytes under another name is loaded again when it is next executed
+    let sha512 = contract_sha512(&contract);
+    if let Ok(mut module_cache) = module_cache.lock() {
+        module_cache.invalidate(&sha512);
+    }
+    if let Some(disk_cache) = disk_cache {
+        disk_cache.invalidate(&sha512);
+    }
+
+    Ok(())
+}
+
+fn pause_contract(
+    payload: PauseContractAction,
+    signer: &str,
+    state: &mut SabreState,
+    admin_permissions: &dyn AdminPermission,
+) -> Result<(), ApplyError> {
+    set_contract_paused(
+        payload.name(),
+        payload.version(),
+        true,
+        signer,
+        state,
+        admin_permissions,
+    )
+}
+
+fn resume_contract(
+    payload: ResumeContractAction,
+    signer: &str,
+    state: &mut SabreState,
+    admin_permissions: &dyn AdminPermission,
+) -> Result<(), ApplyError> {
+    set_contract_paused(
+        payload.name(),
+        payload.version(),
+        false,
+        signer,
+        state,
+        admin_permissions,
+    )
+}
+
+/// Marks the version entry of a contract in its registry as paused, or clears the mark
+///
+/// Only the registry is updated, so a contract can be paused however large its module is.
+fn set_contract_paused(
+    name: &str,
+    version: &str,
+    paused: bool,
+    signer: &str,
+    state: &mut SabreState,
+    admin_permissions: &dyn AdminPermission,
+) -> Result<(), ApplyError> {
+    let contract_registry = match state.get_contract_registry(name) {
+        Ok(None) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Contract Registry does not exist: {}",
+                name,
+            )));
+        }
+        Ok(Some(contract_registry)) => contract_registry,
+        Err(err) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Unable to check state: {}",
+                err,
+            )));
+        }
+    };
+
+    // Check if signer is an owner or an admin
+    can_update_contract_registry(contract_registry.clone(), signer, state, admin_permissions)?;
+
+    let mut versions = contract_registry.versions().to_vec();
+    let index = versions
+        .iter()
+        .position(|contract_registry_version| contract_registry_version.version() == version)
+        .ok_or_else(|| {
+            ApplyError::InvalidTransaction(format!(
+                "Contract Registry does not contain version: {}, {}",
+                name, version,
+            ))
+        })?;
+    versions[index] = versions[index]
+        .clone()
+        .into_builder()
+        .with_paused(paused)
+        .build()
+        .map_err(|_| ApplyError::InvalidTransaction(String::from("Cannot build version")))?;
+
+    let contract_registry = contract_registry
+        .into_builder()
+        .with_versions(versions)
+        .build()
+        .map_err(|_| {
+            ApplyError::InvalidTransaction(String::from("Cannot build contract registry"))
+        })?;
+
+    info!(
+        "{} contract {}, {}",
+        if paused { "Paused" } else { "Resumed" },
+        name,
+        version
+    );
+    state.set_contract_registry(name, contract_registry)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_contract(
     payload: ExecuteContractAction,
     signer: &str,
     signature: &str,
     state: &mut SabreState,
+    runtime: &Runtime,
+    module_cache: &ContractCache,
+    disk_cache: Option<&DiskModuleCache>,
+    smart_permission_cache: &SmartPermissionCache,
+    limits: ExecutionLimits,
+    timeout: Option<ExecutionTimeout>,
+    metrics: Option<&Metrics>,
+    health: Option<&Health>,
 ) -> Result<(), ApplyError> {
     let name = payload.name();
-    let version = payload.version();
+    let version = resolve_version(&payload, state)?;
+    let version = version.as_str();
+    let _contract_scope = logging::enter_contract(name, version);
+
+    // The registry is read before the contract, so that a paused contract is rejected without
+    // reading its module
+    let contract_registry = check_contract_not_paused(name, version, state)?;
 
     let contract = match state.get_contract(name, version) {
         Ok(Some(contract)) => contract,
@@ -390,7 +1238,404 @@ fn execute_contract(
         }
     };
 
-    for input in payload.inputs() {
+    check_namespace_permissions(name, payload.inputs(), payload.outputs(), state)?;
+
+    let limits = contract_limits(contract_registry.as_ref(), state, limits)?;
+
+    let mut loaded = false;
+    let sha512 = contract_sha512(&contract);
+    let mut cached = module_cache
+        .lock()
+        .map_err(|_| ApplyError::InternalError("Contract cache lock poisoned".into()))?;
+    let module = cached
+        .get_or_load(&cached_module_key(runtime, &sha512, limits), || {
+            loaded = true;
+            load_contract(
+                runtime,
+                disk_cache,
+                &contract,
+                &sha512,
+                registered_sha512(contract_registry.as_ref(), version),
+                limits,
+            )
+        })
+        .map_err(|err| {
+            ApplyError::InvalidTransaction(format!(
+                "Unable to load contract {}, {}: {:?}",
+                name, version, err
+            ))
+        })?;
+    let cached_modules = cached.len();
+    // The cache is unlocked before the contract is executed, since the contracts it invokes are
+    // loaded through it
+    drop(cached);
+
+    let transaction = Transaction {
+        signer: signer.into(),
+        signature: signature.into(),
+        inputs: payload.inputs().to_vec(),
+        outputs: payload.outputs().to_vec(),
+    };
+    let limits = ExecutionLimits {
+        deadline: timeout.map(|timeout| Instant::now() + timeout.duration),
+        ..limits
+    };
+    if let Some(metrics) = metrics {
+        metrics.record_cache_lookup(!loaded);
+    }
+    if let Some(health) = health {
+        health.record_cache_lookup(!loaded, cached_modules);
+    }
+
+    let started = Instant::now();
+    let execution = runtime.entrypoint(
+        &module,
+        state.context(),
+        payload.payload().to_vec(),
+        &transaction,
+        limits,
+        module_cache,
+        smart_permission_cache,
+    );
+    if let Some(metrics) = metrics {
+        metrics.record_execution(started.elapsed());
+        metrics.record_instructions(name, version, execution.instructions);
+    }
+    let result = execution.result.map_err(|e| match timeout {
+        Some(timeout) if limits.timed_out() => timeout.reject(format!(
+            "Wasm contract did not finish within {} ms: {}, {}",
+            timeout.duration.as_millis(),
+            name,
+            version,
+        )),
+        _ => ApplyError::InvalidTransaction(format!("{:?}", e)),
+    })?;
+
+    // The error the contract reported, if any, is added to the error of the transaction
+    let reported = result
+        .error
+        .map(|error| format!(": {}", error))
+        .unwrap_or_default();
+    match result.result {
+        None => Err(ApplyError::InvalidTransaction(format!(
+            "Wasm contract did not return a result: {}, {}",
+            name, version,
+        ))),
+        Some(1) => Ok(()),
+        Some(-3) => Err(ApplyError::InvalidTransaction(format!(
+            "Wasm contract returned invalid transaction: {}, {}{}",
+            name, version, reported,
+        ))),
+        Some(num) => Err(ApplyError::InternalError(format!(
+            "Wasm contract returned internal error: {}{}",
+            num, reported,
+        ))),
+    }
+}
+
+/// Return the limits a contract executes with: those of the handler, with the memory limit of
+/// its contract registry, if it sets one, and the float policy and wasm protocol version settings
+fn contract_limits(
+    contract_registry: Option<&ContractRegistry>,
+    state: &mut SabreState,
+    limits: ExecutionLimits,
+) -> Result<ExecutionLimits, ApplyError> {
+    let max_memory_pages = match contract_registry {
+        Some(contract_registry) if contract_registry.max_memory_pages() > 0 => {
+            contract_registry.max_memory_pages()
+        }
+        _ => limits.max_memory_pages,
+    };
+    Ok(ExecutionLimits {
+        max_memory_pages,
+        float_policy: get_float_policy(state)?,
+        wasm_protocol_version: get_wasm_protocol_version(state)?,
+        ..limits
+    })
+}
+
+/// Return the key of the module of the contract in the disk cache
+///
+/// A module is loaded for a memory limit, float policy and wasm protocol version, so they are part
+/// of its key.
+fn module_key(contract_sha512: &str, limits: ExecutionLimits) -> String {
+    format!(
+        "{}:{}:{}:{}",
+        contract_sha512, limits.max_memory_pages, limits.float_policy, limits.wasm_protocol_version
+    )
+}
+
+/// Return the key of the module the runtime loads for a contract in the contract cache, which
+/// holds the modules of both runtimes when contracts compiled by wasmtime invoke others
+pub(crate) fn cached_module_key(
+    runtime: &Runtime,
+    contract_sha512: &str,
+    limits: ExecutionLimits,
+) -> String {
+    format!("{}:{}", module_key(contract_sha512, limits), runtime.name())
+}
+
+/// Return the sha512 of the bytes of a contract, by which its modules are cached
+pub(crate) fn contract_sha512(contract: &Contract) -> String {
+    let mut sha = Sha512::new();
+    sha.input(contract.contract());
+    sha.result_str()
+}
+
+/// Return the sha512 the contract registry records for a version of its contract
+fn registered_sha512<'a>(
+    contract_registry: Option<&'a ContractRegistry>,
+    version: &str,
+) -> Option<&'a str> {
+    contract_registry?
+        .versions()
+        .iter()
+        .find(|contract_registry_version| contract_registry_version.version() == version)
+        .map(|contract_registry_version| contract_registry_version.contract_sha512().as_str())
+}
+
+/// Load a contract with the runtime, taking it from the disk cache if the cache has an entry
+/// stored for the sha512 its contract registry records, and storing it in the cache otherwise
+///
+/// A contract whose bytes in state do not match the sha512 recorded for them is neither taken
+/// from nor stored in the cache.
+fn load_contract(
+    runtime: &Runtime,
+    disk_cache: Option<&DiskModuleCache>,
+    contract: &Contract,
+    contract_sha512: &str,
+    registered_sha512: Option<&str>,
+    limits: ExecutionLimits,
+) -> Result<LoadedModule, ExternalsError> {
+    let disk_cache = match (disk_cache, registered_sha512) {
+        (Some(disk_cache), Some(registered_sha512))
+            if registered_sha512.eq_ignore_ascii_case(contract_sha512) =>
+        {
+            disk_cache
+        }
+        _ => {
+            return runtime.load(
+                contract.contract(),
+                limits.max_memory_pages,
+                limits.float_policy,
+                limits.wasm_protocol_version,
+            )
+        }
+    };
+
+    let key = module_key(contract_sha512, limits);
+    if let Some(artifact) = disk_cache.get(runtime.name(), &key, contract_sha512) {
+        match runtime.load_artifact(&artifact) {
+            Ok(module) => return Ok(module),
+            // The entry is replaced below
+            Err(err) => warn!("Unable to load module {} from the disk cache: {}", key, err),
+        }
+    }
+
+    let (module, artifact) = runtime.load_with_artifact(
+        contract.contract(),
+        limits.max_memory_pages,
+        limits.float_policy,
+        limits.wasm_protocol_version,
+    )?;
+    disk_cache.put(runtime.name(), &key, contract_sha512, &artifact);
+
+    Ok(module)
+}
+
+/// Return the version of the contract an execute contract action executes: its version, or the
+/// highest semver version in the contract's registry which matches its version requirement
+///
+/// Versions of the contract which are not semver versions never match a requirement.
+fn resolve_version(
+    payload: &ExecuteContractAction,
+    state: &mut SabreState,
+) -> Result<String, ApplyError> {
+    let name = payload.name();
+    let version_requirement = payload.version_requirement();
+    if version_requirement.is_empty() {
+        return Ok(payload.version().into());
+    }
+
+    let requirement = VersionReq::parse(version_requirement).map_err(|err| {
+        ApplyError::InvalidTransaction(format!(
+            "Invalid version requirement {}: {}",
+            version_requirement, err
+        ))
+    })?;
+    let contract_registry = match state.get_contract_registry(name) {
+        Ok(Some(contract_registry)) => contract_registry,
+        Ok(None) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Contract Registry does not exist {}",
+                name,
+            )));
+        }
+        Err(err) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Unable to check state: {}",
+                err,
+            )));
+        }
+    };
+
+    let version = contract_registry
+        .versions()
+        .iter()
+        .filter_map(|version| {
+            Version::parse(version.version())
+                .ok()
+                .filter(|parsed| requirement.matches(parsed))
+                .map(|parsed| (parsed, version.version()))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, version)| version.to_string())
+        .ok_or_else(|| {
+            ApplyError::InvalidTransaction(format!(
+                "No version of contract {} matches {}",
+                name, version_requirement
+            ))
+        })?;
+    info!(
+        "Resolved version requirement {} of contract {} to {}",
+        version_requirement, name, version
+    );
+    Ok(version)
+}
+
+/// Return the float policy of the network, which is `allow` unless the float policy setting is
+/// set
+fn get_float_policy(state: &mut SabreState) -> Result<FloatPolicy, ApplyError> {
+    let setting = match state.get_float_policy_setting() {
+        Ok(Some(setting)) => setting,
+        Ok(None) => return Ok(FloatPolicy::Allow),
+        Err(err) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Unable to check state: {}",
+                err,
+            )));
+        }
+    };
+
+    match setting
+        .get_entries()
+        .iter()
+        .find(|entry| entry.key == FLOAT_POLICY_SETTING_KEY)
+    {
+        Some(entry) => entry.value.trim().parse().map_err(|err| {
+            ApplyError::InvalidTransaction(format!(
+                "Invalid {} setting: {}",
+                FLOAT_POLICY_SETTING_KEY, err
+            ))
+        }),
+        None => Ok(FloatPolicy::Allow),
+    }
+}
+
+/// Return the version of WebAssembly contracts may use, which is 1 unless the wasm protocol
+/// version setting is set
+fn get_wasm_protocol_version(state: &mut SabreState) -> Result<WasmProtocolVersion, ApplyError> {
+    let setting = match state.get_wasm_protocol_version_setting() {
+        Ok(Some(setting)) => setting,
+        Ok(None) => return Ok(WasmProtocolVersion::V1),
+        Err(err) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Unable to check state: {}",
+                err,
+            )));
+        }
+    };
+
+    match setting
+        .get_entries()
+        .iter()
+        .find(|entry| entry.key == WASM_PROTOCOL_VERSION_SETTING_KEY)
+    {
+        Some(entry) => entry.value.trim().parse().map_err(|err| {
+            ApplyError::InvalidTransaction(format!(
+                "Invalid {} setting: {}",
+                WASM_PROTOCOL_VERSION_SETTING_KEY, err
+            ))
+        }),
+        None => Ok(WasmProtocolVersion::V1),
+    }
+}
+
+/// Return the number of bytes a new contract may have, which is the transaction processor's limit
+/// unless the max contract size setting sets a lower one
+fn get_max_contract_size(
+    state: &mut SabreState,
+    max_contract_size: usize,
+) -> Result<usize, ApplyError> {
+    let setting = match state.get_max_contract_size_setting() {
+        Ok(Some(setting)) => setting,
+        Ok(None) => return Ok(max_contract_size),
+        Err(err) => {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Unable to check state: {}",
+                err,
+            )));
+        }
+    };
+
+    match setting
+        .get_entries()
+        .iter()
+        .find(|entry| entry.key == MAX_CONTRACT_SIZE_SETTING_KEY)
+    {
+        Some(entry) => {
+            let limit = entry.value.trim().parse::<usize>().map_err(|err| {
+                ApplyError::InvalidTransaction(format!(
+                    "Invalid {} setting: {}",
+                    MAX_CONTRACT_SIZE_SETTING_KEY, err
+                ))
+            })?;
+            Ok(limit.min(max_contract_size))
+        }
+        None => Ok(max_contract_size),
+    }
+}
+
+/// Check that a version of a contract is not paused in its contract registry, returning the
+/// registry
+pub(crate) fn check_contract_not_paused(
+    name: &str,
+    version: &str,
+    state: &mut SabreState,
+) -> Result<Option<ContractRegistry>, ApplyError> {
+    let contract_registry = state
+        .get_contract_registry(name)
+        .map_err(|err| ApplyError::InvalidTransaction(format!("Unable to check state: {}", err)))?;
+    let paused = contract_registry
+        .iter()
+        .flat_map(|contract_registry| contract_registry.versions())
+        .any(|contract_registry_version| {
+            contract_registry_version.version() == version && contract_registry_version.paused()
+        });
+    if paused {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Contract paused: {}, {}",
+            name, version,
+        )));
+    }
+
+    Ok(contract_registry)
+}
+
+/// Check that the namespace registries of the given inputs and outputs permit the contract to
+/// read from every input and write to every output
+pub(crate) fn check_namespace_permissions(
+    name: &str,
+    inputs: &[String],
+    outputs: &[String],
+    state: &mut SabreState,
+) -> Result<(), ApplyError> {
+    for input in inputs {
+        // The contracts among the inputs are read by Sabre to execute the contracts the contract
+        // invokes, so reading them requires no permission
+        if input.starts_with(CONTRACT_PREFIX) {
+            continue;
+        }
         let namespace = match input.get(..6) {
             Some(namespace) => namespace,
             None => {
@@ -448,7 +1693,7 @@ fn execute_contract(
         }
     }
 
-    for output in payload.outputs() {
+    for output in outputs {
         let namespace = match output.get(..6) {
             Some(namespace) => namespace,
             None => {
@@ -505,28 +1750,7 @@ fn execute_contract(
         }
     }
 
-    let mut module = WasmModule::new(contract.contract(), state.context())
-        .expect("Failed to create can_add module");
-
-    let result = module
-        .entrypoint(payload.payload().to_vec(), signer.into(), signature.into())
-        .map_err(|e| ApplyError::InvalidTransaction(format!("{:?}", e)))?;
-
-    match result {
-        None => Err(ApplyError::InvalidTransaction(format!(
-            "Wasm contract did not return a result: {}, {}",
-            name, version,
-        ))),
-        Some(1) => Ok(()),
-        Some(-3) => Err(ApplyError::InvalidTransaction(format!(
-            "Wasm contract returned invalid transaction: {}, {}",
-            name, version,
-        ))),
-        Some(num) => Err(ApplyError::InternalError(format!(
-            "Wasm contract returned internal error: {}",
-            num
-        ))),
-    }
+    Ok(())
 }
 
 fn create_contract_registry(
@@ -560,9 +1784,12 @@ fn create_contract_registry(
         )));
     }
 
+    check_max_memory_pages(payload.max_memory_pages()).map_err(ApplyError::InvalidTransaction)?;
+
     let contract_registry = ContractRegistryBuilder::new()
         .with_name(name.into())
         .with_owners(payload.owners().to_vec())
+        .with_max_memory_pages(payload.max_memory_pages())
         .build()
         .map_err(|_| {
             ApplyError::InvalidTransaction(String::from("Cannot build contract registry"))
@@ -1094,3 +2321,386 @@ fn can_update_contract_registry(
     }
     Ok(())
 }
+
+// helper function to check that a memory limit can be given to a WebAssembly memory
+fn check_max_memory_pages(max_memory_pages: u32) -> Result<(), String> {
+    if max_memory_pages > MAX_MEMORY_PAGES {
+        return Err(format!(
+            "Memory limit must be at most {} pages: {}",
+            MAX_MEMORY_PAGES, max_memory_pages,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use parity_wasm::elements::Instruction;
+    use sabre_sdk::protocol::{
+        AUDIT_SETTING_ADDRESS, FLOAT_POLICY_SETTING_ADDRESS, MAX_CONTRACT_SIZE_SETTING_ADDRESS,
+    };
+
+    use crate::addressing::{
+        compute_smart_permission_address, make_contract_address, make_contract_chunk_address,
+    };
+    use crate::admin::AllowAllAdminPermission;
+    use crate::test_support::*;
+
+    /// A contract which invokes the smart permission named by the signature of its transaction,
+    /// at the address given by its payload, ten times:
+    ///
+    /// ```text
+    /// (module
+    ///   (import "env" "create_collection" (func (param i32) (result i32)))
+    ///   (import "env" "invoke_smart_permission"
+    ///     (func (param i32 i32 i32 i32 i32 i32) (result i32)))
+    ///   (func (param $payload i32) (param $signer i32) (param $signature i32) (result i32)
+    ///     (local $roles i32) (local $i i32)
+    ///     local.get $payload call 0 local.set $roles
+    ///     loop
+    ///       local.get $payload local.get $signature local.get $roles
+    ///       local.get $payload local.get $signer local.get $payload
+    ///       call 1 drop
+    ///       local.get $i i32.const 1 i32.add local.tee $i
+    ///       i32.const 10 i32.lt_u br_if 0
+    ///     end
+    ///     i32.const 1)
+    ///   (export "entrypoint" (func 2)))
+    /// ```
+    const INVOKES_SMART_PERMISSION: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x17, 0x03, 0x60, 0x01, 0x7f, 0x01,
+        0x7f, 0x60, 0x06, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x01, 0x7f, 0x60, 0x03, 0x7f, 0x7f,
+        0x7f, 0x01, 0x7f, 0x02, 0x37, 0x02, 0x03, 0x65, 0x6e, 0x76, 0x11, 0x63, 0x72, 0x65, 0x61,
+        0x74, 0x65, 0x5f, 0x63, 0x6f, 0x6c, 0x6c, 0x65, 0x63, 0x74, 0x69, 0x6f, 0x6e, 0x00, 0x00,
+        0x03, 0x65, 0x6e, 0x76, 0x17, 0x69, 0x6e, 0x76, 0x6f, 0x6b, 0x65, 0x5f, 0x73, 0x6d, 0x61,
+        0x72, 0x74, 0x5f, 0x70, 0x65, 0x72, 0x6d, 0x69, 0x73, 0x73, 0x69, 0x6f, 0x6e, 0x00, 0x01,
+        0x03, 0x02, 0x01, 0x02, 0x07, 0x0e, 0x01, 0x0a, 0x65, 0x6e, 0x74, 0x72, 0x79, 0x70, 0x6f,
+        0x69, 0x6e, 0x74, 0x00, 0x02, 0x0a, 0x2c, 0x01, 0x2a, 0x01, 0x02, 0x7f, 0x20, 0x00, 0x10,
+        0x00, 0x21, 0x03, 0x03, 0x40, 0x20, 0x00, 0x20, 0x02, 0x20, 0x03, 0x20, 0x00, 0x20, 0x01,
+        0x20, 0x00, 0x10, 0x01, 0x1a, 0x20, 0x04, 0x41, 0x01, 0x6a, 0x22, 0x04, 0x41, 0x0a, 0x49,
+        0x0d, 0x00, 0x0b, 0x41, 0x01, 0x0b,
+    ];
+
+    /// A contract which invokes the contract named by the signature of its transaction, in the
+    /// version given by its payload, with its payload, ten times, returning the last result:
+    ///
+    /// ```text
+    /// (module
+    ///   (import "env" "invoke_contract" (func (param i32 i32 i32) (result i32)))
+    ///   (func (param i32 i32 i32) (result i32)
+    ///     (local i32 i32)
+    ///     loop
+    ///       local.get 2 local.get 0 local.get 0 call 0 local.set 3
+    ///       local.get 4 i32.const 1 i32.add local.tee 4
+    ///       i32.const 10 i32.lt_u br_if 0
+    ///     end
+    ///     local.get 3)
+    ///   (export "entrypoint" (func 1)))
+    /// ```
+    const INVOKES_CONTRACT: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x01, 0x60, 0x03, 0x7f, 0x7f,
+        0x7f, 0x01, 0x7f, 0x02, 0x17, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x0f, 0x69, 0x6e, 0x76, 0x6f,
+        0x6b, 0x65, 0x5f, 0x63, 0x6f, 0x6e, 0x74, 0x72, 0x61, 0x63, 0x74, 0x00, 0x00, 0x03, 0x02,
+        0x01, 0x00, 0x07, 0x0e, 0x01, 0x0a, 0x65, 0x6e, 0x74, 0x72, 0x79, 0x70, 0x6f, 0x69, 0x6e,
+        0x74, 0x00, 0x01, 0x0a, 0x21, 0x01, 0x1f, 0x01, 0x02, 0x7f, 0x03, 0x40, 0x20, 0x02, 0x20,
+        0x00, 0x20, 0x00, 0x10, 0x00, 0x21, 0x03, 0x20, 0x04, 0x41, 0x01, 0x6a, 0x22, 0x04, 0x41,
+        0x0a, 0x49, 0x0d, 0x00, 0x0b, 0x20, 0x03, 0x0b,
+    ];
+
+    #[test]
+    fn execute_contract_without_settings_in_inputs() {
+        let mut entries = HashMap::new();
+        let wasm = contract(vec![Instruction::I32Const(1), Instruction::End]);
+        store_contract(&mut entries, "test", "1.0", &wasm);
+
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission));
+        let result = apply(
+            &handler,
+            &entries,
+            execute_payload("test", "1.0"),
+            execute_inputs("test", "1.0"),
+        );
+        assert!(result.is_ok(), "{:?}", result);
+
+        // The float policy must be read, or the contract could avoid it
+        let inputs = execute_inputs("test", "1.0")
+            .into_iter()
+            .filter(|input| input != FLOAT_POLICY_SETTING_ADDRESS)
+            .collect();
+        assert_invalid(
+            apply(&handler, &entries, execute_payload("test", "1.0"), inputs),
+            "Unable to check state",
+        );
+    }
+
+    #[test]
+    fn create_contract_without_max_contract_size_setting_in_inputs() {
+        let mut entries = HashMap::new();
+        store_contract_registry(&mut entries, "test");
+        let wasm = contract(vec![Instruction::I32Const(1), Instruction::End]);
+        let mut inputs = vec![
+            make_contract_address("test", "1.0").unwrap(),
+            make_contract_registry_address("test").unwrap(),
+        ];
+
+        // The setting must be read, or the contract could avoid its limit
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission));
+        assert_invalid(
+            apply(
+                &handler,
+                &entries,
+                create_payload("test", "1.0", &wasm),
+                inputs.clone(),
+            ),
+            "Unable to check state",
+        );
+
+        inputs.push(MAX_CONTRACT_SIZE_SETTING_ADDRESS.into());
+        let result = apply(
+            &handler,
+            &entries,
+            create_payload("test", "1.0", &wasm),
+            inputs.clone(),
+        );
+        assert!(result.is_ok(), "{:?}", result);
+
+        // The limit of the transaction processor still applies
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission))
+            .with_max_contract_size(wasm.len() - 1);
+        assert_invalid(
+            apply(
+                &handler,
+                &entries,
+                create_payload("test", "1.0", &wasm),
+                inputs,
+            ),
+            "Invalid contract",
+        );
+    }
+
+    #[test]
+    fn administer_without_audit_setting_in_inputs() {
+        let mut entries = HashMap::new();
+        store_setting(
+            &mut entries,
+            AUDIT_SETTING_ADDRESS,
+            AUDIT_SETTING_KEY,
+            "true",
+        );
+
+        // The audit setting must be read, or the action could avoid being recorded
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission));
+        match apply(
+            &handler,
+            &entries,
+            create_contract_registry_payload("test"),
+            vec![CONTRACT_REGISTRY_PREFIX.into(), AUDIT_LOG_PREFIX.into()],
+        ) {
+            Err(ApplyError::InvalidTransaction(_)) => (),
+            result => panic!("Action was applied without the audit setting: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn administer_without_audit_log_in_outputs() {
+        let mut entries = HashMap::new();
+        store_setting(
+            &mut entries,
+            AUDIT_SETTING_ADDRESS,
+            AUDIT_SETTING_KEY,
+            "true",
+        );
+        let registry_address = make_contract_registry_address("test").unwrap();
+        let audit_log_address = make_audit_log_address(&registry_address).unwrap();
+        let inputs = vec![
+            CONTRACT_REGISTRY_PREFIX.into(),
+            AUDIT_LOG_PREFIX.into(),
+            AUDIT_SETTING_ADDRESS.into(),
+        ];
+
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission));
+        assert_invalid(
+            apply_with_outputs(
+                &handler,
+                &entries,
+                create_contract_registry_payload("test"),
+                inputs.clone(),
+                vec![CONTRACT_REGISTRY_PREFIX.into()],
+            ),
+            "cannot be recorded",
+        );
+
+        // With the audit log among its outputs, the action is recorded
+        let changes = apply(
+            &handler,
+            &entries,
+            create_contract_registry_payload("test"),
+            inputs,
+        )
+        .expect("Contract registry was not created");
+        assert!(changes.contains_key(&registry_address));
+        assert!(changes.contains_key(&audit_log_address));
+    }
+
+    #[test]
+    fn smart_permissions_share_the_budget_of_their_contract() {
+        let mut entries = HashMap::new();
+        store_contract(&mut entries, "test", "1.0", INVOKES_SMART_PERMISSION);
+        // The contract names the smart permission with the signature of its transaction
+        let smart_permission = SmartPermissionBuilder::new()
+            .with_name("test".into())
+            .with_org_id("org".into())
+            .with_function(counting_module(4))
+            .build()
+            .expect("Unable to build smart permission");
+        write(&mut entries, |state| {
+            state.set_smart_permission("org", "test", smart_permission)
+        });
+        let smart_permission_address = compute_smart_permission_address("org", "test");
+        let mut inputs = execute_inputs("test", "1.0");
+        inputs.push(smart_permission_address.clone());
+        let payload = execute_payload_with("test", "1.0", smart_permission_address.as_bytes());
+
+        // Each execution of the smart permission runs within the budget, but not ten of them
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission))
+            .with_max_instructions(20_000);
+        assert_invalid(
+            apply(&handler, &entries, payload.clone(), inputs.clone()),
+            "budget exceeded",
+        );
+
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission))
+            .with_max_instructions(200_000);
+        let result = apply(&handler, &entries, payload, inputs);
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn invoked_contract_cannot_be_paused() {
+        let mut entries = HashMap::new();
+        store_contract(&mut entries, "caller", "1.0", INVOKES_CONTRACT);
+        // The caller names the invoked contract with the signature of its transaction
+        let wasm = contract(vec![Instruction::I32Const(1), Instruction::End]);
+        store_contract(&mut entries, "test", "1.0", &wasm);
+        let mut inputs = execute_inputs("caller", "1.0");
+        inputs.push(make_contract_address("test", "1.0").unwrap());
+        inputs.push(make_contract_registry_address("test").unwrap());
+        let payload = execute_payload_with("caller", "1.0", b"1.0");
+
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission));
+        let result = apply(&handler, &entries, payload.clone(), inputs.clone());
+        assert!(result.is_ok(), "{:?}", result);
+
+        let version = VersionBuilder::new()
+            .with_version("1.0".into())
+            .with_contract_sha512("0".repeat(128))
+            .with_creator(SIGNER.into())
+            .with_paused(true)
+            .build()
+            .expect("Unable to build version");
+        let contract_registry = ContractRegistryBuilder::new()
+            .with_name("test".into())
+            .with_versions(vec![version])
+            .with_owners(vec![SIGNER.into()])
+            .build()
+            .expect("Unable to build contract registry");
+        write(&mut entries, |state| {
+            state.set_contract_registry("test", contract_registry)
+        });
+        assert_invalid(
+            apply(&handler, &entries, payload, inputs),
+            "Contract paused: test, 1.0",
+        );
+    }
+
+    #[test]
+    fn invoked_contracts_share_the_budget_of_their_caller() {
+        let mut entries = HashMap::new();
+        store_contract(&mut entries, "caller", "1.0", INVOKES_CONTRACT);
+        store_contract(&mut entries, "test", "1.0", &counting_module(3));
+        let mut inputs = execute_inputs("caller", "1.0");
+        inputs.push(make_contract_address("test", "1.0").unwrap());
+        inputs.push(make_contract_registry_address("test").unwrap());
+        let payload = execute_payload_with("caller", "1.0", b"1.0");
+
+        // Each execution of the invoked contract runs within the budget, but not ten of them
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission))
+            .with_max_instructions(20_000);
+        assert_invalid(
+            apply(&handler, &entries, payload.clone(), inputs.clone()),
+            "budget exceeded",
+        );
+
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission))
+            .with_max_instructions(200_000);
+        let result = apply(&handler, &entries, payload, inputs);
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn contract_chunks_are_limited_to_the_max_contract_size() {
+        let mut entries = HashMap::new();
+        store_contract_registry(&mut entries, "test");
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission))
+            .with_max_contract_size(10);
+
+        assert_invalid(
+            apply_to(&handler, &mut entries, chunk_payload(0, &[0; 11])),
+            "exceed the maximum contract size",
+        );
+        assert_invalid(
+            apply_to(&handler, &mut entries, chunk_payload(1, &[0; 4])),
+            "must be uploaded before chunk 1",
+        );
+        assert_invalid(
+            apply_to(
+                &handler,
+                &mut entries,
+                chunk_payload(MAX_CONTRACT_CHUNKS, &[0; 4]),
+            ),
+            "more than 4096 chunks",
+        );
+
+        apply_to(&handler, &mut entries, chunk_payload(0, &[0; 6])).expect("Chunk 0 rejected");
+        assert_invalid(
+            apply_to(&handler, &mut entries, chunk_payload(1, &[0; 5])),
+            "exceed the maximum contract size",
+        );
+        apply_to(&handler, &mut entries, chunk_payload(1, &[0; 4])).expect("Chunk 1 rejected");
+
+        // Replacing an earlier chunk could grow the upload past the limit
+        assert_invalid(
+            apply_to(&handler, &mut entries, chunk_payload(0, &[0; 6])),
+            "Only the last contract chunk may be replaced",
+        );
+        apply_to(&handler, &mut entries, chunk_payload(1, &[1; 4])).expect("Chunk 1 rejected");
+    }
+
+    #[test]
+    fn committing_a_contract_removes_all_of_its_chunks() {
+        let mut entries = HashMap::new();
+        store_contract_registry(&mut entries, "test");
+        let handler = SabreTransactionHandler::new(Box::new(AllowAllAdminPermission));
+        let wasm = contract(vec![Instruction::I32Const(1), Instruction::End]);
+        let (first, second) = wasm.split_at(wasm.len() / 2);
+        apply_to(&handler, &mut entries, chunk_payload(0, first)).expect("Chunk 0 rejected");
+        apply_to(&handler, &mut entries, chunk_payload(1, second)).expect("Chunk 1 rejected");
+
+        // Committing only the first chunk would leave the second in state
+        assert_invalid(
+            apply_to(&handler, &mut entries, commit_payload(1, first)),
+            "Contract has more than 1 chunks",
+        );
+
+        apply_to(&handler, &mut entries, commit_payload(2, &wasm)).expect("Commit rejected");
+        assert!(entries.contains_key(&make_contract_address("test", "1.0").unwrap()));
+        for index in 0..3 {
+            let address = make_contract_chunk_address("test", "1.0", index).unwrap();
+            assert!(!entries.contains_key(&address), "Chunk {} was left", index);
+        }
+    }
+}
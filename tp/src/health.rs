@@ -0,0 +1,143 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reports the health of the transaction processor over HTTP, for liveness and readiness probes
+//!
+//! `GET /health` is answered while the transaction processor is running, and `GET /ready` only
+//! while its validator is reachable and it has registered with the validator. Both answer with
+//! a JSON object describing:
+//!
+//! - `validator`: the `endpoint` of the validator, and whether it is `reachable`
+//! - `registered`: whether the transaction processor has sent its registration to the validator
+//! - `contract_cache`: the number of `entries` in the contract cache, its `capacity`, and the
+//!   executions of contracts which were cache `hits` and `misses`
+
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::http::{self, Response};
+use crate::logging::push_json_string;
+
+/// How long a connection to the validator may take before it is considered unreachable
+const VALIDATOR_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The health of a transaction handler, which may be shared with the thread serving it
+pub struct Health {
+    validator_endpoint: String,
+    registered: AtomicBool,
+    cache_entries: AtomicUsize,
+    cache_capacity: AtomicUsize,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+impl Health {
+    /// Create the health of a transaction processor connecting to the validator at
+    /// `validator_endpoint`, such as `tcp://localhost:4004`
+    pub fn new(validator_endpoint: &str) -> Health {
+        Health {
+            validator_endpoint: validator_endpoint.into(),
+            registered: AtomicBool::new(false),
+            cache_entries: AtomicUsize::new(0),
+            cache_capacity: AtomicUsize::new(0),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Record that the transaction processor has registered with the validator
+    pub fn record_registration(&self) {
+        self.registered.store(true, Ordering::Relaxed);
+    }
+
+    /// Record an execution of a contract whose module was, or was not, in the contract cache,
+    /// and the number of modules in the cache after it
+    pub fn record_cache_lookup(&self, hit: bool, entries: usize) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.cache_entries.store(entries, Ordering::Relaxed);
+    }
+
+    /// Record the number of modules the contract cache holds at most
+    pub fn record_cache_capacity(&self, capacity: usize) {
+        self.cache_capacity.store(capacity, Ordering::Relaxed);
+    }
+
+    /// Render the health as a JSON object, with whether the transaction processor is ready to
+    /// process transactions: its validator is reachable and it has registered with the validator
+    pub fn render(&self) -> (bool, String) {
+        let reachable = self.validator_reachable();
+        let registered = self.registered.load(Ordering::Relaxed);
+        let ready = reachable && registered;
+
+        let mut endpoint = String::new();
+        push_json_string(&mut endpoint, &self.validator_endpoint);
+        let body = format!(
+            "{{\"ready\":{},\"validator\":{{\"endpoint\":{},\"reachable\":{}}},\
+             \"registered\":{},\"contract_cache\":{{\"entries\":{},\"capacity\":{},\
+             \"hits\":{},\"misses\":{}}}}}\n",
+            ready,
+            endpoint,
+            reachable,
+            registered,
+            self.cache_entries.load(Ordering::Relaxed),
+            self.cache_capacity.load(Ordering::Relaxed),
+            self.cache_hits.load(Ordering::Relaxed),
+            self.cache_misses.load(Ordering::Relaxed),
+        );
+        (ready, body)
+    }
+
+    /// Return whether a TCP connection can be made to the validator's endpoint
+    fn validator_reachable(&self) -> bool {
+        let address = match self.validator_endpoint.strip_prefix("tcp://") {
+            Some(address) => address,
+            None => return false,
+        };
+        match address.to_socket_addrs() {
+            Ok(mut addresses) => addresses.any(|address| {
+                TcpStream::connect_timeout(&address, VALIDATOR_CONNECT_TIMEOUT).is_ok()
+            }),
+            Err(err) => {
+                debug!("Unable to resolve validator endpoint {}: {}", address, err);
+                false
+            }
+        }
+    }
+}
+
+/// Serve the health at `http://<address>/health` and `http://<address>/ready` from a background
+/// thread
+///
+/// Fails if the address cannot be bound.
+pub fn serve(address: &str, health: Arc<Health>) -> Result<(), String> {
+    http::serve("health", address, move |path| {
+        let (ready, body) = match path {
+            "/health" | "/ready" => health.render(),
+            _ => return Response::not_found("Health is served at /health and /ready\n"),
+        };
+        let status = if path == "/ready" && !ready {
+            "503 Service Unavailable"
+        } else {
+            "200 OK"
+        };
+        Response::new(status, "application/json", body)
+    })
+}
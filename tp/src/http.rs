@@ -0,0 +1,167 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal HTTP server, which answers the `GET` requests of monitoring systems such as
+//! Prometheus and Kubernetes from a background thread, and a minimal client for the `GET`
+//! requests the transaction processor makes to a REST API
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+/// The response to a request
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new(status: &'static str, content_type: &'static str, body: String) -> Response {
+        Response {
+            status,
+            content_type,
+            body,
+        }
+    }
+
+    pub fn not_found(body: &str) -> Response {
+        Response::new("404 Not Found", "text/plain", body.into())
+    }
+}
+
+/// Answer the requests made to `address` from a thread named `name`, calling `respond` with the
+/// path of each `GET` request
+///
+/// Fails if the address cannot be bound.
+pub fn serve<F>(name: &str, address: &str, respond: F) -> Result<(), String>
+where
+    F: Fn(&str) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind(address)
+        .map_err(|err| format!("Unable to bind {} address {}: {}", name, address, err))?;
+    info!("Serving {} on {}", name, address);
+
+    let thread_name = name.to_string();
+    thread::Builder::new()
+        .name(name.into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(err) = answer(stream, &respond) {
+                            debug!("Unable to respond to {} request: {}", thread_name, err);
+                        }
+                    }
+                    Err(err) => debug!("Unable to accept {} connection: {}", thread_name, err),
+                }
+            }
+        })
+        .map_err(|err| format!("Unable to start {} thread: {}", name, err))?;
+
+    Ok(())
+}
+
+fn answer<F>(stream: TcpStream, respond: &F) -> std::io::Result<()>
+where
+    F: Fn(&str) -> Response,
+{
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Read the rest of the request head, which is not needed
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => respond(path),
+        _ => Response::new(
+            "405 Method Not Allowed",
+            "text/plain",
+            String::from("Only GET requests are answered\n"),
+        ),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+/// The time a request made with `get` may take to connect, and each read of its response
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Send a `GET` request to the `http://` URL, returning the status code and body of the response
+pub fn get(url: &str) -> Result<(u16, Vec<u8>), String> {
+    let target = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("Unable to request {}: only http URLs are supported", url))?;
+    let (host, path) = match target.find('/') {
+        Some(index) => target.split_at(index),
+        None => (target, "/"),
+    };
+    let address = if host.rsplit(']').next().unwrap_or(host).contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+
+    let response = request(&address, host, path)
+        .map_err(|err| format!("Unable to request {}: {}", url, err))?;
+
+    let head_length = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| format!("Unable to request {}: incomplete response", url))?;
+    let status = String::from_utf8_lossy(&response[..head_length])
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| format!("Unable to request {}: invalid response status", url))?;
+
+    Ok((status, response[head_length + 4..].to_vec()))
+}
+
+fn request(address: &str, host: &str, path: &str) -> std::io::Result<Vec<u8>> {
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "host has no address"))?;
+    let mut stream = TcpStream::connect_timeout(&address, CLIENT_TIMEOUT)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    // An HTTP/1.0 response is not chunked, and ends when the server closes the connection
+    write!(
+        stream,
+        "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n\r\n",
+        path, host
+    )?;
+    stream.flush()?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    Ok(response)
+}
@@ -12,14 +12,58 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Executes Sabre transactions, so that projects other than the `sawtooth-sabre` transaction
+//! processor can apply them in-process
+//!
+//! `handler::SabreTransactionHandler` is the entry point: it is created with one of the
+//! `admin::AdminPermission` implementations, configured with its `with_*` methods, and applies a
+//! transaction to any `TransactionContext` through the Sawtooth SDK's `TransactionHandler`
+//! trait. Contracts are executed by the handler; the wasm executor itself is not part of the API.
+//!
+//! To run handlers connected to a validator, as the binary does, use `processor::start`. The
+//! loggers in `logging` prefix the messages of the handler with the transaction and contract they
+//! belong to.
+//!
+//! ```ignore
+//! use sawtooth_sabre::admin::SettingsAdminPermission;
+//! use sawtooth_sabre::handler::SabreTransactionHandler;
+//! use sawtooth_sdk::processor::handler::TransactionHandler;
+//!
+//! let handler = SabreTransactionHandler::new(Box::new(SettingsAdminPermission))
+//!     .with_max_instructions(10_000_000);
+//! handler.apply(&request, &mut context)?;
+//! ```
+
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate serde_derive;
 
 mod addressing;
 pub mod admin;
+pub mod bench;
+pub mod config;
+pub mod disk_cache;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
 pub mod handler;
+pub mod health;
+mod http;
+pub mod logging;
+pub mod metrics;
 mod payload;
+pub mod preload;
+pub mod processor;
+mod registry_cache;
+pub mod shutdown;
 mod state;
+#[cfg(test)]
+mod test_support;
 mod wasm_executor;
+pub mod worker_pool;
 
-pub use sabre_sdk::protocol::{ADMINISTRATORS_SETTING_ADDRESS, ADMINISTRATORS_SETTING_KEY};
+pub use sabre_sdk::protocol::{
+    ADMINISTRATORS_SETTING_ADDRESS, ADMINISTRATORS_SETTING_KEY, FLOAT_POLICY_SETTING_ADDRESS,
+    FLOAT_POLICY_SETTING_KEY, WASM_PROTOCOL_VERSION_SETTING_ADDRESS,
+    WASM_PROTOCOL_VERSION_SETTING_KEY,
+};
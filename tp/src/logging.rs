@@ -0,0 +1,295 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Writes the log records of the transaction processor as text or as JSON, one object per line
+//!
+//! Each object has the fields `timestamp`, `level`, `target` and `message`, the `txn_id` of the
+//! transaction being processed and the `contract_name` and `contract_version` of the contract it
+//! executes, if any, and the key-values of the record, such as `duration_ms`.
+//!
+//! The messages contracts log have the target `contract`, and their own level, so that they can
+//! be logged without the transaction processor's records, or vice versa. As text, they are
+//! prefixed with the transaction and contract.
+
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::io::Write;
+
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use simple_logger::SimpleLogger;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+thread_local! {
+    static CONTEXT: RefCell<LogContext> = RefCell::new(LogContext::default());
+}
+
+/// The transaction and contract a thread is processing, which are added to the records it logs
+#[derive(Clone, Default)]
+struct LogContext {
+    transaction_id: Option<String>,
+    contract: Option<(String, String)>,
+}
+
+/// Restores the log context of the thread which created it when dropped
+pub struct LogScope {
+    previous: LogContext,
+}
+
+impl Drop for LogScope {
+    fn drop(&mut self) {
+        let previous = std::mem::take(&mut self.previous);
+        CONTEXT.with(|context| *context.borrow_mut() = previous);
+    }
+}
+
+/// Add the id of a transaction to the records logged by this thread until the scope is dropped
+pub fn enter_transaction(transaction_id: &str) -> LogScope {
+    update_context(|_| LogContext {
+        transaction_id: Some(transaction_id.into()),
+        contract: None,
+    })
+}
+
+/// Add the name and version of a contract to the records logged by this thread until the scope
+/// is dropped
+pub fn enter_contract(name: &str, version: &str) -> LogScope {
+    update_context(|context| LogContext {
+        contract: Some((name.into(), version.into())),
+        ..context.clone()
+    })
+}
+
+fn update_context<F: FnOnce(&LogContext) -> LogContext>(update: F) -> LogScope {
+    CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        let updated = update(&context);
+        LogScope {
+            previous: std::mem::replace(&mut *context, updated),
+        }
+    })
+}
+
+/// The target of the messages logged by contracts
+pub const CONTRACT_TARGET: &str = "contract";
+
+/// Log a message of the contract being executed
+pub fn log_contract_message(level: Level, message: &str) {
+    log!(target: CONTRACT_TARGET, level, "{}", message);
+}
+
+/// Return the most verbose level at which the messages of contracts are logged
+pub fn contract_level() -> LevelFilter {
+    [
+        Level::Trace,
+        Level::Debug,
+        Level::Info,
+        Level::Warn,
+        Level::Error,
+    ]
+    .iter()
+    .find(|level| log_enabled!(target: CONTRACT_TARGET, **level))
+    .map(|level| level.to_level_filter())
+    .unwrap_or(LevelFilter::Off)
+}
+
+/// A logger which writes each record to stdout as text, adding the transaction and contract to
+/// the messages of contracts
+pub struct TextLogger {
+    logger: SimpleLogger,
+    max_level: LevelFilter,
+}
+
+impl TextLogger {
+    /// Create a logger which logs records at `level`, and the messages of contracts at
+    /// `contract_level`
+    pub fn new(level: LevelFilter, contract_level: LevelFilter) -> TextLogger {
+        TextLogger {
+            logger: SimpleLogger::new()
+                .with_level(level)
+                .with_module_level(CONTRACT_TARGET, contract_level),
+            max_level: level.max(contract_level),
+        }
+    }
+
+    /// Make this the logger of the process
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        log::set_max_level(self.max_level);
+        log::set_boxed_logger(Box::new(self))
+    }
+}
+
+impl Log for TextLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.target() != CONTRACT_TARGET {
+            return self.logger.log(record);
+        }
+
+        let mut prefix = String::new();
+        CONTEXT.with(|context| {
+            let context = context.borrow();
+            if let Some((name, version)) = &context.contract {
+                let _ = write!(prefix, "{} {} ", name, version);
+            }
+            if let Some(transaction_id) = &context.transaction_id {
+                let _ = write!(prefix, "txn {}", transaction_id);
+            }
+        });
+        self.logger.log(
+            &Record::builder()
+                .args(format_args!("[{}] {}", prefix.trim_end(), record.args()))
+                .metadata(record.metadata().clone())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.logger.flush()
+    }
+}
+
+/// A logger which writes each record to stdout as a JSON object
+pub struct JsonLogger {
+    level: LevelFilter,
+    contract_level: LevelFilter,
+}
+
+impl JsonLogger {
+    /// Create a logger which logs records at `level`, and the messages of contracts at
+    /// `contract_level`
+    pub fn new(level: LevelFilter, contract_level: LevelFilter) -> JsonLogger {
+        JsonLogger {
+            level,
+            contract_level,
+        }
+    }
+
+    /// Make this the logger of the process
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        log::set_max_level(self.level.max(self.contract_level));
+        log::set_boxed_logger(Box::new(self))
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        if metadata.target() == CONTRACT_TARGET {
+            metadata.level() <= self.contract_level
+        } else {
+            metadata.level() <= self.level
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        let mut line = String::from("{");
+        write_field(&mut line, "timestamp", &timestamp);
+        write_field(&mut line, "level", record.level().as_str());
+        write_field(&mut line, "target", record.target());
+        write_field(&mut line, "message", &record.args().to_string());
+
+        CONTEXT.with(|context| {
+            let context = context.borrow();
+            if let Some(transaction_id) = &context.transaction_id {
+                write_field(&mut line, "txn_id", transaction_id);
+            }
+            if let Some((name, version)) = &context.contract {
+                write_field(&mut line, "contract_name", name);
+                write_field(&mut line, "contract_version", version);
+            }
+        });
+
+        let _ = record
+            .key_values()
+            .visit(&mut KeyValueWriter { line: &mut line });
+        line.push('}');
+
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        let _ = writeln!(stdout, "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Writes the key-values of a record as fields, numbers and booleans as JSON numbers and
+/// booleans, and any other value as a string
+struct KeyValueWriter<'a> {
+    line: &'a mut String,
+}
+
+impl<'a, 'kvs> VisitSource<'kvs> for KeyValueWriter<'a> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        if let Some(number) = value.to_u64() {
+            write_raw_field(self.line, key.as_str(), &number.to_string());
+        } else if let Some(number) = value.to_i64() {
+            write_raw_field(self.line, key.as_str(), &number.to_string());
+        } else if let Some(boolean) = value.to_bool() {
+            write_raw_field(self.line, key.as_str(), &boolean.to_string());
+        } else {
+            write_field(self.line, key.as_str(), &value.to_string());
+        }
+        Ok(())
+    }
+}
+
+fn write_field(line: &mut String, key: &str, value: &str) {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    push_json_string(&mut quoted, value);
+    write_raw_field(line, key, &quoted);
+}
+
+/// Write a field whose value is already JSON
+fn write_raw_field(line: &mut String, key: &str, value: &str) {
+    if !line.ends_with('{') {
+        line.push(',');
+    }
+    push_json_string(line, key);
+    line.push(':');
+    line.push_str(value);
+}
+
+pub(crate) fn push_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -17,12 +17,33 @@ extern crate clap;
 #[macro_use]
 extern crate log;
 
-use clap::Arg;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{Arg, SubCommand};
 use log::LevelFilter;
 
 use sawtooth_sabre::admin;
-use sawtooth_sabre::handler::SabreTransactionHandler;
-use sawtooth_sdk::processor::TransactionProcessor;
+use sawtooth_sabre::bench;
+use sawtooth_sabre::config::TpConfig;
+use sawtooth_sabre::disk_cache::DiskModuleCache;
+#[cfg(feature = "fuzz")]
+use sawtooth_sabre::fuzz;
+use sawtooth_sabre::handler::{
+    SabreTransactionHandler, TimeoutError, WasmBackend, DEFAULT_CONTRACT_CACHE_SIZE,
+    DEFAULT_MAX_CALL_DEPTH, DEFAULT_MAX_CONTRACT_SIZE, DEFAULT_MAX_INSTRUCTIONS,
+    DEFAULT_MAX_MEMORY_PAGES, DEFAULT_MAX_STACK_DEPTH, DEFAULT_MAX_STACK_SIZE,
+};
+use sawtooth_sabre::health::{self, Health};
+use sawtooth_sabre::logging::{JsonLogger, TextLogger};
+use sawtooth_sabre::metrics::{self, Metrics};
+use sawtooth_sabre::preload::{Contracts, Preload, DEFAULT_REST_API_URL};
+use sawtooth_sabre::processor;
+use sawtooth_sabre::shutdown::DEFAULT_DRAIN_TIMEOUT;
+use sawtooth_sabre::worker_pool::DEFAULT_WORKERS;
 
 fn main() {
     let mut app = clap_app!(wasm_store_tp =>
@@ -33,38 +54,563 @@ fn main() {
         (@arg verbose: -v --verbose +multiple
          "increase output verbosity"));
 
+    app = app.arg(
+        Arg::with_name("config")
+            .long("config")
+            .takes_value(true)
+            .long_help(
+                "Path to a TOML file of default values for the options of the transaction \
+                 processor, overridden by those given on the command line (default \
+                 /etc/sabre/sabre-tp.toml, if it exists)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("log_format")
+            .long("log-format")
+            .takes_value(true)
+            .possible_values(&["text", "json"])
+            .long_help(
+                "Format of log records: text (the default), or json, which writes each record \
+                 as a JSON object on its own line",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("contract_log_level")
+            .long("contract-log-level")
+            .takes_value(true)
+            .possible_values(&["off", "error", "warn", "info", "debug", "trace"])
+            .long_help(
+                "Level at which the messages contracts log are written, independently of the \
+                 transaction processor's records; defaults to the level set by --verbose",
+            ),
+    );
+
     app = app.arg(
         Arg::with_name("admin_allow_all")
             .long("admin-allow-all")
             .long_help("Turns off the check for admin keys in Sawtooth Settings"),
     );
 
+    app = app.arg(
+        Arg::with_name("contract_cache_size")
+            .long("contract-cache-size")
+            .takes_value(true)
+            .long_help(
+                "Number of parsed contracts, and of parsed smart permissions, to keep in memory \
+                 between executions; 0 disables the caches (default 32)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("module_cache_dir")
+            .long("module-cache-dir")
+            .takes_value(true)
+            .value_name("directory")
+            .long_help(
+                "Directory in which loaded contracts are stored, so that they are not parsed, \
+                 validated and compiled again after a restart; a stored contract is only used \
+                 if its contract registry records the sha512 it was stored for; the directory \
+                 must be owned by the transaction processor's user and writable by no one else, \
+                 since its contents are executed (default none)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("max_instructions")
+            .long("max-instructions")
+            .takes_value(true)
+            .long_help(
+                "Number of instructions each execution of a contract may run before its \
+                 transaction is rejected (default 100000000)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("max_memory_pages")
+            .long("max-memory-pages")
+            .takes_value(true)
+            .long_help(
+                "Number of 64KiB pages of memory a contract may use, unless its contract \
+                 registry sets a limit (default 1024)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("max_call_depth")
+            .long("max-call-depth")
+            .takes_value(true)
+            .long_help(
+                "Depth of nested contract invocations a contract may make; 0 prevents contracts \
+                 from invoking contracts (default 8)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("max_stack_depth")
+            .long("max-stack-depth")
+            .takes_value(true)
+            .long_help(
+                "Number of nested function calls a contract interpreted by wasmi may make \
+                 before its transaction is rejected (default 65536)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("max_stack_size")
+            .long("max-stack-size")
+            .takes_value(true)
+            .long_help(
+                "Number of bytes of stack a contract may use before its transaction is \
+                 rejected: of value stack for wasmi, and of native stack for wasmtime \
+                 (default 1048576)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("max_contract_size")
+            .long("max-contract-size")
+            .takes_value(true)
+            .long_help(
+                "Number of bytes a contract may have; larger contracts are rejected when they \
+                 are created (default 16777216). The sawtooth.swa.max_contract_size setting \
+                 may set a lower limit",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("execution_timeout")
+            .long("execution-timeout")
+            .takes_value(true)
+            .long_help(
+                "Number of milliseconds each execution of a contract may take before it is \
+                 aborted and its transaction rejected (default no limit)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("execution_timeout_error")
+            .long("execution-timeout-error")
+            .takes_value(true)
+            .possible_values(&["invalid", "internal"])
+            .long_help(
+                "How a transaction whose contract times out is rejected: as an invalid \
+                 transaction (the default), or with an internal error, so that the validator \
+                 retries it; the timeout may be set in the configuration file rather than \
+                 with --execution-timeout",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("workers")
+            .long("workers")
+            .takes_value(true)
+            .long_help(
+                "Number of transactions to process concurrently, each on its own thread with \
+                 its own contract cache; the validator only sends concurrently transactions \
+                 whose inputs and outputs do not conflict (default 1)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("drain_timeout")
+            .long("drain-timeout")
+            .takes_value(true)
+            .long_help(
+                "Number of milliseconds the transaction processor may take to finish the \
+                 transaction it is processing and unregister from the validator once it is \
+                 signalled to shut down, before it exits anyway (default 20000)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("metrics_address")
+            .long("metrics-address")
+            .takes_value(true)
+            .long_help(
+                "Address, such as 0.0.0.0:9101, at which to serve metrics in the Prometheus \
+                 text format at /metrics (default metrics are not served)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("health_address")
+            .long("health-address")
+            .takes_value(true)
+            .long_help(
+                "Address, such as 0.0.0.0:9102, at which to serve the health of the transaction \
+                 processor at /health and its readiness at /ready (default health is not \
+                 served)",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("preload_contract")
+            .long("preload-contract")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("name:version")
+            .long_help(
+                "Contract to load into the contract cache at startup, read from the REST API, \
+                 so that the first transaction which executes it does not wait for it to be \
+                 loaded; may be repeated, and all preloads every contract in state",
+            ),
+    );
+
+    app = app.arg(
+        Arg::with_name("rest_api_url")
+            .long("rest-api-url")
+            .takes_value(true)
+            .long_help(
+                "URL of the REST API from which the contracts given with --preload-contract \
+                 are read (default http://localhost:8008)",
+            ),
+    );
+
+    #[cfg(feature = "wasmtime")]
+    {
+        app = app.arg(
+            Arg::with_name("wasm_backend")
+                .long("wasm-backend")
+                .takes_value(true)
+                .possible_values(&["wasmi", "wasmtime"])
+                .long_help(
+                    "WebAssembly runtime which executes contracts: the wasmi interpreter \
+                     (the default), or wasmtime, which compiles contracts to native code",
+                ),
+        );
+    }
+
+    app = app.subcommand(
+        SubCommand::with_name("bench")
+            .about(
+                "Replays batches against state in memory, applying them with the options given \
+                 before this subcommand, and reports their throughput, latencies and cache \
+                 statistics",
+            )
+            .arg(
+                Arg::with_name("batches")
+                    .long("batches")
+                    .takes_value(true)
+                    .required(true)
+                    .long_help(
+                        "Path to a serialized BatchList, such as one written by the sabre CLI \
+                         with --output; state starts empty, so the batches must create the \
+                         registries and contracts their transactions use",
+                    ),
+            ),
+    );
+
+    #[cfg(feature = "fuzz")]
+    {
+        app = app.subcommand(
+            SubCommand::with_name("fuzz-smoke")
+                .about(
+                    "Runs the fuzz targets over the inputs of a corpus, failing if any target \
+                     panics, so that CI can check the targets without a fuzzer",
+                )
+                .arg(
+                    Arg::with_name("corpus")
+                        .long("corpus")
+                        .takes_value(true)
+                        .required(true)
+                        .long_help(
+                            "Path to a corpus with a directory of inputs for each fuzz target, \
+                             such as tp/fuzz/corpus",
+                        ),
+                ),
+        );
+    }
+
     let matches = app.get_matches();
-    let logger = simple_logger::SimpleLogger::new();
-    let logger = match matches.occurrences_of("verbose") {
-        0 => logger.with_level(LevelFilter::Warn),
-        1 => logger.with_level(LevelFilter::Info),
-        2 => logger.with_level(LevelFilter::Debug),
-        _ => logger.with_level(LevelFilter::Trace),
+
+    // The configuration is loaded before the logger, whose level it may set
+    let config = TpConfig::load(matches.value_of("config")).unwrap_or_else(|err| {
+        eprintln!("Unable to start Sabre transaction processor: {}", err);
+        process::exit(1)
+    });
+
+    let level = match matches.occurrences_of("verbose") {
+        0 => config.log_level().unwrap_or(LevelFilter::Warn),
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    let contract_level = match value_t!(matches, "contract_log_level", LevelFilter) {
+        Ok(contract_level) => contract_level,
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => {
+            config.contract_log_level().unwrap_or(level)
+        }
+        Err(err) => err.exit(),
     };
 
-    logger.init().expect("Failed to create logger");
+    match matches
+        .value_of("log_format")
+        .or_else(|| config.log_format())
+    {
+        Some("json") => JsonLogger::new(level, contract_level).init(),
+        _ => TextLogger::new(level, contract_level).init(),
+    }
+    .expect("Failed to create logger");
+
+    #[cfg(feature = "fuzz")]
+    {
+        if let Some(fuzz_matches) = matches.subcommand_matches("fuzz-smoke") {
+            let corpus = fuzz_matches.value_of("corpus").unwrap_or_default();
+            match fuzz::smoke(Path::new(corpus)) {
+                Ok(count) => println!("Ran the fuzz targets over {} inputs", count),
+                Err(err) => {
+                    error!("Fuzz smoke test failed: {}", err);
+                    process::exit(1)
+                }
+            }
+            return;
+        }
+    }
 
     let connect = matches
         .value_of("connect")
+        .or_else(|| config.connect())
         .unwrap_or("tcp://localhost:4004");
 
-    let handler = {
-        if matches.is_present("admin_allow_all") {
-            warn!("Starting Sabre transaction processor without admin key verifcation");
-            SabreTransactionHandler::new(Box::new(admin::AllowAllAdminPermission::default()))
-        } else {
-            SabreTransactionHandler::new(Box::new(admin::SettingsAdminPermission::default()))
+    let contract_cache_size = match value_t!(matches, "contract_cache_size", usize) {
+        Ok(size) => size,
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => config
+            .contract_cache_size()
+            .unwrap_or(DEFAULT_CONTRACT_CACHE_SIZE),
+        Err(err) => err.exit(),
+    };
+
+    let max_instructions = match value_t!(matches, "max_instructions", u64) {
+        Ok(max_instructions) => max_instructions,
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => config
+            .max_instructions()
+            .unwrap_or(DEFAULT_MAX_INSTRUCTIONS),
+        Err(err) => err.exit(),
+    };
+
+    let max_memory_pages = match value_t!(matches, "max_memory_pages", u32) {
+        Ok(max_memory_pages) => max_memory_pages,
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => config
+            .max_memory_pages()
+            .unwrap_or(DEFAULT_MAX_MEMORY_PAGES),
+        Err(err) => err.exit(),
+    };
+
+    let max_call_depth = match value_t!(matches, "max_call_depth", u32) {
+        Ok(max_call_depth) => max_call_depth,
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => {
+            config.max_call_depth().unwrap_or(DEFAULT_MAX_CALL_DEPTH)
+        }
+        Err(err) => err.exit(),
+    };
+
+    let max_stack_depth = match value_t!(matches, "max_stack_depth", u32) {
+        Ok(max_stack_depth) => max_stack_depth,
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => {
+            config.max_stack_depth().unwrap_or(DEFAULT_MAX_STACK_DEPTH)
+        }
+        Err(err) => err.exit(),
+    };
+
+    let max_stack_size = match value_t!(matches, "max_stack_size", usize) {
+        Ok(max_stack_size) => max_stack_size,
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => {
+            config.max_stack_size().unwrap_or(DEFAULT_MAX_STACK_SIZE)
+        }
+        Err(err) => err.exit(),
+    };
+
+    let max_contract_size = match value_t!(matches, "max_contract_size", usize) {
+        Ok(max_contract_size) => max_contract_size,
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => config
+            .max_contract_size()
+            .unwrap_or(DEFAULT_MAX_CONTRACT_SIZE),
+        Err(err) => err.exit(),
+    };
+
+    let execution_timeout = match value_t!(matches, "execution_timeout", u64) {
+        Ok(millis) => Some(Duration::from_millis(millis)),
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => {
+            config.execution_timeout().map(Duration::from_millis)
+        }
+        Err(err) => err.exit(),
+    };
+
+    let workers = match value_t!(matches, "workers", usize) {
+        Ok(workers) if workers > 0 => workers,
+        Ok(_) => clap::Error::value_validation_auto("workers must be at least 1".into()).exit(),
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => {
+            config.workers().unwrap_or(DEFAULT_WORKERS)
+        }
+        Err(err) => err.exit(),
+    };
+
+    let drain_timeout = match value_t!(matches, "drain_timeout", u64) {
+        Ok(millis) => Duration::from_millis(millis),
+        Err(err) if err.kind == clap::ErrorKind::ArgumentNotFound => config
+            .drain_timeout()
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_DRAIN_TIMEOUT),
+        Err(err) => err.exit(),
+    };
+
+    let timeout_error = match matches
+        .value_of("execution_timeout_error")
+        .or_else(|| config.execution_timeout_error())
+    {
+        Some("internal") => TimeoutError::InternalError,
+        _ => TimeoutError::InvalidTransaction,
+    };
+
+    #[cfg(feature = "wasmtime")]
+    let backend = match matches.value_of("wasm_backend") {
+        Some("wasmtime") => WasmBackend::Wasmtime,
+        _ => WasmBackend::Wasmi,
+    };
+    #[cfg(not(feature = "wasmtime"))]
+    let backend = WasmBackend::Wasmi;
+
+    let admin_allow_all = matches.is_present("admin_allow_all");
+    if admin_allow_all {
+        warn!("Starting Sabre transaction processor without admin key verifcation");
+    }
+
+    let metrics = matches
+        .value_of("metrics_address")
+        .or_else(|| config.metrics_address())
+        .map(|address| {
+            let metrics = Arc::new(Metrics::new());
+            if let Err(err) = metrics::serve(address, metrics.clone()) {
+                error!("Unable to start Sabre transaction processor: {}", err);
+                process::exit(1)
+            }
+            metrics
+        });
+
+    let health = matches
+        .value_of("health_address")
+        .or_else(|| config.health_address())
+        .map(|address| {
+            let health = Arc::new(Health::new(connect));
+            if let Err(err) = health::serve(address, health.clone()) {
+                error!("Unable to start Sabre transaction processor: {}", err);
+                process::exit(1)
+            }
+            health
+        });
+
+    // The workers of a worker pool share the module cache directory, so that a contract stored
+    // by one is loaded from it by the others
+    let disk_cache = matches
+        .value_of("module_cache_dir")
+        .or_else(|| config.module_cache_dir())
+        .map(
+            |directory| match DiskModuleCache::new(Path::new(directory)) {
+                Ok(disk_cache) => Arc::new(disk_cache),
+                Err(err) => {
+                    error!("Unable to start Sabre transaction processor: {}", err);
+                    process::exit(1)
+                }
+            },
+        );
+
+    let preload_contracts = matches.values_of("preload_contract").map(|values| {
+        let values = values.collect::<Vec<_>>();
+        if values.contains(&"all") {
+            return Contracts::All;
+        }
+        Contracts::Listed(
+            values
+                .into_iter()
+                .map(|value| match value.split_once(':') {
+                    Some((name, version)) if !name.is_empty() && !version.is_empty() => {
+                        (name.to_string(), version.to_string())
+                    }
+                    _ => clap::Error::value_validation_auto(format!(
+                        "preload-contract must be name:version or all: {}",
+                        value
+                    ))
+                    .exit(),
+                })
+                .collect(),
+        )
+    });
+
+    // The contracts are read once, and loaded by the handler of each worker. A transaction
+    // processor which cannot read them still starts, and loads them when they are executed.
+    let preload = preload_contracts.and_then(|contracts| {
+        let rest_api_url = matches
+            .value_of("rest_api_url")
+            .unwrap_or(DEFAULT_REST_API_URL);
+        match Preload::fetch(rest_api_url, contracts) {
+            Ok(preload) => Some(preload),
+            Err(err) => {
+                warn!("Unable to preload contracts: {}", err);
+                None
+            }
+        }
+    });
+
+    // Each worker of a worker pool has its own handler, and so its own contract cache
+    let new_handler = move || {
+        let mut handler = {
+            if admin_allow_all {
+                SabreTransactionHandler::new(Box::new(admin::AllowAllAdminPermission::default()))
+            } else {
+                SabreTransactionHandler::new(Box::new(admin::SettingsAdminPermission::default()))
+            }
+        }
+        .with_contract_cache_size(contract_cache_size)
+        .with_max_instructions(max_instructions)
+        .with_max_call_depth(max_call_depth)
+        .with_max_contract_size(max_contract_size)
+        .with_max_memory_pages(max_memory_pages)
+        .and_then(|handler| handler.with_max_stack_depth(max_stack_depth))
+        .and_then(|handler| handler.with_max_stack_size(max_stack_size))
+        .and_then(|handler| handler.with_wasm_backend(backend))
+        .unwrap_or_else(|err| {
+            error!("Unable to start Sabre transaction processor: {}", err);
+            process::exit(1)
+        });
+
+        if let Some(timeout) = execution_timeout {
+            handler = handler.with_execution_timeout(timeout, timeout_error);
+        }
+        if let Some(metrics) = &metrics {
+            handler = handler.with_metrics(metrics.clone());
+        }
+        if let Some(health) = &health {
+            handler = handler.with_health(health.clone());
+        }
+        if let Some(disk_cache) = &disk_cache {
+            handler = handler.with_disk_module_cache(disk_cache.clone());
+        }
+        if let Some(preload) = &preload {
+            handler = handler.with_preloaded_contracts(preload);
         }
+        handler
     };
 
-    let mut processor = TransactionProcessor::new(connect);
+    if let Some(bench_matches) = matches.subcommand_matches("bench") {
+        let path = bench_matches.value_of("batches").unwrap_or_default();
+        let report = fs::read(path)
+            .map_err(|err| format!("Unable to read {}: {}", path, err))
+            .and_then(|batch_list| bench::replay(new_handler(), &batch_list))
+            .unwrap_or_else(|err| {
+                error!("Unable to replay batches: {}", err);
+                process::exit(1)
+            });
+        print!("{}", report);
+        return;
+    }
 
-    processor.add_handler(&handler);
-    processor.start();
+    if let Err(err) = processor::start(connect, workers, drain_timeout, new_handler) {
+        error!("Unable to start Sabre transaction processor: {}", err);
+        process::exit(1)
+    }
+    info!("Sabre transaction processor stopped");
 }
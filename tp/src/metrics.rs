@@ -0,0 +1,298 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Collects metrics of the transactions processed by the Sabre transaction handler, and serves
+//! them over HTTP in the Prometheus text format
+//!
+//! The exporter answers `GET /metrics` with:
+//!
+//! - `sabre_transactions_total`, the transactions processed, labeled by their action and result:
+//!   `ok`, `invalid` or `internal_error`
+//! - `sabre_contract_execution_seconds`, a histogram of the time contract executions take
+//! - `sabre_contract_instructions`, a histogram of the instructions contract executions run, and
+//!   `sabre_contract_instructions_by_contract`, the same histogram for each contract, labeled by
+//!   its name and version
+//! - `sabre_contract_cache_hits_total` and `sabre_contract_cache_misses_total`, the contracts
+//!   which were and were not found in the contract cache when they were executed
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use sabre_sdk::protocol::payload::Action;
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use crate::http::{self, Response};
+
+/// The upper bounds, in seconds, of the buckets of the execution time histogram
+const EXECUTION_SECONDS_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The upper bounds of the buckets of the instruction histograms, up to the default budget of
+/// 100,000,000 instructions and beyond
+const INSTRUCTIONS_BUCKETS: &[f64] = &[1e3, 1e4, 1e5, 1e6, 2.5e6, 5e6, 1e7, 2.5e7, 5e7, 1e8, 1e9];
+
+/// The metrics of a transaction handler, which may be shared with the thread serving them
+pub struct Metrics {
+    /// The number of transactions processed, by action and result
+    transactions: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    execution_seconds: Mutex<Histogram>,
+    instructions: Mutex<Histogram>,
+    /// The instructions run by the executions of each contract, by its name and version
+    contract_instructions: Mutex<BTreeMap<(String, String), Histogram>>,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        Metrics::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            transactions: Mutex::new(BTreeMap::new()),
+            execution_seconds: Mutex::new(Histogram::new(EXECUTION_SECONDS_BUCKETS)),
+            instructions: Mutex::new(Histogram::new(INSTRUCTIONS_BUCKETS)),
+            contract_instructions: Mutex::new(BTreeMap::new()),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a processed transaction
+    pub fn record_transaction(&self, action: &Action, result: &Result<(), ApplyError>) {
+        let result = match result {
+            Ok(()) => "ok",
+            Err(ApplyError::InvalidTransaction(_)) => "invalid",
+            Err(_) => "internal_error",
+        };
+        if let Ok(mut transactions) = self.transactions.lock() {
+            *transactions
+                .entry((action_name(action), result))
+                .or_insert(0) += 1;
+        }
+    }
+
+    /// Record the time an execution of a contract took
+    pub fn record_execution(&self, duration: Duration) {
+        if let Ok(mut histogram) = self.execution_seconds.lock() {
+            histogram.observe(duration.as_secs_f64());
+        }
+    }
+
+    /// Record the instructions an execution of a contract ran
+    pub fn record_instructions(&self, name: &str, version: &str, instructions: u64) {
+        if let Ok(mut histogram) = self.instructions.lock() {
+            histogram.observe(instructions as f64);
+        }
+        if let Ok(mut contracts) = self.contract_instructions.lock() {
+            contracts
+                .entry((name.to_string(), version.to_string()))
+                .or_insert_with(|| Histogram::new(INSTRUCTIONS_BUCKETS))
+                .observe(instructions as f64);
+        }
+    }
+
+    /// Count an execution of a contract whose module was, or was not, in the contract cache
+    pub fn record_cache_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The number of executions of contracts found in the contract cache
+    pub fn cache_hits(&self) -> u64 {
+        self.cache_hits.load(Ordering::Relaxed)
+    }
+
+    /// The number of executions of contracts which were not in the contract cache
+    pub fn cache_misses(&self) -> u64 {
+        self.cache_misses.load(Ordering::Relaxed)
+    }
+
+    /// Render the metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP sabre_transactions_total Transactions processed by Sabre\n");
+        out.push_str("# TYPE sabre_transactions_total counter\n");
+        if let Ok(transactions) = self.transactions.lock() {
+            for ((action, result), count) in transactions.iter() {
+                let _ = writeln!(
+                    out,
+                    "sabre_transactions_total{{action=\"{}\",result=\"{}\"}} {}",
+                    action, result, count
+                );
+            }
+        }
+
+        out.push_str(
+            "# HELP sabre_contract_execution_seconds Time taken by executions of contracts\n",
+        );
+        out.push_str("# TYPE sabre_contract_execution_seconds histogram\n");
+        if let Ok(histogram) = self.execution_seconds.lock() {
+            histogram.render("sabre_contract_execution_seconds", "", &mut out);
+        }
+
+        out.push_str(
+            "# HELP sabre_contract_instructions Instructions run by executions of contracts\n",
+        );
+        out.push_str("# TYPE sabre_contract_instructions histogram\n");
+        if let Ok(histogram) = self.instructions.lock() {
+            histogram.render("sabre_contract_instructions", "", &mut out);
+        }
+
+        out.push_str(
+            "# HELP sabre_contract_instructions_by_contract Instructions run by executions of \
+             each contract\n",
+        );
+        out.push_str("# TYPE sabre_contract_instructions_by_contract histogram\n");
+        if let Ok(contracts) = self.contract_instructions.lock() {
+            for ((name, version), histogram) in contracts.iter() {
+                let labels = format!(
+                    "contract=\"{}\",version=\"{}\"",
+                    escape_label(name),
+                    escape_label(version)
+                );
+                histogram.render("sabre_contract_instructions_by_contract", &labels, &mut out);
+            }
+        }
+
+        for (name, help, counter) in &[
+            (
+                "sabre_contract_cache_hits_total",
+                "Executions of contracts found in the contract cache",
+                &self.cache_hits,
+            ),
+            (
+                "sabre_contract_cache_misses_total",
+                "Executions of contracts which were loaded because they were not cached",
+                &self.cache_misses,
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, counter.load(Ordering::Relaxed));
+        }
+
+        out
+    }
+}
+
+/// A Prometheus histogram
+struct Histogram {
+    /// The upper bounds of the buckets
+    bounds: &'static [f64],
+    /// The number of observations in each bucket, not counting the observations of the smaller
+    /// buckets
+    buckets: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Histogram {
+        Histogram {
+            bounds,
+            buckets: vec![0; bounds.len()],
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+
+    /// Render the histogram, with `labels`, such as `contract="intkey"`, if it has any
+    fn render(&self, name: &str, labels: &str, out: &mut String) {
+        let (bucket_labels, labels) = if labels.is_empty() {
+            (String::new(), String::new())
+        } else {
+            (format!("{},", labels), format!("{{{}}}", labels))
+        };
+
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(self.buckets.iter()) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}le=\"{}\"}} {}",
+                name, bucket_labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}le=\"+Inf\"}} {}",
+            name, bucket_labels, self.count
+        );
+        let _ = writeln!(out, "{}_sum{} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, labels, self.count);
+    }
+}
+
+/// Escape a label value for the Prometheus text format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serve the metrics at `http://<address>/metrics` from a background thread
+///
+/// Fails if the address cannot be bound.
+pub fn serve(address: &str, metrics: Arc<Metrics>) -> Result<(), String> {
+    http::serve("metrics", address, move |path| match path {
+        "/metrics" => Response::new("200 OK", "text/plain; version=0.0.4", metrics.render()),
+        _ => Response::not_found("Metrics are served at /metrics\n"),
+    })
+}
+
+/// Return the name of an action used to label its transactions
+pub(crate) fn action_name(action: &Action) -> &'static str {
+    match action {
+        Action::CreateContract(_) => "create_contract",
+        Action::DeleteContract(_) => "delete_contract",
+        Action::ExecuteContract(_) => "execute_contract",
+        Action::CreateContractRegistry(_) => "create_contract_registry",
+        Action::DeleteContractRegistry(_) => "delete_contract_registry",
+        Action::UpdateContractRegistryOwners(_) => "update_contract_registry_owners",
+        Action::CreateNamespaceRegistry(_) => "create_namespace_registry",
+        Action::DeleteNamespaceRegistry(_) => "delete_namespace_registry",
+        Action::UpdateNamespaceRegistryOwners(_) => "update_namespace_registry_owners",
+        Action::CreateNamespaceRegistryPermission(_) => "create_namespace_registry_permission",
+        Action::DeleteNamespaceRegistryPermission(_) => "delete_namespace_registry_permission",
+        Action::CreateSmartPermission(_) => "create_smart_permission",
+        Action::UpdateSmartPermission(_) => "update_smart_permission",
+        Action::DeleteSmartPermission(_) => "delete_smart_permission",
+        Action::CreateContractChunk(_) => "create_contract_chunk",
+        Action::CommitContract(_) => "commit_contract",
+        Action::PauseContract(_) => "pause_contract",
+        Action::ResumeContract(_) => "resume_contract",
+    }
+}
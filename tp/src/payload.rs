@@ -77,10 +77,21 @@ impl SabreRequestPayload {
                         "Contract name cannot be an empty string",
                     )));
                 }
-                if execute_contract.version().is_empty() {
-                    return Err(ApplyError::InvalidTransaction(String::from(
-                        "Contract version cannot be an empty string",
-                    )));
+                match (
+                    execute_contract.version().is_empty(),
+                    execute_contract.version_requirement().is_empty(),
+                ) {
+                    (true, true) => {
+                        return Err(ApplyError::InvalidTransaction(String::from(
+                            "Contract version cannot be an empty string",
+                        )));
+                    }
+                    (false, false) => {
+                        return Err(ApplyError::InvalidTransaction(String::from(
+                            "Contract version and version requirement cannot both be set",
+                        )));
+                    }
+                    _ => (),
                 }
                 if execute_contract.inputs().is_empty() {
                     return Err(ApplyError::InvalidTransaction(String::from(
@@ -241,6 +252,79 @@ impl SabreRequestPayload {
                     ));
                 }
             }
+            Action::CreateContractChunk(create_contract_chunk) => {
+                if create_contract_chunk.name().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract name cannot be an empty string",
+                    )));
+                }
+                if create_contract_chunk.version().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract version cannot be an empty string",
+                    )));
+                }
+                if create_contract_chunk.chunk().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract chunk bytes cannot be an empty",
+                    )));
+                }
+            }
+            Action::CommitContract(commit_contract) => {
+                if commit_contract.name().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract name cannot be an empty string",
+                    )));
+                }
+                if commit_contract.version().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract version cannot be an empty string",
+                    )));
+                }
+                if commit_contract.inputs().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract inputs cannot be an empty",
+                    )));
+                }
+                if commit_contract.outputs().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract outputs cannot be an empty",
+                    )));
+                }
+                if commit_contract.chunk_count() == 0 {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract chunk count cannot be zero",
+                    )));
+                }
+                if commit_contract.contract_sha512().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract sha512 cannot be an empty string",
+                    )));
+                }
+            }
+            Action::PauseContract(pause_contract) => {
+                if pause_contract.name().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract name cannot be an empty string",
+                    )));
+                }
+                if pause_contract.version().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract version cannot be an empty string",
+                    )));
+                }
+            }
+            Action::ResumeContract(resume_contract) => {
+                if resume_contract.name().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract name cannot be an empty string",
+                    )));
+                }
+                if resume_contract.version().is_empty() {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Contract version cannot be an empty string",
+                    )));
+                }
+            }
         };
 
         Ok(Some(SabreRequestPayload {
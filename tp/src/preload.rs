@@ -0,0 +1,233 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reads contracts from the REST API of a validator when the transaction processor starts, so
+//! that its handlers can load them before the first transactions which execute them
+//!
+//! A transaction processor can only read state while it applies a transaction, so the state
+//! entries a handler reads to load a contract, its contract, its contract registry and the float
+//! policy and wasm protocol version settings, are read once from the REST API and kept in a `Preload`. Each handler then
+//! loads the contracts from the same entries with `with_preloaded_contracts`.
+
+use std::collections::HashMap;
+
+use sabre_sdk::protocol::state::ContractList;
+use sabre_sdk::protocol::{FLOAT_POLICY_SETTING_ADDRESS, WASM_PROTOCOL_VERSION_SETTING_ADDRESS};
+use sabre_sdk::protos::FromBytes;
+use sawtooth_sdk::processor::handler::{ContextError, TransactionContext};
+use serde_json::Value;
+
+use crate::addressing::{
+    make_contract_address, make_contract_registry_address, CONTRACT_PREFIX,
+    CONTRACT_REGISTRY_PREFIX,
+};
+use crate::http;
+
+/// The REST API of a validator running on the same host
+pub const DEFAULT_REST_API_URL: &str = "http://localhost:8008";
+
+/// The contracts to preload
+pub enum Contracts {
+    /// Every version of every contract in state
+    All,
+    /// The contracts with the given names and versions
+    Listed(Vec<(String, String)>),
+}
+
+/// The contracts to preload, with the state entries read to load them
+pub struct Preload {
+    contracts: Vec<(String, String)>,
+    state: HashMap<String, Vec<u8>>,
+}
+
+impl Preload {
+    /// Read the contracts, and the state entries their handlers read to load them, from the REST
+    /// API at `rest_api_url`
+    ///
+    /// A listed contract which is not in state is left for `with_preloaded_contracts` to report.
+    pub fn fetch(rest_api_url: &str, contracts: Contracts) -> Result<Preload, String> {
+        let rest_api_url = rest_api_url.trim_end_matches('/');
+        let mut state = HashMap::new();
+
+        let contracts = match contracts {
+            Contracts::All => {
+                for prefix in &[CONTRACT_PREFIX, CONTRACT_REGISTRY_PREFIX] {
+                    state.extend(get_state_with_prefix(rest_api_url, prefix)?);
+                }
+
+                let mut contracts = Vec::new();
+                for (address, data) in &state {
+                    if !address.starts_with(CONTRACT_PREFIX) {
+                        continue;
+                    }
+                    let contract_list = ContractList::from_bytes(data).map_err(|err| {
+                        format!("Unable to read contract list at {}: {}", address, err)
+                    })?;
+                    contracts.extend(
+                        contract_list
+                            .contracts()
+                            .iter()
+                            .map(|contract| (contract.name().into(), contract.version().into())),
+                    );
+                }
+                contracts.sort();
+                contracts
+            }
+            Contracts::Listed(contracts) => {
+                for (name, version) in &contracts {
+                    let contract_address =
+                        make_contract_address(name, version).map_err(|err| err.to_string())?;
+                    let registry_address =
+                        make_contract_registry_address(name).map_err(|err| err.to_string())?;
+                    for address in [contract_address, registry_address] {
+                        if let Some(data) = get_state_entry(rest_api_url, &address)? {
+                            state.insert(address, data);
+                        }
+                    }
+                }
+                contracts
+            }
+        };
+
+        for address in [
+            FLOAT_POLICY_SETTING_ADDRESS,
+            WASM_PROTOCOL_VERSION_SETTING_ADDRESS,
+        ] {
+            if let Some(data) = get_state_entry(rest_api_url, address)? {
+                state.insert(address.into(), data);
+            }
+        }
+
+        Ok(Preload { contracts, state })
+    }
+
+    /// The names and versions of the contracts to preload
+    pub fn contracts(&self) -> &[(String, String)] {
+        &self.contracts
+    }
+
+    /// Return a context reading the state entries which were read from the REST API
+    pub(crate) fn context(&self) -> PreloadContext<'_> {
+        PreloadContext { state: &self.state }
+    }
+}
+
+/// A context which reads the state entries of a `Preload`, and which cannot change state
+pub(crate) struct PreloadContext<'a> {
+    state: &'a HashMap<String, Vec<u8>>,
+}
+
+impl<'a> TransactionContext for PreloadContext<'a> {
+    fn get_state_entries(
+        &self,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, ContextError> {
+        Ok(addresses
+            .iter()
+            .filter_map(|address| {
+                self.state
+                    .get(address)
+                    .map(|data| (address.clone(), data.clone()))
+            })
+            .collect())
+    }
+
+    fn set_state_entries(&self, _entries: Vec<(String, Vec<u8>)>) -> Result<(), ContextError> {
+        Err(ContextError::AuthorizationError(
+            "Preloading contracts cannot set state".into(),
+        ))
+    }
+
+    fn delete_state_entries(&self, _addresses: &[String]) -> Result<Vec<String>, ContextError> {
+        Err(ContextError::AuthorizationError(
+            "Preloading contracts cannot delete state".into(),
+        ))
+    }
+
+    fn add_receipt_data(&self, _data: &[u8]) -> Result<(), ContextError> {
+        Ok(())
+    }
+
+    fn add_event(
+        &self,
+        _event_type: String,
+        _attributes: Vec<(String, String)>,
+        _data: &[u8],
+    ) -> Result<(), ContextError> {
+        Ok(())
+    }
+}
+
+/// Return the data stored at the address, or `None` if there is no state at the address
+fn get_state_entry(rest_api_url: &str, address: &str) -> Result<Option<Vec<u8>>, String> {
+    let url = format!("{}/state/{}", rest_api_url, address);
+    let (status, body) = http::get(&url)?;
+    if status == 404 {
+        return Ok(None);
+    }
+
+    let response = parse_response(&url, status, &body)?;
+    let data = response["data"]
+        .as_str()
+        .ok_or_else(|| format!("Unable to parse state response from {}", url))?;
+    decode(address, data).map(Some)
+}
+
+/// Return all state entries under the address prefix, reading every page of them
+fn get_state_with_prefix(
+    rest_api_url: &str,
+    prefix: &str,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut entries = Vec::new();
+    let mut next_page = Some(format!("{}/state?address={}", rest_api_url, prefix));
+
+    while let Some(url) = next_page {
+        let (status, body) = http::get(&url)?;
+        let page = parse_response(&url, status, &body)?;
+
+        let data = page["data"]
+            .as_array()
+            .ok_or_else(|| format!("Unable to parse state response from {}", url))?;
+        for entry in data {
+            match (entry["address"].as_str(), entry["data"].as_str()) {
+                (Some(address), Some(data)) => {
+                    entries.push((address.into(), decode(address, data)?))
+                }
+                _ => return Err(format!("Unable to parse state response from {}", url)),
+            }
+        }
+
+        next_page = page["paging"]["next"].as_str().map(String::from);
+    }
+
+    Ok(entries)
+}
+
+fn parse_response(url: &str, status: u16, body: &[u8]) -> Result<Value, String> {
+    if status != 200 {
+        return Err(format!(
+            "Unable to read state from {}: status {}: {}",
+            url,
+            status,
+            String::from_utf8_lossy(body).trim()
+        ));
+    }
+
+    serde_json::from_slice(body)
+        .map_err(|err| format!("Unable to parse state response from {}: {}", url, err))
+}
+
+fn decode(address: &str, data: &str) -> Result<Vec<u8>, String> {
+    base64::decode(data).map_err(|_| format!("Unable to decode state at {}", address))
+}
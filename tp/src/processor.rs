@@ -0,0 +1,56 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs Sabre transaction handlers as a transaction processor connected to a validator
+//!
+//! This is what the `sawtooth-sabre` binary does once it has parsed its options; a project which
+//! applies Sabre transactions itself, without a validator, only needs the handler.
+
+use std::time::Duration;
+
+use sawtooth_sdk::processor::TransactionProcessor;
+
+use crate::handler::SabreTransactionHandler;
+use crate::shutdown;
+use crate::worker_pool::WorkerPool;
+
+/// Connect to the validator at `endpoint` and process transactions with `workers` handlers
+/// created by `new_handler`, until the process is signalled to shut down
+///
+/// A single worker processes transactions with the Sawtooth SDK's `TransactionProcessor`, and
+/// more with a `WorkerPool`. The process exits if it has not shut down within `drain_timeout`
+/// of the signal.
+pub fn start<F>(
+    endpoint: &str,
+    workers: usize,
+    drain_timeout: Duration,
+    new_handler: F,
+) -> Result<(), String>
+where
+    F: Fn() -> SabreTransactionHandler + Send + Sync + 'static,
+{
+    shutdown::exit_after_drain_timeout(drain_timeout)?;
+
+    if workers > 1 {
+        WorkerPool::new(endpoint, workers, new_handler).start()?;
+    } else {
+        let handler = new_handler();
+        let mut processor = TransactionProcessor::new(endpoint);
+
+        processor.add_handler(&handler);
+        processor.start();
+    }
+
+    Ok(())
+}
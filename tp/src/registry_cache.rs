@@ -0,0 +1,230 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Caches the namespace registries and contract registries read by a transaction
+//!
+//! A transaction reads and writes state through a `RegistryContext`, which keeps the registries
+//! the transaction has read or written, so that each is requested from the validator at most
+//! once per transaction. Registries are not kept between transactions: the validator gives each
+//! transaction a context of its own, with the state of its own position in the chain, so a
+//! registry read by one transaction may have been written by another transaction processor, or
+//! by a block of another fork, by the time another reads it.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use sawtooth_sdk::processor::handler::{ContextError, TransactionContext};
+
+use crate::addressing::is_registry_address;
+
+/// The context of a transaction, which reads registries from the transaction's own reads and
+/// writes before requesting them from the validator
+pub struct RegistryContext<'a> {
+    context: &'a dyn TransactionContext,
+    /// The registries the transaction has read or written, which are `None` for a registry
+    /// which does not exist
+    entries: RefCell<HashMap<String, Option<Vec<u8>>>>,
+}
+
+impl<'a> RegistryContext<'a> {
+    pub fn new(context: &'a dyn TransactionContext) -> RegistryContext<'a> {
+        RegistryContext {
+            context,
+            entries: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Forget the registries at the addresses, which are about to be written or deleted
+    fn forget<'b, I>(&self, addresses: I)
+    where
+        I: IntoIterator<Item = &'b String>,
+    {
+        let mut entries = self.entries.borrow_mut();
+        for address in addresses {
+            if is_registry_address(address) {
+                entries.remove(address);
+            }
+        }
+    }
+}
+
+impl<'a> TransactionContext for RegistryContext<'a> {
+    fn get_state_entries(
+        &self,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, ContextError> {
+        let mut found = HashMap::new();
+        let mut requested = Vec::new();
+        {
+            let entries = self.entries.borrow();
+            for address in addresses {
+                match entries.get(address) {
+                    Some(data) => {
+                        found.insert(address.clone(), data.clone());
+                    }
+                    None => requested.push(address.clone()),
+                }
+            }
+        }
+
+        if !requested.is_empty() {
+            let fetched = self
+                .context
+                .get_state_entries(&requested)?
+                .into_iter()
+                .filter(|(_, data)| !data.is_empty())
+                .collect::<HashMap<_, _>>();
+
+            let mut entries = self.entries.borrow_mut();
+            for address in requested {
+                let data = fetched.get(&address).cloned();
+                if is_registry_address(&address) {
+                    entries.insert(address.clone(), data.clone());
+                }
+                found.insert(address, data);
+            }
+        }
+
+        Ok(addresses
+            .iter()
+            .filter_map(|address| match found.get(address) {
+                Some(Some(data)) => Some((address.clone(), data.clone())),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn set_state_entries(&self, entries: Vec<(String, Vec<u8>)>) -> Result<(), ContextError> {
+        self.forget(entries.iter().map(|(address, _)| address));
+        let registries = entries
+            .iter()
+            .filter(|(address, _)| is_registry_address(address))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        self.context.set_state_entries(entries)?;
+
+        let mut cached = self.entries.borrow_mut();
+        for (address, data) in registries {
+            cached.insert(address, Some(data).filter(|data| !data.is_empty()));
+        }
+        Ok(())
+    }
+
+    fn delete_state_entries(&self, addresses: &[String]) -> Result<Vec<String>, ContextError> {
+        self.forget(addresses);
+
+        let deleted = self.context.delete_state_entries(addresses)?;
+
+        let mut cached = self.entries.borrow_mut();
+        for address in addresses {
+            if is_registry_address(address) {
+                cached.insert(address.clone(), None);
+            }
+        }
+        Ok(deleted)
+    }
+
+    fn add_receipt_data(&self, data: &[u8]) -> Result<(), ContextError> {
+        self.context.add_receipt_data(data)
+    }
+
+    fn add_event(
+        &self,
+        event_type: String,
+        attributes: Vec<(String, String)>,
+        data: &[u8],
+    ) -> Result<(), ContextError> {
+        self.context.add_event(event_type, attributes, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    use crate::addressing::make_namespace_registry_address;
+    use crate::bench::context::{Changes, MemoryContext};
+
+    /// A context which counts the reads it is asked for
+    struct CountingContext<'a> {
+        context: MemoryContext<'a>,
+        reads: Cell<usize>,
+    }
+
+    impl<'a> TransactionContext for CountingContext<'a> {
+        fn get_state_entries(
+            &self,
+            addresses: &[String],
+        ) -> Result<Vec<(String, Vec<u8>)>, ContextError> {
+            self.reads.set(self.reads.get() + addresses.len());
+            self.context.get_state_entries(addresses)
+        }
+
+        fn set_state_entries(&self, entries: Vec<(String, Vec<u8>)>) -> Result<(), ContextError> {
+            self.context.set_state_entries(entries)
+        }
+
+        fn delete_state_entries(&self, addresses: &[String]) -> Result<Vec<String>, ContextError> {
+            self.context.delete_state_entries(addresses)
+        }
+
+        fn add_receipt_data(&self, data: &[u8]) -> Result<(), ContextError> {
+            self.context.add_receipt_data(data)
+        }
+
+        fn add_event(
+            &self,
+            event_type: String,
+            attributes: Vec<(String, String)>,
+            data: &[u8],
+        ) -> Result<(), ContextError> {
+            self.context.add_event(event_type, attributes, data)
+        }
+    }
+
+    #[test]
+    fn registries_are_read_once_per_transaction() {
+        let address = make_namespace_registry_address("abcdef").unwrap();
+        let inputs = vec![address.clone()];
+        let mut state = HashMap::new();
+        state.insert(address.clone(), b"registry".to_vec());
+        let batch = Changes::new();
+        let context = CountingContext {
+            context: MemoryContext::new(&state, &batch, &inputs, &inputs),
+            reads: Cell::new(0),
+        };
+
+        let registry_context = RegistryContext::new(&context);
+        for _ in 0..2 {
+            assert_eq!(
+                registry_context.get_state_entries(&inputs).unwrap(),
+                vec![(address.clone(), b"registry".to_vec())]
+            );
+        }
+        assert_eq!(context.reads.get(), 1);
+
+        // A registry the transaction writes is read as it was written
+        registry_context
+            .set_state_entries(vec![(address.clone(), b"written".to_vec())])
+            .unwrap();
+        assert_eq!(
+            registry_context.get_state_entries(&inputs).unwrap(),
+            vec![(address.clone(), b"written".to_vec())]
+        );
+        assert_eq!(context.reads.get(), 1);
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bounds the time the transaction processor takes to shut down
+//!
+//! The Sawtooth SDK handles SIGINT, SIGTERM and SIGHUP by letting the transaction being
+//! processed finish, unregistering from the validator and returning from
+//! `TransactionProcessor::start`, as does `WorkerPool::start`. A contract which is still
+//! executing, or a validator which does not answer, can delay that indefinitely, so the process
+//! exits once the drain timeout has passed since the signal.
+//!
+//! The SDK and the worker pool only install their signal handlers if no others are installed, so
+//! the handlers here are installed after theirs, and call them.
+
+use std::mem;
+use std::process;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use libc::c_int;
+
+/// The time the transaction processor may take to shut down by default
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The signals the SDK may shut the transaction processor down on
+const SIGNALS: [c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+/// How often the SDK's signal handlers, and then a signal, are checked for
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// The SDK's or worker pool's handlers of `SIGNALS`
+static SDK_HANDLERS: [AtomicUsize; 3] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+/// Exit the process with status 1 if it is still running `drain_timeout` after it is signalled
+/// to shut down
+pub fn exit_after_drain_timeout(drain_timeout: Duration) -> Result<(), String> {
+    thread::Builder::new()
+        .name("shutdown".into())
+        .spawn(move || {
+            // The SDK installs its handlers when the transaction processor starts, starting
+            // with SIGINT and SIGTERM
+            while sdk_handler(libc::SIGINT).is_none() || sdk_handler(libc::SIGTERM).is_none() {
+                thread::sleep(POLL_INTERVAL);
+            }
+            thread::sleep(POLL_INTERVAL);
+            if let Err(err) = install_handlers() {
+                warn!("Shutdown will not be limited by the drain timeout: {}", err);
+                return;
+            }
+
+            while !SIGNALLED.load(Ordering::SeqCst) {
+                thread::sleep(POLL_INTERVAL);
+            }
+            info!(
+                "Shutting down, waiting up to {} ms for the transaction being processed",
+                drain_timeout.as_millis()
+            );
+            thread::sleep(drain_timeout);
+            error!(
+                "Transaction processor did not shut down within {} ms, exiting",
+                drain_timeout.as_millis()
+            );
+            process::exit(1);
+        })
+        .map(|_| ())
+        .map_err(|err| format!("Unable to start shutdown thread: {}", err))
+}
+
+/// Return the action installed for a signal, unless it is the default action
+fn sdk_handler(signal: c_int) -> Option<libc::sigaction> {
+    // Safety: a null action only queries the installed action
+    unsafe {
+        let mut installed: libc::sigaction = mem::zeroed();
+        if libc::sigaction(signal, ptr::null(), &mut installed) != 0 {
+            return None;
+        }
+        if installed.sa_sigaction == libc::SIG_DFL {
+            None
+        } else {
+            Some(installed)
+        }
+    }
+}
+
+fn install_handlers() -> Result<(), String> {
+    for (index, signal) in SIGNALS.iter().enumerate() {
+        let sdk = match sdk_handler(*signal) {
+            Some(sdk) => sdk,
+            None => continue,
+        };
+        if sdk.sa_flags & libc::SA_SIGINFO != 0 || sdk.sa_sigaction == libc::SIG_IGN {
+            return Err(format!("signal {} is handled in an unexpected way", signal));
+        }
+        SDK_HANDLERS[index].store(sdk.sa_sigaction, Ordering::SeqCst);
+
+        // Safety: `on_signal` only touches atomics and calls the SDK's handler, which are
+        // async-signal-safe
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = on_signal as extern "C" fn(c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(*signal, &action, ptr::null_mut()) != 0 {
+                return Err(format!("unable to handle signal {}", signal));
+            }
+        }
+    }
+    Ok(())
+}
+
+extern "C" fn on_signal(signal: c_int) {
+    SIGNALLED.store(true, Ordering::SeqCst);
+    if let Some(index) = SIGNALS.iter().position(|handled| *handled == signal) {
+        let sdk = SDK_HANDLERS[index].load(Ordering::SeqCst);
+        if sdk != 0 {
+            // Safety: the SDK's handler was installed without SA_SIGINFO, so it takes only the
+            // signal
+            let sdk: extern "C" fn(c_int) = unsafe { mem::transmute(sdk) };
+            sdk(signal);
+        }
+    }
+}
@@ -15,19 +15,26 @@ use protobuf::Message;
 
 use sabre_sdk::protocol::pike::state::{Agent, AgentList, Organization, OrganizationList};
 use sabre_sdk::protocol::state::{
-    Contract, ContractList, ContractListBuilder, ContractRegistry, ContractRegistryList,
-    ContractRegistryListBuilder, NamespaceRegistry, NamespaceRegistryList,
+    AuditEntry, AuditLog, AuditLogBuilder, Contract, ContractChunk, ContractChunkList,
+    ContractChunkListBuilder, ContractList, ContractListBuilder, ContractRegistry,
+    ContractRegistryList, ContractRegistryListBuilder, NamespaceRegistry, NamespaceRegistryList,
     NamespaceRegistryListBuilder, SmartPermission, SmartPermissionList, SmartPermissionListBuilder,
 };
-use sabre_sdk::protocol::ADMINISTRATORS_SETTING_ADDRESS;
+use sabre_sdk::protocol::{
+    ADMINISTRATORS_SETTING_ADDRESS, AUDIT_SETTING_ADDRESS, BLOCK_INFO_CONFIG_ADDRESS,
+    FLOAT_POLICY_SETTING_ADDRESS, MAX_CONTRACT_SIZE_SETTING_ADDRESS,
+    WASM_PROTOCOL_VERSION_SETTING_ADDRESS,
+};
 use sabre_sdk::protos::{FromBytes, IntoBytes};
+use sawtooth_sdk::messages::block_info::{BlockInfo, BlockInfoConfig};
 use sawtooth_sdk::messages::setting::Setting;
-use sawtooth_sdk::processor::handler::ApplyError;
 use sawtooth_sdk::processor::handler::TransactionContext;
+use sawtooth_sdk::processor::handler::{ApplyError, ContextError};
 
 use crate::addressing::{
     compute_agent_address, compute_org_address, compute_smart_permission_address,
-    make_contract_address, make_contract_registry_address, make_namespace_registry_address,
+    make_audit_log_address, make_block_info_address, make_contract_address,
+    make_contract_chunk_address, make_contract_registry_address, make_namespace_registry_address,
 };
 
 pub struct SabreState<'a> {
@@ -44,25 +51,113 @@ impl<'a> SabreState<'a> {
     }
 
     pub fn get_admin_setting(&mut self) -> Result<Option<Setting>, ApplyError> {
-        let d = self
-            .context
-            .get_state_entry(ADMINISTRATORS_SETTING_ADDRESS)?;
-        match d {
+        self.get_setting(ADMINISTRATORS_SETTING_ADDRESS)
+    }
+
+    pub fn get_float_policy_setting(&mut self) -> Result<Option<Setting>, ApplyError> {
+        self.get_setting(FLOAT_POLICY_SETTING_ADDRESS)
+    }
+
+    pub fn get_max_contract_size_setting(&mut self) -> Result<Option<Setting>, ApplyError> {
+        self.get_setting(MAX_CONTRACT_SIZE_SETTING_ADDRESS)
+    }
+
+    pub fn get_audit_setting(&mut self) -> Result<Option<Setting>, ApplyError> {
+        self.get_setting(AUDIT_SETTING_ADDRESS)
+    }
+
+    /// Returns the wasm protocol version setting, or `None` if it is not set or the transaction
+    /// may not read it, as transactions built before the setting existed do not have it among
+    /// their inputs
+    pub fn get_wasm_protocol_version_setting(&mut self) -> Result<Option<Setting>, ApplyError> {
+        self.get_optional_setting(WASM_PROTOCOL_VERSION_SETTING_ADDRESS)
+    }
+
+    fn get_setting(&mut self, address: &str) -> Result<Option<Setting>, ApplyError> {
+        parse_setting(self.context.get_state_entry(address)?)
+    }
+
+    /// Returns a setting which transactions built before it existed do not have among their
+    /// inputs, treating it as unset if the transaction may not read it
+    fn get_optional_setting(&mut self, address: &str) -> Result<Option<Setting>, ApplyError> {
+        match self.context.get_state_entry(address) {
+            Ok(entry) => parse_setting(entry),
+            Err(ContextError::AuthorizationError(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns the latest block recorded by the Block Info transaction family, or `None` if there
+    /// is none or the transaction may not read it
+    pub fn get_latest_block_info(&mut self) -> Result<Option<BlockInfo>, ApplyError> {
+        let config: BlockInfoConfig = match self.get_block_info_entry(BLOCK_INFO_CONFIG_ADDRESS)? {
+            Some(packed) => Message::parse_from_bytes(packed.as_slice()).map_err(|err| {
+                ApplyError::InvalidTransaction(format!(
+                    "Cannot deserialize block info config: {:?}",
+                    err,
+                ))
+            })?,
+            None => return Ok(None),
+        };
+
+        let address = make_block_info_address(config.get_latest_block());
+        match self.get_block_info_entry(&address)? {
             Some(packed) => {
-                let setting: Setting =
+                let block_info: BlockInfo =
                     Message::parse_from_bytes(packed.as_slice()).map_err(|err| {
                         ApplyError::InvalidTransaction(format!(
-                            "Cannot deserialize setting: {:?}",
+                            "Cannot deserialize block info: {:?}",
                             err,
                         ))
                     })?;
-
-                Ok(Some(setting))
+                Ok(Some(block_info))
             }
             None => Ok(None),
         }
     }
 
+    fn get_block_info_entry(&mut self, address: &str) -> Result<Option<Vec<u8>>, ApplyError> {
+        match self.context.get_state_entry(address) {
+            Ok(entry) => Ok(entry),
+            // The block info is not among the transaction's inputs
+            Err(ContextError::AuthorizationError(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn get_audit_log(&mut self, registry_address: &str) -> Result<AuditLog, ApplyError> {
+        let address = make_audit_log_address(registry_address)?;
+        let d = self.context.get_state_entry(&address)?;
+        match d {
+            Some(packed) => AuditLog::from_bytes(packed.as_slice()).map_err(|err| {
+                ApplyError::InvalidTransaction(format!("Cannot deserialize audit log: {:?}", err,))
+            }),
+            None => Ok(AuditLog::default()),
+        }
+    }
+
+    pub fn add_audit_entry(
+        &mut self,
+        registry_address: &str,
+        entry: AuditEntry,
+    ) -> Result<(), ApplyError> {
+        let mut entries = self.get_audit_log(registry_address)?.entries().to_vec();
+        entries.push(entry);
+
+        let audit_log = AuditLogBuilder::new()
+            .with_entries(entries)
+            .build()
+            .map_err(|_| ApplyError::InvalidTransaction(String::from("Cannot build audit log")))?;
+
+        let serialized = audit_log.into_bytes().map_err(|err| {
+            ApplyError::InvalidTransaction(format!("Cannot serialize audit log: {:?}", err,))
+        })?;
+        self.context
+            .set_state_entry(make_audit_log_address(registry_address)?, serialized)
+            .map_err(|err| ApplyError::InvalidTransaction(format!("{}", err)))?;
+        Ok(())
+    }
+
     pub fn get_contract(
         &mut self,
         name: &str,
@@ -156,6 +251,106 @@ impl<'a> SabreState<'a> {
         Ok(())
     }
 
+    pub fn get_contract_chunk(
+        &mut self,
+        name: &str,
+        version: &str,
+        index: u32,
+    ) -> Result<Option<ContractChunk>, ApplyError> {
+        let address = make_contract_chunk_address(name, version, index)?;
+        let d = self.context.get_state_entry(&address)?;
+        match d {
+            Some(packed) => {
+                let chunks = ContractChunkList::from_bytes(packed.as_slice()).map_err(|err| {
+                    ApplyError::InvalidTransaction(format!(
+                        "Cannot deserialize contract chunk list: {:?}",
+                        err,
+                    ))
+                })?;
+                Ok(chunks
+                    .chunks()
+                    .iter()
+                    .find(|c| c.name() == name && c.version() == version && c.index() == index)
+                    .cloned())
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_contract_chunk(&mut self, new_chunk: ContractChunk) -> Result<(), ApplyError> {
+        let name = new_chunk.name().to_string();
+        let version = new_chunk.version().to_string();
+        let index = new_chunk.index();
+        let address = make_contract_chunk_address(&name, &version, index)?;
+        let d = self.context.get_state_entry(&address)?;
+        let mut chunks = match d {
+            Some(packed) => match ContractChunkList::from_bytes(packed.as_slice()) {
+                Ok(chunks) => {
+                    // remove old chunk if it exists
+                    chunks
+                        .chunks()
+                        .iter()
+                        .filter(|c| {
+                            !(c.name() == name && c.version() == version && c.index() == index)
+                        })
+                        .cloned()
+                        .collect::<Vec<ContractChunk>>()
+                }
+                Err(err) => {
+                    return Err(ApplyError::InvalidTransaction(format!(
+                        "Cannot deserialize contract chunk list: {}",
+                        err,
+                    )));
+                }
+            },
+            None => vec![],
+        };
+        chunks.push(new_chunk);
+
+        // build new ContractChunkList and set in state
+        let chunk_list = ContractChunkListBuilder::new()
+            .with_chunks(chunks)
+            .build()
+            .map_err(|_| {
+                ApplyError::InvalidTransaction(String::from("Cannot build contract chunk list"))
+            })?;
+
+        let serialized = chunk_list.into_bytes().map_err(|err| {
+            ApplyError::InvalidTransaction(format!(
+                "Cannot serialize contract chunk list: {:?}",
+                err,
+            ))
+        })?;
+        self.context
+            .set_state_entry(address, serialized)
+            .map_err(|err| ApplyError::InvalidTransaction(format!("{}", err)))?;
+        Ok(())
+    }
+
+    pub fn delete_contract_chunk(
+        &mut self,
+        name: &str,
+        version: &str,
+        index: u32,
+    ) -> Result<(), ApplyError> {
+        let address = make_contract_chunk_address(name, version, index)?;
+        let d = self.context.delete_state_entry(&address)?;
+        let deleted = match d {
+            Some(deleted) => deleted,
+            None => {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "Cannot delete contract chunk",
+                )));
+            }
+        };
+        if deleted != address {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "Cannot delete contract chunk",
+            )));
+        };
+        Ok(())
+    }
+
     pub fn get_contract_registry(
         &mut self,
         name: &str,
@@ -508,3 +703,16 @@ impl<'a> SabreState<'a> {
         }
     }
 }
+
+fn parse_setting(entry: Option<Vec<u8>>) -> Result<Option<Setting>, ApplyError> {
+    match entry {
+        Some(packed) => {
+            let setting: Setting = Message::parse_from_bytes(packed.as_slice()).map_err(|err| {
+                ApplyError::InvalidTransaction(format!("Cannot deserialize setting: {:?}", err,))
+            })?;
+
+            Ok(Some(setting))
+        }
+        None => Ok(None),
+    }
+}
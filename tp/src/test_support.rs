@@ -0,0 +1,356 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The contracts, state and transactions the unit tests are built from
+//!
+//! State is kept in a `HashMap` of addresses to entries, written with `write` and the `store_*`
+//! functions, and transactions signed by `SIGNER` are applied to it with `apply` and the other
+//! `apply_*` functions. Every transaction has the signature "test".
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
+use parity_wasm::builder;
+use parity_wasm::elements::{BlockType, Instruction, Instructions, Local, ValueType};
+use protobuf::Message;
+use protobuf::RepeatedField;
+use sabre_sdk::protocol::payload::{
+    ActionBuildError, CommitContractActionBuilder, CreateContractActionBuilder,
+    CreateContractChunkActionBuilder, CreateContractRegistryActionBuilder,
+    ExecuteContractActionBuilder, SabrePayloadBuilder,
+};
+use sabre_sdk::protocol::state::{
+    ContractBuilder, ContractRegistryBuilder, NamespaceRegistryBuilder, PermissionBuilder,
+};
+use sabre_sdk::protocol::FLOAT_POLICY_SETTING_ADDRESS;
+use sabre_sdk::protos::IntoBytes;
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::messages::setting::{Setting, Setting_Entry};
+use sawtooth_sdk::messages::transaction::TransactionHeader;
+use sawtooth_sdk::processor::handler::{ApplyError, TransactionHandler};
+
+use crate::addressing::{
+    make_contract_address, make_contract_registry_address, make_namespace_registry_address,
+};
+use crate::bench::context::{Changes, MemoryContext};
+use crate::handler::SabreTransactionHandler;
+use crate::state::SabreState;
+
+/// The signer of every transaction, who owns the registries the tests store
+pub const SIGNER: &str = "0283a0a7b1637599d9c2d67ac7e2411b3e2ba0e4fd9fd6e412572ad2f9ab23e832";
+
+/// The namespace contracts read and write
+pub const NAMESPACE: &str = "abcdef";
+
+/// Return a contract whose entrypoint runs the instructions, which leave its result
+pub fn contract(instructions: Vec<Instruction>) -> Vec<u8> {
+    let module = builder::module()
+        .function()
+        .signature()
+        .with_params(vec![ValueType::I32; 3])
+        .with_result(ValueType::I32)
+        .build()
+        .body()
+        .with_instructions(Instructions::new(instructions))
+        .build()
+        .build()
+        .export()
+        .field("entrypoint")
+        .internal()
+        .func(0)
+        .build()
+        .build();
+    parity_wasm::serialize(module).expect("Unable to serialize contract")
+}
+
+/// Return a module whose entrypoint takes `params` parameters and counts to 1000 before
+/// returning 1
+pub fn counting_module(params: usize) -> Vec<u8> {
+    let counter = params as u32;
+    let module = builder::module()
+        .function()
+        .signature()
+        .with_params(vec![ValueType::I32; params])
+        .with_result(ValueType::I32)
+        .build()
+        .body()
+        .with_locals(vec![Local::new(1, ValueType::I32)])
+        .with_instructions(Instructions::new(vec![
+            Instruction::Loop(BlockType::NoResult),
+            Instruction::GetLocal(counter),
+            Instruction::I32Const(1),
+            Instruction::I32Add,
+            Instruction::TeeLocal(counter),
+            Instruction::I32Const(1000),
+            Instruction::I32LtU,
+            Instruction::BrIf(0),
+            Instruction::End,
+            Instruction::I32Const(1),
+            Instruction::End,
+        ]))
+        .build()
+        .build()
+        .export()
+        .field("entrypoint")
+        .internal()
+        .func(0)
+        .build()
+        .build();
+    parity_wasm::serialize(module).expect("Unable to serialize module")
+}
+
+/// Write to the state as a transaction which may write every address would
+pub fn write<F>(entries: &mut HashMap<String, Vec<u8>>, f: F)
+where
+    F: FnOnce(&mut SabreState) -> Result<(), ApplyError>,
+{
+    let everything = vec![String::new()];
+    let batch = Changes::new();
+    let mut context = MemoryContext::new(entries, &batch, &everything, &everything);
+    f(&mut SabreState::new(&mut context)).expect("Unable to write state");
+    let changes = context.into_changes();
+    keep(entries, changes);
+}
+
+/// Store a version of a contract, and permit it to read and write the namespace
+pub fn store_contract(
+    entries: &mut HashMap<String, Vec<u8>>,
+    name: &str,
+    version: &str,
+    wasm: &[u8],
+) {
+    let contract = ContractBuilder::new()
+        .with_name(name.into())
+        .with_version(version.into())
+        .with_inputs(vec![NAMESPACE.into()])
+        .with_outputs(vec![NAMESPACE.into()])
+        .with_creator(SIGNER.into())
+        .with_contract(wasm.to_vec())
+        .build()
+        .expect("Unable to build contract");
+    let permission = PermissionBuilder::new()
+        .with_contract_name(name.into())
+        .with_read(true)
+        .with_write(true)
+        .build()
+        .expect("Unable to build permission");
+    write(entries, |state| {
+        let mut permissions = state
+            .get_namespace_registry(NAMESPACE)?
+            .map(|registry| registry.permissions().to_vec())
+            .unwrap_or_default();
+        permissions.push(permission);
+        let namespace_registry = NamespaceRegistryBuilder::new()
+            .with_namespace(NAMESPACE.into())
+            .with_owners(vec![SIGNER.into()])
+            .with_permissions(permissions)
+            .build()
+            .expect("Unable to build namespace registry");
+        state.set_contract(name, version, contract)?;
+        state.set_namespace_registry(NAMESPACE, namespace_registry)
+    });
+}
+
+/// Store a setting with one entry
+pub fn store_setting(
+    entries: &mut HashMap<String, Vec<u8>>,
+    address: &str,
+    key: &str,
+    value: &str,
+) {
+    let mut entry = Setting_Entry::new();
+    entry.set_key(key.into());
+    entry.set_value(value.into());
+    let mut setting = Setting::new();
+    setting.set_entries(RepeatedField::from_vec(vec![entry]));
+    entries.insert(
+        address.into(),
+        setting
+            .write_to_bytes()
+            .expect("Unable to serialize setting"),
+    );
+}
+
+/// Store an empty contract registry owned by the signer
+pub fn store_contract_registry(entries: &mut HashMap<String, Vec<u8>>, name: &str) {
+    let contract_registry = ContractRegistryBuilder::new()
+        .with_name(name.into())
+        .with_owners(vec![SIGNER.into()])
+        .build()
+        .expect("Unable to build contract registry");
+    write(entries, |state| {
+        state.set_contract_registry(name, contract_registry)
+    });
+}
+
+/// Return the serialized payload of the action an action builder built
+pub fn payload(builder: Result<SabrePayloadBuilder, ActionBuildError>) -> Vec<u8> {
+    builder
+        .expect("Unable to build action")
+        .build()
+        .expect("Unable to build payload")
+        .into_bytes()
+        .expect("Unable to serialize payload")
+}
+
+/// Return the payload creating a version of a contract
+pub fn create_payload(name: &str, version: &str, wasm: &[u8]) -> Vec<u8> {
+    payload(
+        CreateContractActionBuilder::new()
+            .with_name(name.into())
+            .with_version(version.into())
+            .with_inputs(vec![NAMESPACE.into()])
+            .with_outputs(vec![NAMESPACE.into()])
+            .with_contract(wasm.to_vec())
+            .into_payload_builder(),
+    )
+}
+
+/// Return the payload executing a version of a contract which reads and writes the namespace
+pub fn execute_payload(name: &str, version: &str) -> Vec<u8> {
+    execute_payload_with(name, version, b"test")
+}
+
+/// Return the payload executing a version of a contract with the payload of the contract
+pub fn execute_payload_with(name: &str, version: &str, contract_payload: &[u8]) -> Vec<u8> {
+    payload(
+        ExecuteContractActionBuilder::new()
+            .with_name(name.into())
+            .with_version(version.into())
+            .with_inputs(vec![NAMESPACE.into()])
+            .with_outputs(vec![NAMESPACE.into()])
+            .with_payload(contract_payload.to_vec())
+            .into_payload_builder(),
+    )
+}
+
+/// Return the payload creating a contract registry owned by the signer
+pub fn create_contract_registry_payload(name: &str) -> Vec<u8> {
+    payload(
+        CreateContractRegistryActionBuilder::new()
+            .with_name(name.into())
+            .with_owners(vec![SIGNER.into()])
+            .into_payload_builder(),
+    )
+}
+
+/// Return the payload uploading a chunk of version 1.0 of the test contract
+pub fn chunk_payload(index: u32, chunk: &[u8]) -> Vec<u8> {
+    payload(
+        CreateContractChunkActionBuilder::new()
+            .with_name("test".into())
+            .with_version("1.0".into())
+            .with_index(index)
+            .with_chunk(chunk.to_vec())
+            .into_payload_builder(),
+    )
+}
+
+/// Return the payload committing version 1.0 of the test contract from its chunks
+pub fn commit_payload(chunk_count: u32, wasm: &[u8]) -> Vec<u8> {
+    let mut sha = Sha512::new();
+    sha.input(wasm);
+    payload(
+        CommitContractActionBuilder::new()
+            .with_name("test".into())
+            .with_version("1.0".into())
+            .with_inputs(vec![NAMESPACE.into()])
+            .with_outputs(vec![NAMESPACE.into()])
+            .with_chunk_count(chunk_count)
+            .with_contract_sha512(sha.result_str())
+            .into_payload_builder(),
+    )
+}
+
+/// The inputs a transaction executing a contract has if it was built before the wasm protocol
+/// version setting existed
+pub fn execute_inputs(name: &str, version: &str) -> Vec<String> {
+    vec![
+        make_contract_address(name, version).unwrap(),
+        make_contract_registry_address(name).unwrap(),
+        make_namespace_registry_address(NAMESPACE).unwrap(),
+        FLOAT_POLICY_SETTING_ADDRESS.into(),
+        NAMESPACE.into(),
+    ]
+}
+
+/// Apply a transaction with the payload and the inputs, which are also its outputs
+pub fn apply(
+    handler: &SabreTransactionHandler,
+    entries: &HashMap<String, Vec<u8>>,
+    payload: Vec<u8>,
+    inputs: Vec<String>,
+) -> Result<Changes, ApplyError> {
+    apply_with_outputs(handler, entries, payload, inputs.clone(), inputs)
+}
+
+/// Apply a transaction with the payload, the inputs and the outputs, returning its changes
+pub fn apply_with_outputs(
+    handler: &SabreTransactionHandler,
+    entries: &HashMap<String, Vec<u8>>,
+    payload: Vec<u8>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+) -> Result<Changes, ApplyError> {
+    let mut header = TransactionHeader::new();
+    header.set_signer_public_key(SIGNER.into());
+    header.set_inputs(RepeatedField::from_vec(inputs));
+    header.set_outputs(RepeatedField::from_vec(outputs));
+    let mut request = TpProcessRequest::new();
+    request.set_payload(payload);
+    request.set_signature("test".into());
+    request.set_header(header);
+
+    let batch = Changes::new();
+    let mut context = MemoryContext::new(
+        entries,
+        &batch,
+        request.get_header().get_inputs(),
+        request.get_header().get_outputs(),
+    );
+    handler.apply(&request, &mut context)?;
+    Ok(context.into_changes())
+}
+
+/// Apply a transaction which may read and write every address, keeping its changes
+pub fn apply_to(
+    handler: &SabreTransactionHandler,
+    entries: &mut HashMap<String, Vec<u8>>,
+    payload: Vec<u8>,
+) -> Result<(), ApplyError> {
+    let changes = apply(handler, entries, payload, vec![String::new()])?;
+    keep(entries, changes);
+    Ok(())
+}
+
+/// Assert that a transaction was invalid, with a message containing `expected`
+pub fn assert_invalid<T: Debug>(result: Result<T, ApplyError>, expected: &str) {
+    match result {
+        Err(ApplyError::InvalidTransaction(message)) => {
+            assert!(message.contains(expected), "{}", message)
+        }
+        result => panic!("Expected \"{}\", found {:?}", expected, result),
+    }
+}
+
+fn keep(entries: &mut HashMap<String, Vec<u8>>, changes: Changes) {
+    for (address, data) in changes {
+        match data {
+            Some(data) => entries.insert(address, data),
+            None => entries.remove(&address),
+        };
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Keeps the floating-point arithmetic of contracts deterministic
+//!
+//! WebAssembly floating-point arithmetic is deterministic except for the bits of the NaNs it
+//! produces, which may differ between CPUs and runtimes. A contract which stores those bits in
+//! state, or branches on them, can make validators disagree on the result of its transaction.
+//! The float policy of a network either rejects contracts which use floating-point
+//! instructions, or rewrites them so that every NaN they compute is the canonical NaN.
+
+use std::fmt;
+use std::str::FromStr;
+
+use parity_wasm::elements::{FuncBody, Instruction, Local, Module, Type, ValueType};
+
+use crate::wasm_executor::wasm_externals::ExternalsError;
+
+/// The canonical 32-bit NaN, which has only the most significant bit of its mantissa set
+const CANONICAL_NAN_F32: u32 = 0x7fc0_0000;
+
+/// The canonical 64-bit NaN, which has only the most significant bit of its mantissa set
+const CANONICAL_NAN_F64: u64 = 0x7ff8_0000_0000_0000;
+
+/// How the floating-point instructions of contracts are handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatPolicy {
+    /// Contracts are executed as they are
+    Allow,
+    /// Contracts which contain floating-point instructions fail to load
+    Reject,
+    /// Every NaN a contract computes is replaced by the canonical NaN
+    Canonicalize,
+}
+
+impl fmt::Display for FloatPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FloatPolicy::Allow => write!(f, "allow"),
+            FloatPolicy::Reject => write!(f, "reject"),
+            FloatPolicy::Canonicalize => write!(f, "canonicalize"),
+        }
+    }
+}
+
+impl FromStr for FloatPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<FloatPolicy, String> {
+        match policy {
+            "allow" => Ok(FloatPolicy::Allow),
+            "reject" => Ok(FloatPolicy::Reject),
+            "canonicalize" => Ok(FloatPolicy::Canonicalize),
+            _ => Err(format!(
+                "Float policy must be allow, reject or canonicalize, not {}",
+                policy
+            )),
+        }
+    }
+}
+
+/// Apply the float policy to a contract, failing if the policy rejects it
+pub fn apply_float_policy(module: &mut Module, policy: FloatPolicy) -> Result<(), ExternalsError> {
+    match policy {
+        FloatPolicy::Allow => Ok(()),
+        FloatPolicy::Reject => reject_floats(module),
+        FloatPolicy::Canonicalize => canonicalize_nans(module),
+    }
+}
+
+fn reject_floats(module: &Module) -> Result<(), ExternalsError> {
+    let global_inits = module
+        .global_section()
+        .map(|section| section.entries())
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|global| global.init_expr().code());
+    let code = module
+        .code_section()
+        .map(|section| section.bodies())
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|body| body.code().elements());
+
+    match global_inits
+        .chain(code)
+        .find(|instruction| is_float(instruction))
+    {
+        Some(instruction) => Err(ExternalsError::from(format!(
+            "Contract contains the floating-point instruction {}, which is not allowed",
+            instruction
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn canonicalize_nans(module: &mut Module) -> Result<(), ExternalsError> {
+    let functions = module
+        .function_section()
+        .map(|section| section.entries().to_vec())
+        .unwrap_or_default();
+    let types = module
+        .type_section()
+        .map(|section| section.types().to_vec())
+        .unwrap_or_default();
+
+    if let Some(code) = module.code_section_mut() {
+        for (index, body) in code.bodies_mut().iter_mut().enumerate() {
+            let params = functions
+                .get(index)
+                .and_then(|function| types.get(function.type_ref() as usize))
+                .map(|Type::Function(function_type)| function_type.params().len())
+                .ok_or_else(|| ExternalsError::from(format!("Function {} has no type", index)))?;
+            canonicalize_body(body, params as u32);
+        }
+    }
+
+    Ok(())
+}
+
+/// Follow each instruction of a function which may compute a NaN with code which replaces the
+/// result by the canonical NaN if it is a NaN
+fn canonicalize_body(body: &mut FuncBody, params: u32) {
+    let code = body.code().elements();
+    let needs_f32 = code
+        .iter()
+        .any(|instruction| produces_nan(instruction) == Some(ValueType::F32));
+    let needs_f64 = code
+        .iter()
+        .any(|instruction| produces_nan(instruction) == Some(ValueType::F64));
+    if !needs_f32 && !needs_f64 {
+        return;
+    }
+
+    // The result is kept in a new local while it is compared with itself, which is only false
+    // for a NaN
+    let mut next_local = params + body.locals().iter().map(|local| local.count()).sum::<u32>();
+    let mut scratch_local = |value_type| {
+        body.locals_mut().push(Local::new(1, value_type));
+        next_local += 1;
+        next_local - 1
+    };
+    let f32_local = if needs_f32 {
+        scratch_local(ValueType::F32)
+    } else {
+        0
+    };
+    let f64_local = if needs_f64 {
+        scratch_local(ValueType::F64)
+    } else {
+        0
+    };
+
+    let mut canonicalized = Vec::with_capacity(body.code().elements().len());
+    for instruction in body.code().elements() {
+        canonicalized.push(instruction.clone());
+        let (local, canonical_nan, equal) = match produces_nan(instruction) {
+            Some(ValueType::F32) => (
+                f32_local,
+                Instruction::F32Const(CANONICAL_NAN_F32),
+                Instruction::F32Eq,
+            ),
+            Some(ValueType::F64) => (
+                f64_local,
+                Instruction::F64Const(CANONICAL_NAN_F64),
+                Instruction::F64Eq,
+            ),
+            _ => continue,
+        };
+        canonicalized.extend(vec![
+            Instruction::TeeLocal(local),
+            canonical_nan,
+            Instruction::GetLocal(local),
+            Instruction::GetLocal(local),
+            equal,
+            Instruction::Select,
+        ]);
+    }
+    *body.code_mut().elements_mut() = canonicalized;
+}
+
+/// Return the type of the result of an instruction whose result may be a NaN with arbitrary
+/// bits
+///
+/// The sign operations and reinterpretations only move bits, so the NaNs they produce are the
+/// NaNs the contract gave them.
+fn produces_nan(instruction: &Instruction) -> Option<ValueType> {
+    match instruction {
+        Instruction::F32Ceil
+        | Instruction::F32Floor
+        | Instruction::F32Trunc
+        | Instruction::F32Nearest
+        | Instruction::F32Sqrt
+        | Instruction::F32Add
+        | Instruction::F32Sub
+        | Instruction::F32Mul
+        | Instruction::F32Div
+        | Instruction::F32Min
+        | Instruction::F32Max
+        | Instruction::F32DemoteF64 => Some(ValueType::F32),
+        Instruction::F64Ceil
+        | Instruction::F64Floor
+        | Instruction::F64Trunc
+        | Instruction::F64Nearest
+        | Instruction::F64Sqrt
+        | Instruction::F64Add
+        | Instruction::F64Sub
+        | Instruction::F64Mul
+        | Instruction::F64Div
+        | Instruction::F64Min
+        | Instruction::F64Max
+        | Instruction::F64PromoteF32 => Some(ValueType::F64),
+        _ => None,
+    }
+}
+
+/// Return whether an instruction operates on floating-point values
+fn is_float(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::F32Load(..)
+            | Instruction::F64Load(..)
+            | Instruction::F32Store(..)
+            | Instruction::F64Store(..)
+            | Instruction::F32Const(..)
+            | Instruction::F64Const(..)
+            | Instruction::F32Eq
+            | Instruction::F32Ne
+            | Instruction::F32Lt
+            | Instruction::F32Gt
+            | Instruction::F32Le
+            | Instruction::F32Ge
+            | Instruction::F64Eq
+            | Instruction::F64Ne
+            | Instruction::F64Lt
+            | Instruction::F64Gt
+            | Instruction::F64Le
+            | Instruction::F64Ge
+            | Instruction::F32Abs
+            | Instruction::F32Neg
+            | Instruction::F32Copysign
+            | Instruction::F64Abs
+            | Instruction::F64Neg
+            | Instruction::F64Copysign
+            | Instruction::I32TruncSF32
+            | Instruction::I32TruncUF32
+            | Instruction::I32TruncSF64
+            | Instruction::I32TruncUF64
+            | Instruction::I64TruncSF32
+            | Instruction::I64TruncUF32
+            | Instruction::I64TruncSF64
+            | Instruction::I64TruncUF64
+            | Instruction::F32ConvertSI32
+            | Instruction::F32ConvertUI32
+            | Instruction::F32ConvertSI64
+            | Instruction::F32ConvertUI64
+            | Instruction::F64ConvertSI32
+            | Instruction::F64ConvertUI32
+            | Instruction::F64ConvertSI64
+            | Instruction::F64ConvertUI64
+            | Instruction::I32ReinterpretF32
+            | Instruction::I64ReinterpretF64
+            | Instruction::F32ReinterpretI32
+            | Instruction::F64ReinterpretI64
+    ) || produces_nan(instruction).is_some()
+}
@@ -0,0 +1,810 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains the host functions contracts import from the `env` module, independent of the
+//! WebAssembly runtime executing them
+//!
+//! Each runtime gives the functions access to the contract's memory through `ContractMemory`.
+//! An `Err` returned by a function traps, ending the execution of the contract.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::Instant;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
+use log::{Level, LevelFilter};
+use sabre_sdk::protocol::state::{SmartPermission, SmartPermissionList};
+use sabre_sdk::protos::FromBytes;
+use sawtooth_sdk::processor::handler::{ApplyError, TransactionContext};
+use wasmi::MemoryRef;
+
+use crate::handler::{
+    cached_module_key, check_contract_not_paused, check_namespace_permissions, contract_sha512,
+};
+use crate::logging;
+use crate::state::SabreState;
+use crate::wasm_executor::memory_limit::PAGE_SIZE;
+use crate::wasm_executor::metering::InstructionMeter;
+use crate::wasm_executor::module_cache::{ContractCache, SmartPermissionCache};
+use crate::wasm_executor::wasm_externals::{ExternalsError, SmartPermissionModule};
+use crate::wasm_executor::wasm_module::{self, WasmModule};
+use crate::wasm_executor::{ContractError, ExecutionLimits, Runtime, Transaction};
+
+/// The linear memory of a contract instance
+pub trait ContractMemory {
+    /// Read `length` bytes starting at `offset`
+    fn get(&self, offset: u32, length: usize) -> Result<Vec<u8>, ExternalsError>;
+
+    /// Write the bytes starting at `offset`
+    fn set(&mut self, offset: u32, data: &[u8]) -> Result<(), ExternalsError>;
+}
+
+impl ContractMemory for MemoryRef {
+    fn get(&self, offset: u32, length: usize) -> Result<Vec<u8>, ExternalsError> {
+        (**self).get(offset, length).map_err(ExternalsError::from)
+    }
+
+    fn set(&mut self, offset: u32, data: &[u8]) -> Result<(), ExternalsError> {
+        (**self).set(offset, data).map_err(ExternalsError::from)
+    }
+}
+
+/// The state shared by the host functions during one execution of a contract: the pointers
+/// written to the contract's memory, and the collections of those pointers
+pub struct HostState<'a> {
+    context: &'a mut dyn TransactionContext,
+    ptrs: HashMap<u32, Pointer>,
+    ptr_collections: HashMap<u32, Vec<u32>>,
+    memory_write_offset: u32,
+    /// The transaction the contract is executed for, which is `None` for a smart permission
+    transaction: Option<&'a Transaction>,
+    /// The limits of the contract, which the smart permissions and contracts it invokes are
+    /// given too
+    limits: ExecutionLimits,
+    /// The contracts already loaded, from which the contracts it invokes are taken
+    contract_cache: &'a ContractCache,
+    /// The smart permissions already loaded, shared with the smart permissions and contracts it
+    /// invokes
+    smart_permission_cache: &'a SmartPermissionCache,
+    /// The error the contract last reported with `set_error`
+    error: Option<ContractError>,
+}
+
+impl<'a> HostState<'a> {
+    pub fn new(
+        context: &'a mut dyn TransactionContext,
+        transaction: Option<&'a Transaction>,
+        limits: ExecutionLimits,
+        contract_cache: &'a ContractCache,
+        smart_permission_cache: &'a SmartPermissionCache,
+    ) -> HostState<'a> {
+        HostState {
+            context,
+            ptrs: HashMap::new(),
+            ptr_collections: HashMap::new(),
+            memory_write_offset: 0,
+            transaction,
+            limits,
+            contract_cache,
+            smart_permission_cache,
+            error: None,
+        }
+    }
+
+    /// Return the limits of the contract
+    #[cfg(feature = "wasmtime")]
+    pub fn limits(&self) -> ExecutionLimits {
+        self.limits
+    }
+
+    /// Return the error the contract reported with `set_error`, if any
+    pub fn take_error(&mut self) -> Option<ContractError> {
+        self.error.take()
+    }
+
+    fn ptr_to_string(
+        &self,
+        memory: &dyn ContractMemory,
+        raw_ptr: u32,
+    ) -> Result<String, ExternalsError> {
+        let bytes = self.ptr_to_vec(memory, raw_ptr)?;
+
+        String::from_utf8(bytes).map_err(ExternalsError::from)
+    }
+
+    fn ptr_to_vec(
+        &self,
+        memory: &dyn ContractMemory,
+        raw_ptr: u32,
+    ) -> Result<Vec<u8>, ExternalsError> {
+        if let Some(p) = self.ptrs.get(&raw_ptr) {
+            memory.get(p.raw, p.length)
+        } else {
+            Err(ExternalsError::from(format!(
+                "ptr referencing {} not found",
+                raw_ptr
+            )))
+        }
+    }
+
+    /// The strings referenced by the pointers in a collection, or `None` if there is no
+    /// collection with the given head
+    fn collection_to_strings(
+        &self,
+        memory: &dyn ContractMemory,
+        head_ptr: u32,
+    ) -> Result<Option<Vec<String>>, ExternalsError> {
+        match self.ptr_collections.get(&head_ptr) {
+            Some(ptrs) => ptrs
+                .iter()
+                .map(|ptr| self.ptr_to_string(memory, *ptr))
+                .collect::<Result<_, _>>()
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn write_data(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        data: Vec<u8>,
+    ) -> Result<u32, ExternalsError> {
+        memory.set(self.memory_write_offset, &data)?;
+
+        let ptr = Pointer {
+            raw: self.memory_write_offset,
+            length: data.len(),
+            capacity: data.capacity(),
+        };
+
+        let raw_ptr = ptr.raw;
+
+        // In case the data to be added is empty, keep the memory_write_offset
+        // moving to the next location.
+        let offset_to_add = if data.capacity() == 0 {
+            1
+        } else {
+            u32::try_from(data.capacity()).unwrap_or(u32::MAX)
+        };
+        let next_offset = self
+            .memory_write_offset
+            .checked_add(offset_to_add)
+            .ok_or_else(|| ExternalsError::from("Host memory exhausted"))?;
+
+        self.ptrs.insert(self.memory_write_offset, ptr);
+        self.memory_write_offset = next_offset;
+
+        debug!("moved the pointer to {:?}", self.memory_write_offset);
+
+        Ok(raw_ptr)
+    }
+
+    /// Takes a list of pointers and associates them,
+    /// effectively creating a list
+    ///
+    /// Returns a result either containing the raw value
+    /// of the first pointer in the list or an externals
+    /// error
+    pub fn collect_ptrs(&mut self, raw_ptrs: Vec<u32>) -> Result<u32, ExternalsError> {
+        info!("associating pointers: {:?}", raw_ptrs);
+        if raw_ptrs.iter().all(|x| self.ptrs.contains_key(x)) {
+            self.ptr_collections.insert(raw_ptrs[0], raw_ptrs.clone());
+            Ok(raw_ptrs[0])
+        } else {
+            Err(ExternalsError::from(
+                "Attempting to create a ptr collection with nonexistant pointers",
+            ))
+        }
+    }
+
+    /// Write each item and collect the pointers to them, or write an empty item if there are
+    /// none, returning the raw pointer to the collection or empty item
+    fn write_collection(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        items: Vec<Vec<u8>>,
+    ) -> Result<u32, ExternalsError> {
+        let mut ptr_vec = Vec::new();
+        for item in items {
+            ptr_vec.push(self.write_data(memory, item)?);
+        }
+
+        // collect ptrs or return empty vec
+        if ptr_vec.is_empty() {
+            self.write_data(memory, Vec::new())
+        } else {
+            self.collect_ptrs(ptr_vec)
+        }
+    }
+
+    pub fn get_smart_permission(
+        &mut self,
+        address: &str,
+        name: &str,
+    ) -> Result<Option<SmartPermission>, ExternalsError> {
+        let d = self.context.get_state_entry(address)?;
+        match d {
+            Some(packed) => {
+                let smart_permissions = match SmartPermissionList::from_bytes(packed.as_slice()) {
+                    Ok(smart_permissions) => smart_permissions,
+                    Err(err) => {
+                        return Err(ExternalsError::from(format!(
+                            "Cannot deserialize smart permission list: {:?}",
+                            err
+                        )));
+                    }
+                };
+
+                for smart_permission in smart_permissions.smart_permissions() {
+                    if smart_permission.name() == name {
+                        return Ok(Some(smart_permission.clone()));
+                    }
+                }
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Host function `get_state`: read the state entries at the addresses in a collection
+    pub fn get_state(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        head_ptr: u32,
+    ) -> Result<i32, ExternalsError> {
+        let timer = Instant::now();
+        let addr_vec = match self.collection_to_strings(memory, head_ptr)? {
+            Some(addresses) => addresses,
+            None => return Ok(-1),
+        };
+
+        info!("Attempting to get state, addresses: {:?}", addr_vec);
+
+        let state = self.context.get_state_entries(&addr_vec)?;
+
+        let mut items = Vec::new();
+        for (addr, data) in state {
+            items.push(addr.into_bytes());
+            items.push(data);
+        }
+        let raw_ptr = self.write_collection(memory, items)?;
+
+        info!(
+            "GET_STATE Execution time: {} secs {} ms",
+            timer.elapsed().as_secs(),
+            timer.elapsed().subsec_millis()
+        );
+
+        Ok(raw_ptr as i32)
+    }
+
+    /// Host function `set_state`: write the address and data pairs in a collection to state
+    pub fn set_state(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        head_ptr: u32,
+    ) -> Result<i32, ExternalsError> {
+        let timer = Instant::now();
+        let addr_state = match self.ptr_collections.get(&head_ptr) {
+            Some(addresses) => addresses.clone(),
+            None => return Ok(-1),
+        };
+
+        // if the length is not even return deserialization error
+        if (addr_state.len() % 2) != 0 {
+            return Ok(-1);
+        }
+
+        let mut entries = Vec::new();
+        for entry in addr_state.chunks(2) {
+            let address = self.ptr_to_string(memory, entry[0])?;
+            let data = self.ptr_to_vec(memory, entry[1])?;
+            entries.push((address, data));
+        }
+
+        info!("Attempting to set state, entries: {:?}", entries);
+
+        let result = match self.context.set_state_entries(entries) {
+            Ok(()) => 1,
+            Err(err) => {
+                error!("Set Error: {}", err);
+                0
+            }
+        };
+
+        info!(
+            "SET_STATE Execution time: {} secs {} ms",
+            timer.elapsed().as_secs(),
+            timer.elapsed().subsec_millis()
+        );
+
+        Ok(result)
+    }
+
+    /// Host function `delete_state`: delete the state entries at the addresses in a collection
+    pub fn delete_state(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        head_ptr: u32,
+    ) -> Result<i32, ExternalsError> {
+        let addr_vec = match self.collection_to_strings(memory, head_ptr)? {
+            Some(addresses) => addresses,
+            None => return Ok(-1),
+        };
+
+        info!("Attempting to delete state, addresses: {:?}", addr_vec);
+        let result = self.context.delete_state_entries(&addr_vec)?;
+
+        let items = result.into_iter().map(String::into_bytes).collect();
+        Ok(self.write_collection(memory, items)? as i32)
+    }
+
+    /// Host function `add_event`
+    pub fn add_event(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        event_type_ptr: u32,
+        attribute_list_ptr: u32,
+        data_ptr: u32,
+    ) -> Result<i32, ExternalsError> {
+        let attribute_list = match self.ptr_collections.get(&attribute_list_ptr) {
+            Some(attributes) => attributes.clone(),
+            None => return Ok(-1),
+        };
+
+        // if the length is even return deserialization error
+        // the list should have a starting element followed by key, value pair
+        if (attribute_list.len() % 2) == 0 {
+            return Ok(-1);
+        }
+
+        let mut attributes = Vec::new();
+        if attribute_list.len() > 1 {
+            for entry in attribute_list[1..].chunks(2) {
+                let key = self.ptr_to_string(memory, entry[0])?;
+                let value = self.ptr_to_string(memory, entry[1])?;
+                attributes.push((key, value));
+            }
+        }
+
+        let event_type = self.ptr_to_string(memory, event_type_ptr)?;
+
+        let data = self.ptr_to_vec(memory, data_ptr)?;
+
+        info!(
+            "Attempting to add event, event_type: {:?}, attributes: {:?}, data: {:?}",
+            event_type, attributes, data
+        );
+
+        match self.context.add_event(event_type, attributes, &data) {
+            Ok(()) => Ok(0),
+            Err(err) => {
+                error!("Add event Error: {}", err);
+                Ok(1)
+            }
+        }
+    }
+
+    /// Host function `add_receipt_data`
+    pub fn add_receipt_data(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        data_ptr: u32,
+    ) -> Result<i32, ExternalsError> {
+        let data = self.ptr_to_vec(memory, data_ptr)?;
+
+        info!("Attempting to add receipt data, data: {:?}", data);
+
+        match self.context.add_receipt_data(&data) {
+            Ok(()) => Ok(0),
+            Err(err) => {
+                error!("Add receipt data Error: {}", err);
+                Ok(1)
+            }
+        }
+    }
+
+    /// Host function `set_error`: report the code and message of the error the contract is about
+    /// to return, replacing any error it reported before
+    pub fn set_error(
+        &mut self,
+        memory: &dyn ContractMemory,
+        code: i32,
+        message_ptr: u32,
+    ) -> Result<i32, ExternalsError> {
+        let message = self.ptr_to_string(memory, message_ptr)?;
+
+        info!("Contract reported error {}: {}", code, message);
+        self.error = Some(ContractError { code, message });
+        Ok(0)
+    }
+
+    /// Host function `invoke_smart_permission`
+    ///
+    /// Smart permissions are always executed by the interpreter. A smart permission is read from
+    /// state each time it is invoked, but only parsed and validated if it is not in the smart
+    /// permission cache. The instructions it runs are charged to `meter`, the meter of the
+    /// contract which invokes it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn smart_permission(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        meter: &mut InstructionMeter,
+        contract_addr_ptr: u32,
+        name_ptr: u32,
+        roles_head_ptr: u32,
+        org_id_ptr: u32,
+        public_key_ptr: u32,
+        payload_ptr: u32,
+    ) -> Result<i32, ExternalsError> {
+        let timer = Instant::now();
+        let role_vec = match self.collection_to_strings(memory, roles_head_ptr)? {
+            Some(roles) => roles,
+            None => return Ok(-1),
+        };
+        let org_id = self.ptr_to_string(memory, org_id_ptr)?;
+        let public_key = self.ptr_to_string(memory, public_key_ptr)?;
+        let payload = self.ptr_to_vec(memory, payload_ptr)?;
+        let name = self.ptr_to_string(memory, name_ptr)?;
+        let contract_addr = self.ptr_to_string(memory, contract_addr_ptr)?;
+
+        let contract = if let Some(sp) = self.get_smart_permission(&contract_addr, &name)? {
+            sp
+        } else {
+            return Ok(-2);
+        };
+
+        // A module is loaded for a memory limit, float policy and wasm protocol version, so they
+        // are part of its key in the cache
+        let mut sha = Sha512::new();
+        sha.input(contract.function());
+        let module = self
+            .smart_permission_cache
+            .lock()
+            .map_err(|_| ExternalsError::from("Smart permission cache lock poisoned"))?
+            .get_or_load(
+                &format!(
+                    "{}:{}:{}:{}:{}",
+                    contract_addr,
+                    sha.result_str(),
+                    self.limits.max_memory_pages,
+                    self.limits.float_policy,
+                    self.limits.wasm_protocol_version
+                ),
+                || {
+                    wasm_module::load(
+                        contract.function(),
+                        self.limits.max_memory_pages,
+                        self.limits.float_policy,
+                        self.limits.wasm_protocol_version,
+                    )
+                },
+            )
+            .map_err(|err| {
+                ExternalsError::from(format!("Unable to load smart permission {}: {}", name, err))
+            })?;
+
+        // Invoke Smart Permission
+        let result = SmartPermissionModule::new(
+            &module,
+            self.context,
+            self.limits,
+            meter,
+            self.contract_cache,
+            self.smart_permission_cache,
+        )
+        .entrypoint(role_vec, org_id, public_key, payload)
+        .map_err(|e| ExternalsError::from(format!("{:?}", e)))?;
+
+        match result {
+            Some(x) => {
+                info!(
+                    "SMART_PERMISSION Execution time: {} secs {} ms",
+                    timer.elapsed().as_secs(),
+                    timer.elapsed().subsec_millis()
+                );
+                Ok(x)
+            }
+            None => Err(ExternalsError::from("No result returned")),
+        }
+    }
+
+    /// Host function `invoke_contract`: execute another contract for the same transaction, with
+    /// the same signer, returning the result of its entrypoint
+    ///
+    /// The invoked contract must be permitted to read and write every input and output of the
+    /// transaction, as the contract executed by the transaction is, and may not be paused, so the
+    /// transaction must have its contract registry among its inputs. Invoked contracts are always
+    /// executed by the interpreter, and kept in the contract cache once loaded. The instructions
+    /// the invoked contract runs are charged to `meter`, the meter of the contract invoking it.
+    pub fn invoke_contract(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        meter: &mut InstructionMeter,
+        name_ptr: u32,
+        version_ptr: u32,
+        payload_ptr: u32,
+    ) -> Result<i32, ExternalsError> {
+        let timer = Instant::now();
+        let transaction = self
+            .transaction
+            .ok_or_else(|| ExternalsError::from("Smart permissions cannot invoke contracts"))?;
+        if self.limits.max_call_depth == 0 {
+            return Err(ExternalsError::from(
+                "Contract invocations are nested more deeply than allowed",
+            ));
+        }
+
+        let name = self.ptr_to_string(memory, name_ptr)?;
+        let version = self.ptr_to_string(memory, version_ptr)?;
+        let payload = self.ptr_to_vec(memory, payload_ptr)?;
+
+        let _contract_scope = logging::enter_contract(&name, &version);
+        info!("Attempting to invoke contract: {}, {}", name, version);
+
+        // As for the contract executed by the transaction, the registry is read before the
+        // contract, so that a paused contract is rejected without reading its module
+        let mut state = SabreState::new(&mut *self.context);
+        check_contract_not_paused(&name, &version, &mut state).map_err(apply_error)?;
+        let contract = match state.get_contract(&name, &version).map_err(apply_error)? {
+            Some(contract) => contract,
+            None => {
+                return Err(ExternalsError::from(format!(
+                    "Contract does not exist: {}, {}",
+                    name, version
+                )))
+            }
+        };
+        check_namespace_permissions(&name, &transaction.inputs, &transaction.outputs, &mut state)
+            .map_err(apply_error)?;
+
+        let limits = ExecutionLimits {
+            max_call_depth: self.limits.max_call_depth - 1,
+            ..self.limits
+        };
+        // The cache is only locked while the module is loaded, since the invoked contract may
+        // invoke contracts too
+        let runtime = Runtime::Wasmi;
+        let module = self
+            .contract_cache
+            .lock()
+            .map_err(|_| ExternalsError::from("Contract cache lock poisoned"))?
+            .get_or_load(
+                &cached_module_key(&runtime, &contract_sha512(&contract), limits),
+                || {
+                    runtime.load(
+                        contract.contract(),
+                        limits.max_memory_pages,
+                        limits.float_policy,
+                        limits.wasm_protocol_version,
+                    )
+                },
+            )?;
+        let module = module.as_wasmi().ok_or_else(|| {
+            ExternalsError::from("Invoked contract was loaded by a different runtime")
+        })?;
+        let result = WasmModule::new(
+            module,
+            self.context,
+            transaction,
+            limits,
+            self.contract_cache,
+            self.smart_permission_cache,
+        )
+        .entrypoint_metered(payload, meter)
+        .map_err(|err| {
+            ExternalsError::from(format!(
+                "Invoked contract {}, {} failed: {}",
+                name,
+                version,
+                err.message()
+            ))
+        })?;
+
+        match result.result {
+            Some(x) => {
+                info!(
+                    "INVOKE_CONTRACT Execution time: {} secs {} ms",
+                    timer.elapsed().as_secs(),
+                    timer.elapsed().subsec_millis()
+                );
+                Ok(x)
+            }
+            None => Err(ExternalsError::from("No result returned")),
+        }
+    }
+
+    /// Host function `get_ptr_len`
+    pub fn get_ptr_len(&self, raw_ptr: u32) -> i32 {
+        match self.ptrs.get(&raw_ptr) {
+            Some(ptr) => ptr.length as i32,
+            None => -1,
+        }
+    }
+
+    /// Host function `get_ptr_capacity`
+    pub fn get_ptr_capacity(&self, raw_ptr: u32) -> i32 {
+        match self.ptrs.get(&raw_ptr) {
+            Some(ptr) => ptr.capacity as i32,
+            None => -1,
+        }
+    }
+
+    /// Host function `alloc`
+    pub fn alloc(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        len: i32,
+    ) -> Result<i32, ExternalsError> {
+        let timer = Instant::now();
+        // A length the contract's memory could not hold fails before the host allocates it
+        let len = match usize::try_from(len) {
+            Ok(len) if len <= self.limits.max_memory_pages as usize * PAGE_SIZE => len,
+            _ => {
+                return Err(ExternalsError::from(format!(
+                    "Unable to allocate {} bytes",
+                    len
+                )))
+            }
+        };
+        let raw_ptr = self.write_data(memory, vec![0; len])?;
+        info!(
+            "ALLOC Execution time: {} secs {} ms",
+            timer.elapsed().as_secs(),
+            timer.elapsed().subsec_millis()
+        );
+
+        Ok(raw_ptr as i32)
+    }
+
+    /// Host function `read_byte`
+    pub fn read_byte(
+        &self,
+        memory: &dyn ContractMemory,
+        offset: i32,
+    ) -> Result<i32, ExternalsError> {
+        let byte = memory.get(offset as u32, 1)?[0];
+        Ok(i32::from(byte))
+    }
+
+    /// Host function `write_byte`
+    pub fn write_byte(
+        &mut self,
+        memory: &mut dyn ContractMemory,
+        raw_ptr: u32,
+        offset: u32,
+        data: i32,
+    ) -> Result<i32, ExternalsError> {
+        if let Some(p) = self.ptrs.get(&raw_ptr) {
+            let offset = p.raw.checked_add(offset).ok_or_else(|| {
+                ExternalsError::from(format!(
+                    "Offset {} of pointer {} is out of the bounds of memory",
+                    offset, raw_ptr
+                ))
+            })?;
+            memory.set(offset, &[data as u8])?;
+            Ok(1)
+        } else {
+            Ok(-1)
+        }
+    }
+
+    /// Host function `get_ptr_collection_len`
+    pub fn get_collection_len(&self, head_ptr: u32) -> i32 {
+        info!("Retrieving collection length. Head pointer {}", head_ptr);
+
+        if let Some(v) = self.ptr_collections.get(&head_ptr) {
+            info!("Collection found elements in collection: {}", v.len());
+            v.len() as i32
+        } else {
+            -1
+        }
+    }
+
+    /// Host function `get_ptr_from_collection`
+    pub fn get_ptr_from_collection(&self, head_ptr: u32, index: u32) -> i32 {
+        info!("Retrieving pointer head_ptr: {} index: {}", head_ptr, index);
+
+        if let Some(v) = self.ptr_collections.get(&head_ptr) {
+            if index as usize >= v.len() {
+                info!("Invalid index");
+                -1
+            } else {
+                info!("Pointer retrieved: {}", v[index as usize]);
+                v[index as usize] as i32
+            }
+        } else {
+            -1
+        }
+    }
+
+    /// Host function `create_collection`
+    pub fn create_collection(&mut self, head: u32) -> Result<u32, ExternalsError> {
+        info!("create_collection: {:?}", head);
+        self.ptr_collections.insert(head, vec![head]);
+        Ok(head)
+    }
+
+    /// Host function `add_to_collection`
+    pub fn add_to_collection(&mut self, head: u32, raw_ptr: u32) -> Result<u32, ExternalsError> {
+        info!("adding to collection: {:?}", raw_ptr);
+        if let Some(x) = self.ptr_collections.get_mut(&head) {
+            x.push(raw_ptr);
+            Ok(head)
+        } else {
+            Err(ExternalsError::from(
+                "Attempting to add a ptr to nonexistant collecttion",
+            ))
+        }
+    }
+
+    /// Host function `log_buffer`: log a message of the contract, at a level from 0 for error to
+    /// 4 for trace
+    pub fn log(
+        &self,
+        memory: &dyn ContractMemory,
+        log_level: u32,
+        log_ptr: u32,
+    ) -> Result<(), ExternalsError> {
+        let log_string = self.ptr_to_string(memory, log_ptr)?;
+        let level = match log_level {
+            0 => Level::Error,
+            1 => Level::Warn,
+            2 => Level::Info,
+            3 => Level::Debug,
+            4 => Level::Trace,
+            _ => {
+                warn!("Unknown log level requested: {}", log_level);
+                return Ok(());
+            }
+        };
+        logging::log_contract_message(level, &log_string);
+        Ok(())
+    }
+
+    /// Host function `log_level`: the level at which the transaction processor logs the messages
+    /// of contracts
+    pub fn log_level(&self) -> i32 {
+        match logging::contract_level() {
+            LevelFilter::Trace => 4,
+            LevelFilter::Debug => 3,
+            LevelFilter::Info => 2,
+            LevelFilter::Warn => 1,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Pointer {
+    raw: u32,
+    length: usize,
+    capacity: usize,
+}
+
+impl fmt::Debug for Pointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Pointer {{ raw: {}, length: {}, capacity {} }}",
+            self.raw, self.length, self.capacity
+        )
+    }
+}
+
+fn apply_error(err: ApplyError) -> ExternalsError {
+    ExternalsError::from(err.to_string())
+}
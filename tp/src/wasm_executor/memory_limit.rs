@@ -0,0 +1,63 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Limits the linear memory of contract instances
+//!
+//! A contract has two memories: the memory it defines itself, and the `env` memory the host
+//! functions write to, which the contract may also import. Neither may grow beyond the
+//! contract's limit; growing a memory past it fails as if the memory were declared with that
+//! maximum.
+
+use std::cmp;
+
+use parity_wasm::elements::{MemoryType, Module};
+
+use crate::wasm_executor::wasm_externals::ExternalsError;
+
+/// The largest number of pages a 32-bit WebAssembly memory may have
+pub const MAX_MEMORY_PAGES: u32 = 65536;
+
+/// The size of a page of WebAssembly memory
+pub const PAGE_SIZE: usize = 65536;
+
+/// The number of pages the `env` memory starts with, if the limit allows
+const HOST_MEMORY_PAGES: u32 = 256;
+
+/// The number of pages the `env` memory of a contract limited to `max_pages` starts with
+pub fn host_memory_pages(max_pages: u32) -> u32 {
+    cmp::min(HOST_MEMORY_PAGES, max_pages)
+}
+
+/// Lower the maximum of the memory a contract defines to `max_pages`, failing if the contract
+/// requires more memory than that to be instantiated
+pub fn limit_memory(module: &mut Module, max_pages: u32) -> Result<(), ExternalsError> {
+    if let Some(section) = module.memory_section_mut() {
+        for memory in section.entries_mut() {
+            let limits = *memory.limits();
+            if limits.initial() > max_pages {
+                return Err(ExternalsError::from(format!(
+                    "Contract requires {} pages of memory, more than its limit of {}",
+                    limits.initial(),
+                    max_pages
+                )));
+            }
+            let maximum = limits
+                .maximum()
+                .map_or(max_pages, |maximum| cmp::min(maximum, max_pages));
+            *memory = MemoryType::new(limits.initial(), Some(maximum));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,300 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Limits the number of instructions a contract interpreted by wasmi may run, and the time it
+//! may run for
+//!
+//! When a contract is loaded, a mutable global holding the instructions the instance may still
+//! run is added to it, and each straight-line run of instructions in its functions is preceded by
+//! code which subtracts the number of instructions in the run from the global. Since a run ends
+//! at every branch and at the start of every block and loop, each iteration of a loop and each
+//! function call is charged.
+//!
+//! The global starts at zero, and whenever it drops below zero the contract calls the
+//! `__sabre_meter` host function, which charges the overdraft to the execution's budget and hands
+//! out the next instructions, at most `INSTRUCTIONS_PER_CHECK` at a time. The host function also
+//! checks the deadline of the execution, so that a contract which is still running when the
+//! deadline passes is stopped soon after.
+//!
+//! The global is exported as `CREDIT_EXPORT`, so that once the contract returns, the credit it
+//! has not used can be subtracted from the instructions handed out to it, to count the
+//! instructions it ran.
+
+use std::cmp;
+use std::convert::TryFrom;
+
+use parity_wasm::elements::{
+    BlockType, ExportEntry, ExportSection, External, FunctionType, GlobalEntry, GlobalSection,
+    GlobalType, ImportCountType, ImportEntry, ImportSection, InitExpr, Instruction, Internal,
+    Module, Section, Type, TypeSection, ValueType,
+};
+
+use crate::wasm_executor::wasm_externals::ExternalsError;
+use crate::wasm_executor::ExecutionLimits;
+
+/// The name of the host function a metered contract imports from the `env` module
+pub const METER_FUNCTION: &str = "__sabre_meter";
+
+/// The name under which a metered contract exports the global holding its credit
+pub const CREDIT_EXPORT: &str = "__sabre_credit";
+
+/// The number of instructions a contract is given each time it calls the meter host function
+const INSTRUCTIONS_PER_CHECK: u64 = 10_000;
+
+/// Add instruction metering to a contract
+pub fn add_metering(module: &mut Module) -> Result<(), ExternalsError> {
+    let meter_function = import_meter_function(module)?;
+
+    let credit_global = module.globals_space() as u32;
+    let credit = GlobalEntry::new(
+        GlobalType::new(ValueType::I64, true),
+        InitExpr::new(vec![Instruction::I64Const(0), Instruction::End]),
+    );
+    match module.global_section_mut() {
+        Some(section) => section.entries_mut().push(credit),
+        None => insert_section(
+            module,
+            Section::Global(GlobalSection::with_entries(vec![credit])),
+        )?,
+    }
+
+    let export = ExportEntry::new(CREDIT_EXPORT.into(), Internal::Global(credit_global));
+    match module.export_section_mut() {
+        Some(section) => section.entries_mut().push(export),
+        None => insert_section(
+            module,
+            Section::Export(ExportSection::with_entries(vec![export])),
+        )?,
+    }
+
+    if let Some(code) = module.code_section_mut() {
+        for body in code.bodies_mut() {
+            let metered = meter(body.code().elements(), credit_global, meter_function);
+            *body.code_mut().elements_mut() = metered;
+        }
+    }
+
+    Ok(())
+}
+
+/// Hands out instructions to the instances of a metered contract as they run: those of the
+/// contract, and of the contracts and smart permissions it invokes, which share its budget
+pub struct InstructionMeter {
+    limits: ExecutionLimits,
+    /// The instructions the instances may run in all
+    budget: u64,
+    /// The instructions of the budget which have not been handed out yet
+    remaining: u64,
+    exhausted: bool,
+}
+
+impl InstructionMeter {
+    pub fn new(limits: ExecutionLimits) -> InstructionMeter {
+        InstructionMeter::with_budget(limits, limits.max_instructions)
+    }
+
+    /// Create a meter handing out at most `budget` instructions, which is lower than the limit
+    /// for the contracts and smart permissions invoked by a contract which has already run some
+    /// of its own
+    pub fn with_budget(limits: ExecutionLimits, budget: u64) -> InstructionMeter {
+        InstructionMeter {
+            limits,
+            budget,
+            remaining: budget,
+            exhausted: false,
+        }
+    }
+
+    /// Charge the overdraft of an instance whose credit dropped below zero, returning its new
+    /// credit, or fail if the budget is exhausted or the deadline of the execution has passed
+    pub fn refill(&mut self, credit: i64) -> Result<i64, ExternalsError> {
+        let overdraft = u64::try_from(credit.saturating_neg()).unwrap_or(0);
+        if overdraft > self.remaining {
+            self.exhausted = true;
+            return Err(budget_exceeded(self.limits.max_instructions));
+        }
+        self.remaining -= overdraft;
+
+        if self.limits.timed_out() {
+            return Err(timed_out());
+        }
+
+        let credit = cmp::min(self.remaining, INSTRUCTIONS_PER_CHECK);
+        self.remaining -= credit;
+        Ok(credit as i64)
+    }
+
+    /// Settle the credit of an instance which has returned or trapped: the credit it has not
+    /// used goes back to the budget, and its overdraft is charged to it, up to what remains
+    pub fn settle(&mut self, credit: i64) {
+        self.remaining = match u64::try_from(credit) {
+            Ok(unused) => cmp::min(self.remaining.saturating_add(unused), self.budget),
+            Err(_) => self.remaining.saturating_sub(credit.unsigned_abs()),
+        };
+    }
+
+    /// Return whether the instances have run out of instructions
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Return the number of instructions the instances whose credit has been settled have run,
+    /// which is at most the budget
+    pub fn instructions_run(&self) -> u64 {
+        self.budget - self.remaining
+    }
+}
+
+/// The error returned when a contract runs more instructions than it is allowed
+pub fn budget_exceeded(max_instructions: u64) -> ExternalsError {
+    ExternalsError::from(format!(
+        "Contract execution budget exceeded: the contract ran more than {} instructions",
+        max_instructions
+    ))
+}
+
+/// The error returned when a contract is still running when the deadline of its execution passes
+pub fn timed_out() -> ExternalsError {
+    ExternalsError::from("Contract execution timed out")
+}
+
+/// Import the meter host function into a contract, returning its function index
+///
+/// The imported function is given the index after the contract's other imported functions, so
+/// every reference to a function the contract defines is shifted up by one.
+fn import_meter_function(module: &mut Module) -> Result<u32, ExternalsError> {
+    let meter_type = Type::Function(FunctionType::new(
+        vec![ValueType::I64],
+        vec![ValueType::I64],
+    ));
+    let type_index = match module.type_section_mut() {
+        Some(section) => {
+            section.types_mut().push(meter_type);
+            section.types().len() - 1
+        }
+        None => {
+            insert_section(
+                module,
+                Section::Type(TypeSection::with_types(vec![meter_type])),
+            )?;
+            0
+        }
+    } as u32;
+
+    let meter_function = module.import_count(ImportCountType::Function) as u32;
+    let import = ImportEntry::new(
+        "env".into(),
+        METER_FUNCTION.into(),
+        External::Function(type_index),
+    );
+    match module.import_section_mut() {
+        Some(section) => section.entries_mut().push(import),
+        None => insert_section(
+            module,
+            Section::Import(ImportSection::with_entries(vec![import])),
+        )?,
+    }
+
+    // The function indices of the contract from the meter function on are shifted past it. A
+    // function may also be referred to by `ref.func`, or by an expression of a passive or
+    // declarative element segment, but parity-wasm parses neither, and `proposals` rejects them
+    // in contracts using later versions of WebAssembly, so there are none to shift.
+    let shift = |index: &mut u32| {
+        if *index >= meter_function {
+            *index += 1;
+        }
+    };
+    if let Some(code) = module.code_section_mut() {
+        for body in code.bodies_mut() {
+            for instruction in body.code_mut().elements_mut() {
+                if let Instruction::Call(index) = instruction {
+                    shift(index);
+                }
+            }
+        }
+    }
+    if let Some(section) = module.export_section_mut() {
+        for export in section.entries_mut() {
+            if let Internal::Function(index) = export.internal_mut() {
+                shift(index);
+            }
+        }
+    }
+    if let Some(section) = module.elements_section_mut() {
+        for segment in section.entries_mut() {
+            segment.members_mut().iter_mut().for_each(shift);
+        }
+    }
+    if let Some(mut start) = module.start_section() {
+        shift(&mut start);
+        module.set_start_section(start);
+    }
+
+    Ok(meter_function)
+}
+
+fn insert_section(module: &mut Module, section: Section) -> Result<(), ExternalsError> {
+    module
+        .insert_section(section)
+        .map_err(|err| ExternalsError::from(format!("Unable to meter contract: {}", err)))
+}
+
+/// Return the instructions of a function body, with each run of instructions preceded by the
+/// code charging it to the instance's credit
+fn meter(
+    instructions: &[Instruction],
+    credit_global: u32,
+    meter_function: u32,
+) -> Vec<Instruction> {
+    let mut metered = Vec::with_capacity(instructions.len() * 2);
+    let mut run_start = 0;
+    for (index, instruction) in instructions.iter().enumerate() {
+        if ends_run(instruction) || index == instructions.len() - 1 {
+            let run = &instructions[run_start..=index];
+            metered.extend_from_slice(&[
+                Instruction::GetGlobal(credit_global),
+                Instruction::I64Const(run.len() as i64),
+                Instruction::I64Sub,
+                Instruction::SetGlobal(credit_global),
+                Instruction::GetGlobal(credit_global),
+                Instruction::I64Const(0),
+                Instruction::I64LtS,
+                Instruction::If(BlockType::NoResult),
+                Instruction::GetGlobal(credit_global),
+                Instruction::Call(meter_function),
+                Instruction::SetGlobal(credit_global),
+                Instruction::End,
+            ]);
+            metered.extend_from_slice(run);
+            run_start = index + 1;
+        }
+    }
+    metered
+}
+
+/// Return whether a run of straight-line instructions ends with this instruction
+fn ends_run(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Block(_)
+            | Instruction::Loop(_)
+            | Instruction::If(_)
+            | Instruction::Else
+            | Instruction::End
+            | Instruction::Br(_)
+            | Instruction::BrIf(_)
+            | Instruction::BrTable(_)
+            | Instruction::Return
+    )
+}
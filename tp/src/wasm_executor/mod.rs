@@ -11,5 +11,262 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+pub mod float_policy;
+pub mod host;
+pub mod memory_limit;
+pub mod metering;
+pub mod module_cache;
+pub mod proposals;
+pub mod stack_limit;
+pub mod validation;
+pub mod wasi;
 pub mod wasm_externals;
 pub mod wasm_module;
+#[cfg(feature = "wasmtime")]
+pub mod wasmtime_module;
+
+use std::fmt;
+use std::time::Instant;
+
+use sawtooth_sdk::processor::handler::TransactionContext;
+
+use crate::wasm_executor::float_policy::FloatPolicy;
+use crate::wasm_executor::module_cache::{ContractCache, SmartPermissionCache};
+use crate::wasm_executor::proposals::WasmProtocolVersion;
+use crate::wasm_executor::wasm_externals::ExternalsError;
+use crate::wasm_executor::wasm_module::WasmModule;
+#[cfg(feature = "wasmtime")]
+use crate::wasm_executor::wasmtime_module::WasmtimeEngine;
+
+/// The resources one execution of a contract may use
+#[derive(Clone, Copy)]
+pub struct ExecutionLimits {
+    /// The number of instructions the contract may run
+    pub max_instructions: u64,
+    /// The number of 64KiB pages each of the contract's memories may grow to
+    pub max_memory_pages: u32,
+    /// The time by which the contract, and the smart permissions and contracts it invokes, must
+    /// have finished
+    pub deadline: Option<Instant>,
+    /// The depth of nested contract invocations the contract may make
+    pub max_call_depth: u32,
+    /// The number of nested function calls the contract may make, if it is interpreted by wasmi
+    pub max_stack_depth: u32,
+    /// The number of bytes of value stack the contract may use if it is interpreted by wasmi, or
+    /// of native stack if it is compiled by wasmtime
+    pub max_stack_size: usize,
+    /// How the floating-point instructions of the contract, and of the smart permissions and
+    /// contracts it invokes, are handled
+    pub float_policy: FloatPolicy,
+    /// The version of WebAssembly the contract, and the smart permissions and contracts it
+    /// invokes, may use
+    pub wasm_protocol_version: WasmProtocolVersion,
+}
+
+impl ExecutionLimits {
+    /// Return whether the deadline of the execution has passed
+    pub fn timed_out(&self) -> bool {
+        matches!(self.deadline, Some(deadline) if Instant::now() >= deadline)
+    }
+}
+
+/// The transaction a contract is executed for, which the contracts it invokes are executed for
+/// too
+pub struct Transaction {
+    pub signer: String,
+    pub signature: String,
+    /// The addresses the contracts executed for the transaction must be permitted to read
+    pub inputs: Vec<String>,
+    /// The addresses the contracts executed for the transaction must be permitted to write
+    pub outputs: Vec<String>,
+}
+
+/// The result a contract's entrypoint returned, and the error the contract reported with the
+/// `set_error` host function
+pub struct ContractResult {
+    pub result: Option<i32>,
+    pub error: Option<ContractError>,
+}
+
+/// An error reported by a contract: a code defined by the contract, which is 0 if it gives none,
+/// and a message
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContractError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.code == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{} (error code {})", self.message, self.code)
+        }
+    }
+}
+
+/// An execution of a contract: its result, and the number of instructions it ran
+pub struct Execution {
+    pub result: Result<ContractResult, ExternalsError>,
+    /// The instructions the contract and the contracts and smart permissions it invoked ran,
+    /// since they share its budget
+    pub instructions: u64,
+}
+
+/// The WebAssembly runtime which executes contracts
+pub enum Runtime {
+    /// The wasmi interpreter
+    Wasmi,
+    /// Contracts compiled to native code by wasmtime
+    #[cfg(feature = "wasmtime")]
+    Wasmtime(WasmtimeEngine),
+}
+
+/// A contract loaded by a `Runtime`, which can be executed any number of times
+pub enum LoadedModule {
+    Wasmi(wasmi::Module),
+    #[cfg(feature = "wasmtime")]
+    Wasmtime(wasmtime::Module),
+}
+
+impl LoadedModule {
+    /// Return the module if it was loaded by the interpreter
+    pub fn as_wasmi(&self) -> Option<&wasmi::Module> {
+        match self {
+            LoadedModule::Wasmi(module) => Some(module),
+            #[cfg(feature = "wasmtime")]
+            LoadedModule::Wasmtime(_) => None,
+        }
+    }
+}
+
+impl Runtime {
+    /// Parse and validate a contract, compiling it if the runtime is a compiler
+    ///
+    /// A module loaded for a memory limit, float policy and wasm protocol version must only be
+    /// executed with that limit, policy and version.
+    pub fn load(
+        &self,
+        wasm: &[u8],
+        max_memory_pages: u32,
+        float_policy: FloatPolicy,
+        wasm_protocol_version: WasmProtocolVersion,
+    ) -> Result<LoadedModule, ExternalsError> {
+        match self {
+            Runtime::Wasmi => Ok(LoadedModule::Wasmi(wasm_module::load(
+                wasm,
+                max_memory_pages,
+                float_policy,
+                wasm_protocol_version,
+            )?)),
+            #[cfg(feature = "wasmtime")]
+            Runtime::Wasmtime(engine) => Ok(LoadedModule::Wasmtime(engine.compile(
+                wasm,
+                float_policy,
+                wasm_protocol_version,
+            )?)),
+        }
+    }
+
+    /// Load a contract as `load` does, also returning the artifact from which `load_artifact`
+    /// loads it again: the instrumented contract for wasmi, and the native code for wasmtime
+    pub fn load_with_artifact(
+        &self,
+        wasm: &[u8],
+        max_memory_pages: u32,
+        float_policy: FloatPolicy,
+        wasm_protocol_version: WasmProtocolVersion,
+    ) -> Result<(LoadedModule, Vec<u8>), ExternalsError> {
+        match self {
+            Runtime::Wasmi => {
+                let artifact = parity_wasm::serialize(wasm_module::instrument(
+                    wasm,
+                    max_memory_pages,
+                    float_policy,
+                    wasm_protocol_version,
+                )?)
+                .map_err(|err| {
+                    ExternalsError::from(format!("Unable to serialize contract: {}", err))
+                })?;
+                let module = wasmi::Module::from_buffer(&artifact)?;
+                Ok((LoadedModule::Wasmi(module), artifact))
+            }
+            #[cfg(feature = "wasmtime")]
+            Runtime::Wasmtime(engine) => {
+                let module = engine.compile(wasm, float_policy, wasm_protocol_version)?;
+                let artifact = engine.serialize(&module)?;
+                Ok((LoadedModule::Wasmtime(module), artifact))
+            }
+        }
+    }
+
+    /// Load a contract from an artifact returned by `load_with_artifact` of the same runtime,
+    /// which must not have changed since
+    pub fn load_artifact(&self, artifact: &[u8]) -> Result<LoadedModule, ExternalsError> {
+        match self {
+            Runtime::Wasmi => Ok(LoadedModule::Wasmi(wasmi::Module::from_buffer(artifact)?)),
+            #[cfg(feature = "wasmtime")]
+            Runtime::Wasmtime(engine) => Ok(LoadedModule::Wasmtime(engine.deserialize(artifact)?)),
+        }
+    }
+
+    /// Return the name of the runtime, which distinguishes the artifacts of runtimes
+    pub fn name(&self) -> &'static str {
+        match self {
+            Runtime::Wasmi => "wasmi",
+            #[cfg(feature = "wasmtime")]
+            Runtime::Wasmtime(_) => "wasmtime",
+        }
+    }
+
+    /// Call the entrypoint of a contract loaded by this runtime, which fails if the contract
+    /// exceeds the limits, loading the contracts and smart permissions it invokes through the
+    /// caches
+    #[allow(clippy::too_many_arguments)]
+    pub fn entrypoint(
+        &self,
+        module: &LoadedModule,
+        context: &mut dyn TransactionContext,
+        payload: Vec<u8>,
+        transaction: &Transaction,
+        limits: ExecutionLimits,
+        contract_cache: &ContractCache,
+        smart_permission_cache: &SmartPermissionCache,
+    ) -> Execution {
+        match (self, module) {
+            (Runtime::Wasmi, LoadedModule::Wasmi(module)) => {
+                let mut module = WasmModule::new(
+                    module,
+                    context,
+                    transaction,
+                    limits,
+                    contract_cache,
+                    smart_permission_cache,
+                );
+                let result = module.entrypoint(payload);
+                Execution {
+                    result,
+                    instructions: module.instructions(),
+                }
+            }
+            #[cfg(feature = "wasmtime")]
+            (Runtime::Wasmtime(engine), LoadedModule::Wasmtime(module)) => engine.entrypoint(
+                module,
+                context,
+                payload,
+                transaction,
+                limits,
+                contract_cache,
+                smart_permission_cache,
+            ),
+            #[cfg(feature = "wasmtime")]
+            _ => Execution {
+                result: Err(ExternalsError::from(
+                    "Contract was loaded by a different runtime",
+                )),
+                instructions: 0,
+            },
+        }
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::wasm_executor::wasm_externals::ExternalsError;
+use crate::wasm_executor::LoadedModule;
+
+/// A least-recently-used cache of contracts loaded by a WebAssembly runtime, keyed by the sha512
+/// of the contract and the limits it was loaded with
+///
+/// Since a contract is identified by its hash, a contract whose bytes change is loaded again
+/// rather than taken from the cache, so a contract created again under a name and version which
+/// was deleted is never executed from the module of the deleted contract. The modules of a
+/// deleted contract are removed with `invalidate`, and other stale modules are evicted once they
+/// are least recently used.
+pub struct ModuleCache<M> {
+    capacity: usize,
+    modules: HashMap<String, Arc<M>>,
+    /// The keys of the cached modules, from least to most recently used
+    order: VecDeque<String>,
+}
+
+/// The contract cache of a handler, which holds the modules its runtime loads for the contracts
+/// transactions execute, and the interpreted modules of the contracts they invoke
+pub type ContractCache = Mutex<ModuleCache<LoadedModule>>;
+
+/// The smart permissions loaded by wasmi, which always executes them, keyed by the address of
+/// their smart permission list, the sha512 of the smart permission and the limits it was loaded
+/// with
+///
+/// The cache is shared by the contracts executed by a handler, and the smart permissions they
+/// invoke, so it is locked only while a smart permission is looked up or loaded.
+pub type SmartPermissionCache = Mutex<ModuleCache<wasmi::Module>>;
+
+impl<M> ModuleCache<M> {
+    /// Create a cache holding at most `capacity` modules; a capacity of 0 disables caching
+    pub fn new(capacity: usize) -> ModuleCache<M> {
+        ModuleCache {
+            capacity,
+            modules: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Return the module with the given key, loading it with `load` if it is not cached
+    pub fn get_or_load<F>(&mut self, key: &str, load: F) -> Result<Arc<M>, ExternalsError>
+    where
+        F: FnOnce() -> Result<M, ExternalsError>,
+    {
+        if let Some(module) = self.modules.get(key) {
+            let module = module.clone();
+            self.touch(key);
+            debug!("Using cached module {}", key);
+            return Ok(module);
+        }
+
+        let module = Arc::new(load()?);
+        if self.capacity == 0 {
+            return Ok(module);
+        }
+
+        if self.modules.len() >= self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.modules.remove(&evicted);
+                debug!("Evicted module {} from cache", evicted);
+            }
+        }
+        self.modules.insert(key.to_string(), module.clone());
+        self.order.push_back(key.to_string());
+
+        Ok(module)
+    }
+
+    /// Return the number of modules in the cache
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    /// Return the number of modules the cache holds at most
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Remove every module from the cache
+    pub fn clear(&mut self) {
+        self.modules.clear();
+        self.order.clear();
+    }
+
+    /// Remove the modules loaded from the contract with the given sha512, whichever limits they
+    /// were loaded with
+    pub fn invalidate(&mut self, sha512: &str) {
+        let prefix = format!("{}:", sha512);
+        let modules = &mut self.modules;
+        self.order.retain(|key| {
+            if key.starts_with(&prefix) {
+                modules.remove(key);
+                debug!("Invalidated module {}", key);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Mark the module with the given key as the most recently used
+    fn touch(&mut self, key: &str) {
+        if let Some(position) = self.order.iter().position(|cached| cached == key) {
+            if let Some(cached) = self.order.remove(position) {
+                self.order.push_back(cached);
+            }
+        }
+    }
+}
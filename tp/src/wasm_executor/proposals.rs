@@ -0,0 +1,799 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Accepts the WebAssembly proposals recent toolchains use by default, by rewriting the
+//! contracts which use them into WebAssembly 1.0
+//!
+//! Sabre parses and interprets WebAssembly 1.0, so under version 2 of the wasm protocol a
+//! contract is rewritten before it is parsed: the sign extension instructions become shifts,
+//! `memory.copy` and `memory.fill` become calls to functions added to the contract, and the
+//! encodings of the reference types proposal which only refer to the contract's table are
+//! replaced by those of WebAssembly 1.0. Contracts are rewritten for every runtime, so that
+//! validators running different runtimes accept and execute the same contracts. What needs
+//! reference values, several tables or memories, or passive segments, which Rust contracts do
+//! not use, is rejected.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use parity_wasm::elements::{
+    BlockType, Func, FuncBody, FunctionType, Instruction, Instructions, Internal, Local, Module,
+    Type, ValueType,
+};
+
+use crate::wasm_executor::wasm_externals::ExternalsError;
+
+const TYPE_SECTION: u8 = 1;
+const IMPORT_SECTION: u8 = 2;
+const FUNCTION_SECTION: u8 = 3;
+const ELEMENT_SECTION: u8 = 9;
+const CODE_SECTION: u8 = 10;
+const DATA_SECTION: u8 = 11;
+const DATA_COUNT_SECTION: u8 = 12;
+
+const END: u8 = 0x0b;
+const CALL: u8 = 0x10;
+const CALL_INDIRECT: u8 = 0x11;
+const SELECT: u8 = 0x1b;
+const SELECT_TYPED: u8 = 0x1c;
+const GLOBAL_GET: u8 = 0x23;
+const MEMORY_SIZE: u8 = 0x3f;
+const MEMORY_GROW: u8 = 0x40;
+const I32_CONST: u8 = 0x41;
+const I64_CONST: u8 = 0x42;
+const F32_CONST: u8 = 0x43;
+const F64_CONST: u8 = 0x44;
+const I32_SHL: u8 = 0x74;
+const I32_SHR_S: u8 = 0x75;
+const I64_SHL: u8 = 0x86;
+const I64_SHR_S: u8 = 0x87;
+const I32_EXTEND8_S: u8 = 0xc0;
+const I32_EXTEND16_S: u8 = 0xc1;
+const I64_EXTEND8_S: u8 = 0xc2;
+const I64_EXTEND16_S: u8 = 0xc3;
+const I64_EXTEND32_S: u8 = 0xc4;
+const MISC_PREFIX: u8 = 0xfc;
+const MEMORY_COPY: u32 = 10;
+const MEMORY_FILL: u32 = 11;
+
+/// The version of WebAssembly contracts may use, which a network sets with the wasm protocol
+/// version setting so that its validators upgrade together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WasmProtocolVersion {
+    /// WebAssembly 1.0
+    V1,
+    /// WebAssembly 1.0 with the bulk memory, sign extension and reference types proposals
+    V2,
+}
+
+impl fmt::Display for WasmProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WasmProtocolVersion::V1 => write!(f, "1"),
+            WasmProtocolVersion::V2 => write!(f, "2"),
+        }
+    }
+}
+
+impl FromStr for WasmProtocolVersion {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<WasmProtocolVersion, String> {
+        match version {
+            "1" => Ok(WasmProtocolVersion::V1),
+            "2" => Ok(WasmProtocolVersion::V2),
+            _ => Err(format!(
+                "Wasm protocol version must be 1 or 2, not {}",
+                version
+            )),
+        }
+    }
+}
+
+/// Parse a contract, first rewriting what it uses of the proposals the protocol version accepts
+/// into WebAssembly 1.0
+pub fn parse_contract(wasm: &[u8], version: WasmProtocolVersion) -> Result<Module, ExternalsError> {
+    let rewritten = match version {
+        WasmProtocolVersion::V1 => None,
+        WasmProtocolVersion::V2 => Some(rewrite(wasm)?),
+    };
+    let (wasm, bulk_memory) = match &rewritten {
+        Some((wasm, bulk_memory)) => (wasm.as_slice(), *bulk_memory),
+        None => (wasm, false),
+    };
+
+    let mut module = Module::from_bytes(wasm)
+        .map_err(|err| ExternalsError::from(format!("Unable to parse contract: {}", err)))?;
+    if bulk_memory {
+        check_function_references(&module)?;
+        add_bulk_memory_functions(&mut module)?;
+    }
+
+    Ok(module)
+}
+
+/// Return the contract with the proposals rewritten, and whether it calls the functions which
+/// replace `memory.copy` and `memory.fill`, which are added once it is parsed
+fn rewrite(wasm: &[u8]) -> Result<(Vec<u8>, bool), ExternalsError> {
+    let mut reader = Reader::new(wasm);
+    // The magic number and version are checked by the parser
+    let mut rewritten = reader.take(8)?.to_vec();
+    let mut functions = 0;
+    let mut code = Code::default();
+
+    while !reader.is_empty() {
+        let id = reader.byte()?;
+        let size = reader.u32()? as usize;
+        let mut section = Reader::new(reader.take(size)?);
+
+        let payload = match id {
+            TYPE_SECTION => {
+                code.types = section.u32()?;
+                section.bytes.to_vec()
+            }
+            IMPORT_SECTION => {
+                functions += imported_functions(&mut section)?;
+                section.bytes.to_vec()
+            }
+            FUNCTION_SECTION => {
+                functions += section.u32()?;
+                section.bytes.to_vec()
+            }
+            ELEMENT_SECTION => rewrite_elements(&mut section)?,
+            CODE_SECTION => {
+                // The functions are added after those of the contract
+                code.memory_copy = functions;
+                code.memory_fill = functions + 1;
+                code.rewrite(&mut section)?
+            }
+            DATA_SECTION => rewrite_data(&mut section)?,
+            // The data count is only needed by the instructions of passive data segments, which
+            // are rejected
+            DATA_COUNT_SECTION => continue,
+            _ => section.bytes.to_vec(),
+        };
+
+        rewritten.push(id);
+        write_u32(&mut rewritten, payload.len() as u32);
+        rewritten.extend(payload);
+    }
+
+    Ok((rewritten, code.bulk_memory))
+}
+
+/// Return the number of functions in an import section
+fn imported_functions(section: &mut Reader) -> Result<u32, ExternalsError> {
+    let mut functions = 0;
+    for _ in 0..section.u32()? {
+        for _name in 0..2 {
+            let length = section.u32()? as usize;
+            section.take(length)?;
+        }
+        match section.byte()? {
+            // Function
+            0x00 => {
+                section.u32()?;
+                functions += 1;
+            }
+            // Table
+            0x01 => {
+                section.byte()?;
+                section.limits()?;
+            }
+            // Memory
+            0x02 => section.limits()?,
+            // Global
+            0x03 => {
+                section.take(2)?;
+            }
+            kind => return Err(unsupported(&format!("import of kind 0x{:02x}", kind))),
+        }
+    }
+    Ok(functions)
+}
+
+/// Rewrite the element segments of the contract's table which give their table explicitly
+fn rewrite_elements(section: &mut Reader) -> Result<Vec<u8>, ExternalsError> {
+    let mut rewritten = Vec::new();
+    let count = section.u32()?;
+    write_u32(&mut rewritten, count);
+
+    for _ in 0..count {
+        match section.u32()? {
+            0 => (),
+            2 => {
+                let table = section.u32()?;
+                let offset = section.const_expr()?;
+                let kind = section.byte()?;
+                if table != 0 || kind != 0x00 {
+                    return Err(unsupported("element segment of a second table"));
+                }
+                rewritten.push(0);
+                rewritten.extend(offset);
+                let start = section.position;
+                for _ in 0..section.u32()? {
+                    section.u32()?;
+                }
+                rewritten.extend(section.since(start));
+                continue;
+            }
+            _ => return Err(unsupported("passive or declarative element segment")),
+        }
+
+        rewritten.push(0);
+        let start = section.position;
+        section.const_expr()?;
+        for _ in 0..section.u32()? {
+            section.u32()?;
+        }
+        rewritten.extend(section.since(start));
+    }
+
+    Ok(rewritten)
+}
+
+/// Rewrite the data segments of the contract's memory which give their memory explicitly
+fn rewrite_data(section: &mut Reader) -> Result<Vec<u8>, ExternalsError> {
+    let mut rewritten = Vec::new();
+    let count = section.u32()?;
+    write_u32(&mut rewritten, count);
+
+    for _ in 0..count {
+        match section.u32()? {
+            0 => (),
+            2 if section.u32()? == 0 => (),
+            2 => return Err(unsupported("data segment of a second memory")),
+            _ => return Err(unsupported("passive data segment")),
+        }
+
+        rewritten.push(0);
+        let start = section.position;
+        section.const_expr()?;
+        let length = section.u32()? as usize;
+        section.take(length)?;
+        rewritten.extend(section.since(start));
+    }
+
+    Ok(rewritten)
+}
+
+/// Rewrites the instructions of function bodies
+#[derive(Default)]
+struct Code {
+    /// The number of types the contract defines
+    types: u32,
+    /// The index of the function which replaces `memory.copy`
+    memory_copy: u32,
+    /// The index of the function which replaces `memory.fill`
+    memory_fill: u32,
+    /// Whether either function is called
+    bulk_memory: bool,
+}
+
+impl Code {
+    fn rewrite(&mut self, section: &mut Reader) -> Result<Vec<u8>, ExternalsError> {
+        let mut rewritten = Vec::new();
+        let count = section.u32()?;
+        write_u32(&mut rewritten, count);
+
+        for _ in 0..count {
+            let size = section.u32()? as usize;
+            let mut body = Reader::new(section.take(size)?);
+            let mut code = Vec::new();
+
+            for _ in 0..body.u32()? {
+                body.u32()?;
+                body.byte()?;
+            }
+            code.extend(body.since(0));
+            while !body.is_empty() {
+                self.rewrite_instruction(&mut body, &mut code)?;
+            }
+
+            write_u32(&mut rewritten, code.len() as u32);
+            rewritten.extend(code);
+        }
+
+        Ok(rewritten)
+    }
+
+    fn rewrite_instruction(
+        &mut self,
+        body: &mut Reader,
+        code: &mut Vec<u8>,
+    ) -> Result<(), ExternalsError> {
+        let start = body.position;
+        match body.byte()? {
+            // block, loop and if, whose block type is a value type or a type index
+            0x02..=0x04 => body.leb()?,
+            // br and br_if
+            0x0c | 0x0d => body.leb()?,
+            // br_table
+            0x0e => {
+                for _ in 0..=body.u32()? {
+                    body.u32()?;
+                }
+            }
+            // The functions and type added for `memory.copy` and `memory.fill` come after those
+            // of the contract, which may only refer to its own
+            CALL => {
+                if body.u32()? >= self.memory_copy {
+                    return Err(invalid("call of a function the contract does not define"));
+                }
+            }
+            CALL_INDIRECT => {
+                let type_index = body.u32()?;
+                if type_index >= self.types {
+                    return Err(invalid(
+                        "call_indirect of a type the contract does not define",
+                    ));
+                }
+                if body.u32()? != 0 {
+                    return Err(unsupported("call_indirect of a second table"));
+                }
+                code.push(CALL_INDIRECT);
+                write_u32(code, type_index);
+                code.push(0x00);
+                return Ok(());
+            }
+            SELECT_TYPED => {
+                let count = body.u32()?;
+                let value_type = body.byte()?;
+                if count != 1 || !(0x7c..=0x7f).contains(&value_type) {
+                    return Err(unsupported("select of references"));
+                }
+                code.push(SELECT);
+                return Ok(());
+            }
+            // local.get, local.set, local.tee, global.get and global.set
+            0x20..=0x24 => body.leb()?,
+            0x25 => return Err(unsupported("table.get")),
+            0x26 => return Err(unsupported("table.set")),
+            // Loads and stores, with their alignment and offset
+            0x28..=0x3e => {
+                body.leb()?;
+                body.leb()?;
+            }
+            opcode @ MEMORY_SIZE..=MEMORY_GROW => {
+                if body.u32()? != 0 {
+                    return Err(unsupported("instruction of a second memory"));
+                }
+                code.extend(&[opcode, 0x00]);
+                return Ok(());
+            }
+            I32_CONST | I64_CONST => body.leb()?,
+            F32_CONST => {
+                body.take(4)?;
+            }
+            F64_CONST => {
+                body.take(8)?;
+            }
+            opcode @ I32_EXTEND8_S..=I64_EXTEND32_S => {
+                code.extend(sign_extension(opcode));
+                return Ok(());
+            }
+            0xd0 => return Err(unsupported("ref.null")),
+            0xd1 => return Err(unsupported("ref.is_null")),
+            0xd2 => return Err(unsupported("ref.func")),
+            MISC_PREFIX => {
+                let instruction = body.u32()?;
+                let function = match instruction {
+                    MEMORY_COPY => {
+                        body.u32()?;
+                        self.memory_copy
+                    }
+                    MEMORY_FILL => self.memory_fill,
+                    _ => return Err(unsupported(misc_instruction(instruction))),
+                };
+                if body.u32()? != 0 {
+                    return Err(unsupported("instruction of a second memory"));
+                }
+                code.push(CALL);
+                write_u32(code, function);
+                self.bulk_memory = true;
+                return Ok(());
+            }
+            0xfd => return Err(unsupported("SIMD instruction")),
+            0xfe => return Err(unsupported("atomic instruction")),
+            // The instructions without immediates
+            0x00 | 0x01 | 0x05 | END | 0x0f | 0x1a | SELECT | 0x45..=0xbf => (),
+            opcode => return Err(unsupported(&format!("instruction 0x{:02x}", opcode))),
+        }
+
+        code.extend(body.since(start));
+        Ok(())
+    }
+}
+
+/// Return the WebAssembly 1.0 instructions which sign-extend the low bits of the value on the
+/// stack, shifting them to the top of the value and back
+fn sign_extension(opcode: u8) -> &'static [u8] {
+    match opcode {
+        I32_EXTEND8_S => &[I32_CONST, 24, I32_SHL, I32_CONST, 24, I32_SHR_S],
+        I32_EXTEND16_S => &[I32_CONST, 16, I32_SHL, I32_CONST, 16, I32_SHR_S],
+        I64_EXTEND8_S => &[I64_CONST, 56, I64_SHL, I64_CONST, 56, I64_SHR_S],
+        I64_EXTEND16_S => &[I64_CONST, 48, I64_SHL, I64_CONST, 48, I64_SHR_S],
+        _ => &[I64_CONST, 32, I64_SHL, I64_CONST, 32, I64_SHR_S],
+    }
+}
+
+/// Return the name of an instruction with the 0xfc prefix other than `memory.copy` and
+/// `memory.fill`
+fn misc_instruction(instruction: u32) -> &'static str {
+    match instruction {
+        0..=7 => "non-trapping float-to-int conversion",
+        8 => "memory.init",
+        9 => "data.drop",
+        12 => "table.init",
+        13 => "elem.drop",
+        14 => "table.copy",
+        15 => "table.grow",
+        16 => "table.size",
+        17 => "table.fill",
+        _ => "unknown instruction with the 0xfc prefix",
+    }
+}
+
+fn unsupported(what: &str) -> ExternalsError {
+    ExternalsError::from(format!(
+        "Contract contains a {}, which Sabre does not support",
+        what
+    ))
+}
+
+fn invalid(what: &str) -> ExternalsError {
+    ExternalsError::from(format!(
+        "Unable to validate contract: it contains a {}",
+        what
+    ))
+}
+
+/// Check that the exports, element segments and start function of the rewritten contract only
+/// refer to the functions it defines, before the functions replacing `memory.copy` and
+/// `memory.fill` are added after them
+fn check_function_references(module: &Module) -> Result<(), ExternalsError> {
+    let functions = module.functions_space() as u32;
+    let exported = module
+        .export_section()
+        .map(|section| section.entries())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|export| match export.internal() {
+            Internal::Function(index) => Some(*index),
+            _ => None,
+        });
+    let elements = module
+        .elements_section()
+        .map(|section| section.entries())
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|segment| segment.members().iter().copied());
+
+    if exported
+        .chain(elements)
+        .chain(module.start_section())
+        .any(|index| index >= functions)
+    {
+        return Err(invalid(
+            "reference to a function the contract does not define",
+        ));
+    }
+    Ok(())
+}
+
+/// Add the functions which replace `memory.copy` and `memory.fill`, which take the destination
+/// address, the source address or byte value, and the number of bytes
+fn add_bulk_memory_functions(module: &mut Module) -> Result<(), ExternalsError> {
+    let types = module
+        .type_section_mut()
+        .ok_or_else(|| ExternalsError::from("Contract has functions but no types"))?
+        .types_mut();
+    let type_ref = types.len() as u32;
+    types.push(Type::Function(FunctionType::new(
+        vec![ValueType::I32, ValueType::I32, ValueType::I32],
+        vec![],
+    )));
+
+    let functions = module
+        .function_section_mut()
+        .ok_or_else(|| ExternalsError::from("Contract has code but no functions"))?
+        .entries_mut();
+    functions.push(Func::new(type_ref));
+    functions.push(Func::new(type_ref));
+
+    let bodies = module
+        .code_section_mut()
+        .ok_or_else(|| ExternalsError::from("Contract has no code"))?
+        .bodies_mut();
+    for code in [memory_copy(), memory_fill()] {
+        bodies.push(FuncBody::new(
+            vec![Local::new(1, ValueType::I32), Local::new(1, ValueType::I64)],
+            Instructions::new(code),
+        ));
+    }
+
+    Ok(())
+}
+
+// The locals of the functions which replace `memory.copy` and `memory.fill`
+const DESTINATION: u32 = 0;
+const SOURCE: u32 = 1;
+const VALUE: u32 = 1;
+const LENGTH: u32 = 2;
+const INDEX: u32 = 3;
+const WORD: u32 = 4;
+
+/// Return the instructions of `memory.copy`, which copies eight bytes at a time, from the first
+/// if the destination is below the source and from the last otherwise, so that overlapping
+/// bytes are copied before they are overwritten
+fn memory_copy() -> Vec<Instruction> {
+    use Instruction::*;
+
+    let mut code = bounds_check(SOURCE);
+    code.extend(bounds_check(DESTINATION));
+    code.extend(vec![
+        GetLocal(DESTINATION),
+        GetLocal(SOURCE),
+        I32LeU,
+        If(BlockType::NoResult),
+    ]);
+    code.extend(forward(8, copy(I64Load(0, 0), I64Store(0, 0))));
+    code.extend(forward(1, copy(I32Load8U(0, 0), I32Store8(0, 0))));
+    code.push(Else);
+    code.extend(vec![GetLocal(LENGTH), SetLocal(INDEX)]);
+    code.extend(backward(8, copy(I64Load(0, 0), I64Store(0, 0))));
+    code.extend(backward(1, copy(I32Load8U(0, 0), I32Store8(0, 0))));
+    code.extend(vec![End, End]);
+    code
+}
+
+/// Return the instructions of `memory.fill`, which writes eight bytes at a time
+fn memory_fill() -> Vec<Instruction> {
+    use Instruction::*;
+
+    let mut code = bounds_check(DESTINATION);
+    // The value repeated in each byte of a word
+    code.extend(vec![
+        GetLocal(VALUE),
+        I32Const(0xff),
+        I32And,
+        SetLocal(VALUE),
+        GetLocal(VALUE),
+        I64ExtendUI32,
+        I64Const(0x0101_0101_0101_0101),
+        I64Mul,
+        SetLocal(WORD),
+    ]);
+    code.extend(forward(
+        8,
+        vec![
+            GetLocal(DESTINATION),
+            GetLocal(INDEX),
+            I32Add,
+            GetLocal(WORD),
+            I64Store(0, 0),
+        ],
+    ));
+    code.extend(forward(
+        1,
+        vec![
+            GetLocal(DESTINATION),
+            GetLocal(INDEX),
+            I32Add,
+            GetLocal(VALUE),
+            I32Store8(0, 0),
+        ],
+    ));
+    code.push(End);
+    code
+}
+
+/// Return the instructions which trap if the `LENGTH` bytes from the address in the local are
+/// not all in memory, before any is written
+fn bounds_check(address: u32) -> Vec<Instruction> {
+    use Instruction::*;
+
+    vec![
+        GetLocal(address),
+        I64ExtendUI32,
+        GetLocal(LENGTH),
+        I64ExtendUI32,
+        I64Add,
+        CurrentMemory(0),
+        I64ExtendUI32,
+        I64Const(16),
+        I64Shl,
+        I64GtU,
+        If(BlockType::NoResult),
+        Unreachable,
+        End,
+    ]
+}
+
+/// Return the instructions which copy `step` bytes at `INDEX` from the source to the destination
+fn copy(load: Instruction, store: Instruction) -> Vec<Instruction> {
+    use Instruction::*;
+
+    vec![
+        GetLocal(DESTINATION),
+        GetLocal(INDEX),
+        I32Add,
+        GetLocal(SOURCE),
+        GetLocal(INDEX),
+        I32Add,
+        load,
+        store,
+    ]
+}
+
+/// Return a loop running `body` at each `step` bytes from `INDEX` up to `LENGTH`, while `step`
+/// bytes remain
+fn forward(step: i32, body: Vec<Instruction>) -> Vec<Instruction> {
+    use Instruction::*;
+
+    let mut code = vec![
+        Block(BlockType::NoResult),
+        Loop(BlockType::NoResult),
+        GetLocal(LENGTH),
+        GetLocal(INDEX),
+        I32Sub,
+        I32Const(step),
+        I32LtU,
+        BrIf(1),
+    ];
+    code.extend(body);
+    code.extend(vec![
+        GetLocal(INDEX),
+        I32Const(step),
+        I32Add,
+        SetLocal(INDEX),
+        Br(0),
+        End,
+        End,
+    ]);
+    code
+}
+
+/// Return a loop running `body` at each `step` bytes from `INDEX` down to 0, while `step` bytes
+/// remain
+fn backward(step: i32, body: Vec<Instruction>) -> Vec<Instruction> {
+    use Instruction::*;
+
+    let mut code = vec![
+        Block(BlockType::NoResult),
+        Loop(BlockType::NoResult),
+        GetLocal(INDEX),
+        I32Const(step),
+        I32LtU,
+        BrIf(1),
+        GetLocal(INDEX),
+        I32Const(step),
+        I32Sub,
+        SetLocal(INDEX),
+    ];
+    code.extend(body);
+    code.extend(vec![Br(0), End, End]);
+    code
+}
+
+/// Reads the encoding of a module
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    /// Return the bytes read since `start`
+    fn since(&self, start: usize) -> &'a [u8] {
+        &self.bytes[start..self.position]
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], ExternalsError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| ExternalsError::from("Unable to parse contract: unexpected end"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, ExternalsError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Read an unsigned LEB128 integer of at most 32 bits
+    fn u32(&mut self) -> Result<u32, ExternalsError> {
+        let mut value = 0u64;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return u32::try_from(value).map_err(|_| {
+                    ExternalsError::from("Unable to parse contract: integer is too large")
+                });
+            }
+        }
+        Err(ExternalsError::from(
+            "Unable to parse contract: integer is too long",
+        ))
+    }
+
+    /// Skip a LEB128 integer, signed or not, of at most 64 bits
+    fn leb(&mut self) -> Result<(), ExternalsError> {
+        for _ in 0..10 {
+            if self.byte()? & 0x80 == 0 {
+                return Ok(());
+            }
+        }
+        Err(ExternalsError::from(
+            "Unable to parse contract: integer is too long",
+        ))
+    }
+
+    /// Skip the limits of a table or memory
+    fn limits(&mut self) -> Result<(), ExternalsError> {
+        let flags = self.byte()?;
+        self.u32()?;
+        if flags & 0x01 != 0 {
+            self.u32()?;
+        }
+        Ok(())
+    }
+
+    /// Read the constant expression of a global or segment, up to and including its end
+    fn const_expr(&mut self) -> Result<&'a [u8], ExternalsError> {
+        let start = self.position;
+        loop {
+            match self.byte()? {
+                END => return Ok(self.since(start)),
+                I32_CONST | I64_CONST | GLOBAL_GET => self.leb()?,
+                F32_CONST => {
+                    self.take(4)?;
+                }
+                F64_CONST => {
+                    self.take(8)?;
+                }
+                opcode => {
+                    return Err(unsupported(&format!(
+                        "constant expression with instruction 0x{:02x}",
+                        opcode
+                    )))
+                }
+            }
+        }
+    }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
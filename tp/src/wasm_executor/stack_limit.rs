@@ -0,0 +1,44 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Limits the stacks a contract may use, and reports a contract which exhausts them
+//!
+//! wasmi keeps the values of a contract's functions on a value stack of a fixed number of bytes
+//! and their calls on a call stack of a fixed number of frames; a contract which overflows
+//! either traps. Each execution of a contract, or of a smart permission or contract it invokes,
+//! has stacks of its own.
+
+use wasmi::{Error, StackRecycler, TrapKind};
+
+use crate::wasm_executor::wasm_externals::ExternalsError;
+use crate::wasm_executor::ExecutionLimits;
+
+/// Return a recycler creating the stacks of an execution with the limits
+pub fn stack_recycler(limits: ExecutionLimits) -> StackRecycler {
+    StackRecycler::with_limits(limits.max_stack_size, limits.max_stack_depth as usize)
+}
+
+/// Return whether an execution failed because the contract overflowed one of its stacks
+pub fn is_stack_overflow(err: &Error) -> bool {
+    matches!(err, Error::Trap(trap) if matches!(trap.kind(), TrapKind::StackOverflow))
+}
+
+/// The error returned when a contract interpreted by wasmi overflows one of its stacks
+pub fn stack_exhausted(limits: ExecutionLimits) -> ExternalsError {
+    ExternalsError::from(format!(
+        "Contract stack exhausted: the contract nested more than {} calls or used more than {} \
+         bytes of value stack",
+        limits.max_stack_depth, limits.max_stack_size
+    ))
+}
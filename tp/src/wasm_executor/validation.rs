@@ -0,0 +1,219 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks contracts when they are created, so that a contract which can never be executed is
+//! rejected by the transaction which creates it rather than by its first execution
+
+use parity_wasm::elements::{External, FunctionType, Internal, Module, Type, ValueType};
+use wasmi::Signature;
+
+use crate::wasm_executor::proposals::{self, WasmProtocolVersion};
+use crate::wasm_executor::wasi;
+use crate::wasm_executor::wasm_externals::{host_function_signature, ExternalsError};
+
+/// The function every contract exports, which Sabre calls to execute it
+const ENTRYPOINT: &str = "entrypoint";
+
+/// The prefix of the modules the WebAssembly System Interface provides, such as
+/// `wasi_snapshot_preview1`
+const WASI_MODULE_PREFIX: &str = "wasi";
+
+/// Check that a contract is at most `max_size` bytes, is valid WebAssembly using only the
+/// features Sabre supports under the wasm protocol version, imports only the Sabre host functions and memory and the WASI
+/// functions Sabre provides, and exports an entrypoint which takes the pointers to the payload,
+/// signer and signature and returns the result
+pub fn validate_contract(
+    wasm: &[u8],
+    max_size: usize,
+    wasm_protocol_version: WasmProtocolVersion,
+) -> Result<(), ExternalsError> {
+    if wasm.len() > max_size {
+        return Err(ExternalsError::from(format!(
+            "Contract is {} bytes, more than the limit of {}",
+            wasm.len(),
+            max_size
+        )));
+    }
+
+    // Parsing fails for the instructions of WebAssembly proposals which Sabre does not support,
+    // and for those of the proposals which Sabre rewrites, unless the wasm protocol version
+    // accepts them
+    let module = proposals::parse_contract(wasm, wasm_protocol_version)?;
+
+    if module.start_section().is_some() {
+        return Err(ExternalsError::from(
+            "Contract has a start function, which is not allowed",
+        ));
+    }
+    check_imports(&module)?;
+    check_entrypoint(&module)?;
+
+    // Validate the types and instructions of the contract's functions
+    wasmi::Module::from_parity_wasm_module(module)
+        .map_err(|err| ExternalsError::from(format!("Unable to validate contract: {}", err)))?;
+
+    Ok(())
+}
+
+/// Check that a contract imports only the Sabre host functions and the memory of module `env`,
+/// and the WASI functions Sabre provides, with their signatures, naming the first import which
+/// is not
+///
+/// Contracts are checked when they are created and again when they are loaded to be executed,
+/// since a contract created by an older version of Sabre may import what Sabre does not provide.
+pub fn check_imports(module: &Module) -> Result<(), ExternalsError> {
+    let imports = module
+        .import_section()
+        .map(|section| section.entries())
+        .unwrap_or_default();
+
+    for import in imports {
+        let (expected, kind) = match import.module() {
+            "env" => (host_function_signature(import.field()), "host function"),
+            wasi::WASI_MODULE => (
+                wasi::wasi_function_signature(import.field()),
+                "WASI function",
+            ),
+            name if name.starts_with(WASI_MODULE_PREFIX) => {
+                return Err(ExternalsError::from(format!(
+                    "Contract imports {} from module {}, but the only WASI module Sabre \
+                     provides is {}",
+                    import.field(),
+                    name,
+                    wasi::WASI_MODULE
+                )))
+            }
+            name => {
+                return Err(ExternalsError::from(format!(
+                    "Contract imports {} from module {}, but Sabre only provides modules env and \
+                     {}",
+                    import.field(),
+                    name,
+                    wasi::WASI_MODULE
+                )))
+            }
+        };
+
+        match import.external() {
+            External::Function(type_ref) => {
+                let expected = expected.ok_or_else(|| {
+                    ExternalsError::from(format!(
+                        "Contract imports {} from module {}, which is not a {} Sabre provides",
+                        import.field(),
+                        import.module(),
+                        kind
+                    ))
+                })?;
+                if function_type(module, *type_ref).map(signature) != Some(expected) {
+                    return Err(ExternalsError::from(format!(
+                        "Contract imports the {} {} with the wrong signature",
+                        kind,
+                        import.field()
+                    )));
+                }
+            }
+            External::Memory(_) if import.module() == "env" && import.field() == "memory" => (),
+            _ => {
+                return Err(ExternalsError::from(format!(
+                    "Contract imports {} from module {}, which Sabre does not provide",
+                    import.field(),
+                    import.module()
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_entrypoint(module: &Module) -> Result<(), ExternalsError> {
+    let function = module
+        .export_section()
+        .map(|section| section.entries())
+        .unwrap_or_default()
+        .iter()
+        .find(|export| export.field() == ENTRYPOINT)
+        .and_then(|export| match export.internal() {
+            Internal::Function(index) => Some(*index),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            ExternalsError::from(format!(
+                "Contract does not export the {} function",
+                ENTRYPOINT
+            ))
+        })?;
+
+    let expected = FunctionType::new(
+        vec![ValueType::I32, ValueType::I32, ValueType::I32],
+        vec![ValueType::I32],
+    );
+    if function_type_ref(module, function).and_then(|type_ref| function_type(module, type_ref))
+        != Some(&expected)
+    {
+        return Err(ExternalsError::from(format!(
+            "The {} function of the contract must take three i32 pointers and return an i32",
+            ENTRYPOINT
+        )));
+    }
+
+    Ok(())
+}
+
+/// Return the index of the type of a function, counting the imported functions first
+fn function_type_ref(module: &Module, function: u32) -> Option<u32> {
+    let imported = module
+        .import_section()
+        .map(|section| section.entries())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|import| match import.external() {
+            External::Function(type_ref) => Some(*type_ref),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    match imported.get(function as usize) {
+        Some(type_ref) => Some(*type_ref),
+        None => module
+            .function_section()
+            .and_then(|section| section.entries().get(function as usize - imported.len()))
+            .map(|function| function.type_ref()),
+    }
+}
+
+fn function_type(module: &Module, type_ref: u32) -> Option<&FunctionType> {
+    module
+        .type_section()
+        .and_then(|section| section.types().get(type_ref as usize))
+        .map(|Type::Function(function_type)| function_type)
+}
+
+fn signature(function_type: &FunctionType) -> Signature {
+    let value_type = |value_type: &ValueType| match value_type {
+        ValueType::I32 => wasmi::ValueType::I32,
+        ValueType::I64 => wasmi::ValueType::I64,
+        ValueType::F32 => wasmi::ValueType::F32,
+        ValueType::F64 => wasmi::ValueType::F64,
+    };
+
+    Signature::new(
+        function_type
+            .params()
+            .iter()
+            .map(value_type)
+            .collect::<Vec<_>>(),
+        function_type.results().first().map(value_type),
+    )
+}
@@ -0,0 +1,238 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Provides a deterministic subset of the WebAssembly System Interface, so that contracts built
+//! for the `wasm32-wasi` target can be executed without patching their imports
+//!
+//! A contract has no arguments or environment variables, and cannot read the clocks or random
+//! numbers, which would differ between validators; those functions return `ENOSYS`. What a
+//! contract writes to stdout and stderr is written to the transaction processor's log, and any
+//! other file descriptor is `EBADF`. Unlike the Sabre host functions, which read the memory of
+//! module `env`, the WASI functions read the memory the contract exports, where the toolchains
+//! place the buffers they pass.
+
+use wasmi::{
+    Error, FuncInstance, FuncRef, ModuleImportResolver, RuntimeArgs, RuntimeValue, Signature,
+    ValueType,
+};
+
+use crate::wasm_executor::host::ContractMemory;
+use crate::wasm_executor::wasm_externals::ExternalsError;
+
+/// The WASI module Sabre provides functions of
+pub const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+const ERRNO_SUCCESS: i32 = 0;
+const ERRNO_BADF: i32 = 8;
+const ERRNO_NOSYS: i32 = 52;
+
+const STDOUT: i32 = 1;
+const STDERR: i32 = 2;
+
+/// The size of a `ciovec`: the pointer to a buffer and its length
+const IOVEC_SIZE: u32 = 8;
+
+// WASI function indices, which follow the host function indices
+
+const ARGS_GET_IDX: usize = 100;
+const ARGS_SIZES_GET_IDX: usize = 101;
+const ENVIRON_GET_IDX: usize = 102;
+const ENVIRON_SIZES_GET_IDX: usize = 103;
+const CLOCK_RES_GET_IDX: usize = 104;
+const CLOCK_TIME_GET_IDX: usize = 105;
+const RANDOM_GET_IDX: usize = 106;
+const FD_WRITE_IDX: usize = 107;
+const PROC_EXIT_IDX: usize = 108;
+
+/// The WASI functions contracts may import from `WASI_MODULE`: their names, indices, parameters
+/// and results
+const WASI_FUNCTIONS: &[(&str, usize, &[ValueType], Option<ValueType>)] = &[
+    (
+        "args_get",
+        ARGS_GET_IDX,
+        &[ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "args_sizes_get",
+        ARGS_SIZES_GET_IDX,
+        &[ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "environ_get",
+        ENVIRON_GET_IDX,
+        &[ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "environ_sizes_get",
+        ENVIRON_SIZES_GET_IDX,
+        &[ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "clock_res_get",
+        CLOCK_RES_GET_IDX,
+        &[ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "clock_time_get",
+        CLOCK_TIME_GET_IDX,
+        &[ValueType::I32, ValueType::I64, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "random_get",
+        RANDOM_GET_IDX,
+        &[ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "fd_write",
+        FD_WRITE_IDX,
+        &[
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+        ],
+        Some(ValueType::I32),
+    ),
+    ("proc_exit", PROC_EXIT_IDX, &[ValueType::I32], None),
+];
+
+/// Return the signature of the WASI function a contract may import from `WASI_MODULE` under the
+/// given name
+pub fn wasi_function_signature(name: &str) -> Option<Signature> {
+    WASI_FUNCTIONS
+        .iter()
+        .find(|(wasi_function, ..)| *wasi_function == name)
+        .map(|(_, _, params, result)| Signature::new(*params, *result))
+}
+
+/// Return whether a function index of wasmi is one of a WASI function
+pub fn is_wasi_function(index: usize) -> bool {
+    WASI_FUNCTIONS
+        .iter()
+        .any(|(_, wasi_index, ..)| *wasi_index == index)
+}
+
+/// Call the WASI function with the given wasmi index
+pub fn invoke(
+    index: usize,
+    memory: &mut dyn ContractMemory,
+    args: RuntimeArgs,
+) -> Result<Option<RuntimeValue>, ExternalsError> {
+    let errno = match index {
+        ARGS_GET_IDX | ENVIRON_GET_IDX => get(),
+        ARGS_SIZES_GET_IDX | ENVIRON_SIZES_GET_IDX => sizes_get(memory, args.nth(0), args.nth(1))?,
+        CLOCK_RES_GET_IDX | CLOCK_TIME_GET_IDX | RANDOM_GET_IDX => unsupported(),
+        FD_WRITE_IDX => fd_write(memory, args.nth(0), args.nth(1), args.nth(2), args.nth(3))?,
+        PROC_EXIT_IDX => return Err(proc_exit(args.nth(0))),
+        _ => return Err(ExternalsError::from("Function does not exist")),
+    };
+
+    Ok(Some(RuntimeValue::I32(errno)))
+}
+
+/// `args_get` and `environ_get`: there are no arguments or environment variables to write
+pub fn get() -> i32 {
+    ERRNO_SUCCESS
+}
+
+/// `args_sizes_get` and `environ_sizes_get`: write that there are no arguments or environment
+/// variables, which take no bytes
+pub fn sizes_get(
+    memory: &mut dyn ContractMemory,
+    count_ptr: u32,
+    size_ptr: u32,
+) -> Result<i32, ExternalsError> {
+    memory.set(count_ptr, &0u32.to_le_bytes())?;
+    memory.set(size_ptr, &0u32.to_le_bytes())?;
+    Ok(ERRNO_SUCCESS)
+}
+
+/// `clock_res_get`, `clock_time_get` and `random_get`, which would make the execution of a
+/// contract differ between validators
+pub fn unsupported() -> i32 {
+    ERRNO_NOSYS
+}
+
+/// `fd_write`: write the buffers of the iovecs to the log, at info level for stdout and warn level
+/// for stderr, and the number of bytes written to `nwritten_ptr`
+pub fn fd_write(
+    memory: &mut dyn ContractMemory,
+    fd: i32,
+    iovs_ptr: u32,
+    iovs_len: u32,
+    nwritten_ptr: u32,
+) -> Result<i32, ExternalsError> {
+    if fd != STDOUT && fd != STDERR {
+        return Ok(ERRNO_BADF);
+    }
+
+    let mut written = Vec::new();
+    for iov in 0..iovs_len {
+        let iov_ptr = iov
+            .checked_mul(IOVEC_SIZE)
+            .and_then(|offset| iovs_ptr.checked_add(offset))
+            .ok_or_else(|| ExternalsError::from("The iovecs passed to fd_write overflow memory"))?;
+        let iovec = memory.get(iov_ptr, IOVEC_SIZE as usize)?;
+        let buf_ptr = u32::from_le_bytes([iovec[0], iovec[1], iovec[2], iovec[3]]);
+        let buf_len = u32::from_le_bytes([iovec[4], iovec[5], iovec[6], iovec[7]]);
+        written.extend(memory.get(buf_ptr, buf_len as usize)?);
+    }
+
+    let output = String::from_utf8_lossy(&written);
+    let output = output.trim_end_matches('\n');
+    if fd == STDOUT {
+        info!("Contract stdout: {}", output);
+    } else {
+        warn!("Contract stderr: {}", output);
+    }
+
+    memory.set(nwritten_ptr, &(written.len() as u32).to_le_bytes())?;
+    Ok(ERRNO_SUCCESS)
+}
+
+/// `proc_exit`: end the execution of the contract
+pub fn proc_exit(code: i32) -> ExternalsError {
+    ExternalsError::from(format!("Contract exited with code {}", code))
+}
+
+/// The error of a WASI function called by a contract which does not export its memory
+pub fn no_memory() -> ExternalsError {
+    ExternalsError::from("Contract must export its memory to call WASI functions")
+}
+
+/// Resolves the WASI functions contracts import from `WASI_MODULE` for wasmi, which calls them
+/// through `invoke`
+pub struct WasiResolver;
+
+impl ModuleImportResolver for WasiResolver {
+    fn resolve_func(&self, field_name: &str, _signature: &Signature) -> Result<FuncRef, Error> {
+        match WASI_FUNCTIONS.iter().find(|(name, ..)| *name == field_name) {
+            Some((_, index, params, result)) => Ok(FuncInstance::alloc_host(
+                Signature::new(*params, *result),
+                *index,
+            )),
+            None => Err(Error::Instantiation(format!(
+                "Export {} not found",
+                field_name
+            ))),
+        }
+    }
+}
@@ -13,22 +13,25 @@
 // limitations under the License.
 
 use std::boxed::Box;
-use std::collections::HashMap;
 use std::fmt;
 use std::string::FromUtf8Error;
-use std::time::Instant;
 
-use log::{max_level, LevelFilter};
-use sabre_sdk::protocol::state::{SmartPermission, SmartPermissionList};
-use sabre_sdk::protos::FromBytes;
 use sawtooth_sdk::processor::handler::{ContextError, TransactionContext};
 use wasmi::memory_units::Pages;
 use wasmi::{
     Error, Externals, FuncInstance, FuncRef, HostError, ImportsBuilder, MemoryDescriptor,
-    MemoryInstance, MemoryRef, Module, ModuleImportResolver, ModuleInstance, RuntimeArgs,
-    RuntimeValue, Signature, Trap, TrapKind, ValueType,
+    MemoryInstance, MemoryRef, Module, ModuleImportResolver, ModuleInstance, ModuleRef,
+    RuntimeArgs, RuntimeValue, Signature, Trap, TrapKind, ValueType,
 };
 
+use crate::wasm_executor::host::HostState;
+use crate::wasm_executor::memory_limit;
+use crate::wasm_executor::metering;
+use crate::wasm_executor::module_cache::{ContractCache, SmartPermissionCache};
+use crate::wasm_executor::stack_limit;
+use crate::wasm_executor::wasi::{self, WasiResolver};
+use crate::wasm_executor::{ContractError, ExecutionLimits, Transaction};
+
 // External function indices
 
 /// Args
@@ -168,89 +171,228 @@ const LOG_LEVEL: usize = 14;
 ///
 const ADD_EVENT_IDX: usize = 15;
 
+/// Args
+///
+/// 1) Data, that is opaque to the validator and attached to the transaction receipt
+///
+/// Returns - 0 if the add_receipt_data() is successful, 1 otherwise
+///
+const ADD_RECEIPT_DATA_IDX: usize = 17;
+
+/// Args
+///
+/// 1) Contract name
+/// 2) Contract version
+/// 3) Payload to execute the contract with
+///
+/// Returns - the result of the contract's entrypoint
+///
+const INVOKE_CONTRACT_IDX: usize = 18;
+
+/// Args
+///
+/// 1) The credit of a metered contract, which has dropped below zero
+///
+/// Returns - the new credit of the contract; traps if the contract has exceeded its limits
+///
+const METER_IDX: usize = 16;
+
+/// Args
+///
+/// 1) Code of the error, defined by the contract
+/// 2) Message of the error
+///
+/// Returns - 0 once the error is recorded
+///
+const SET_ERROR_IDX: usize = 19;
+
+/// The host functions contracts may import from the `env` module: their names, indices,
+/// parameters and results
+pub(crate) const HOST_FUNCTIONS: &[(&str, usize, &[ValueType], Option<ValueType>)] = &[
+    (
+        "get_state",
+        GET_STATE_IDX,
+        &[ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "set_state",
+        SET_STATE_IDX,
+        &[ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "delete_state",
+        DELETE_STATE_IDX,
+        &[ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "add_event",
+        ADD_EVENT_IDX,
+        &[ValueType::I32, ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "add_receipt_data",
+        ADD_RECEIPT_DATA_IDX,
+        &[ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "invoke_contract",
+        INVOKE_CONTRACT_IDX,
+        &[ValueType::I32, ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "invoke_smart_permission",
+        SMART_PERMISSION,
+        &[
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+            ValueType::I32,
+        ],
+        Some(ValueType::I32),
+    ),
+    (
+        "get_ptr_len",
+        GET_PTR_LEN_IDX,
+        &[ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "get_ptr_capacity",
+        GET_PTR_CAP_IDX,
+        &[ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    ("alloc", ALLOC_IDX, &[ValueType::I32], Some(ValueType::I32)),
+    (
+        "read_byte",
+        READ_BYTE_IDX,
+        &[ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "write_byte",
+        WRITE_BYTE_IDX,
+        &[ValueType::I32, ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "get_ptr_collection_len",
+        GET_COLLECTION_LEN_IDX,
+        &[ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "get_ptr_from_collection",
+        GET_PTR_FROM_COLLECTION_IDX,
+        &[ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "create_collection",
+        CREATE_COLLECTION,
+        &[ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "add_to_collection",
+        ADD_TO_COLLECTION,
+        &[ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    (
+        "set_error",
+        SET_ERROR_IDX,
+        &[ValueType::I32, ValueType::I32],
+        Some(ValueType::I32),
+    ),
+    ("log_buffer", LOG, &[ValueType::I32, ValueType::I32], None),
+    ("log_level", LOG_LEVEL, &[], Some(ValueType::I32)),
+];
+
+/// Return the signature of the host function a contract may import from the `env` module under
+/// the given name
+pub fn host_function_signature(name: &str) -> Option<Signature> {
+    HOST_FUNCTIONS
+        .iter()
+        .find(|(host_function, ..)| *host_function == name)
+        .map(|(_, _, params, result)| Signature::new(*params, *result))
+}
+
 pub struct WasmExternals<'a> {
     pub memory_ref: MemoryRef,
-    context: &'a mut dyn TransactionContext,
-    ptrs: HashMap<u32, Pointer>,
-    ptr_collections: HashMap<u32, Vec<u32>>,
-    memory_write_offset: u32,
+    /// The memory the contract exports, which the WASI functions read
+    contract_memory: Option<MemoryRef>,
+    host: HostState<'a>,
+    /// The meter of the execution, shared with the contracts and smart permissions the contract
+    /// invokes
+    meter: &'a mut metering::InstructionMeter,
 }
 
 impl<'a> WasmExternals<'a> {
     pub fn new(
         memory_ref: Option<MemoryRef>,
         context: &'a mut dyn TransactionContext,
-    ) -> Result<WasmExternals, ExternalsError> {
+        transaction: Option<&'a Transaction>,
+        limits: ExecutionLimits,
+        meter: &'a mut metering::InstructionMeter,
+        contract_cache: &'a ContractCache,
+        smart_permission_cache: &'a SmartPermissionCache,
+    ) -> Result<WasmExternals<'a>, ExternalsError> {
         let m_ref = if let Some(m) = memory_ref {
             m
         } else {
-            MemoryInstance::alloc(Pages(256), None)?
+            MemoryInstance::alloc(
+                Pages(memory_limit::host_memory_pages(limits.max_memory_pages) as usize),
+                Some(Pages(limits.max_memory_pages as usize)),
+            )?
         };
 
         Ok(WasmExternals {
             memory_ref: m_ref,
-            context,
-            ptrs: HashMap::new(),
-            ptr_collections: HashMap::new(),
-            memory_write_offset: 0,
+            contract_memory: None,
+            host: HostState::new(
+                context,
+                transaction,
+                limits,
+                contract_cache,
+                smart_permission_cache,
+            ),
+            meter,
         })
     }
 
-    fn ptr_to_string(&mut self, raw_ptr: u32) -> Result<String, ExternalsError> {
-        if let Some(p) = self.ptrs.get(&raw_ptr) {
-            let bytes = self.get_memory_ref().get(p.raw, p.length)?;
-
-            String::from_utf8(bytes).map_err(ExternalsError::from)
-        } else {
-            Err(ExternalsError::from(format!(
-                "ptr referencing {} not found",
-                raw_ptr
-            )))
-        }
-    }
-
-    fn ptr_to_vec(&mut self, raw_ptr: u32) -> Result<Vec<u8>, ExternalsError> {
-        if let Some(p) = self.ptrs.get(&raw_ptr) {
-            self.get_memory_ref()
-                .get(p.raw, p.length)
-                .map_err(ExternalsError::from)
-        } else {
-            Err(ExternalsError::from(format!(
-                "ptr referencing {} not found",
-                raw_ptr
-            )))
-        }
-    }
-
     fn get_memory_ref(&self) -> MemoryRef {
         self.memory_ref.clone()
     }
 
-    pub fn write_data(&mut self, data: Vec<u8>) -> Result<u32, ExternalsError> {
-        self.get_memory_ref().set(self.memory_write_offset, &data)?;
-
-        let ptr = Pointer {
-            raw: self.memory_write_offset,
-            length: data.len(),
-            capacity: data.capacity(),
-        };
-
-        let raw_ptr = ptr.raw;
-
-        // In case the data to be added is empty, keep the memory_write_offset
-        // moving to the next location.
-        let offset_to_add = if data.capacity() == 0 {
-            1
-        } else {
-            data.capacity() as u32
-        };
+    /// Instantiate a module with the host functions of module `env` and the WASI functions,
+    /// keeping the memory the instance exports for the WASI functions
+    pub fn instantiate(&mut self, module: &Module) -> Result<ModuleRef, ExternalsError> {
+        let instance = ModuleInstance::new(
+            module,
+            &ImportsBuilder::new()
+                .with_resolver("env", &*self)
+                .with_resolver(wasi::WASI_MODULE, &WasiResolver),
+        )?
+        .assert_no_start();
 
-        self.ptrs.insert(self.memory_write_offset, ptr);
-        self.memory_write_offset += offset_to_add;
+        self.contract_memory = instance
+            .export_by_name("memory")
+            .and_then(|export| export.as_memory().cloned());
 
-        debug!("moved the pointer to {:?}", self.memory_write_offset);
+        Ok(instance)
+    }
 
-        Ok(raw_ptr)
+    pub fn write_data(&mut self, data: Vec<u8>) -> Result<u32, ExternalsError> {
+        self.host.write_data(&mut self.memory_ref, data)
     }
 
     /// Takes a list of pointers and associates them,
@@ -260,283 +402,33 @@ impl<'a> WasmExternals<'a> {
     /// of the first pointer in the list or an externals
     /// error
     pub fn collect_ptrs(&mut self, raw_ptrs: Vec<u32>) -> Result<u32, ExternalsError> {
-        info!("associating pointers: {:?}", raw_ptrs);
-        if raw_ptrs.iter().all(|x| self.ptrs.contains_key(&x)) {
-            self.ptr_collections.insert(raw_ptrs[0], raw_ptrs.clone());
-            Ok(raw_ptrs[0])
-        } else {
-            Err(ExternalsError::from(
-                "Attempting to create a ptr collection with nonexistant pointers",
-            ))
-        }
+        self.host.collect_ptrs(raw_ptrs)
     }
 
-    pub fn add_to_collection(&mut self, head: u32, raw_ptr: u32) -> Result<u32, ExternalsError> {
-        info!("adding to collection: {:?}", raw_ptr);
-        if let Some(x) = self.ptr_collections.get_mut(&head) {
-            x.push(raw_ptr);
-            Ok(head)
-        } else {
-            Err(ExternalsError::from(
-                "Attempting to add a ptr to nonexistant collecttion",
-            ))
-        }
+    /// Return the error the contract reported with `set_error`, if any
+    pub fn take_error(&mut self) -> Option<ContractError> {
+        self.host.take_error()
     }
 
-    pub fn create_collection(&mut self, head: u32) -> Result<u32, ExternalsError> {
-        info!("create_collection: {:?}", head);
-        self.ptr_collections.insert(head, vec![head]);
-        Ok(head)
+    /// Return whether the contract has run out of instructions
+    pub fn is_exhausted(&self) -> bool {
+        self.meter.is_exhausted()
     }
 
-    pub fn get_smart_permission(
-        &mut self,
-        address: &str,
-        name: &str,
-    ) -> Result<Option<SmartPermission>, ExternalsError> {
-        let d = self.context.get_state_entry(address)?;
-        match d {
-            Some(packed) => {
-                let smart_permissions = match SmartPermissionList::from_bytes(packed.as_slice()) {
-                    Ok(smart_permissions) => smart_permissions,
-                    Err(err) => {
-                        return Err(ExternalsError {
-                            message: format!("Cannot deserialize smart permission list: {:?}", err),
-                        });
-                    }
-                };
-
-                for smart_permission in smart_permissions.smart_permissions() {
-                    if smart_permission.name() == name {
-                        return Ok(Some(smart_permission.clone()));
-                    }
-                }
-                Ok(None)
-            }
-            None => Ok(None),
-        }
-    }
-
-    fn smart_permission(&mut self, args: RuntimeArgs) -> Result<Option<RuntimeValue>, Trap> {
-        let timer = Instant::now();
-        let contract_addr_ptr: i32 = args.nth(0);
-        let name: i32 = args.nth(1);
-        let roles_head_ptr: u32 = args.nth(2);
-        let org_id_ptr: i32 = args.nth(3);
-        let public_key_ptr: i32 = args.nth(4);
-        let payload_ptr: i32 = args.nth(5);
-
-        let roles = match self.ptr_collections.get(&roles_head_ptr) {
-            Some(roles) => roles.clone(),
-            None => return Ok(Some(RuntimeValue::I32(-1))),
-        };
-        let mut role_vec = Vec::new();
-        for role in roles {
-            let role_str = self.ptr_to_string(role).map_err(ExternalsError::from)?;
-            role_vec.push(role_str);
-        }
-        let org_id = self.ptr_to_string(org_id_ptr as u32)?;
-        let public_key = self.ptr_to_string(public_key_ptr as u32)?;
-        let payload = self.ptr_to_vec(payload_ptr as u32)?;
-        let name = self.ptr_to_string(name as u32)?;
-        let contract_addr = self.ptr_to_string(contract_addr_ptr as u32)?;
-
-        let contract = if let Some(sp) = self.get_smart_permission(&contract_addr, &name)? {
-            sp
-        } else {
-            return Ok(Some(RuntimeValue::I32(-2)));
-        };
-
-        // Invoke Smart Permission
-        let mut module = SmartPermissionModule::new(contract.function(), self.context)
-            .expect("Failed to create can_add module");
-        let result = module
-            .entrypoint(role_vec, org_id, public_key, payload.to_vec())
-            .map_err(|e| ExternalsError::from(format!("{:?}", e)))?;
-
-        match result {
-            Some(x) => {
-                info!(
-                    "SMART_PERMISSION Execution time: {} secs {} ms",
-                    timer.elapsed().as_secs(),
-                    timer.elapsed().subsec_millis()
-                );
-                Ok(Some(RuntimeValue::I32(x)))
-            }
-            None => Err(ExternalsError::trap("No result returned".into())),
+    /// Settle the credit of an instance which has returned, so that the instructions it did not
+    /// use may be run by the instances which follow it
+    pub fn settle(&mut self, instance: &ModuleRef) {
+        let credit = instance
+            .export_by_name(metering::CREDIT_EXPORT)
+            .and_then(|export| export.as_global().map(|global| global.get()));
+        if let Some(RuntimeValue::I64(credit)) = credit {
+            self.meter.settle(credit);
         }
     }
 
-    fn get_state(
-        &mut self,
-        args: RuntimeArgs,
-        timer: Instant,
-    ) -> Result<Option<RuntimeValue>, Trap> {
-        let head_ptr: u32 = args.nth(0);
-        let addresses = match self.ptr_collections.get(&head_ptr) {
-            Some(addresses) => addresses.clone(),
-            None => return Ok(Some(RuntimeValue::I32(-1))),
-        };
-        let mut addr_vec = Vec::new();
-        for addr in addresses {
-            let address = self.ptr_to_string(addr).map_err(ExternalsError::from)?;
-            addr_vec.push(address);
-        }
-
-        info!("Attempting to get state, addresses: {:?}", addr_vec);
-
-        let state = self
-            .context
-            .get_state_entries(&addr_vec)
-            .map_err(ExternalsError::from)?;
-
-        let mut ptr_vec = Vec::new();
-        for (addr, data) in state {
-            let addr_raw_ptr = self.write_data(addr.as_bytes().to_vec())?;
-            ptr_vec.push(addr_raw_ptr);
-
-            let data_raw_ptr = self.write_data(data)?;
-            ptr_vec.push(data_raw_ptr);
-        }
-
-        // collect ptrs or return empty vec
-        let raw_ptr = if ptr_vec.is_empty() {
-            self.write_data(Vec::new())?
-        } else {
-            self.collect_ptrs(ptr_vec)?
-        };
-
-        info!(
-            "GET_STATE Execution time: {} secs {} ms",
-            timer.elapsed().as_secs(),
-            timer.elapsed().subsec_millis()
-        );
-
-        Ok(Some(RuntimeValue::I32(raw_ptr as i32)))
-    }
-
-    fn set_state(
-        &mut self,
-        args: RuntimeArgs,
-        timer: Instant,
-    ) -> Result<Option<RuntimeValue>, Trap> {
-        let head_ptr: u32 = args.nth(0);
-        let addr_state = match self.ptr_collections.get(&head_ptr) {
-            Some(addresses) => addresses.clone(),
-            None => return Ok(Some(RuntimeValue::I32(-1))),
-        };
-
-        // if the length is not even return deserialization error
-        if (addr_state.len() % 2) != 0 {
-            return Ok(Some(RuntimeValue::I32(-1)));
-        }
-
-        let mut entries = Vec::new();
-        for entry in addr_state.chunks(2) {
-            let address = self.ptr_to_string(entry[0]).map_err(ExternalsError::from)?;
-            let data = self.ptr_to_vec(entry[1])?;
-            entries.push((address, data));
-        }
-
-        info!("Attempting to set state, entries: {:?}", entries);
-
-        match self.context.set_state_entries(entries) {
-            Ok(()) => {
-                info!(
-                    "SET_STATE Execution time: {} secs {} ms",
-                    timer.elapsed().as_secs(),
-                    timer.elapsed().subsec_millis()
-                );
-                Ok(Some(RuntimeValue::I32(1)))
-            }
-            Err(err) => {
-                error!("Set Error: {}", err);
-                info!(
-                    "SET_STATE Execution time: {} secs {} ms",
-                    timer.elapsed().as_secs(),
-                    timer.elapsed().subsec_millis()
-                );
-                Ok(Some(RuntimeValue::I32(0)))
-            }
-        }
-    }
-
-    fn delete_state(&mut self, args: RuntimeArgs) -> Result<Option<RuntimeValue>, Trap> {
-        let head_ptr: u32 = args.nth(0);
-
-        let addresses = match self.ptr_collections.get(&head_ptr) {
-            Some(addresses) => addresses.clone(),
-            None => return Ok(Some(RuntimeValue::I32(-1))),
-        };
-        let mut addr_vec = Vec::new();
-        for addr in addresses {
-            let address = self.ptr_to_string(addr).map_err(ExternalsError::from)?;
-            addr_vec.push(address);
-        }
-        info!("Attempting to delete state, addresses: {:?}", addr_vec);
-        let result = self
-            .context
-            .delete_state_entries(&addr_vec)
-            .map_err(ExternalsError::from)?;
-
-        let mut ptr_vec = Vec::new();
-        for addr in result {
-            let raw_ptr = self.write_data(addr.as_bytes().to_vec())?;
-            ptr_vec.push(raw_ptr);
-        }
-
-        // collect ptrs or return empty vec
-        let raw_ptr = if ptr_vec.is_empty() {
-            self.write_data(Vec::new())?
-        } else {
-            self.collect_ptrs(ptr_vec)?
-        };
-
-        Ok(Some(RuntimeValue::I32(raw_ptr as i32)))
-    }
-
-    fn add_event(
-        &mut self,
-        event_type_ptr: u32,
-        attribute_list_ptr: u32,
-        data_ptr: u32,
-    ) -> Result<Option<RuntimeValue>, Trap> {
-        let attribute_list = match self.ptr_collections.get(&attribute_list_ptr) {
-            Some(attributes) => attributes.clone(),
-            None => return Ok(Some(RuntimeValue::I32(-1))),
-        };
-
-        // if the length is even return deserialization error
-        // the list should have a starting element followed by key, value pair
-        if (attribute_list.len() % 2) == 0 {
-            return Ok(Some(RuntimeValue::I32(-1)));
-        }
-
-        let mut attributes = Vec::new();
-        if attribute_list.len() > 1 {
-            for entry in attribute_list[1..].chunks(2) {
-                let key = self.ptr_to_string(entry[0]).map_err(ExternalsError::from)?;
-                let value = self.ptr_to_string(entry[1]).map_err(ExternalsError::from)?;
-                attributes.push((key, value));
-            }
-        }
-
-        let event_type = self.ptr_to_string(event_type_ptr)?;
-
-        let data = self.ptr_to_vec(data_ptr)?;
-
-        info!(
-            "Attempting to add event, event_type: {:?}, attributes: {:?}, data: {:?}",
-            event_type, attributes, data
-        );
-
-        match self.context.add_event(event_type, attributes, &data) {
-            Ok(()) => Ok(Some(RuntimeValue::I32(0))),
-            Err(err) => {
-                error!("Add event Error: {}", err);
-                Ok(Some(RuntimeValue::I32(1)))
-            }
-        }
+    /// Return the number of instructions the settled instances have run
+    pub fn instructions_run(&self) -> u64 {
+        self.meter.instructions_run()
     }
 }
 
@@ -546,223 +438,76 @@ impl<'a> Externals for WasmExternals<'a> {
         index: usize,
         args: RuntimeArgs,
     ) -> Result<Option<RuntimeValue>, Trap> {
-        let timer = Instant::now();
-        match index {
-            GET_STATE_IDX => self.get_state(args, timer),
-            SET_STATE_IDX => self.set_state(args, timer),
-            DELETE_STATE_IDX => self.delete_state(args),
-            ADD_EVENT_IDX => {
-                let event_type = args.nth(0);
-                let attributes = args.nth(1);
-                let data = args.nth(2);
-                self.add_event(event_type, attributes, data)
-            }
-            GET_PTR_LEN_IDX => {
-                let addr = args.nth(0);
-
-                if let Some(ptr) = self.ptrs.get(&addr) {
-                    Ok(Some(RuntimeValue::I32(ptr.length as i32)))
-                } else {
-                    Ok(Some(RuntimeValue::I32(-1)))
-                }
-            }
-            GET_PTR_CAP_IDX => {
-                let addr = args.nth(0);
-
-                if let Some(ptr) = self.ptrs.get(&addr) {
-                    Ok(Some(RuntimeValue::I32(ptr.capacity as i32)))
-                } else {
-                    Ok(Some(RuntimeValue::I32(-1)))
-                }
-            }
-            ALLOC_IDX => {
-                let len: i32 = args.nth(0);
-
-                let raw_ptr = self.write_data(vec![0; len as usize])?;
-                info!(
-                    "ALLOC Execution time: {} secs {} ms",
-                    timer.elapsed().as_secs(),
-                    timer.elapsed().subsec_millis()
-                );
-
-                Ok(Some(RuntimeValue::I32(raw_ptr as i32)))
-            }
-            READ_BYTE_IDX => {
-                let offset: i32 = args.nth(0);
-                let byte = self
-                    .get_memory_ref()
-                    .get(offset as u32, 1)
-                    .map_err(ExternalsError::from)?[0];
-                Ok(Some(RuntimeValue::I32(i32::from(byte))))
-            }
-            WRITE_BYTE_IDX => {
-                let ptr: u32 = args.nth(0);
-                let offset: u32 = args.nth(1);
-                let data: i32 = args.nth(2);
-
-                if let Some(p) = self.ptrs.get(&ptr) {
-                    self.get_memory_ref()
-                        .set(p.raw + offset, vec![data as u8].as_slice())
-                        .map_err(ExternalsError::from)?;
-
-                    Ok(Some(RuntimeValue::I32(1)))
-                } else {
-                    Ok(Some(RuntimeValue::I32(-1)))
-                }
-            }
-            GET_COLLECTION_LEN_IDX => {
-                let head_ptr: u32 = args.nth(0);
-
-                info!("Retrieving collection length. Head pointer {}", head_ptr);
-
-                if let Some(v) = self.ptr_collections.get(&head_ptr) {
-                    info!("Collection found elements in collection: {}", v.len());
-                    Ok(Some(RuntimeValue::I32(v.len() as i32)))
-                } else {
-                    Ok(Some(RuntimeValue::I32(-1)))
-                }
+        let memory = &mut self.memory_ref;
+        let host = &mut self.host;
+        let result = match index {
+            GET_STATE_IDX => host.get_state(memory, args.nth(0))?,
+            SET_STATE_IDX => host.set_state(memory, args.nth(0))?,
+            DELETE_STATE_IDX => host.delete_state(memory, args.nth(0))?,
+            ADD_EVENT_IDX => host.add_event(memory, args.nth(0), args.nth(1), args.nth(2))?,
+            ADD_RECEIPT_DATA_IDX => host.add_receipt_data(memory, args.nth(0))?,
+            GET_PTR_LEN_IDX => host.get_ptr_len(args.nth(0)),
+            GET_PTR_CAP_IDX => host.get_ptr_capacity(args.nth(0)),
+            ALLOC_IDX => host.alloc(memory, args.nth(0))?,
+            READ_BYTE_IDX => host.read_byte(memory, args.nth(0))?,
+            WRITE_BYTE_IDX => host.write_byte(memory, args.nth(0), args.nth(1), args.nth(2))?,
+            GET_COLLECTION_LEN_IDX => host.get_collection_len(args.nth(0)),
+            GET_PTR_FROM_COLLECTION_IDX => host.get_ptr_from_collection(args.nth(0), args.nth(1)),
+            CREATE_COLLECTION => host.create_collection(args.nth(0))? as i32,
+            ADD_TO_COLLECTION => host.add_to_collection(args.nth(0), args.nth(1))? as i32,
+            SMART_PERMISSION => host.smart_permission(
+                memory,
+                &mut *self.meter,
+                args.nth(0),
+                args.nth(1),
+                args.nth(2),
+                args.nth(3),
+                args.nth(4),
+                args.nth(5),
+            )?,
+            INVOKE_CONTRACT_IDX => host.invoke_contract(
+                memory,
+                &mut *self.meter,
+                args.nth(0),
+                args.nth(1),
+                args.nth(2),
+            )?,
+            SET_ERROR_IDX => host.set_error(memory, args.nth(0), args.nth(1))?,
+            LOG => {
+                host.log(memory, args.nth(0), args.nth(1))?;
+                return Ok(None);
             }
-            GET_PTR_FROM_COLLECTION_IDX => {
-                let head_ptr: u32 = args.nth(0);
-                let index: u32 = args.nth(1);
-
-                info!("Retrieving pointer head_ptr: {} index: {}", head_ptr, index);
-
-                if let Some(v) = self.ptr_collections.get(&head_ptr) {
-                    if index as usize >= v.len() {
-                        info!("Invalid index");
-                        Ok(Some(RuntimeValue::I32(-1)))
-                    } else {
-                        info!("Pointer retrieved: {}", v[index as usize]);
-                        Ok(Some(RuntimeValue::I32(v[index as usize] as i32)))
-                    }
-                } else {
-                    Ok(Some(RuntimeValue::I32(-1)))
-                }
+            LOG_LEVEL => host.log_level(),
+            METER_IDX => {
+                let credit = self.meter.refill(args.nth(0))?;
+                return Ok(Some(RuntimeValue::I64(credit)));
             }
-            CREATE_COLLECTION => {
-                let head_ptr: u32 = args.nth(0);
-                self.create_collection(head_ptr)?;
-                Ok(Some(RuntimeValue::I32(head_ptr as i32)))
+            index if wasi::is_wasi_function(index) => {
+                let memory = self.contract_memory.as_mut().ok_or_else(wasi::no_memory)?;
+                return Ok(wasi::invoke(index, memory, args)?);
             }
-            ADD_TO_COLLECTION => {
-                let head_ptr: u32 = args.nth(0);
-                let raw_ptr: u32 = args.nth(1);
+            _ => return Err(ExternalsError::trap("Function does not exist".into())),
+        };
 
-                self.add_to_collection(head_ptr, raw_ptr)?;
-                Ok(Some(RuntimeValue::I32(head_ptr as i32)))
-            }
-            SMART_PERMISSION => self.smart_permission(args),
-            LOG => {
-                let log_level: u32 = args.nth(0);
-                let log_ptr: u32 = args.nth(1);
-                let log_string = self.ptr_to_string(log_ptr)?;
-                match log_level {
-                    0 => error!("{}", log_string),
-                    1 => warn!("{}", log_string),
-                    2 => info!("{}", log_string),
-                    3 => debug!("{}", log_string),
-                    4 => trace!("{}", log_string),
-                    _ => warn!("Unknown log level requested: {}", log_level),
-                }
-                Ok(None)
-            }
-            LOG_LEVEL => match max_level() {
-                LevelFilter::Trace => Ok(Some(RuntimeValue::I32(4))),
-                LevelFilter::Debug => Ok(Some(RuntimeValue::I32(3))),
-                LevelFilter::Info => Ok(Some(RuntimeValue::I32(2))),
-                LevelFilter::Warn => Ok(Some(RuntimeValue::I32(1))),
-                _ => Ok(Some(RuntimeValue::I32(0))),
-            },
-            _ => Err(ExternalsError::trap("Function does not exist".into())),
-        }
+        Ok(Some(RuntimeValue::I32(result)))
     }
 }
 
 impl<'a> ModuleImportResolver for WasmExternals<'a> {
     fn resolve_func(&self, field_name: &str, _signature: &Signature) -> Result<FuncRef, Error> {
-        match field_name {
-            "get_state" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], Some(ValueType::I32)),
-                GET_STATE_IDX,
-            )),
-            "set_state" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], Some(ValueType::I32)),
-                SET_STATE_IDX,
-            )),
-            "delete_state" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], Some(ValueType::I32)),
-                DELETE_STATE_IDX,
-            )),
-            "add_event" => Ok(FuncInstance::alloc_host(
-                Signature::new(
-                    &[ValueType::I32, ValueType::I32, ValueType::I32][..],
-                    Some(ValueType::I32),
-                ),
-                ADD_EVENT_IDX,
-            )),
-            "invoke_smart_permission" => Ok(FuncInstance::alloc_host(
-                Signature::new(
-                    &[
-                        ValueType::I32,
-                        ValueType::I32,
-                        ValueType::I32,
-                        ValueType::I32,
-                        ValueType::I32,
-                        ValueType::I32,
-                    ][..],
-                    Some(ValueType::I32),
-                ),
-                SMART_PERMISSION,
-            )),
-            "get_ptr_len" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], Some(ValueType::I32)),
-                GET_PTR_LEN_IDX,
-            )),
-            "get_ptr_capacity" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], Some(ValueType::I32)),
-                GET_PTR_CAP_IDX,
-            )),
-            "alloc" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], Some(ValueType::I32)),
-                ALLOC_IDX,
-            )),
-            "read_byte" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], Some(ValueType::I32)),
-                READ_BYTE_IDX,
-            )),
-            "write_byte" => Ok(FuncInstance::alloc_host(
-                Signature::new(
-                    &[ValueType::I32, ValueType::I32, ValueType::I32][..],
-                    Some(ValueType::I32),
-                ),
-                WRITE_BYTE_IDX,
-            )),
-            "get_ptr_collection_len" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], Some(ValueType::I32)),
-                GET_COLLECTION_LEN_IDX,
-            )),
-            "get_ptr_from_collection" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32, ValueType::I32][..], Some(ValueType::I32)),
-                GET_PTR_FROM_COLLECTION_IDX,
-            )),
-            "create_collection" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32][..], Some(ValueType::I32)),
-                CREATE_COLLECTION,
-            )),
-            "add_to_collection" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32, ValueType::I32][..], Some(ValueType::I32)),
-                ADD_TO_COLLECTION,
-            )),
-            "log_buffer" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[ValueType::I32, ValueType::I32][..], None),
-                LOG,
-            )),
-            "log_level" => Ok(FuncInstance::alloc_host(
-                Signature::new(&[][..], Some(ValueType::I32)),
-                LOG_LEVEL,
+        if field_name == metering::METER_FUNCTION {
+            return Ok(FuncInstance::alloc_host(
+                Signature::new(&[ValueType::I64][..], Some(ValueType::I64)),
+                METER_IDX,
+            ));
+        }
+
+        match HOST_FUNCTIONS.iter().find(|(name, ..)| *name == field_name) {
+            Some((_, index, params, result)) => Ok(FuncInstance::alloc_host(
+                Signature::new(*params, *result),
+                *index,
             )),
-            _ => Err(Error::Instantiation(format!(
+            None => Err(Error::Instantiation(format!(
                 "Export {} not found",
                 field_name
             ))),
@@ -784,23 +529,6 @@ impl<'a> ModuleImportResolver for WasmExternals<'a> {
     }
 }
 
-#[derive(Clone)]
-struct Pointer {
-    raw: u32,
-    length: usize,
-    capacity: usize,
-}
-
-impl fmt::Debug for Pointer {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "Pointer {{ raw: {}, length: {}, capacity {} }}",
-            self.raw, self.length, self.capacity
-        )
-    }
-}
-
 #[derive(Debug)]
 pub struct ExternalsError {
     message: String,
@@ -810,6 +538,10 @@ impl ExternalsError {
     fn trap(msg: String) -> Trap {
         Trap::from(TrapKind::Host(Box::new(ExternalsError::from(msg))))
     }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 
 impl fmt::Display for ExternalsError {
@@ -830,8 +562,18 @@ impl<'a> From<&'a str> for ExternalsError {
 
 impl From<Error> for ExternalsError {
     fn from(e: Error) -> Self {
-        ExternalsError {
-            message: format!("{:?}", e),
+        // A trap raised by a host function keeps the host function's error, so that errors of
+        // nested executions are not wrapped in one trap for each level
+        match e
+            .as_host_error()
+            .and_then(|err| err.downcast_ref::<ExternalsError>())
+        {
+            Some(err) => ExternalsError {
+                message: err.message.clone(),
+            },
+            None => ExternalsError {
+                message: format!("{:?}", e),
+            },
         }
     }
 }
@@ -858,18 +600,34 @@ impl From<ContextError> for ExternalsError {
     }
 }
 
-struct SmartPermissionModule<'a> {
+pub struct SmartPermissionModule<'a> {
     context: &'a mut dyn TransactionContext,
-    module: Module,
+    module: &'a Module,
+    limits: ExecutionLimits,
+    meter: &'a mut metering::InstructionMeter,
+    contract_cache: &'a ContractCache,
+    smart_permission_cache: &'a SmartPermissionCache,
 }
 
 impl<'a> SmartPermissionModule<'a> {
+    /// Create a `SmartPermissionModule` to execute a smart permission which was already loaded
+    /// by `wasm_module::load`, charging the instructions it runs to `meter`
     pub fn new(
-        wasm: &[u8],
+        module: &'a Module,
         context: &'a mut dyn TransactionContext,
-    ) -> Result<SmartPermissionModule<'a>, ExternalsError> {
-        let module = Module::from_buffer(wasm)?;
-        Ok(SmartPermissionModule { context, module })
+        limits: ExecutionLimits,
+        meter: &'a mut metering::InstructionMeter,
+        contract_cache: &'a ContractCache,
+        smart_permission_cache: &'a SmartPermissionCache,
+    ) -> SmartPermissionModule<'a> {
+        SmartPermissionModule {
+            context,
+            module,
+            limits,
+            meter,
+            contract_cache,
+            smart_permission_cache,
+        }
     }
 
     pub fn entrypoint(
@@ -879,13 +637,17 @@ impl<'a> SmartPermissionModule<'a> {
         public_key: String,
         payload: Vec<u8>,
     ) -> Result<Option<i32>, ExternalsError> {
-        let mut env = WasmExternals::new(None, self.context)?;
+        let mut env = WasmExternals::new(
+            None,
+            self.context,
+            None,
+            self.limits,
+            self.meter,
+            self.contract_cache,
+            self.smart_permission_cache,
+        )?;
 
-        let instance = ModuleInstance::new(
-            &self.module,
-            &ImportsBuilder::new().with_resolver("env", &env),
-        )?
-        .assert_no_start();
+        let instance = env.instantiate(self.module)?;
 
         info!("Writing roles to memory");
 
@@ -920,7 +682,7 @@ impl<'a> SmartPermissionModule<'a> {
         let payload_ptr = env.write_data(payload)? as i32;
         info!("Payload written to memory");
 
-        let result = instance.invoke_export(
+        let result = instance.invoke_export_with_stack(
             "entrypoint",
             &[
                 RuntimeValue::I32(role_list_ptr),
@@ -929,7 +691,20 @@ impl<'a> SmartPermissionModule<'a> {
                 RuntimeValue::I32(payload_ptr),
             ],
             &mut env,
-        )?;
+            &mut stack_limit::stack_recycler(self.limits),
+        );
+        env.settle(&instance);
+        let result = match result {
+            Ok(result) => result,
+            Err(_) if env.is_exhausted() => {
+                return Err(metering::budget_exceeded(self.limits.max_instructions))
+            }
+            Err(_) if self.limits.timed_out() => return Err(metering::timed_out()),
+            Err(err) if stack_limit::is_stack_overflow(&err) => {
+                return Err(stack_limit::stack_exhausted(self.limits))
+            }
+            Err(err) => return Err(err.into()),
+        };
 
         if let Some(RuntimeValue::I32(i)) = result {
             Ok(Some(i))
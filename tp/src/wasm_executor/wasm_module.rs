@@ -13,48 +13,128 @@
 // limitations under the License.
 
 use sawtooth_sdk::processor::handler::TransactionContext;
-use wasmi::{ImportsBuilder, Module, ModuleInstance, RuntimeValue};
+use wasmi::{Module, RuntimeValue};
 
+use crate::wasm_executor::float_policy::{self, FloatPolicy};
+use crate::wasm_executor::memory_limit;
+use crate::wasm_executor::metering;
+use crate::wasm_executor::module_cache::{ContractCache, SmartPermissionCache};
+use crate::wasm_executor::proposals::{self, WasmProtocolVersion};
+use crate::wasm_executor::stack_limit;
+use crate::wasm_executor::validation;
 use crate::wasm_executor::wasm_externals::{ExternalsError, WasmExternals};
+use crate::wasm_executor::{ContractResult, ExecutionLimits, Transaction};
+
+/// Parse and validate a contract for wasmi, rewriting the proposals the wasm protocol version
+/// accepts, checking its imports, limiting its memory to `max_memory_pages` pages, applying the
+/// float policy and adding instruction metering to it
+pub fn load(
+    wasm: &[u8],
+    max_memory_pages: u32,
+    float_policy: FloatPolicy,
+    wasm_protocol_version: WasmProtocolVersion,
+) -> Result<Module, ExternalsError> {
+    Ok(Module::from_parity_wasm_module(instrument(
+        wasm,
+        max_memory_pages,
+        float_policy,
+        wasm_protocol_version,
+    )?)?)
+}
+
+/// Prepare a contract as `load` does, returning the instrumented contract, which wasmi then only
+/// has to parse and validate
+pub fn instrument(
+    wasm: &[u8],
+    max_memory_pages: u32,
+    float_policy: FloatPolicy,
+    wasm_protocol_version: WasmProtocolVersion,
+) -> Result<parity_wasm::elements::Module, ExternalsError> {
+    let mut module = proposals::parse_contract(wasm, wasm_protocol_version)?;
+    // The contract is validated before it is instrumented, since it may not refer to the global
+    // and function metering adds, and contracts stored by older versions of Sabre, and smart
+    // permissions, were not validated when they were stored
+    wasmi::Module::from_parity_wasm_module(module.clone())
+        .map_err(|err| ExternalsError::from(format!("Unable to validate contract: {}", err)))?;
+    validation::check_imports(&module)?;
+    memory_limit::limit_memory(&mut module, max_memory_pages)?;
+    float_policy::apply_float_policy(&mut module, float_policy)?;
+    metering::add_metering(&mut module)?;
+
+    Ok(module)
+}
 
 pub struct WasmModule<'a> {
     context: &'a mut dyn TransactionContext,
-    module: Module,
+    module: &'a Module,
+    transaction: &'a Transaction,
+    limits: ExecutionLimits,
+    contract_cache: &'a ContractCache,
+    smart_permission_cache: &'a SmartPermissionCache,
+    /// The number of instructions the last execution of the module ran
+    instructions: u64,
 }
 
 impl<'a> WasmModule<'a> {
+    /// Create a `WasmModule` to execute a module which was already loaded by `load`
     pub fn new(
-        wasm: &[u8],
+        module: &'a Module,
         context: &'a mut dyn TransactionContext,
-    ) -> Result<WasmModule<'a>, ExternalsError> {
-        let module = Module::from_buffer(wasm)?;
-        Ok(WasmModule { context, module })
+        transaction: &'a Transaction,
+        limits: ExecutionLimits,
+        contract_cache: &'a ContractCache,
+        smart_permission_cache: &'a SmartPermissionCache,
+    ) -> WasmModule<'a> {
+        WasmModule {
+            context,
+            module,
+            transaction,
+            limits,
+            contract_cache,
+            smart_permission_cache,
+            instructions: 0,
+        }
+    }
+
+    /// Return the number of instructions the last execution of the module ran, counting those
+    /// of the contracts and smart permissions it invoked
+    pub fn instructions(&self) -> u64 {
+        self.instructions
     }
 
-    pub fn entrypoint(
+    pub fn entrypoint(&mut self, payload: Vec<u8>) -> Result<ContractResult, ExternalsError> {
+        let mut meter = metering::InstructionMeter::new(self.limits);
+        self.entrypoint_metered(payload, &mut meter)
+    }
+
+    /// Execute the module, charging the instructions it runs to `meter`
+    pub(crate) fn entrypoint_metered(
         &mut self,
         payload: Vec<u8>,
-        signer: String,
-        signature: String,
-    ) -> Result<Option<i32>, ExternalsError> {
-        let mut env = WasmExternals::new(None, self.context)?;
+        meter: &mut metering::InstructionMeter,
+    ) -> Result<ContractResult, ExternalsError> {
+        let mut env = WasmExternals::new(
+            None,
+            self.context,
+            Some(self.transaction),
+            self.limits,
+            meter,
+            self.contract_cache,
+            self.smart_permission_cache,
+        )?;
 
-        let instance = ModuleInstance::new(
-            &self.module,
-            &ImportsBuilder::new().with_resolver("env", &env),
-        )?
-        .assert_no_start();
+        let instance = env.instantiate(self.module)?;
 
         let payload_ptr = env.write_data(payload)? as i32;
         info!("Payload written to memory");
 
-        let signer_ptr = env.write_data(signer.into_bytes())? as i32;
+        let signer_ptr = env.write_data(self.transaction.signer.clone().into_bytes())? as i32;
         info!("Signer written to memory");
 
-        let signature_ptr = env.write_data(signature.into_bytes())? as i32;
+        let signature_ptr = env.write_data(self.transaction.signature.clone().into_bytes())? as i32;
         info!("Signature written to memory");
 
-        let result = instance.invoke_export(
+        let result = instance.invoke_export_with_stack(
             "entrypoint",
             &[
                 RuntimeValue::I32(payload_ptr),
@@ -62,12 +142,131 @@ impl<'a> WasmModule<'a> {
                 RuntimeValue::I32(signature_ptr),
             ],
             &mut env,
-        )?;
+            &mut stack_limit::stack_recycler(self.limits),
+        );
+        env.settle(&instance);
+        self.instructions = env.instructions_run();
+        let result = match result {
+            Ok(result) => result,
+            Err(_) if env.is_exhausted() => {
+                return Err(metering::budget_exceeded(self.limits.max_instructions))
+            }
+            Err(_) if self.limits.timed_out() => return Err(metering::timed_out()),
+            Err(err) if stack_limit::is_stack_overflow(&err) => {
+                return Err(stack_limit::stack_exhausted(self.limits))
+            }
+            Err(err) => return Err(err.into()),
+        };
 
-        if let Some(RuntimeValue::I32(i)) = result {
-            Ok(Some(i))
+        let result = if let Some(RuntimeValue::I32(i)) = result {
+            Some(i)
         } else {
-            Ok(None)
+            None
+        };
+
+        Ok(ContractResult {
+            result,
+            error: env.take_error(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parity_wasm::elements::Instruction;
+
+    use crate::test_support::contract;
+
+    /// A contract which fills memory with `memory.fill`, then calls the function Sabre adds to
+    /// replace it, whose index follows the contract's only function:
+    ///
+    /// ```text
+    /// (module
+    ///   (memory 1)
+    ///   (func (param i32 i32 i32) (result i32)
+    ///     i32.const 0 i32.const 0 i32.const 0 memory.fill
+    ///     i32.const 0 i32.const 0 i32.const 0 call 1
+    ///     i32.const 1)
+    ///   (export "entrypoint" (func 0)))
+    /// ```
+    const CALLS_MEMORY_FILL_FUNCTION: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x01, 0x60, 0x03, 0x7f, 0x7f,
+        0x7f, 0x01, 0x7f, 0x03, 0x02, 0x01, 0x00, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x0e, 0x01,
+        0x0a, 0x65, 0x6e, 0x74, 0x72, 0x79, 0x70, 0x6f, 0x69, 0x6e, 0x74, 0x00, 0x00, 0x0a, 0x17,
+        0x01, 0x15, 0x00, 0x41, 0x00, 0x41, 0x00, 0x41, 0x00, 0xfc, 0x0b, 0x00, 0x41, 0x00, 0x41,
+        0x00, 0x41, 0x00, 0x10, 0x01, 0x41, 0x01, 0x0b,
+    ];
+
+    /// The same contract without the call
+    const MEMORY_FILL: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x01, 0x60, 0x03, 0x7f, 0x7f,
+        0x7f, 0x01, 0x7f, 0x03, 0x02, 0x01, 0x00, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x0e, 0x01,
+        0x0a, 0x65, 0x6e, 0x74, 0x72, 0x79, 0x70, 0x6f, 0x69, 0x6e, 0x74, 0x00, 0x00, 0x0a, 0x0f,
+        0x01, 0x0d, 0x00, 0x41, 0x00, 0x41, 0x00, 0x41, 0x00, 0xfc, 0x0b, 0x00, 0x41, 0x01, 0x0b,
+    ];
+
+    /// A contract which refers to its function with `ref.func`, whose index metering would have
+    /// to shift:
+    ///
+    /// ```text
+    /// (module
+    ///   (func (param i32 i32 i32) (result i32)
+    ///     ref.func 0 drop
+    ///     i32.const 1)
+    ///   (export "entrypoint" (func 0)))
+    /// ```
+    const REF_FUNC: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x01, 0x60, 0x03, 0x7f, 0x7f,
+        0x7f, 0x01, 0x7f, 0x03, 0x02, 0x01, 0x00, 0x07, 0x0e, 0x01, 0x0a, 0x65, 0x6e, 0x74, 0x72,
+        0x79, 0x70, 0x6f, 0x69, 0x6e, 0x74, 0x00, 0x00, 0x0a, 0x09, 0x01, 0x07, 0x00, 0xd2, 0x00,
+        0x1a, 0x41, 0x01, 0x0b,
+    ];
+
+    fn load_error(wasm: &[u8], wasm_protocol_version: WasmProtocolVersion) -> String {
+        match load(wasm, 1, FloatPolicy::Allow, wasm_protocol_version) {
+            Ok(_) => panic!("Invalid contract was loaded"),
+            Err(err) => err.message().to_string(),
         }
     }
+
+    #[test]
+    fn contract_cannot_set_its_credit() {
+        // The global metering adds is the contract's first
+        let wasm = contract(vec![
+            Instruction::I64Const(i64::MAX),
+            Instruction::SetGlobal(0),
+            Instruction::I32Const(1),
+            Instruction::End,
+        ]);
+        for version in &[WasmProtocolVersion::V1, WasmProtocolVersion::V2] {
+            let message = load_error(&wasm, *version);
+            assert!(
+                message.contains("Unable to validate contract"),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn contract_cannot_call_bulk_memory_functions() {
+        assert!(load(MEMORY_FILL, 1, FloatPolicy::Allow, WasmProtocolVersion::V2).is_ok());
+
+        let message = load_error(CALLS_MEMORY_FILL_FUNCTION, WasmProtocolVersion::V2);
+        assert!(
+            message.contains("call of a function the contract does not define"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn contract_cannot_refer_to_functions_with_ref_func() {
+        load_error(REF_FUNC, WasmProtocolVersion::V1);
+
+        let message = load_error(REF_FUNC, WasmProtocolVersion::V2);
+        assert!(message.contains("ref.func"), "{}", message);
+    }
 }
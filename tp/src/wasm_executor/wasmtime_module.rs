@@ -0,0 +1,677 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contains a runtime which compiles contracts to native code with wasmtime
+//!
+//! Contracts are compiled by cranelift when they are loaded, before they are executed, once their
+//! imports are checked, and the compiled modules are kept in the transaction processor's contract
+//! cache. Executions are metered with fuel: an execution is given one unit of fuel for each
+//! instruction it may run, and a contract which runs out traps, so that a contract which does not
+//! terminate cannot stall the transaction processor. The memories of the contract are limited by
+//! the store they are created in. Contracts are rewritten for the float policy with parity-wasm
+//! before they are compiled. An execution with a deadline is watched by a thread which interrupts
+//! it once the deadline passes.
+
+use std::fmt;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use sawtooth_sdk::processor::handler::TransactionContext;
+use wasmtime::{
+    Caller, Config, Engine, Extern, Linker, Memory, MemoryType, Module, Store, StoreLimits,
+    StoreLimitsBuilder, Trap, TrapCode,
+};
+
+use crate::wasm_executor::float_policy::{self, FloatPolicy};
+use crate::wasm_executor::host::{ContractMemory, HostState};
+use crate::wasm_executor::memory_limit::{self, PAGE_SIZE};
+use crate::wasm_executor::metering;
+use crate::wasm_executor::module_cache::{ContractCache, SmartPermissionCache};
+use crate::wasm_executor::proposals::{self, WasmProtocolVersion};
+use crate::wasm_executor::validation;
+use crate::wasm_executor::wasi;
+use crate::wasm_executor::wasm_externals::ExternalsError;
+use crate::wasm_executor::{ContractResult, Execution, ExecutionLimits, Transaction};
+
+/// Compiles and executes contracts with fuel metering enabled
+#[derive(Clone)]
+pub struct WasmtimeEngine {
+    engine: Engine,
+}
+
+/// The data of the store a contract is executed in
+struct ContractState<'a> {
+    host: HostState<'a>,
+    memory: Option<Memory>,
+    limits: StoreLimits,
+}
+
+impl WasmtimeEngine {
+    /// Create an engine whose contracts may use `max_stack_size` bytes of native stack
+    pub fn new(max_stack_size: usize) -> Result<WasmtimeEngine, ExternalsError> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        config.max_wasm_stack(max_stack_size);
+        // Contracts are rewritten into WebAssembly 1.0 before they are compiled, as they are for
+        // wasmi, so that both runtimes accept the same contracts
+        config.wasm_bulk_memory(false);
+        config.wasm_reference_types(false);
+        let engine = Engine::new(&config).map_err(|err| {
+            ExternalsError::from(format!("Unable to create wasmtime engine: {}", err))
+        })?;
+
+        Ok(WasmtimeEngine { engine })
+    }
+
+    /// Compile a contract to native code, rewriting the proposals the wasm protocol version
+    /// accepts and applying the float policy to it first
+    pub fn compile(
+        &self,
+        wasm: &[u8],
+        float_policy: FloatPolicy,
+        wasm_protocol_version: WasmProtocolVersion,
+    ) -> Result<Module, ExternalsError> {
+        let mut module = proposals::parse_contract(wasm, wasm_protocol_version)?;
+        validation::check_imports(&module)?;
+        let wasm = match (float_policy, wasm_protocol_version) {
+            (FloatPolicy::Allow, WasmProtocolVersion::V1) => wasm.to_vec(),
+            _ => {
+                float_policy::apply_float_policy(&mut module, float_policy)?;
+                parity_wasm::serialize(module).map_err(|err| {
+                    ExternalsError::from(format!("Unable to serialize contract: {}", err))
+                })?
+            }
+        };
+        Module::new(&self.engine, wasm)
+            .map_err(|err| ExternalsError::from(format!("Unable to compile contract: {}", err)))
+    }
+
+    /// Serialize a compiled contract, so that `deserialize` loads it without compiling it again
+    pub fn serialize(&self, module: &Module) -> Result<Vec<u8>, ExternalsError> {
+        module
+            .serialize()
+            .map_err(|err| ExternalsError::from(format!("Unable to serialize module: {}", err)))
+    }
+
+    /// Load a contract serialized by `serialize`, which wasmtime checks was compiled by a
+    /// compatible engine
+    ///
+    /// The native code is not validated, so the bytes must have been returned by `serialize`
+    /// and have not changed since.
+    pub fn deserialize(&self, serialized: &[u8]) -> Result<Module, ExternalsError> {
+        // Safety: the caller guarantees the bytes were serialized by wasmtime
+        unsafe { Module::deserialize(&self.engine, serialized) }
+            .map_err(|err| ExternalsError::from(format!("Unable to deserialize module: {}", err)))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn entrypoint(
+        &self,
+        module: &Module,
+        context: &mut dyn TransactionContext,
+        payload: Vec<u8>,
+        transaction: &Transaction,
+        limits: ExecutionLimits,
+        contract_cache: &ContractCache,
+        smart_permission_cache: &SmartPermissionCache,
+    ) -> Execution {
+        let mut store = Store::new(
+            &self.engine,
+            ContractState {
+                host: HostState::new(
+                    context,
+                    Some(transaction),
+                    limits,
+                    contract_cache,
+                    smart_permission_cache,
+                ),
+                memory: None,
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(limits.max_memory_pages as usize * PAGE_SIZE)
+                    .build(),
+            },
+        );
+        let result = self.execute(&mut store, module, payload, transaction, limits);
+        Execution {
+            result,
+            instructions: store.fuel_consumed().unwrap_or(0),
+        }
+    }
+
+    /// Execute the entrypoint of a contract in a store whose fuel has not been set yet
+    fn execute(
+        &self,
+        store: &mut Store<ContractState<'_>>,
+        module: &Module,
+        payload: Vec<u8>,
+        transaction: &Transaction,
+        limits: ExecutionLimits,
+    ) -> Result<ContractResult, ExternalsError> {
+        store.limiter(|state| &mut state.limits);
+        store.add_fuel(limits.max_instructions).map_err(error)?;
+        // The epoch is only advanced by the watchdog of an execution which times out, and an
+        // execution is interrupted as soon as the epoch advances
+        store.set_epoch_deadline(1);
+
+        let memory_type = MemoryType::new(
+            memory_limit::host_memory_pages(limits.max_memory_pages),
+            Some(limits.max_memory_pages),
+        );
+        let memory = Memory::new(&mut *store, memory_type).map_err(error)?;
+        store.data_mut().memory = Some(memory);
+
+        let mut linker = Linker::new(&self.engine);
+        linker.define("env", "memory", memory).map_err(error)?;
+        define_host_functions(&mut linker)?;
+        define_wasi_functions(&mut linker)?;
+        let instance = linker.instantiate(&mut *store, module).map_err(error)?;
+
+        let (payload_ptr, signer_ptr, signature_ptr) = {
+            let (data, state) = memory.data_and_store_mut(&mut *store);
+            let mut memory = SliceMemory(data);
+
+            let payload_ptr = state.host.write_data(&mut memory, payload)? as i32;
+            info!("Payload written to memory");
+
+            let signer_ptr = state
+                .host
+                .write_data(&mut memory, transaction.signer.clone().into_bytes())?
+                as i32;
+            info!("Signer written to memory");
+
+            let signature_ptr = state
+                .host
+                .write_data(&mut memory, transaction.signature.clone().into_bytes())?
+                as i32;
+            info!("Signature written to memory");
+
+            (payload_ptr, signer_ptr, signature_ptr)
+        };
+
+        let entrypoint = instance
+            .get_typed_func::<(i32, i32, i32), i32, _>(&mut *store, "entrypoint")
+            .map_err(error)?;
+
+        let watchdog = limits
+            .deadline
+            .map(|deadline| Watchdog::start(self.engine.clone(), deadline));
+        let result = entrypoint.call(&mut *store, (payload_ptr, signer_ptr, signature_ptr));
+        drop(watchdog);
+
+        match result {
+            Ok(result) => Ok(ContractResult {
+                result: Some(result),
+                error: store.data_mut().host.take_error(),
+            }),
+            Err(_) if store.fuel_consumed() >= Some(limits.max_instructions) => {
+                Err(metering::budget_exceeded(limits.max_instructions))
+            }
+            Err(_) if limits.timed_out() => Err(metering::timed_out()),
+            Err(trap) if trap.trap_code() == Some(TrapCode::StackOverflow) => {
+                Err(ExternalsError::from(format!(
+                    "Contract stack exhausted: the contract used more than {} bytes of stack",
+                    limits.max_stack_size
+                )))
+            }
+            Err(trap) => Err(error(trap)),
+        }
+    }
+}
+
+/// A thread which advances the epoch of an engine, interrupting the execution running in it, if
+/// it is not dropped before a deadline
+struct Watchdog {
+    cancel: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    fn start(engine: Engine, deadline: Instant) -> Watchdog {
+        let (cancel, cancelled) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if let Err(RecvTimeoutError::Timeout) = cancelled.recv_timeout(timeout) {
+                engine.increment_epoch();
+            }
+        });
+
+        Watchdog {
+            cancel: Some(cancel),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread, which is joined so that it cannot advance the
+        // epoch once the next execution has started
+        self.cancel.take();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Execution watchdog panicked");
+            }
+        }
+    }
+}
+
+/// Define the functions contracts import from the `env` module
+fn define_host_functions<'a>(linker: &mut Linker<ContractState<'a>>) -> Result<(), ExternalsError> {
+    linker
+        .func_wrap(
+            "env",
+            "get_state",
+            |mut caller: Caller<'_, ContractState<'a>>, head_ptr: i32| {
+                with_memory(&mut caller, |host, memory| {
+                    host.get_state(memory, head_ptr as u32)
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "set_state",
+            |mut caller: Caller<'_, ContractState<'a>>, head_ptr: i32| {
+                with_memory(&mut caller, |host, memory| {
+                    host.set_state(memory, head_ptr as u32)
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "delete_state",
+            |mut caller: Caller<'_, ContractState<'a>>, head_ptr: i32| {
+                with_memory(&mut caller, |host, memory| {
+                    host.delete_state(memory, head_ptr as u32)
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "add_event",
+            |mut caller: Caller<'_, ContractState<'a>>,
+             event_type_ptr: i32,
+             attributes_ptr: i32,
+             data_ptr: i32| {
+                with_memory(&mut caller, |host, memory| {
+                    host.add_event(
+                        memory,
+                        event_type_ptr as u32,
+                        attributes_ptr as u32,
+                        data_ptr as u32,
+                    )
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "add_receipt_data",
+            |mut caller: Caller<'_, ContractState<'a>>, data_ptr: i32| {
+                with_memory(&mut caller, |host, memory| {
+                    host.add_receipt_data(memory, data_ptr as u32)
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "invoke_smart_permission",
+            |mut caller: Caller<'_, ContractState<'a>>,
+             contract_addr_ptr: i32,
+             name_ptr: i32,
+             roles_head_ptr: i32,
+             org_id_ptr: i32,
+             public_key_ptr: i32,
+             payload_ptr: i32| {
+                with_meter(&mut caller, |host, memory, meter| {
+                    host.smart_permission(
+                        memory,
+                        meter,
+                        contract_addr_ptr as u32,
+                        name_ptr as u32,
+                        roles_head_ptr as u32,
+                        org_id_ptr as u32,
+                        public_key_ptr as u32,
+                        payload_ptr as u32,
+                    )
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "invoke_contract",
+            |mut caller: Caller<'_, ContractState<'a>>,
+             name_ptr: i32,
+             version_ptr: i32,
+             payload_ptr: i32| {
+                with_meter(&mut caller, |host, memory, meter| {
+                    host.invoke_contract(
+                        memory,
+                        meter,
+                        name_ptr as u32,
+                        version_ptr as u32,
+                        payload_ptr as u32,
+                    )
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "get_ptr_len",
+            |caller: Caller<'_, ContractState<'a>>, ptr: i32| {
+                caller.data().host.get_ptr_len(ptr as u32)
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "get_ptr_capacity",
+            |caller: Caller<'_, ContractState<'a>>, ptr: i32| {
+                caller.data().host.get_ptr_capacity(ptr as u32)
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "alloc",
+            |mut caller: Caller<'_, ContractState<'a>>, len: i32| {
+                with_memory(&mut caller, |host, memory| host.alloc(memory, len))
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "read_byte",
+            |mut caller: Caller<'_, ContractState<'a>>, offset: i32| {
+                with_memory(&mut caller, |host, memory| host.read_byte(memory, offset))
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "write_byte",
+            |mut caller: Caller<'_, ContractState<'a>>, ptr: i32, offset: i32, data: i32| {
+                with_memory(&mut caller, |host, memory| {
+                    host.write_byte(memory, ptr as u32, offset as u32, data)
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "get_ptr_collection_len",
+            |caller: Caller<'_, ContractState<'a>>, head_ptr: i32| {
+                caller.data().host.get_collection_len(head_ptr as u32)
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "get_ptr_from_collection",
+            |caller: Caller<'_, ContractState<'a>>, head_ptr: i32, index: i32| {
+                caller
+                    .data()
+                    .host
+                    .get_ptr_from_collection(head_ptr as u32, index as u32)
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "create_collection",
+            |mut caller: Caller<'_, ContractState<'a>>, head_ptr: i32| {
+                caller
+                    .data_mut()
+                    .host
+                    .create_collection(head_ptr as u32)
+                    .map(|head| head as i32)
+                    .map_err(trap)
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "add_to_collection",
+            |mut caller: Caller<'_, ContractState<'a>>, head_ptr: i32, ptr: i32| {
+                caller
+                    .data_mut()
+                    .host
+                    .add_to_collection(head_ptr as u32, ptr as u32)
+                    .map(|head| head as i32)
+                    .map_err(trap)
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "set_error",
+            |mut caller: Caller<'_, ContractState<'a>>, code: i32, message_ptr: i32| {
+                with_memory(&mut caller, |host, memory| {
+                    host.set_error(memory, code, message_ptr as u32)
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "log_buffer",
+            |mut caller: Caller<'_, ContractState<'a>>, log_level: i32, log_ptr: i32| {
+                with_memory(&mut caller, |host, memory| {
+                    host.log(memory, log_level as u32, log_ptr as u32)
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            "env",
+            "log_level",
+            |caller: Caller<'_, ContractState<'a>>| caller.data().host.log_level(),
+        )
+        .map_err(error)?;
+
+    Ok(())
+}
+
+/// Define the functions contracts import from the WASI module
+fn define_wasi_functions<'a>(linker: &mut Linker<ContractState<'a>>) -> Result<(), ExternalsError> {
+    for name in &["args_get", "environ_get"] {
+        linker
+            .func_wrap(
+                wasi::WASI_MODULE,
+                name,
+                |_: Caller<'_, ContractState<'a>>, _: i32, _: i32| wasi::get(),
+            )
+            .map_err(error)?;
+    }
+    for name in &["args_sizes_get", "environ_sizes_get"] {
+        linker
+            .func_wrap(
+                wasi::WASI_MODULE,
+                name,
+                |mut caller: Caller<'_, ContractState<'a>>, count_ptr: i32, size_ptr: i32| {
+                    with_contract_memory(&mut caller, |memory| {
+                        wasi::sizes_get(memory, count_ptr as u32, size_ptr as u32)
+                    })
+                },
+            )
+            .map_err(error)?;
+    }
+    for name in &["clock_res_get", "random_get"] {
+        linker
+            .func_wrap(
+                wasi::WASI_MODULE,
+                name,
+                |_: Caller<'_, ContractState<'a>>, _: i32, _: i32| wasi::unsupported(),
+            )
+            .map_err(error)?;
+    }
+    linker
+        .func_wrap(
+            wasi::WASI_MODULE,
+            "clock_time_get",
+            |_: Caller<'_, ContractState<'a>>, _: i32, _: i64, _: i32| wasi::unsupported(),
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            wasi::WASI_MODULE,
+            "fd_write",
+            |mut caller: Caller<'_, ContractState<'a>>,
+             fd: i32,
+             iovs_ptr: i32,
+             iovs_len: i32,
+             nwritten_ptr: i32| {
+                with_contract_memory(&mut caller, |memory| {
+                    wasi::fd_write(
+                        memory,
+                        fd,
+                        iovs_ptr as u32,
+                        iovs_len as u32,
+                        nwritten_ptr as u32,
+                    )
+                })
+            },
+        )
+        .map_err(error)?;
+    linker
+        .func_wrap(
+            wasi::WASI_MODULE,
+            "proc_exit",
+            |_: Caller<'_, ContractState<'a>>, code: i32| -> Result<(), Trap> {
+                Err(trap(wasi::proc_exit(code)))
+            },
+        )
+        .map_err(error)?;
+
+    Ok(())
+}
+
+/// Call a host function with the memory of the contract calling it
+fn with_memory<'a, R, F>(caller: &mut Caller<'_, ContractState<'a>>, f: F) -> Result<R, Trap>
+where
+    F: FnOnce(&mut HostState<'a>, &mut SliceMemory) -> Result<R, ExternalsError>,
+{
+    let memory = caller
+        .data()
+        .memory
+        .ok_or_else(|| Trap::new("Contract memory is not defined"))?;
+    let (data, state) = memory.data_and_store_mut(caller);
+
+    f(&mut state.host, &mut SliceMemory(data)).map_err(trap)
+}
+
+/// Call a host function which executes contracts or smart permissions with the memory of the
+/// contract calling it and a meter holding the fuel the contract has left, then consume the fuel
+/// they ran, so that they share the budget of the contract
+fn with_meter<'a, R, F>(caller: &mut Caller<'_, ContractState<'a>>, f: F) -> Result<R, Trap>
+where
+    F: FnOnce(
+        &mut HostState<'a>,
+        &mut SliceMemory,
+        &mut metering::InstructionMeter,
+    ) -> Result<R, ExternalsError>,
+{
+    let limits = caller.data().host.limits();
+    let fuel_left = limits
+        .max_instructions
+        .saturating_sub(caller.fuel_consumed().unwrap_or(0));
+    let mut meter = metering::InstructionMeter::with_budget(limits, fuel_left);
+
+    let result = with_memory(caller, |host, memory| f(host, memory, &mut meter));
+    caller
+        .consume_fuel(meter.instructions_run())
+        .map_err(|err| trap(error(err)))?;
+    result
+}
+
+/// Call a WASI function with the memory the contract calling it exports
+fn with_contract_memory<R, F>(caller: &mut Caller<'_, ContractState<'_>>, f: F) -> Result<R, Trap>
+where
+    F: FnOnce(&mut SliceMemory) -> Result<R, ExternalsError>,
+{
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| trap(wasi::no_memory()))?;
+
+    f(&mut SliceMemory(memory.data_mut(caller))).map_err(trap)
+}
+
+/// The memory of a contract, borrowed from its store while a host function runs
+struct SliceMemory<'m>(&'m mut [u8]);
+
+impl<'m> ContractMemory for SliceMemory<'m> {
+    fn get(&self, offset: u32, length: usize) -> Result<Vec<u8>, ExternalsError> {
+        let start = offset as usize;
+        start
+            .checked_add(length)
+            .and_then(|end| self.0.get(start..end))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| out_of_bounds(offset, length))
+    }
+
+    fn set(&mut self, offset: u32, data: &[u8]) -> Result<(), ExternalsError> {
+        let start = offset as usize;
+        match start
+            .checked_add(data.len())
+            .and_then(|end| self.0.get_mut(start..end))
+        {
+            Some(region) => {
+                region.copy_from_slice(data);
+                Ok(())
+            }
+            None => Err(out_of_bounds(offset, data.len())),
+        }
+    }
+}
+
+fn out_of_bounds(offset: u32, length: usize) -> ExternalsError {
+    ExternalsError::from(format!(
+        "{} bytes at offset {} are out of the bounds of memory",
+        length, offset
+    ))
+}
+
+fn error<E: fmt::Display>(err: E) -> ExternalsError {
+    ExternalsError::from(err.to_string())
+}
+
+fn trap(err: ExternalsError) -> Trap {
+    Trap::new(err.to_string())
+}
@@ -0,0 +1,206 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The state of a transaction processed by a worker, read and written through the validator
+
+use protobuf::{Message as _, RepeatedField};
+use sawtooth_sdk::messages::events::{Event, Event_Attribute};
+use sawtooth_sdk::messages::state_context::*;
+use sawtooth_sdk::messages::validator::Message_MessageType;
+use sawtooth_sdk::messaging::stream::MessageSender;
+use sawtooth_sdk::messaging::zmq_stream::ZmqMessageSender;
+use sawtooth_sdk::processor::handler::{ContextError, TransactionContext};
+
+use crate::worker_pool::correlation_id;
+
+/// The context of a transaction, which sends its requests to the validator over the connection
+/// shared by the workers
+pub struct WorkerContext {
+    context_id: String,
+    sender: ZmqMessageSender,
+}
+
+impl WorkerContext {
+    pub fn new(context_id: &str, sender: ZmqMessageSender) -> WorkerContext {
+        WorkerContext {
+            context_id: context_id.into(),
+            sender,
+        }
+    }
+
+    /// Send a request to the validator and return the content of its response
+    fn request<M: protobuf::Message>(
+        &self,
+        message_type: Message_MessageType,
+        request: &M,
+    ) -> Result<Vec<u8>, ContextError> {
+        let mut future =
+            self.sender
+                .send(message_type, &correlation_id(), &request.write_to_bytes()?)?;
+        Ok(future.get()?.get_content().to_vec())
+    }
+}
+
+impl TransactionContext for WorkerContext {
+    fn get_state_entries(
+        &self,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, ContextError> {
+        let mut request = TpStateGetRequest::new();
+        request.set_context_id(self.context_id.clone());
+        request.set_addresses(RepeatedField::from_slice(addresses));
+        let response = TpStateGetResponse::parse_from_bytes(
+            &self.request(Message_MessageType::TP_STATE_GET_REQUEST, &request)?,
+        )?;
+
+        match response.get_status() {
+            TpStateGetResponse_Status::OK => Ok(response
+                .get_entries()
+                .iter()
+                // Addresses which are not set are returned without data
+                .filter(|entry| !entry.get_data().is_empty())
+                .map(|entry| (entry.get_address().into(), entry.get_data().to_vec()))
+                .collect()),
+            TpStateGetResponse_Status::AUTHORIZATION_ERROR => {
+                Err(ContextError::AuthorizationError(format!(
+                    "Tried to get unauthorized addresses: {:?}",
+                    addresses
+                )))
+            }
+            TpStateGetResponse_Status::STATUS_UNSET => Err(ContextError::ResponseAttributeError(
+                "Status was not set for TpStateGetResponse".into(),
+            )),
+        }
+    }
+
+    fn set_state_entries(&self, entries: Vec<(String, Vec<u8>)>) -> Result<(), ContextError> {
+        let addresses = entries
+            .iter()
+            .map(|(address, _)| address.clone())
+            .collect::<Vec<_>>();
+        let mut request = TpStateSetRequest::new();
+        request.set_context_id(self.context_id.clone());
+        request.set_entries(
+            entries
+                .into_iter()
+                .map(|(address, data)| {
+                    let mut entry = TpStateEntry::new();
+                    entry.set_address(address);
+                    entry.set_data(data);
+                    entry
+                })
+                .collect(),
+        );
+        let response = TpStateSetResponse::parse_from_bytes(
+            &self.request(Message_MessageType::TP_STATE_SET_REQUEST, &request)?,
+        )?;
+
+        match response.get_status() {
+            TpStateSetResponse_Status::OK => Ok(()),
+            TpStateSetResponse_Status::AUTHORIZATION_ERROR => {
+                Err(ContextError::AuthorizationError(format!(
+                    "Tried to set unauthorized addresses: {:?}",
+                    addresses
+                )))
+            }
+            TpStateSetResponse_Status::STATUS_UNSET => Err(ContextError::ResponseAttributeError(
+                "Status was not set for TpStateSetResponse".into(),
+            )),
+        }
+    }
+
+    fn delete_state_entries(&self, addresses: &[String]) -> Result<Vec<String>, ContextError> {
+        let mut request = TpStateDeleteRequest::new();
+        request.set_context_id(self.context_id.clone());
+        request.set_addresses(RepeatedField::from_slice(addresses));
+        let response = TpStateDeleteResponse::parse_from_bytes(
+            &self.request(Message_MessageType::TP_STATE_DELETE_REQUEST, &request)?,
+        )?;
+
+        match response.get_status() {
+            TpStateDeleteResponse_Status::OK => Ok(response.get_addresses().to_vec()),
+            TpStateDeleteResponse_Status::AUTHORIZATION_ERROR => {
+                Err(ContextError::AuthorizationError(format!(
+                    "Tried to delete unauthorized addresses: {:?}",
+                    addresses
+                )))
+            }
+            TpStateDeleteResponse_Status::STATUS_UNSET => {
+                Err(ContextError::ResponseAttributeError(
+                    "Status was not set for TpStateDeleteResponse".into(),
+                ))
+            }
+        }
+    }
+
+    fn add_receipt_data(&self, data: &[u8]) -> Result<(), ContextError> {
+        let mut request = TpReceiptAddDataRequest::new();
+        request.set_context_id(self.context_id.clone());
+        request.set_data(data.to_vec());
+        let response = TpReceiptAddDataResponse::parse_from_bytes(
+            &self.request(Message_MessageType::TP_RECEIPT_ADD_DATA_REQUEST, &request)?,
+        )?;
+
+        match response.get_status() {
+            TpReceiptAddDataResponse_Status::OK => Ok(()),
+            TpReceiptAddDataResponse_Status::ERROR => Err(ContextError::TransactionReceiptError(
+                format!("Failed to add receipt data {:?}", data),
+            )),
+            TpReceiptAddDataResponse_Status::STATUS_UNSET => {
+                Err(ContextError::ResponseAttributeError(
+                    "Status was not set for TpReceiptAddDataResponse".into(),
+                ))
+            }
+        }
+    }
+
+    fn add_event(
+        &self,
+        event_type: String,
+        attributes: Vec<(String, String)>,
+        data: &[u8],
+    ) -> Result<(), ContextError> {
+        let mut event = Event::new();
+        event.set_event_type(event_type);
+        event.set_attributes(
+            attributes
+                .into_iter()
+                .map(|(key, value)| {
+                    let mut attribute = Event_Attribute::new();
+                    attribute.set_key(key);
+                    attribute.set_value(value);
+                    attribute
+                })
+                .collect(),
+        );
+        event.set_data(data.to_vec());
+
+        let mut request = TpEventAddRequest::new();
+        request.set_context_id(self.context_id.clone());
+        request.set_event(event);
+        let response = TpEventAddResponse::parse_from_bytes(
+            &self.request(Message_MessageType::TP_EVENT_ADD_REQUEST, &request)?,
+        )?;
+
+        match response.get_status() {
+            TpEventAddResponse_Status::OK => Ok(()),
+            TpEventAddResponse_Status::ERROR => Err(ContextError::TransactionReceiptError(
+                format!("Failed to add event {:?}", request.get_event()),
+            )),
+            TpEventAddResponse_Status::STATUS_UNSET => Err(ContextError::ResponseAttributeError(
+                "Status was not set for TpEventAddResponse".into(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright 2021 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Processes transactions concurrently on a pool of worker threads
+//!
+//! The Sawtooth SDK's `TransactionProcessor` applies one transaction at a time. A `WorkerPool`
+//! registers with the validator with a maximum occupancy of its number of workers, so that the
+//! validator's scheduler may send it that many transactions at once, which it only does for
+//! transactions whose inputs and outputs do not conflict. Each worker applies transactions with
+//! its own handler, so that workers do not share contract caches or WebAssembly instances.
+
+mod context;
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use protobuf::{Message as _, RepeatedField};
+use sawtooth_sdk::messages::network::PingResponse;
+use sawtooth_sdk::messages::processor::{
+    TpProcessRequest, TpProcessResponse, TpProcessResponse_Status, TpRegisterRequest,
+    TpRegisterResponse, TpRegisterResponse_Status, TpUnregisterRequest,
+};
+use sawtooth_sdk::messages::validator::Message_MessageType;
+use sawtooth_sdk::messaging::stream::{
+    MessageConnection, MessageReceiver, MessageSender, ReceiveError,
+};
+use sawtooth_sdk::messaging::zmq_stream::{ZmqMessageConnection, ZmqMessageSender};
+use sawtooth_sdk::processor::handler::{ApplyError, TransactionHandler};
+
+use self::context::WorkerContext;
+
+/// The number of workers which process transactions by default
+pub const DEFAULT_WORKERS: usize = 1;
+
+/// How long the validator may take to answer the registration of the pool before it is checked
+/// whether the pool has been signalled to shut down
+const REGISTER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the validator may take to answer the unregistration of the pool
+const UNREGISTER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long the pool waits to reconnect after its registration fails
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// How long the pool waits for a message before it checks whether it has been signalled to shut
+/// down
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A transaction process request, and the correlation id its response is sent with
+type Job = (String, Vec<u8>);
+
+/// A transaction processor which applies transactions on `workers` threads
+pub struct WorkerPool<F> {
+    endpoint: String,
+    workers: usize,
+    new_handler: Arc<F>,
+}
+
+impl<F, H> WorkerPool<F>
+where
+    F: Fn() -> H + Send + Sync + 'static,
+    H: TransactionHandler,
+{
+    /// Create a pool connecting to the validator at `endpoint`, whose workers each apply
+    /// transactions with a handler made by `new_handler`
+    pub fn new(endpoint: &str, workers: usize, new_handler: F) -> WorkerPool<F> {
+        WorkerPool {
+            endpoint: endpoint.into(),
+            workers,
+            new_handler: Arc::new(new_handler),
+        }
+    }
+
+    /// Connect to the validator and process transactions until the process receives SIGINT,
+    /// SIGTERM or SIGHUP, reconnecting if the connection is lost
+    ///
+    /// Once signalled, the pool stops receiving transactions, lets its workers finish the
+    /// transactions they were sent and unregisters from the validator.
+    pub fn start(&self) -> Result<(), String> {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let signalled = shutdown.clone();
+        ctrlc::set_handler(move || signalled.store(true, Ordering::SeqCst))
+            .map_err(|err| format!("Unable to handle signals: {}", err))?;
+
+        // The pool registers the family of its workers' handlers
+        let handler = (self.new_handler)();
+
+        while !shutdown.load(Ordering::SeqCst) {
+            info!("connecting to endpoint: {}", self.endpoint);
+            let (mut sender, receiver) = ZmqMessageConnection::new(&self.endpoint).create();
+
+            if !self.register(&handler, &sender, &shutdown) {
+                sender.close();
+                if !shutdown.load(Ordering::SeqCst) {
+                    thread::sleep(RECONNECT_DELAY);
+                }
+                continue;
+            }
+
+            let (jobs, queue) = mpsc::channel();
+            let queue = Arc::new(Mutex::new(queue));
+            let workers = (0..self.workers)
+                .map(|index| self.start_worker(index, &sender, &queue))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let disconnected = receive(&sender, &receiver, &jobs, &shutdown);
+
+            // Closing the queue stops each worker once it has applied the jobs it was sent
+            drop(jobs);
+            for worker in workers {
+                if worker.join().is_err() {
+                    error!("A worker panicked");
+                }
+            }
+
+            if disconnected {
+                info!("Trying to Reconnect");
+            } else {
+                unregister(&sender);
+            }
+            sender.close();
+        }
+
+        Ok(())
+    }
+
+    /// Register the family of a handler with the validator, returning whether the validator
+    /// accepted the registration
+    fn register(&self, handler: &H, sender: &ZmqMessageSender, shutdown: &AtomicBool) -> bool {
+        for version in handler.family_versions() {
+            let mut request = TpRegisterRequest::new();
+            request.set_family(handler.family_name());
+            request.set_version(version.clone());
+            request.set_namespaces(RepeatedField::from_vec(handler.namespaces()));
+            request.set_max_occupancy(self.workers as u32);
+            info!(
+                "sending TpRegisterRequest: {} {} with {} workers",
+                handler.family_name(),
+                version,
+                self.workers
+            );
+
+            let mut future = match request
+                .write_to_bytes()
+                .map_err(|err| err.to_string())
+                .and_then(|request| {
+                    sender
+                        .send(
+                            Message_MessageType::TP_REGISTER_REQUEST,
+                            &correlation_id(),
+                            &request,
+                        )
+                        .map_err(|err| err.to_string())
+                }) {
+                Ok(future) => future,
+                Err(err) => {
+                    error!("Registration failed: {}", err);
+                    return false;
+                }
+            };
+
+            let response = loop {
+                match future.get_timeout(REGISTER_TIMEOUT) {
+                    Ok(response) => break response,
+                    Err(_) if shutdown.load(Ordering::SeqCst) => return false,
+                    Err(_) => (),
+                }
+            };
+            match TpRegisterResponse::parse_from_bytes(response.get_content()) {
+                Ok(response) if response.get_status() == TpRegisterResponse_Status::OK => (),
+                Ok(response) => {
+                    error!("Registration failed: {:?}", response.get_status());
+                    return false;
+                }
+                Err(err) => {
+                    error!("Registration failed: {}", err);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn start_worker(
+        &self,
+        index: usize,
+        sender: &ZmqMessageSender,
+        queue: &Arc<Mutex<Receiver<Job>>>,
+    ) -> Result<JoinHandle<()>, String> {
+        let new_handler = self.new_handler.clone();
+        let sender = sender.clone();
+        let queue = queue.clone();
+        thread::Builder::new()
+            .name(format!("worker-{}", index))
+            .spawn(move || work(&new_handler(), &sender, &queue))
+            .map_err(|err| format!("Unable to start worker {}: {}", index, err))
+    }
+}
+
+/// Send the transactions the validator sends to the workers and answer its pings, until the
+/// pool is signalled to shut down or the connection is lost, returning whether it was lost
+fn receive(
+    sender: &ZmqMessageSender,
+    receiver: &MessageReceiver,
+    jobs: &Sender<Job>,
+    shutdown: &AtomicBool,
+) -> bool {
+    while !shutdown.load(Ordering::SeqCst) {
+        let mut message = match receiver.recv_timeout(RECEIVE_TIMEOUT) {
+            Ok(Ok(message)) => message,
+            Ok(Err(ReceiveError::DisconnectedError)) | Err(RecvTimeoutError::Disconnected) => {
+                return true
+            }
+            Ok(Err(err)) => {
+                error!("Error: {}", err);
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+        };
+
+        match message.get_message_type() {
+            Message_MessageType::TP_PROCESS_REQUEST => {
+                let job = (message.get_correlation_id().into(), message.take_content());
+                if jobs.send(job).is_err() {
+                    error!("Unable to send transaction to the workers");
+                    return true;
+                }
+            }
+            Message_MessageType::PING_REQUEST => reply(
+                sender,
+                message.get_correlation_id(),
+                Message_MessageType::PING_RESPONSE,
+                &PingResponse::new(),
+            ),
+            message_type => info!(
+                "Transaction Processor received invalid message type: {:?}",
+                message_type
+            ),
+        }
+    }
+    false
+}
+
+/// Apply the transactions sent to the queue until it is closed
+fn work<H: TransactionHandler>(
+    handler: &H,
+    sender: &ZmqMessageSender,
+    queue: &Mutex<Receiver<Job>>,
+) {
+    loop {
+        let (correlation_id, content) = match queue.lock().map(|queue| queue.recv()) {
+            Ok(Ok(job)) => job,
+            _ => return,
+        };
+
+        let request = match TpProcessRequest::parse_from_bytes(&content) {
+            Ok(request) => request,
+            Err(err) => {
+                error!("Cannot parse TpProcessRequest: {}", err);
+                continue;
+            }
+        };
+        let mut context = WorkerContext::new(request.get_context_id(), sender.clone());
+
+        let mut response = TpProcessResponse::new();
+        match handler.apply(&request, &mut context) {
+            Ok(()) => {
+                info!("TP_PROCESS_REQUEST sending TpProcessResponse: OK");
+                response.set_status(TpProcessResponse_Status::OK);
+            }
+            Err(ApplyError::InvalidTransaction(message)) => {
+                info!("TP_PROCESS_REQUEST sending TpProcessResponse: {}", message);
+                response.set_status(TpProcessResponse_Status::INVALID_TRANSACTION);
+                response.set_message(message);
+            }
+            Err(err) => {
+                info!("TP_PROCESS_REQUEST sending TpProcessResponse: {}", err);
+                response.set_status(TpProcessResponse_Status::INTERNAL_ERROR);
+                response.set_message(err.to_string());
+            }
+        }
+
+        reply(
+            sender,
+            &correlation_id,
+            Message_MessageType::TP_PROCESS_RESPONSE,
+            &response,
+        );
+    }
+}
+
+fn reply<M: protobuf::Message>(
+    sender: &ZmqMessageSender,
+    correlation_id: &str,
+    message_type: Message_MessageType,
+    response: &M,
+) {
+    let result = response
+        .write_to_bytes()
+        .map_err(|err| format!("Serialization failed: {}", err))
+        .and_then(|response| {
+            sender
+                .reply(message_type, correlation_id, &response)
+                .map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        error!("Unable to send {:?}: {}", message_type, err);
+    }
+}
+
+fn unregister(sender: &ZmqMessageSender) {
+    info!("sending TpUnregisterRequest");
+    let result = TpUnregisterRequest::new()
+        .write_to_bytes()
+        .map_err(|err| err.to_string())
+        .and_then(|request| {
+            sender
+                .send(
+                    Message_MessageType::TP_UNREGISTER_REQUEST,
+                    &correlation_id(),
+                    &request,
+                )
+                .map_err(|err| err.to_string())
+        })
+        .and_then(|mut future| {
+            future
+                .get_timeout(UNREGISTER_TIMEOUT)
+                .map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        info!("Unregistration failed: {}", err);
+    }
+}
+
+/// Return an id, unique to this process, to correlate a request with its response
+pub(crate) fn correlation_id() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "sabre-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )
+}